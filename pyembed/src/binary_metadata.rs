@@ -0,0 +1,118 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Arbitrary named data embedded in the binary at build time.
+
+Applications can record data such as build provenance or license texts in
+a dedicated section of the executable (`.pyoxmd` on ELF and PE,
+`__DATA,__pyoxmd` on Mach-O), where it can be inspected with standard
+binary tools and read back at run-time.
+
+The section content is a sequence of entries preceded by a header:
+
+* `PYOXMETA` magic (8 bytes)
+* Number of entries (little-endian u32)
+
+Each entry is:
+
+* Length of name (little-endian u32)
+* UTF-8 name
+* Length of data (little-endian u32)
+* Data
+*/
+
+use {
+    cpython::exc::KeyError,
+    cpython::{PyBytes, PyErr, PyObject, PyResult, Python, PythonObject, ToPyObject},
+    lazy_static::lazy_static,
+    std::{convert::TryInto, sync::Mutex},
+};
+
+/// Magic at the start of serialized binary metadata.
+pub const BINARY_METADATA_MAGIC: &[u8] = b"PYOXMETA";
+
+lazy_static! {
+    /// Entries available to `binary_metadata()`.
+    static ref BINARY_METADATA: Mutex<Vec<(String, Vec<u8>)>> = Mutex::new(vec![]);
+}
+
+/// Split `len` bytes off the front of `data`.
+fn take<'d>(data: &mut &'d [u8], len: usize) -> Result<&'d [u8], &'static str> {
+    if data.len() < len {
+        return Err("binary metadata is truncated");
+    }
+
+    let (value, rest) = data.split_at(len);
+    *data = rest;
+
+    Ok(value)
+}
+
+/// Split a little-endian u32 length off the front of `data`.
+fn take_len(data: &mut &[u8]) -> Result<usize, &'static str> {
+    Ok(u32::from_le_bytes(take(data, 4)?.try_into().unwrap()) as usize)
+}
+
+/// Parse serialized binary metadata into `(name, data)` pairs.
+pub fn parse_binary_metadata(data: &[u8]) -> Result<Vec<(String, &[u8])>, &'static str> {
+    if !data.starts_with(BINARY_METADATA_MAGIC) {
+        return Err("binary metadata does not begin with expected magic");
+    }
+
+    let mut remaining = &data[BINARY_METADATA_MAGIC.len()..];
+    let count = take_len(&mut remaining)?;
+    let mut entries = Vec::new();
+
+    for _ in 0..count {
+        let name_len = take_len(&mut remaining)?;
+        let name = std::str::from_utf8(take(&mut remaining, name_len)?)
+            .map_err(|_| "binary metadata name is not valid UTF-8")?
+            .to_string();
+        let data_len = take_len(&mut remaining)?;
+
+        entries.push((name, take(&mut remaining, data_len)?));
+    }
+
+    Ok(entries)
+}
+
+/// Define the entries available to `binary_metadata()`.
+#[cfg(not(library_mode = "extension"))]
+pub(crate) fn register_binary_metadata(data: Option<&[u8]>) -> Result<(), &'static str> {
+    let entries = match data {
+        Some(data) => parse_binary_metadata(data)?
+            .into_iter()
+            .map(|(name, data)| (name, data.to_vec()))
+            .collect(),
+        None => vec![],
+    };
+
+    *BINARY_METADATA.lock().unwrap() = entries;
+
+    Ok(())
+}
+
+/// Obtain the data of a binary metadata entry as `bytes`.
+pub(crate) fn binary_metadata(py: Python, name: String) -> PyResult<PyObject> {
+    BINARY_METADATA
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(n, _)| n == &name)
+        .map(|(_, data)| PyBytes::new(py, data).into_object())
+        .ok_or_else(|| PyErr::new::<KeyError, _>(py, format!("unknown binary metadata: {}", name)))
+}
+
+/// Obtain the names of binary metadata entries.
+pub(crate) fn binary_metadata_names(py: Python) -> PyResult<PyObject> {
+    Ok(BINARY_METADATA
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>()
+        .to_py_object(py)
+        .into_object())
+}
@@ -5,16 +5,74 @@
 //! Data structures for configuring a Python interpreter.
 
 use {
+    crate::external_assets::ExternalAsset,
     python3_sys as pyffi,
     python_packaging::interpreter::{
         PythonInterpreterConfig, PythonInterpreterProfile, PythonRawAllocator, TerminfoResolution,
     },
     std::{
-        ffi::{CString, OsString},
+        ffi::{CString, OsStr, OsString},
         path::{Path, PathBuf},
     },
 };
 
+/// Argument starting a diagnostic REPL when `debug_shell` is enabled.
+pub const DEBUG_SHELL_FLAG: &str = "--pyoxidizer-debug-shell";
+
+/// Banner printed when starting the diagnostic REPL.
+const DEBUG_SHELL_BANNER: &str = "PyOxidizer debug shell\n\
+    \n\
+    importer            the OxidizedFinder instance, if any\n\
+    resources           indexed resources, keyed by name\n\
+    config              the interpreter configuration\n\
+    diagnose_import(n)  report how module n is found and why importing it fails\n";
+
+/// Python source code preparing `__main__` of the diagnostic REPL.
+///
+/// `{config}` is replaced by the hex encoded UTF-8 rendering of the
+/// interpreter configuration.
+const DEBUG_SHELL_STARTUP_CODE: &str = r#"import importlib
+import sys
+import traceback
+
+importer = None
+for finder in sys.meta_path:
+    if type(finder).__name__ == "OxidizedFinder":
+        importer = finder
+        break
+
+resources = {}
+if importer is not None:
+    resources = {r.name: r for r in importer.indexed_resources()}
+
+config = bytes.fromhex("{config}").decode("utf-8")
+
+
+def diagnose_import(name):
+    print("indexed resource: %r" % (resources.get(name),))
+    for finder in sys.meta_path:
+        try:
+            spec = finder.find_spec(name, None)
+        except Exception as e:
+            print("%r failed: %r" % (finder, e))
+            continue
+        if spec is not None:
+            print("found by %r: %r" % (finder, spec))
+            break
+    else:
+        print("no meta path finder found %s" % name)
+
+    try:
+        module = importlib.import_module(name)
+    except BaseException:
+        traceback.print_exc()
+    else:
+        print("imported %r" % module)
+
+
+del finder
+"#;
+
 /// Defines an extra extension module to load.
 #[derive(Clone, Debug)]
 pub struct ExtensionModule {
@@ -25,6 +83,22 @@ pub struct ExtensionModule {
     pub init_func: unsafe extern "C" fn() -> *mut pyffi::PyObject,
 }
 
+/// Defines a file containing packed resources data to load at run-time.
+#[derive(Clone, Debug)]
+pub struct PackedResourcesFile {
+    /// Path to the file.
+    ///
+    /// `$ORIGIN` in the path is expanded to the directory of the current
+    /// executable.
+    pub path: PathBuf,
+
+    /// Expected SHA-256 of the file content, as a hex string.
+    ///
+    /// If set, interpreter initialization fails if the file content
+    /// doesn't match.
+    pub sha256: Option<String>,
+}
+
 /// Configure a Python interpreter.
 ///
 /// This type defines the configuration of a Python interpreter. It is used
@@ -102,6 +176,15 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// Whether to install the default `PathFinder` meta path finder.
     pub filesystem_importer: bool,
 
+    /// Directories containing Python source files to prefer over resources.
+    ///
+    /// If non-empty, a meta path finder searching these directories is
+    /// installed before all other finders. Top-level modules and packages
+    /// present in these directories are loaded from source instead of from
+    /// `packed_resources`, so edits to a project's source are picked up
+    /// without rebuilding. Intended for development builds only.
+    pub dev_source_roots: Vec<PathBuf>,
+
     /// Reference to packed resources data.
     ///
     /// The referenced data contains Python module data. It likely comes from an
@@ -112,6 +195,46 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// meta path importer during interpreter initialization.
     pub packed_resources: Vec<&'a [u8]>,
 
+    /// Whether packed resources data is appended to the current executable.
+    ///
+    /// If true, the current executable is memory mapped during interpreter
+    /// initialization and the packed resources overlay at its end is loaded
+    /// after `packed_resources`. See the `python_packed_resources::overlay`
+    /// module for the format of the overlay.
+    pub packed_resources_overlay: bool,
+
+    /// Files containing packed resources data.
+    ///
+    /// The files are memory mapped during interpreter initialization and
+    /// their data is loaded after `packed_resources`. This allows multiple
+    /// executables to share resources installed outside of them.
+    pub packed_resources_files: Vec<PackedResourcesFile>,
+
+    /// Directories containing resources to load at run-time.
+    ///
+    /// A directory containing a `packed-resources` file has that file's
+    /// packed resources data loaded. Other directories are scanned for
+    /// Python modules, bytecode, extension modules and resource files, which
+    /// are loaded from the filesystem. Bytecode doesn't need accompanying
+    /// source. This allows installing additional packages next to an
+    /// executable.
+    ///
+    /// Resources are loaded after `packed_resources` and
+    /// `packed_resources_files` and replace resources of the same name.
+    /// Scanned directories are loaded last. Directories that don't exist
+    /// are ignored. Only used with `oxidized_importer`.
+    ///
+    /// `$ORIGIN` in paths is expanded to the directory of the current
+    /// executable.
+    pub resource_dirs: Vec<PathBuf>,
+
+    /// Command line flag defining additional `resource_dirs`.
+    ///
+    /// If set, `<flag> <path>` and `<flag>=<path>` arguments are removed from
+    /// the arguments seen by Python and their paths appended to
+    /// `resource_dirs`. Arguments after `--` are left alone.
+    pub resource_dirs_flag: Option<String>,
+
     /// Extra extension modules to make available to the interpreter.
     ///
     /// The values will effectively be passed to ``PyImport_ExtendInitTab()``.
@@ -134,6 +257,26 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// values passed to `int main()`.
     pub argvb: bool,
 
+    /// Value to replace `sys.argv[0]` with.
+    ///
+    /// `$ORIGIN` is expanded to the directory of the current executable.
+    pub argv0: Option<String>,
+
+    /// Arguments to insert after `sys.argv[0]`.
+    pub prepend_args: Vec<String>,
+
+    /// Entry points to dispatch to, as `(name, target)`.
+    ///
+    /// This allows a single executable to provide several tools, busybox
+    /// style. The entry point named after the executable is run instead of
+    /// the configured run mode. Otherwise, the first argument can name the
+    /// entry point to run. That argument is removed and becomes
+    /// `sys.argv[0]`.
+    ///
+    /// A target is either a module to run as `__main__` or a
+    /// `module:function` whose return value is the exit code.
+    pub entry_points: Vec<(String, String)>,
+
     /// Whether to set sys.frozen=True.
     ///
     /// Setting this will enable Python to emulate "frozen" binaries, such as
@@ -146,6 +289,75 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// of setting this attribute.
     pub sys_meipass: bool,
 
+    /// Names of modules to import before running the configured code.
+    ///
+    /// Modules are imported in order by `run_as_main()`, before the run
+    /// mode or the REPL starts. This allows applying monkeypatches or
+    /// preparing the environment before the application's own code runs.
+    /// If an import fails, the configured code isn't run.
+    pub pre_run_modules: Vec<String>,
+
+    /// Names of modules to add to the interpreter's frozen modules table.
+    ///
+    /// The in-memory bytecode of these modules in the embedded packed
+    /// resources is registered in `PyImport_FrozenModules` during
+    /// interpreter initialization, so the modules are loaded by Python's
+    /// `FrozenImporter` without involving Python-level importer code.
+    /// Initialization fails if a module has no in-memory bytecode at the
+    /// configured optimization level.
+    pub frozen_modules: Vec<String>,
+
+    /// Names of modules to import in a background thread during startup.
+    ///
+    /// Modules are imported in order by a daemon thread started during
+    /// interpreter initialization, before the configured code runs. When
+    /// the main thread releases the GIL, e.g. while waiting for input, the
+    /// thread imports modules the application will need later, hiding
+    /// import latency. Import errors are ignored.
+    pub preload_modules: Vec<String>,
+
+    /// Stack size of threads created by Python, in bytes.
+    ///
+    /// This is passed to `threading.stack_size()` during interpreter
+    /// initialization, so it applies to all threads started by Python code.
+    /// It must be at least 32 KiB. Platform limits may apply.
+    pub thread_stack_size: Option<usize>,
+
+    /// Name of an `asyncio` event loop policy class to install.
+    ///
+    /// An instance of the class is passed to `asyncio.set_event_loop_policy()`
+    /// during interpreter initialization. e.g. `WindowsSelectorEventLoopPolicy`
+    /// selects the selector event loop on Windows instead of the proactor
+    /// event loop, which some libraries require.
+    pub asyncio_event_loop_policy: Option<String>,
+
+    /// Text to print instead of Python's default banner when starting a REPL.
+    ///
+    /// Only used when no code to run is configured and the interpreter
+    /// falls back to the interactive interpreter.
+    pub repl_banner: Option<String>,
+
+    /// Python source code to execute in `__main__` before starting a REPL.
+    ///
+    /// This behaves like a `PYTHONSTARTUP` file that is baked into the
+    /// binary. It can be used to pre-import modules or otherwise prepare
+    /// the interactive environment.
+    pub repl_startup_code: Option<String>,
+
+    /// Value to set `sys.ps1` to when starting a REPL.
+    pub repl_ps1: Option<String>,
+
+    /// Value to set `sys.ps2` to when starting a REPL.
+    pub repl_ps2: Option<String>,
+
+    /// Whether a `DEBUG_SHELL_FLAG` first argument starts a diagnostic REPL.
+    ///
+    /// The REPL runs instead of the configured run mode, entry points and
+    /// `pre_run_modules`, and exposes the importer, its indexed resources
+    /// and the interpreter configuration for debugging import failures.
+    /// Other arguments are ignored.
+    pub debug_shell: bool,
+
     /// How to resolve the `terminfo` database.
     pub terminfo_resolution: TerminfoResolution,
 
@@ -158,6 +370,66 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// executable.
     pub tcl_library: Option<PathBuf>,
 
+    /// Environment variables to set before the interpreter is initialized.
+    ///
+    /// `$ORIGIN` in values is expanded to the directory of the current
+    /// executable. Existing values of the variables are replaced.
+    pub environment_variables: Vec<(String, String)>,
+
+    /// Gettext domains to bind, as `(domain, locale directory)`.
+    ///
+    /// `gettext.bindtextdomain()` is called for each domain after the
+    /// interpreter is initialized, so `gettext` finds catalogs installed
+    /// next to the executable without the application specifying where.
+    ///
+    /// `$ORIGIN` in paths is expanded to the directory of the current
+    /// executable.
+    pub gettext_domains: Vec<(String, PathBuf)>,
+
+    /// Directory of bundled time zone files.
+    ///
+    /// If set, `zoneinfo.reset_tzpath()` is called with this directory after
+    /// the interpreter is initialized, so time zones are looked up in it
+    /// without setting `PYTHONTZPATH`, which child processes would inherit.
+    ///
+    /// `$ORIGIN` in the path is expanded to the directory of the current
+    /// executable.
+    pub tzpath: Option<PathBuf>,
+
+    /// Large assets to download when the application first requests them.
+    ///
+    /// Assets are obtained via `oxidized_importer.external_asset_path()`,
+    /// which downloads the asset if it isn't present and verifies its
+    /// content against the recorded hash.
+    pub external_assets: Vec<ExternalAsset>,
+
+    /// Serialized named data embedded in the binary.
+    ///
+    /// This likely references a static placed in a dedicated section of
+    /// the executable by PyOxidizer. Entries are obtained via
+    /// `oxidized_importer.binary_metadata()`. See the `binary_metadata`
+    /// module for the format of the data.
+    pub binary_metadata: Option<&'a [u8]>,
+
+    /// Packages requiring a capability to be imported, as `(package, capability)`.
+    ///
+    /// Resources of a package whose capability isn't granted (see
+    /// `capabilities` and `capabilities_env`) are removed from the
+    /// `oxidized_importer` index, so the package and its submodules can't
+    /// be imported and its resources can't be loaded. This allows shipping
+    /// tiered feature sets in one binary. Note that this isn't a security
+    /// boundary: the restricted code is still present in the binary.
+    pub restricted_packages: Vec<(String, String)>,
+
+    /// Capabilities granted to the application.
+    ///
+    /// Applications embedding Python can set this at run-time, e.g. after
+    /// validating a license.
+    pub capabilities: Vec<String>,
+
+    /// Environment variable holding additional `,` delimited capabilities.
+    pub capabilities_env: Option<String>,
+
     /// Environment variable holding the directory to write a loaded modules file.
     ///
     /// If this value is set and the environment it refers to is set,
@@ -180,14 +452,40 @@ impl<'a> Default for OxidizedPythonInterpreterConfig<'a> {
             set_missing_path_configuration: true,
             oxidized_importer: false,
             filesystem_importer: true,
+            dev_source_roots: vec![],
             packed_resources: vec![],
+            packed_resources_overlay: false,
+            packed_resources_files: vec![],
+            resource_dirs: vec![],
+            resource_dirs_flag: None,
             extra_extension_modules: None,
             argv: None,
             argvb: false,
+            argv0: None,
+            prepend_args: vec![],
+            entry_points: vec![],
             sys_frozen: false,
             sys_meipass: false,
+            pre_run_modules: vec![],
+            frozen_modules: vec![],
+            preload_modules: vec![],
+            thread_stack_size: None,
+            asyncio_event_loop_policy: None,
+            repl_banner: None,
+            repl_startup_code: None,
+            repl_ps1: None,
+            repl_ps2: None,
+            debug_shell: false,
             terminfo_resolution: TerminfoResolution::Dynamic,
             tcl_library: None,
+            environment_variables: vec![],
+            gettext_domains: vec![],
+            tzpath: None,
+            external_assets: vec![],
+            binary_metadata: None,
+            restricted_packages: vec![],
+            capabilities: vec![],
+            capabilities_env: None,
             write_modules_directory_env: None,
         }
     }
@@ -250,6 +548,174 @@ impl<'a> OxidizedPythonInterpreterConfig<'a> {
         }
     }
 
+    /// Resolve the value to use for `sys.argv`, applying `debug_shell`,
+    /// `resource_dirs_flag`, `entry_points`, `argv0` and `prepend_args`.
+    ///
+    /// If an entry point or the debug shell is selected, the run configuration
+    /// is updated to run it. Returns `None` if `sys.argv` should be left alone.
+    pub fn resolve_entry_point_argv(&mut self) -> Result<Option<Vec<OsString>>, String> {
+        if self.entry_points.is_empty()
+            && self.argv0.is_none()
+            && self.prepend_args.is_empty()
+            && self.resource_dirs_flag.is_none()
+            && !self.debug_shell
+        {
+            return Ok(self.resolve_sys_argv());
+        }
+
+        let mut args = self.resolve_sys_argvb();
+        if args.is_empty() {
+            args.push(OsString::new());
+        }
+
+        if self.debug_shell && args.get(1).and_then(|arg| arg.to_str()) == Some(DEBUG_SHELL_FLAG) {
+            args.truncate(1);
+            self.set_debug_shell();
+
+            return Ok(Some(args));
+        }
+
+        if let Some(flag) = self.resource_dirs_flag.clone() {
+            self.take_resource_dirs_args(&flag, &mut args)?;
+        }
+
+        if !self.entry_points.is_empty() {
+            let invoked = invoked_name(&args[0]);
+            let first = args
+                .get(1)
+                .and_then(|arg| arg.to_str())
+                .map(|s| s.to_string());
+
+            let selected = if let Some((_, target)) = self
+                .entry_points
+                .iter()
+                .find(|(name, _)| Some(name) == invoked.as_ref())
+            {
+                Some(target.clone())
+            } else if let Some((name, target)) = self
+                .entry_points
+                .iter()
+                .find(|(name, _)| Some(name) == first.as_ref())
+            {
+                args.remove(1);
+                args[0] = OsString::from(name);
+                Some(target.clone())
+            } else {
+                None
+            };
+
+            match selected {
+                Some(target) => self.set_entry_point(&target),
+                None if !self.uses_py_runmain() && !self.uses_repl() => {
+                    return Err(format!(
+                        "usage: {} <command> [args...]; available commands: {}",
+                        invoked.unwrap_or_default(),
+                        self.entry_points
+                            .iter()
+                            .map(|(name, _)| name.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ));
+                }
+                None => {}
+            }
+        }
+
+        if let Some(argv0) = self.argv0.clone() {
+            let origin = self.ensure_origin()?;
+            args[0] = OsString::from(argv0.replace("$ORIGIN", &origin.display().to_string()));
+        }
+
+        for (i, arg) in self.prepend_args.iter().enumerate() {
+            args.insert(i + 1, OsString::from(arg));
+        }
+
+        Ok(Some(args))
+    }
+
+    /// Remove `resource_dirs_flag` arguments, recording their paths.
+    fn take_resource_dirs_args(
+        &mut self,
+        flag: &str,
+        args: &mut Vec<OsString>,
+    ) -> Result<(), String> {
+        let prefix = format!("{}=", flag);
+
+        let mut i = 1;
+        while i < args.len() {
+            let arg = args[i].to_str().unwrap_or_default();
+
+            if arg == "--" {
+                break;
+            } else if arg == flag {
+                if i + 1 == args.len() {
+                    return Err(format!("{} requires a path argument", flag));
+                }
+                let path = args.remove(i + 1);
+                args.remove(i);
+                self.resource_dirs.push(PathBuf::from(path));
+            } else if let Some(path) = arg.strip_prefix(&prefix) {
+                self.resource_dirs.push(PathBuf::from(path));
+                args.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Configure the interpreter to run the diagnostic REPL.
+    fn set_debug_shell(&mut self) {
+        // Packed resources data is large and not worth rendering.
+        let mut rendered = self.clone();
+        rendered.packed_resources = vec![];
+        let rendered = format!(
+            "{:#?}\npacked_resources: {} blobs\n",
+            rendered,
+            self.packed_resources.len()
+        );
+
+        let config = &mut self.interpreter_config;
+        config.run_command = None;
+        config.run_filename = None;
+        config.run_module = None;
+
+        self.pre_run_modules = vec![];
+        self.repl_banner = Some(DEBUG_SHELL_BANNER.to_string());
+        self.repl_startup_code = Some(
+            DEBUG_SHELL_STARTUP_CODE.replace(
+                "{config}",
+                &rendered
+                    .as_bytes()
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<String>(),
+            ),
+        );
+    }
+
+    /// Configure the interpreter to run an entry point target.
+    fn set_entry_point(&mut self, target: &str) {
+        let config = &mut self.interpreter_config;
+        config.run_command = None;
+        config.run_filename = None;
+        config.run_module = None;
+
+        let mut parts = target.splitn(2, ':');
+        match (parts.next(), parts.next()) {
+            (Some(module), Some(function)) => {
+                config.run_command = Some(format!(
+                    "import sys\nfrom {} import {}\nsys.exit({}())\n",
+                    module, function, function
+                ));
+            }
+            _ => {
+                config.run_module = Some(target.to_string());
+            }
+        }
+    }
+
     /// Resolve the value to use for `sys.argvb`.
     pub fn resolve_sys_argvb(&self) -> Vec<OsString> {
         if let Some(args) = &self.interpreter_config.argv {
@@ -261,6 +727,46 @@ impl<'a> OxidizedPythonInterpreterConfig<'a> {
         }
     }
 
+    /// Resolve the paths of `packed_resources_files`.
+    pub fn resolve_packed_resources_files(
+        &mut self,
+    ) -> Result<Vec<PackedResourcesFile>, &'static str> {
+        let origin = self.ensure_origin()?;
+        let origin_string = origin.display().to_string();
+
+        Ok(self
+            .packed_resources_files
+            .iter()
+            .map(|f| PackedResourcesFile {
+                path: PathBuf::from(
+                    f.path
+                        .display()
+                        .to_string()
+                        .replace("$ORIGIN", &origin_string),
+                ),
+                sha256: f.sha256.clone(),
+            })
+            .collect::<Vec<_>>())
+    }
+
+    /// Resolve the paths of `resource_dirs`.
+    pub fn resolve_resource_dirs(&mut self) -> Result<Vec<PathBuf>, &'static str> {
+        let origin = self.ensure_origin()?;
+        let origin_string = origin.display().to_string();
+
+        Ok(self
+            .resource_dirs
+            .iter()
+            .map(|path| {
+                PathBuf::from(
+                    path.display()
+                        .to_string()
+                        .replace("$ORIGIN", &origin_string),
+                )
+            })
+            .collect::<Vec<_>>())
+    }
+
     /// Resolves the value to use for `TCL_LIBRARY`.
     pub fn resolve_tcl_library(&mut self) -> Result<Option<OsString>, &'static str> {
         let origin = self.ensure_origin()?;
@@ -279,4 +785,106 @@ impl<'a> OxidizedPythonInterpreterConfig<'a> {
             Ok(None)
         }
     }
+
+    /// Resolves environment variables to set, expanding `$ORIGIN`.
+    pub fn resolve_environment_variables(
+        &mut self,
+    ) -> Result<Vec<(String, OsString)>, &'static str> {
+        let origin = self.ensure_origin()?;
+        let origin_string = origin.display().to_string();
+
+        let variables = self
+            .environment_variables
+            .iter()
+            .map(|(key, value)| {
+                (
+                    key.clone(),
+                    OsString::from(value.replace("$ORIGIN", &origin_string)),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        Ok(variables)
+    }
+
+    /// Resolve the locale directories of `gettext_domains`.
+    pub fn resolve_gettext_domains(&mut self) -> Result<Vec<(String, PathBuf)>, &'static str> {
+        let origin = self.ensure_origin()?;
+        let origin_string = origin.display().to_string();
+
+        Ok(self
+            .gettext_domains
+            .iter()
+            .map(|(domain, path)| {
+                (
+                    domain.clone(),
+                    PathBuf::from(
+                        path.display()
+                            .to_string()
+                            .replace("$ORIGIN", &origin_string),
+                    ),
+                )
+            })
+            .collect::<Vec<_>>())
+    }
+
+    /// Resolve the directory of bundled time zone files.
+    pub fn resolve_tzpath(&mut self) -> Result<Option<PathBuf>, &'static str> {
+        let origin = self.ensure_origin()?;
+        let origin_string = origin.display().to_string();
+
+        Ok(self.tzpath.as_ref().map(|path| {
+            PathBuf::from(
+                path.display()
+                    .to_string()
+                    .replace("$ORIGIN", &origin_string),
+            )
+        }))
+    }
+
+    /// Resolve the capabilities granted to the application.
+    pub fn resolve_capabilities(&self) -> Vec<String> {
+        let mut capabilities = self.capabilities.clone();
+
+        if let Some(key) = &self.capabilities_env {
+            if let Ok(value) = std::env::var(key) {
+                capabilities.extend(
+                    value
+                        .split(',')
+                        .map(|s| s.trim())
+                        .filter(|s| !s.is_empty())
+                        .map(|s| s.to_string()),
+                );
+            }
+        }
+
+        capabilities
+    }
+
+    /// Resolve the paths of `external_assets`.
+    pub fn resolve_external_assets(&mut self) -> Result<Vec<ExternalAsset>, &'static str> {
+        let origin = self.ensure_origin()?;
+        let origin_string = origin.display().to_string();
+
+        Ok(self
+            .external_assets
+            .iter()
+            .map(|a| ExternalAsset {
+                path: PathBuf::from(
+                    a.path
+                        .display()
+                        .to_string()
+                        .replace("$ORIGIN", &origin_string),
+                ),
+                ..a.clone()
+            })
+            .collect::<Vec<_>>())
+    }
+}
+
+/// The name an executable was invoked as, without directory or `.exe`.
+fn invoked_name(argv0: &OsStr) -> Option<String> {
+    let name = Path::new(argv0).file_name()?.to_str()?;
+
+    Some(name.strip_suffix(".exe").unwrap_or(name).to_string())
 }
@@ -0,0 +1,152 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Large assets downloaded when first needed.
+
+Applications can depend on payloads too large to ship in an installer, such
+as browser builds or machine learning models. The URL and SHA-256 of these
+assets are recorded at build time and the asset is fetched and verified the
+first time the application asks for it.
+*/
+
+use {
+    crate::conversion::path_to_pyobject,
+    cpython::exc::{KeyError, OSError},
+    cpython::{ObjectProtocol, PyErr, PyObject, PyResult, Python},
+    lazy_static::lazy_static,
+    sha2::{Digest, Sha256},
+    std::{
+        fs::File,
+        path::{Path, PathBuf},
+        sync::Mutex,
+    },
+};
+
+/// Defines an asset to download on first use.
+#[derive(Clone, Debug)]
+pub struct ExternalAsset {
+    /// Name the application uses to request the asset.
+    pub name: String,
+
+    /// URL to download the asset from.
+    pub url: String,
+
+    /// Expected SHA-256 of the asset content, as a hex string.
+    pub sha256: String,
+
+    /// Path to install the asset to.
+    ///
+    /// `$ORIGIN` in the path is expanded to the directory of the current
+    /// executable.
+    pub path: PathBuf,
+}
+
+lazy_static! {
+    /// Registered assets and whether their content has been verified.
+    static ref EXTERNAL_ASSETS: Mutex<Vec<(ExternalAsset, bool)>> = Mutex::new(vec![]);
+}
+
+/// Define the assets available to `external_asset_path()`.
+///
+/// Paths of assets should already have `$ORIGIN` expanded.
+#[cfg(not(library_mode = "extension"))]
+pub(crate) fn register_external_assets(assets: Vec<ExternalAsset>) {
+    *EXTERNAL_ASSETS.lock().unwrap() = assets.into_iter().map(|a| (a, false)).collect();
+}
+
+fn file_sha256(path: &Path) -> std::io::Result<String> {
+    let mut fh = File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut fh, &mut hasher)?;
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Download a URL to a path using Python's `urllib`.
+///
+/// This honors proxy settings and certificate stores the same way
+/// application code using the standard library would.
+fn download(py: Python, url: &str, path: &Path) -> PyResult<()> {
+    let urllib_request = py.import("urllib.request")?;
+    let shutil = py.import("shutil")?;
+    let builtins = py.import("builtins")?;
+
+    let response = urllib_request.call(py, "urlopen", (url,), None)?;
+    let fh = builtins.call(py, "open", (path_to_pyobject(py, path)?, "wb"), None)?;
+
+    let res = shutil.call(py, "copyfileobj", (&response, &fh), None);
+    fh.call_method(py, "close", cpython::NoArgs, None)?;
+    response.call_method(py, "close", cpython::NoArgs, None)?;
+    res?;
+
+    Ok(())
+}
+
+/// Obtain the filesystem path of an asset, downloading it if needed.
+///
+/// An existing file is verified against the expected hash once per process.
+/// A missing or mismatched file is downloaded to a temporary file, verified
+/// and then moved into place, so an interrupted download is never used.
+pub(crate) fn external_asset_path(py: Python, name: String) -> PyResult<PyObject> {
+    // The lock is not held while running Python code, as that can release
+    // the GIL and another thread could request an asset.
+    let (asset, verified) = EXTERNAL_ASSETS
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(a, _)| a.name == name)
+        .cloned()
+        .ok_or_else(|| PyErr::new::<KeyError, _>(py, format!("unknown asset: {}", name)))?;
+
+    if verified && asset.path.exists() {
+        return path_to_pyobject(py, &asset.path);
+    }
+
+    let matches = |path: &Path| -> bool {
+        py.allow_threads(|| file_sha256(path))
+            .map(|digest| digest == asset.sha256.to_lowercase())
+            .unwrap_or(false)
+    };
+
+    if !asset.path.exists() || !matches(&asset.path) {
+        if let Some(parent) = asset.path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| PyErr::new::<OSError, _>(py, e.to_string()))?;
+        }
+
+        let mut temp_name = asset.path.file_name().unwrap_or_default().to_os_string();
+        temp_name.push(format!(".{}.part", uuid::Uuid::new_v4()));
+        let temp_path = asset.path.with_file_name(temp_name);
+
+        let res = download(py, &asset.url, &temp_path).and_then(|_| {
+            if matches(&temp_path) {
+                std::fs::rename(&temp_path, &asset.path)
+                    .map_err(|e| PyErr::new::<OSError, _>(py, e.to_string()))
+            } else {
+                Err(PyErr::new::<OSError, _>(
+                    py,
+                    format!(
+                        "content of {} does not match expected SHA-256 {}",
+                        asset.url, asset.sha256
+                    ),
+                ))
+            }
+        });
+
+        if res.is_err() {
+            let _ = std::fs::remove_file(&temp_path);
+        }
+
+        res?;
+    }
+
+    for entry in EXTERNAL_ASSETS.lock().unwrap().iter_mut() {
+        if entry.0.name == name {
+            entry.1 = true;
+        }
+    }
+
+    path_to_pyobject(py, &asset.path)
+}
@@ -12,7 +12,9 @@ for importing Python modules from memory.
 #[cfg(not(library_mode = "extension"))]
 use cpython::NoArgs;
 use {
+    super::binary_metadata::{binary_metadata, binary_metadata_names},
     super::conversion::pyobject_to_pathbuf,
+    super::external_assets::external_asset_path,
     super::python_resources::{
         pyobject_to_resource, resource_to_pyobject, ModuleFlavor, OptimizeLevel, OxidizedResource,
         PythonResourcesState,
@@ -25,7 +27,11 @@ use {
         PyModule, PyObject, PyResult, PyString, PyTuple, Python, PythonObject, ToPyObject,
     },
     python3_sys as pyffi,
-    std::sync::Arc,
+    python_packaging::python_version::capabilities_for,
+    std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
 };
 #[cfg(windows)]
 use {
@@ -270,6 +276,8 @@ pub(crate) struct ImporterState {
     exec_fn: PyObject,
     /// Bytecode optimization level currently in effect.
     optimize_level: OptimizeLevel,
+    /// Size of bytecode materialized into code objects, in bytes.
+    bytecode_bytes: AtomicUsize,
     /// Holds state about importable resources.
     ///
     /// This field is a PyCapsule and is a glorified wrapper around
@@ -403,6 +411,7 @@ impl ImporterState {
             decode_source,
             exec_fn,
             optimize_level,
+            bytecode_bytes: AtomicUsize::new(0),
             resources_state: capsule,
             resources_state_owned,
             _resources_py_object: resources_py_object,
@@ -705,6 +714,9 @@ impl OxidizedFinder {
             &state.decode_source,
             &state.io_module,
         )? {
+            state
+                .bytecode_bytes
+                .fetch_add(bytecode.len(py)?, Ordering::Relaxed);
             let code = state.marshal_loads.call(py, (bytecode,), None)?;
             let dict = module.getattr(py, "__dict__")?;
 
@@ -768,6 +780,9 @@ impl OxidizedFinder {
             &state.decode_source,
             &state.io_module,
         )? {
+            state
+                .bytecode_bytes
+                .fetch_add(bytecode.len(py)?, Ordering::Relaxed);
             state.marshal_loads.call(py, (bytecode,), None)
         } else if module.flavor == ModuleFlavor::Frozen {
             state
@@ -1310,6 +1325,39 @@ fn get_module_state<'a>(py: Python, m: &'a PyModule) -> Result<&'a mut ModuleSta
     Ok(unsafe { &mut *state })
 }
 
+/// Report memory used by `OxidizedFinder` instances on `sys.meta_path`.
+///
+/// Returns a dict of sizes in bytes of the resources index, of resource data
+/// held in memory and of bytecode materialized into code objects.
+fn memory_stats(py: Python) -> PyResult<PyObject> {
+    let sys_module = py.import("sys")?;
+    let meta_path = sys_module.get(py, "meta_path")?;
+
+    let mut index = 0;
+    let mut module_data = 0;
+    let mut bytecode = 0;
+
+    for finder in meta_path.iter(py)? {
+        let finder = finder?;
+
+        if let Ok(finder) = finder.cast_as::<OxidizedFinder>(py) {
+            let state = finder.state(py);
+            let stats = state.get_resources_state().memory_stats();
+
+            index += stats.index;
+            module_data += stats.module_data;
+            bytecode += state.bytecode_bytes.load(Ordering::Relaxed);
+        }
+    }
+
+    let dict = PyDict::new(py);
+    dict.set_item(py, "index", index)?;
+    dict.set_item(py, "module_data", module_data)?;
+    dict.set_item(py, "bytecode", bytecode)?;
+
+    Ok(dict.into_object())
+}
+
 /// Decodes source bytes into a str.
 ///
 /// This is effectively a reimplementation of
@@ -1402,13 +1450,16 @@ fn module_init(py: Python, m: &PyModule) -> PyResult<()> {
     let sys_module = py.import("sys")?;
     let version_info = sys_module.get(py, "version_info")?;
 
-    let major_version = version_info.getattr(py, "major")?.extract::<i32>(py)?;
-    let minor_version = version_info.getattr(py, "minor")?.extract::<i32>(py)?;
+    let major_version = version_info.getattr(py, "major")?.extract::<u8>(py)?;
+    let minor_version = version_info.getattr(py, "minor")?.extract::<u8>(py)?;
 
-    if major_version < 3 || minor_version < 8 {
+    if capabilities_for(major_version, minor_version).is_none() {
         return Err(PyErr::new::<ImportError, _>(
             py,
-            "module requires Python 3.8+",
+            format!(
+                "module does not support Python {}.{}",
+                major_version, minor_version
+            ),
         ));
     }
 
@@ -1429,6 +1480,22 @@ fn module_init(py: Python, m: &PyModule) -> PyResult<()> {
         "find_resources_in_path",
         py_fn!(py, find_resources_in_path(path: PyObject)),
     )?;
+    m.add(
+        py,
+        "external_asset_path",
+        py_fn!(py, external_asset_path(name: String)),
+    )?;
+    m.add(
+        py,
+        "binary_metadata",
+        py_fn!(py, binary_metadata(name: String)),
+    )?;
+    m.add(
+        py,
+        "binary_metadata_names",
+        py_fn!(py, binary_metadata_names()),
+    )?;
+    m.add(py, "memory_stats", py_fn!(py, memory_stats()))?;
 
     m.add(py, "OxidizedFinder", py.get_type::<OxidizedFinder>())?;
     m.add(py, "OxidizedResource", py.get_type::<OxidizedResource>())?;
@@ -5,8 +5,10 @@
 //! Manage an embedded Python interpreter.
 
 use {
+    super::binary_metadata::register_binary_metadata,
     super::config::OxidizedPythonInterpreterConfig,
     super::conversion::osstring_to_bytes,
+    super::external_assets::register_external_assets,
     super::importer::{
         initialize_importer, PyInit_oxidized_importer, OXIDIZED_IMPORTER_NAME,
         OXIDIZED_IMPORTER_NAME_STR,
@@ -15,20 +17,28 @@ use {
     super::osutils::resolve_terminfo_dirs,
     super::pyalloc::{make_raw_rust_memory_allocator, RawAllocator},
     super::python_resources::PythonResourcesState,
+    super::resource_scanning::resolve_module_suffixes,
     cpython::{
-        GILGuard, NoArgs, ObjectProtocol, PyDict, PyErr, PyList, PyString, Python, ToPyObject,
+        GILGuard, NoArgs, ObjectProtocol, PyDict, PyErr, PyList, PyResult, PyString, Python,
+        ToPyObject,
     },
     lazy_static::lazy_static,
     python3_sys as pyffi,
-    python_packaging::interpreter::{MemoryAllocatorBackend, TerminfoResolution},
-    std::collections::BTreeSet,
-    std::convert::TryInto,
+    python_packaging::{
+        interpreter::{MemoryAllocatorBackend, TerminfoResolution, BUNDLED_TERMINFO_DIRECTORY},
+        resource::BytecodeOptimizationLevel,
+    },
+    python_packed_resources::overlay::find_overlay,
+    sha2::{Digest, Sha256},
+    std::borrow::Cow,
+    std::collections::{BTreeMap, BTreeSet},
+    std::convert::{TryFrom, TryInto},
     std::env,
     std::ffi::{CStr, OsString},
     std::fmt::{Display, Formatter},
     std::fs,
     std::io::Write,
-    std::path::PathBuf,
+    std::path::{Path, PathBuf},
 };
 
 #[cfg(target_family = "unix")]
@@ -272,6 +282,11 @@ pub struct MainPythonInterpreter<'python, 'interpreter: 'python, 'resources: 'in
     /// in this field. We also store the object in a box so it is on the
     /// heap and not dynamic.
     resources_state: Option<Box<PythonResourcesState<'resources, u8>>>,
+
+    /// Memory mappings of `packed_resources_files` and of the executable.
+    ///
+    /// `resources_state` references this memory, so these must outlive it.
+    packed_resources_mmaps: Vec<memmap::Mmap>,
 }
 
 impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpreter, 'resources> {
@@ -279,7 +294,7 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
     ///
     /// The Python interpreter is initialized as a side-effect. The GIL is held.
     pub fn new(
-        config: OxidizedPythonInterpreterConfig<'resources>,
+        mut config: OxidizedPythonInterpreterConfig<'resources>,
     ) -> Result<MainPythonInterpreter<'python, 'interpreter, 'resources>, NewInterpreterError> {
         match config.terminfo_resolution {
             TerminfoResolution::Dynamic => {
@@ -290,6 +305,28 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
             TerminfoResolution::Static(ref v) => {
                 env::set_var("TERMINFO_DIRS", v);
             }
+            TerminfoResolution::Bundled => {
+                // Like dynamic resolution, respect an existing environment variable.
+                if env::var("TERMINFO_DIRS").is_err() {
+                    let origin = config
+                        .ensure_origin()
+                        .map_err(NewInterpreterError::Simple)?;
+
+                    let mut dirs = vec![origin
+                        .join(BUNDLED_TERMINFO_DIRECTORY)
+                        .display()
+                        .to_string()];
+
+                    // Fall back to the system database for entries not bundled.
+                    if let Some(v) = resolve_terminfo_dirs() {
+                        if !v.is_empty() {
+                            dirs.push(v);
+                        }
+                    }
+
+                    env::set_var("TERMINFO_DIRS", dirs.join(":"));
+                }
+            }
             TerminfoResolution::None => {}
         }
 
@@ -301,6 +338,7 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
             gil: None,
             py: None,
             resources_state: None,
+            packed_resources_mmaps: vec![],
         };
 
         res.init()?;
@@ -351,6 +389,13 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
             std::env::set_var("TCL_LIBRARY", tcl_library);
         }
 
+        for (key, value) in self.config.resolve_environment_variables()? {
+            std::env::set_var(key, value);
+        }
+
+        register_external_assets(self.config.resolve_external_assets()?);
+        register_binary_metadata(self.config.binary_metadata)?;
+
         self.config
             .resolve_module_search_paths()
             .map_err(|e| NewInterpreterError::Simple(e))?;
@@ -403,6 +448,13 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
             }
         }
 
+        // Selecting an entry point changes the run configuration. So this
+        // must happen before it is converted.
+        let args = self
+            .config
+            .resolve_entry_point_argv()
+            .map_err(NewInterpreterError::Dynamic)?;
+
         let mut py_config: pyffi::PyConfig = (&self.config)
             .try_into()
             .map_err(NewInterpreterError::Dynamic)?;
@@ -411,12 +463,40 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
         // our custom importer before Python attempts any imports.
         py_config._init_main = 0;
 
+        // We print our own banner when starting a customized REPL. So
+        // prevent Python from printing its default one.
+        if self.config.repl_banner.is_some() && self.config.uses_repl() {
+            py_config.quiet = 1;
+        }
+
         // Set PyConfig.argv if we didn't do so already.
-        if let Some(args) = self.config.resolve_sys_argv() {
+        if let Some(args) = args {
             set_argv(&mut py_config, &args)?;
         }
 
+        // Our PyConfig bindings lack platlibdir. So it is applied via an
+        // environment variable, which is only read during core
+        // initialization. Restore the previous value afterwards so it isn't
+        // inherited by child processes.
+        let previous_platlibdir =
+            self.config
+                .interpreter_config
+                .platlibdir
+                .as_ref()
+                .map(|platlibdir| {
+                    let previous = std::env::var_os("PYTHONPLATLIBDIR");
+                    std::env::set_var("PYTHONPLATLIBDIR", platlibdir);
+                    previous
+                });
+
         let status = unsafe { pyffi::Py_InitializeFromConfig(&py_config) };
+
+        match previous_platlibdir {
+            Some(Some(value)) => std::env::set_var("PYTHONPLATLIBDIR", value),
+            Some(None) => std::env::remove_var("PYTHONPLATLIBDIR"),
+            None => {}
+        }
+
         if unsafe { pyffi::PyStatus_Exception(status) } != 0 {
             return Err(NewInterpreterError::new_from_pystatus(
                 &status,
@@ -432,6 +512,27 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
         let py = unsafe { Python::assume_gil_acquired() };
 
         if self.config.oxidized_importer {
+            let mut packed_resources = self.config.packed_resources.clone();
+            if self.config.packed_resources_overlay {
+                packed_resources.push(self.map_packed_resources_overlay()?);
+            }
+            packed_resources.extend(self.map_packed_resources_files()?);
+
+            set_pyimport_frozen_modules(&self.config, &packed_resources)?;
+
+            // Directories holding packed resources data are loaded like
+            // `packed_resources_files`. Others need scanning.
+            let mut scanned_dirs = vec![];
+            for dir in self.config.resolve_resource_dirs()? {
+                let packed_path = dir.join("packed-resources");
+
+                if packed_path.is_file() {
+                    packed_resources.push(self.map_packed_resources_file(&packed_path, None)?);
+                } else if dir.is_dir() {
+                    scanned_dirs.push(dir);
+                }
+            }
+
             self.resources_state = Some(Box::new(
                 PythonResourcesState::new_from_env()
                     .map_err(|err| NewInterpreterError::Simple(err))?,
@@ -439,9 +540,26 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
 
             if let Some(ref mut resources_state) = self.resources_state {
                 resources_state
-                    .load(&self.config.packed_resources)
+                    .load(&packed_resources)
                     .map_err(|err| NewInterpreterError::Simple(err))?;
 
+                if !scanned_dirs.is_empty() {
+                    let (cache_tag, suffixes) = resolve_module_suffixes(py).map_err(|err| {
+                        NewInterpreterError::new_from_pyerr(py, err, "resolving module suffixes")
+                    })?;
+
+                    for dir in &scanned_dirs {
+                        resources_state
+                            .load_resources_dir(dir, &cache_tag, &suffixes)
+                            .map_err(NewInterpreterError::Dynamic)?;
+                    }
+                }
+
+                resources_state.restrict_packages(
+                    &self.config.restricted_packages,
+                    &self.config.resolve_capabilities(),
+                );
+
                 let oxidized_importer = py.import(OXIDIZED_IMPORTER_NAME_STR).map_err(|err| {
                     NewInterpreterError::new_from_pyerr(
                         py,
@@ -496,6 +614,12 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
                 })?;
         }
 
+        if !self.config.dev_source_roots.is_empty() {
+            install_dev_source_finder(py, &self.config.dev_source_roots).map_err(|err| {
+                NewInterpreterError::new_from_pyerr(py, err, "installing dev source finder")
+            })?;
+        }
+
         /* Pre-initialization functions we could support:
          *
          * PyObject_SetArenaAllocator()
@@ -561,6 +685,88 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
             }
         }
 
+        // These need to be in effect before any code starts threads or
+        // event loops.
+        if let Some(stack_size) = self.config.thread_stack_size {
+            let thread_module = py
+                .import("_thread")
+                .map_err(|err| NewInterpreterError::new_from_pyerr(py, err, "importing _thread"))?;
+
+            thread_module
+                .call(py, "stack_size", (stack_size,), None)
+                .map_err(|err| {
+                    NewInterpreterError::new_from_pyerr(py, err, "setting thread stack size")
+                })?;
+        }
+
+        if let Some(policy_name) = &self.config.asyncio_event_loop_policy {
+            let asyncio_module = py
+                .import("asyncio")
+                .map_err(|err| NewInterpreterError::new_from_pyerr(py, err, "importing asyncio"))?;
+
+            asyncio_module
+                .get(py, policy_name.as_str())
+                .and_then(|policy_type| policy_type.call(py, NoArgs, None))
+                .and_then(|policy| {
+                    asyncio_module.call(py, "set_event_loop_policy", (policy,), None)
+                })
+                .map_err(|err| {
+                    NewInterpreterError::new_from_pyerr(
+                        py,
+                        err,
+                        &format!("setting asyncio event loop policy {}", policy_name),
+                    )
+                })?;
+        }
+
+        if !self.config.preload_modules.is_empty() {
+            start_module_preloading(py, &self.config.preload_modules).map_err(|err| {
+                NewInterpreterError::new_from_pyerr(py, err, "starting module preloading")
+            })?;
+        }
+
+        let gettext_domains = self.config.resolve_gettext_domains()?;
+
+        if !gettext_domains.is_empty() {
+            let gettext_module = py
+                .import("gettext")
+                .map_err(|err| NewInterpreterError::new_from_pyerr(py, err, "importing gettext"))?;
+
+            for (domain, path) in gettext_domains {
+                gettext_module
+                    .call(
+                        py,
+                        "bindtextdomain",
+                        (domain.as_str(), path.display().to_string()),
+                        None,
+                    )
+                    .map_err(|err| {
+                        NewInterpreterError::new_from_pyerr(
+                            py,
+                            err,
+                            &format!("binding gettext domain {}", domain),
+                        )
+                    })?;
+            }
+        }
+
+        if let Some(tzpath) = self.config.resolve_tzpath()? {
+            let zoneinfo_module = py.import("zoneinfo").map_err(|err| {
+                NewInterpreterError::new_from_pyerr(py, err, "importing zoneinfo")
+            })?;
+
+            zoneinfo_module
+                .call(
+                    py,
+                    "reset_tzpath",
+                    (vec![tzpath.display().to_string()],),
+                    None,
+                )
+                .map_err(|err| {
+                    NewInterpreterError::new_from_pyerr(py, err, "setting zoneinfo search path")
+                })?;
+        }
+
         Ok(())
     }
 
@@ -607,6 +813,9 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
     /// `OxidizedPythonInterpreterConfig.run` and return an integer suitable
     /// for use as a process exit code.
     ///
+    /// If no code to run is configured but any of the `repl_*` fields are
+    /// set, the customized interactive interpreter (REPL) is started.
+    ///
     /// `Py_RunMain` is the most robust mechanism to run code, files, or
     /// modules, as `Py_RunMain()` invokes the same APIs that `python` would.
     ///
@@ -616,7 +825,19 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
     /// to keep the interpreter alive or inspect the evaluation result, consider
     /// calling a function in the `python_eval` module.
     pub fn run_as_main(&mut self) -> i32 {
-        if self.config.uses_py_runmain() {
+        if let Err(msg) = self.import_pre_run_modules() {
+            eprintln!("{}", msg);
+            return 1;
+        }
+
+        if self.config.uses_repl() {
+            if let Err(msg) = self.prepare_repl() {
+                eprintln!("{}", msg);
+                return 1;
+            }
+        }
+
+        if self.config.uses_py_runmain() || self.config.uses_repl() {
             let res = unsafe { pyffi::Py_RunMain() };
 
             // Py_RunMain() finalizes the interpreter. So drop our refs and state.
@@ -631,6 +852,181 @@ impl<'python, 'interpreter, 'resources> MainPythonInterpreter<'python, 'interpre
             0
         }
     }
+
+    /// Memory map the files in `packed_resources_files`, verifying their content.
+    ///
+    /// The mappings are retained by this instance.
+    fn map_packed_resources_files(&mut self) -> Result<Vec<&'resources [u8]>, NewInterpreterError> {
+        let mut datas = vec![];
+
+        for file in self.config.resolve_packed_resources_files()? {
+            datas.push(self.map_packed_resources_file(&file.path, file.sha256.as_deref())?);
+        }
+
+        Ok(datas)
+    }
+
+    /// Memory map the current executable and locate its packed resources overlay.
+    ///
+    /// The mapping is retained by this instance.
+    fn map_packed_resources_overlay(&mut self) -> Result<&'resources [u8], NewInterpreterError> {
+        self.config.ensure_origin()?;
+        let exe = self.config.exe.clone().unwrap();
+
+        let f = fs::File::open(&exe).map_err(|e| {
+            NewInterpreterError::Dynamic(format!("unable to open {}: {}", exe.display(), e))
+        })?;
+
+        let mapped = unsafe { memmap::Mmap::map(&f) }.map_err(|e| {
+            NewInterpreterError::Dynamic(format!("unable to memory map {}: {}", exe.display(), e))
+        })?;
+
+        let overlay = find_overlay(&mapped).ok_or_else(|| {
+            NewInterpreterError::Dynamic(format!(
+                "{} does not contain packed resources data; was it modified after building?",
+                exe.display()
+            ))
+        })?;
+
+        // See map_packed_resources_file() for why this is safe.
+        let data = unsafe { std::slice::from_raw_parts::<u8>(overlay.as_ptr(), overlay.len()) };
+
+        self.packed_resources_mmaps.push(mapped);
+
+        Ok(data)
+    }
+
+    /// Memory map a packed resources file, verifying its content if a SHA-256 is given.
+    ///
+    /// The mapping is retained by this instance.
+    fn map_packed_resources_file(
+        &mut self,
+        path: &Path,
+        sha256: Option<&str>,
+    ) -> Result<&'resources [u8], NewInterpreterError> {
+        let f = fs::File::open(path).map_err(|e| {
+            NewInterpreterError::Dynamic(format!(
+                "unable to open packed resources file {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        let mapped = unsafe { memmap::Mmap::map(&f) }.map_err(|e| {
+            NewInterpreterError::Dynamic(format!(
+                "unable to memory map packed resources file {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        if let Some(expected) = sha256 {
+            let actual = format!("{:x}", Sha256::digest(&mapped));
+
+            if !actual.eq_ignore_ascii_case(expected) {
+                return Err(NewInterpreterError::Dynamic(format!(
+                    "packed resources file {} has SHA-256 {}; expected {}",
+                    path.display(),
+                    actual,
+                    expected
+                )));
+            }
+        }
+
+        // As with the importer's resources file support, we create a slice
+        // that isn't bound to the lifetime of the mapping. The mapping is
+        // stored in this instance, so it outlives the parsed resources.
+        let data = unsafe { std::slice::from_raw_parts::<u8>(mapped.as_ptr(), mapped.len()) };
+
+        self.packed_resources_mmaps.push(mapped);
+
+        Ok(data)
+    }
+
+    /// Execute Python source code in the `__main__` module.
+    ///
+    /// Unlike `run_as_main()`, the interpreter remains initialized afterwards,
+    /// so this can be called multiple times. An uncaught exception is printed
+    /// to `sys.stderr` and results in an error.
+    pub fn run_code(&mut self, code: &str) -> Result<(), &'static str> {
+        let py = self.acquire_gil()?;
+
+        match py.run(code, None, None) {
+            Ok(()) => Ok(()),
+            Err(err) => {
+                err.print(py);
+                Err("uncaught Python exception")
+            }
+        }
+    }
+
+    /// Import the modules of `pre_run_modules`, in order.
+    fn import_pre_run_modules(&mut self) -> Result<(), NewInterpreterError> {
+        if self.config.pre_run_modules.is_empty() {
+            return Ok(());
+        }
+
+        let py = self.acquire_gil()?;
+
+        for name in &self.config.pre_run_modules {
+            py.import(name).map_err(|err| {
+                NewInterpreterError::new_from_pyerr(
+                    py,
+                    err,
+                    &format!("importing pre-run module {}", name),
+                )
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Prepare the interpreter for running a customized REPL.
+    ///
+    /// This sets `sys.ps1` and `sys.ps2`, prints the custom banner and runs
+    /// the configured startup code, mimicking what `Py_RunMain()` does for
+    /// the default banner and `PYTHONSTARTUP`.
+    fn prepare_repl(&mut self) -> Result<(), NewInterpreterError> {
+        let py = self.acquire_gil()?;
+
+        let sys = py
+            .import("sys")
+            .map_err(|err| NewInterpreterError::new_from_pyerr(py, err, "obtaining sys module"))?;
+
+        if let Some(ps1) = &self.config.repl_ps1 {
+            sys.add(py, "ps1", ps1)
+                .map_err(|err| NewInterpreterError::new_from_pyerr(py, err, "setting sys.ps1"))?;
+        }
+
+        if let Some(ps2) = &self.config.repl_ps2 {
+            sys.add(py, "ps2", ps2)
+                .map_err(|err| NewInterpreterError::new_from_pyerr(py, err, "setting sys.ps2"))?;
+        }
+
+        if let Some(banner) = &self.config.repl_banner {
+            // Python only prints its banner if stdin is interactive. Do the same.
+            let interactive = self.config.interpreter_config.interactive == Some(true)
+                || sys
+                    .get(py, "stdin")
+                    .and_then(|stdin| stdin.call_method(py, "isatty", NoArgs, None))
+                    .and_then(|value| value.is_true(py))
+                    .unwrap_or(false);
+
+            if interactive {
+                eprintln!("{}", banner);
+            }
+        }
+
+        // Like PYTHONSTARTUP, errors are printed but don't prevent the REPL
+        // from starting.
+        if let Some(code) = &self.config.repl_startup_code {
+            if let Err(err) = py.run(code, None, None) {
+                err.print(py);
+            }
+        }
+
+        Ok(())
+    }
 }
 
 static mut ORIGINAL_BUILTIN_EXTENSIONS: Option<Vec<pyffi::_inittab>> = None;
@@ -702,6 +1098,224 @@ fn set_pyimport_inittab(config: &OxidizedPythonInterpreterConfig) {
     }
 }
 
+/// Frozen modules tables maintained by `set_pyimport_frozen_modules()`.
+#[derive(Default)]
+struct FrozenModulesState {
+    /// Entries of the table Python was built with.
+    original: Option<Vec<pyffi::_frozen>>,
+    /// The table `PyImport_FrozenModules` points to.
+    replaced: Option<Box<Vec<pyffi::_frozen>>>,
+    /// Names referenced by entries of `replaced`.
+    names: Vec<std::ffi::CString>,
+}
+
+// Entries reference static data, names owned by the state and mapped
+// resources data, none of which is tied to a thread.
+unsafe impl Send for FrozenModulesState {}
+
+lazy_static! {
+    static ref FROZEN_MODULES: std::sync::Mutex<FrozenModulesState> =
+        std::sync::Mutex::new(FrozenModulesState::default());
+}
+
+/// Set PyImport_FrozenModules from config options.
+///
+/// Like `PyImport_Inittab`, we maintain our own copy of this array. Added
+/// entries reference bytecode in the packed resources data, which remains
+/// mapped for the lifetime of the interpreter. The array is consulted by
+/// `FrozenImporter` on every import, so it can be replaced after the core
+/// of Python is initialized.
+fn set_pyimport_frozen_modules(
+    config: &OxidizedPythonInterpreterConfig,
+    packed_resources: &[&[u8]],
+) -> Result<(), NewInterpreterError> {
+    let mut state = FROZEN_MODULES.lock().unwrap_or_else(|e| e.into_inner());
+
+    if state.original.is_none() {
+        if config.frozen_modules.is_empty() {
+            return Ok(());
+        }
+
+        let mut entries: Vec<pyffi::_frozen> = Vec::new();
+
+        for i in 0.. {
+            let record = unsafe { pyffi::PyImport_FrozenModules.offset(i) };
+
+            if unsafe { (*record).name.is_null() } {
+                break;
+            }
+
+            entries.push(unsafe { *record });
+        }
+
+        state.original = Some(entries);
+    }
+
+    let optimize_level = config
+        .interpreter_config
+        .optimization_level
+        .unwrap_or(BytecodeOptimizationLevel::Zero);
+
+    // Later resources data replaces earlier, as when indexing resources.
+    let mut bytecodes = BTreeMap::new();
+    for data in packed_resources {
+        for resource in python_packed_resources::parser::load_resources(data)? {
+            let resource = resource?;
+
+            if !config
+                .frozen_modules
+                .iter()
+                .any(|name| name == &resource.name)
+            {
+                continue;
+            }
+
+            let bytecode = match optimize_level {
+                BytecodeOptimizationLevel::Zero => resource.in_memory_bytecode,
+                BytecodeOptimizationLevel::One => resource.in_memory_bytecode_opt1,
+                BytecodeOptimizationLevel::Two => resource.in_memory_bytecode_opt2,
+            };
+
+            // Parsed data is borrowed from the packed resources.
+            if let Some(Cow::Borrowed(bytecode)) = bytecode {
+                bytecodes.insert(resource.name.to_string(), (resource.is_package, bytecode));
+            }
+        }
+    }
+
+    let mut modules = Box::new(state.original.as_ref().unwrap().clone());
+    let mut names = Vec::new();
+
+    for name in &config.frozen_modules {
+        let (is_package, bytecode) = bytecodes.get(name).ok_or_else(|| {
+            NewInterpreterError::Dynamic(format!(
+                "frozen module {} has no in-memory bytecode at optimization level {:?}",
+                name, optimize_level
+            ))
+        })?;
+
+        let c_name = std::ffi::CString::new(name.as_str())
+            .map_err(|_| NewInterpreterError::Simple("invalid frozen module name"))?;
+
+        // Packages are denoted by a negative size.
+        let size = i32::try_from(bytecode.len()).map_err(|_| {
+            NewInterpreterError::Dynamic(format!("frozen module {} is too large", name))
+        })?;
+        modules.push(pyffi::_frozen {
+            name: c_name.as_ptr(),
+            code: bytecode.as_ptr(),
+            size: if *is_package { -size } else { size },
+        });
+        names.push(c_name);
+    }
+
+    // Add sentinel record with NULLs.
+    modules.push(pyffi::_frozen {
+        name: std::ptr::null(),
+        code: std::ptr::null(),
+        size: 0,
+    });
+
+    // And finally replace the static in Python's code with our instance.
+    let ptr = modules.as_ptr();
+    state.replaced = Some(modules);
+    unsafe {
+        pyffi::PyImport_FrozenModules = ptr;
+    }
+    state.names = names;
+
+    Ok(())
+}
+
+/// Request that the main thread of the interpreter raise `KeyboardInterrupt`.
+///
+/// This is the equivalent of Python's `_thread.interrupt_main()`. It can be
+/// called from any thread, without holding the GIL, including from signal
+/// and service control handlers.
+pub fn interrupt_main() {
+    unsafe { pyffi::PyErr_SetInterrupt() }
+}
+
+/// Python source code defining a meta path finder for development source roots.
+///
+/// Only paths under the roots are searched, so submodules of packages
+/// provided by other finders are unaffected.
+const DEV_SOURCE_FINDER_CODE: &str = r#"
+import os
+import sys
+from importlib.machinery import PathFinder
+
+
+class DevSourceFinder:
+    def __init__(self, roots):
+        self._roots = [os.path.abspath(p) for p in roots]
+
+    def _is_under_roots(self, path):
+        path = os.path.abspath(path)
+        return any(path == r or path.startswith(r + os.sep) for r in self._roots)
+
+    def find_spec(self, fullname, path=None, target=None):
+        if path is None:
+            path = self._roots
+        else:
+            path = [p for p in path if isinstance(p, str) and self._is_under_roots(p)]
+
+        if not path:
+            return None
+
+        return PathFinder.find_spec(fullname, path, target)
+
+    def invalidate_caches(self):
+        PathFinder.invalidate_caches()
+
+
+sys.meta_path.insert(0, DevSourceFinder(roots))
+"#;
+
+/// Install a meta path finder preferring modules in the given directories.
+fn install_dev_source_finder(py: Python, roots: &[PathBuf]) -> PyResult<()> {
+    let globals = PyDict::new(py);
+    globals.set_item(py, "__name__", "_pyembed_dev_source")?;
+    globals.set_item(
+        py,
+        "roots",
+        roots
+            .iter()
+            .map(|p| p.display().to_string())
+            .collect::<Vec<_>>(),
+    )?;
+
+    py.run(DEV_SOURCE_FINDER_CODE, Some(&globals), None)
+}
+
+/// Python source code starting a thread importing modules in order.
+const PRELOAD_MODULES_CODE: &str = r#"
+import importlib
+import threading
+
+
+def _preload(names):
+    for name in names:
+        try:
+            importlib.import_module(name)
+        except Exception:
+            pass
+
+
+threading.Thread(
+    target=_preload, args=(names,), name="pyembed-preload", daemon=True
+).start()
+"#;
+
+/// Start a daemon thread importing modules in the background.
+fn start_module_preloading(py: Python, names: &[String]) -> PyResult<()> {
+    let globals = PyDict::new(py);
+    globals.set_item(py, "__name__", "_pyembed_preload")?;
+    globals.set_item(py, "names", names.to_vec())?;
+
+    py.run(PRELOAD_MODULES_CODE, Some(&globals), None)
+}
+
 /// Write loaded Python modules to a directory.
 ///
 /// Given a Python interpreter and a path to a directory, this will create a
@@ -240,6 +240,18 @@ impl<'a> OxidizedPythonInterpreterConfig<'a> {
             || self.interpreter_config.run_filename.is_some()
             || self.interpreter_config.run_module.is_some()
     }
+
+    /// Whether the run configuration should start a customized REPL.
+    ///
+    /// This is true if no code to run is configured and any of the `repl_*`
+    /// fields are set.
+    pub(crate) fn uses_repl(&self) -> bool {
+        !self.uses_py_runmain()
+            && (self.repl_banner.is_some()
+                || self.repl_startup_code.is_some()
+                || self.repl_ps1.is_some()
+                || self.repl_ps2.is_some())
+    }
 }
 
 pub fn python_interpreter_config_to_py_pre_config(
@@ -480,6 +492,9 @@ pub fn python_interpreter_config_to_py_config(
     if let Some(home) = &value.home {
         set_config_string_from_path(&config, &config.home, home, "setting home")?;
     }
+    if let Some(module_search_paths_set) = value.module_search_paths_set {
+        config.module_search_paths_set = if module_search_paths_set { 1 } else { 0 };
+    }
     if let Some(module_search_paths) = &value.module_search_paths {
         config.module_search_paths_set = 1;
 
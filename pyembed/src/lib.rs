@@ -39,8 +39,8 @@ interfacing.
 **It is an explicit goal of this crate to rely on as few external dependencies
 as possible.** This is because we want to minimize bloat in produced binaries.
 At this time, we have required direct dependencies on published versions of the
-`anyhow`, `lazy_static`, `libc`, `memmap`, `python-packed-resources`, and `uuid`
-crates. On Windows, this list is extended by `memory-module-sys` and `winapi`,
+`anyhow`, `lazy_static`, `libc`, `memmap`, `python-packed-resources`, `sha2`,
+and `uuid` crates. On Windows, this list is extended by `memory-module-sys` and `winapi`,
 which are required to support loading DLLs from memory. We also have an optional
 direct dependency on the `jemalloc-sys` crate.
 
@@ -83,9 +83,11 @@ That crate's build script will attempt to find a `libpython` from the
 
 */
 
+mod binary_metadata;
 #[cfg(not(library_mode = "extension"))]
 mod config;
 mod conversion;
+mod external_assets;
 #[allow(clippy::transmute_ptr_to_ptr, clippy::zero_ptr)]
 mod importer;
 #[cfg(not(library_mode = "extension"))]
@@ -112,13 +114,22 @@ pub mod technotes;
 #[cfg(test)]
 mod test;
 
+#[allow(unused_imports)]
+pub use crate::binary_metadata::{parse_binary_metadata, BINARY_METADATA_MAGIC};
+
+#[cfg(not(library_mode = "extension"))]
+#[allow(unused_imports)]
+pub use crate::config::{
+    ExtensionModule, OxidizedPythonInterpreterConfig, PackedResourcesFile, DEBUG_SHELL_FLAG,
+};
+
 #[cfg(not(library_mode = "extension"))]
 #[allow(unused_imports)]
-pub use crate::config::{ExtensionModule, OxidizedPythonInterpreterConfig};
+pub use crate::external_assets::ExternalAsset;
 
 #[cfg(not(library_mode = "extension"))]
 #[allow(unused_imports)]
-pub use crate::interpreter::{MainPythonInterpreter, NewInterpreterError};
+pub use crate::interpreter::{interrupt_main, MainPythonInterpreter, NewInterpreterError};
 
 #[cfg(library_mode = "extension")]
 pub use crate::importer::PyInit_oxidized_importer;
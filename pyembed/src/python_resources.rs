@@ -10,19 +10,22 @@ use {
     super::conversion::{
         path_to_pathlib_path, path_to_pyobject, pyobject_optional_resources_map_to_owned_bytes,
         pyobject_optional_resources_map_to_pathbuf, pyobject_to_owned_bytes_optional,
-        pyobject_to_pathbuf_optional,
+        pyobject_to_pathbuf, pyobject_to_pathbuf_optional,
     },
     anyhow::Result,
     cpython::exc::{ImportError, OSError, TypeError},
     cpython::{
-        py_class, NoArgs, ObjectProtocol, PyBytes, PyDict, PyErr, PyList, PyModule, PyObject,
-        PyResult, PyString, PyTuple, Python, PythonObject, ToPyObject,
+        py_class, NoArgs, ObjectProtocol, PyBytes, PyClone, PyDict, PyErr, PyList, PyModule,
+        PyObject, PyResult, PyString, PyTuple, Python, PythonObject, ToPyObject,
     },
     python3_sys as pyffi,
+    python_packaging::filesystem_scanning::find_python_resources,
+    python_packaging::module_util::{normalize_module_name, PythonModuleSuffixes},
+    python_packaging::resource::{BytecodeOptimizationLevel, DataLocation, PythonResource},
     python_packed_resources::data::Resource,
     std::borrow::Cow,
     std::cell::RefCell,
-    std::collections::HashMap,
+    std::collections::{HashMap, HashSet},
     std::ffi::CStr,
     std::iter::FromIterator,
     std::path::{Path, PathBuf},
@@ -50,10 +53,12 @@ where
                 entry.in_memory_bytecode.is_some() || entry.relative_path_module_bytecode.is_some()
             }
             OptimizeLevel::One => {
-                entry.in_memory_bytecode_opt1.is_some() || entry.in_memory_bytecode_opt1.is_some()
+                entry.in_memory_bytecode_opt1.is_some()
+                    || entry.relative_path_module_bytecode_opt1.is_some()
             }
             OptimizeLevel::Two => {
-                entry.in_memory_bytecode_opt2.is_some() || entry.in_memory_bytecode_opt2.is_some()
+                entry.in_memory_bytecode_opt2.is_some()
+                    || entry.relative_path_module_bytecode_opt2.is_some()
             }
         }
 }
@@ -355,6 +360,18 @@ impl<'a> ImportablePythonModule<'a, u8> {
     }
 }
 
+/// Memory used by the resources of a `PythonResourcesState`, in bytes.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct ResourcesMemoryStats {
+    /// Memory used by the index of resources.
+    pub index: usize,
+
+    /// Size of resource data held in memory.
+    ///
+    /// Data of resources installed on the filesystem is not included.
+    pub module_data: usize,
+}
+
 /// Defines Python resources available for import.
 #[derive(Debug)]
 pub(crate) struct PythonResourcesState<'a, X>
@@ -412,6 +429,270 @@ impl<'a> PythonResourcesState<'a, u8> {
         Ok(())
     }
 
+    /// Index Python resources in a directory on the filesystem.
+    ///
+    /// The directory is scanned like a `sys.path` entry. Modules having
+    /// bytecode next to where their source would be, without the source,
+    /// are also found, so the directory can hold bytecode only.
+    ///
+    /// Resources replace existing resources of the same name. Their data is
+    /// read from the filesystem when needed.
+    pub fn load_resources_dir(
+        &mut self,
+        path: &Path,
+        cache_tag: &str,
+        suffixes: &PythonModuleSuffixes,
+    ) -> Result<(), String> {
+        let mut seen = HashSet::new();
+
+        for resource in find_python_resources(path, cache_tag, suffixes, false, true) {
+            let resource =
+                resource.map_err(|e| format!("error scanning {}: {}", path.display(), e))?;
+
+            match resource {
+                PythonResource::ModuleSource(module) => {
+                    let module = module.into_owned();
+                    let entry = self.dir_resource(&mut seen, &module.name);
+                    entry.is_module = true;
+                    entry.is_package |= module.is_package;
+                    if let DataLocation::Path(source_path) = module.source {
+                        entry.relative_path_module_source = Some(Cow::Owned(source_path));
+                    }
+                }
+                PythonResource::ModuleBytecode(module) => {
+                    let module = module.into_owned();
+                    // Joined rather than passed as a prefix, which would need
+                    // the path as a str.
+                    let bytecode_path = path.join(module.resolve_path(""));
+
+                    let entry = self.dir_resource(&mut seen, &module.name);
+                    entry.is_module = true;
+                    entry.is_package |= module.is_package;
+
+                    let field = match module.optimize_level {
+                        BytecodeOptimizationLevel::Zero => &mut entry.relative_path_module_bytecode,
+                        BytecodeOptimizationLevel::One => {
+                            &mut entry.relative_path_module_bytecode_opt1
+                        }
+                        BytecodeOptimizationLevel::Two => {
+                            &mut entry.relative_path_module_bytecode_opt2
+                        }
+                    };
+                    *field = Some(Cow::Owned(bytecode_path));
+                }
+                PythonResource::ExtensionModule(module) => {
+                    let module = module.into_owned();
+                    if let Some(DataLocation::Path(library_path)) = module.shared_library {
+                        let entry = self.dir_resource(&mut seen, &module.name);
+                        entry.is_extension_module = true;
+                        entry.is_package |= module.is_package;
+                        entry.relative_path_extension_module_shared_library =
+                            Some(Cow::Owned(library_path));
+                    }
+                }
+                PythonResource::PackageResource(resource) => {
+                    let resource = resource.into_owned();
+                    if let DataLocation::Path(data_path) = resource.data {
+                        let entry = self.dir_resource(&mut seen, &resource.leaf_package);
+                        entry.is_module = true;
+                        entry.is_package = true;
+                        entry
+                            .relative_path_package_resources
+                            .get_or_insert_with(HashMap::new)
+                            .insert(Cow::Owned(resource.relative_name), Cow::Owned(data_path));
+                    }
+                }
+                PythonResource::PackageDistributionResource(resource) => {
+                    let resource = resource.into_owned();
+                    if let DataLocation::Path(data_path) = resource.data {
+                        let entry = self.dir_resource(&mut seen, &resource.package);
+                        entry.is_module = true;
+                        entry.is_package = true;
+                        entry
+                            .relative_path_distribution_resources
+                            .get_or_insert_with(HashMap::new)
+                            .insert(Cow::Owned(resource.name), Cow::Owned(data_path));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        self.load_sourceless_bytecode(path, &mut seen)
+            .map_err(|e| format!("error scanning {}: {}", path.display(), e))
+    }
+
+    /// Index bytecode files next to where the source of their module would be.
+    ///
+    /// This is what Python's `SourcelessFileLoader` loads. As with Python, the
+    /// file is only used if the module has no source and is used regardless
+    /// of the optimization level.
+    ///
+    /// Only directories of regular packages, having an `__init__.py` or
+    /// `__init__.pyc` file, are searched. Symlinks to directories aren't
+    /// followed. Modules found with bytecode in `__pycache__` keep it.
+    fn load_sourceless_bytecode(
+        &mut self,
+        root: &Path,
+        seen: &mut HashSet<String>,
+    ) -> std::io::Result<()> {
+        let mut dirs = vec![(root.to_path_buf(), vec![])];
+
+        while let Some((dir, package_parts)) = dirs.pop() {
+            let mut entries = std::fs::read_dir(&dir)?.collect::<std::io::Result<Vec<_>>>()?;
+            entries.sort_by_key(|entry| entry.file_name());
+
+            for entry in entries {
+                let path = entry.path();
+                // Module names are strings. So other files can't be modules.
+                let file_name = match entry.file_name().into_string() {
+                    Ok(file_name) => file_name,
+                    Err(_) => continue,
+                };
+
+                // Unlike `Path::is_dir()`, this doesn't follow symlinks.
+                if entry.file_type()?.is_dir() {
+                    if file_name != "__pycache__"
+                        && !file_name.contains('.')
+                        && (path.join("__init__.py").is_file()
+                            || path.join("__init__.pyc").is_file())
+                    {
+                        let mut parts = package_parts.clone();
+                        parts.push(file_name);
+                        dirs.push((path, parts));
+                    }
+
+                    continue;
+                }
+
+                let stem = match file_name.strip_suffix(".pyc") {
+                    Some(stem) if !stem.contains('.') => stem,
+                    _ => continue,
+                };
+
+                let is_package = stem == "__init__";
+                let mut parts = package_parts.clone();
+                if !is_package {
+                    parts.push(stem.to_string());
+                }
+                if parts.is_empty() {
+                    continue;
+                }
+                let name = normalize_module_name(&parts.join("."));
+
+                let entry = self.dir_resource(seen, &name);
+                if entry.relative_path_module_source.is_some()
+                    || entry
+                        .relative_path_extension_module_shared_library
+                        .is_some()
+                {
+                    continue;
+                }
+
+                entry.is_module = true;
+                entry.is_package |= is_package;
+                for field in [
+                    &mut entry.relative_path_module_bytecode,
+                    &mut entry.relative_path_module_bytecode_opt1,
+                    &mut entry.relative_path_module_bytecode_opt2,
+                ]
+                .iter_mut()
+                {
+                    if field.is_none() {
+                        **field = Some(Cow::Owned(path.clone()));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Obtain the entry of a resource found in a resources directory.
+    ///
+    /// The first time a name is seen, the entry replaces any existing resource.
+    fn dir_resource(&mut self, seen: &mut HashSet<String>, name: &str) -> &mut Resource<'a, u8> {
+        if seen.insert(name.to_string()) {
+            self.resources.insert(
+                Cow::Owned(name.to_string()),
+                Resource {
+                    name: Cow::Owned(name.to_string()),
+                    ..Resource::default()
+                },
+            );
+        }
+
+        self.resources.get_mut(name).unwrap()
+    }
+
+    /// Remove resources of packages requiring capabilities that aren't granted.
+    ///
+    /// `restricted_packages` holds `(package, capability)` pairs. Removed
+    /// resources can't be imported or loaded.
+    pub fn restrict_packages(
+        &mut self,
+        restricted_packages: &[(String, String)],
+        capabilities: &[String],
+    ) {
+        let denied = restricted_packages
+            .iter()
+            .filter(|(_, capability)| !capabilities.contains(capability))
+            .map(|(package, _)| package.as_str())
+            .collect::<Vec<_>>();
+
+        if denied.is_empty() {
+            return;
+        }
+
+        self.resources.retain(|name, _| {
+            !denied.iter().any(|package| {
+                name == package
+                    || (name.starts_with(package)
+                        && name.as_bytes().get(package.len()) == Some(&b'.'))
+            })
+        });
+    }
+
+    /// Obtain the memory used by resources.
+    pub fn memory_stats(&self) -> ResourcesMemoryStats {
+        let mut stats = ResourcesMemoryStats {
+            index: self.resources.capacity()
+                * std::mem::size_of::<(Cow<'a, str>, Resource<'a, u8>)>(),
+            ..Default::default()
+        };
+
+        for (name, resource) in &self.resources {
+            if let Cow::Owned(name) = name {
+                stats.index += name.capacity();
+            }
+
+            stats.module_data += [
+                &resource.in_memory_source,
+                &resource.in_memory_bytecode,
+                &resource.in_memory_bytecode_opt1,
+                &resource.in_memory_bytecode_opt2,
+                &resource.in_memory_extension_module_shared_library,
+                &resource.in_memory_shared_library,
+                &resource.file_data_embedded,
+            ]
+            .iter()
+            .filter_map(|data| data.as_ref().map(|data| data.len()))
+            .sum::<usize>();
+
+            for resources in [
+                &resource.in_memory_package_resources,
+                &resource.in_memory_distribution_resources,
+            ]
+            .iter()
+            .filter_map(|resources| resources.as_ref())
+            {
+                stats.module_data += resources.values().map(|data| data.len()).sum::<usize>();
+            }
+        }
+
+        stats
+    }
+
     /// Add a resource to the instance.
     ///
     /// Memory in the resource must live for at least as long as the lifetime of
@@ -638,7 +919,10 @@ impl<'a> PythonResourcesState<'a, u8> {
         // this functionality some day. But it should likely never be the default
         // because it goes against the spirit of requiring all resources to be
         // known ahead-of-time.
-        let native_path = PathBuf::from(path.to_string_lossy(py).to_string());
+        //
+        // Paths are converted like os.fsencode() so undecodable file names,
+        // which Python represents with surrogate escapes, survive.
+        let native_path = pyobject_to_pathbuf(py, path.as_object().clone_ref(py))?;
 
         let (relative_path, check_in_memory, check_relative_path) =
             if let Ok(relative_path) = native_path.strip_prefix(&self.current_exe) {
@@ -11,12 +11,42 @@ use {
         PythonPackageDistributionResource, PythonPackageResource,
     },
     cpython::exc::ValueError,
-    cpython::{ObjectProtocol, PyErr, PyObject, PyResult, Python, PythonObject, ToPyObject},
+    cpython::{
+        NoArgs, ObjectProtocol, PyErr, PyObject, PyResult, Python, PythonObject, ToPyObject,
+    },
     python_packaging::filesystem_scanning::find_python_resources,
     python_packaging::module_util::PythonModuleSuffixes,
     python_packaging::resource::PythonResource,
 };
 
+/// Resolve the bytecode cache tag and module suffixes of the interpreter.
+///
+/// Unlike `importlib.machinery`, this works before the importers are fully
+/// initialized.
+pub(crate) fn resolve_module_suffixes(py: Python) -> PyResult<(String, PythonModuleSuffixes)> {
+    let sys_module = py.import("sys")?;
+    let implementation = sys_module.get(py, "implementation")?;
+    let cache_tag = implementation
+        .getattr(py, "cache_tag")?
+        .extract::<String>(py)?;
+
+    let imp_module = py.import("_imp")?;
+    let extension = imp_module
+        .call(py, "extension_suffixes", NoArgs, None)?
+        .extract::<Vec<String>>(py)?;
+
+    Ok((
+        cache_tag,
+        PythonModuleSuffixes {
+            source: vec![".py".to_string()],
+            bytecode: vec![".pyc".to_string()],
+            debug_bytecode: vec![".pyc".to_string()],
+            optimized_bytecode: vec![".pyc".to_string()],
+            extension,
+        },
+    ))
+}
+
 /// Scans a filesystem path for Python resources and turns them into Python types.
 pub(crate) fn find_resources_in_path(py: Python, path: PyObject) -> PyResult<PyObject> {
     let path = pyobject_to_pathbuf(py, path)?;
@@ -89,6 +119,11 @@ pub(crate) fn find_resources_in_path(py: Python, path: PyObject) -> PyResult<PyO
             PythonResource::EggFile(_) => {}
             PythonResource::PathExtension(_) => {}
             PythonResource::File(_) => {}
+            PythonResource::GettextCatalog(catalog) => {
+                res.push(
+                    PythonPackageResource::new(py, catalog.to_package_resource())?.into_object(),
+                );
+            }
         }
     }
 
@@ -106,6 +106,27 @@ rusty_fork_test! {
         );
     }
 
+    /// Restricted packages can only be found with their capability.
+    #[test]
+    fn restricted_packages() {
+        for (capabilities, found) in [(vec![], false), (vec!["pro".to_string()], true)].iter() {
+            let mut config = OxidizedPythonInterpreterConfig::default();
+            config.interpreter_config.parse_argv = Some(false);
+            config.oxidized_importer = true;
+            config.set_missing_path_configuration = false;
+            config.restricted_packages = vec![("errno".to_string(), "pro".to_string())];
+            config.capabilities = capabilities.clone();
+            let mut interp = MainPythonInterpreter::new(config).unwrap();
+            let importer = get_importer(&mut interp).unwrap();
+            let py = interp.acquire_gil().unwrap();
+
+            let spec = importer
+                .call_method(py, "find_spec", ("errno", py.None()), None)
+                .unwrap();
+            assert_eq!(spec != py.None(), *found);
+        }
+    }
+
     /// Run test_importer_builtins.py.
     #[test]
     fn builtins_py() {
@@ -3,7 +3,10 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use {
-    crate::{MainPythonInterpreter, OxidizedPythonInterpreterConfig},
+    crate::{
+        MainPythonInterpreter, OxidizedPythonInterpreterConfig, PackedResourcesFile,
+        DEBUG_SHELL_FLAG,
+    },
     cpython::{ObjectProtocol, PyBytes, PyList, PyObject, PyString, PyStringData},
     python3_sys as pyffi,
     python_packaging::interpreter::PythonInterpreterProfile,
@@ -381,4 +384,321 @@ rusty_fork_test! {
         let tcl_library = config.resolve_tcl_library().unwrap();
         assert_eq!(tcl_library, Some(origin.join("lib").join("tcl8.6").into_os_string()));
     }
+
+    #[test]
+    fn test_packed_resources_files_origin() {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.packed_resources_files = vec![PackedResourcesFile {
+            path: PathBuf::from("$ORIGIN/runtime/packed-resources"),
+            sha256: None,
+        }];
+
+        let origin = std::env::current_exe()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .to_path_buf();
+
+        let files = config.resolve_packed_resources_files().unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, origin.join("runtime").join("packed-resources"));
+    }
+
+    #[test]
+    fn test_entry_points() {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.entry_points = vec![
+            ("tool-a".to_string(), "myapp.a".to_string()),
+            ("tool-b".to_string(), "myapp.b:main".to_string()),
+        ];
+
+        config.argv = Some(vec!["/usr/bin/tool-a".into(), "x".into()]);
+        let args = config.resolve_entry_point_argv().unwrap().unwrap();
+        assert_eq!(args, vec![OsString::from("/usr/bin/tool-a"), OsString::from("x")]);
+        assert_eq!(config.interpreter_config.run_module, Some("myapp.a".to_string()));
+
+        config.argv = Some(vec!["myapp".into(), "tool-b".into(), "x".into()]);
+        config.prepend_args = vec!["--verbose".to_string()];
+        let args = config.resolve_entry_point_argv().unwrap().unwrap();
+        assert_eq!(args, vec![
+            OsString::from("tool-b"),
+            OsString::from("--verbose"),
+            OsString::from("x"),
+        ]);
+        assert_eq!(config.interpreter_config.run_module, None);
+        assert_eq!(
+            config.interpreter_config.run_command,
+            Some("import sys\nfrom myapp.b import main\nsys.exit(main())\n".to_string())
+        );
+
+        config.interpreter_config.run_command = None;
+        config.argv = Some(vec!["myapp".into(), "tool-c".into()]);
+        assert!(config.resolve_entry_point_argv().is_err());
+
+        config.interpreter_config.run_module = Some("myapp".to_string());
+        config.argv0 = Some("myapp".to_string());
+        let args = config.resolve_entry_point_argv().unwrap().unwrap();
+        assert_eq!(args, vec![
+            OsString::from("myapp"),
+            OsString::from("--verbose"),
+            OsString::from("tool-c"),
+        ]);
+        assert_eq!(config.interpreter_config.run_module, Some("myapp".to_string()));
+    }
+
+    #[test]
+    fn test_resource_dirs_flag() {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.resource_dirs = vec![PathBuf::from("$ORIGIN/site")];
+        config.resource_dirs_flag = Some("--extensions".to_string());
+
+        config.argv = Some(vec![
+            "myapp".into(),
+            "--extensions".into(),
+            "/opt/a".into(),
+            "x".into(),
+            "--extensions=/opt/b".into(),
+            "--".into(),
+            "--extensions=/opt/c".into(),
+        ]);
+        let args = config.resolve_entry_point_argv().unwrap().unwrap();
+        assert_eq!(args, vec![
+            OsString::from("myapp"),
+            OsString::from("x"),
+            OsString::from("--"),
+            OsString::from("--extensions=/opt/c"),
+        ]);
+
+        let origin = std::env::current_exe()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .to_path_buf();
+
+        assert_eq!(config.resolve_resource_dirs().unwrap(), vec![
+            origin.join("site"),
+            PathBuf::from("/opt/a"),
+            PathBuf::from("/opt/b"),
+        ]);
+
+        config.argv = Some(vec!["myapp".into(), "--extensions".into()]);
+        assert!(config.resolve_entry_point_argv().is_err());
+    }
+
+    #[test]
+    fn test_debug_shell() {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.interpreter_config.run_module = Some("myapp".to_string());
+        config.pre_run_modules = vec!["myapp.setup".to_string()];
+
+        config.argv = Some(vec!["myapp".into(), DEBUG_SHELL_FLAG.into(), "x".into()]);
+        let args = config.resolve_entry_point_argv().unwrap().unwrap();
+        assert_eq!(args, vec![
+            OsString::from("myapp"),
+            OsString::from(DEBUG_SHELL_FLAG),
+            OsString::from("x"),
+        ]);
+        assert_eq!(config.interpreter_config.run_module, Some("myapp".to_string()));
+
+        config.debug_shell = true;
+        config.argv = Some(vec!["myapp".into(), "x".into(), DEBUG_SHELL_FLAG.into()]);
+        let args = config.resolve_entry_point_argv().unwrap().unwrap();
+        assert_eq!(args.len(), 3);
+        assert_eq!(config.interpreter_config.run_module, Some("myapp".to_string()));
+
+        config.argv = Some(vec!["myapp".into(), DEBUG_SHELL_FLAG.into(), "x".into()]);
+        let args = config.resolve_entry_point_argv().unwrap().unwrap();
+        assert_eq!(args, vec![OsString::from("myapp")]);
+        assert_eq!(config.interpreter_config.run_module, None);
+        assert!(config.pre_run_modules.is_empty());
+        assert!(config.uses_repl());
+        assert!(config
+            .repl_startup_code
+            .as_ref()
+            .unwrap()
+            .contains("def diagnose_import(name):"));
+    }
+
+    #[test]
+    fn test_run_code() {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        // Otherwise the Rust arguments are interpreted as Python arguments.
+        config.interpreter_config.parse_argv = Some(false);
+        config.set_missing_path_configuration = false;
+        let mut interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.run_code("value = 40 + 2").unwrap();
+        interp.run_code("assert value == 42").unwrap();
+        assert!(interp.run_code("raise ValueError('oops')").is_err());
+
+        let py = interp.acquire_gil().unwrap();
+        let main = py.import("__main__").unwrap();
+        assert_eq!(main.get(py, "value").unwrap().extract::<i64>(py).unwrap(), 42);
+    }
+
+    #[test]
+    fn test_thread_and_asyncio_hooks() {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.interpreter_config.parse_argv = Some(false);
+        config.set_missing_path_configuration = false;
+        config.thread_stack_size = Some(512 * 1024);
+        config.asyncio_event_loop_policy = Some("DefaultEventLoopPolicy".to_string());
+        let mut interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp
+            .run_code("import threading; assert threading.stack_size() == 512 * 1024")
+            .unwrap();
+        interp
+            .run_code(
+                "import asyncio; \
+                 assert isinstance(asyncio.get_event_loop_policy(), asyncio.DefaultEventLoopPolicy)",
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_asyncio_event_loop_policy_unknown() {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.interpreter_config.parse_argv = Some(false);
+        config.set_missing_path_configuration = false;
+        config.asyncio_event_loop_policy = Some("MissingEventLoopPolicy".to_string());
+
+        assert!(MainPythonInterpreter::new(config).is_err());
+    }
+
+    #[test]
+    fn test_gettext_domains() {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.interpreter_config.parse_argv = Some(false);
+        config.set_missing_path_configuration = false;
+        config.gettext_domains = vec![("myapp".to_string(), PathBuf::from("$ORIGIN/locale"))];
+
+        let origin = std::env::current_exe()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .to_path_buf();
+
+        let mut interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp
+            .run_code(&format!(
+                "import gettext; assert gettext.bindtextdomain('myapp') == {:?}",
+                origin.join("locale").display().to_string()
+            ))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_tzpath() {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.interpreter_config.parse_argv = Some(false);
+        config.set_missing_path_configuration = false;
+        config.tzpath = Some(PathBuf::from("$ORIGIN/lib/tzdata/zoneinfo"));
+
+        let origin = std::env::current_exe()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .to_path_buf();
+
+        let mut interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp
+            .run_code(&format!(
+                "import os, zoneinfo; assert zoneinfo.TZPATH == ({:?},); assert 'PYTHONTZPATH' not in os.environ",
+                origin.join("lib/tzdata/zoneinfo").display().to_string()
+            ))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_dev_source_roots() {
+        let root = std::env::temp_dir().join("pyembed-test-dev-source-roots");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("pyembed_dev_source.py"), "VALUE = 42\n").unwrap();
+
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.interpreter_config.parse_argv = Some(false);
+        config.set_missing_path_configuration = false;
+        config.dev_source_roots = vec![root.clone()];
+        let mut interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp
+            .run_code("import pyembed_dev_source; assert pyembed_dev_source.VALUE == 42")
+            .unwrap();
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_resource_dirs() {
+        let root = std::env::temp_dir().join("pyembed-test-resource-dirs");
+        std::fs::create_dir_all(root.join("pyembed_sourceless")).unwrap();
+        std::fs::write(root.join("pyembed_resource_dir.py"), "VALUE = 42\n").unwrap();
+        std::fs::write(root.join("pyembed_sourceless").join("__init__.pyc"), "").unwrap();
+        std::fs::create_dir_all(root.join("not_a_package")).unwrap();
+        std::fs::write(root.join("not_a_package").join("pyembed_hidden.pyc"), "").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&root, root.join("pyembed_sourceless").join("loop")).unwrap();
+
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.interpreter_config.parse_argv = Some(false);
+        config.oxidized_importer = true;
+        config.set_missing_path_configuration = false;
+        config.resource_dirs = vec![root.clone(), root.join("missing")];
+        let mut interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp
+            .run_code("import pyembed_resource_dir; assert pyembed_resource_dir.VALUE == 42")
+            .unwrap();
+        interp
+            .run_code(
+                "import importlib.util; \
+                 assert importlib.util.find_spec('pyembed_sourceless').submodule_search_locations",
+            )
+            .unwrap();
+        interp
+            .run_code(
+                "try:\n    import not_a_package.pyembed_hidden\n    assert False\n\
+                 except ImportError:\n    pass\n",
+            )
+            .unwrap();
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_binary_metadata() {
+        let mut data = b"PYOXMETA\x01\x00\x00\x00\x05\x00\x00\x00build".to_vec();
+        data.extend_from_slice(b"\x03\x00\x00\x00abc");
+
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.interpreter_config.parse_argv = Some(false);
+        config.oxidized_importer = true;
+        config.set_missing_path_configuration = false;
+        config.binary_metadata = Some(&data);
+        let mut interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp
+            .run_code(
+                "import oxidized_importer; \
+                 assert oxidized_importer.binary_metadata('build') == b'abc'; \
+                 assert oxidized_importer.binary_metadata_names() == ['build']",
+            )
+            .unwrap();
+        assert!(interp
+            .run_code("import oxidized_importer; oxidized_importer.binary_metadata('missing')")
+            .is_err());
+    }
+
+    #[test]
+    fn test_binary_metadata_invalid() {
+        let mut config = OxidizedPythonInterpreterConfig::default();
+        config.interpreter_config.parse_argv = Some(false);
+        config.set_missing_path_configuration = false;
+        config.binary_metadata = Some(b"PYOXMETA\x01\x00");
+
+        assert!(MainPythonInterpreter::new(config).is_err());
+    }
 }
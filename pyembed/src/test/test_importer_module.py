@@ -15,7 +15,9 @@ class TestImporterModule(unittest.TestCase):
             attrs,
             {
                 "decode_source",
+                "external_asset_path",
                 "find_resources_in_path",
+                "memory_stats",
                 "OxidizedFinder",
                 "OxidizedResourceCollector",
                 "OxidizedResourceReader",
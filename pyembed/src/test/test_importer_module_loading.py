@@ -15,6 +15,7 @@ from oxidized_importer import (
     OxidizedFinder,
     OxidizedResourceCollector,
     find_resources_in_path,
+    memory_stats,
 )
 
 
@@ -143,6 +144,30 @@ class TestImporterModuleLoading(unittest.TestCase):
         with self.assertRaises(ImportError):
             f.get_filename("my_package")
 
+    def test_memory_stats(self):
+        p = self._make_package("my_package")
+
+        with (p / "__init__.py").open("wb") as fh:
+            fh.write(b"import io\n")
+
+        before = memory_stats()
+        self.assertEqual(set(before.keys()), {"index", "module_data", "bytecode"})
+
+        f = self._finder_from_td()
+        sys.meta_path.insert(0, f)
+        try:
+            stats = memory_stats()
+            self.assertGreater(stats["index"], before["index"])
+            self.assertGreater(stats["module_data"], before["module_data"])
+
+            spec = f.find_spec("my_package", None)
+            m = importlib.util.module_from_spec(spec)
+            f.exec_module(m)
+
+            self.assertGreater(memory_stats()["bytecode"], stats["bytecode"])
+        finally:
+            sys.meta_path.remove(f)
+
 
 if __name__ == "__main__":
     unittest.main(exit=False)
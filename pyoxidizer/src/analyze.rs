@@ -5,11 +5,12 @@
 //! Analyze binaries for distribution compatibility.
 
 use {
-    anyhow::Result,
+    anyhow::{anyhow, Result},
     byteorder::ReadBytesExt,
     lazy_static::lazy_static,
     std::{
         collections::BTreeMap,
+        convert::TryFrom,
         ffi::CStr,
         fs::File,
         io::{Cursor, Read},
@@ -477,6 +478,45 @@ pub fn find_undefined_elf_symbols(buffer: &[u8], elf: &goblin::elf::Elf) -> Vec<
     res
 }
 
+/// Find dynamic symbols of an ELF binary requiring a glibc newer than `floor`.
+///
+/// Returned values have the form `symbol@GLIBC_X.Y` and are sorted.
+pub fn find_elf_symbols_requiring_glibc(data: &[u8], floor: &str) -> Result<Vec<String>> {
+    let floor = version_compare::Version::from(floor)
+        .ok_or_else(|| anyhow!("unable to parse glibc version {}", floor))?;
+
+    let elf = goblin::elf::Elf::parse(data)?;
+
+    // Binaries without versioned symbols can't require a specific glibc.
+    if elf.dynamic.is_none()
+        || !elf
+            .section_headers
+            .iter()
+            .any(|header| header.sh_type == goblin::elf::section_header::SHT_GNU_VERSYM)
+    {
+        return Ok(vec![]);
+    }
+
+    let mut res = find_undefined_elf_symbols(data, &elf)
+        .into_iter()
+        .filter_map(|symbol| {
+            let version = symbol.version?;
+            let glibc_version = version_compare::Version::from(version.strip_prefix("GLIBC_")?)?;
+
+            if glibc_version > floor {
+                Some(format!("{}@{}", symbol.symbol, version))
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    res.sort();
+    res.dedup();
+
+    Ok(res)
+}
+
 pub fn find_pe_dependencies(data: &[u8]) -> Result<Vec<String>> {
     let pe = goblin::pe::PE::parse(data)?;
     Ok(pe.libraries.iter().map(|l| (*l).to_string()).collect())
@@ -487,3 +527,205 @@ pub fn find_pe_dependencies_path(path: &Path) -> Result<Vec<String>> {
     let data = std::fs::read(path)?;
     find_pe_dependencies(&data)
 }
+
+/// A macOS version.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct MacOsVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl MacOsVersion {
+    /// Decode a version encoded as `xxxx.yy.zz` nibbles in Mach-O load commands.
+    fn from_mach(value: u32) -> Self {
+        Self {
+            major: value >> 16,
+            minor: (value >> 8) & 0xff,
+            patch: value & 0xff,
+        }
+    }
+}
+
+impl std::fmt::Display for MacOsVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.patch == 0 {
+            write!(f, "{}.{}", self.major, self.minor)
+        } else {
+            write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+        }
+    }
+}
+
+impl TryFrom<&str> for MacOsVersion {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let parts = value
+            .split('.')
+            .map(|part| part.parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("{} is not a valid macOS version", value))?;
+
+        match parts.as_slice() {
+            [major, minor] => Ok(Self {
+                major: *major,
+                minor: *minor,
+                patch: 0,
+            }),
+            [major, minor, patch] => Ok(Self {
+                major: *major,
+                minor: *minor,
+                patch: *patch,
+            }),
+            _ => Err(format!(
+                "{} is not a valid macOS version; expected a value like 10.15",
+                value
+            )),
+        }
+    }
+}
+
+/// Mach-O load command declaring the platform and minimum OS version.
+///
+/// Newer linkers emit it instead of `LC_VERSION_MIN_MACOSX`. goblin doesn't
+/// know about it.
+const LC_BUILD_VERSION: u32 = 0x32;
+
+/// Platform value of `LC_BUILD_VERSION` for macOS.
+const PLATFORM_MACOS: u32 = 1;
+
+fn macho_minimum_macos_version(macho: &goblin::mach::MachO, data: &[u8]) -> Option<MacOsVersion> {
+    let read_u32 = |offset: usize| -> Option<u32> {
+        let bytes = data.get(offset..offset + 4)?;
+        let mut cursor = Cursor::new(bytes);
+
+        if macho.little_endian {
+            cursor.read_u32::<byteorder::LittleEndian>().ok()
+        } else {
+            cursor.read_u32::<byteorder::BigEndian>().ok()
+        }
+    };
+
+    macho.load_commands.iter().find_map(|lc| match &lc.command {
+        goblin::mach::load_command::CommandVariant::VersionMinMacosx(command) => {
+            Some(MacOsVersion::from_mach(command.version))
+        }
+        goblin::mach::load_command::CommandVariant::Unimplemented(header)
+            if header.cmd == LC_BUILD_VERSION =>
+        {
+            // struct build_version_command { cmd, cmdsize, platform, minos, ... }
+            if read_u32(lc.offset + 8)? == PLATFORM_MACOS {
+                Some(MacOsVersion::from_mach(read_u32(lc.offset + 12)?))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    })
+}
+
+/// Find the minimum macOS version a Mach-O binary requires.
+///
+/// For universal binaries, the highest version of all architectures is
+/// returned. `None` is returned if the binary doesn't declare a version.
+pub fn find_macho_minimum_macos_version(data: &[u8]) -> Result<Option<MacOsVersion>> {
+    match goblin::mach::Mach::parse(data)? {
+        goblin::mach::Mach::Binary(macho) => Ok(macho_minimum_macos_version(&macho, data)),
+        goblin::mach::Mach::Fat(multi) => {
+            let mut res = None;
+
+            for arch in multi.iter_arches() {
+                let arch_data = arch?.slice(data);
+                let macho = goblin::mach::MachO::parse(arch_data, 0)?;
+
+                res = res.max(macho_minimum_macos_version(&macho, arch_data));
+            }
+
+            Ok(res)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    fn test_find_elf_symbols_requiring_glibc() -> Result<()> {
+        // The test binary itself links against glibc.
+        let data = std::fs::read(std::env::current_exe()?)?;
+
+        let symbols = find_elf_symbols_requiring_glibc(&data, "2.0")?;
+        assert!(!symbols.is_empty());
+        assert!(symbols.iter().all(|s| s.contains("@GLIBC_")));
+
+        assert!(find_elf_symbols_requiring_glibc(&data, "99.0")?.is_empty());
+
+        Ok(())
+    }
+
+    /// Produce a 64-bit little-endian Mach-O dylib with a single load command.
+    fn macho_with_command(cmd: u32, payload: &[u32]) -> Vec<u8> {
+        let cmdsize = 8 + 4 * payload.len() as u32;
+
+        let header = [
+            0xfeed_facf, // MH_MAGIC_64
+            0x0100_0007, // CPU_TYPE_X86_64
+            3,           // CPU_SUBTYPE_X86_64_ALL
+            6,           // MH_DYLIB
+            1,           // ncmds
+            cmdsize,
+            0, // flags
+            0, // reserved
+        ];
+
+        header
+            .iter()
+            .chain([cmd, cmdsize].iter())
+            .chain(payload.iter())
+            .flat_map(|v| v.to_le_bytes().to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn test_macos_version() {
+        let v = MacOsVersion::try_from("10.15").unwrap();
+        assert_eq!(v.to_string(), "10.15");
+        assert_eq!(
+            MacOsVersion::try_from("10.9.5").unwrap().to_string(),
+            "10.9.5"
+        );
+        assert!(MacOsVersion::try_from("10.9").unwrap() < v);
+        assert!(MacOsVersion::try_from("11.0").unwrap() > v);
+        assert!(MacOsVersion::try_from("10").is_err());
+        assert!(MacOsVersion::try_from("ten.fifteen").is_err());
+    }
+
+    #[test]
+    fn test_find_macho_minimum_macos_version() -> Result<()> {
+        // LC_VERSION_MIN_MACOSX { version, sdk }
+        let data = macho_with_command(0x24, &[0x000a_0900, 0x000a_0f00]);
+        assert_eq!(
+            find_macho_minimum_macos_version(&data)?,
+            Some(MacOsVersion::try_from("10.9").unwrap())
+        );
+
+        // LC_BUILD_VERSION { platform, minos, sdk, ntools }
+        let data = macho_with_command(
+            LC_BUILD_VERSION,
+            &[PLATFORM_MACOS, 0x000b_0000, 0x000b_0100, 0],
+        );
+        assert_eq!(
+            find_macho_minimum_macos_version(&data)?,
+            Some(MacOsVersion::try_from("11.0").unwrap())
+        );
+
+        // iOS.
+        let data = macho_with_command(LC_BUILD_VERSION, &[2, 0x000e_0000, 0x000e_0000, 0]);
+        assert_eq!(find_macho_minimum_macos_version(&data)?, None);
+
+        Ok(())
+    }
+}
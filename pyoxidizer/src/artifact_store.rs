@@ -0,0 +1,545 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+A content-addressable store for intermediate build artifacts.
+
+Builds produce the same intermediate artifacts, such as compiled bytecode
+and object files of extension modules, over and over. The store keeps them
+in the user cache, shared by all projects, so later builds reuse them.
+
+Objects are stored under the SHA-256 of their content. Keys, derived from
+the inputs producing an artifact, map to the object holding it. Reading an
+object marks it as used and `ArtifactStore::gc()` removes the least
+recently used objects until the store fits in a size limit.
+*/
+
+use {
+    crate::{environment::user_cache_dir, file_lock::FileLock},
+    anyhow::{anyhow, Context, Result},
+    filetime::FileTime,
+    python_packaging::{
+        bytecode::{CompileMode, PythonBytecodeCompiler},
+        resource::BytecodeOptimizationLevel,
+    },
+    sha2::{Digest, Sha256},
+    std::{
+        fs,
+        path::{Path, PathBuf},
+    },
+    uuid::Uuid,
+};
+
+/// Environment variable that disables the artifact store.
+pub const NO_ARTIFACT_STORE_ENV: &str = "PYOXIDIZER_NO_ARTIFACT_STORE";
+
+/// Compute a key identifying an artifact from the inputs producing it.
+///
+/// Inputs are length prefixed, so different splits of the same bytes
+/// produce different keys.
+pub fn artifact_key(inputs: &[&[u8]]) -> String {
+    let mut hasher = Sha256::new();
+
+    for input in inputs {
+        hasher.update(&(input.len() as u64).to_le_bytes());
+        hasher.update(input);
+    }
+
+    hex::encode(hasher.finalize())
+}
+
+/// Parse a size like `10G`, `512M`, `64K` or `1000`.
+///
+/// Suffixes are binary multiples. A trailing `B` or `iB` is accepted.
+pub fn parse_size(value: &str) -> Result<u64> {
+    let upper = value.trim().to_uppercase();
+    let trimmed = upper
+        .strip_suffix("IB")
+        .or_else(|| upper.strip_suffix('B'))
+        .unwrap_or(&upper);
+
+    let (number, multiplier) = match trimmed.chars().last() {
+        Some('K') => (&trimmed[..trimmed.len() - 1], 1 << 10),
+        Some('M') => (&trimmed[..trimmed.len() - 1], 1 << 20),
+        Some('G') => (&trimmed[..trimmed.len() - 1], 1 << 30),
+        Some('T') => (&trimmed[..trimmed.len() - 1], 1 << 40),
+        _ => (trimmed, 1),
+    };
+
+    let number = number
+        .trim()
+        .parse::<u64>()
+        .map_err(|_| anyhow!("invalid size: {}", value))?;
+
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| anyhow!("size too large: {}", value))
+}
+
+/// Disk usage of an artifact store.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StoreUsage {
+    /// Number of stored objects.
+    pub objects: usize,
+
+    /// Total size of stored objects in bytes.
+    pub size: u64,
+}
+
+/// Outcome of garbage collecting an artifact store.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GcStats {
+    /// Number of removed objects.
+    pub removed_objects: usize,
+
+    /// Size of removed objects in bytes.
+    pub removed_size: u64,
+
+    /// Usage of the store after collection.
+    pub remaining: StoreUsage,
+}
+
+/// A content-addressable store of build artifacts.
+#[derive(Clone, Debug)]
+pub struct ArtifactStore {
+    root: PathBuf,
+}
+
+impl ArtifactStore {
+    /// Open a store rooted at a directory.
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+        }
+    }
+
+    /// Open the store in the user cache, unless disabled by `NO_ARTIFACT_STORE_ENV`.
+    pub fn open_default() -> Result<Option<Self>> {
+        if std::env::var(NO_ARTIFACT_STORE_ENV).is_ok() {
+            return Ok(None);
+        }
+
+        Ok(Some(Self::new(&user_cache_dir()?.join("artifacts"))))
+    }
+
+    /// The directory holding the store.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn objects_dir(&self) -> PathBuf {
+        self.root.join("objects")
+    }
+
+    fn keys_dir(&self) -> PathBuf {
+        self.root.join("keys")
+    }
+
+    /// Resolve the path of an object from its digest.
+    pub fn object_path(&self, digest: &str) -> PathBuf {
+        self.objects_dir().join(&digest[0..2]).join(&digest[2..])
+    }
+
+    fn key_path(&self, key: &str) -> PathBuf {
+        self.keys_dir().join(&key[0..2]).join(&key[2..])
+    }
+
+    /// Write a file so readers never see partial content.
+    fn write_atomic(&self, path: &Path, data: &[u8]) -> Result<()> {
+        let temp_dir = self.root.join("tmp");
+        fs::create_dir_all(&temp_dir)
+            .with_context(|| format!("creating {}", temp_dir.display()))?;
+
+        let temp_path = temp_dir.join(Uuid::new_v4().to_string());
+        fs::write(&temp_path, data).with_context(|| format!("writing {}", temp_path.display()))?;
+
+        let parent = path
+            .parent()
+            .ok_or_else(|| anyhow!("unable to determine parent directory"))?;
+        fs::create_dir_all(parent).with_context(|| format!("creating {}", parent.display()))?;
+
+        // Renaming over an existing file fails on Windows. Another build
+        // storing the same content concurrently is fine.
+        fs::rename(&temp_path, path).or_else(|e| {
+            fs::remove_file(&temp_path)?;
+
+            if path.exists() {
+                Ok(())
+            } else {
+                Err(anyhow::Error::from(e).context(format!("renaming to {}", path.display())))
+            }
+        })
+    }
+
+    /// Mark a stored file as used.
+    fn touch(path: &Path) -> Result<()> {
+        filetime::set_file_mtime(path, FileTime::now())
+            .with_context(|| format!("updating modified time of {}", path.display()))
+    }
+
+    /// Store data, returning its digest.
+    pub fn put(&self, data: &[u8]) -> Result<String> {
+        let digest = hex::encode(Sha256::digest(data));
+        let path = self.object_path(&digest);
+
+        if path.exists() {
+            Self::touch(&path)?;
+        } else {
+            self.write_atomic(&path, data)?;
+        }
+
+        Ok(digest)
+    }
+
+    /// Obtain the path of a stored object, marking it as used.
+    ///
+    /// Returns `None` if the object isn't stored.
+    pub fn get_path(&self, digest: &str) -> Result<Option<PathBuf>> {
+        let path = self.object_path(digest);
+
+        if path.exists() {
+            Self::touch(&path)?;
+            Ok(Some(path))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Obtain the content of a stored object, marking it as used.
+    pub fn get(&self, digest: &str) -> Result<Option<Vec<u8>>> {
+        match self.get_path(digest)? {
+            Some(path) => Ok(Some(
+                fs::read(&path).with_context(|| format!("reading {}", path.display()))?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Store data under a key, returning its digest.
+    pub fn put_keyed(&self, key: &str, data: &[u8]) -> Result<String> {
+        let digest = self.put(data)?;
+        self.write_atomic(&self.key_path(key), digest.as_bytes())?;
+
+        Ok(digest)
+    }
+
+    /// Obtain the content stored under a key.
+    ///
+    /// Returns `None` if nothing is stored under the key or its object was
+    /// collected.
+    pub fn get_keyed(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let key_path = self.key_path(key);
+
+        let digest = match fs::read_to_string(&key_path) {
+            Ok(digest) => digest,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(
+                    anyhow::Error::from(e).context(format!("reading {}", key_path.display()))
+                )
+            }
+        };
+
+        self.get(digest.trim())
+    }
+
+    /// Find stored objects with their size and last use.
+    fn objects(&self) -> Result<Vec<(PathBuf, u64, FileTime)>> {
+        let objects_dir = self.objects_dir();
+        if !objects_dir.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut res = vec![];
+        for entry in walkdir::WalkDir::new(&objects_dir) {
+            let entry = entry?;
+
+            if entry.file_type().is_file() {
+                let metadata = entry.metadata()?;
+                res.push((
+                    entry.path().to_path_buf(),
+                    metadata.len(),
+                    FileTime::from_last_modification_time(&metadata),
+                ));
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Compute the disk usage of the store.
+    pub fn usage(&self) -> Result<StoreUsage> {
+        let objects = self.objects()?;
+
+        Ok(StoreUsage {
+            objects: objects.len(),
+            size: objects.iter().map(|(_, size, _)| size).sum(),
+        })
+    }
+
+    /// Remove the least recently used objects until the store fits in `max_size` bytes.
+    ///
+    /// Keys referring to removed objects are removed as well.
+    pub fn gc(&self, logger: &slog::Logger, max_size: u64) -> Result<GcStats> {
+        let _lock = FileLock::acquire(logger, &self.root.join("gc.lock"))?;
+
+        let mut objects = self.objects()?;
+        objects.sort_by(|a, b| a.2.cmp(&b.2).then_with(|| a.0.cmp(&b.0)));
+
+        let mut stats = GcStats {
+            remaining: StoreUsage {
+                objects: objects.len(),
+                size: objects.iter().map(|(_, size, _)| size).sum(),
+            },
+            ..GcStats::default()
+        };
+
+        for (path, size, _) in objects {
+            if stats.remaining.size <= max_size {
+                break;
+            }
+
+            fs::remove_file(&path).with_context(|| format!("removing {}", path.display()))?;
+
+            stats.removed_objects += 1;
+            stats.removed_size += size;
+            stats.remaining.objects -= 1;
+            stats.remaining.size -= size;
+        }
+
+        let keys_dir = self.keys_dir();
+        if keys_dir.exists() {
+            for entry in walkdir::WalkDir::new(&keys_dir) {
+                let entry = entry?;
+
+                if entry.file_type().is_file() {
+                    let digest = fs::read_to_string(entry.path())?;
+
+                    if !self.object_path(digest.trim()).exists() {
+                        fs::remove_file(entry.path())
+                            .with_context(|| format!("removing {}", entry.path().display()))?;
+                    }
+                }
+            }
+        }
+
+        Ok(stats)
+    }
+}
+
+/// A `PythonBytecodeCompiler` reusing bytecode from an `ArtifactStore`.
+///
+/// Bytecode is keyed by the magic number of the compiler, the source, the
+/// filename embedded in code objects, the optimization level and the
+/// output mode. Errors reading or writing the store are ignored.
+pub struct CachedBytecodeCompiler {
+    inner: Box<dyn PythonBytecodeCompiler>,
+    store: ArtifactStore,
+}
+
+impl CachedBytecodeCompiler {
+    pub fn new(inner: Box<dyn PythonBytecodeCompiler>, store: ArtifactStore) -> Self {
+        Self { inner, store }
+    }
+
+    /// Wrap a compiler if the default artifact store is enabled.
+    pub fn wrap_default(
+        inner: Box<dyn PythonBytecodeCompiler>,
+    ) -> Result<Box<dyn PythonBytecodeCompiler>> {
+        Ok(match ArtifactStore::open_default()? {
+            Some(store) => Box::new(Self::new(inner, store)),
+            None => inner,
+        })
+    }
+}
+
+impl PythonBytecodeCompiler for CachedBytecodeCompiler {
+    fn get_magic_number(&self) -> u32 {
+        self.inner.get_magic_number()
+    }
+
+    fn compile(
+        &mut self,
+        source: &[u8],
+        filename: &str,
+        optimize: BytecodeOptimizationLevel,
+        strip_docstrings: bool,
+        output_mode: CompileMode,
+    ) -> Result<Vec<u8>> {
+        let mode: &[u8] = match output_mode {
+            CompileMode::Bytecode => b"bytecode",
+            CompileMode::PycCheckedHash => b"pyc-checked-hash",
+            CompileMode::PycUncheckedHash => b"pyc-unchecked-hash",
+        };
+
+        let key = artifact_key(&[
+            b"bytecode",
+            &self.inner.get_magic_number().to_le_bytes(),
+            &i32::from(optimize).to_le_bytes(),
+            &[strip_docstrings as u8],
+            mode,
+            filename.as_bytes(),
+            source,
+        ]);
+
+        // The store is only a cache: an unreadable or unwritable store
+        // falls back to compiling.
+        if let Ok(Some(bytecode)) = self.store.get_keyed(&key) {
+            return Ok(bytecode);
+        }
+
+        let bytecode =
+            self.inner
+                .compile(source, filename, optimize, strip_docstrings, output_mode)?;
+        let _ = self.store.put_keyed(&key, &bytecode);
+
+        Ok(bytecode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct CountingCompiler {
+        calls: std::rc::Rc<std::cell::Cell<usize>>,
+    }
+
+    impl PythonBytecodeCompiler for CountingCompiler {
+        fn get_magic_number(&self) -> u32 {
+            42
+        }
+
+        fn compile(
+            &mut self,
+            source: &[u8],
+            _filename: &str,
+            _optimize: BytecodeOptimizationLevel,
+            _strip_docstrings: bool,
+            _output_mode: CompileMode,
+        ) -> Result<Vec<u8>> {
+            self.calls.set(self.calls.get() + 1);
+            Ok(source.iter().rev().cloned().collect())
+        }
+    }
+
+    #[test]
+    fn test_parse_size() -> Result<()> {
+        assert_eq!(parse_size("1000")?, 1000);
+        assert_eq!(parse_size("64K")?, 64 * 1024);
+        assert_eq!(parse_size("512MB")?, 512 * 1024 * 1024);
+        assert_eq!(parse_size("10G")?, 10 * 1024 * 1024 * 1024);
+        assert_eq!(parse_size("1 GiB")?, 1024 * 1024 * 1024);
+        assert!(parse_size("ten").is_err());
+        assert!(parse_size("").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_store() -> Result<()> {
+        let logger = crate::testutil::get_logger()?;
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let store = ArtifactStore::new(temp_dir.path());
+
+        let digest = store.put(b"foo")?;
+        assert_eq!(store.put(b"foo")?, digest);
+        assert_eq!(store.get(&digest)?, Some(b"foo".to_vec()));
+        assert_eq!(store.get(&"0".repeat(64))?, None);
+
+        let key = artifact_key(&[b"a", b"bc"]);
+        assert_ne!(key, artifact_key(&[b"ab", b"c"]));
+        assert_eq!(store.get_keyed(&key)?, None);
+        store.put_keyed(&key, b"bar")?;
+        assert_eq!(store.get_keyed(&key)?, Some(b"bar".to_vec()));
+
+        assert_eq!(
+            store.usage()?,
+            StoreUsage {
+                objects: 2,
+                size: 6
+            }
+        );
+
+        // The oldest object is collected first.
+        filetime::set_file_mtime(
+            store.object_path(&digest),
+            FileTime::from_unix_time(1_000_000, 0),
+        )?;
+        let stats = store.gc(&logger, 4)?;
+        assert_eq!(stats.removed_objects, 1);
+        assert_eq!(stats.removed_size, 3);
+        assert_eq!(stats.remaining.size, 3);
+        assert_eq!(store.get(&digest)?, None);
+        assert_eq!(store.get_keyed(&key)?, Some(b"bar".to_vec()));
+
+        store.gc(&logger, 0)?;
+        assert_eq!(store.usage()?, StoreUsage::default());
+        assert_eq!(store.get_keyed(&key)?, None);
+        assert!(!store.key_path(&key).exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cached_bytecode_compiler() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let calls = std::rc::Rc::new(std::cell::Cell::new(0));
+
+        let mut compiler = CachedBytecodeCompiler::new(
+            Box::new(CountingCompiler {
+                calls: calls.clone(),
+            }),
+            ArtifactStore::new(temp_dir.path()),
+        );
+
+        let compile = |compiler: &mut CachedBytecodeCompiler, filename: &str| {
+            compiler.compile(
+                b"abc",
+                filename,
+                BytecodeOptimizationLevel::Zero,
+                false,
+                CompileMode::Bytecode,
+            )
+        };
+
+        assert_eq!(compile(&mut compiler, "foo.py")?, b"cba".to_vec());
+        assert_eq!(compile(&mut compiler, "foo.py")?, b"cba".to_vec());
+        assert_eq!(calls.get(), 1);
+
+        compile(&mut compiler, "bar.py")?;
+        assert_eq!(calls.get(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cached_bytecode_compiler_store_error() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let root = temp_dir.path().join("store");
+        std::fs::write(&root, b"not a directory")?;
+        let calls = std::rc::Rc::new(std::cell::Cell::new(0));
+
+        let mut compiler = CachedBytecodeCompiler::new(
+            Box::new(CountingCompiler {
+                calls: calls.clone(),
+            }),
+            ArtifactStore::new(&root),
+        );
+
+        for _ in 0..2 {
+            let bytecode = compiler.compile(
+                b"abc",
+                "foo.py",
+                BytecodeOptimizationLevel::Zero,
+                false,
+                CompileMode::Bytecode,
+            )?;
+            assert_eq!(bytecode, b"cba".to_vec());
+        }
+        assert_eq!(calls.get(), 2);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,173 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Layout of the build directory.
+//!
+//! Outputs are namespaced by target triple and build profile, so builds for
+//! different triples, or debug and release builds, don't overwrite each
+//! other:
+//!
+//! ```text
+//! build/
+//!   python_distributions/            Python distributions, shared
+//!   pyoxidizer-build.log             link to the log of the last build
+//!   <triple>/<profile>/
+//!     pyoxidizer-build.log           log of the last build
+//!     <target>/                      output directory of a target
+//! ```
+//!
+//! Versions before 0.10 wrote the build log to the build directory. A link
+//! at this path keeps scripts relying on it working.
+
+use {
+    crate::logging::BUILD_LOG_FILENAME,
+    anyhow::{anyhow, Context, Result},
+    std::path::{Path, PathBuf},
+};
+
+/// Name of the build profile of a debug or release build.
+pub fn profile_name(release: bool) -> &'static str {
+    if release {
+        "release"
+    } else {
+        "debug"
+    }
+}
+
+/// Paths of outputs in a build directory, for a target triple and profile.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BuildLayout {
+    build_path: PathBuf,
+    target_triple: String,
+    release: bool,
+}
+
+impl BuildLayout {
+    pub fn new(build_path: &Path, target_triple: &str, release: bool) -> Self {
+        Self {
+            build_path: build_path.to_path_buf(),
+            target_triple: target_triple.to_string(),
+            release,
+        }
+    }
+
+    /// Name of the build profile, `debug` or `release`.
+    pub fn profile(&self) -> &'static str {
+        profile_name(self.release)
+    }
+
+    /// Directory holding all outputs of the target triple and profile.
+    pub fn profile_path(&self) -> PathBuf {
+        self.build_path
+            .join(&self.target_triple)
+            .join(self.profile())
+    }
+
+    /// Output directory of a target.
+    pub fn target_output_path(&self, target: &str) -> PathBuf {
+        self.profile_path().join(target)
+    }
+
+    /// Path of the build log.
+    pub fn build_log_path(&self) -> PathBuf {
+        self.profile_path().join(BUILD_LOG_FILENAME)
+    }
+
+    /// Link the path versions before 0.10 used for the build log.
+    pub fn link_legacy_build_log_path(&self) -> Result<()> {
+        link_file(
+            &self.build_path.join(BUILD_LOG_FILENAME),
+            &Path::new(&self.target_triple)
+                .join(self.profile())
+                .join(BUILD_LOG_FILENAME),
+        )
+    }
+}
+
+/// Make `link` a symlink to the file `dest`, relative to the directory of `link`.
+///
+/// The symlink is created next to `link` and then renamed over it, so a file
+/// existing at `link` is only replaced once the symlink exists. A directory
+/// at `link` is left alone. Creating symlinks requires special permissions
+/// on Windows, so callers should treat failures as non-fatal.
+fn link_file(link: &Path, dest: &Path) -> Result<()> {
+    if std::fs::symlink_metadata(link).map_or(false, |metadata| metadata.is_dir()) {
+        return Err(anyhow!("not linking {}: it is a directory", link.display()));
+    }
+
+    let mut temp_name = link
+        .file_name()
+        .ok_or_else(|| anyhow!("{} has no file name", link.display()))?
+        .to_os_string();
+    temp_name.push(".link-tmp");
+    let temp_link = link.with_file_name(temp_name);
+
+    if std::fs::symlink_metadata(&temp_link).is_ok() {
+        std::fs::remove_file(&temp_link)
+            .with_context(|| format!("removing {}", temp_link.display()))?;
+    }
+
+    #[cfg(unix)]
+    let res = std::os::unix::fs::symlink(dest, &temp_link);
+
+    #[cfg(windows)]
+    let res = std::os::windows::fs::symlink_file(dest, &temp_link);
+
+    res.with_context(|| format!("linking {} to {}", temp_link.display(), dest.display()))?;
+
+    std::fs::rename(&temp_link, link).map_err(|e| {
+        let _ = std::fs::remove_file(&temp_link);
+        anyhow!("replacing {}: {}", link.display(), e)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paths() {
+        let layout = BuildLayout::new(Path::new("build"), "x86_64-unknown-linux-gnu", true);
+
+        assert_eq!(
+            layout.target_output_path("exe"),
+            PathBuf::from("build/x86_64-unknown-linux-gnu/release/exe")
+        );
+        assert_eq!(
+            layout.build_log_path(),
+            PathBuf::from("build/x86_64-unknown-linux-gnu/release").join(BUILD_LOG_FILENAME)
+        );
+        assert_ne!(
+            layout.target_output_path("exe"),
+            BuildLayout::new(Path::new("build"), "x86_64-unknown-linux-gnu", false)
+                .target_output_path("exe")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_legacy_links() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let layout = BuildLayout::new(temp_dir.path(), "x86_64-unknown-linux-gnu", false);
+        let legacy = temp_dir.path().join(BUILD_LOG_FILENAME);
+
+        // A log written by a previous version is replaced.
+        std::fs::write(&legacy, b"stale")?;
+        std::fs::create_dir_all(layout.profile_path())?;
+        std::fs::write(layout.build_log_path(), b"log")?;
+        layout.link_legacy_build_log_path()?;
+        layout.link_legacy_build_log_path()?;
+        assert_eq!(std::fs::read(&legacy)?, b"log");
+        assert!(std::fs::symlink_metadata(&legacy)?.file_type().is_symlink());
+
+        // A directory in the way is left alone.
+        std::fs::remove_file(&legacy)?;
+        std::fs::create_dir(&legacy)?;
+        std::fs::write(legacy.join("keep"), b"")?;
+        assert!(layout.link_legacy_build_log_path().is_err());
+        assert!(legacy.join("keep").exists());
+
+        Ok(())
+    }
+}
@@ -4,8 +4,9 @@
 
 use {
     crate::{
-        analyze, environment::PYOXIDIZER_VERSION, logging, project_building, project_layout,
-        projectmgmt,
+        analyze,
+        environment::{self, PYOXIDIZER_VERSION},
+        logging, project_building, project_layout, projectmgmt, resource_patching,
     },
     anyhow::{anyhow, Result},
     clap::{App, AppSettings, Arg, SubCommand},
@@ -59,6 +60,132 @@ they were created with.
 On success, instructions on potential next steps are printed.
 ";
 
+const MIGRATE_PYINSTALLER_ABOUT: &str = "\
+Create a PyOxidizer configuration file from a PyInstaller spec file.
+
+The scripts, datas, hiddenimports and excludes arguments to `Analysis()`
+and the name and console arguments to `EXE()` are translated into a
+`pyoxidizer.bzl` file written next to SPEC. PyOxidizer can't set the icon
+of executables, so the icon argument is only noted in a comment. The spec file is parsed, not
+executed, so settings computed by Python code can't be migrated. These and
+other unsupported settings are listed in comments at the top of the
+generated file.
+
+The command fails if the `pyoxidizer.bzl` file already exists.
+";
+
+const MIGRATE_CX_FREEZE_ABOUT: &str = "\
+Create a PyOxidizer configuration file from a cx_Freeze setup.py file.
+
+Each `Executable()` passed to `setup()` becomes a build target of a
+`pyoxidizer.bzl` file written next to SETUP_PY. The packages, includes,
+excludes, include_files and path options of the `build_exe` command and
+`install_requires` are translated as well. The file is parsed, not
+executed, so settings computed by Python code can't be migrated. These and
+other unsupported settings are listed in comments at the top of the
+generated file.
+
+The command fails if the `pyoxidizer.bzl` file already exists.
+";
+
+const MIGRATE_BRIEFCASE_ABOUT: &str = "\
+Create a PyOxidizer configuration file from a Briefcase pyproject.toml file.
+
+Each `[tool.briefcase.app.<name>]` section becomes a build target of a
+`pyoxidizer.bzl` file written next to PYPROJECT. The app's sources are
+added as packages and its package is run as `__main__`, like Briefcase
+does. Requirements of the macOS, linux and windows platforms are installed
+when building for these platforms. Settings of other platforms and output
+formats are listed in comments at the top of the generated file.
+
+The command fails if the `pyoxidizer.bzl` file already exists.
+";
+
+const MIGRATE_CONFIG_ABOUT: &str = "\
+Rewrite a configuration file to stop using deprecated functionality.
+
+Uses of deprecated functions, methods and attributes having a replacement
+are rewritten to use the replacement. Uses of deprecated functionality
+without a replacement are listed and need to be changed by hand.
+
+Strings and comments are left untouched.
+";
+
+const DUMP_STARLARK_API_ABOUT: &str = "\
+Print a description of the configuration file API.
+
+The description lists the global functions and variables and the types
+available to configuration files, with the parameters of functions and
+methods and the types of attributes. It is intended for editor plugins
+and external tools validating configuration files.
+
+The description matches the version of this pyoxidizer executable.
+";
+
+const CACHE_ABOUT: &str = "\
+Show the Python distributions extracted into the user cache.
+
+Builds extract each Python distribution once into the per-user cache
+directory and share the extracted files. This command lists the
+extracted distributions with their Python version, target triple,
+libpython link mode and size on disk, followed by the size of the
+artifact store holding intermediate build artifacts, such as compiled
+bytecode.
+
+Entries are never removed automatically. Delete a distribution's
+directory to reclaim its space; it is extracted again when needed.
+`pyoxidizer cache gc` trims the artifact store.
+";
+
+const CACHE_GC_ABOUT: &str = "\
+Remove least recently used artifacts from the artifact store.
+
+Artifacts are removed, least recently used first, until the store fits
+in the size given by --max-size. Sizes accept K, M, G and T suffixes,
+e.g. `--max-size 10G`. Removed artifacts are rebuilt when needed.
+";
+
+const GENERATE_DIST_MANIFEST_ABOUT: &str = "\
+Build targets and describe their artifacts as JSON.
+
+This command builds the resolved targets of a configuration file once for
+each --target-triple and emits a manifest of every file they write,
+grouped by target triple. Each artifact records its path relative to the
+output directory of its target, the name of the target, its kind
+(executable, library, header, installer, package-manager-manifest or
+file), its size and its SHA-256.
+
+The manifest is intended for release tooling and update servers. Since
+targets can generally only be built on the operating system they run on,
+release matrices spanning operating systems should generate a manifest on
+each and merge their `platforms` entries.
+
+TARGET values are the targets to resolve. If not given, the default
+targets are resolved.
+";
+
+const GENERATE_RUST_PROJECT_ABOUT: &str = "\
+Write the Rust project used to build an executable.
+
+`pyoxidizer build` builds executables from a Rust project in a temporary
+directory. This command writes that project, including the artifacts
+derived from the configuration file, to the directory given by --out so
+it can be inspected or customized.
+
+TARGET is the name of a target resolving to a PythonExecutable. The
+default target is used if not given.
+
+If the --out directory does not exist, a complete Rust project is created.
+If it already exists, only its `pyoxidizer-artifacts` directory is
+rewritten. Run this command again after changing the configuration file
+to re-sync the project. Settings that affect Rust sources or Cargo.toml,
+such as `windows_subsystem` or `cargo_features()`, require generating into
+a new directory.
+
+The project must be built with the environment variables printed on
+success, which tell Cargo where to find the artifacts.
+";
+
 const RUN_BUILD_SCRIPT_ABOUT: &str = "\
 Runs a crate build script to generate Python artifacts.
 
@@ -89,6 +216,65 @@ conversion is critical for properly packaging Python applications and
 bugs can result in incorrect install layouts, missing resources, etc.
 ";
 
+const TEST_CONFIG_ABOUT: &str = "\
+Run tests defined in a PyOxidizer configuration file.
+
+The PATH argument is a filesystem path to a directory containing a
+PyOxidizer configuration file.
+
+Functions whose name begins with `test_` defined in the configuration file
+or in a sibling `pyoxidizer_test.bzl` file are executed. Each test runs in
+a fresh Starlark environment having assertion functions like `assert_eq()`
+available. Targets are not resolved and builds write to a temporary
+directory.
+
+The exit code is non-zero if any test fails.
+";
+
+const VERIFY_RESOURCES_ABOUT: &str = "\
+Strictly validate a packed resources data file.
+
+The FILE argument is a filesystem path to a file containing packed resources
+data, such as the `packed-resources` file written when building embedded
+resources.
+
+Every resource in the file is parsed and the structure of the file is
+validated. Structural errors are reported along with the offset at which they
+were detected. The exit code is non-zero if the file is invalid.
+";
+
+const COMPARE_RESOURCES_ABOUT: &str = "\
+Compare the resource indices of two packed resources data files.
+
+The OLD and NEW arguments are filesystem paths to files containing packed
+resources data, e.g. from two builds of the same application.
+
+The index of a resource is its position in the data. Resources are written
+sorted by name, so indices only depend on the set of resources. This command
+verifies both files are in that order and reports resources that were added,
+removed or whose index changed. The exit code is non-zero if a file isn't
+sorted by name.
+";
+
+const PATCH_RESOURCES_ABOUT: &str = "\
+Replace resource data in a built executable.
+
+This allows shipping fixes to data files without rebuilding an executable.
+The BINARY argument is the path of an executable built with
+`packed_resources_embedding = \"overlay\"`. Its packed resources data is
+rewritten in place.
+
+Each --replace argument has the form RESOURCE=FILE. RESOURCE is the path of
+an existing package resource, such as `mypkg/config.json`, or of a file
+installed in memory. FILE is the path of a file holding its new data. Only
+resource data can be replaced: adding or removing resources or changing
+Python modules requires a rebuild.
+
+Patching invalidates code signatures. Authenticode signatures of Windows
+executables are removed and the PE checksum is recomputed. Executables must
+be signed again before they are distributed.
+";
+
 pub fn run_cli() -> Result<()> {
     let env = crate::environment::resolve_environment()?;
 
@@ -103,6 +289,13 @@ pub fn run_cli() -> Result<()> {
                 .long("verbose")
                 .help("Enable verbose output"),
         )
+        .arg(
+            Arg::with_name("explain")
+                .long("explain")
+                .takes_value(true)
+                .value_name("CODE")
+                .help("Explain an error code printed when evaluating a config file"),
+        )
         .subcommand(
             SubCommand::with_name("add")
                 .setting(AppSettings::ArgRequiredElseHelp)
@@ -176,6 +369,83 @@ pub fn run_cli() -> Result<()> {
                     "Filesystem path to scan for resources. Must be a directory or Python wheel",
                 )),
         )
+        .subcommand(
+            SubCommand::with_name("generate-dist-manifest")
+                .about("Build targets and describe their artifacts as JSON")
+                .long_about(GENERATE_DIST_MANIFEST_ABOUT)
+                .arg(
+                    Arg::with_name("target_triple")
+                        .long("target-triple")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("Rust target triple to build for"),
+                )
+                .arg(
+                    Arg::with_name("release")
+                        .long("release")
+                        .help("Build a release build"),
+                )
+                .arg(
+                    Arg::with_name("path")
+                        .long("path")
+                        .takes_value(true)
+                        .default_value(".")
+                        .value_name("PATH")
+                        .help("Directory containing project to build"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .takes_value(true)
+                        .value_name("PATH")
+                        .help("File to write the manifest to instead of stdout"),
+                )
+                .arg(
+                    Arg::with_name("targets")
+                        .value_name("TARGET")
+                        .multiple(true)
+                        .help("Target to resolve"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("generate-rust-project")
+                .setting(AppSettings::ArgRequiredElseHelp)
+                .about("Write the Rust project used to build an executable")
+                .long_about(GENERATE_RUST_PROJECT_ABOUT)
+                .arg(
+                    Arg::with_name("target_triple")
+                        .long("target-triple")
+                        .takes_value(true)
+                        .help("Rust target triple to build for"),
+                )
+                .arg(
+                    Arg::with_name("release")
+                        .long("release")
+                        .help("Generate artifacts for a release build"),
+                )
+                .arg(
+                    Arg::with_name("path")
+                        .long("path")
+                        .takes_value(true)
+                        .default_value(".")
+                        .value_name("PATH")
+                        .help("Directory containing project to evaluate"),
+                )
+                .arg(
+                    Arg::with_name("out")
+                        .long("out")
+                        .takes_value(true)
+                        .required(true)
+                        .value_name("DIR")
+                        .help("Directory to write the Rust project to"),
+                )
+                .arg(
+                    Arg::with_name("target")
+                        .value_name("TARGET")
+                        .help("Target to generate a project for"),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("init-config-file")
                 .setting(AppSettings::ArgRequiredElseHelp)
@@ -213,6 +483,91 @@ pub fn run_cli() -> Result<()> {
                         .help("Path of project directory to create"),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("migrate-pyinstaller")
+                .setting(AppSettings::ArgRequiredElseHelp)
+                .about("Create a PyOxidizer configuration file from a PyInstaller spec file")
+                .long_about(MIGRATE_PYINSTALLER_ABOUT)
+                .arg(
+                    Arg::with_name("spec")
+                        .required(true)
+                        .value_name("SPEC")
+                        .help("Path to PyInstaller spec file"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("migrate-cx-freeze")
+                .setting(AppSettings::ArgRequiredElseHelp)
+                .about("Create a PyOxidizer configuration file from a cx_Freeze setup.py file")
+                .long_about(MIGRATE_CX_FREEZE_ABOUT)
+                .arg(
+                    Arg::with_name("setup_py")
+                        .required(true)
+                        .value_name("SETUP_PY")
+                        .help("Path to cx_Freeze setup.py file"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("migrate-briefcase")
+                .setting(AppSettings::ArgRequiredElseHelp)
+                .about(
+                    "Create a PyOxidizer configuration file from a Briefcase pyproject.toml file",
+                )
+                .long_about(MIGRATE_BRIEFCASE_ABOUT)
+                .arg(
+                    Arg::with_name("pyproject")
+                        .required(true)
+                        .value_name("PYPROJECT")
+                        .help("Path to Briefcase pyproject.toml file"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("migrate-config")
+                .about("Rewrite a configuration file to stop using deprecated functionality")
+                .long_about(MIGRATE_CONFIG_ABOUT)
+                .arg(
+                    Arg::with_name("dry_run")
+                        .long("dry-run")
+                        .help("Print what would be changed without writing the file"),
+                )
+                .arg(
+                    Arg::with_name("path")
+                        .default_value(".")
+                        .value_name("PATH")
+                        .help("Path to project to migrate"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("dump-starlark-api")
+                .about("Print a description of the configuration file API")
+                .long_about(DUMP_STARLARK_API_ABOUT)
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["json"])
+                        .default_value("json")
+                        .help("Output format"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("cache")
+                .about("Show extracted Python distributions in the user cache")
+                .long_about(CACHE_ABOUT)
+                .subcommand(
+                    SubCommand::with_name("gc")
+                        .about("Remove least recently used artifacts from the user cache")
+                        .long_about(CACHE_GC_ABOUT)
+                        .arg(
+                            Arg::with_name("max_size")
+                                .long("max-size")
+                                .takes_value(true)
+                                .required(true)
+                                .value_name("SIZE")
+                                .help("Size the artifact store is trimmed to"),
+                        ),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("list-targets")
                 .setting(AppSettings::ArgRequiredElseHelp)
@@ -224,6 +579,23 @@ pub fn run_cli() -> Result<()> {
                         .help("Path to project to evaluate"),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("test-config")
+                .about("Run tests defined in a configuration file")
+                .long_about(TEST_CONFIG_ABOUT)
+                .arg(
+                    Arg::with_name("target_triple")
+                        .long("target-triple")
+                        .takes_value(true)
+                        .help("Rust target triple to evaluate configuration for"),
+                )
+                .arg(
+                    Arg::with_name("path")
+                        .default_value(".")
+                        .value_name("PATH")
+                        .help("Path to project to evaluate"),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("build")
                 .setting(AppSettings::ArgRequiredElseHelp)
@@ -240,6 +612,30 @@ pub fn run_cli() -> Result<()> {
                         .long("release")
                         .help("Build a release binary"),
                 )
+                .arg(
+                    Arg::with_name("dry_run")
+                        .long("dry-run")
+                        .help("Print what would be built without building anything"),
+                )
+                .arg(
+                    Arg::with_name("trace_resources")
+                        .long("trace-resources")
+                        .takes_value(true)
+                        .value_name("PATTERN")
+                        .help("Log packaging decisions for resources matching a glob pattern"),
+                )
+                .arg(
+                    Arg::with_name("update_snapshots")
+                        .long("update-snapshots")
+                        .help("Write resources snapshots instead of failing when they differ"),
+                )
+                .arg(
+                    Arg::with_name("timings_json")
+                        .long("timings-json")
+                        .takes_value(true)
+                        .value_name("PATH")
+                        .help("Write time spent in each build phase to a JSON file"),
+                )
                 .arg(
                     Arg::with_name("path")
                         .long("path")
@@ -248,6 +644,13 @@ pub fn run_cli() -> Result<()> {
                         .value_name("PATH")
                         .help("Directory containing project to build"),
                 )
+                .arg(
+                    Arg::with_name("app")
+                        .long("app")
+                        .takes_value(true)
+                        .value_name("NAME")
+                        .help("Application of a workspace to build"),
+                )
                 .arg(
                     Arg::with_name("targets")
                         .value_name("TARGET")
@@ -321,8 +724,61 @@ pub fn run_cli() -> Result<()> {
                         .help("Path to Python distribution to analyze"),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("verify-resources")
+                .about("Validate a packed resources data file")
+                .long_about(VERIFY_RESOURCES_ABOUT)
+                .arg(
+                    Arg::with_name("path")
+                        .required(true)
+                        .value_name("FILE")
+                        .help("Path to packed resources file to verify"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("compare-resources")
+                .about("Compare the resource indices of two packed resources data files")
+                .long_about(COMPARE_RESOURCES_ABOUT)
+                .arg(
+                    Arg::with_name("old")
+                        .required(true)
+                        .value_name("OLD")
+                        .help("Path to packed resources file of the old build"),
+                )
+                .arg(
+                    Arg::with_name("new")
+                        .required(true)
+                        .value_name("NEW")
+                        .help("Path to packed resources file of the new build"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("patch-resources")
+                .about("Replace resource data in a built executable")
+                .long_about(PATCH_RESOURCES_ABOUT)
+                .arg(
+                    Arg::with_name("replace")
+                        .long("replace")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .required(true)
+                        .value_name("RESOURCE=FILE")
+                        .help("Resource to replace and file holding its new data"),
+                )
+                .arg(
+                    Arg::with_name("path")
+                        .required(true)
+                        .value_name("BINARY")
+                        .help("Path to executable to patch"),
+                ),
+        )
         .get_matches();
 
+    if let Some(code) = matches.value_of("explain") {
+        return projectmgmt::explain_error_code(code);
+    }
+
     let verbose = matches.is_present("verbose");
 
     let log_level = if verbose {
@@ -333,6 +789,8 @@ pub fn run_cli() -> Result<()> {
 
     let logger_context = logging::logger_from_env(log_level);
 
+    environment::set_temp_dir_from_env()?;
+
     match matches.subcommand() {
         ("add", Some(args)) => {
             let path = args.value_of("path").unwrap();
@@ -350,8 +808,13 @@ pub fn run_cli() -> Result<()> {
 
         ("build", Some(args)) => {
             let release = args.is_present("release");
+            let dry_run = args.is_present("dry_run");
+            let trace_resources = args.value_of("trace_resources");
+            let update_snapshots = args.is_present("update_snapshots");
+            let timings_json = args.value_of("timings_json").map(Path::new);
             let target_triple = args.value_of("target_triple");
             let path = args.value_of("path").unwrap();
+            let app = args.value_of("app");
             let resolve_targets = if let Some(values) = args.values_of("targets") {
                 Some(values.map(|x| x.to_string()).collect())
             } else {
@@ -361,10 +824,15 @@ pub fn run_cli() -> Result<()> {
             projectmgmt::build(
                 &logger_context.logger,
                 Path::new(path),
+                app,
                 target_triple,
                 resolve_targets,
                 release,
                 verbose,
+                dry_run,
+                trace_resources,
+                update_snapshots,
+                timings_json,
             )
         }
 
@@ -399,6 +867,49 @@ pub fn run_cli() -> Result<()> {
             }
         }
 
+        ("generate-dist-manifest", Some(args)) => {
+            let target_triples = args
+                .values_of("target_triple")
+                .map(|values| values.collect::<Vec<_>>())
+                .unwrap_or_default();
+            let release = args.is_present("release");
+            let path = args.value_of("path").unwrap();
+            let output = args.value_of("output").map(Path::new);
+            let resolve_targets = if let Some(values) = args.values_of("targets") {
+                Some(values.map(|x| x.to_string()).collect())
+            } else {
+                None
+            };
+
+            projectmgmt::generate_dist_manifest(
+                &logger_context.logger,
+                Path::new(path),
+                &target_triples,
+                resolve_targets,
+                release,
+                verbose,
+                output,
+            )
+        }
+
+        ("generate-rust-project", Some(args)) => {
+            let target_triple = args.value_of("target_triple");
+            let release = args.is_present("release");
+            let path = args.value_of("path").unwrap();
+            let out = args.value_of("out").unwrap();
+            let target = args.value_of("target");
+
+            projectmgmt::generate_rust_project(
+                &logger_context.logger,
+                Path::new(path),
+                target_triple,
+                target,
+                release,
+                verbose,
+                Path::new(out),
+            )
+        }
+
         ("init-config-file", Some(args)) => {
             let code = args.value_of("python-code");
             let pip_install = if args.is_present("pip-install") {
@@ -418,6 +929,18 @@ pub fn run_cli() -> Result<()> {
             projectmgmt::list_targets(&logger_context.logger, Path::new(path))
         }
 
+        ("test-config", Some(args)) => {
+            let target_triple = args.value_of("target_triple");
+            let path = args.value_of("path").unwrap();
+
+            projectmgmt::test_config(
+                &logger_context.logger,
+                Path::new(path),
+                target_triple,
+                verbose,
+            )
+        }
+
         ("init-rust-project", Some(args)) => {
             let path = args.value_of("path").unwrap();
             let project_path = Path::new(path);
@@ -425,6 +948,46 @@ pub fn run_cli() -> Result<()> {
             projectmgmt::init_rust_project(&project_path)
         }
 
+        ("migrate-pyinstaller", Some(args)) => {
+            let spec = args.value_of("spec").unwrap();
+
+            projectmgmt::migrate_pyinstaller(Path::new(spec))
+        }
+
+        ("migrate-cx-freeze", Some(args)) => {
+            let setup_py = args.value_of("setup_py").unwrap();
+
+            projectmgmt::migrate_cx_freeze(Path::new(setup_py))
+        }
+
+        ("migrate-briefcase", Some(args)) => {
+            let pyproject = args.value_of("pyproject").unwrap();
+
+            projectmgmt::migrate_briefcase(Path::new(pyproject))
+        }
+
+        ("cache", Some(args)) => match args.subcommand() {
+            ("gc", Some(args)) => {
+                let max_size = args.value_of("max_size").unwrap();
+
+                projectmgmt::cache_gc(&logger_context.logger, max_size)
+            }
+            _ => projectmgmt::show_cache(),
+        },
+
+        ("dump-starlark-api", Some(args)) => {
+            let format = args.value_of("format").unwrap();
+
+            projectmgmt::dump_starlark_api(&logger_context.logger, format)
+        }
+
+        ("migrate-config", Some(args)) => {
+            let dry_run = args.is_present("dry_run");
+            let path = args.value_of("path").unwrap();
+
+            projectmgmt::migrate_config(&logger_context.logger, Path::new(path), dry_run)
+        }
+
         ("python-distribution-extract", Some(args)) => {
             let dist_path = args.value_of("dist_path").unwrap();
             let dest_path = args.value_of("dest_path").unwrap();
@@ -435,13 +998,13 @@ pub fn run_cli() -> Result<()> {
         ("python-distribution-info", Some(args)) => {
             let dist_path = args.value_of("path").unwrap();
 
-            projectmgmt::python_distribution_info(dist_path)
+            projectmgmt::python_distribution_info(&logger_context.logger, dist_path)
         }
 
         ("python-distribution-licenses", Some(args)) => {
             let path = args.value_of("path").unwrap();
 
-            projectmgmt::python_distribution_licenses(path)
+            projectmgmt::python_distribution_licenses(&logger_context.logger, path)
         }
 
         ("run-build-script", Some(args)) => {
@@ -469,6 +1032,34 @@ pub fn run_cli() -> Result<()> {
             )
         }
 
+        ("verify-resources", Some(args)) => {
+            let path = args.value_of("path").unwrap();
+
+            projectmgmt::verify_resources(Path::new(path))
+        }
+
+        ("compare-resources", Some(args)) => {
+            let old = args.value_of("old").unwrap();
+            let new = args.value_of("new").unwrap();
+
+            projectmgmt::compare_resources(Path::new(old), Path::new(new))
+        }
+
+        ("patch-resources", Some(args)) => {
+            let path = args.value_of("path").unwrap();
+            let replacements = args
+                .values_of("replace")
+                .unwrap()
+                .map(resource_patching::parse_replacement)
+                .collect::<Result<Vec<_>>>()?;
+
+            resource_patching::patch_executable_resources(
+                &logger_context.logger,
+                Path::new(path),
+                &replacements,
+            )
+        }
+
         _ => Err(anyhow!("invalid sub-command")),
     }
 }
@@ -0,0 +1,259 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Machine-readable descriptions of the artifacts of a release.
+
+`pyoxidizer generate-dist-manifest` builds the targets of a configuration
+file for each platform of a release matrix and describes the artifacts they
+write, so release tooling and update servers can publish them without
+knowing how they were built.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    serde::Serialize,
+    sha2::{Digest, Sha256},
+    std::path::{Path, PathBuf},
+};
+
+/// Version of the manifest format.
+///
+/// This is incremented when fields are removed or change meaning.
+pub const DIST_MANIFEST_FORMAT_VERSION: u32 = 1;
+
+/// What an artifact is.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArtifactKind {
+    /// An executable.
+    Executable,
+    /// A static or shared library.
+    Library,
+    /// A C header for a library.
+    Header,
+    /// An installer, such as a macOS `.pkg`.
+    Installer,
+    /// A manifest for a package manager, such as a Homebrew cask.
+    PackageManagerManifest,
+    /// Any other file.
+    File,
+}
+
+impl ArtifactKind {
+    /// Classify an artifact from the type of the value writing it.
+    pub fn classify(value_type: &str, path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        match value_type {
+            "MacOsPkgBuilder" => Self::Installer,
+            "PackageManagerManifests" => Self::PackageManagerManifest,
+            "PythonExecutable" | "PythonLibrary" if extension == "h" => Self::Header,
+            "PythonExecutable" => match extension.as_str() {
+                "so" | "dylib" | "dll" => Self::Library,
+                _ => Self::Executable,
+            },
+            "PythonLibrary" => Self::Library,
+            _ => Self::File,
+        }
+    }
+}
+
+/// Describes a single artifact.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DistArtifact {
+    /// Path of the artifact relative to the output directory of its target.
+    ///
+    /// Components are separated by `/` on all platforms.
+    pub name: String,
+
+    /// Name of the target writing the artifact.
+    pub target: String,
+
+    pub kind: ArtifactKind,
+
+    /// Path of the artifact on the machine the manifest was generated on.
+    pub path: PathBuf,
+
+    /// Size of the artifact in bytes.
+    pub size: u64,
+
+    /// Hex encoded SHA-256 of the artifact.
+    pub sha256: String,
+}
+
+/// Describes the artifacts built for a target triple.
+#[derive(Clone, Debug, Serialize)]
+pub struct DistPlatform {
+    pub target_triple: String,
+    pub artifacts: Vec<DistArtifact>,
+}
+
+/// Describes the artifacts of a release.
+#[derive(Clone, Debug, Serialize)]
+pub struct DistManifest {
+    pub format_version: u32,
+
+    /// Version of PyOxidizer generating the manifest.
+    pub pyoxidizer_version: String,
+
+    /// Whether artifacts were built in release mode.
+    pub release: bool,
+
+    pub platforms: Vec<DistPlatform>,
+}
+
+/// Describe a single artifact file.
+fn describe_file(
+    target: &str,
+    output_path: &Path,
+    path: &Path,
+    value_type: &str,
+) -> Result<DistArtifact> {
+    let data = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    let name = path
+        .strip_prefix(output_path)
+        .map_err(|_| {
+            anyhow!(
+                "artifact {} is not in {}",
+                path.display(),
+                output_path.display()
+            )
+        })?
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    Ok(DistArtifact {
+        name,
+        target: target.to_string(),
+        kind: ArtifactKind::classify(value_type, path),
+        path: path.to_path_buf(),
+        size: data.len() as u64,
+        sha256: hex::encode(Sha256::digest(&data)),
+    })
+}
+
+/// Describe the artifacts a target wrote to its output directory.
+///
+/// `artifacts` are paths of artifacts paired with the type of the value
+/// writing them. Directories are expanded into the files in them.
+pub fn describe_target_artifacts(
+    target: &str,
+    output_path: &Path,
+    artifacts: &[(PathBuf, &str)],
+) -> Result<Vec<DistArtifact>> {
+    let mut res = vec![];
+
+    for (path, value_type) in artifacts {
+        if path.is_dir() {
+            for entry in
+                walkdir::WalkDir::new(path).sort_by(|a, b| a.file_name().cmp(b.file_name()))
+            {
+                let entry = entry?;
+
+                if entry.file_type().is_file() {
+                    res.push(describe_file(
+                        target,
+                        output_path,
+                        entry.path(),
+                        value_type,
+                    )?);
+                }
+            }
+        } else if path.is_file() {
+            res.push(describe_file(target, output_path, path, value_type)?);
+        } else {
+            return Err(anyhow!(
+                "artifact {} of target {} does not exist",
+                path.display(),
+                target
+            ));
+        }
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            ArtifactKind::classify("PythonExecutable", Path::new("myapp.exe")),
+            ArtifactKind::Executable
+        );
+        assert_eq!(
+            ArtifactKind::classify("PythonExecutable", Path::new("myapp")),
+            ArtifactKind::Executable
+        );
+        assert_eq!(
+            ArtifactKind::classify("PythonExecutable", Path::new("libmyapp.so")),
+            ArtifactKind::Library
+        );
+        assert_eq!(
+            ArtifactKind::classify("PythonLibrary", Path::new("myapp.h")),
+            ArtifactKind::Header
+        );
+        assert_eq!(
+            ArtifactKind::classify("MacOsPkgBuilder", Path::new("myapp-1.0.pkg")),
+            ArtifactKind::Installer
+        );
+        assert_eq!(
+            ArtifactKind::classify("FileManifest", Path::new("lib/foo.py")),
+            ArtifactKind::File
+        );
+    }
+
+    #[test]
+    fn test_describe_target_artifacts() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let output_path = temp_dir.path().join("install");
+        std::fs::create_dir_all(output_path.join("app").join("lib"))?;
+        std::fs::write(output_path.join("myapp"), "hello")?;
+        std::fs::write(output_path.join("app").join("lib").join("a.py"), "")?;
+        std::fs::write(output_path.join("app").join("b.py"), "")?;
+
+        let artifacts = describe_target_artifacts(
+            "install",
+            &output_path,
+            &[
+                (output_path.join("myapp"), "PythonExecutable"),
+                (output_path.join("app"), "PythonWasiBundle"),
+            ],
+        )?;
+
+        assert_eq!(
+            artifacts
+                .iter()
+                .map(|a| (a.name.as_str(), a.kind, a.size))
+                .collect::<Vec<_>>(),
+            vec![
+                ("myapp", ArtifactKind::Executable, 5),
+                ("app/b.py", ArtifactKind::File, 0),
+                ("app/lib/a.py", ArtifactKind::File, 0),
+            ]
+        );
+        assert_eq!(
+            artifacts[0].sha256,
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert_eq!(artifacts[0].target, "install");
+
+        assert!(describe_target_artifacts(
+            "install",
+            &output_path,
+            &[(output_path.join("missing"), "PythonExecutable")],
+        )
+        .is_err());
+
+        Ok(())
+    }
+}
@@ -5,11 +5,12 @@
 //! Resolve details about the PyOxidizer execution environment.
 
 use {
-    crate::project_layout::PyembedLocation,
+    crate::{paths::canonicalize_path, project_layout::PyembedLocation},
     anyhow::{anyhow, Result},
-    git2::{Commit, Repository},
+    git2::{Commit, DescribeFormatOptions, DescribeOptions, Repository},
     lazy_static::lazy_static,
     std::{
+        convert::TryFrom,
         env,
         path::{Path, PathBuf},
     },
@@ -42,7 +43,7 @@ lazy_static! {
     ///
     // Remember to update the CI configuration in ci/azure-pipelines-template.yml
     // when this changes.
-    pub static ref MINIMUM_RUST_VERSION: semver::Version = semver::Version::new(1, 40, 0);
+    pub static ref MINIMUM_RUST_VERSION: semver::Version = semver::Version::new(1, 50, 0);
 
     /// Target triples for Linux.
     pub static ref LINUX_TARGET_TRIPLES: Vec<&'static str> = vec![
@@ -55,6 +56,14 @@ lazy_static! {
         "x86_64-apple-darwin",
     ];
 
+    /// Target triples for mobile platforms.
+    ///
+    /// Only libraries can be built for these targets, not executables.
+    pub static ref MOBILE_TARGET_TRIPLES: Vec<&'static str> = vec![
+        "aarch64-apple-ios",
+        "aarch64-linux-android",
+    ];
+
     /// Target triples for Windows.
     pub static ref WINDOWS_TARGET_TRIPLES: Vec<&'static str> = vec![
         "i686-pc-windows-gnu",
@@ -77,20 +86,110 @@ fn find_root_git_commit(commit: Commit) -> Commit {
     current
 }
 
-pub fn canonicalize_path(path: &Path) -> Result<PathBuf, std::io::Error> {
-    let mut p = path.canonicalize()?;
+/// Environment variable defining the directory to write temporary files to.
+pub const TEMP_DIR_ENV: &str = "PYOXIDIZER_TEMP_DIR";
+
+/// Environment variable that disables checking for free disk space.
+pub const NO_DISK_SPACE_CHECK_ENV: &str = "PYOXIDIZER_NO_DISK_SPACE_CHECK";
+
+/// Estimated free space a build needs in the temporary directory.
+///
+/// Covers installing packages with pip and linking libpython from the
+/// distribution's object files.
+pub const TEMP_DIR_REQUIRED_SPACE: u64 = 1024 * 1024 * 1024;
+
+/// Estimated free space a build needs in the build directory.
+///
+/// Covers an extracted Python distribution and a Cargo target directory.
+pub const BUILD_DIR_REQUIRED_SPACE: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Estimated free space extracting a Python distribution needs.
+pub const DISTRIBUTION_REQUIRED_SPACE: u64 = 512 * 1024 * 1024;
+
+/// Write temporary files to a directory.
+///
+/// This sets the variables `std::env::temp_dir()` is derived from for the
+/// current process. Processes we spawn, such as pip and the C compiler,
+/// inherit them. So their temporary files end up there as well.
+pub fn set_temp_dir(path: &Path) -> Result<()> {
+    std::fs::create_dir_all(path)?;
+    let path = canonicalize_path(path)?;
 
-    // Strip \\?\ prefix on Windows and replace \ with /, which is valid.
     if cfg!(windows) {
-        let mut s = p.display().to_string().replace("\\", "/");
-        if s.starts_with("//?/") {
-            s = s[4..].to_string();
-        }
+        env::set_var("TEMP", &path);
+        env::set_var("TMP", &path);
+    } else {
+        env::set_var("TMPDIR", &path);
+    }
+
+    Ok(())
+}
+
+/// Apply the temporary directory defined by the environment, if any.
+pub fn set_temp_dir_from_env() -> Result<()> {
+    if let Ok(path) = env::var(TEMP_DIR_ENV) {
+        set_temp_dir(Path::new(&path))?;
+    }
+
+    Ok(())
+}
+
+/// Verify the filesystem holding `path` has at least `required` bytes free.
+///
+/// `path` does not need to exist yet. `purpose` describes what the space is
+/// needed for in the error message.
+///
+/// Builds on a small tmpfs otherwise fail midway through with errors that
+/// don't make the cause obvious.
+pub fn check_disk_space(path: &Path, required: u64, purpose: &str) -> Result<()> {
+    if env::var(NO_DISK_SPACE_CHECK_ENV).is_ok() {
+        return Ok(());
+    }
+
+    let existing = match path.ancestors().find(|p| p.exists()) {
+        Some(p) => p,
+        None => return Ok(()),
+    };
+
+    let available = fs2::available_space(existing)?;
+
+    if available < required {
+        return Err(anyhow!(
+            "{} has {} MiB free but {} likely needs at least {} MiB; \
+             free up space, use a directory on a larger filesystem, \
+             or set {} to skip this check",
+            existing.display(),
+            available / 1024 / 1024,
+            purpose,
+            required / 1024 / 1024,
+            NO_DISK_SPACE_CHECK_ENV
+        ));
+    }
+
+    Ok(())
+}
+
+/// Resolve the directory PyOxidizer caches data in across projects.
+///
+/// `PYOXIDIZER_CACHE_DIR` takes precedence. Otherwise the platform's
+/// per-user cache directory is used.
+pub fn user_cache_dir() -> Result<PathBuf> {
+    if let Ok(path) = env::var("PYOXIDIZER_CACHE_DIR") {
+        return Ok(PathBuf::from(path));
+    }
 
-        p = PathBuf::from(s);
+    let base = if cfg!(windows) {
+        env::var("LOCALAPPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var("HOME").map(|home| PathBuf::from(home).join("Library").join("Caches"))
+    } else {
+        env::var("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|_| env::var("HOME").map(|home| PathBuf::from(home).join(".cache")))
     }
+    .map_err(|_| anyhow!("unable to resolve user cache directory; set PYOXIDIZER_CACHE_DIR"))?;
 
-    Ok(p)
+    Ok(base.join("pyoxidizer"))
 }
 
 /// Describes the location of the PyOxidizer source files.
@@ -220,3 +319,204 @@ pub fn resolve_environment() -> Result<Environment> {
 
     Ok(Environment { pyoxidizer_source })
 }
+
+/// Convert `git describe --long` output to a version string.
+///
+/// A commit with a tag is versioned as the tag, without `tag_prefix`. Other
+/// commits get the latest tag plus SemVer build metadata holding the number
+/// of commits since the tag and the abbreviated commit, e.g.
+/// `1.2.0+3.g1a2b3c4`. `-dirty` becomes `dirty` build metadata.
+fn describe_to_version(describe: &str, tag_prefix: &str) -> Result<String> {
+    let (describe, dirty) = match describe.strip_suffix("-dirty") {
+        Some(s) => (s, true),
+        None => (describe, false),
+    };
+
+    let mut parts = describe.rsplitn(3, '-');
+    let (commit, distance, tag) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(commit), Some(distance), Some(tag)) if commit.starts_with('g') => {
+            (commit, distance, tag)
+        }
+        _ => return Err(anyhow!("unexpected git describe output: {}", describe)),
+    };
+
+    let distance = distance
+        .parse::<u64>()
+        .map_err(|_| anyhow!("unexpected git describe output: {}", describe))?;
+    let version = tag
+        .strip_prefix(tag_prefix)
+        .ok_or_else(|| anyhow!("tag {} doesn't start with {}", tag, tag_prefix))?;
+
+    let mut metadata = vec![];
+    if distance > 0 {
+        metadata.push(distance.to_string());
+        metadata.push(commit.to_string());
+    }
+    if dirty {
+        metadata.push("dirty".to_string());
+    }
+
+    Ok(if metadata.is_empty() {
+        version.to_string()
+    } else {
+        format!("{}+{}", version, metadata.join("."))
+    })
+}
+
+/// Derive a version string from the tags of the Git repository at a path.
+///
+/// Only tags starting with `tag_prefix` are considered. See
+/// `describe_to_version()` for the format of the version.
+pub fn vcs_version(path: &Path, tag_prefix: &str) -> Result<String> {
+    let repo = Repository::discover(path)
+        .map_err(|e| anyhow!("unable to find Git repository at {}: {}", path.display(), e))?;
+
+    let describe = repo
+        .describe(
+            DescribeOptions::new()
+                .describe_tags()
+                .pattern(&format!("{}*", tag_prefix)),
+        )
+        .map_err(|e| anyhow!("unable to find a tag starting with {}: {}", tag_prefix, e))?
+        .format(Some(
+            DescribeFormatOptions::new()
+                .always_use_long_format(true)
+                .dirty_suffix("-dirty"),
+        ))?;
+
+    describe_to_version(&describe, tag_prefix)
+}
+
+/// Convert a version string to the 4 numeric fields of Windows versions.
+///
+/// Windows version resources and MSI `ProductVersion` only accept numbers.
+/// The first 3 numeric components of the version are used, followed by the
+/// number of commits since the tag from `vcs_version()` build metadata, e.g.
+/// `1.2.0+3.g1a2b3c4` becomes `[1, 2, 0, 3]`. Pre-release identifiers are
+/// dropped. MSI limits the first 2 fields to 255 and the others to 65535.
+pub fn windows_version_fields(version: &str) -> Result<[u16; 4]> {
+    let mut parts = version.splitn(2, '+');
+    let core = parts.next().unwrap_or(version);
+    let metadata = parts.next();
+    let core = core.split('-').next().unwrap_or(core);
+
+    let parts = core.split('.').collect::<Vec<_>>();
+    if parts.len() > 3 {
+        return Err(anyhow!(
+            "{} has more than 3 numeric components; cannot convert to a Windows version",
+            version
+        ));
+    }
+
+    let mut fields = [0u16; 4];
+    for (i, part) in parts.iter().enumerate() {
+        fields[i] = part.parse::<u16>().map_err(|_| {
+            anyhow!(
+                "{} is not a numeric version; expected a value like 1.2.0",
+                version
+            )
+        })?;
+    }
+
+    if let Some(distance) = metadata
+        .and_then(|metadata| metadata.split('.').next())
+        .and_then(|distance| distance.parse::<u64>().ok())
+    {
+        fields[3] = u16::try_from(distance)
+            .map_err(|_| anyhow!("{} commits since the tag exceeds 65535", distance))?;
+    }
+
+    if fields[0] > 255 || fields[1] > 255 {
+        return Err(anyhow!(
+            "{} cannot be converted to a Windows version; major and minor versions must not exceed 255",
+            version
+        ));
+    }
+
+    Ok(fields)
+}
+
+/// Convert a version string to a numeric `a.b.c.d` Windows version.
+///
+/// See `windows_version_fields()` for how the version is converted.
+pub fn numeric_version(version: &str) -> Result<String> {
+    Ok(windows_version_fields(version)?
+        .iter()
+        .map(|field| field.to_string())
+        .collect::<Vec<_>>()
+        .join("."))
+}
+
+/// Whether the name of an environment variable suggests it holds credentials.
+pub fn is_secret_env_var(name: &str) -> bool {
+    const SECRETS: &[&str] = &["TOKEN", "PASSWORD", "SECRET", "KEY", "AUTH", "CREDENTIAL"];
+
+    let upper = name.to_uppercase();
+
+    SECRETS.iter().any(|secret| upper.contains(secret))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_disk_space() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+
+        check_disk_space(temp_dir.path(), 0, "test")?;
+        check_disk_space(&temp_dir.path().join("missing").join("dir"), 0, "test")?;
+        assert!(check_disk_space(temp_dir.path(), u64::MAX, "test").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_describe_to_version() -> Result<()> {
+        assert_eq!(describe_to_version("v1.2.0-0-g1a2b3c4", "v")?, "1.2.0");
+        assert_eq!(
+            describe_to_version("v1.2.0-3-g1a2b3c4", "v")?,
+            "1.2.0+3.g1a2b3c4"
+        );
+        assert_eq!(
+            describe_to_version("v1.2.0-0-g1a2b3c4-dirty", "v")?,
+            "1.2.0+dirty"
+        );
+        assert_eq!(
+            describe_to_version("release-1.2.0-rc1-3-g1a2b3c4-dirty", "release-")?,
+            "1.2.0-rc1+3.g1a2b3c4.dirty"
+        );
+        assert!(describe_to_version("1.2.0-0-g1a2b3c4", "v").is_err());
+        assert!(describe_to_version("1a2b3c4", "v").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_numeric_version() -> Result<()> {
+        assert_eq!(numeric_version("1.2.0")?, "1.2.0.0");
+        assert_eq!(numeric_version("1.2")?, "1.2.0.0");
+        assert_eq!(numeric_version("1.2.0+3.g1a2b3c4")?, "1.2.0.3");
+        assert_eq!(numeric_version("1.2.0+dirty")?, "1.2.0.0");
+        assert_eq!(numeric_version("1.2.0-rc1+3.g1a2b3c4.dirty")?, "1.2.0.3");
+        assert_eq!(
+            windows_version_fields("255.255.65535")?,
+            [255, 255, 65535, 0]
+        );
+        assert!(numeric_version("256.0.0").is_err());
+        assert!(numeric_version("1.2.65536").is_err());
+        assert!(numeric_version("1.2.3.4").is_err());
+        assert!(numeric_version("1.x").is_err());
+        assert!(numeric_version("1.2.0+70000.g1a2b3c4").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_secret_env_var() {
+        assert!(is_secret_env_var("GITHUB_TOKEN"));
+        assert!(is_secret_env_var("aws_secret_access_key"));
+        assert!(!is_secret_env_var("PATH"));
+        assert!(!is_secret_env_var("PIP_INDEX_URL"));
+    }
+}
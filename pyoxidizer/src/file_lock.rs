@@ -0,0 +1,108 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Coordinate concurrent PyOxidizer processes.
+//!
+//! Multiple `pyoxidizer` processes commonly run at once, e.g. parallel CI
+//! jobs or Cargo build scripts for several crates. They share download
+//! caches and build directories, so writers serialize on advisory lock
+//! files.
+
+use {
+    anyhow::{Context, Result},
+    fs2::FileExt,
+    slog::warn,
+    std::{
+        fs::{create_dir_all, File, OpenOptions},
+        path::{Path, PathBuf},
+    },
+};
+
+/// An exclusive lock on a file, released when dropped.
+pub struct FileLock {
+    file: File,
+}
+
+impl FileLock {
+    /// Obtain an exclusive lock on a file, waiting for other holders.
+    ///
+    /// The file and its parent directories are created if needed.
+    pub fn acquire(logger: &slog::Logger, path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent).with_context(|| format!("creating {}", parent.display()))?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)
+            .with_context(|| format!("could not create {}", path.display()))?;
+
+        if file.try_lock_exclusive().is_err() {
+            warn!(
+                logger,
+                "waiting for lock on {} held by another process",
+                path.display()
+            );
+
+            file.lock_exclusive()
+                .with_context(|| format!("failed to obtain lock for {}", path.display()))?;
+        }
+
+        Ok(Self { file })
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // Closing the file releases the lock too. But Windows may release
+        // locks of closed files with a delay, so unlock explicitly.
+        let _ = self.file.unlock();
+    }
+}
+
+/// Resolve the path of the lock file guarding another path.
+///
+/// The lock file is a sibling so it survives the path being replaced.
+pub fn sibling_lock_path(path: &Path) -> PathBuf {
+    let mut name = path
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_default();
+    name.push(".lock");
+
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_lock() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let path = temp_dir.path().join("dir").join("test.lock");
+
+        let logger = crate::testutil::get_logger()?;
+        let lock = FileLock::acquire(&logger, &path)?;
+
+        let other = File::open(&path)?;
+        assert!(other.try_lock_exclusive().is_err());
+
+        drop(lock);
+        other.try_lock_exclusive()?;
+        other.unlock()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sibling_lock_path() {
+        assert_eq!(
+            sibling_lock_path(Path::new("build/python.1234")),
+            PathBuf::from("build/python.1234.lock")
+        );
+    }
+}
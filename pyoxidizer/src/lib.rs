@@ -12,14 +12,25 @@ This library exposes that functionality to other tools.
 */
 
 pub mod analyze;
+pub mod artifact_store;
+pub mod build_layout;
+pub mod dist_manifest;
 pub mod environment;
+pub mod file_lock;
 pub mod logging;
+pub mod migrate;
+pub mod paths;
 pub mod project_building;
 pub mod project_layout;
 pub mod projectmgmt;
 pub mod py_packaging;
+pub mod python_advisories;
 pub mod python_distributions;
+pub mod release_patch;
+pub mod resource_patching;
 pub mod starlark;
+pub mod timing;
+pub mod trust;
 
 #[cfg(test)]
 mod testutil;
@@ -28,17 +28,28 @@ a rather effective and powerful tool.
 */
 
 mod analyze;
+mod artifact_store;
+mod build_layout;
 mod cli;
+mod dist_manifest;
 mod environment;
+mod file_lock;
 mod logging;
+mod migrate;
+mod paths;
 mod project_building;
 mod project_layout;
 mod projectmgmt;
 mod py_packaging;
+mod python_advisories;
 mod python_distributions;
+mod release_patch;
+mod resource_patching;
 pub mod starlark;
 #[cfg(test)]
 mod testutil;
+mod timing;
+mod trust;
 
 fn main() {
     std::process::exit(match cli::run_cli() {
@@ -0,0 +1,352 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Migrate Briefcase `pyproject.toml` files to PyOxidizer configuration files.
+
+Each `[tool.briefcase.app.<name>]` section becomes its own build target.
+Briefcase runs an app by running its package as `__main__`, so we do the
+same.
+*/
+
+use {
+    super::{
+        normalize_path, starlark_path, starlark_string, MigratedConfig, MigratedExecutable,
+        PipInstall,
+    },
+    anyhow::{anyhow, Context, Result},
+    std::path::{Path, PathBuf},
+    toml::Value,
+};
+
+/// Platforms we can translate settings of, with the Starlark expression
+/// testing whether the build targets them.
+const PLATFORMS: &[(&str, &str)] = &[
+    ("macOS", "\"apple-darwin\" in BUILD_TARGET_TRIPLE"),
+    ("linux", "\"linux\" in BUILD_TARGET_TRIPLE"),
+    ("windows", "\"windows\" in BUILD_TARGET_TRIPLE"),
+];
+
+/// An app declared in a `pyproject.toml`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BriefcaseApp {
+    pub name: String,
+
+    /// Paths of the app's packages.
+    pub sources: Vec<String>,
+
+    /// Requirements on all platforms.
+    pub requires: Vec<String>,
+
+    /// Requirements on specific platforms, as `(platform, requirements)`.
+    pub platform_requires: Vec<(String, Vec<String>)>,
+
+    /// Whether the app is a console application.
+    pub console_app: bool,
+
+    /// Path to the app's icon, without extension.
+    pub icon: Option<String>,
+}
+
+/// Settings extracted from a Briefcase `pyproject.toml`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BriefcaseProject {
+    pub apps: Vec<BriefcaseApp>,
+
+    /// Settings that couldn't be interpreted.
+    pub warnings: Vec<String>,
+}
+
+impl BriefcaseProject {
+    /// Parse the source of a `pyproject.toml`.
+    pub fn parse(source: &str) -> Result<Self> {
+        let value = source.parse::<Value>()?;
+
+        let briefcase = value
+            .get("tool")
+            .and_then(|x| x.get("briefcase"))
+            .ok_or_else(|| anyhow!("pyproject.toml has no [tool.briefcase] section"))?;
+
+        let mut project = BriefcaseProject::default();
+
+        // Settings of the project apply to all of its apps.
+        let project_requires = project.strings("requires", briefcase.get("requires"));
+
+        let apps = match briefcase.get("app").and_then(|x| x.as_table()) {
+            Some(apps) if !apps.is_empty() => apps,
+            _ => {
+                return Err(anyhow!(
+                    "pyproject.toml does not declare any Briefcase apps"
+                ))
+            }
+        };
+
+        for (name, settings) in apps {
+            let mut app = BriefcaseApp {
+                name: name.clone(),
+                sources: project.strings("sources", settings.get("sources")),
+                requires: project_requires.clone(),
+                console_app: settings
+                    .get("console_app")
+                    .and_then(|x| x.as_bool())
+                    .unwrap_or(false),
+                icon: settings
+                    .get("icon")
+                    .and_then(|x| x.as_str())
+                    .map(|s| s.to_string()),
+                ..Default::default()
+            };
+
+            app.requires
+                .extend(project.strings("requires", settings.get("requires")));
+
+            for (key, value) in settings.as_table().into_iter().flatten() {
+                if !value.is_table() {
+                    continue;
+                }
+
+                if PLATFORMS.iter().any(|(platform, _)| platform == key) {
+                    let requires = project.strings("requires", value.get("requires"));
+                    if !requires.is_empty() {
+                        app.platform_requires.push((key.clone(), requires));
+                    }
+                } else {
+                    project
+                        .warnings
+                        .push(format!("{} settings of {} are not migrated", key, name));
+                }
+            }
+
+            project.apps.push(app);
+        }
+
+        Ok(project)
+    }
+
+    /// Parse a `pyproject.toml`.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let source =
+            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+
+        Self::parse(&source).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    fn strings(&mut self, field: &str, value: Option<&Value>) -> Vec<String> {
+        match value {
+            Some(Value::Array(items)) => items
+                .iter()
+                .filter_map(|item| match item.as_str() {
+                    Some(s) => Some(s.to_string()),
+                    None => {
+                        self.warnings
+                            .push(format!("could not interpret {} entry {}", field, item));
+                        None
+                    }
+                })
+                .collect(),
+            Some(value) => {
+                self.warnings
+                    .push(format!("could not interpret {}: {}", field, value));
+                vec![]
+            }
+            None => vec![],
+        }
+    }
+}
+
+fn pip_install(condition: Option<&str>, requires: &[String]) -> PipInstall {
+    PipInstall {
+        condition: condition.map(|s| s.to_string()),
+        args: requires
+            .iter()
+            .map(|x| starlark_string(x))
+            .collect::<Vec<_>>()
+            .join(", "),
+    }
+}
+
+/// Derive the configuration for a `pyproject.toml`.
+pub fn migrate(project: &BriefcaseProject, filename: &str) -> MigratedConfig {
+    let mut config = MigratedConfig {
+        source_filename: filename.to_string(),
+        source_kind: "Briefcase project file".to_string(),
+        command: "migrate-briefcase".to_string(),
+        notes: project.warnings.clone(),
+        ..Default::default()
+    };
+
+    let executables = project
+        .apps
+        .iter()
+        .map(|app| {
+            let mut exe = MigratedExecutable {
+                program_name: app.name.clone(),
+                windows_subsystem: Some(
+                    if app.console_app {
+                        "console"
+                    } else {
+                        "windows"
+                    }
+                    .to_string(),
+                ),
+                main_module: Some(starlark_string(&app.name.replace('-', "_"))),
+                icon: app.icon.clone(),
+                ..Default::default()
+            };
+
+            // Sources are package directories. They are read from their
+            // parent, which must be the same for all of them.
+            let mut root = None;
+            for source in &app.sources {
+                let path = PathBuf::from(normalize_path(source));
+                let parent = path
+                    .parent()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_default();
+                let package = path
+                    .file_name()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default();
+
+                if root.get_or_insert_with(|| parent.clone()) == &parent {
+                    exe.packages.push(starlark_string(&package));
+                } else {
+                    config.notes.push(format!(
+                        "sources of {} are in different directories; {} is not migrated",
+                        app.name, source
+                    ));
+                }
+            }
+            exe.package_root = root.map(|root| starlark_path(&root));
+
+            if !app.requires.is_empty() {
+                exe.pip_install.push(pip_install(None, &app.requires));
+            }
+            for (platform, requires) in &app.platform_requires {
+                let condition = PLATFORMS
+                    .iter()
+                    .find(|(p, _)| p == platform)
+                    .map(|(_, condition)| *condition);
+                exe.pip_install.push(pip_install(condition, requires));
+            }
+
+            exe
+        })
+        .collect();
+
+    config.add_executables(executables);
+
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PYPROJECT: &str = r#"[tool.briefcase]
+project_name = "Hello World"
+bundle = "com.example"
+version = "0.0.1"
+requires = ["attrs"]
+
+[tool.briefcase.app.helloworld]
+formal_name = "Hello World"
+icon = "src/helloworld/resources/helloworld"
+sources = ["src/helloworld", "src/common"]
+requires = ["requests"]
+
+[tool.briefcase.app.helloworld.macOS]
+requires = ["toga-cocoa>=0.3.0"]
+
+[tool.briefcase.app.helloworld.linux]
+requires = ["toga-gtk>=0.3.0"]
+
+[tool.briefcase.app.helloworld.android]
+requires = ["toga-android>=0.3.0"]
+
+[tool.briefcase.app.hello-cli]
+sources = ["cli/hello_cli"]
+console_app = true
+"#;
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        let project = BriefcaseProject::parse(PYPROJECT)?;
+
+        assert_eq!(
+            project.apps,
+            vec![
+                BriefcaseApp {
+                    name: "hello-cli".to_string(),
+                    sources: vec!["cli/hello_cli".to_string()],
+                    requires: vec!["attrs".to_string()],
+                    console_app: true,
+                    ..Default::default()
+                },
+                BriefcaseApp {
+                    name: "helloworld".to_string(),
+                    sources: vec!["src/helloworld".to_string(), "src/common".to_string()],
+                    requires: vec!["attrs".to_string(), "requests".to_string()],
+                    platform_requires: vec![
+                        ("linux".to_string(), vec!["toga-gtk>=0.3.0".to_string()]),
+                        ("macOS".to_string(), vec!["toga-cocoa>=0.3.0".to_string()]),
+                    ],
+                    console_app: false,
+                    icon: Some("src/helloworld/resources/helloworld".to_string()),
+                },
+            ]
+        );
+        assert_eq!(
+            project.warnings,
+            vec!["android settings of helloworld are not migrated".to_string()]
+        );
+
+        assert!(BriefcaseProject::parse("[tool.black]\n").is_err());
+        assert!(BriefcaseProject::parse("[tool.briefcase]\nversion = \"1\"\n").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate() -> Result<()> {
+        let project = BriefcaseProject::parse(PYPROJECT)?;
+        let config = migrate(&project, "pyproject.toml");
+
+        assert!(!config.sys_frozen);
+        assert_eq!(config.executables.len(), 2);
+
+        let exe = &config.executables[0];
+        assert_eq!(exe.target, "exe_hello_cli");
+        assert_eq!(exe.main_module, Some("\"hello_cli\"".to_string()));
+        assert_eq!(exe.package_root, Some("CWD + \"/cli\"".to_string()));
+        assert_eq!(exe.windows_subsystem, Some("console".to_string()));
+
+        let exe = &config.executables[1];
+        assert_eq!(exe.target, "exe_helloworld");
+        assert_eq!(exe.package_root, Some("CWD + \"/src\"".to_string()));
+        assert_eq!(
+            exe.packages,
+            vec!["\"helloworld\"".to_string(), "\"common\"".to_string()]
+        );
+        assert_eq!(
+            exe.pip_install,
+            vec![
+                PipInstall {
+                    condition: None,
+                    args: "\"attrs\", \"requests\"".to_string(),
+                },
+                PipInstall {
+                    condition: Some("\"linux\" in BUILD_TARGET_TRIPLE".to_string()),
+                    args: "\"toga-gtk>=0.3.0\"".to_string(),
+                },
+                PipInstall {
+                    condition: Some("\"apple-darwin\" in BUILD_TARGET_TRIPLE".to_string()),
+                    args: "\"toga-cocoa>=0.3.0\"".to_string(),
+                },
+            ]
+        );
+
+        Ok(())
+    }
+}
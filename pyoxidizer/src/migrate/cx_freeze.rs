@@ -0,0 +1,494 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Migrate cx_Freeze `setup.py` files to PyOxidizer configuration files.
+
+We extract the `executables` and the `build_exe` options passed to
+`setup()`. Each `Executable()` becomes its own build target.
+*/
+
+use {
+    super::{
+        python_source::{strings, Expr, SourceFile},
+        starlark_path, starlark_string, MigratedConfig, MigratedExecutable, PipInstall,
+    },
+    anyhow::{anyhow, Context, Result},
+    std::path::{Path, PathBuf},
+};
+
+/// An `Executable()` declared in a `setup.py`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CxFreezeExecutable {
+    /// Script to run.
+    pub script: String,
+
+    /// Name of the built executable.
+    pub target_name: Option<String>,
+
+    /// Whether the executable uses a GUI base on Windows.
+    pub gui: bool,
+
+    /// Path to the executable's icon.
+    pub icon: Option<String>,
+}
+
+/// Settings extracted from a cx_Freeze `setup.py`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CxFreezeSetup {
+    pub executables: Vec<CxFreezeExecutable>,
+
+    /// Packages to include with all their modules.
+    pub packages: Vec<String>,
+
+    /// Modules to include.
+    pub includes: Vec<String>,
+
+    /// Modules to exclude.
+    pub excludes: Vec<String>,
+
+    /// Files to copy as `(source, target)`.
+    ///
+    /// Without a target, files are copied to the build directory under
+    /// their own name.
+    pub include_files: Vec<(String, Option<String>)>,
+
+    /// Paths to search for modules.
+    pub path: Vec<String>,
+
+    /// Distributions from `install_requires`.
+    pub install_requires: Vec<String>,
+
+    /// Constructs that couldn't be interpreted.
+    pub warnings: Vec<String>,
+}
+
+impl CxFreezeSetup {
+    /// Parse the source of a `setup.py`.
+    pub fn parse(source: &str) -> Result<Self> {
+        let source = SourceFile::parse(source)?;
+        let mut setup = CxFreezeSetup::default();
+
+        let call = source
+            .find_call("setup")
+            .ok_or_else(|| anyhow!("setup.py does not call setup()"))?;
+
+        match call.arg(None, &["executables"]) {
+            Some(Expr::List(items)) => {
+                for item in items {
+                    if let Some(exe) = setup.executable(item) {
+                        setup.executables.push(exe);
+                    }
+                }
+            }
+            Some(value) => setup.warnings.push(format!(
+                "could not interpret executables: {}",
+                value.describe()
+            )),
+            None => {}
+        }
+
+        if setup.executables.is_empty() {
+            return Err(anyhow!("setup() does not declare any executables"));
+        }
+
+        if let Some(value) = call.arg(None, &["install_requires"]) {
+            setup.install_requires = strings("install_requires", value, &mut setup.warnings);
+        }
+
+        match call.arg(None, &["options"]) {
+            Some(Expr::Dict(commands)) => {
+                for (command, options) in commands {
+                    match (command.as_str(), options) {
+                        (Some("build_exe"), Expr::Dict(options)) => {
+                            for (key, value) in options {
+                                setup.build_exe_option(key, value);
+                            }
+                        }
+                        (Some(command), _) => setup
+                            .warnings
+                            .push(format!("{} options are not migrated", command)),
+                        (None, _) => setup.warnings.push(format!(
+                            "could not interpret options for {}",
+                            command.describe()
+                        )),
+                    }
+                }
+            }
+            Some(value) => setup
+                .warnings
+                .push(format!("could not interpret options: {}", value.describe())),
+            None => {}
+        }
+
+        Ok(setup)
+    }
+
+    /// Parse a `setup.py`.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let source =
+            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+
+        Self::parse(&source).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    fn executable(&mut self, value: &Expr) -> Option<CxFreezeExecutable> {
+        let script = match value {
+            Expr::Call { func, .. } if func.ends_with("Executable") => {
+                value.arg(Some(0), &["script"])
+            }
+            _ => None,
+        };
+
+        let script = match script.and_then(|x| x.as_str()) {
+            Some(script) => script.to_string(),
+            None => {
+                self.warnings.push(format!(
+                    "could not interpret executables entry {}",
+                    value.describe()
+                ));
+                return None;
+            }
+        };
+
+        let mut exe = CxFreezeExecutable {
+            script,
+            ..Default::default()
+        };
+
+        match value.arg(Some(3), &["target_name", "targetName"]) {
+            Some(Expr::Str(name)) => exe.target_name = Some(name.clone()),
+            Some(Expr::None) | None => {}
+            Some(name) => self.warnings.push(format!(
+                "could not interpret target_name of {}: {}",
+                exe.script,
+                name.describe()
+            )),
+        }
+
+        if let Some(base) = value.arg(Some(2), &["base"]) {
+            match is_gui_base(base) {
+                Some(gui) => exe.gui = gui,
+                None => self.warnings.push(format!(
+                    "could not interpret base of {}: {}",
+                    exe.script,
+                    base.describe()
+                )),
+            }
+        }
+
+        match value.arg(Some(4), &["icon"]) {
+            Some(Expr::Str(icon)) => exe.icon = Some(icon.clone()),
+            Some(Expr::None) | None => {}
+            Some(icon) => self.warnings.push(format!(
+                "could not interpret icon of {}: {}",
+                exe.script,
+                icon.describe()
+            )),
+        }
+
+        Some(exe)
+    }
+
+    fn build_exe_option(&mut self, key: &Expr, value: &Expr) {
+        // Options can be spelled as on the command line.
+        let key = match key.as_str() {
+            Some(key) => key.replace('-', "_"),
+            None => {
+                self.warnings.push(format!(
+                    "could not interpret build_exe option {}",
+                    key.describe()
+                ));
+                return;
+            }
+        };
+
+        match key.as_str() {
+            "packages" => self.packages = strings("packages", value, &mut self.warnings),
+            "includes" => self.includes = strings("includes", value, &mut self.warnings),
+            "excludes" => self.excludes = strings("excludes", value, &mut self.warnings),
+            "path" => self.path = strings("path", value, &mut self.warnings),
+            "include_files" => match value {
+                Expr::List(items) => {
+                    for item in items {
+                        match item {
+                            Expr::Str(source) => self.include_files.push((source.clone(), None)),
+                            Expr::List(pair) => match pair.as_slice() {
+                                [Expr::Str(source), Expr::Str(target)] => self
+                                    .include_files
+                                    .push((source.clone(), Some(target.clone()))),
+                                _ => self.warnings.push(format!(
+                                    "could not interpret include_files entry {}",
+                                    item.describe()
+                                )),
+                            },
+                            _ => self.warnings.push(format!(
+                                "could not interpret include_files entry {}",
+                                item.describe()
+                            )),
+                        }
+                    }
+                }
+                _ => self.warnings.push(format!(
+                    "could not interpret include_files: {}",
+                    value.describe()
+                )),
+            },
+            _ => self
+                .warnings
+                .push(format!("build_exe option {} is not migrated", key)),
+        }
+    }
+}
+
+/// Whether an executable's `base` is a GUI base.
+///
+/// A conditional base, usually `"Win32GUI" if sys.platform == "win32" else
+/// None`, is a GUI base if either branch is.
+fn is_gui_base(base: &Expr) -> Option<bool> {
+    match base {
+        Expr::None => Some(false),
+        Expr::Str(s) => Some(s.to_lowercase().contains("gui")),
+        Expr::Conditional(a, b) => match (is_gui_base(a), is_gui_base(b)) {
+            (Some(a), Some(b)) => Some(a || b),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Derive the configuration for a `setup.py` in `source_dir`.
+///
+/// `source_dir` is consulted to find the packages next to the scripts
+/// and to tell data directories from files.
+pub fn migrate(setup: &CxFreezeSetup, setup_filename: &str, source_dir: &Path) -> MigratedConfig {
+    let mut config = MigratedConfig {
+        source_filename: setup_filename.to_string(),
+        source_kind: "cx_Freeze setup file".to_string(),
+        command: "migrate-cx-freeze".to_string(),
+        sys_frozen: true,
+        excludes: setup.excludes.iter().map(|x| starlark_string(x)).collect(),
+        notes: setup.warnings.clone(),
+        ..Default::default()
+    };
+
+    let pip_install = if !setup.install_requires.is_empty() {
+        Some(PipInstall {
+            condition: None,
+            args: setup
+                .install_requires
+                .iter()
+                .map(|x| starlark_string(x))
+                .collect::<Vec<_>>()
+                .join(", "),
+        })
+    } else if source_dir.join("requirements.txt").is_file() {
+        Some(PipInstall {
+            condition: None,
+            args: format!("\"-r\", {}", starlark_path("requirements.txt")),
+        })
+    } else {
+        None
+    };
+
+    let executables = setup
+        .executables
+        .iter()
+        .map(|e| {
+            let program_name = match &e.target_name {
+                Some(name) => name.trim_end_matches(".exe").to_string(),
+                None => PathBuf::from(e.script.replace('\\', "/"))
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+            };
+
+            let mut exe = MigratedExecutable {
+                program_name,
+                windows_subsystem: Some(if e.gui { "windows" } else { "console" }.to_string()),
+                pip_install: pip_install.iter().cloned().collect(),
+                search_paths: setup.path.iter().map(|p| starlark_string(p)).collect(),
+                required_modules: setup
+                    .packages
+                    .iter()
+                    .chain(setup.includes.iter())
+                    .map(|x| starlark_string(x))
+                    .collect(),
+                icon: e.icon.clone(),
+                ..Default::default()
+            };
+            exe.set_main_script(source_dir, &e.script);
+
+            exe
+        })
+        .collect();
+
+    config.add_executables(executables);
+
+    for (source, target) in &setup.include_files {
+        let source_path = PathBuf::from(source.replace('\\', "/"));
+        let source_name = source_path
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let is_dir = source_dir.join(&source_path).is_dir();
+
+        // cx_Freeze copies to a target path while we install files into a
+        // directory. Renamed files can't be expressed that way.
+        let dest = match target {
+            None if is_dir => Some(source_name),
+            None => Some("".to_string()),
+            Some(target) if is_dir => Some(target.clone()),
+            Some(target) => {
+                let target_path = PathBuf::from(target.replace('\\', "/"));
+
+                if target.ends_with('/') || target.ends_with('\\') {
+                    Some(target.clone())
+                } else if target_path.file_name() == source_path.file_name() {
+                    Some(
+                        target_path
+                            .parent()
+                            .map(|p| p.display().to_string())
+                            .unwrap_or_default(),
+                    )
+                } else {
+                    None
+                }
+            }
+        };
+
+        match dest {
+            Some(dest) => config.add_data_files(source_dir, source, &dest),
+            None => config.unmigrated_datas.push(format!(
+                "{} -> {}",
+                source,
+                target.as_deref().unwrap_or(".")
+            )),
+        }
+    }
+
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::migrate::DataGlob};
+
+    const SETUP_PY: &str = r#"import sys
+from cx_Freeze import setup, Executable
+
+build_exe_options = {
+    "packages": ["os", "myapp"],
+    "excludes": ["tkinter"],
+    "include_files": ["assets", ("config/default.json", "config/default.json"), ("README.md", "docs/README.txt")],
+    "optimize": 2,
+}
+
+setup(
+    name="myapp",
+    version="0.1",
+    install_requires=["requests>=2.0"],
+    options={"build_exe": build_exe_options, "bdist_msi": {"upgrade_code": "{...}"}},
+    executables=[
+        Executable("src/main.py", base="Win32GUI" if sys.platform == "win32" else None, target_name="myapp.exe", icon="app.ico"),
+        Executable("src/tool.py"),
+    ],
+)
+"#;
+
+    #[test]
+    fn test_parse_setup() -> Result<()> {
+        let setup = CxFreezeSetup::parse(SETUP_PY)?;
+
+        assert_eq!(
+            setup.executables,
+            vec![
+                CxFreezeExecutable {
+                    script: "src/main.py".to_string(),
+                    target_name: Some("myapp.exe".to_string()),
+                    gui: true,
+                    icon: Some("app.ico".to_string()),
+                },
+                CxFreezeExecutable {
+                    script: "src/tool.py".to_string(),
+                    ..Default::default()
+                },
+            ]
+        );
+        assert_eq!(setup.packages, vec!["os".to_string(), "myapp".to_string()]);
+        assert_eq!(setup.excludes, vec!["tkinter".to_string()]);
+        assert_eq!(setup.install_requires, vec!["requests>=2.0".to_string()]);
+        assert_eq!(setup.include_files.len(), 3);
+        assert_eq!(
+            setup.warnings,
+            vec![
+                "build_exe option optimize is not migrated".to_string(),
+                "bdist_msi options are not migrated".to_string(),
+            ]
+        );
+
+        assert!(CxFreezeSetup::parse("setup(name='foo')\n").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let source_dir = temp_dir.path();
+
+        std::fs::create_dir_all(source_dir.join("src").join("myapp"))?;
+        std::fs::write(source_dir.join("src").join("myapp").join("__init__.py"), "")?;
+        std::fs::create_dir_all(source_dir.join("assets"))?;
+
+        let setup = CxFreezeSetup::parse(SETUP_PY)?;
+        let config = migrate(&setup, "setup.py", source_dir);
+
+        assert!(config.sys_frozen);
+        assert!(!config.sys_meipass);
+        assert_eq!(
+            config
+                .executables
+                .iter()
+                .map(|e| (e.target.as_str(), e.program_name.as_str()))
+                .collect::<Vec<_>>(),
+            vec![("exe_myapp", "myapp"), ("exe_tool", "tool")]
+        );
+
+        let exe = &config.executables[1];
+        assert_eq!(exe.windows_subsystem, Some("console".to_string()));
+        assert_eq!(exe.main_module, Some("\"tool\"".to_string()));
+        assert_eq!(
+            exe.packages,
+            vec!["\"tool\"".to_string(), "\"myapp\"".to_string()]
+        );
+        assert_eq!(
+            exe.pip_install,
+            vec![PipInstall {
+                condition: None,
+                args: "\"requests>=2.0\"".to_string(),
+            }]
+        );
+
+        assert_eq!(
+            config.datas,
+            vec![
+                DataGlob {
+                    include: "\"assets/**/*\"".to_string(),
+                    strip_prefix: "CWD".to_string(),
+                },
+                DataGlob {
+                    include: "\"config/default.json\"".to_string(),
+                    strip_prefix: "CWD".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            config.unmigrated_datas,
+            vec!["README.md -> docs/README.txt".to_string()]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,333 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Migrate configurations of other Python packaging tools to PyOxidizer.
+
+Each importer extracts what it can from the other tool's configuration into
+a [`MigratedConfig`], which is rendered into a `pyoxidizer.bzl` file.
+Settings that can't be migrated are recorded as notes, so they can be
+migrated by hand.
+*/
+
+pub mod briefcase;
+pub mod cx_freeze;
+pub mod pyinstaller;
+mod python_source;
+
+use {
+    serde::Serialize,
+    std::path::{Path, PathBuf},
+};
+
+/// Files to install with `glob()`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DataGlob {
+    /// Starlark expression of the `glob()` pattern.
+    pub include: String,
+
+    /// Starlark expression of the `strip_prefix` argument.
+    pub strip_prefix: String,
+}
+
+/// A `pip_install()` call.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PipInstall {
+    /// Starlark expression the call is conditional on.
+    pub condition: Option<String>,
+
+    /// Starlark expressions of the arguments to pip, joined by `, `.
+    pub args: String,
+}
+
+/// Values for the template of a migrated executable.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct MigratedExecutable {
+    /// Name of the build target producing the executable.
+    pub target: String,
+    pub program_name: String,
+    pub windows_subsystem: Option<String>,
+    pub main_module: Option<String>,
+    pub package_root: Option<String>,
+    pub packages: Vec<String>,
+    pub pip_install: Vec<PipInstall>,
+    /// Paths the original tool searched for imports.
+    pub search_paths: Vec<String>,
+    /// Modules the original configuration included explicitly.
+    pub required_modules: Vec<String>,
+    pub icon: Option<String>,
+}
+
+impl MigratedExecutable {
+    /// Run a script as the main module.
+    ///
+    /// The script's directory becomes the package root, with the script
+    /// and the packages next to it as the application's packages.
+    /// `source_dir` is the directory `script` is relative to.
+    pub fn set_main_script(&mut self, source_dir: &Path, script: &str) {
+        let script = normalize_path(script);
+        let script_path = PathBuf::from(&script);
+
+        let root = script_path
+            .parent()
+            .map(|p| p.display().to_string())
+            .unwrap_or_default();
+        let stem = script_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        // The main script's siblings are likely the application's own
+        // packages.
+        let mut packages = vec![stem.clone()];
+        if let Ok(entries) = std::fs::read_dir(source_dir.join(&root)) {
+            let mut found = entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().join("__init__.py").is_file())
+                .filter_map(|entry| entry.file_name().to_str().map(|s| s.to_string()))
+                .collect::<Vec<_>>();
+            found.sort();
+            packages.extend(found);
+        }
+
+        self.main_module = Some(starlark_string(&stem));
+        self.package_root = Some(starlark_path(&root));
+        self.packages = packages.iter().map(|p| starlark_string(p)).collect();
+    }
+}
+
+/// Values for the template of a migrated configuration file.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct MigratedConfig {
+    /// Name of the file the configuration was migrated from.
+    pub source_filename: String,
+    /// Description of the migrated file, e.g. `PyInstaller spec file`.
+    pub source_kind: String,
+    /// `pyoxidizer` command performing the migration.
+    pub command: String,
+    pub sys_frozen: bool,
+    pub sys_meipass: bool,
+    pub executables: Vec<MigratedExecutable>,
+    pub excludes: Vec<String>,
+    pub datas: Vec<DataGlob>,
+    pub unmigrated_datas: Vec<String>,
+    pub notes: Vec<String>,
+}
+
+impl MigratedConfig {
+    /// Add executables, assigning each a unique build target.
+    ///
+    /// A lone executable is built by the `exe` target, as in newly
+    /// created projects.
+    pub fn add_executables(&mut self, executables: Vec<MigratedExecutable>) {
+        let single = executables.len() == 1;
+
+        for mut exe in executables {
+            let base = if single {
+                "exe".to_string()
+            } else {
+                format!("exe_{}", identifier(&exe.program_name))
+            };
+
+            let mut target = base.clone();
+            let mut i = 2;
+            while self.executables.iter().any(|e| e.target == target) {
+                target = format!("{}_{}", base, i);
+                i += 1;
+            }
+
+            exe.target = target;
+            self.executables.push(exe);
+        }
+    }
+
+    /// Install data files next to the executables.
+    ///
+    /// `source` is a file, directory or glob pattern relative to
+    /// `source_dir`. Files are installed into the `dest` directory and
+    /// directories are installed recursively as `dest`.
+    pub fn add_data_files(&mut self, source_dir: &Path, source: &str, dest: &str) {
+        let source = normalize_path(source);
+        let dest = normalize_path(dest).trim_start_matches('/').to_string();
+
+        let (include, anchor) = if source_dir.join(&source).is_dir() {
+            (format!("{}/**/*", source), source.clone())
+        } else {
+            (
+                source.clone(),
+                PathBuf::from(&source)
+                    .parent()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_default(),
+            )
+        };
+
+        // Files are installed at their path relative to `strip_prefix`.
+        // So this only works if the destination is a suffix of the
+        // files' directory.
+        let strip_prefix = if dest.is_empty() {
+            Some(anchor)
+        } else if anchor == dest {
+            Some("".to_string())
+        } else {
+            anchor
+                .strip_suffix(&format!("/{}", dest))
+                .map(|s| s.to_string())
+        };
+
+        match strip_prefix {
+            Some(strip_prefix) => self.datas.push(DataGlob {
+                include: starlark_string(&include),
+                strip_prefix: starlark_path(&strip_prefix),
+            }),
+            None => self.unmigrated_datas.push(format!(
+                "{} -> {}",
+                source,
+                if dest.is_empty() { "." } else { &dest }
+            )),
+        }
+    }
+}
+
+/// Format a string as a Starlark string literal.
+pub fn starlark_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Format a normalized path as a Starlark expression.
+///
+/// Relative paths are relative to the config file.
+pub fn starlark_path(path: &str) -> String {
+    if path.is_empty() {
+        "CWD".to_string()
+    } else if is_absolute_path(path) {
+        starlark_string(path)
+    } else {
+        format!("CWD + {}", starlark_string(&format!("/{}", path)))
+    }
+}
+
+/// Normalize a path from a migrated file to a `/` delimited path.
+///
+/// Absolute paths keep their leading `/`.
+pub fn normalize_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+
+    let normalized = path
+        .split('/')
+        .filter(|c| !c.is_empty() && *c != ".")
+        .collect::<Vec<_>>()
+        .join("/");
+
+    if path.starts_with('/') {
+        format!("/{}", normalized)
+    } else {
+        normalized
+    }
+}
+
+/// Whether a normalized path is absolute, including Windows drive paths.
+fn is_absolute_path(path: &str) -> bool {
+    path.starts_with('/') || path.as_bytes().get(1..3) == Some(&b":/"[..])
+}
+
+/// Derive a Starlark identifier from a name.
+fn identifier(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, anyhow::Result};
+
+    #[test]
+    fn test_add_executables() {
+        let mut config = MigratedConfig::default();
+        config.add_executables(vec![MigratedExecutable {
+            program_name: "app".to_string(),
+            ..Default::default()
+        }]);
+        assert_eq!(config.executables[0].target, "exe");
+
+        let mut config = MigratedConfig::default();
+        config.add_executables(
+            ["My App", "my-app", "tool"]
+                .iter()
+                .map(|name| MigratedExecutable {
+                    program_name: name.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+        );
+        assert_eq!(
+            config
+                .executables
+                .iter()
+                .map(|e| e.target.as_str())
+                .collect::<Vec<_>>(),
+            vec!["exe_my_app", "exe_my_app_2", "exe_tool"]
+        );
+    }
+
+    #[test]
+    fn test_add_data_files() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let source_dir = temp_dir.path();
+        std::fs::create_dir_all(source_dir.join("assets"))?;
+
+        let mut config = MigratedConfig::default();
+        config.add_data_files(source_dir, "assets", "assets");
+        config.add_data_files(source_dir, "config/default.json", "config");
+        config.add_data_files(source_dir, "share\\data\\*.txt", "data");
+        config.add_data_files(source_dir, "data/*.txt", "elsewhere");
+
+        assert_eq!(
+            config.datas,
+            vec![
+                DataGlob {
+                    include: "\"assets/**/*\"".to_string(),
+                    strip_prefix: "CWD".to_string(),
+                },
+                DataGlob {
+                    include: "\"config/default.json\"".to_string(),
+                    strip_prefix: "CWD".to_string(),
+                },
+                DataGlob {
+                    include: "\"share/data/*.txt\"".to_string(),
+                    strip_prefix: "CWD + \"/share\"".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            config.unmigrated_datas,
+            vec!["data/*.txt -> elsewhere".to_string()]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("src\\app.py"), "src/app.py");
+        assert_eq!(normalize_path("./assets//images/"), "assets/images");
+        assert_eq!(normalize_path("/opt/app/data"), "/opt/app/data");
+        assert_eq!(normalize_path("C:\\app\\data"), "C:/app/data");
+    }
+
+    #[test]
+    fn test_starlark_path() {
+        assert_eq!(starlark_path(""), "CWD");
+        assert_eq!(starlark_path("src"), "CWD + \"/src\"");
+        assert_eq!(starlark_path("/opt/app"), "\"/opt/app\"");
+        assert_eq!(starlark_path("C:/app"), "\"C:/app\"");
+    }
+}
@@ -0,0 +1,420 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Migrate PyInstaller spec files to PyOxidizer configuration files.
+
+We extract the arguments to `Analysis()` and `EXE()` from the spec file.
+Anything we can't interpret is reported so it can be migrated by hand.
+*/
+
+use {
+    super::{
+        python_source::{strings, Expr, SourceFile},
+        starlark_path, starlark_string, MigratedConfig, MigratedExecutable, PipInstall,
+    },
+    anyhow::{anyhow, Context, Result},
+    std::path::Path,
+};
+
+/// Settings extracted from a PyInstaller spec file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PyInstallerSpec {
+    /// Name of the built executable.
+    pub name: Option<String>,
+
+    /// Scripts to run, as passed to `Analysis()`.
+    pub scripts: Vec<String>,
+
+    /// Extra paths to search for imports.
+    pub pathex: Vec<String>,
+
+    /// Data files as `(source, destination directory)`.
+    pub datas: Vec<(String, String)>,
+
+    /// Modules PyInstaller's import analysis wouldn't find.
+    pub hidden_imports: Vec<String>,
+
+    /// Modules to exclude.
+    pub excludes: Vec<String>,
+
+    /// Path to the executable's icon.
+    pub icon: Option<String>,
+
+    /// Whether the executable is a console application.
+    pub console: Option<bool>,
+
+    /// Constructs that couldn't be interpreted.
+    pub warnings: Vec<String>,
+}
+
+impl PyInstallerSpec {
+    /// Parse the source of a spec file.
+    pub fn parse(source: &str) -> Result<Self> {
+        let source = SourceFile::parse(source)?;
+        let mut spec = PyInstallerSpec::default();
+
+        let analysis = source
+            .find_call("Analysis")
+            .ok_or_else(|| anyhow!("spec file does not call Analysis()"))?;
+
+        if let Some(value) = analysis.arg(Some(0), &["scripts"]) {
+            spec.scripts = strings("scripts", value, &mut spec.warnings);
+        }
+        if let Some(value) = analysis.arg(Some(1), &["pathex"]) {
+            spec.pathex = strings("pathex", value, &mut spec.warnings);
+        }
+        if let Some(value) = analysis.arg(Some(3), &["datas"]) {
+            spec.datas = spec.pairs("datas", value);
+        }
+        if let Some(value) = analysis.arg(Some(4), &["hiddenimports"]) {
+            spec.hidden_imports = strings("hiddenimports", value, &mut spec.warnings);
+        }
+        if let Some(value) = analysis.arg(Some(8), &["excludes"]) {
+            spec.excludes = strings("excludes", value, &mut spec.warnings);
+        }
+        if let Some(value) = analysis.arg(Some(2), &["binaries"]) {
+            if !matches!(value, Expr::List(items) if items.is_empty()) {
+                spec.warnings
+                    .push(format!("binaries are not migrated: {}", value.describe()));
+            }
+        }
+
+        if let Some(Expr::Call { kwargs, .. }) = source.find_call("EXE") {
+            for (key, value) in kwargs {
+                match (key.as_str(), value) {
+                    ("name", Expr::Str(s)) => spec.name = Some(s.clone()),
+                    ("console", Expr::Bool(b)) => spec.console = Some(*b),
+                    ("windowed", Expr::Bool(b)) => spec.console = Some(!*b),
+                    ("icon", Expr::Str(s)) => spec.icon = Some(s.clone()),
+                    ("icon", Expr::List(items)) => {
+                        spec.icon = items.iter().find_map(|x| x.as_str().map(|s| s.to_string()))
+                    }
+                    ("name", _) | ("console", _) | ("windowed", _) | ("icon", _) => {
+                        spec.warnings.push(format!(
+                            "could not interpret EXE() argument {}={}",
+                            key,
+                            value.describe()
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+        } else {
+            spec.warnings
+                .push("spec file does not call EXE(); using defaults".to_string());
+        }
+
+        if source.find_call("BUNDLE").is_some() {
+            spec.warnings
+                .push("macOS application bundles (BUNDLE()) are not migrated".to_string());
+        }
+
+        Ok(spec)
+    }
+
+    /// Parse a spec file.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let source =
+            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+
+        Self::parse(&source).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    fn pairs(&mut self, field: &str, value: &Expr) -> Vec<(String, String)> {
+        match value {
+            Expr::List(items) => items
+                .iter()
+                .filter_map(|item| match item {
+                    Expr::List(pair) => match pair.as_slice() {
+                        [Expr::Str(source), Expr::Str(dest)] => {
+                            Some((source.clone(), dest.clone()))
+                        }
+                        _ => {
+                            self.warnings.push(format!(
+                                "could not interpret {} entry {}",
+                                field,
+                                item.describe()
+                            ));
+                            None
+                        }
+                    },
+                    _ => {
+                        self.warnings.push(format!(
+                            "could not interpret {} entry {}",
+                            field,
+                            item.describe()
+                        ));
+                        None
+                    }
+                })
+                .collect(),
+            Expr::None => vec![],
+            _ => {
+                self.warnings.push(format!(
+                    "could not interpret {}: {}",
+                    field,
+                    value.describe()
+                ));
+                vec![]
+            }
+        }
+    }
+}
+
+/// Derive the configuration for a spec file in `spec_dir`.
+///
+/// `spec_dir` is consulted to find the packages next to the main script
+/// and to tell data directories from files.
+pub fn migrate(spec: &PyInstallerSpec, spec_filename: &str, spec_dir: &Path) -> MigratedConfig {
+    let mut config = MigratedConfig {
+        source_filename: spec_filename.to_string(),
+        source_kind: "PyInstaller spec file".to_string(),
+        command: "migrate-pyinstaller".to_string(),
+        sys_frozen: true,
+        sys_meipass: true,
+        excludes: spec.excludes.iter().map(|x| starlark_string(x)).collect(),
+        notes: spec.warnings.clone(),
+        ..Default::default()
+    };
+
+    let mut exe = MigratedExecutable {
+        program_name: spec
+            .name
+            .clone()
+            .unwrap_or_else(|| spec_filename.trim_end_matches(".spec").to_string()),
+        windows_subsystem: spec
+            .console
+            .map(|console| if console { "console" } else { "windows" }.to_string()),
+        search_paths: spec.pathex.iter().map(|p| starlark_string(p)).collect(),
+        required_modules: spec
+            .hidden_imports
+            .iter()
+            .map(|x| starlark_string(x))
+            .collect(),
+        icon: spec.icon.clone(),
+        ..Default::default()
+    };
+
+    if let Some(script) = spec.scripts.first() {
+        exe.set_main_script(spec_dir, script);
+
+        if spec.scripts.len() > 1 {
+            config.notes.push(format!(
+                "only the first script ({}) is run; others are not migrated",
+                script
+            ));
+        }
+    } else {
+        config
+            .notes
+            .push("no scripts found in Analysis(); a Python REPL is run".to_string());
+    }
+
+    if spec_dir.join("requirements.txt").is_file() {
+        exe.pip_install.push(PipInstall {
+            condition: None,
+            args: format!("\"-r\", {}", starlark_path("requirements.txt")),
+        });
+    }
+
+    config.add_executables(vec![exe]);
+
+    for (source, dest) in &spec.datas {
+        config.add_data_files(spec_dir, source, dest);
+    }
+
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::migrate::normalize_path};
+
+    const SPEC: &str = r#"# -*- mode: python ; coding: utf-8 -*-
+
+block_cipher = None
+
+datas = [('assets', 'assets'), ("config/default.json", "config")]
+datas += collect_data_files('certifi')
+
+a = Analysis(['src\\app.py'],
+             pathex=['/home/user/app'],
+             binaries=[],
+             datas=datas,
+             hiddenimports=['pkg_resources.py2_warn', "sqlalchemy.sql.default_comparator"],
+             hookspath=[],
+             runtime_hooks=[],
+             excludes=['tkinter', 'test'],
+             win_no_prefer_redirects=False,
+             win_private_assemblies=False,
+             cipher=block_cipher,
+             noarchive=False)
+pyz = PYZ(a.pure, a.zipped_data,
+             cipher=block_cipher)
+exe = EXE(pyz,
+          a.scripts,
+          [],
+          exclude_binaries=True,
+          name='myapp',
+          debug=False,
+          bootloader_ignore_signals=False,
+          strip=False,
+          upx=True,
+          console=False,
+          icon='resources/app.ico')
+coll = COLLECT(exe,
+               a.binaries,
+               a.zipfiles,
+               a.datas,
+               strip=False,
+               upx=True,
+               upx_exclude=[],
+               name='myapp')
+"#;
+
+    #[test]
+    fn test_parse_spec() -> Result<()> {
+        let spec = PyInstallerSpec::parse(SPEC)?;
+
+        assert_eq!(spec.name, Some("myapp".to_string()));
+        assert_eq!(spec.scripts, vec!["src\\app.py".to_string()]);
+        assert_eq!(spec.pathex, vec!["/home/user/app".to_string()]);
+        assert_eq!(
+            spec.datas,
+            vec![
+                ("assets".to_string(), "assets".to_string()),
+                ("config/default.json".to_string(), "config".to_string())
+            ]
+        );
+        assert_eq!(
+            spec.hidden_imports,
+            vec![
+                "pkg_resources.py2_warn".to_string(),
+                "sqlalchemy.sql.default_comparator".to_string()
+            ]
+        );
+        assert_eq!(
+            spec.excludes,
+            vec!["tkinter".to_string(), "test".to_string()]
+        );
+        assert_eq!(spec.icon, Some("resources/app.ico".to_string()));
+        assert_eq!(spec.console, Some(false));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_unsupported() -> Result<()> {
+        let spec = PyInstallerSpec::parse(
+            "a = Analysis(['app.py'], datas=collect_data_files('certifi'), \
+             hiddenimports=['foo', name])\n\
+             exe = EXE(a.scripts, name=get_name(), console=True)\n",
+        )?;
+
+        assert!(spec.datas.is_empty());
+        assert_eq!(spec.hidden_imports, vec!["foo".to_string()]);
+        assert_eq!(spec.name, None);
+        assert_eq!(spec.console, Some(true));
+        assert_eq!(
+            spec.warnings,
+            vec![
+                "could not interpret datas: collect_data_files(...)".to_string(),
+                "could not interpret hiddenimports entry name".to_string(),
+                "could not interpret EXE() argument name=get_name(...)".to_string(),
+            ]
+        );
+
+        assert!(PyInstallerSpec::parse("print('hello')\n").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrated_config() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let spec_dir = temp_dir.path();
+
+        std::fs::create_dir_all(spec_dir.join("src").join("mypkg"))?;
+        std::fs::write(spec_dir.join("src").join("mypkg").join("__init__.py"), "")?;
+        std::fs::create_dir_all(spec_dir.join("assets"))?;
+
+        std::fs::write(spec_dir.join("requirements.txt"), "")?;
+
+        let spec = PyInstallerSpec::parse(SPEC)?;
+        let config = migrate(&spec, "myapp.spec", spec_dir);
+
+        assert!(config.sys_meipass);
+        assert_eq!(config.excludes, vec!["\"tkinter\"", "\"test\""]);
+        assert_eq!(config.datas.len(), 2);
+        assert_eq!(config.executables.len(), 1);
+
+        let exe = &config.executables[0];
+        assert_eq!(exe.target, "exe");
+        assert_eq!(exe.program_name, "myapp");
+        assert_eq!(exe.windows_subsystem, Some("windows".to_string()));
+        assert_eq!(exe.main_module, Some("\"app\"".to_string()));
+        assert_eq!(exe.package_root, Some("CWD + \"/src\"".to_string()));
+        assert_eq!(
+            exe.packages,
+            vec!["\"app\"".to_string(), "\"mypkg\"".to_string()]
+        );
+        assert_eq!(
+            exe.pip_install,
+            vec![PipInstall {
+                condition: None,
+                args: "\"-r\", CWD + \"/requirements.txt\"".to_string(),
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrated_config_file() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let spec_dir = temp_dir.path();
+        let data_dir = spec_dir.join("shared").join("data");
+        std::fs::create_dir_all(&data_dir)?;
+
+        let mut spec = PyInstallerSpec::parse(SPEC)?;
+        spec.datas
+            .push((data_dir.display().to_string(), "data".to_string()));
+
+        let config = migrate(&spec, "myapp.spec", spec_dir);
+        let path = spec_dir.join("pyoxidizer.bzl");
+        crate::project_layout::write_migrated_config_file(&path, &config)?;
+        let bzl = std::fs::read_to_string(&path)?;
+
+        let map = std::sync::Arc::new(std::sync::Mutex::new(codemap::CodeMap::new()));
+        assert!(starlark::syntax::parser::parse(
+            &map,
+            "pyoxidizer.bzl",
+            &bzl,
+            starlark::syntax::dialect::Dialect::Bzl
+        )
+        .is_ok());
+
+        assert!(bzl.contains("from the PyInstaller spec file myapp.spec"));
+        assert!(bzl.contains("    python_config.sys_meipass = True\n"));
+        assert!(bzl.contains("    python_config.run_module = \"app\"\n"));
+        assert!(bzl.contains("    exe = dist.to_python_executable(\n        name=\"myapp\",\n"));
+        assert!(bzl.contains("    exe.windows_subsystem = \"windows\"\n"));
+        assert!(bzl.contains("(resources/app.ico)"));
+        assert!(bzl.contains("        path=CWD + \"/src\",\n"));
+        assert!(bzl.contains("    # \"sqlalchemy.sql.default_comparator\"\n"));
+        assert!(bzl.contains(&format!(
+            "    files.add_manifest(glob([{}], strip_prefix={}))\n",
+            starlark_string(&format!(
+                "{}/**/*",
+                normalize_path(&data_dir.display().to_string())
+            )),
+            starlark_string(&normalize_path(
+                &spec_dir.join("shared").display().to_string()
+            )),
+        )));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,774 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Interpret Python source files of other packaging tools.
+
+Configuration of tools like PyInstaller and cx_Freeze is Python source. We
+don't execute it. Instead, we parse the subset of Python commonly found in
+it (literals, simple assignments and calls) and resolve references to
+variables. Expressions we don't interpret are kept as text so they can be
+reported.
+*/
+
+use {
+    anyhow::{anyhow, Result},
+    std::collections::HashMap,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Name(String),
+    Str(String),
+    Number(String),
+    Punct(char),
+    Newline,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let chars = source.chars().collect::<Vec<_>>();
+    let mut tokens = vec![];
+    let mut depth = 0usize;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c == '#' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '\\' && chars.get(i + 1) == Some(&'\n') {
+            i += 2;
+        } else if c == '\n' {
+            // Newlines inside brackets don't end statements.
+            if depth == 0 && tokens.last() != Some(&Token::Newline) {
+                tokens.push(Token::Newline);
+            }
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let name = chars[start..i].iter().collect::<String>();
+
+            let is_prefix = name.len() <= 2
+                && name
+                    .chars()
+                    .all(|c| matches!(c.to_ascii_lowercase(), 'r' | 'b' | 'u' | 'f'));
+            if is_prefix && matches!(chars.get(i), Some('\'') | Some('"')) {
+                let raw = name.to_ascii_lowercase().contains('r');
+                let (value, end) = read_string(&chars, i, raw)?;
+                tokens.push(Token::Str(value));
+                i = end;
+            } else {
+                tokens.push(Token::Name(name));
+            }
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.') {
+                i += 1;
+            }
+            tokens.push(Token::Number(chars[start..i].iter().collect()));
+        } else if c == '\'' || c == '"' {
+            let (value, end) = read_string(&chars, i, false)?;
+            tokens.push(Token::Str(value));
+            i = end;
+        } else {
+            match c {
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            tokens.push(Token::Punct(c));
+            i += 1;
+        }
+    }
+
+    tokens.push(Token::Newline);
+
+    Ok(tokens)
+}
+
+/// Read a string literal starting at the opening quote.
+///
+/// Returns the value and the index after the closing quote.
+fn read_string(chars: &[char], start: usize, raw: bool) -> Result<(String, usize)> {
+    let quote = chars[start];
+    let triple = chars.get(start + 1) == Some(&quote) && chars.get(start + 2) == Some(&quote);
+    let mut i = if triple { start + 3 } else { start + 1 };
+    let mut value = String::new();
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c == '\\' && i + 1 < chars.len() {
+            let next = chars[i + 1];
+            if raw {
+                value.push(c);
+                value.push(next);
+            } else {
+                match next {
+                    'n' => value.push('\n'),
+                    't' => value.push('\t'),
+                    '\n' => {}
+                    _ => value.push(next),
+                }
+            }
+            i += 2;
+        } else if c == quote
+            && (!triple || (chars.get(i + 1) == Some(&quote) && chars.get(i + 2) == Some(&quote)))
+        {
+            return Ok((value, if triple { i + 3 } else { i + 1 }));
+        } else if c == '\n' && !triple {
+            break;
+        } else {
+            value.push(c);
+            i += 1;
+        }
+    }
+
+    Err(anyhow!("unterminated string literal"))
+}
+
+/// A Python expression.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Str(String),
+    Bool(bool),
+    None,
+    Number(String),
+    /// A list or tuple.
+    List(Vec<Expr>),
+    Dict(Vec<(Expr, Expr)>),
+    Name(String),
+    Call {
+        func: String,
+        args: Vec<Expr>,
+        kwargs: Vec<(String, Expr)>,
+    },
+    /// Operands of `+`, combined once variables are resolved.
+    Add(Vec<Expr>),
+    /// `a if condition else b`, with the condition discarded.
+    Conditional(Box<Expr>, Box<Expr>),
+    /// An expression we don't interpret, as approximate source text.
+    Other(String),
+}
+
+impl Expr {
+    /// Describe the expression for humans, approximating its source.
+    pub fn describe(&self) -> String {
+        match self {
+            Expr::Str(s) => format!("{:?}", s),
+            Expr::Bool(v) => if *v { "True" } else { "False" }.to_string(),
+            Expr::None => "None".to_string(),
+            Expr::Number(n) => n.clone(),
+            Expr::List(items) => format!(
+                "[{}]",
+                items
+                    .iter()
+                    .map(|x| x.describe())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Expr::Dict(items) => format!(
+                "{{{}}}",
+                items
+                    .iter()
+                    .map(|(k, v)| format!("{}: {}", k.describe(), v.describe()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Expr::Name(name) => name.clone(),
+            Expr::Call { func, .. } => format!("{}(...)", func),
+            Expr::Add(operands) => operands
+                .iter()
+                .map(|x| x.describe())
+                .collect::<Vec<_>>()
+                .join(" + "),
+            Expr::Conditional(a, b) => format!("{} if ... else {}", a.describe(), b.describe()),
+            Expr::Other(s) => s.clone(),
+        }
+    }
+
+    /// Obtain the value of a string expression.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Expr::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Obtain an argument of a call expression by position or keyword.
+    pub fn arg(&self, index: Option<usize>, names: &[&str]) -> Option<&Expr> {
+        match self {
+            Expr::Call { args, kwargs, .. } => kwargs
+                .iter()
+                .find(|(k, _)| names.contains(&k.as_str()))
+                .map(|(_, v)| v)
+                .or_else(|| index.and_then(|i| args.get(i))),
+            _ => None,
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(&Token::Punct(c)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Skip to the end of the current statement.
+    fn skip_statement(&mut self) {
+        while let Some(token) = self.next() {
+            if token == Token::Newline {
+                break;
+            }
+        }
+    }
+
+    /// Skip tokens up to the closing bracket of an open bracket.
+    fn skip_bracketed(&mut self) {
+        let mut depth = 1;
+        while let Some(token) = self.next() {
+            match token {
+                Token::Punct('(') | Token::Punct('[') | Token::Punct('{') => depth += 1,
+                Token::Punct(')') | Token::Punct(']') | Token::Punct('}') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn parse_expr(&mut self) -> Expr {
+        let mut operands = vec![self.parse_primary()];
+        while self.eat('+') {
+            operands.push(self.parse_primary());
+        }
+        let expr = if operands.len() == 1 {
+            operands.pop().unwrap()
+        } else {
+            Expr::Add(operands)
+        };
+
+        if matches!(self.peek(), Some(Token::Name(n)) if n == "if") {
+            self.pos += 1;
+
+            // Skip the condition.
+            while let Some(token) = self.peek() {
+                match token {
+                    Token::Name(n) if n == "else" => break,
+                    Token::Newline
+                    | Token::Punct(',')
+                    | Token::Punct(')')
+                    | Token::Punct(']')
+                    | Token::Punct('}') => {
+                        return Expr::Other(format!("{} if ...", expr.describe()));
+                    }
+                    Token::Punct('(') | Token::Punct('[') | Token::Punct('{') => {
+                        self.pos += 1;
+                        self.skip_bracketed();
+                    }
+                    _ => self.pos += 1,
+                }
+            }
+            self.pos += 1;
+
+            return Expr::Conditional(Box::new(expr), Box::new(self.parse_expr()));
+        }
+
+        // Comprehensions and other operators aren't interpreted.
+        if matches!(self.peek(), Some(Token::Name(n)) if n == "for")
+            || matches!(self.peek(), Some(Token::Punct(c)) if "-*/%|&<>!".contains(*c))
+        {
+            let text = expr.describe();
+            self.skip_expression();
+            return Expr::Other(format!("{} ...", text));
+        }
+
+        expr
+    }
+
+    /// Skip the rest of an expression we don't interpret.
+    fn skip_expression(&mut self) {
+        while let Some(token) = self.peek() {
+            match token {
+                Token::Newline
+                | Token::Punct(',')
+                | Token::Punct(')')
+                | Token::Punct(']')
+                | Token::Punct('}') => break,
+                Token::Punct('(') | Token::Punct('[') | Token::Punct('{') => {
+                    self.pos += 1;
+                    self.skip_bracketed();
+                }
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    fn parse_primary(&mut self) -> Expr {
+        match self.next() {
+            Some(Token::Str(mut s)) => {
+                // Adjacent string literals are concatenated.
+                while let Some(Token::Str(next)) = self.peek().cloned() {
+                    s.push_str(&next);
+                    self.pos += 1;
+                }
+                Expr::Str(s)
+            }
+            Some(Token::Number(n)) => Expr::Number(n),
+            Some(Token::Name(name)) => {
+                let mut name = name;
+                while self.peek() == Some(&Token::Punct('.')) {
+                    self.pos += 1;
+                    match self.next() {
+                        Some(Token::Name(attr)) => {
+                            name.push('.');
+                            name.push_str(&attr);
+                        }
+                        _ => return Expr::Other(name),
+                    }
+                }
+
+                if self.eat('(') {
+                    self.parse_call(name)
+                } else if self.peek() == Some(&Token::Punct('[')) {
+                    self.pos += 1;
+                    self.skip_bracketed();
+                    Expr::Other(format!("{}[...]", name))
+                } else {
+                    match name.as_str() {
+                        "True" => Expr::Bool(true),
+                        "False" => Expr::Bool(false),
+                        "None" => Expr::None,
+                        _ => Expr::Name(name),
+                    }
+                }
+            }
+            Some(Token::Punct('[')) => self.parse_sequence(']'),
+            Some(Token::Punct('(')) => self.parse_sequence(')'),
+            Some(Token::Punct('{')) => self.parse_dict(),
+            Some(Token::Punct('-')) => match self.parse_primary() {
+                Expr::Number(n) => Expr::Number(format!("-{}", n)),
+                other => Expr::Other(format!("-{}", other.describe())),
+            },
+            Some(Token::Punct(c)) => Expr::Other(c.to_string()),
+            Some(Token::Newline) | None => {
+                self.pos -= 1;
+                Expr::Other("".to_string())
+            }
+        }
+    }
+
+    fn parse_sequence(&mut self, close: char) -> Expr {
+        let mut items = vec![];
+
+        loop {
+            if self.eat(close) {
+                break;
+            }
+
+            match self.peek() {
+                Some(Token::Newline) | None => break,
+                _ => {}
+            }
+
+            let start = self.pos;
+            items.push(self.parse_expr());
+
+            if !self.eat(',') && self.peek() != Some(&Token::Punct(close)) {
+                // Something we don't understand. Give up on this sequence.
+                if self.pos == start {
+                    self.pos += 1;
+                }
+                self.skip_bracketed();
+                return Expr::Other(format!(
+                    "{}...{}",
+                    if close == ']' { '[' } else { '(' },
+                    close
+                ));
+            }
+        }
+
+        Expr::List(items)
+    }
+
+    fn parse_dict(&mut self) -> Expr {
+        let mut items = vec![];
+
+        loop {
+            if self.eat('}') {
+                break;
+            }
+
+            match self.peek() {
+                Some(Token::Newline) | None => break,
+                _ => {}
+            }
+
+            let start = self.pos;
+            let key = self.parse_expr();
+
+            if !self.eat(':') {
+                // A set or something we don't understand.
+                if self.pos == start {
+                    self.pos += 1;
+                }
+                self.skip_bracketed();
+                return Expr::Other("{...}".to_string());
+            }
+
+            items.push((key, self.parse_expr()));
+
+            if !self.eat(',') && self.peek() != Some(&Token::Punct('}')) {
+                self.skip_bracketed();
+                return Expr::Other("{...}".to_string());
+            }
+        }
+
+        Expr::Dict(items)
+    }
+
+    fn parse_call(&mut self, func: String) -> Expr {
+        let mut args = vec![];
+        let mut kwargs = vec![];
+
+        loop {
+            if self.eat(')') {
+                break;
+            }
+
+            match self.peek() {
+                Some(Token::Newline) | None => break,
+                _ => {}
+            }
+
+            let start = self.pos;
+
+            if let (Some(Token::Name(name)), Some(Token::Punct('='))) = (
+                self.tokens.get(self.pos).cloned(),
+                self.tokens.get(self.pos + 1).cloned(),
+            ) {
+                if self.tokens.get(self.pos + 2) != Some(&Token::Punct('=')) {
+                    self.pos += 2;
+                    kwargs.push((name, self.parse_expr()));
+                } else {
+                    args.push(self.parse_expr());
+                }
+            } else {
+                args.push(self.parse_expr());
+            }
+
+            if !self.eat(',') && self.peek() != Some(&Token::Punct(')')) {
+                if self.pos == start {
+                    self.pos += 1;
+                }
+                self.skip_bracketed();
+                break;
+            }
+        }
+
+        Expr::Call { func, args, kwargs }
+    }
+}
+
+/// Top-level statements of a source file.
+struct Statements {
+    /// Variable assignments, in order.
+    assignments: Vec<(String, Expr)>,
+
+    /// Calls not assigned to anything.
+    expressions: Vec<Expr>,
+}
+
+fn parse_statements(source: &str) -> Result<Statements> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        pos: 0,
+    };
+
+    let mut assignments = vec![];
+    let mut expressions = vec![];
+
+    while parser.peek().is_some() {
+        match (
+            parser.tokens.get(parser.pos).cloned(),
+            parser.tokens.get(parser.pos + 1).cloned(),
+        ) {
+            (Some(Token::Newline), _) => {
+                parser.pos += 1;
+            }
+            (Some(Token::Name(keyword)), _)
+                if [
+                    "import", "from", "def", "class", "if", "for", "while", "with",
+                ]
+                .contains(&keyword.as_str()) =>
+            {
+                parser.skip_statement();
+            }
+            (Some(Token::Name(name)), Some(Token::Punct('='))) => {
+                parser.pos += 2;
+                let value = parser.parse_expr();
+                assignments.push((name, value));
+                parser.skip_statement();
+            }
+            (Some(Token::Name(name)), Some(Token::Punct('+')))
+                if parser.tokens.get(parser.pos + 2) == Some(&Token::Punct('=')) =>
+            {
+                parser.pos += 3;
+                let value = parser.parse_expr();
+                assignments.push((name.clone(), Expr::Add(vec![Expr::Name(name), value])));
+                parser.skip_statement();
+            }
+            _ => {
+                let start = parser.pos;
+                let value = parser.parse_expr();
+                if matches!(value, Expr::Call { .. }) {
+                    expressions.push(value);
+                }
+                if parser.pos == start {
+                    parser.pos += 1;
+                }
+                parser.skip_statement();
+            }
+        }
+    }
+
+    Ok(Statements {
+        assignments,
+        expressions,
+    })
+}
+
+/// Replace references to variables with their values.
+fn resolve(variables: &HashMap<String, Expr>, value: Expr) -> Expr {
+    match value {
+        Expr::Name(name) => match variables.get(&name) {
+            Some(value) => resolve(variables, value.clone()),
+            None => Expr::Name(name),
+        },
+        Expr::List(items) => Expr::List(
+            items
+                .into_iter()
+                .map(|item| resolve(variables, item))
+                .collect(),
+        ),
+        Expr::Dict(items) => Expr::Dict(
+            items
+                .into_iter()
+                .map(|(k, v)| (resolve(variables, k), resolve(variables, v)))
+                .collect(),
+        ),
+        Expr::Call { func, args, kwargs } => Expr::Call {
+            func,
+            args: args.into_iter().map(|x| resolve(variables, x)).collect(),
+            kwargs: kwargs
+                .into_iter()
+                .map(|(k, v)| (k, resolve(variables, v)))
+                .collect(),
+        },
+        Expr::Add(operands) => operands
+            .into_iter()
+            .map(|x| resolve(variables, x))
+            .reduce(add)
+            .unwrap_or(Expr::None),
+        Expr::Conditional(a, b) => Expr::Conditional(
+            Box::new(resolve(variables, *a)),
+            Box::new(resolve(variables, *b)),
+        ),
+        value => value,
+    }
+}
+
+/// Evaluate `a + b`.
+///
+/// Lists are concatenated with operands we can't interpret kept as list
+/// items, so the interpretable items of e.g. `datas + collect_data_files()`
+/// are preserved.
+fn add(a: Expr, b: Expr) -> Expr {
+    match (a, b) {
+        (Expr::Str(a), Expr::Str(b)) => Expr::Str(a + &b),
+        (Expr::List(mut a), Expr::List(b)) => {
+            a.extend(b);
+            Expr::List(a)
+        }
+        (Expr::List(mut a), b) => {
+            a.push(b);
+            Expr::List(a)
+        }
+        (a, Expr::List(b)) => Expr::List(std::iter::once(a).chain(b).collect()),
+        (a, b) => Expr::Add(vec![a, b]),
+    }
+}
+
+/// Interpret a list of strings.
+///
+/// Entries which aren't strings are skipped with a warning naming `field`.
+pub fn strings(field: &str, value: &Expr, warnings: &mut Vec<String>) -> Vec<String> {
+    match value {
+        Expr::List(items) => items
+            .iter()
+            .filter_map(|item| match item {
+                Expr::Str(s) => Some(s.clone()),
+                _ => {
+                    warnings.push(format!(
+                        "could not interpret {} entry {}",
+                        field,
+                        item.describe()
+                    ));
+                    None
+                }
+            })
+            .collect(),
+        Expr::None => vec![],
+        _ => {
+            warnings.push(format!(
+                "could not interpret {}: {}",
+                field,
+                value.describe()
+            ));
+            vec![]
+        }
+    }
+}
+
+/// A parsed Python source file.
+pub struct SourceFile {
+    /// Calls made at the top level, with variables resolved.
+    calls: Vec<Expr>,
+}
+
+impl SourceFile {
+    /// Parse Python source code.
+    pub fn parse(source: &str) -> Result<Self> {
+        let Statements {
+            assignments,
+            expressions,
+        } = parse_statements(source)?;
+
+        let mut variables = HashMap::new();
+        let mut calls = vec![];
+
+        for (name, value) in assignments {
+            // Resolve now, as the value may refer to the variable's
+            // previous value.
+            let value = resolve(&variables, value);
+            if let Expr::Call { .. } = &value {
+                calls.push(value.clone());
+            }
+            variables.insert(name, value);
+        }
+
+        for value in expressions {
+            calls.push(resolve(&variables, value));
+        }
+
+        Ok(Self { calls })
+    }
+
+    /// Find the first top-level call to a function.
+    ///
+    /// Calls via a module attribute, e.g. `cx_Freeze.setup()`, match too.
+    pub fn find_call(&self, name: &str) -> Option<&Expr> {
+        self.calls.iter().find(|call| match call {
+            Expr::Call { func, .. } => func == name || func.ends_with(&format!(".{}", name)),
+            _ => false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        let source = SourceFile::parse(
+            "import sys\n\
+             base = 'Win32GUI' if sys.platform == 'win32' else None\n\
+             options = {'build_exe': {'packages': ['os'], 'excludes': ['tkinter']}}\n\
+             files = ['a.txt']\n\
+             files += [('b', 'c')]\n\
+             setup(name='app', options=options, executables=[Executable('app.py', base=base)],\n\
+                   data=files)\n",
+        )?;
+
+        let setup = source.find_call("setup").unwrap();
+        assert_eq!(
+            setup.arg(None, &["name"]),
+            Some(&Expr::Str("app".to_string()))
+        );
+
+        assert_eq!(
+            setup.arg(None, &["options"]),
+            Some(&Expr::Dict(vec![(
+                Expr::Str("build_exe".to_string()),
+                Expr::Dict(vec![
+                    (
+                        Expr::Str("packages".to_string()),
+                        Expr::List(vec![Expr::Str("os".to_string())])
+                    ),
+                    (
+                        Expr::Str("excludes".to_string()),
+                        Expr::List(vec![Expr::Str("tkinter".to_string())])
+                    ),
+                ])
+            )]))
+        );
+
+        assert_eq!(
+            setup.arg(None, &["data"]),
+            Some(&Expr::List(vec![
+                Expr::Str("a.txt".to_string()),
+                Expr::List(vec![Expr::Str("b".to_string()), Expr::Str("c".to_string())]),
+            ]))
+        );
+
+        let executables = match setup.arg(None, &["executables"]) {
+            Some(Expr::List(items)) => items.clone(),
+            _ => vec![],
+        };
+        assert_eq!(executables.len(), 1);
+        assert_eq!(
+            executables[0].arg(Some(0), &["script"]),
+            Some(&Expr::Str("app.py".to_string()))
+        );
+        assert_eq!(
+            executables[0].arg(Some(2), &["base"]),
+            Some(&Expr::Conditional(
+                Box::new(Expr::Str("Win32GUI".to_string())),
+                Box::new(Expr::None)
+            ))
+        );
+
+        Ok(())
+    }
+}
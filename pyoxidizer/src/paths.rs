@@ -0,0 +1,190 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Filesystem path handling that holds up on Windows and macOS.
+//!
+//! Long Windows paths are handled by `tugger::paths`. Windows and macOS
+//! filesystems are also case-insensitive by default, so files differing
+//! only by case overwrite each other when materialized there.
+
+use {
+    anyhow::{anyhow, Result},
+    std::{
+        collections::HashMap,
+        path::{Path, PathBuf},
+    },
+    tugger::{file_resource::FileManifest, paths::LONG_PATH_THRESHOLD},
+};
+
+pub use tugger::paths::long_path;
+
+/// Convert a `\\?\` verbatim path to the regular form, using `/` separators.
+fn strip_verbatim_string(path: &str) -> String {
+    if let Some(unc) = path.strip_prefix(r"\\?\UNC\") {
+        format!("//{}", unc.replace('\\', "/"))
+    } else if let Some(local) = path.strip_prefix(r"\\?\") {
+        local.replace('\\', "/")
+    } else {
+        path.replace('\\', "/")
+    }
+}
+
+/// Canonicalize a path, in a form other Windows tools understand.
+///
+/// On Windows, the verbatim prefix `std::fs::canonicalize()` adds is
+/// removed and `\` is replaced with `/`, which is valid. Paths too long
+/// for the regular form stay verbatim.
+pub fn canonicalize_path(path: &Path) -> Result<PathBuf, std::io::Error> {
+    let mut p = path.canonicalize()?;
+
+    if cfg!(windows) {
+        let s = p.display().to_string();
+
+        if s.len() < LONG_PATH_THRESHOLD {
+            p = PathBuf::from(strip_verbatim_string(&s));
+        }
+    }
+
+    Ok(p)
+}
+
+/// Find paths which only differ by case.
+///
+/// Returns pairs of colliding paths, in the order they were seen.
+pub fn case_collisions<'a>(paths: impl IntoIterator<Item = &'a Path>) -> Vec<(PathBuf, PathBuf)> {
+    let mut seen: HashMap<String, &Path> = HashMap::new();
+    let mut res = vec![];
+
+    for path in paths {
+        let key = path.display().to_string().replace('\\', "/").to_lowercase();
+
+        match seen.get(&key) {
+            Some(existing) if *existing != path => {
+                res.push((existing.to_path_buf(), path.to_path_buf()));
+            }
+            Some(_) => {}
+            None => {
+                seen.insert(key, path);
+            }
+        }
+    }
+
+    res
+}
+
+/// Whether filesystems of a target are case-insensitive by default.
+pub fn is_case_insensitive_target(target_triple: &str) -> bool {
+    target_triple.contains("-windows") || target_triple.contains("-apple-")
+}
+
+/// Error if paths only differing by case would overwrite each other on a target.
+///
+/// Only Windows and macOS targets are checked.
+pub fn check_case_collisions<'a>(
+    target_triple: &str,
+    paths: impl IntoIterator<Item = &'a Path>,
+) -> Result<()> {
+    if !is_case_insensitive_target(target_triple) {
+        return Ok(());
+    }
+
+    let collisions = case_collisions(paths);
+
+    if collisions.is_empty() {
+        return Ok(());
+    }
+
+    Err(anyhow!(
+        "files differing only by case would overwrite each other on case-insensitive filesystems (the default on Windows and macOS):\n{}",
+        collisions
+            .iter()
+            .map(|(a, b)| format!("  {} and {}", a.display(), b.display()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    ))
+}
+
+/// Error if files of a manifest would overwrite each other on this machine.
+fn check_host_case_collisions(manifest: &FileManifest) -> Result<()> {
+    check_case_collisions(env!("HOST"), manifest.entries().map(|(p, _)| p.as_path()))
+}
+
+/// Write the files of a manifest to a directory.
+///
+/// Unlike `FileManifest::write_to_path()`, this refuses to write files
+/// which would overwrite each other on the case-insensitive filesystems of
+/// Windows and macOS machines.
+pub fn write_file_manifest(manifest: &FileManifest, dest_dir: &Path) -> Result<()> {
+    check_host_case_collisions(manifest)?;
+
+    manifest.write_to_path(dest_dir)
+}
+
+/// Write the files of a manifest to a directory, replacing its content.
+pub fn replace_file_manifest(manifest: &FileManifest, dest_dir: &Path) -> Result<()> {
+    check_host_case_collisions(manifest)?;
+
+    manifest.replace_path(dest_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, tugger::file_resource::FileContent};
+
+    #[test]
+    fn test_strip_verbatim_string() {
+        assert_eq!(strip_verbatim_string(r"\\?\C:\a\b"), "C:/a/b");
+        assert_eq!(
+            strip_verbatim_string(r"\\?\UNC\server\share\a"),
+            "//server/share/a"
+        );
+        assert_eq!(strip_verbatim_string(r"C:\a"), "C:/a");
+    }
+
+    #[test]
+    fn test_case_collisions() {
+        let paths = vec![
+            PathBuf::from("lib/Foo/__init__.py"),
+            PathBuf::from("lib/foo/__init__.py"),
+            PathBuf::from("lib/bar.py"),
+            PathBuf::from("lib/bar.py"),
+        ];
+
+        assert_eq!(
+            case_collisions(paths.iter().map(|p| p.as_path())),
+            vec![(paths[0].clone(), paths[1].clone())]
+        );
+        for target in &["x86_64-pc-windows-msvc", "x86_64-apple-darwin"] {
+            assert!(check_case_collisions(target, paths[1..].iter().map(|p| p.as_path())).is_ok());
+            assert!(check_case_collisions(target, paths.iter().map(|p| p.as_path())).is_err());
+        }
+        assert!(check_case_collisions(
+            "x86_64-unknown-linux-gnu",
+            paths.iter().map(|p| p.as_path())
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_write_file_manifest() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let content = FileContent {
+            data: b"foo".to_vec(),
+            executable: false,
+        };
+
+        let mut manifest = FileManifest::default();
+        manifest.add_file(Path::new("a/b.py"), &content)?;
+        replace_file_manifest(&manifest, temp_dir.path())?;
+        assert_eq!(std::fs::read(temp_dir.path().join("a/b.py"))?, b"foo");
+
+        manifest.add_file(Path::new("A/B.py"), &content)?;
+        assert_eq!(
+            write_file_manifest(&manifest, temp_dir.path()).is_err(),
+            is_case_insensitive_target(env!("HOST"))
+        );
+
+        Ok(())
+    }
+}
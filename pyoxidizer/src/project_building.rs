@@ -4,14 +4,30 @@
 
 use {
     crate::{
-        environment::{canonicalize_path, MINIMUM_RUST_VERSION},
-        project_layout::initialize_project,
-        py_packaging::binary::{EmbeddedPythonContext, PythonBinaryBuilder},
+        analyze::find_elf_symbols_requiring_glibc,
+        environment::{
+            set_temp_dir_from_env, user_cache_dir, MINIMUM_RUST_VERSION, MOBILE_TARGET_TRIPLES,
+            PYOXIDIZER_VERSION, TEMP_DIR_ENV,
+        },
+        file_lock::{sibling_lock_path, FileLock},
+        paths::canonicalize_path,
+        project_layout::{
+            initialize_library_project, initialize_project, set_new_cargo_toml_default_features,
+            PyembedLocation,
+        },
+        py_packaging::{
+            binary::{EmbeddedPythonContext, PythonBinaryBuilder},
+            config::PackedResourcesEmbedding,
+            windows_runtime::resolve_windows_runtime_dlls,
+        },
         starlark::eval::EvaluationContext,
+        timing::{PhaseTimer, PHASE_CARGO_BUILD},
     },
     anyhow::{anyhow, Context, Result},
     duct::cmd,
-    slog::warn,
+    python_packed_resources::overlay::append_overlay,
+    sha2::{Digest, Sha256},
+    slog::{debug, warn},
     starlark_dialect_build_targets::ResolvedTarget,
     std::{
         collections::{hash_map::RandomState, HashMap},
@@ -91,21 +107,216 @@ pub struct BuiltExecutable {
     pub binary_data: EmbeddedPythonContext,
 }
 
-/// Build an executable embedding Python using an existing Rust project.
+/// Holds results from building a library.
+pub struct BuiltLibrary {
+    /// Base name of the library. e.g. `foo` for `libfoo.so`.
+    pub name: String,
+
+    /// File name and content of the static library, if one was built.
+    pub static_library: Option<(String, Vec<u8>)>,
+
+    /// File name and content of the shared library.
+    pub shared_library: (String, Vec<u8>),
+
+    /// Holds state generated from building.
+    pub binary_data: EmbeddedPythonContext,
+}
+
+/// Resolve the filename of an executable built for a given target triple.
+pub fn executable_filename(bin_name: &str, target: &str) -> String {
+    if target.contains("pc-windows") {
+        format!("{}.exe", bin_name)
+    } else {
+        bin_name.to_string()
+    }
+}
+
+/// Resolve the filenames of the static and shared libraries built for a given target triple.
 ///
-/// The path to the produced executable is returned.
+/// `lib_name` is the name of the Rust library crate.
+pub fn library_filenames(lib_name: &str, target: &str) -> (String, String) {
+    if target.contains("pc-windows") {
+        (format!("{}.lib", lib_name), format!("{}.dll", lib_name))
+    } else if target.contains("-apple-") {
+        (
+            format!("lib{}.a", lib_name),
+            format!("lib{}.dylib", lib_name),
+        )
+    } else {
+        (format!("lib{}.a", lib_name), format!("lib{}.so", lib_name))
+    }
+}
+
+/// Derive a C header declaring the functions exported by a built library.
+pub fn library_c_header(lib_name: &str) -> String {
+    let guard = format!("{}_H", lib_name.to_uppercase());
+
+    format!(
+        "/* Functions exported by a library embedding Python, generated by PyOxidizer. */\n\
+        \n\
+        #ifndef {guard}\n\
+        #define {guard}\n\
+        \n\
+        #ifdef __cplusplus\n\
+        extern \"C\" {{\n\
+        #endif\n\
+        \n\
+        /* Initialize the embedded Python interpreter. Returns 0 on success. */\n\
+        int pyoxidizer_init(void);\n\
+        \n\
+        /* Run the configured Python code, initializing and finalizing the\n\
+         * interpreter as needed. Returns the exit code of the Python code. */\n\
+        int pyoxidizer_run(void);\n\
+        \n\
+        /* Run the configured Python code with the given process arguments,\n\
+         * initializing and finalizing the interpreter. argv[0] is the program\n\
+         * name. Returns the exit code of the Python code. */\n\
+        int pyoxidizer_run_main(int argc, char **argv);\n\
+        \n\
+        /* Evaluate Python source code in the __main__ module, initializing the\n\
+         * interpreter if needed. Returns 0 on success and -1 if an exception\n\
+         * was raised. */\n\
+        int pyoxidizer_eval(const char *code);\n\
+        \n\
+        /* Finalize the interpreter created by pyoxidizer_init(). */\n\
+        void pyoxidizer_finalize(void);\n\
+        \n\
+        #ifdef __cplusplus\n\
+        }}\n\
+        #endif\n\
+        \n\
+        #endif /* {guard} */\n",
+        guard = guard
+    )
+}
+
+/// Describes the Cargo target to build in a Rust project embedding Python.
+enum CargoTarget<'a> {
+    /// A binary with the given name.
+    Bin(&'a str),
+    /// The project's library.
+    Lib,
+}
+
+/// Resolve the Cargo features to build a Rust project embedding Python with.
+fn embedded_python_cargo_features(
+    exe: &dyn PythonBinaryBuilder,
+    embedded_data: &EmbeddedPythonContext,
+) -> Vec<String> {
+    let mut features = vec!["build-mode-prebuilt-artifacts".to_string()];
+
+    // If we have a real libpython, let cpython crate link against it. Otherwise
+    // leave symbols unresolved, as we'll provide them.
+    features.push(
+        if embedded_data.linking_info.libpython_filename.is_some() {
+            "cpython-link-default"
+        } else {
+            "cpython-link-unresolved-static"
+        }
+        .to_string(),
+    );
+
+    if exe.requires_jemalloc() {
+        features.push("jemalloc".to_string());
+    }
+
+    for feature in exe.cargo_features() {
+        if !features.contains(feature) {
+            features.push(feature.clone());
+        }
+    }
+
+    features
+}
+
+/// Resolve the environment variables to build a Rust project embedding Python with.
+///
+/// These are in addition to the environment `cargo` normally inherits.
+fn embedded_python_build_env(
+    exe: &dyn PythonBinaryBuilder,
+    embedded_data: &EmbeddedPythonContext,
+    artifacts_path: &Path,
+) -> Result<Vec<(String, String)>> {
+    // Variables from the config take precedence over the caller's environment
+    // but not over the variables we need to drive the build.
+    let mut envs = exe
+        .build_env()
+        .iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect::<Vec<_>>();
+
+    envs.push((
+        "PYOXIDIZER_ARTIFACT_DIR".to_string(),
+        artifacts_path.display().to_string(),
+    ));
+    envs.push(("PYOXIDIZER_REUSE_ARTIFACTS".to_string(), "1".to_string()));
+
+    // rustc and the C compilers invoked by build scripts derive the minimum
+    // macOS version of produced binaries from this variable.
+    if let Some(deployment_target) = exe.macos_deployment_target() {
+        if embedded_data.target_triple.ends_with("-apple-darwin") {
+            envs.push((
+                "MACOSX_DEPLOYMENT_TARGET".to_string(),
+                deployment_target.to_string(),
+            ));
+        }
+    }
+
+    // Set PYTHON_SYS_EXECUTABLE so python3-sys uses our distribution's Python to configure
+    // itself.
+    // TODO the build environment requiring use of target arch executable prevents
+    // cross-compiling. We should be able to pass in all state without having to
+    // run an executable in a build script.
+    let python_exe_path = exe.target_python_exe_path();
+    envs.push((
+        "PYTHON_SYS_EXECUTABLE".to_string(),
+        python_exe_path.display().to_string(),
+    ));
+
+    // If linking against an existing dynamic library on Windows, add the path to that
+    // library to an environment variable so link.exe can find it.
+    if let Some(libpython_filename) = &embedded_data.linking_info.libpython_filename {
+        if cfg!(windows) {
+            let libpython_dir = libpython_filename
+                .parent()
+                .ok_or_else(|| anyhow!("unable to find parent directory of python DLL"))?;
+
+            envs.push((
+                "LIB".to_string(),
+                if let Ok(lib) = std::env::var("LIB") {
+                    format!("{};{}", lib, libpython_dir.display())
+                } else {
+                    format!("{}", libpython_dir.display())
+                },
+            ));
+        }
+    }
+
+    // static-nobundle link kind requires nightly Rust compiler until
+    // https://github.com/rust-lang/rust/issues/37403 is resolved.
+    if cfg!(windows) {
+        envs.push(("RUSTC_BOOTSTRAP".to_string(), "1".to_string()));
+    }
+
+    Ok(envs)
+}
+
+/// Run `cargo build` for a Rust project embedding Python.
+///
+/// Artifacts needed to embed Python are derived and written to
+/// `artifacts_path` before Cargo is invoked.
 #[allow(clippy::too_many_arguments)]
-pub fn build_executable_with_rust_project(
+fn cargo_build_embedded_python(
     logger: &slog::Logger,
     project_path: &Path,
-    bin_name: &str,
+    cargo_target: CargoTarget,
     exe: &dyn PythonBinaryBuilder,
     build_path: &Path,
     artifacts_path: &Path,
     target: &str,
     opt_level: &str,
     release: bool,
-) -> Result<BuiltExecutable> {
+) -> Result<EmbeddedPythonContext> {
     create_dir_all(&artifacts_path)
         .with_context(|| "creating directory for PyOxidizer build artifacts")?;
 
@@ -124,10 +335,6 @@ pub fn build_executable_with_rust_project(
     warn!(logger, "building with Rust {}", rust_version);
 
     let target_base_path = build_path.join("target");
-    let target_triple_base_path =
-        target_base_path
-            .join(target)
-            .join(if release { "release" } else { "debug" });
 
     let mut args = Vec::new();
     args.push("build");
@@ -138,77 +345,44 @@ pub fn build_executable_with_rust_project(
     args.push("--target-dir");
     args.push(&target_dir);
 
-    args.push("--bin");
-    args.push(bin_name);
+    match cargo_target {
+        CargoTarget::Bin(bin_name) => {
+            args.push("--bin");
+            args.push(bin_name);
+        }
+        CargoTarget::Lib => {
+            args.push("--lib");
+        }
+    }
 
     if release {
         args.push("--release");
     }
 
     args.push("--no-default-features");
-    let mut features = vec!["build-mode-prebuilt-artifacts"];
-
-    // If we have a real libpython, let cpython crate link against it. Otherwise
-    // leave symbols unresolved, as we'll provide them.
-    features.push(if embedded_data.linking_info.libpython_filename.is_some() {
-        "cpython-link-default"
-    } else {
-        "cpython-link-unresolved-static"
-    });
-
-    if exe.requires_jemalloc() {
-        features.push("jemalloc");
-    }
-
-    let features = features.join(" ");
+    let features = embedded_python_cargo_features(exe, &embedded_data).join(" ");
 
     if !features.is_empty() {
         args.push("--features");
         args.push(&features);
     }
 
+    let build_env = embedded_python_build_env(exe, &embedded_data, artifacts_path)?;
+    for (name, value) in &build_env {
+        debug!(logger, "cargo environment: {}={}", name, value);
+    }
+
     let mut envs: HashMap<String, String, RandomState> = HashMap::from_iter(std::env::vars());
-    envs.insert(
-        "PYOXIDIZER_ARTIFACT_DIR".to_string(),
-        artifacts_path.display().to_string(),
-    );
-    envs.insert("PYOXIDIZER_REUSE_ARTIFACTS".to_string(), "1".to_string());
+    envs.extend(build_env);
 
-    // Set PYTHON_SYS_EXECUTABLE so python3-sys uses our distribution's Python to configure
-    // itself.
-    // TODO the build environment requiring use of target arch executable prevents
-    // cross-compiling. We should be able to pass in all state without having to
-    // run an executable in a build script.
-    let python_exe_path = exe.target_python_exe_path();
-    envs.insert(
-        "PYTHON_SYS_EXECUTABLE".to_string(),
-        python_exe_path.display().to_string(),
+    debug!(
+        logger,
+        "running cargo {:?} in {}",
+        args,
+        project_path.display()
     );
 
-    // If linking against an existing dynamic library on Windows, add the path to that
-    // library to an environment variable so link.exe can find it.
-    if let Some(libpython_filename) = &embedded_data.linking_info.libpython_filename {
-        if cfg!(windows) {
-            let libpython_dir = libpython_filename
-                .parent()
-                .ok_or_else(|| anyhow!("unable to find parent directory of python DLL"))?;
-
-            envs.insert(
-                "LIB".to_string(),
-                if let Ok(lib) = std::env::var("LIB") {
-                    format!("{};{}", lib, libpython_dir.display())
-                } else {
-                    format!("{}", libpython_dir.display())
-                },
-            );
-        }
-    }
-
-    // static-nobundle link kind requires nightly Rust compiler until
-    // https://github.com/rust-lang/rust/issues/37403 is resolved.
-    if cfg!(windows) {
-        envs.insert("RUSTC_BOOTSTRAP".to_string(), "1".to_string());
-    }
+    let _timer = PhaseTimer::new(PHASE_CARGO_BUILD);
 
     // TODO force cargo to colorize output under certain circumstances?
     let command = cmd("cargo", &args)
@@ -229,21 +403,57 @@ pub fn build_executable_with_rust_project(
         return Err(anyhow!("cargo build failed"));
     }
 
-    let exe_name = if target.contains("pc-windows") {
-        format!("{}.exe", bin_name)
-    } else {
-        bin_name.to_string()
-    };
+    Ok(embedded_data)
+}
 
-    let exe_path = target_triple_base_path.join(&exe_name);
+/// Build an executable embedding Python using an existing Rust project.
+///
+/// The path to the produced executable is returned.
+#[allow(clippy::too_many_arguments)]
+pub fn build_executable_with_rust_project(
+    logger: &slog::Logger,
+    project_path: &Path,
+    bin_name: &str,
+    exe: &dyn PythonBinaryBuilder,
+    build_path: &Path,
+    artifacts_path: &Path,
+    target: &str,
+    opt_level: &str,
+    release: bool,
+) -> Result<BuiltExecutable> {
+    let embedded_data = cargo_build_embedded_python(
+        logger,
+        project_path,
+        CargoTarget::Bin(bin_name),
+        exe,
+        build_path,
+        artifacts_path,
+        target,
+        opt_level,
+        release,
+    )?;
+
+    let target_triple_base_path =
+        build_path
+            .join("target")
+            .join(target)
+            .join(if release { "release" } else { "debug" });
+
+    let exe_path = target_triple_base_path.join(executable_filename(bin_name, target));
 
     if !exe_path.exists() {
         return Err(anyhow!("{} does not exist", exe_path.display()));
     }
 
-    let exe_data = std::fs::read(&exe_path)?;
+    let mut exe_data = std::fs::read(&exe_path)?;
     let exe_name = exe_path.file_name().unwrap().to_string_lossy().to_string();
 
+    if embedded_data.config.packed_resources_embedding == PackedResourcesEmbedding::Overlay
+        && !embedded_data.resources.is_empty()
+    {
+        append_overlay(&mut exe_data, &embedded_data.resources);
+    }
+
     Ok(BuiltExecutable {
         exe_path: Some(exe_path),
         exe_name,
@@ -252,6 +462,233 @@ pub fn build_executable_with_rust_project(
     })
 }
 
+/// Build a library embedding Python using an existing Rust library project.
+#[allow(clippy::too_many_arguments)]
+pub fn build_library_with_rust_project(
+    logger: &slog::Logger,
+    project_path: &Path,
+    lib_name: &str,
+    exe: &dyn PythonBinaryBuilder,
+    build_path: &Path,
+    artifacts_path: &Path,
+    target: &str,
+    opt_level: &str,
+    release: bool,
+    static_library: bool,
+) -> Result<BuiltLibrary> {
+    let embedded_data = cargo_build_embedded_python(
+        logger,
+        project_path,
+        CargoTarget::Lib,
+        exe,
+        build_path,
+        artifacts_path,
+        target,
+        opt_level,
+        release,
+    )?;
+
+    let target_triple_base_path =
+        build_path
+            .join("target")
+            .join(target)
+            .join(if release { "release" } else { "debug" });
+
+    let (static_filename, shared_filename) = library_filenames(lib_name, target);
+
+    let read_library = |filename: String| -> Result<(String, Vec<u8>)> {
+        let path = target_triple_base_path.join(&filename);
+
+        if !path.exists() {
+            return Err(anyhow!("{} does not exist", path.display()));
+        }
+
+        Ok((filename, std::fs::read(&path)?))
+    };
+
+    Ok(BuiltLibrary {
+        name: lib_name.to_string(),
+        static_library: if static_library {
+            Some(read_library(static_filename)?)
+        } else {
+            None
+        },
+        shared_library: read_library(shared_filename)?,
+        binary_data: embedded_data,
+    })
+}
+
+/// Environment variable that disables sharing build directories between builds.
+pub const NO_BUILD_CACHE_ENV: &str = "PYOXIDIZER_NO_BUILD_CACHE";
+
+/// A Cargo build directory shared by builds of temporary Rust projects.
+///
+/// Compiling pyembed and its dependencies takes minutes. Temporary projects
+/// embedding the same pyembed, Python distribution and features for the same
+/// target reuse a build directory in the user cache keyed by those inputs, so
+/// only the first build pays that cost.
+///
+/// The directory holds an exclusive lock for the lifetime of this instance,
+/// as concurrent builds would otherwise overwrite each other's artifacts.
+struct SharedBuildDir {
+    path: PathBuf,
+    _lock: FileLock,
+}
+
+impl SharedBuildDir {
+    /// Obtain the shared build directory for a binary, if caching is enabled.
+    fn new(
+        logger: &slog::Logger,
+        pyembed_location: &PyembedLocation,
+        exe: &dyn PythonBinaryBuilder,
+        target: &str,
+        release: bool,
+    ) -> Result<Option<Self>> {
+        if env::var(NO_BUILD_CACHE_ENV).is_ok() {
+            return Ok(None);
+        }
+
+        let python_exe_data = std::fs::read(exe.target_python_exe_path())
+            .with_context(|| format!("reading {}", exe.target_python_exe_path().display()))?;
+
+        // Everything that influences how pyembed and its dependencies are
+        // compiled. Cargo tracks the Rust toolchain itself.
+        let mut inputs = vec![
+            format!("pyoxidizer={}", *PYOXIDIZER_VERSION),
+            format!("pyembed={}", pyembed_location.cargo_manifest_fields()),
+            format!("python={}", hex::encode(Sha256::digest(&python_exe_data))),
+            format!("link-mode={:?}", exe.libpython_link_mode()),
+            format!("jemalloc={}", exe.requires_jemalloc()),
+            format!(
+                "macos-deployment-target={:?}",
+                exe.macos_deployment_target()
+            ),
+            format!("target={}", target),
+            format!("release={}", release),
+        ];
+        inputs.extend(
+            exe.cargo_features()
+                .iter()
+                .map(|f| format!("feature={}", f)),
+        );
+        inputs.extend(
+            exe.build_env()
+                .iter()
+                .map(|(k, v)| format!("env={}={}", k, v)),
+        );
+
+        let key = hex::encode(Sha256::digest(inputs.join("\n").as_bytes()));
+        let path = user_cache_dir()?.join("rust-builds").join(&key[0..16]);
+        create_dir_all(&path).with_context(|| format!("creating {}", path.display()))?;
+
+        let lock = FileLock::acquire(logger, &path.join("build-lock"))?;
+
+        warn!(logger, "using shared build directory {}", path.display());
+
+        Ok(Some(Self { path, _lock: lock }))
+    }
+}
+
+/// Verify a built executable and its shared objects run with glibc `floor`.
+///
+/// Fails with a report of offending symbols per file otherwise.
+fn verify_glibc_floor(build: &BuiltExecutable, floor: &str) -> Result<()> {
+    let binaries = std::iter::once((build.exe_name.clone(), &build.exe_data)).chain(
+        build
+            .binary_data
+            .extra_files
+            .entries()
+            .map(|(path, content)| (path.display().to_string(), &content.data)),
+    );
+
+    let mut report = vec![];
+
+    for (name, data) in binaries {
+        if !data.starts_with(b"\x7fELF") {
+            continue;
+        }
+
+        let symbols = find_elf_symbols_requiring_glibc(data, floor)
+            .with_context(|| format!("analyzing {}", name))?;
+
+        if !symbols.is_empty() {
+            report.push(format!("{}: {}", name, symbols.join(", ")));
+        }
+    }
+
+    if report.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "binaries require a glibc newer than {}:\n  {}",
+            floor,
+            report.join("\n  ")
+        ))
+    }
+}
+
+/// Number of packages listed when a size budget is exceeded.
+const SIZE_REPORT_PACKAGES: usize = 20;
+
+/// Format a size in bytes as megabytes.
+fn format_mb(size: u64) -> String {
+    format!("{:.1} MB", size as f64 / (1024.0 * 1024.0))
+}
+
+/// Verify a built executable and its installed files fit in `max_size_mb`.
+///
+/// Fails with a breakdown of the largest packages otherwise.
+fn verify_size_budget(build: &BuiltExecutable, max_size_mb: u64) -> Result<()> {
+    let total = build.exe_data.len() as u64
+        + build
+            .binary_data
+            .extra_files
+            .entries()
+            .map(|(_, content)| content.data.len() as u64)
+            .sum::<u64>();
+
+    if total <= max_size_mb * 1024 * 1024 {
+        return Ok(());
+    }
+
+    let mut packages = build
+        .binary_data
+        .package_sizes
+        .iter()
+        .map(|(name, size)| (*size, name.as_str()))
+        .collect::<Vec<_>>();
+    packages.sort_by(|a, b| b.cmp(a));
+
+    let mut report = packages
+        .iter()
+        .take(SIZE_REPORT_PACKAGES)
+        .map(|(size, name)| format!("{}: {}", name, format_mb(*size)))
+        .collect::<Vec<_>>();
+
+    if packages.len() > SIZE_REPORT_PACKAGES {
+        let rest = &packages[SIZE_REPORT_PACKAGES..];
+        report.push(format!(
+            "{} smaller packages: {}",
+            rest.len(),
+            format_mb(rest.iter().map(|(size, _)| size).sum())
+        ));
+    }
+
+    let packages_total = packages.iter().map(|(size, _)| size).sum::<u64>();
+    report.push(format!(
+        "interpreter and other files: {}",
+        format_mb(total.saturating_sub(packages_total))
+    ));
+
+    Err(anyhow!(
+        "{} and its files are {}, exceeding max_size_mb of {}:\n  {}",
+        build.exe_name,
+        format_mb(total),
+        max_size_mb,
+        report.join("\n  ")
+    ))
+}
+
 /// Build a Python executable using a temporary Rust project.
 ///
 /// Returns the binary data constituting the built executable.
@@ -263,6 +700,13 @@ pub fn build_python_executable(
     opt_level: &str,
     release: bool,
 ) -> Result<BuiltExecutable> {
+    if MOBILE_TARGET_TRIPLES.contains(&target) {
+        return Err(anyhow!(
+            "executables cannot be built for {}; use PythonExecutable.to_library() to build a library instead",
+            target
+        ));
+    }
+
     let env = crate::environment::resolve_environment()?;
     let pyembed_location = env.as_pyembed_location();
 
@@ -270,8 +714,13 @@ pub fn build_python_executable(
 
     // Directory needs to have name of project.
     let project_path = temp_dir.path().join(bin_name);
-    let build_path = temp_dir.path().join("build");
-    let artifacts_path = temp_dir.path().join("artifacts");
+
+    let shared_build_dir = SharedBuildDir::new(logger, &pyembed_location, exe, target, release)?;
+    let build_path = match &shared_build_dir {
+        Some(shared) => shared.path.clone(),
+        None => temp_dir.path().join("build"),
+    };
+    let artifacts_path = build_path.join("artifacts");
 
     initialize_project(
         &project_path,
@@ -279,6 +728,8 @@ pub fn build_python_executable(
         None,
         &[],
         exe.windows_subsystem(),
+        exe.windows_service_name().as_deref(),
+        exe.version().as_deref(),
     )?;
 
     let mut build = build_executable_with_rust_project(
@@ -296,9 +747,222 @@ pub fn build_python_executable(
     // Blank out the path since it is in the temporary directory.
     build.exe_path = None;
 
+    if target.contains("-windows-msvc") {
+        let mut requirements = build.binary_data.windows_runtime_requirements.clone();
+        requirements.add_binary(&build.exe_data)?;
+
+        let runtime_dlls = resolve_windows_runtime_dlls(
+            logger,
+            exe.windows_runtime_dlls_mode(),
+            target,
+            &requirements,
+            None,
+        )?;
+        build.binary_data.extra_files.add_manifest(&runtime_dlls)?;
+    }
+
+    if let Some(floor) = exe.linux_minimum_glibc_version() {
+        if target.contains("-linux-gnu") {
+            verify_glibc_floor(&build, floor)?;
+        }
+    }
+
+    if let Some(max_size_mb) = exe.max_size_mb() {
+        verify_size_budget(&build, max_size_mb)?;
+    }
+
     Ok(build)
 }
 
+/// Name of the directory in a generated Rust project holding build artifacts.
+pub const GENERATED_PROJECT_ARTIFACTS_DIR: &str = "pyoxidizer-artifacts";
+
+/// Write the Rust project used to build an executable to a persistent location.
+///
+/// The Rust project is only created if `project_path` does not exist. The
+/// build artifacts in its `pyoxidizer-artifacts` directory are always
+/// rewritten. So calling this again after the configuration changes
+/// re-syncs the embedded Python state without clobbering edits to the
+/// project's source files.
+///
+/// As with `cargo init`, the project and its binary are named after the
+/// final component of `project_path`.
+///
+/// Returns the environment variables `cargo build` must be run with.
+pub fn generate_rust_project(
+    logger: &slog::Logger,
+    exe: &dyn PythonBinaryBuilder,
+    project_path: &Path,
+    opt_level: &str,
+) -> Result<Vec<(String, String)>> {
+    let embedded_data = exe.to_embedded_python_context(logger, opt_level)?;
+
+    if project_path.exists() {
+        warn!(
+            logger,
+            "{} exists; only refreshing {}",
+            project_path.display(),
+            GENERATED_PROJECT_ARTIFACTS_DIR
+        );
+    } else {
+        let env = crate::environment::resolve_environment()?;
+
+        initialize_project(
+            project_path,
+            &env.as_pyembed_location(),
+            None,
+            &[],
+            exe.windows_subsystem(),
+            exe.windows_service_name().as_deref(),
+            exe.version().as_deref(),
+        )?;
+
+        // The project is built from the artifacts we write, not by evaluating
+        // a config file. So the default one written above would be misleading.
+        std::fs::remove_file(project_path.join("pyoxidizer.bzl"))?;
+
+        set_new_cargo_toml_default_features(
+            &project_path.join("Cargo.toml"),
+            &embedded_python_cargo_features(exe, &embedded_data),
+        )?;
+    }
+
+    let artifacts_path = canonicalize_path(project_path)?.join(GENERATED_PROJECT_ARTIFACTS_DIR);
+    create_dir_all(&artifacts_path)?;
+    embedded_data.write_files(&artifacts_path)?;
+
+    embedded_python_build_env(exe, &embedded_data, &artifacts_path)
+}
+
+/// Build a library embedding Python using a temporary Rust project.
+///
+/// The library exports C functions to initialize and run the interpreter.
+/// A shared library is always produced. A static library is also produced
+/// if `static_library` is true.
+pub fn build_python_library(
+    logger: &slog::Logger,
+    name: &str,
+    exe: &dyn PythonBinaryBuilder,
+    target: &str,
+    opt_level: &str,
+    release: bool,
+    static_library: bool,
+) -> Result<BuiltLibrary> {
+    let env = crate::environment::resolve_environment()?;
+    let pyembed_location = env.as_pyembed_location();
+
+    let temp_dir = tempdir::TempDir::new("pyoxidizer")?;
+
+    // Cargo derives the library name from the project directory, normalizing
+    // dashes to underscores.
+    let lib_name = name.replace('-', "_");
+
+    let project_path = temp_dir.path().join(&lib_name);
+
+    let shared_build_dir = SharedBuildDir::new(logger, &pyembed_location, exe, target, release)?;
+    let build_path = match &shared_build_dir {
+        Some(shared) => shared.path.clone(),
+        None => temp_dir.path().join("build"),
+    };
+    let artifacts_path = build_path.join("artifacts");
+
+    initialize_library_project(&project_path, &pyembed_location, static_library)?;
+
+    build_library_with_rust_project(
+        logger,
+        &project_path,
+        &lib_name,
+        exe,
+        &build_path,
+        &artifacts_path,
+        target,
+        opt_level,
+        release,
+        static_library,
+    )
+}
+
+/// Derive Python code that invokes a post build test entry point.
+///
+/// The entry point is either a module name, which is imported and run as
+/// `__main__`, or `module:function`, in which case the function is called
+/// and its return value is used as the exit code.
+pub fn post_build_test_code(entry_point: &str) -> Result<String> {
+    let (module, function) = match entry_point.find(':') {
+        Some(pos) => (&entry_point[0..pos], Some(&entry_point[pos + 1..])),
+        None => (entry_point, None),
+    };
+
+    let is_identifier = |s: &str| {
+        !s.is_empty()
+            && !s.starts_with(|c: char| c.is_ascii_digit())
+            && s.chars().all(|c| c.is_alphanumeric() || c == '_')
+    };
+
+    let function_valid = match function {
+        Some(function) => is_identifier(function),
+        None => true,
+    };
+
+    if !module.split('.').all(is_identifier) || !function_valid {
+        return Err(anyhow!(
+            "invalid post build test entry point: {}; expected module or module:function",
+            entry_point
+        ));
+    }
+
+    Ok(if let Some(function) = function {
+        format!(
+            "import sys; from {} import {}; sys.exit({}())",
+            module, function, function
+        )
+    } else {
+        format!(
+            "import runpy; runpy.run_module('{}', run_name='__main__')",
+            module
+        )
+    })
+}
+
+/// Run the post build test of a built executable.
+///
+/// The executable is invoked with `-c` to run the test entry point. This
+/// requires the executable's interpreter configuration to parse command
+/// line arguments.
+pub fn run_post_build_test(
+    logger: &slog::Logger,
+    exe_path: &Path,
+    entry_point: &str,
+) -> Result<()> {
+    let code = post_build_test_code(entry_point)?;
+
+    warn!(
+        logger,
+        "running post build test {} with {}",
+        entry_point,
+        exe_path.display()
+    );
+
+    let status = std::process::Command::new(exe_path)
+        .arg("-c")
+        .arg(&code)
+        .current_dir(exe_path.parent().unwrap_or_else(|| Path::new(".")))
+        .status()
+        .with_context(|| format!("running {}", exe_path.display()))?;
+
+    if status.success() {
+        warn!(logger, "post build test {} passed", entry_point);
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "post build test {} failed: {} exited with {}",
+            entry_point,
+            exe_path.display(),
+            status
+        ))
+    }
+}
+
 /// Build artifacts needed by the pyembed crate.
 ///
 /// This will resolve `resolve_target` or the default then build it. Built
@@ -316,6 +980,10 @@ pub fn build_pyembed_artifacts(
 
     let artifacts_path = canonicalize_path(artifacts_path)?;
 
+    // Build scripts of several crates or profiles can share an artifacts
+    // directory via PYOXIDIZER_ARTIFACT_DIR.
+    let _lock = FileLock::acquire(logger, &sibling_lock_path(&artifacts_path))?;
+
     if artifacts_current(logger, config_path, &artifacts_path) {
         return Ok(());
     }
@@ -400,6 +1068,9 @@ pub fn run_from_build(
     println!("cargo:rerun-if-changed={}", build_script);
 
     println!("cargo:rerun-if-env-changed=PYOXIDIZER_CONFIG");
+    println!("cargo:rerun-if-env-changed={}", TEMP_DIR_ENV);
+
+    set_temp_dir_from_env()?;
 
     // TODO use these variables?
     //let host = env::var("HOST").expect("HOST not defined");
@@ -566,4 +1237,71 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_mobile_executable() -> Result<()> {
+        let logger = get_logger()?;
+        let options = StandalonePythonExecutableBuilderOptions::default();
+        let pre_built = options.new_builder()?;
+
+        assert!(build_python_executable(
+            &logger,
+            "myapp",
+            pre_built.as_ref(),
+            "aarch64-apple-ios",
+            "0",
+            false,
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_library_filenames() {
+        assert_eq!(
+            library_filenames("myapp", "aarch64-linux-android"),
+            ("libmyapp.a".to_string(), "libmyapp.so".to_string())
+        );
+        assert_eq!(
+            library_filenames("myapp", "aarch64-apple-ios"),
+            ("libmyapp.a".to_string(), "libmyapp.dylib".to_string())
+        );
+        assert_eq!(
+            library_filenames("myapp", "x86_64-pc-windows-msvc"),
+            ("myapp.lib".to_string(), "myapp.dll".to_string())
+        );
+    }
+
+    #[test]
+    fn test_library_c_header() {
+        let header = library_c_header("myapp");
+
+        assert!(header.contains("#ifndef MYAPP_H\n"));
+        assert!(header.contains("int pyoxidizer_init(void);\n"));
+        assert!(header.contains("int pyoxidizer_run(void);\n"));
+        assert!(header.contains("int pyoxidizer_run_main(int argc, char **argv);\n"));
+        assert!(header.contains("int pyoxidizer_eval(const char *code);\n"));
+        assert!(header.contains("void pyoxidizer_finalize(void);\n"));
+    }
+
+    #[test]
+    fn test_post_build_test_code() -> Result<()> {
+        assert_eq!(
+            post_build_test_code("myapp.selftest:main")?,
+            "import sys; from myapp.selftest import main; sys.exit(main())"
+        );
+        assert_eq!(
+            post_build_test_code("myapp.selftest")?,
+            "import runpy; runpy.run_module('myapp.selftest', run_name='__main__')"
+        );
+
+        assert!(post_build_test_code("").is_err());
+        assert!(post_build_test_code("myapp:").is_err());
+        assert!(post_build_test_code("myapp..selftest").is_err());
+        assert!(post_build_test_code("myapp; import os").is_err());
+        assert!(post_build_test_code("1app:main").is_err());
+
+        Ok(())
+    }
 }
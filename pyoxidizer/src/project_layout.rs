@@ -5,7 +5,12 @@
 //! Handle file layout of PyOxidizer projects.
 
 use {
-    crate::environment::{PyOxidizerSource, BUILD_GIT_COMMIT, PYOXIDIZER_VERSION},
+    crate::{
+        environment::{
+            windows_version_fields, PyOxidizerSource, BUILD_GIT_COMMIT, PYOXIDIZER_VERSION,
+        },
+        migrate::MigratedConfig,
+    },
     anyhow::{anyhow, Result},
     handlebars::Handlebars,
     lazy_static::lazy_static,
@@ -40,6 +45,9 @@ lazy_static! {
                 include_str!("templates/new-cargo-config"),
             )
             .unwrap();
+        handlebars
+            .register_template_string("new-lib.rs", include_str!("templates/new-lib.rs"))
+            .unwrap();
         handlebars
             .register_template_string("new-main.rs", include_str!("templates/new-main.rs"))
             .unwrap();
@@ -49,6 +57,12 @@ lazy_static! {
                 include_str!("templates/new-pyoxidizer.bzl"),
             )
             .unwrap();
+        handlebars
+            .register_template_string(
+                "migrated-pyoxidizer.bzl",
+                include_str!("templates/migrated-pyoxidizer.bzl"),
+            )
+            .unwrap();
 
         handlebars
     };
@@ -72,6 +86,8 @@ struct TemplateData {
 
     python_distributions: Vec<PythonDistribution>,
     program_name: Option<String>,
+    version: Option<String>,
+    windows_version: Option<String>,
     code: Option<String>,
     pip_install_simple: Vec<String>,
 }
@@ -87,6 +103,8 @@ impl TemplateData {
             pyoxidizer_git_tag: None,
             python_distributions: Vec::new(),
             program_name: None,
+            version: None,
+            windows_version: None,
             code: None,
             pip_install_simple: Vec::new(),
         }
@@ -164,12 +182,22 @@ pub fn write_new_build_rs(path: &Path, program_name: &str) -> Result<()> {
 /// Write a new main.rs file that runs the embedded Python interpreter.
 ///
 /// `windows_subsystem` is the value of the `windows_subsystem` Rust attribute.
-pub fn write_new_main_rs(path: &Path, windows_subsystem: &str) -> Result<()> {
+///
+/// If `windows_service_name` is set, the program can also install itself and
+/// run as a Windows service with that name.
+pub fn write_new_main_rs(
+    path: &Path,
+    windows_subsystem: &str,
+    windows_service_name: Option<&str>,
+) -> Result<()> {
     let mut data: BTreeMap<String, String> = BTreeMap::new();
     data.insert(
         "windows_subsystem".to_string(),
         windows_subsystem.to_string(),
     );
+    if let Some(name) = windows_service_name {
+        data.insert("windows_service_name".to_string(), name.to_string());
+    }
     let t = HANDLEBARS.render("new-main.rs", &data)?;
 
     println!("writing {}", path.to_str().unwrap());
@@ -179,6 +207,17 @@ pub fn write_new_main_rs(path: &Path, windows_subsystem: &str) -> Result<()> {
     Ok(())
 }
 
+/// Write a new lib.rs file exporting C functions to run the embedded Python interpreter.
+pub fn write_new_lib_rs(path: &Path) -> Result<()> {
+    let data: BTreeMap<String, String> = BTreeMap::new();
+    let t = HANDLEBARS.render("new-lib.rs", &data)?;
+
+    println!("writing {}", path.display());
+    std::fs::write(path, t)?;
+
+    Ok(())
+}
+
 /// Writes default PyOxidizer config files into a project directory.
 pub fn write_new_pyoxidizer_config_file(
     project_dir: &Path,
@@ -209,6 +248,30 @@ pub fn write_new_pyoxidizer_config_file(
     Ok(())
 }
 
+#[derive(Serialize)]
+struct MigratedTemplateData<'a> {
+    pyoxidizer_version: &'a str,
+    pyoxidizer_commit: &'a str,
+    #[serde(flatten)]
+    config: &'a MigratedConfig,
+}
+
+/// Writes a PyOxidizer config file migrated from another packaging tool.
+pub fn write_migrated_config_file(path: &Path, config: &MigratedConfig) -> Result<()> {
+    let data = MigratedTemplateData {
+        pyoxidizer_version: &PYOXIDIZER_VERSION,
+        pyoxidizer_commit: BUILD_GIT_COMMIT,
+        config,
+    };
+
+    let t = HANDLEBARS.render("migrated-pyoxidizer.bzl", &data)?;
+
+    println!("writing {}", path.display());
+    std::fs::write(path, t)?;
+
+    Ok(())
+}
+
 /// Write an application manifest and corresponding resource file.
 ///
 /// This is used on Windows to allow the built executable to use long paths.
@@ -217,9 +280,27 @@ pub fn write_new_pyoxidizer_config_file(
 /// might be able to remove this someday. It isn't clear if you get long
 /// paths support if using that version of the Windows SDK or if you have
 /// to be running on a modern Windows version as well.
-pub fn write_application_manifest(project_dir: &Path, program_name: &str) -> Result<()> {
+///
+/// If `version` is defined, the resource script also defines a version
+/// resource. Its numeric fields are derived with
+/// `environment::windows_version_fields()`.
+pub fn write_application_manifest(
+    project_dir: &Path,
+    program_name: &str,
+    version: Option<&str>,
+) -> Result<()> {
     let mut data = TemplateData::new();
     data.program_name = Some(program_name.to_string());
+    if let Some(version) = version {
+        data.version = Some(version.to_string());
+        data.windows_version = Some(
+            windows_version_fields(version)?
+                .iter()
+                .map(|field| field.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+    }
 
     let manifest_path = project_dir.join(format!("{}.exe.manifest", program_name));
     let manifest_data = HANDLEBARS.render("exe.manifest", &data)?;
@@ -292,7 +373,8 @@ impl PyembedLocation {
     pub fn cargo_manifest_fields(&self) -> String {
         match self {
             Self::Version(version) => format!("version = \"{}\"", version),
-            Self::Path(path) => format!("path = \"{}\"", path.display()),
+            // A literal string, as verbatim Windows paths contain backslashes.
+            Self::Path(path) => format!("path = '{}'", path.display()),
             Self::Git(url, commit) => format!("git = \"{}\", rev = \"{}\"", url, commit),
         }
     }
@@ -348,6 +430,76 @@ pub fn update_new_cargo_toml(path: &Path, pyembed_location: &PyembedLocation) ->
     Ok(())
 }
 
+/// Replace the default features in the Cargo.toml of a new Rust project.
+pub fn set_new_cargo_toml_default_features(path: &Path, features: &[String]) -> Result<()> {
+    let content = std::fs::read_to_string(path)?;
+
+    let old = "default = [\"build-mode-pyoxidizer-exe\"]\n";
+    if !content.contains(old) {
+        return Err(anyhow!("could not find default features in Cargo.toml"));
+    }
+
+    let new = format!(
+        "default = [{}]\n",
+        features
+            .iter()
+            .map(|f| format!("\"{}\"", f))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    std::fs::write(path, content.replace(old, &new))?;
+
+    Ok(())
+}
+
+/// Update the Cargo.toml of a new Rust library project to produce C libraries.
+///
+/// A shared library is always built. A static library is also built if
+/// `static_library` is true. This also adds the `once_cell` dependency
+/// holding the interpreter.
+pub fn update_new_cargo_toml_library(path: &Path, static_library: bool) -> Result<()> {
+    let content = std::fs::read_to_string(path)?;
+
+    let build_dependencies = "\n[build-dependencies]\n";
+    if !content.contains(build_dependencies) {
+        return Err(anyhow!("could not find build dependencies in Cargo.toml"));
+    }
+    let mut content = content.replacen(
+        build_dependencies,
+        &format!("once_cell = \"1.4\"\n{}", build_dependencies),
+        1,
+    );
+
+    content.push('\n');
+    content.push_str("[lib]\n");
+    if static_library {
+        content.push_str("crate-type = [\"cdylib\", \"staticlib\"]\n");
+    } else {
+        content.push_str("crate-type = [\"cdylib\"]\n");
+    }
+
+    std::fs::write(path, content)?;
+
+    Ok(())
+}
+
+/// Update the Cargo.toml of a new project to build a Windows service.
+///
+/// This adds the `winapi` dependency used to talk to the service control
+/// manager.
+pub fn update_new_cargo_toml_windows_service(path: &Path) -> Result<()> {
+    let mut content = std::fs::read_to_string(path)?;
+
+    content.push('\n');
+    content.push_str("[target.'cfg(windows)'.dependencies]\n");
+    content.push_str("winapi = { version = \"0.3\", features = [\"minwindef\", \"winerror\", \"winnt\", \"winsvc\"] }\n");
+
+    std::fs::write(path, content)?;
+
+    Ok(())
+}
+
 /// Initialize a new Rust project using PyOxidizer.
 ///
 /// The created binary application will have the name of the final
@@ -355,12 +507,20 @@ pub fn update_new_cargo_toml(path: &Path, pyembed_location: &PyembedLocation) ->
 ///
 /// `windows_subsystem` is the value of the `windows_subsystem` compiler
 /// attribute.
+///
+/// `windows_service_name` is the name of the Windows service the
+/// application can run as, if any.
+///
+/// `version` is the version of the application embedded in its Windows
+/// version resource, if any.
 pub fn initialize_project(
     project_path: &Path,
     pyembed_location: &PyembedLocation,
     code: Option<&str>,
     pip_install: &[&str],
     windows_subsystem: &str,
+    windows_service_name: Option<&str>,
+    version: Option<&str>,
 ) -> Result<()> {
     let status = std::process::Command::new("cargo")
         .arg("init")
@@ -376,11 +536,233 @@ pub fn initialize_project(
     let name = path.iter().last().unwrap().to_str().unwrap();
     add_pyoxidizer(&path, true)?;
     update_new_cargo_toml(&path.join("Cargo.toml"), pyembed_location)?;
+    if windows_service_name.is_some() {
+        update_new_cargo_toml_windows_service(&path.join("Cargo.toml"))?;
+    }
     write_new_cargo_config(&path)?;
     write_new_build_rs(&path.join("build.rs"), name)?;
-    write_new_main_rs(&path.join("src").join("main.rs"), windows_subsystem)?;
+    write_new_main_rs(
+        &path.join("src").join("main.rs"),
+        windows_subsystem,
+        windows_service_name,
+    )?;
     write_new_pyoxidizer_config_file(&path, &name, code, pip_install)?;
-    write_application_manifest(&path, &name)?;
+    write_application_manifest(&path, &name, version)?;
+
+    Ok(())
+}
+
+/// Initialize a new Rust library project using PyOxidizer.
+///
+/// The created library will have the name of the final path component and
+/// export C functions to initialize and run the embedded Python interpreter.
+///
+/// `static_library` controls whether a static library is built in addition
+/// to the shared library.
+pub fn initialize_library_project(
+    project_path: &Path,
+    pyembed_location: &PyembedLocation,
+    static_library: bool,
+) -> Result<()> {
+    let status = std::process::Command::new("cargo")
+        .arg("init")
+        .arg("--lib")
+        .arg(project_path)
+        .status()?;
+
+    if !status.success() {
+        return Err(anyhow!("cargo init failed"));
+    }
+
+    let path = PathBuf::from(project_path);
+    let name = path.iter().next_back().unwrap().to_str().unwrap();
+    add_pyoxidizer(&path, true)?;
+    update_new_cargo_toml(&path.join("Cargo.toml"), pyembed_location)?;
+    update_new_cargo_toml_library(&path.join("Cargo.toml"), static_library)?;
+    write_new_cargo_config(&path)?;
+    write_new_build_rs(&path.join("build.rs"), name)?;
+    write_new_lib_rs(&path.join("src").join("lib.rs"))?;
+    write_application_manifest(&path, name, None)?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_new_main_rs_windows_service() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let path = temp_dir.path().join("main.rs");
+
+        write_new_main_rs(&path, "console", None)?;
+        let main_rs = std::fs::read_to_string(&path)?;
+        assert!(!main_rs.contains("windows_service"));
+
+        write_new_main_rs(&path, "console", Some("myapp"))?;
+        let main_rs = std::fs::read_to_string(&path)?;
+        assert!(main_rs.contains("windows_service::handle_command_line()"));
+        assert!(main_rs.contains("const SERVICE_NAME: &str = \"myapp\";"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_application_manifest_version() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let rc_path = temp_dir.path().join("myapp-manifest.rc");
+
+        write_application_manifest(temp_dir.path(), "myapp", None)?;
+        let rc = std::fs::read_to_string(&rc_path)?;
+        assert!(rc.contains("1 RT_MANIFEST myapp.exe.manifest"));
+        assert!(!rc.contains("VERSIONINFO"));
+
+        write_application_manifest(temp_dir.path(), "myapp", Some("1.2.0+3.g1a2b3c4"))?;
+        let rc = std::fs::read_to_string(&rc_path)?;
+        assert!(rc.contains("FILEVERSION 1,2,0,3\n"));
+        assert!(rc.contains("PRODUCTVERSION 1,2,0,3\n"));
+        assert!(rc.contains("VALUE \"ProductVersion\", \"1.2.0+3.g1a2b3c4\""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_migrated_config_file() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let path = temp_dir.path().join("pyoxidizer.bzl");
+
+        let mut config = MigratedConfig {
+            source_filename: "myapp.spec".to_string(),
+            source_kind: "PyInstaller spec file".to_string(),
+            command: "migrate-pyinstaller".to_string(),
+            sys_frozen: true,
+            excludes: vec!["\"tkinter\"".to_string()],
+            datas: vec![crate::migrate::DataGlob {
+                include: "\"assets/**/*\"".to_string(),
+                strip_prefix: "CWD".to_string(),
+            }],
+            notes: vec!["binaries are not migrated: [...]".to_string()],
+            ..Default::default()
+        };
+        config.add_executables(vec![crate::migrate::MigratedExecutable {
+            program_name: "myapp".to_string(),
+            windows_subsystem: Some("windows".to_string()),
+            main_module: Some("\"app\"".to_string()),
+            package_root: Some("CWD".to_string()),
+            packages: vec!["\"app\"".to_string()],
+            ..Default::default()
+        }]);
+
+        write_migrated_config_file(&path, &config)?;
+        let bzl = std::fs::read_to_string(&path)?;
+
+        assert!(bzl.contains("# * binaries are not migrated: [...]\n"));
+        assert!(bzl.contains("    policy.register_resource_callback(exclude_resources)\n"));
+        assert!(bzl.contains("    python_config.sys_frozen = True\n"));
+        assert!(!bzl.contains("sys_meipass"));
+        assert!(bzl.contains("    python_config.run_module = \"app\"\n"));
+        assert!(bzl.contains("    exe.windows_subsystem = \"windows\"\n"));
+        assert!(bzl.contains("    files.add_manifest(glob([\"assets/**/*\"], strip_prefix=CWD))\n"));
+        assert!(!bzl.contains("pip_install"));
+        assert!(bzl.contains(
+            "register_target(\"install\", make_install, depends=[\"exe\"], default=True)\n"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_migrated_config_file_multiple_executables() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let path = temp_dir.path().join("pyoxidizer.bzl");
+
+        let mut config = MigratedConfig {
+            source_filename: "pyproject.toml".to_string(),
+            source_kind: "Briefcase project file".to_string(),
+            command: "migrate-briefcase".to_string(),
+            ..Default::default()
+        };
+        config.add_executables(vec![
+            crate::migrate::MigratedExecutable {
+                program_name: "app".to_string(),
+                pip_install: vec![crate::migrate::PipInstall {
+                    condition: Some("\"linux\" in BUILD_TARGET_TRIPLE".to_string()),
+                    args: "\"toga-gtk\"".to_string(),
+                }],
+                ..Default::default()
+            },
+            crate::migrate::MigratedExecutable {
+                program_name: "tool".to_string(),
+                ..Default::default()
+            },
+        ]);
+
+        write_migrated_config_file(&path, &config)?;
+        let bzl = std::fs::read_to_string(&path)?;
+
+        assert!(bzl.contains("def make_exe_app(dist):\n"));
+        assert!(bzl.contains("def make_exe_tool(dist):\n"));
+        assert!(!bzl.contains("sys_frozen"));
+        assert!(bzl.contains(
+            "    if \"linux\" in BUILD_TARGET_TRIPLE:\n        \
+             exe.add_python_resources(exe.pip_install([\"toga-gtk\"]))\n"
+        ));
+        assert!(bzl.contains(
+            "def make_install(exe_app, exe_tool):\n    files = FileManifest()\n\n    \
+             files.add_python_resource(\".\", exe_app)\n    \
+             files.add_python_resource(\".\", exe_tool)\n"
+        ));
+        assert!(bzl.contains(
+            "register_target(\"exe_tool\", make_exe_tool, depends=[\"dist\"])\n\
+             register_target(\"resources\", make_embedded_resources, depends=[\"exe_app\"], default_build_script=True)\n\
+             register_target(\"install\", make_install, depends=[\"exe_app\", \"exe_tool\"], default=True)\n"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_new_cargo_toml_library() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let path = temp_dir.path().join("Cargo.toml");
+
+        std::fs::write(
+            &path,
+            "[dependencies]\npyembed = \"0.1\"\n\n[build-dependencies]\nembed-resource = \"1.3\"\n",
+        )?;
+        update_new_cargo_toml_library(&path, true)?;
+        assert_eq!(
+            std::fs::read_to_string(&path)?,
+            "[dependencies]\npyembed = \"0.1\"\nonce_cell = \"1.4\"\n\n[build-dependencies]\nembed-resource = \"1.3\"\n\n[lib]\ncrate-type = [\"cdylib\", \"staticlib\"]\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_new_cargo_toml_default_features() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let path = temp_dir.path().join("Cargo.toml");
+
+        std::fs::write(
+            &path,
+            "[features]\ndefault = [\"build-mode-pyoxidizer-exe\"]\njemalloc = []\n",
+        )?;
+        set_new_cargo_toml_default_features(
+            &path,
+            &[
+                "build-mode-prebuilt-artifacts".to_string(),
+                "jemalloc".to_string(),
+            ],
+        )?;
+        assert_eq!(
+            std::fs::read_to_string(&path)?,
+            "[features]\ndefault = [\"build-mode-prebuilt-artifacts\", \"jemalloc\"]\njemalloc = []\n"
+        );
+
+        assert!(set_new_cargo_toml_default_features(&path, &[]).is_err());
+
+        Ok(())
+    }
+}
@@ -6,26 +6,55 @@
 
 use {
     crate::{
+        artifact_store::{parse_size, ArtifactStore, NO_ARTIFACT_STORE_ENV},
+        dist_manifest::{
+            describe_target_artifacts, DistManifest, DistPlatform, DIST_MANIFEST_FORMAT_VERSION,
+        },
+        environment::{
+            check_disk_space, BUILD_DIR_REQUIRED_SPACE, BUILD_GIT_COMMIT, PYOXIDIZER_VERSION,
+            TEMP_DIR_REQUIRED_SPACE,
+        },
+        logging::{logged_environment, start_build_log},
+        migrate::{
+            briefcase::{self, BriefcaseProject},
+            cx_freeze::{self, CxFreezeSetup},
+            pyinstaller::{self, PyInstallerSpec},
+            MigratedConfig,
+        },
+        paths::long_path,
         project_building::find_pyoxidizer_config_file_env,
-        project_layout::{initialize_project, write_new_pyoxidizer_config_file},
+        project_layout::{
+            initialize_project, write_migrated_config_file, write_new_pyoxidizer_config_file,
+        },
         py_packaging::{
             distribution::{
-                default_distribution_location, resolve_distribution, DistributionFlavor,
+                default_distribution_location, find_extracted_distributions, resolve_distribution,
+                shared_distributions_dir, DistributionFlavor, NO_DISTRIBUTION_CACHE_ENV,
             },
             standalone_distribution::StandaloneDistribution,
         },
-        starlark::eval::EvaluationContext,
+        starlark::{
+            deprecation::migrate_source,
+            error_codes::{find_error_code, ERROR_CODES},
+            eval::EvaluationContext,
+            testing::find_test_functions,
+        },
+        timing::{format_timings, phase_timings, reset_timings, PhaseTiming},
     },
-    anyhow::{anyhow, Result},
+    anyhow::{anyhow, Context, Result},
     python_packaging::{
         filesystem_scanning::find_python_resources,
         resource::{DataLocation, PythonResource},
         wheel::WheelArchive,
     },
+    serde::Serialize,
+    slog::{debug, warn},
     std::{
+        collections::HashMap,
         fs::create_dir_all,
         io::{Cursor, Read},
         path::Path,
+        time::Instant,
     },
 };
 
@@ -51,6 +80,21 @@ pub fn resolve_target(target: Option<&str>) -> Result<String> {
     }
 }
 
+/// Start writing the build log to the profile directory of the build.
+///
+/// Should be called after the config file is evaluated, as it can change the
+/// build directory. Records logged before are written to the log first.
+fn start_profile_build_log(logger: &slog::Logger, context: &EvaluationContext) -> Result<()> {
+    let layout = context.build_layout()?;
+    start_build_log(&layout.build_log_path())?;
+
+    if let Err(e) = layout.link_legacy_build_log_path() {
+        debug!(logger, "{:#}", e);
+    }
+
+    Ok(())
+}
+
 pub fn list_targets(logger: &slog::Logger, project_path: &Path) -> Result<()> {
     let config_path = find_pyoxidizer_config_file_env(logger, project_path).ok_or_else(|| {
         anyhow!(
@@ -89,17 +133,330 @@ pub fn list_targets(logger: &slog::Logger, project_path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Fail early if there likely isn't enough disk space to build.
+///
+/// The build directory isn't checked if `dry_run` is set, as nothing is
+/// compiled.
+fn check_build_disk_space(context: &EvaluationContext, dry_run: bool) -> Result<()> {
+    check_disk_space(
+        &std::env::temp_dir(),
+        TEMP_DIR_REQUIRED_SPACE,
+        "the temporary directory",
+    )?;
+
+    if !dry_run {
+        check_disk_space(
+            &context.build_path()?,
+            BUILD_DIR_REQUIRED_SPACE,
+            "the build directory",
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Evaluate a config file, checking for free disk space around it.
+///
+/// Evaluation can already install packages and extract distributions, so
+/// space is checked before. The config file can change the build directory,
+/// so it is checked again if it did. `set_temp_dir()` checks the temporary
+/// directory it selects itself.
+fn evaluate_config_file(
+    context: &mut EvaluationContext,
+    config_path: &Path,
+    dry_run: bool,
+) -> Result<()> {
+    check_build_disk_space(context, dry_run)?;
+    let build_path = context.build_path()?;
+
+    context.evaluate_file(config_path)?;
+
+    if !dry_run && context.build_path()? != build_path {
+        check_disk_space(
+            &context.build_path()?,
+            BUILD_DIR_REQUIRED_SPACE,
+            "the build directory",
+        )?;
+    }
+
+    Ok(())
+}
+
 /// Build a PyOxidizer enabled project.
 ///
 /// This is a glorified wrapper around `cargo build`. Our goal is to get the
 /// output from repackaging to give the user something for debugging.
+///
+/// If `dry_run` is set, targets are evaluated but nothing is compiled.
+/// Instead, the resources and artifacts each target would produce are printed.
+///
+/// `trace_resources` is a glob pattern of resource names whose packaging
+/// decisions should be logged.
+///
+/// If `update_snapshots` is set, resources snapshots that are missing or
+/// differ are written instead of failing the build.
+///
+/// Time spent in each build phase is printed at the end. If `timings_path`
+/// is set, it is also written there as JSON.
+///
+/// If `app` is set, the config file is a workspace config file and the
+/// targets of the application it registers under that name are built.
+#[allow(clippy::too_many_arguments)]
 pub fn build(
     logger: &slog::Logger,
     project_path: &Path,
+    app: Option<&str>,
     target_triple: Option<&str>,
     resolve_targets: Option<Vec<String>>,
     release: bool,
     verbose: bool,
+    dry_run: bool,
+    trace_resources: Option<&str>,
+    update_snapshots: bool,
+    timings_path: Option<&Path>,
+) -> Result<()> {
+    let start = Instant::now();
+    reset_timings();
+
+    let config_path = find_pyoxidizer_config_file_env(logger, project_path).ok_or_else(|| {
+        anyhow!(
+            "unable to find PyOxidizer config file at {}",
+            project_path.display()
+        )
+    })?;
+    let target_triple = resolve_target(target_triple)?;
+
+    let (mut context, config_path) = if let Some(app) = app {
+        // Targets of the workspace config file itself aren't resolved.
+        let mut workspace = EvaluationContext::new(
+            logger,
+            &config_path,
+            &target_triple,
+            release,
+            verbose,
+            Some(vec![]),
+            false,
+        )?;
+        workspace.evaluate_file(&config_path)?;
+
+        workspace.app_context(
+            logger,
+            app,
+            &target_triple,
+            release,
+            verbose,
+            resolve_targets,
+        )?
+    } else {
+        let context = EvaluationContext::new(
+            logger,
+            &config_path,
+            &target_triple,
+            release,
+            verbose,
+            resolve_targets,
+            false,
+        )?;
+
+        (context, config_path)
+    };
+
+    debug!(
+        logger,
+        "pyoxidizer {} (commit {})", *PYOXIDIZER_VERSION, BUILD_GIT_COMMIT
+    );
+    debug!(
+        logger,
+        "command line: {:?}",
+        std::env::args().collect::<Vec<_>>()
+    );
+    debug!(logger, "current directory: {:?}", std::env::current_dir()?);
+    debug!(logger, "configuration file: {}", config_path.display());
+    debug!(
+        logger,
+        "target triple: {}; release: {}; dry run: {}", target_triple, release, dry_run
+    );
+    for (name, value) in logged_environment() {
+        debug!(logger, "environment: {}={}", name, value);
+    }
+
+    context.set_update_snapshots(update_snapshots)?;
+
+    if let Err(e) = build_targets(logger, &mut context, &config_path, dry_run, trace_resources) {
+        debug!(logger, "build failed: {:?}", e);
+        return Err(e);
+    }
+
+    let report = TimingsReport {
+        total_seconds: start.elapsed().as_secs_f64(),
+        phases: phase_timings(),
+    };
+
+    debug!(
+        logger,
+        "build timings:\n{}total: {:.2}s",
+        format_timings(&report.phases),
+        report.total_seconds
+    );
+
+    if !report.phases.is_empty() {
+        println!();
+        println!("build timings:");
+        print!("{}", format_timings(&report.phases));
+        println!("total: {:.2}s", report.total_seconds);
+    }
+
+    if let Some(path) = timings_path {
+        std::fs::write(path, serde_json::to_vec_pretty(&report)?)
+            .with_context(|| format!("writing {}", path.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Evaluate a configuration file and build the targets to resolve.
+fn build_targets(
+    logger: &slog::Logger,
+    context: &mut EvaluationContext,
+    config_path: &Path,
+    dry_run: bool,
+    trace_resources: Option<&str>,
+) -> Result<()> {
+    context.set_dry_run(dry_run)?;
+    context.set_trace_resources(trace_resources)?;
+    let evaluated = evaluate_config_file(context, config_path, dry_run);
+    // Records are buffered until the log is started. Start it even if
+    // evaluation failed so the failure is recorded, in the build directory
+    // selected up to that point.
+    let logged = if dry_run {
+        Ok(())
+    } else {
+        start_profile_build_log(logger, context)
+    };
+    evaluated?;
+    logged?;
+
+    for target in context.targets_to_resolve()? {
+        if dry_run {
+            context.dry_run_resolved_target(&target)?;
+        } else {
+            context.build_resolved_target(&target)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// JSON document describing where time went during `pyoxidizer build`.
+#[derive(Serialize)]
+struct TimingsReport {
+    /// Wall time of the whole build, in seconds.
+    total_seconds: f64,
+
+    /// Time spent in each build phase.
+    ///
+    /// Phases don't account for all of the total. Evaluating the config
+    /// file and copying build outputs are not broken out.
+    phases: Vec<PhaseTiming>,
+}
+
+/// Build targets for each target triple of a release and describe their artifacts.
+///
+/// The manifest is written to `output_path` as JSON, or printed if it isn't
+/// set. Targets that can't be built for a target triple on this machine
+/// make this fail, so release matrices spanning operating systems generate
+/// a manifest on each and merge the `platforms` of the results.
+///
+/// `Release` and `ReleasePatch` targets are skipped: nothing is uploaded.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_dist_manifest(
+    logger: &slog::Logger,
+    project_path: &Path,
+    target_triples: &[&str],
+    resolve_targets: Option<Vec<String>>,
+    release: bool,
+    verbose: bool,
+    output_path: Option<&Path>,
+) -> Result<()> {
+    let config_path = find_pyoxidizer_config_file_env(logger, project_path).ok_or_else(|| {
+        anyhow!(
+            "unable to find PyOxidizer config file at {}",
+            project_path.display()
+        )
+    })?;
+
+    let target_triples = if target_triples.is_empty() {
+        vec![default_target()?]
+    } else {
+        target_triples.iter().map(|t| t.to_string()).collect()
+    };
+
+    let mut manifest = DistManifest {
+        format_version: DIST_MANIFEST_FORMAT_VERSION,
+        pyoxidizer_version: PYOXIDIZER_VERSION.to_string(),
+        release,
+        platforms: vec![],
+    };
+
+    for target_triple in target_triples {
+        let mut context = EvaluationContext::new(
+            logger,
+            &config_path,
+            &target_triple,
+            release,
+            verbose,
+            resolve_targets.clone(),
+            false,
+        )?;
+        evaluate_config_file(&mut context, &config_path, false)?;
+
+        let mut artifacts = vec![];
+        for target in context.targets_to_resolve()? {
+            // Releases upload artifacts instead of producing them.
+            if context.target_publishes(&target)? {
+                warn!(logger, "skipping release target {}", target);
+                continue;
+            }
+
+            let resolved = context.build_resolved_target(&target)?;
+
+            artifacts.extend(describe_target_artifacts(
+                &target,
+                &resolved.output_path,
+                &context.target_artifacts(&target)?,
+            )?);
+        }
+
+        manifest.platforms.push(DistPlatform {
+            target_triple,
+            artifacts,
+        });
+    }
+
+    let data = serde_json::to_string_pretty(&manifest)?;
+
+    if let Some(path) = output_path {
+        std::fs::write(path, data).with_context(|| format!("writing {}", path.display()))?;
+    } else {
+        println!("{}", data);
+    }
+
+    Ok(())
+}
+
+/// Write the Rust project for an executable target to a directory.
+///
+/// See `project_building::generate_rust_project()` for how an existing
+/// directory is handled.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_rust_project(
+    logger: &slog::Logger,
+    project_path: &Path,
+    target_triple: Option<&str>,
+    target: Option<&str>,
+    release: bool,
+    verbose: bool,
+    out_path: &Path,
 ) -> Result<()> {
     let config_path = find_pyoxidizer_config_file_env(logger, project_path).ok_or_else(|| {
         anyhow!(
@@ -115,19 +472,138 @@ pub fn build(
         &target_triple,
         release,
         verbose,
-        resolve_targets,
+        target.map(|t| vec![t.to_string()]),
         false,
     )?;
 
     context.evaluate_file(&config_path)?;
 
-    for target in context.targets_to_resolve()? {
-        context.build_resolved_target(&target)?;
+    let target = match target {
+        Some(t) => t.to_string(),
+        None => context
+            .default_target()?
+            .ok_or_else(|| anyhow!("unable to determine target to generate"))?,
+    };
+
+    let envs = context.generate_rust_project(&target, out_path)?;
+
+    println!();
+    println!(
+        "The Rust project for {} has been written to {}",
+        target,
+        out_path.display()
+    );
+    println!();
+    println!("It can be built by doing the following:");
+    println!();
+    println!("  $ cd {}", out_path.display());
+    for (key, value) in &envs {
+        println!("  $ export {}='{}'", key, value);
     }
+    println!("  $ cargo build --target {}", target_triple);
+    println!();
+    println!("Run this command again to re-sync the embedded Python state after");
+    println!("changing the configuration file. Only the pyoxidizer-artifacts");
+    println!("directory is rewritten; edits to other files are preserved.");
 
     Ok(())
 }
 
+/// Name of file holding tests for a configuration file.
+///
+/// This file lives next to the configuration file.
+pub const CONFIG_TEST_FILENAME: &str = "pyoxidizer_test.bzl";
+
+/// Run `test_*` functions defined in a PyOxidizer configuration file.
+///
+/// Functions are discovered in the configuration file and in a sibling
+/// `pyoxidizer_test.bzl` file, if present. Each test function is called in
+/// a freshly evaluated environment in which no targets are resolved, build
+/// artifacts are written to a temporary directory, and `assert_*()`
+/// functions are available.
+pub fn test_config(
+    logger: &slog::Logger,
+    project_path: &Path,
+    target_triple: Option<&str>,
+    verbose: bool,
+) -> Result<()> {
+    let config_path = find_pyoxidizer_config_file_env(logger, project_path).ok_or_else(|| {
+        anyhow!(
+            "unable to find PyOxidizer config file at {}",
+            project_path.display()
+        )
+    })?;
+    let target_triple = resolve_target(target_triple)?;
+
+    let mut paths = vec![config_path.clone()];
+    if let Some(parent) = config_path.parent() {
+        let test_path = parent.join(CONFIG_TEST_FILENAME);
+        if test_path.exists() {
+            paths.push(test_path);
+        }
+    }
+
+    let mut tests = vec![];
+    for path in &paths {
+        let source = std::fs::read_to_string(path)?;
+        tests.extend(find_test_functions(&source));
+    }
+
+    if tests.is_empty() {
+        println!("(no tests defined)");
+        return Ok(());
+    }
+
+    let temp_dir = tempdir::TempDir::new("pyoxidizer-test-config")?;
+
+    let mut failures = vec![];
+
+    for test in &tests {
+        let mut context = EvaluationContext::new(
+            logger,
+            &config_path,
+            &target_triple,
+            false,
+            verbose,
+            Some(Vec::new()),
+            false,
+        )?;
+        context.register_testing_functions();
+        context.set_build_path(&temp_dir.path().join(test))?;
+
+        let res = paths
+            .iter()
+            .try_for_each(|path| context.evaluate_file(path))
+            .and_then(|_| context.evaluate_code(&format!("{}()", test)).map(|_| ()));
+
+        match res {
+            Ok(()) => {
+                println!("{} ... ok", test);
+            }
+            Err(e) => {
+                println!("{} ... FAILED", test);
+                failures.push((test, e));
+            }
+        }
+    }
+
+    for (test, e) in &failures {
+        println!("\n---- {} ----\n{}", test, e);
+    }
+
+    println!(
+        "\n{} passed; {} failed",
+        tests.len() - failures.len(),
+        failures.len()
+    );
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("{} config tests failed", failures.len()))
+    }
+}
+
 pub fn run(
     logger: &slog::Logger,
     project_path: &Path,
@@ -160,7 +636,13 @@ pub fn run(
         resolve_targets,
         false,
     )?;
-    context.evaluate_file(&config_path)?;
+    let evaluated = evaluate_config_file(&mut context, &config_path, false);
+    if let Err(e) = &evaluated {
+        debug!(logger, "build failed: {:?}", e);
+    }
+    let logged = start_profile_build_log(logger, &context);
+    evaluated?;
+    logged?;
 
     context.run_target(target)
 }
@@ -198,7 +680,7 @@ pub fn find_resources(
         if path.is_dir() {
             println!("scanning directory {}", path.display());
             for resource in find_python_resources(
-                path,
+                &long_path(path),
                 dist.cache_tag(),
                 &dist.python_module_suffixes()?,
                 emit_files,
@@ -278,6 +760,9 @@ fn print_resource(r: &PythonResource) {
         PythonResource::File(f) => println!(
             "File {{ path: {}, is_executable: {} }}", f.path.display(), f.is_executable
         ),
+        PythonResource::GettextCatalog(c) => println!(
+            "GettextCatalog {{ package: {}, name: {}, locale: {}, domain: {} }}", c.leaf_package, c.relative_name, c.locale, c.domain
+        ),
     }
 }
 
@@ -318,12 +803,300 @@ pub fn init_config_file(
     Ok(())
 }
 
+/// Write a `pyoxidizer.bzl` next to a file of another packaging tool.
+///
+/// `migrate` receives the file's name and directory and derives the
+/// configuration.
+fn write_migrated_config<F>(source_path: &Path, migrate: F) -> Result<()>
+where
+    F: FnOnce(&str, &Path) -> MigratedConfig,
+{
+    let source_dir = source_path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let source_filename = source_path
+        .file_name()
+        .ok_or_else(|| anyhow!("{} is not a file", source_path.display()))?
+        .to_string_lossy();
+
+    let config_path = source_dir.join("pyoxidizer.bzl");
+    if config_path.exists() {
+        return Err(anyhow!("{} already exists", config_path.display()));
+    }
+
+    let config = migrate(&source_filename, source_dir);
+    write_migrated_config_file(&config_path, &config)?;
+
+    println!();
+    if !config.notes.is_empty() || !config.unmigrated_datas.is_empty() {
+        println!("Some settings could not be migrated and need attention:");
+        println!();
+        for note in &config.notes {
+            println!("  * {}", note);
+        }
+        for data in &config.unmigrated_datas {
+            println!("  * data files {}", data);
+        }
+        println!();
+    }
+    println!("Review the generated configuration file, then build and run the");
+    println!("application:");
+    println!();
+    println!("  $ cd {}", source_dir.display());
+    if config.executables.len() == 1 {
+        println!("  $ pyoxidizer run");
+    } else {
+        println!("  $ pyoxidizer build");
+        for exe in &config.executables {
+            println!("  $ pyoxidizer run --target {}", exe.target);
+        }
+    }
+
+    Ok(())
+}
+
+/// Create a PyOxidizer configuration file from a PyInstaller spec file.
+pub fn migrate_pyinstaller(spec_path: &Path) -> Result<()> {
+    let spec = PyInstallerSpec::from_path(spec_path)?;
+
+    write_migrated_config(spec_path, |filename, dir| {
+        pyinstaller::migrate(&spec, filename, dir)
+    })
+}
+
+/// Create a PyOxidizer configuration file from a cx_Freeze `setup.py`.
+pub fn migrate_cx_freeze(setup_path: &Path) -> Result<()> {
+    let setup = CxFreezeSetup::from_path(setup_path)?;
+
+    write_migrated_config(setup_path, |filename, dir| {
+        cx_freeze::migrate(&setup, filename, dir)
+    })
+}
+
+/// Create a PyOxidizer configuration file from a Briefcase `pyproject.toml`.
+pub fn migrate_briefcase(pyproject_path: &Path) -> Result<()> {
+    let project = BriefcaseProject::from_path(pyproject_path)?;
+
+    write_migrated_config(pyproject_path, |filename, _| {
+        briefcase::migrate(&project, filename)
+    })
+}
+
+/// Print the Python distributions extracted into the user cache.
+pub fn show_cache() -> Result<()> {
+    match shared_distributions_dir()? {
+        Some(dir) => {
+            let distributions = find_extracted_distributions(&dir)?;
+
+            println!("Extracted Python distributions in {}", dir.display());
+            println!();
+
+            for dist in &distributions {
+                let name = dist
+                    .path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+
+                match &dist.summary {
+                    Ok(summary) => println!(
+                        "{}  {}  {}  {}  {}  {:.1} MB",
+                        name,
+                        summary.python_version,
+                        summary.target_triple,
+                        summary.libpython_link_mode,
+                        summary.optimizations,
+                        dist.size as f64 / (1024.0 * 1024.0)
+                    ),
+                    Err(e) => println!(
+                        "{}  (unable to read distribution: {})  {:.1} MB",
+                        name,
+                        e,
+                        dist.size as f64 / (1024.0 * 1024.0)
+                    ),
+                }
+            }
+
+            if !distributions.is_empty() {
+                println!();
+            }
+
+            println!(
+                "{} distributions, {:.1} MB",
+                distributions.len(),
+                distributions.iter().map(|d| d.size).sum::<u64>() as f64 / (1024.0 * 1024.0)
+            );
+        }
+        None => println!(
+            "sharing extracted distributions is disabled by {}",
+            NO_DISTRIBUTION_CACHE_ENV
+        ),
+    }
+
+    println!();
+
+    match ArtifactStore::open_default()? {
+        Some(store) => {
+            let usage = store.usage()?;
+
+            println!(
+                "Artifact store in {}: {} objects, {:.1} MB",
+                store.root().display(),
+                usage.objects,
+                usage.size as f64 / (1024.0 * 1024.0)
+            );
+        }
+        None => println!(
+            "the artifact store is disabled by {}",
+            NO_ARTIFACT_STORE_ENV
+        ),
+    }
+
+    Ok(())
+}
+
+/// Remove least recently used artifacts until the artifact store fits in a size.
+pub fn cache_gc(logger: &slog::Logger, max_size: &str) -> Result<()> {
+    let max_size = parse_size(max_size)?;
+
+    let store = match ArtifactStore::open_default()? {
+        Some(store) => store,
+        None => {
+            println!(
+                "the artifact store is disabled by {}",
+                NO_ARTIFACT_STORE_ENV
+            );
+            return Ok(());
+        }
+    };
+
+    let stats = store.gc(logger, max_size)?;
+
+    println!(
+        "removed {} objects, {:.1} MB; {} objects, {:.1} MB remain",
+        stats.removed_objects,
+        stats.removed_size as f64 / (1024.0 * 1024.0),
+        stats.remaining.objects,
+        stats.remaining.size as f64 / (1024.0 * 1024.0)
+    );
+
+    Ok(())
+}
+
+/// Print a description of the Starlark API.
+pub fn dump_starlark_api(logger: &slog::Logger, format: &str) -> Result<()> {
+    // The configuration file isn't evaluated; it only anchors `CWD`.
+    let config_path = std::env::current_dir()?.join("pyoxidizer.bzl");
+
+    let context = EvaluationContext::new(
+        logger,
+        &config_path,
+        &default_target()?,
+        false,
+        false,
+        None,
+        false,
+    )?;
+
+    let api = context.describe_api()?;
+
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(&api)?),
+        _ => return Err(anyhow!("unsupported format: {}", format)),
+    }
+
+    Ok(())
+}
+
+/// Rewrite uses of deprecated functionality in a configuration file.
+pub fn migrate_config(logger: &slog::Logger, project_path: &Path, dry_run: bool) -> Result<()> {
+    let config_path = find_pyoxidizer_config_file_env(logger, project_path).ok_or_else(|| {
+        anyhow!(
+            "unable to find PyOxidizer config file at {}",
+            project_path.display()
+        )
+    })?;
+
+    let source = std::fs::read_to_string(&config_path)
+        .with_context(|| format!("reading {}", config_path.display()))?;
+    let migration = migrate_source(&source);
+
+    for (line, deprecation) in &migration.rewritten {
+        println!(
+            "{}:{}: {}",
+            config_path.display(),
+            line,
+            deprecation.message()
+        );
+    }
+    for (line, deprecation) in &migration.manual {
+        println!(
+            "{}:{}: {} (migrate by hand)",
+            config_path.display(),
+            line,
+            deprecation.message()
+        );
+    }
+
+    if migration.rewritten.is_empty() {
+        if migration.manual.is_empty() {
+            println!("{} uses no deprecated functionality", config_path.display());
+        }
+    } else if dry_run {
+        println!(
+            "(dry run) would rewrite {} uses in {}",
+            migration.rewritten.len(),
+            config_path.display()
+        );
+    } else {
+        std::fs::write(&config_path, &migration.source)
+            .with_context(|| format!("writing {}", config_path.display()))?;
+        println!(
+            "rewrote {} uses in {}",
+            migration.rewritten.len(),
+            config_path.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Print the explanation of a configuration file error code.
+pub fn explain_error_code(code: &str) -> Result<()> {
+    let entry = find_error_code(code).ok_or_else(|| {
+        anyhow!(
+            "unknown error code {}; known codes: {}",
+            code,
+            ERROR_CODES
+                .iter()
+                .map(|e| e.code)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    })?;
+
+    println!("{}: {}", entry.code, entry.summary);
+    println!();
+    print!("{}", entry.explanation);
+
+    Ok(())
+}
+
 /// Initialize a new Rust project with PyOxidizer support.
 pub fn init_rust_project(project_path: &Path) -> Result<()> {
     let env = crate::environment::resolve_environment()?;
     let pyembed_location = env.as_pyembed_location();
 
-    initialize_project(project_path, &pyembed_location, None, &[], "console")?;
+    initialize_project(
+        project_path,
+        &pyembed_location,
+        None,
+        &[],
+        "console",
+        None,
+        None,
+    )?;
     println!();
     println!(
         "A new Rust binary application has been created in {}",
@@ -358,22 +1131,23 @@ pub fn python_distribution_extract(dist_path: &str, dest_path: &str) -> Result<(
     Ok(())
 }
 
-pub fn python_distribution_info(dist_path: &str) -> Result<()> {
+pub fn python_distribution_info(logger: &slog::Logger, dist_path: &str) -> Result<()> {
     let fh = std::fs::File::open(Path::new(dist_path))?;
     let reader = std::io::BufReader::new(fh);
 
     let temp_dir = tempdir::TempDir::new("python-distribution")?;
     let temp_dir_path = temp_dir.path();
 
-    let dist = StandaloneDistribution::from_tar_zst(reader, temp_dir_path)?;
+    let dist = StandaloneDistribution::from_tar_zst(logger, reader, temp_dir_path)?;
 
     println!("High-Level Metadata");
     println!("===================");
     println!();
-    println!("Target triple: {}", dist.target_triple);
-    println!("Tag:           {}", dist.python_tag);
-    println!("Platform tag:  {}", dist.python_platform_tag);
-    println!("Version:       {}", dist.version);
+    println!("Target triple:  {}", dist.target_triple);
+    println!("Tag:            {}", dist.python_tag);
+    println!("Platform tag:   {}", dist.python_platform_tag);
+    println!("Version:        {}", dist.version);
+    println!("ABI tag:        {}", dist.capabilities.abi_tag());
     println!();
 
     println!("Extension Modules");
@@ -431,14 +1205,14 @@ pub fn python_distribution_info(dist_path: &str) -> Result<()> {
     Ok(())
 }
 
-pub fn python_distribution_licenses(path: &str) -> Result<()> {
+pub fn python_distribution_licenses(logger: &slog::Logger, path: &str) -> Result<()> {
     let fh = std::fs::File::open(Path::new(path))?;
     let reader = std::io::BufReader::new(fh);
 
     let temp_dir = tempdir::TempDir::new("python-distribution")?;
     let temp_dir_path = temp_dir.path();
 
-    let dist = StandaloneDistribution::from_tar_zst(reader, temp_dir_path)?;
+    let dist = StandaloneDistribution::from_tar_zst(logger, reader, temp_dir_path)?;
 
     println!(
         "Python Distribution Licenses: {}",
@@ -506,3 +1280,168 @@ pub fn python_distribution_licenses(path: &str) -> Result<()> {
 
     Ok(())
 }
+
+/// Strictly validate a file containing packed resources data.
+pub fn verify_resources(path: &Path) -> Result<()> {
+    let data = std::fs::read(path)?;
+
+    match python_packed_resources::parser::verify_resources(&data) {
+        Ok(count) => {
+            println!("{}: {} resources; OK", path.display(), count);
+            Ok(())
+        }
+        Err(e) => Err(anyhow!("{}: {}", path.display(), e)),
+    }
+}
+
+/// Read the names of resources in packed resources data, in index order.
+///
+/// Fails if the resources aren't sorted by name.
+fn read_resource_names(path: &Path, data: &[u8]) -> Result<Vec<String>> {
+    let resources = python_packed_resources::parser::load_resources(data)
+        .map_err(|e| anyhow!("{}: {}", path.display(), e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+
+    python_packed_resources::writer::check_resources_order(&resources)
+        .with_context(|| format!("{}", path.display()))?;
+
+    Ok(resources.iter().map(|r| r.name.to_string()).collect())
+}
+
+/// Describe how resource indices differ between two packed resources data.
+///
+/// Returns lines for added and removed resources and resources whose index
+/// changed.
+fn describe_resource_index_changes(old: &[String], new: &[String]) -> Vec<String> {
+    let old_indices = old
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.as_str(), i))
+        .collect::<HashMap<_, _>>();
+    let new_indices = new
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.as_str(), i))
+        .collect::<HashMap<_, _>>();
+
+    let mut lines = vec![];
+
+    for (name, old_index) in old.iter().zip(0..) {
+        match new_indices.get(name.as_str()) {
+            None => lines.push(format!("- {} (index {})", name, old_index)),
+            Some(new_index) if *new_index != old_index => {
+                lines.push(format!("~ {} (index {} -> {})", name, old_index, new_index))
+            }
+            Some(_) => {}
+        }
+    }
+
+    for (name, new_index) in new.iter().zip(0..) {
+        if !old_indices.contains_key(name.as_str()) {
+            lines.push(format!("+ {} (index {})", name, new_index));
+        }
+    }
+
+    lines
+}
+
+/// Compare the resource indices of two files containing packed resources data.
+pub fn compare_resources(old: &Path, new: &Path) -> Result<()> {
+    let old_data = std::fs::read(old).with_context(|| format!("reading {}", old.display()))?;
+    let new_data = std::fs::read(new).with_context(|| format!("reading {}", new.display()))?;
+
+    let old_names = read_resource_names(old, &old_data)?;
+    let new_names = read_resource_names(new, &new_data)?;
+
+    let changes = describe_resource_index_changes(&old_names, &new_names);
+
+    for line in &changes {
+        println!("{}", line);
+    }
+
+    if changes.is_empty() {
+        println!("{} resources; indices identical", new_names.len());
+    } else {
+        println!(
+            "{} resources; {} changes to indices",
+            new_names.len(),
+            changes.len()
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::testutil::*};
+
+    #[test]
+    fn test_describe_resource_index_changes() {
+        let names = |names: &[&str]| names.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert!(
+            describe_resource_index_changes(&names(&["a", "b"]), &names(&["a", "b"])).is_empty()
+        );
+        assert_eq!(
+            describe_resource_index_changes(&names(&["a", "b", "d"]), &names(&["a", "c", "d"])),
+            vec!["- b (index 1)".to_string(), "+ c (index 1)".to_string()]
+        );
+        assert_eq!(
+            describe_resource_index_changes(&names(&["b", "c"]), &names(&["a", "b", "c"])),
+            vec![
+                "~ b (index 0 -> 1)".to_string(),
+                "~ c (index 1 -> 2)".to_string(),
+                "+ a (index 0)".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generate_dist_manifest_skips_release() -> Result<()> {
+        let logger = get_logger()?;
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let project_path = temp_dir.path();
+
+        std::fs::write(project_path.join("README"), "hello")?;
+        // Uploading to the unreachable destination would fail the command.
+        std::fs::write(
+            project_path.join("pyoxidizer.bzl"),
+            r#"
+def make_files():
+    return glob([CWD + "/README"], strip_prefix = CWD + "/")
+
+def make_release():
+    r = Release()
+    r.add_target("files")
+    r.add_http_put("https://127.0.0.1:1/uploads")
+    r.verify_signatures = False
+    return r
+
+register_target("files", make_files)
+register_target("release", make_release)
+resolve_targets()
+"#,
+        )?;
+
+        let manifest_path = project_path.join("dist-manifest.json");
+        generate_dist_manifest(
+            &logger,
+            project_path,
+            &[],
+            Some(vec!["files".to_string(), "release".to_string()]),
+            false,
+            false,
+            Some(&manifest_path),
+        )?;
+
+        let manifest: serde_json::Value = serde_json::from_slice(&std::fs::read(&manifest_path)?)?;
+        let artifacts = manifest["platforms"][0]["artifacts"].as_array().unwrap();
+        assert_eq!(artifacts.len(), 1);
+        assert_eq!(artifacts[0]["target"], "files");
+        assert_eq!(artifacts[0]["name"], "README");
+
+        Ok(())
+    }
+}
@@ -7,18 +7,26 @@ Defining and manipulating binaries embedding Python.
 */
 
 use {
-    super::config::EmbeddedPythonConfig,
+    super::{
+        config::{EmbeddedPythonConfig, ExternalAsset, PackedResourcesEmbedding},
+        packaging_tool::InstallGroup,
+        windows_runtime::{WindowsRuntimeDllsMode, WindowsRuntimeRequirements},
+    },
+    crate::analyze::MacOsVersion,
     anyhow::Result,
     python_packaging::{
         policy::PythonPackagingPolicy,
         resource::{
-            FileData, PythonExtensionModule, PythonModuleSource, PythonPackageDistributionResource,
-            PythonPackageResource, PythonResource,
+            FileData, GettextCatalog, PythonExtensionModule, PythonModuleSource,
+            PythonPackageDistributionResource, PythonPackageResource, PythonResource,
+            SharedLibrary,
+        },
+        resource_collection::{
+            CompiledResourcesCollection, PrePackagedResource, PythonResourceAddCollectionContext,
         },
-        resource_collection::{PrePackagedResource, PythonResourceAddCollectionContext},
     },
     std::{
-        collections::HashMap,
+        collections::{BTreeMap, HashMap},
         fs::File,
         io::Write,
         path::{Path, PathBuf},
@@ -27,6 +35,24 @@ use {
     tugger::file_resource::FileManifest,
 };
 
+/// Name of the file holding packed resources in an external runtime directory.
+pub const EXTERNAL_RUNTIME_PACKED_RESOURCES_FILENAME: &str = "packed-resources";
+
+/// Resolve the directory native shared libraries are installed into.
+///
+/// The path is relative to the binary. Windows always searches the directory
+/// of the executable for DLLs. Elsewhere, the binary is linked with a
+/// run-time search path pointing at this directory.
+pub fn shared_library_install_dir(target_triple: &str) -> &'static str {
+    if target_triple.contains("-windows-") {
+        ""
+    } else if target_triple.contains("-apple-") {
+        "Frameworks"
+    } else {
+        "lib"
+    }
+}
+
 /// How a binary should link against libpython.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum LibpythonLinkMode {
@@ -92,6 +118,91 @@ pub trait PythonBinaryBuilder {
     /// Set the value of the `windows_subsystem` Rust attribute for generated Rust projects.
     fn set_windows_subsystem(&mut self, value: &str) -> Result<()>;
 
+    /// The name of the Windows service the executable can run as.
+    ///
+    /// If set, the generated Rust project handles command line flags to
+    /// install, uninstall, and run the executable as a Windows service.
+    fn windows_service_name(&self) -> &Option<String>;
+
+    /// Set the name of the Windows service the executable can run as.
+    fn set_windows_service_name(&mut self, value: Option<String>) -> Result<()>;
+
+    /// Whether to install Visual C++ runtime DLLs next to built executables.
+    fn windows_runtime_dlls_mode(&self) -> WindowsRuntimeDllsMode;
+
+    /// Set whether to install Visual C++ runtime DLLs next to built executables.
+    fn set_windows_runtime_dlls_mode(&mut self, value: WindowsRuntimeDllsMode);
+
+    /// How packed resources data is stored in built executables.
+    fn packed_resources_embedding(&self) -> PackedResourcesEmbedding;
+
+    /// Set how packed resources data is stored in built executables.
+    fn set_packed_resources_embedding(&mut self, value: PackedResourcesEmbedding);
+
+    /// The minimum macOS version built binaries support.
+    fn macos_deployment_target(&self) -> Option<MacOsVersion>;
+
+    /// Set the minimum macOS version built binaries support.
+    fn set_macos_deployment_target(&mut self, value: Option<MacOsVersion>);
+
+    /// The oldest glibc version built executables must run with.
+    fn linux_minimum_glibc_version(&self) -> &Option<String>;
+
+    /// Set the oldest glibc version built executables must run with.
+    fn set_linux_minimum_glibc_version(&mut self, value: Option<String>) -> Result<()>;
+
+    /// The maximum size in megabytes of built executables and their installed files.
+    fn max_size_mb(&self) -> Option<u64>;
+
+    /// Set the maximum size in megabytes of built executables and their installed files.
+    fn set_max_size_mb(&mut self, value: Option<u64>);
+
+    /// The version of the application.
+    ///
+    /// If set, it is embedded in Windows version resources and in the
+    /// `build_info` binary metadata.
+    fn version(&self) -> &Option<String>;
+
+    /// Set the version of the application.
+    fn set_version(&mut self, value: Option<String>) -> Result<()>;
+
+    /// Whether the `tzdata` package is bundled so `zoneinfo` works without system data.
+    fn bundle_tzdata(&self) -> bool;
+
+    /// Set whether to bundle the `tzdata` package.
+    ///
+    /// If the package hasn't been added by the time the binary is built, it
+    /// is downloaded and added using the packaging policy. If its files are
+    /// installed on the filesystem, `zoneinfo` is pointed at their location
+    /// when the interpreter is initialized.
+    fn set_bundle_tzdata(&mut self, value: bool);
+
+    /// The directory, relative to the binary, holding external runtimes.
+    ///
+    /// If set, packed resources are installed in a runtime directory within
+    /// it, named after the runtime's version and content, instead of being
+    /// embedded in the binary. Native libraries are installed there too,
+    /// except on Windows.
+    fn external_runtime_path(&self) -> &Option<String>;
+
+    /// Set the directory, relative to the binary, holding external runtimes.
+    fn set_external_runtime_path(&mut self, value: Option<String>) -> Result<()>;
+
+    /// Additional Cargo features to enable when building the generated Rust project.
+    fn cargo_features(&self) -> &[String];
+
+    /// Enable an additional Cargo feature when building the generated Rust project.
+    fn add_cargo_feature(&mut self, feature: &str) -> Result<()>;
+
+    /// Environment variables to set when building the generated Rust project.
+    fn build_env(&self) -> &BTreeMap<String, String>;
+
+    /// Set an environment variable to use when building the generated Rust project.
+    fn set_build_env(&mut self, key: &str, value: &str) -> Result<()>;
+
+    /// Obtain the configuration of the embedded Python interpreter.
+    fn embedded_python_config(&self) -> &EmbeddedPythonConfig;
+
     /// Obtain an iterator over all resource entries that will be embedded in the binary.
     ///
     /// This likely does not return extension modules that are statically linked
@@ -112,6 +223,9 @@ pub trait PythonBinaryBuilder {
 
     /// Runs `pip install` using the binary builder's settings.
     ///
+    /// `pip` runs in `cwd`, which relative paths in `install_args` are
+    /// resolved against.
+    ///
     /// Returns resources discovered as part of performing an install.
     fn pip_install(
         &self,
@@ -119,6 +233,21 @@ pub trait PythonBinaryBuilder {
         verbose: bool,
         install_args: &[String],
         extra_envs: &HashMap<String, String>,
+        cwd: &Path,
+    ) -> Result<Vec<PythonResource>>;
+
+    /// Installs several groups of packages concurrently.
+    ///
+    /// `pip` runs in `cwd`, like with `pip_install()`.
+    ///
+    /// Returns resources discovered by the installs, merged in group order.
+    fn install_groups(
+        &self,
+        logger: &slog::Logger,
+        verbose: bool,
+        groups: &[InstallGroup],
+        extra_envs: &HashMap<String, String>,
+        cwd: &Path,
     ) -> Result<Vec<PythonResource>>;
 
     /// Reads Python resources from the filesystem.
@@ -220,6 +349,43 @@ pub trait PythonBinaryBuilder {
         add_context: Option<PythonResourceAddCollectionContext>,
     ) -> Result<()>;
 
+    /// Add a `GettextCatalog` to the resource collection.
+    ///
+    /// If the packaging policy defines a location for gettext catalogs, the
+    /// catalog is installed there and its domain is bound at run-time.
+    /// Otherwise it is added like any other package resource.
+    fn add_gettext_catalog(
+        &mut self,
+        catalog: &GettextCatalog,
+        add_context: Option<PythonResourceAddCollectionContext>,
+    ) -> Result<()>;
+
+    /// Add a native shared library to install next to the binary.
+    ///
+    /// The library is installed into `shared_library_install_dir()` and the
+    /// binary is configured to find it there. `searched_by` names extension
+    /// modules loading the library, which must have already been added.
+    fn add_shared_library(&mut self, library: &SharedLibrary, searched_by: &[String])
+        -> Result<()>;
+
+    /// Configure the binary to use a Qt binding whose resources were added.
+    ///
+    /// Package resources of the binding are installed on the filesystem and
+    /// the interpreter is configured to point Qt at its plugins and QML modules.
+    fn add_qt_support(&mut self, binding: &str) -> Result<()>;
+
+    /// Register a large asset the binary downloads when first requested.
+    ///
+    /// The asset isn't installed with the binary. Only its URL and hash are
+    /// recorded in the interpreter configuration.
+    fn add_external_asset(&mut self, asset: ExternalAsset) -> Result<()>;
+
+    /// Embed named data in a dedicated section of the binary.
+    ///
+    /// The data can be inspected with binary tools and is available at
+    /// run-time via `oxidized_importer.binary_metadata()`.
+    fn add_binary_metadata(&mut self, name: &str, data: &[u8]) -> Result<()>;
+
     /// Filter embedded resources against names in files.
     ///
     /// `files` is files to read names from.
@@ -235,6 +401,11 @@ pub trait PythonBinaryBuilder {
     /// Whether the binary requires the jemalloc library.
     fn requires_jemalloc(&self) -> bool;
 
+    /// Compile collected resources into their final form.
+    ///
+    /// Bytecode is compiled using the host Python executable.
+    fn compile_resources(&self) -> Result<CompiledResourcesCollection>;
+
     /// Obtain an `EmbeddedPythonContext` instance from this one.
     fn to_embedded_python_context(
         &self,
@@ -299,11 +470,21 @@ pub struct EmbeddedPythonContext {
     pub module_names: Vec<u8>,
 
     /// Python resources to embed in the binary.
+    ///
+    /// Empty if resources are loaded from an external runtime instead.
     pub resources: Vec<u8>,
 
     /// Extra files to install next to produced binary.
     pub extra_files: FileManifest,
 
+    /// Size in bytes of resource data attributed to each top-level package.
+    pub package_sizes: BTreeMap<String, u64>,
+
+    /// C runtime DLLs required by extension modules and libraries.
+    ///
+    /// Only populated for Windows targets.
+    pub windows_runtime_requirements: WindowsRuntimeRequirements,
+
     /// Rust target triple for the host we are running on.
     pub host_triple: String,
 
@@ -341,8 +522,14 @@ impl EmbeddedPythonContext {
         };
 
         let config_rs = dest_dir.join("default_python_config.rs");
-        self.config
-            .write_default_python_confis_rs(&config_rs, Some(&embedded_resources))?;
+        self.config.write_default_python_confis_rs(
+            &config_rs,
+            if self.resources.is_empty() {
+                None
+            } else {
+                Some(&embedded_resources)
+            },
+        )?;
 
         let mut cargo_metadata_lines = Vec::new();
         cargo_metadata_lines.extend(self.linking_info.cargo_metadata.clone());
@@ -7,16 +7,18 @@ Configuring a Python interpreter.
 */
 
 use {
-    anyhow::Result,
+    anyhow::{anyhow, Result},
     itertools::Itertools,
     python_packaging::{
         interpreter::{
             Allocator, BytesWarning, CheckHashPYCsMode, CoerceCLocale, MemoryAllocatorBackend,
             PythonInterpreterConfig, PythonInterpreterProfile, TerminfoResolution,
         },
+        python_version::PythonVersionCapabilities,
         resource::BytecodeOptimizationLevel,
     },
     std::{
+        convert::TryFrom,
         io::Write,
         path::{Path, PathBuf},
     },
@@ -44,7 +46,7 @@ fn optional_bool_to_string(value: &Option<bool>) -> String {
 
 fn optional_string_to_string(value: &Option<String>) -> String {
     match value {
-        Some(value) => format_args!("Some(\"{}\")", value).to_string(),
+        Some(value) => format!("Some(r###\"{}\"###.to_string())", value),
         None => "None".to_string(),
     }
 }
@@ -72,6 +74,159 @@ fn optional_vec_string_to_string(value: &Option<Vec<String>>) -> String {
     }
 }
 
+/// How packed resources data is stored in a built executable.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PackedResourcesEmbedding {
+    /// Compiled into the executable as a byte array.
+    Array,
+
+    /// Compiled into the executable in a dedicated linker section.
+    ///
+    /// The section is named `.pyoxres`, or `__DATA,__pyoxres` on macOS.
+    Section,
+
+    /// Appended to the linked executable.
+    Overlay,
+}
+
+impl Default for PackedResourcesEmbedding {
+    fn default() -> Self {
+        Self::Array
+    }
+}
+
+impl ToString for PackedResourcesEmbedding {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Array => "array",
+            Self::Section => "section",
+            Self::Overlay => "overlay",
+        }
+        .to_string()
+    }
+}
+
+impl TryFrom<&str> for PackedResourcesEmbedding {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "array" => Ok(Self::Array),
+            "section" => Ok(Self::Section),
+            "overlay" => Ok(Self::Overlay),
+            _ => Err(format!(
+                "{} is not a valid packed resources embedding; use array, section, or overlay",
+                value
+            )),
+        }
+    }
+}
+
+/// Rust code evaluating to a `Vec<&[u8]>` referencing packed resources data in a file.
+fn packed_resources_rs(path: &Path, embedding: PackedResourcesEmbedding) -> Result<String> {
+    Ok(match embedding {
+        PackedResourcesEmbedding::Array => {
+            format!("vec![include_bytes!(r#\"{}\"#)]", path.display())
+        }
+        PackedResourcesEmbedding::Section => format!(
+            "{{\n        \
+            #[used]\n        \
+            #[cfg_attr(target_os = \"macos\", link_section = \"__DATA,__pyoxres\")]\n        \
+            #[cfg_attr(not(target_os = \"macos\"), link_section = \".pyoxres\")]\n        \
+            static PACKED_RESOURCES: [u8; {}] = *include_bytes!(r#\"{}\"#);\n        \
+            vec![&PACKED_RESOURCES[..]]\n    \
+            }}",
+            std::fs::metadata(path)?.len(),
+            path.display()
+        ),
+        PackedResourcesEmbedding::Overlay => "vec![]".to_string(),
+    })
+}
+
+/// Magic at the start of serialized binary metadata.
+///
+/// This mirrors `pyembed::BINARY_METADATA_MAGIC`.
+const BINARY_METADATA_MAGIC: &[u8] = b"PYOXMETA";
+
+/// Serialize binary metadata entries to the format read by `pyembed`.
+///
+/// The data is a `PYOXMETA` magic followed by the number of entries and
+/// then each entry's name and data, each preceded by its length. All
+/// integers are little-endian u32.
+pub fn serialize_binary_metadata(entries: &[(String, Vec<u8>)]) -> Result<Vec<u8>> {
+    let len = |name: &str, value: usize| -> Result<[u8; 4]> {
+        Ok(u32::try_from(value)
+            .map_err(|_| anyhow!("binary metadata {} is too large", name))?
+            .to_le_bytes())
+    };
+
+    let mut data = BINARY_METADATA_MAGIC.to_vec();
+    data.extend_from_slice(&len("entries", entries.len())?);
+
+    for (name, value) in entries {
+        data.extend_from_slice(&len(name, name.len())?);
+        data.extend_from_slice(name.as_bytes());
+        data.extend_from_slice(&len(name, value.len())?);
+        data.extend_from_slice(value);
+    }
+
+    Ok(data)
+}
+
+/// Obtain Rust code placing binary metadata in a dedicated section.
+fn binary_metadata_rs(entries: &[(String, Vec<u8>)]) -> Result<String> {
+    if entries.is_empty() {
+        return Ok("None".to_string());
+    }
+
+    let data = serialize_binary_metadata(entries)?;
+
+    Ok(format!(
+        "{{\n        \
+        #[used]\n        \
+        #[cfg_attr(target_os = \"macos\", link_section = \"__DATA,__pyoxmd\")]\n        \
+        #[cfg_attr(not(target_os = \"macos\"), link_section = \".pyoxmd\")]\n        \
+        static BINARY_METADATA: [u8; {}] = *b\"{}\";\n        \
+        Some(&BINARY_METADATA[..])\n    \
+        }}",
+        data.len(),
+        data.iter()
+            .flat_map(|b| std::ascii::escape_default(*b))
+            .map(char::from)
+            .collect::<String>()
+    ))
+}
+
+/// A file containing packed resources data to load at run-time.
+///
+/// This type mirrors `pyembed::PackedResourcesFile`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackedResourcesFile {
+    /// Path to the file. May begin with `$ORIGIN`.
+    pub path: PathBuf,
+
+    /// Expected SHA-256 of the file content, as a hex string.
+    pub sha256: Option<String>,
+}
+
+/// A large asset to download when the application first requests it.
+///
+/// This type mirrors `pyembed::ExternalAsset`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExternalAsset {
+    /// Name the application uses to request the asset.
+    pub name: String,
+
+    /// URL to download the asset from.
+    pub url: String,
+
+    /// Expected SHA-256 of the asset content, as a hex string.
+    pub sha256: String,
+
+    /// Path to install the asset to. May begin with `$ORIGIN`.
+    pub path: PathBuf,
+}
+
 /// Represents the run-time configuration of a Python interpreter.
 ///
 /// This type mirrors `pyembed::OxidizedPythonInterpreterConfig`. We can't
@@ -84,11 +239,37 @@ pub struct EmbeddedPythonConfig {
     pub raw_allocator: MemoryAllocatorBackend,
     pub oxidized_importer: bool,
     pub filesystem_importer: bool,
+    pub dev_source_roots: Vec<PathBuf>,
+    pub packed_resources_embedding: PackedResourcesEmbedding,
+    pub packed_resources_files: Vec<PackedResourcesFile>,
+    pub resource_dirs: Vec<PathBuf>,
+    pub resource_dirs_flag: Option<String>,
     pub argvb: bool,
+    pub argv0: Option<String>,
+    pub prepend_args: Vec<String>,
+    pub entry_points: Vec<(String, String)>,
     pub sys_frozen: bool,
     pub sys_meipass: bool,
+    pub pre_run_modules: Vec<String>,
+    pub frozen_modules: Vec<String>,
+    pub preload_modules: Vec<String>,
+    pub thread_stack_size: Option<usize>,
+    pub asyncio_event_loop_policy: Option<String>,
+    pub repl_banner: Option<String>,
+    pub repl_startup_code: Option<String>,
+    pub repl_ps1: Option<String>,
+    pub repl_ps2: Option<String>,
+    pub debug_shell: bool,
     pub terminfo_resolution: TerminfoResolution,
     pub tcl_library: Option<PathBuf>,
+    pub environment_variables: Vec<(String, String)>,
+    pub gettext_domains: Vec<(String, PathBuf)>,
+    pub tzpath: Option<PathBuf>,
+    pub external_assets: Vec<ExternalAsset>,
+    pub binary_metadata: Vec<(String, Vec<u8>)>,
+    pub restricted_packages: Vec<(String, String)>,
+    pub capabilities: Vec<String>,
+    pub capabilities_env: Option<String>,
     pub write_modules_directory_env: Option<String>,
 }
 
@@ -108,17 +289,81 @@ impl Default for EmbeddedPythonConfig {
             raw_allocator: MemoryAllocatorBackend::System,
             oxidized_importer: true,
             filesystem_importer: false,
+            dev_source_roots: vec![],
+            packed_resources_embedding: PackedResourcesEmbedding::Array,
+            packed_resources_files: vec![],
+            resource_dirs: vec![],
+            resource_dirs_flag: None,
             argvb: false,
+            argv0: None,
+            prepend_args: vec![],
+            entry_points: vec![],
             sys_frozen: false,
             sys_meipass: false,
+            pre_run_modules: vec![],
+            frozen_modules: vec![],
+            preload_modules: vec![],
+            thread_stack_size: None,
+            asyncio_event_loop_policy: None,
+            repl_banner: None,
+            repl_startup_code: None,
+            repl_ps1: None,
+            repl_ps2: None,
+            debug_shell: false,
             terminfo_resolution: TerminfoResolution::None,
             tcl_library: None,
+            environment_variables: vec![],
+            gettext_domains: vec![],
+            tzpath: None,
+            external_assets: vec![],
+            binary_metadata: vec![],
+            restricted_packages: vec![],
+            capabilities: vec![],
+            capabilities_env: None,
             write_modules_directory_env: None,
         }
     }
 }
 
 impl EmbeddedPythonConfig {
+    /// Ensure settings are supported by a Python version.
+    ///
+    /// Errors if a configuration field that isn't available in the
+    /// targeted Python version is set.
+    pub fn validate_for_python_version(
+        &self,
+        capabilities: &PythonVersionCapabilities,
+    ) -> Result<()> {
+        let version_dependent_fields = [
+            ("platlibdir", self.config.platlibdir.is_some()),
+            ("show_alloc_count", self.config.show_alloc_count.is_some()),
+        ];
+
+        for (field, is_set) in version_dependent_fields.iter() {
+            if *is_set && !capabilities.has_config_field(field) {
+                return Err(anyhow!(
+                    "interpreter configuration field {} is not supported by Python {}",
+                    field,
+                    capabilities.major_minor()
+                ));
+            }
+        }
+
+        // platlibdir is applied via an environment variable, which
+        // interpreters not using the environment ignore.
+        let uses_environment = self
+            .config
+            .use_environment
+            .unwrap_or(self.config.profile == PythonInterpreterProfile::Python);
+        if self.config.platlibdir.is_some() && !uses_environment {
+            return Err(anyhow!(
+                "interpreter configuration field platlibdir requires use_environment"
+            ));
+        }
+
+        Ok(())
+    }
+
     /// Convert the instance to Rust code that constructs a `pyembed::OxidizedPythonInterpreterConfig`.
     pub fn to_oxidized_python_interpreter_config_rs(
         &self,
@@ -163,9 +408,11 @@ impl EmbeddedPythonConfig {
             legacy_windows_stdio: {},\n        \
             malloc_stats: {},\n        \
             module_search_paths: {},\n        \
+            module_search_paths_set: {},\n        \
             optimization_level: {},\n        \
             parser_debug: {},\n        \
             pathconfig_warnings: {},\n        \
+            platlibdir: {},\n        \
             prefix: {},\n        \
             program_name: {},\n        \
             pycache_prefix: {},\n        \
@@ -191,14 +438,40 @@ impl EmbeddedPythonConfig {
             set_missing_path_configuration: true,\n    \
             oxidized_importer: {},\n    \
             filesystem_importer: {},\n    \
+            dev_source_roots: {},\n    \
             packed_resources: {},\n    \
+            packed_resources_overlay: {},\n    \
+            packed_resources_files: {},\n    \
+            resource_dirs: {},\n    \
+            resource_dirs_flag: {},\n    \
             extra_extension_modules: None,\n    \
             argv: None,\n    \
             argvb: {},\n    \
+            argv0: {},\n    \
+            prepend_args: {},\n    \
+            entry_points: {},\n    \
             sys_frozen: {},\n    \
             sys_meipass: {},\n    \
+            pre_run_modules: {},\n    \
+            frozen_modules: {},\n    \
+            preload_modules: {},\n    \
+            thread_stack_size: {},\n    \
+            asyncio_event_loop_policy: {},\n    \
+            repl_banner: {},\n    \
+            repl_startup_code: {},\n    \
+            repl_ps1: {},\n    \
+            repl_ps2: {},\n    \
+            debug_shell: {},\n    \
             terminfo_resolution: {},\n    \
             tcl_library: {},\n    \
+            environment_variables: {},\n    \
+            gettext_domains: {},\n    \
+            tzpath: {},\n    \
+            external_assets: {},\n    \
+            binary_metadata: {},\n    \
+            restricted_packages: {},\n    \
+            capabilities: {},\n    \
+            capabilities_env: {},\n    \
             write_modules_directory_env: {},\n    \
             }}\n\
             ",
@@ -279,6 +552,7 @@ impl EmbeddedPythonConfig {
                 }
                 None => "None".to_string(),
             },
+            optional_bool_to_string(&self.config.module_search_paths_set),
             match self.config.optimization_level {
                 Some(BytecodeOptimizationLevel::Zero) =>
                     "Some(pyembed::BytecodeOptimizationLevel::Zero)",
@@ -290,6 +564,7 @@ impl EmbeddedPythonConfig {
             },
             optional_bool_to_string(&self.config.parser_debug),
             optional_bool_to_string(&self.config.pathconfig_warnings),
+            optional_string_to_string(&self.config.platlibdir),
             optional_pathbuf_to_string(&self.config.prefix),
             optional_pathbuf_to_string(&self.config.program_name),
             optional_pathbuf_to_string(&self.config.pycache_prefix),
@@ -317,22 +592,170 @@ impl EmbeddedPythonConfig {
             },
             self.oxidized_importer,
             self.filesystem_importer,
+            format!(
+                "vec![{}]",
+                self.dev_source_roots
+                    .iter()
+                    .map(|p| format!("std::path::PathBuf::from(r\"{}\")", p.display()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
             if let Some(path) = packed_resources_path {
-                format!("vec![include_bytes!(r#\"{}\"#)]", path.display())
+                packed_resources_rs(path, self.packed_resources_embedding)?
             } else {
                 "vec![]".to_string()
             },
+            packed_resources_path.is_some()
+                && self.packed_resources_embedding == PackedResourcesEmbedding::Overlay,
+            format!(
+                "vec![{}]",
+                self.packed_resources_files
+                    .iter()
+                    .map(|f| format!(
+                        "pyembed::PackedResourcesFile {{ path: std::path::PathBuf::from(r\"{}\"), sha256: {} }}",
+                        f.path.display(),
+                        optional_string_to_string(&f.sha256)
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            format!(
+                "vec![{}]",
+                self.resource_dirs
+                    .iter()
+                    .map(|p| format!("std::path::PathBuf::from(r\"{}\")", p.display()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            optional_string_to_string(&self.resource_dirs_flag),
             self.argvb,
+            optional_string_to_string(&self.argv0),
+            format!(
+                "vec![{}]",
+                self.prepend_args
+                    .iter()
+                    .map(|arg| format!("r###\"{}\"###.to_string()", arg))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            format!(
+                "vec![{}]",
+                self.entry_points
+                    .iter()
+                    .map(|(name, target)| format!(
+                        "(r###\"{}\"###.to_string(), \"{}\".to_string())",
+                        name, target
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
             self.sys_frozen,
             self.sys_meipass,
+            format!(
+                "vec![{}]",
+                self.pre_run_modules
+                    .iter()
+                    .map(|m| format!("\"{}\".to_string()", m))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            format!(
+                "vec![{}]",
+                self.frozen_modules
+                    .iter()
+                    .map(|m| format!("\"{}\".to_string()", m))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            format!(
+                "vec![{}]",
+                self.preload_modules
+                    .iter()
+                    .map(|m| format!("\"{}\".to_string()", m))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            match &self.thread_stack_size {
+                Some(value) => format!("Some({})", value),
+                None => "None".to_string(),
+            },
+            optional_string_to_string(&self.asyncio_event_loop_policy),
+            optional_string_to_string(&self.repl_banner),
+            optional_string_to_string(&self.repl_startup_code),
+            optional_string_to_string(&self.repl_ps1),
+            optional_string_to_string(&self.repl_ps2),
+            self.debug_shell,
             match self.terminfo_resolution {
                 TerminfoResolution::Dynamic => "pyembed::TerminfoResolution::Dynamic".to_string(),
                 TerminfoResolution::None => "pyembed::TerminfoResolution::None".to_string(),
                 TerminfoResolution::Static(ref v) => {
-                    format!("pyembed::TerminfoResolution::Static(r###\"{}\"###", v)
+                    format!(
+                        "pyembed::TerminfoResolution::Static(r###\"{}\"###.to_string())",
+                        v
+                    )
                 }
+                TerminfoResolution::Bundled => "pyembed::TerminfoResolution::Bundled".to_string(),
             },
             optional_pathbuf_to_string(&self.tcl_library),
+            format!(
+                "vec![{}]",
+                self.environment_variables
+                    .iter()
+                    .map(|(key, value)| format!(
+                        "(r###\"{}\"###.to_string(), r###\"{}\"###.to_string())",
+                        key, value
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            format!(
+                "vec![{}]",
+                self.gettext_domains
+                    .iter()
+                    .map(|(domain, path)| format!(
+                        "(r###\"{}\"###.to_string(), std::path::PathBuf::from(r###\"{}\"###))",
+                        domain,
+                        path.display()
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            optional_pathbuf_to_string(&self.tzpath),
+            format!(
+                "vec![{}]",
+                self.external_assets
+                    .iter()
+                    .map(|a| format!(
+                        "pyembed::ExternalAsset {{ name: r###\"{}\"###.to_string(), url: r###\"{}\"###.to_string(), sha256: r###\"{}\"###.to_string(), path: std::path::PathBuf::from(r###\"{}\"###) }}",
+                        a.name,
+                        a.url,
+                        a.sha256,
+                        a.path.display()
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            binary_metadata_rs(&self.binary_metadata)?,
+            format!(
+                "vec![{}]",
+                self.restricted_packages
+                    .iter()
+                    .map(|(package, capability)| format!(
+                        "(r###\"{}\"###.to_string(), r###\"{}\"###.to_string())",
+                        package, capability
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            format!(
+                "vec![{}]",
+                self.capabilities
+                    .iter()
+                    .map(|capability| format!("r###\"{}\"###.to_string()", capability))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            optional_string_to_string(&self.capabilities_env),
             optional_string_to_string(&self.write_modules_directory_env),
         );
 
@@ -345,15 +768,13 @@ impl EmbeddedPythonConfig {
         path: &Path,
         packed_resources_path: Option<&Path>,
     ) -> Result<()> {
-        let mut f = std::fs::File::create(&path)?;
-
         let indented = self
             .to_oxidized_python_interpreter_config_rs(packed_resources_path)?
             .split('\n')
             .map(|line| "    ".to_string() + line)
             .join("\n");
 
-        f.write_fmt(format_args!(
+        let code = format!(
             "/// Obtain the default Python configuration\n\
              ///\n\
              /// The crate is compiled with a default Python configuration embedded\n\
@@ -361,7 +782,17 @@ impl EmbeddedPythonConfig {
              /// configuration.\n\
              pub fn default_python_config<'a>() -> pyembed::OxidizedPythonInterpreterConfig<'a> {{\n{}\n}}\n",
             indented
-        ))?;
+        );
+
+        // Leave an unchanged file alone so Cargo doesn't recompile crates
+        // including it. When packed resources aren't compiled in, this avoids
+        // relinking if only resources changed.
+        if std::fs::read(path).ok().as_deref() == Some(code.as_bytes()) {
+            return Ok(());
+        }
+
+        let mut f = std::fs::File::create(&path)?;
+        f.write_all(code.as_bytes())?;
 
         Ok(())
     }
@@ -369,7 +800,29 @@ impl EmbeddedPythonConfig {
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use {super::*, python_packaging::python_version::resolve_python_version};
+
+    #[test]
+    fn test_validate_for_python_version() -> Result<()> {
+        let mut config = EmbeddedPythonConfig::default();
+        config.validate_for_python_version(resolve_python_version("3.9")?)?;
+
+        config.config.show_alloc_count = Some(true);
+        config.validate_for_python_version(resolve_python_version("3.8")?)?;
+        assert!(config
+            .validate_for_python_version(resolve_python_version("3.9")?)
+            .is_err());
+
+        let mut config = EmbeddedPythonConfig::default();
+        config.config.profile = PythonInterpreterProfile::Python;
+        config.config.platlibdir = Some("lib64".to_string());
+        config.validate_for_python_version(resolve_python_version("3.9")?)?;
+        assert!(config
+            .validate_for_python_version(resolve_python_version("3.8")?)
+            .is_err());
+
+        Ok(())
+    }
 
     #[test]
     fn test_serialize_module_search_paths() -> Result<()> {
@@ -383,4 +836,317 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_serialize_packed_resources_embedding() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let path = temp_dir.path().join("packed-resources");
+        std::fs::write(&path, b"abc")?;
+
+        let mut config = EmbeddedPythonConfig::default();
+        let code = config.to_oxidized_python_interpreter_config_rs(Some(&path))?;
+        assert!(code.contains(&format!(
+            "packed_resources: vec![include_bytes!(r#\"{}\"#)],",
+            path.display()
+        )));
+        assert!(code.contains("packed_resources_overlay: false,"));
+
+        config.packed_resources_embedding = PackedResourcesEmbedding::Section;
+        let code = config.to_oxidized_python_interpreter_config_rs(Some(&path))?;
+        assert!(code.contains("link_section = \".pyoxres\""));
+        assert!(code.contains("static PACKED_RESOURCES: [u8; 3] = *include_bytes!"));
+        assert!(code.contains("packed_resources_overlay: false,"));
+
+        config.packed_resources_embedding = PackedResourcesEmbedding::Overlay;
+        let code = config.to_oxidized_python_interpreter_config_rs(Some(&path))?;
+        assert!(code.contains("packed_resources: vec![],"));
+        assert!(code.contains("packed_resources_overlay: true,"));
+
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains("packed_resources_overlay: false,"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_packed_resources_files() -> Result<()> {
+        let mut config = EmbeddedPythonConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains("packed_resources_files: vec![],"));
+
+        config.packed_resources_files = vec![PackedResourcesFile {
+            path: PathBuf::from("$ORIGIN/runtime/packed-resources"),
+            sha256: Some("abcd".to_string()),
+        }];
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains("packed_resources_files: vec![pyembed::PackedResourcesFile { path: std::path::PathBuf::from(r\"$ORIGIN/runtime/packed-resources\"), sha256: Some(r###\"abcd\"###.to_string()) }],"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_resource_dirs() -> Result<()> {
+        let mut config = EmbeddedPythonConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains("resource_dirs: vec![],"));
+        assert!(code.contains("resource_dirs_flag: None,"));
+
+        config.resource_dirs = vec![PathBuf::from("$ORIGIN/site-packages")];
+        config.resource_dirs_flag = Some("--extensions".to_string());
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains(
+            "resource_dirs: vec![std::path::PathBuf::from(r\"$ORIGIN/site-packages\")],"
+        ));
+        assert!(code.contains("resource_dirs_flag: Some(r###\"--extensions\"###.to_string()),"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_dev_source_roots() -> Result<()> {
+        let mut config = EmbeddedPythonConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains("dev_source_roots: vec![],"));
+
+        config.dev_source_roots = vec![PathBuf::from("/home/dev/myapp/src")];
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains(
+            "dev_source_roots: vec![std::path::PathBuf::from(r\"/home/dev/myapp/src\")],"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_entry_points() -> Result<()> {
+        let mut config = EmbeddedPythonConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains("argv0: None,"));
+        assert!(code.contains("prepend_args: vec![],"));
+        assert!(code.contains("entry_points: vec![],"));
+
+        config.argv0 = Some("myapp".to_string());
+        config.prepend_args = vec!["--verbose".to_string()];
+        config.entry_points = vec![("tool-a".to_string(), "myapp.a:main".to_string())];
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains("argv0: Some(r###\"myapp\"###.to_string()),"));
+        assert!(code.contains("prepend_args: vec![r###\"--verbose\"###.to_string()],"));
+        assert!(code.contains(
+            "entry_points: vec![(r###\"tool-a\"###.to_string(), \"myapp.a:main\".to_string())],"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_pre_run_modules() -> Result<()> {
+        let mut config = EmbeddedPythonConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains("pre_run_modules: vec![],"));
+
+        config.pre_run_modules = vec!["myapp._patches".to_string(), "myapp.env".to_string()];
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains(
+            "pre_run_modules: vec![\"myapp._patches\".to_string(), \"myapp.env\".to_string()],"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_thread_and_asyncio_hooks() -> Result<()> {
+        let mut config = EmbeddedPythonConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains("thread_stack_size: None,"));
+        assert!(code.contains("asyncio_event_loop_policy: None,"));
+
+        config.thread_stack_size = Some(4194304);
+        config.asyncio_event_loop_policy = Some("WindowsSelectorEventLoopPolicy".to_string());
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains("thread_stack_size: Some(4194304),"));
+        assert!(code.contains(
+            "asyncio_event_loop_policy: Some(r###\"WindowsSelectorEventLoopPolicy\"###.to_string()),"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_frozen_modules() -> Result<()> {
+        let mut config = EmbeddedPythonConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains("frozen_modules: vec![],"));
+
+        config.frozen_modules = vec!["myapp".to_string(), "myapp.cli".to_string()];
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code
+            .contains("frozen_modules: vec![\"myapp\".to_string(), \"myapp.cli\".to_string()],"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_preload_modules() -> Result<()> {
+        let mut config = EmbeddedPythonConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains("preload_modules: vec![],"));
+
+        config.preload_modules = vec!["json".to_string(), "myapp.ui".to_string()];
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(
+            code.contains("preload_modules: vec![\"json\".to_string(), \"myapp.ui\".to_string()],")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_terminfo_resolution() -> Result<()> {
+        let mut config = EmbeddedPythonConfig::default();
+
+        config.terminfo_resolution = TerminfoResolution::Static("/usr/share/terminfo".to_string());
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains("terminfo_resolution: pyembed::TerminfoResolution::Static(r###\"/usr/share/terminfo\"###.to_string()),"));
+
+        config.terminfo_resolution = TerminfoResolution::Bundled;
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains("terminfo_resolution: pyembed::TerminfoResolution::Bundled,"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_repl() -> Result<()> {
+        let mut config = EmbeddedPythonConfig::default();
+        config.repl_banner = Some("My \"App\" Console".to_string());
+        config.repl_startup_code = Some("import os\nimport sys\n".to_string());
+        config.repl_ps1 = Some("app> ".to_string());
+
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+
+        assert!(code.contains("repl_banner: Some(r###\"My \"App\" Console\"###.to_string()),"));
+        assert!(code
+            .contains("repl_startup_code: Some(r###\"import os\nimport sys\n\"###.to_string()),"));
+        assert!(code.contains("repl_ps1: Some(r###\"app> \"###.to_string()),"));
+        assert!(code.contains("repl_ps2: None,"));
+        assert!(code.contains("debug_shell: false,"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_environment_variables() -> Result<()> {
+        let mut config = EmbeddedPythonConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains("environment_variables: vec![],"));
+
+        config.environment_variables = vec![(
+            "QT_PLUGIN_PATH".to_string(),
+            "$ORIGIN/lib/PySide6/Qt/plugins".to_string(),
+        )];
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains("environment_variables: vec![(r###\"QT_PLUGIN_PATH\"###.to_string(), r###\"$ORIGIN/lib/PySide6/Qt/plugins\"###.to_string())],"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_gettext_domains() -> Result<()> {
+        let mut config = EmbeddedPythonConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains("gettext_domains: vec![],"));
+
+        config.gettext_domains = vec![("myapp".to_string(), PathBuf::from("$ORIGIN/locale"))];
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains("gettext_domains: vec![(r###\"myapp\"###.to_string(), std::path::PathBuf::from(r###\"$ORIGIN/locale\"###))],"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_tzpath() -> Result<()> {
+        let mut config = EmbeddedPythonConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains("tzpath: None,"));
+
+        config.tzpath = Some(PathBuf::from("$ORIGIN/lib/tzdata/zoneinfo"));
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code
+            .contains("tzpath: Some(std::path::PathBuf::from(r\"$ORIGIN/lib/tzdata/zoneinfo\")),"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_restricted_packages() -> Result<()> {
+        let mut config = EmbeddedPythonConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains("restricted_packages: vec![],"));
+        assert!(code.contains("capabilities: vec![],"));
+        assert!(code.contains("capabilities_env: None,"));
+
+        config.restricted_packages = vec![("myapp.pro".to_string(), "pro".to_string())];
+        config.capabilities = vec!["basic".to_string()];
+        config.capabilities_env = Some("MYAPP_TIER".to_string());
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains(
+            "restricted_packages: vec![(r###\"myapp.pro\"###.to_string(), r###\"pro\"###.to_string())],"
+        ));
+        assert!(code.contains("capabilities: vec![r###\"basic\"###.to_string()],"));
+        assert!(code.contains("capabilities_env: Some(r###\"MYAPP_TIER\"###.to_string()),"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_external_assets() -> Result<()> {
+        let mut config = EmbeddedPythonConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains("external_assets: vec![],"));
+
+        config.external_assets = vec![ExternalAsset {
+            name: "chromium".to_string(),
+            url: "https://example.com/chromium.zip".to_string(),
+            sha256: "abcd".to_string(),
+            path: PathBuf::from("$ORIGIN/assets/chromium.zip"),
+        }];
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains("external_assets: vec![pyembed::ExternalAsset { name: r###\"chromium\"###.to_string(), url: r###\"https://example.com/chromium.zip\"###.to_string(), sha256: r###\"abcd\"###.to_string(), path: std::path::PathBuf::from(r###\"$ORIGIN/assets/chromium.zip\"###) }],"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_binary_metadata() -> Result<()> {
+        let mut config = EmbeddedPythonConfig::default();
+
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains("binary_metadata: None,"));
+
+        config.binary_metadata = vec![("build".to_string(), b"a\"\x00".to_vec())];
+        assert_eq!(
+            serialize_binary_metadata(&config.binary_metadata)?,
+            b"PYOXMETA\x01\x00\x00\x00\x05\x00\x00\x00build\x03\x00\x00\x00a\"\x00".to_vec()
+        );
+
+        let code = config.to_oxidized_python_interpreter_config_rs(None)?;
+        assert!(code.contains("link_section = \".pyoxmd\""));
+        assert!(code.contains("static BINARY_METADATA: [u8; 28] = *b\"PYOXMETA\\x01\\x00\\x00\\x00\\x05\\x00\\x00\\x00build\\x03\\x00\\x00\\x00a\\\"\\x00\";"));
+        assert!(code.contains("Some(&BINARY_METADATA[..])"));
+
+        Ok(())
+    }
 }
@@ -10,15 +10,22 @@ use {
     super::{
         binary::{LibpythonLinkMode, PythonBinaryBuilder},
         config::EmbeddedPythonConfig,
-        standalone_distribution::StandaloneDistribution,
+        standalone_distribution::{
+            read_distribution_summary, DistributionSummary, StandaloneDistribution,
+        },
+    },
+    crate::{
+        environment::user_cache_dir,
+        file_lock::{sibling_lock_path, FileLock},
+        python_distributions::PYTHON_DISTRIBUTIONS,
+        timing::{PhaseTimer, PHASE_DISTRIBUTION},
     },
-    crate::python_distributions::PYTHON_DISTRIBUTIONS,
     anyhow::{anyhow, Context, Result},
-    fs2::FileExt,
     python_packaging::{
         bytecode::PythonBytecodeCompiler,
         module_util::PythonModuleSuffixes,
         policy::PythonPackagingPolicy,
+        python_version::PythonVersionCapabilities,
         resource::{DataLocation, PythonResource},
     },
     sha2::{Digest, Sha256},
@@ -28,10 +35,11 @@ use {
         convert::TryFrom,
         fs,
         fs::{create_dir_all, File},
-        io::Read,
+        io::{Read, Write},
         ops::DerefMut,
         path::{Path, PathBuf},
         sync::{Arc, Mutex},
+        time::Duration,
     },
     tugger::http::get_http_client,
     url::Url,
@@ -61,6 +69,9 @@ pub struct PythonDistributionRecord {
     /// X.Y major.minor version of Python.
     pub python_major_minor_version: String,
 
+    /// X.Y.Z version of Python.
+    pub python_version: String,
+
     /// Where the distribution can be obtained from.
     pub location: PythonDistributionLocation,
 
@@ -90,12 +101,20 @@ pub trait PythonDistribution {
     /// Obtain the filesystem path to a `python` executable for this distribution.
     fn python_exe_path(&self) -> &Path;
 
+    /// Obtain the directory Python is installed to in this distribution.
+    ///
+    /// It contains `python_exe_path()` and the standard library.
+    fn python_install_dir(&self) -> PathBuf;
+
     /// Obtain the full Python version string.
     fn python_version(&self) -> &str;
 
     /// Obtain the X.Y Python version component. e.g. `3.7`.
     fn python_major_minor_version(&self) -> String;
 
+    /// Obtain properties of this distribution's Python version.
+    fn python_capabilities(&self) -> &'static PythonVersionCapabilities;
+
     /// Obtain the full Python implementation name. e.g. `cpython`.
     fn python_implementation(&self) -> &str;
 
@@ -213,33 +232,17 @@ pub trait PythonDistribution {
 }
 
 /// Multiple threads or processes could race to extract the archive.
-/// So we use a lock file to ensure exclusive access.
-/// TODO use more granular lock based on the output directory (possibly
-/// by putting lock in output directory itself).
+/// So we use a lock file next to the extract directory to ensure exclusive
+/// access.
 pub struct DistributionExtractLock {
-    file: std::fs::File,
+    _lock: FileLock,
 }
 
 impl DistributionExtractLock {
-    pub fn new(extract_dir: &Path) -> Result<Self> {
-        let lock_path = extract_dir
-            .parent()
-            .unwrap()
-            .join("distribution-extract-lock");
-
-        let file = File::create(&lock_path)
-            .context(format!("could not create {}", lock_path.display()))?;
-
-        file.lock_exclusive()
-            .context(format!("failed to obtain lock for {}", lock_path.display()))?;
-
-        Ok(DistributionExtractLock { file })
-    }
-}
-
-impl Drop for DistributionExtractLock {
-    fn drop(&mut self) {
-        self.file.unlock().unwrap();
+    pub fn new(logger: &slog::Logger, extract_dir: &Path) -> Result<Self> {
+        Ok(DistributionExtractLock {
+            _lock: FileLock::acquire(logger, &sibling_lock_path(extract_dir))?,
+        })
     }
 }
 
@@ -261,10 +264,188 @@ fn sha256_path(path: &PathBuf) -> Vec<u8> {
     hasher.finalize().to_vec()
 }
 
+/// Number of times a download is attempted before giving up.
+const DOWNLOAD_ATTEMPTS: u32 = 5;
+
+/// Longest delay between download attempts.
+const MAX_DOWNLOAD_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Delay before retrying a download after `attempt` failed attempts.
+fn download_backoff(attempt: u32) -> Duration {
+    Duration::from_secs(1 << attempt.min(5)).min(MAX_DOWNLOAD_BACKOFF)
+}
+
+/// Parse the offset of the first byte of a `Content-Range` response header.
+fn parse_content_range_start(value: &str) -> Option<u64> {
+    value
+        .strip_prefix("bytes ")?
+        .split('-')
+        .next()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Describe why downloaded data doesn't match its expected SHA-256.
+///
+/// Proxies and captive portals commonly answer with an HTML page instead
+/// of the requested file, so that case is called out.
+fn describe_hash_mismatch(url: &str, expected: &str, actual: &str, prefix: &[u8]) -> String {
+    let prefix = String::from_utf8_lossy(prefix).to_lowercase();
+    let prefix = prefix.trim_start();
+
+    let cause = if prefix.starts_with("<!doctype html") || prefix.starts_with("<html") {
+        "the server returned an HTML page instead of the file, which usually means a \
+         proxy or captive portal intercepted the request; check the HTTP_PROXY and \
+         HTTPS_PROXY environment variables"
+    } else {
+        "the file was corrupted or modified in transit, possibly by a proxy rewriting \
+         responses"
+    };
+
+    format!(
+        "SHA-256 of {} is {} but {} was expected; {}",
+        url, actual, expected, cause
+    )
+}
+
+/// Reports download progress in 10% steps, or every 10 MB if the size is unknown.
+struct DownloadProgress {
+    total: Option<u64>,
+    reported: u64,
+}
+
+impl DownloadProgress {
+    fn update(&mut self, downloaded: u64) {
+        let step = match self.total {
+            Some(total) if total > 0 => downloaded * 10 / total,
+            _ => downloaded / (10 * 1024 * 1024),
+        };
+
+        if step > self.reported {
+            self.reported = step;
+
+            match self.total {
+                Some(total) if total > 0 => println!(
+                    "  {}% ({:.1} / {:.1} MB)",
+                    downloaded * 100 / total,
+                    downloaded as f64 / (1024.0 * 1024.0),
+                    total as f64 / (1024.0 * 1024.0)
+                ),
+                _ => println!("  {:.1} MB", downloaded as f64 / (1024.0 * 1024.0)),
+            }
+        }
+    }
+}
+
+/// Download a URL into a file, resuming from the file's current length.
+///
+/// Returns once the server has sent the complete file.
+fn download_to_partial_file(
+    client: &reqwest::blocking::Client,
+    url: &Url,
+    path: &Path,
+) -> Result<()> {
+    let existing = if path.exists() {
+        fs::metadata(path)?.len()
+    } else {
+        0
+    };
+
+    let mut request = client.get(url.as_str());
+    if existing > 0 {
+        request = request.header("Range", format!("bytes={}-", existing));
+    }
+
+    let mut response = request.send()?;
+    let status = response.status();
+
+    // The partial file is already complete.
+    if existing > 0 && status.as_u16() == 416 {
+        return Ok(());
+    }
+
+    if !status.is_success() {
+        return Err(anyhow!("server responded with HTTP {}", status));
+    }
+
+    // Servers ignoring the range request send the whole file.
+    let resumed = status.as_u16() == 206;
+    if resumed {
+        let start = response
+            .headers()
+            .get("content-range")
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_content_range_start);
+
+        if start != Some(existing) {
+            return Err(anyhow!(
+                "server resumed the download at the wrong offset ({:?} instead of {})",
+                start,
+                existing
+            ));
+        }
+
+        println!(
+            "resuming download at {:.1} MB",
+            existing as f64 / (1024.0 * 1024.0)
+        );
+    }
+
+    let mut fh = fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(path)
+        .with_context(|| format!("opening {}", path.display()))?;
+
+    let mut downloaded = if resumed { existing } else { 0 };
+    let mut progress = DownloadProgress {
+        total: response.content_length().map(|l| l + downloaded),
+        reported: 0,
+    };
+    progress.update(downloaded);
+
+    let mut buffer = [0; 65536];
+    loop {
+        let count = response.read(&mut buffer)?;
+        if count == 0 {
+            break;
+        }
+
+        fh.write_all(&buffer[..count])?;
+        downloaded += count as u64;
+        progress.update(downloaded);
+    }
+
+    if let Some(total) = progress.total {
+        if downloaded != total {
+            return Err(anyhow!(
+                "connection closed after {} of {} bytes",
+                downloaded,
+                total
+            ));
+        }
+    }
+
+    Ok(())
+}
+
 /// Ensure a Python distribution at a URL is available in a local directory.
 ///
+/// Data is downloaded into a `.part` file next to the destination. Failed
+/// attempts are retried with exponential backoff and resume where the
+/// previous attempt, possibly by an earlier process, stopped. The file is
+/// moved into place once its SHA-256 matches `sha256`.
+///
 /// The path to the downloaded and validated file is returned.
-pub fn download_distribution(url: &str, sha256: &str, cache_dir: &Path) -> Result<PathBuf> {
+pub fn download_distribution(
+    logger: &slog::Logger,
+    url: &str,
+    sha256: &str,
+    cache_dir: &Path,
+) -> Result<PathBuf> {
     let expected_hash = hex::decode(sha256)?;
     let u = Url::parse(url)?;
 
@@ -275,7 +456,11 @@ pub fn download_distribution(url: &str, sha256: &str, cache_dir: &Path) -> Resul
         .unwrap()
         .to_string();
 
-    let cache_path = cache_dir.join(basename);
+    let cache_path = cache_dir.join(&basename);
+    let partial_path = cache_dir.join(format!("{}.part", basename));
+
+    // Concurrent processes would otherwise all download the file.
+    let _lock = FileLock::acquire(logger, &sibling_lock_path(&cache_path))?;
 
     if cache_path.exists() {
         let file_hash = sha256_path(&cache_path);
@@ -286,48 +471,91 @@ pub fn download_distribution(url: &str, sha256: &str, cache_dir: &Path) -> Resul
         }
     }
 
-    let mut data: Vec<u8> = Vec::new();
-
     println!("downloading {}", u);
     let client = get_http_client()?;
-    let mut response = client.get(u.as_str()).send()?;
-    response.read_to_end(&mut data)?;
 
-    let mut hasher = Sha256::new();
-    hasher.update(&data);
-
-    let url_hash = hasher.finalize().to_vec();
-    if url_hash != expected_hash {
-        return Err(anyhow!("sha256 of Python distribution does not validate"));
-    }
-
-    let mut temp_cache_path = cache_path.clone();
-    temp_cache_path.set_file_name(format!("{}.tmp", Uuid::new_v4()));
-
-    fs::write(&temp_cache_path, data).context("unable to write distribution file")?;
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+
+        let resuming = partial_path.exists();
+
+        let err = match download_to_partial_file(&client, &u, &partial_path) {
+            Ok(()) => {
+                let file_hash = sha256_path(&partial_path);
+
+                if file_hash == expected_hash {
+                    break;
+                }
+
+                let mut prefix = vec![];
+                File::open(&partial_path)?
+                    .take(256)
+                    .read_to_end(&mut prefix)?;
+                fs::remove_file(&partial_path)?;
+
+                let message =
+                    describe_hash_mismatch(url, sha256, &hex::encode(&file_hash), &prefix);
+
+                // Data from an earlier attempt may have been bad. A fresh
+                // download settles whether the server sends the right file.
+                if resuming && attempt < DOWNLOAD_ATTEMPTS {
+                    anyhow!(message)
+                } else {
+                    return Err(anyhow!(message));
+                }
+            }
+            Err(e) => e,
+        };
 
-    fs::rename(&temp_cache_path, &cache_path)
-        .or_else(|e| -> Result<()> {
-            fs::remove_file(&temp_cache_path)
-                .context("unable to remove temporary distribution file")?;
+        if attempt >= DOWNLOAD_ATTEMPTS {
+            return Err(err.context(format!(
+                "downloading {} failed after {} attempts",
+                url, attempt
+            )));
+        }
 
-            if cache_path.exists() {
-                download_distribution(url, sha256, cache_dir)?;
-                return Ok(());
-            }
+        let delay = download_backoff(attempt);
+        println!(
+            "download attempt {} failed: {}; retrying in {}s",
+            attempt,
+            err,
+            delay.as_secs()
+        );
+        std::thread::sleep(delay);
+    }
 
-            Err(e.into())
-        })
+    replace_cache_file(&partial_path, &cache_path)
         .context("unable to rename downloaded distribution file")?;
 
     Ok(cache_path)
 }
 
-pub fn copy_local_distribution(path: &PathBuf, sha256: &str, cache_dir: &Path) -> Result<PathBuf> {
+/// Move a fully written file into place in a cache directory.
+///
+/// Readers that don't hold the lock for `dest` never see a partial file.
+fn replace_cache_file(source: &Path, dest: &Path) -> Result<()> {
+    // Any existing file failed verification, as we hold the lock guarding
+    // it. `fs::rename()` replaces it, on Windows too (it uses
+    // `MoveFileExW()` with `MOVEFILE_REPLACE_EXISTING`).
+    fs::rename(source, dest).or_else(|e| {
+        fs::remove_file(source)?;
+        Err(e.into())
+    })
+}
+
+pub fn copy_local_distribution(
+    logger: &slog::Logger,
+    path: &PathBuf,
+    sha256: &str,
+    cache_dir: &Path,
+) -> Result<PathBuf> {
     let expected_hash = hex::decode(sha256)?;
     let basename = path.file_name().unwrap().to_str().unwrap().to_string();
     let cache_path = cache_dir.join(basename);
 
+    let _lock = FileLock::acquire(logger, &sibling_lock_path(&cache_path))?;
+
     if cache_path.exists() {
         let file_hash = sha256_path(&cache_path);
 
@@ -347,7 +575,12 @@ pub fn copy_local_distribution(path: &PathBuf, sha256: &str, cache_dir: &Path) -
     }
 
     println!("copying {}", path.display());
-    std::fs::copy(path, &cache_path)?;
+    let mut temp_cache_path = cache_path.clone();
+    temp_cache_path.set_file_name(format!("{}.tmp", Uuid::new_v4()));
+
+    std::fs::copy(path, &temp_cache_path)?;
+    replace_cache_file(&temp_cache_path, &cache_path)
+        .context("unable to rename copied distribution file")?;
 
     Ok(cache_path)
 }
@@ -362,6 +595,7 @@ pub fn copy_local_distribution(path: &PathBuf, sha256: &str, cache_dir: &Path) -
 ///
 /// Local filesystem paths are preferred over remote URLs if both are defined.
 pub fn resolve_python_distribution_archive(
+    logger: &slog::Logger,
     dist: &PythonDistributionLocation,
     cache_dir: &Path,
 ) -> Result<PathBuf> {
@@ -372,24 +606,97 @@ pub fn resolve_python_distribution_archive(
     match dist {
         PythonDistributionLocation::Local { local_path, sha256 } => {
             let p = PathBuf::from(local_path);
-            copy_local_distribution(&p, sha256, cache_dir)
+            copy_local_distribution(logger, &p, sha256, cache_dir)
         }
         PythonDistributionLocation::Url { url, sha256 } => {
-            download_distribution(url, sha256, cache_dir)
+            download_distribution(logger, url, sha256, cache_dir)
+        }
+    }
+}
+
+/// Environment variable that disables sharing extracted distributions between builds.
+pub const NO_DISTRIBUTION_CACHE_ENV: &str = "PYOXIDIZER_NO_DISTRIBUTION_CACHE";
+
+/// Resolve the directory extracted distributions are shared in, if sharing is enabled.
+///
+/// Extracting a distribution writes hundreds of megabytes. Distributions are
+/// identified by the SHA-256 of their archive and never modified after
+/// extraction, except for installing pip. So all builds extract each one
+/// once into the user cache instead of into their build directory.
+pub fn shared_distributions_dir() -> Result<Option<PathBuf>> {
+    if std::env::var(NO_DISTRIBUTION_CACHE_ENV).is_ok() {
+        return Ok(None);
+    }
+
+    Ok(Some(user_cache_dir()?.join("python-distributions")))
+}
+
+/// A distribution extracted into a directory of extracted distributions.
+#[derive(Debug)]
+pub struct ExtractedDistribution {
+    /// Directory the distribution is extracted to.
+    pub path: PathBuf,
+
+    /// Summary of the distribution, or why it couldn't be read.
+    pub summary: Result<DistributionSummary, String>,
+
+    /// Size of the extracted files in bytes.
+    pub size: u64,
+}
+
+/// Find the distributions extracted into a directory.
+///
+/// Results are sorted by path. A missing directory holds no distributions.
+pub fn find_extracted_distributions(dir: &Path) -> Result<Vec<ExtractedDistribution>> {
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut res = vec![];
+
+    for entry in fs::read_dir(dir).with_context(|| format!("reading {}", dir.display()))? {
+        let entry = entry?;
+        let path = entry.path();
+
+        // Lock files and in-progress extractions live next to distributions.
+        if !entry.file_type()?.is_dir()
+            || !entry.file_name().to_string_lossy().starts_with("python.")
+        {
+            continue;
+        }
+
+        let mut size = 0;
+        for file in walkdir::WalkDir::new(&path) {
+            let file = file?;
+            if file.file_type().is_file() {
+                size += file.metadata()?.len();
+            }
         }
+
+        res.push(ExtractedDistribution {
+            summary: read_distribution_summary(&path).map_err(|e| e.to_string()),
+            path,
+            size,
+        });
     }
+
+    res.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(res)
 }
 
 /// Resolve a Python distribution archive.
 ///
-/// Returns a tuple of (archive path, extract directory).
+/// Returns a tuple of (archive path, extract directory). The extract
+/// directory is in `shared_distributions_dir()` if sharing is enabled and
+/// in `distributions_dir` otherwise.
 pub fn resolve_python_distribution_from_location(
     logger: &slog::Logger,
     location: &PythonDistributionLocation,
     distributions_dir: &Path,
 ) -> Result<(PathBuf, PathBuf)> {
     warn!(logger, "resolving Python distribution {:?}", location);
-    let path = resolve_python_distribution_archive(location, distributions_dir)?;
+    let path = resolve_python_distribution_archive(logger, location, distributions_dir)?;
     warn!(
         logger,
         "Python distribution available at {}",
@@ -401,7 +708,9 @@ pub fn resolve_python_distribution_from_location(
         PythonDistributionLocation::Url { sha256, .. } => sha256,
     };
 
-    let distribution_path = distributions_dir.join(format!("python.{}", &distribution_hash[0..12]));
+    let extract_root =
+        shared_distributions_dir()?.unwrap_or_else(|| distributions_dir.to_path_buf());
+    let distribution_path = extract_root.join(format!("python.{}", &distribution_hash[0..12]));
 
     Ok((path, distribution_path))
 }
@@ -446,6 +755,11 @@ type DistributionCacheValue = Arc<Mutex<Option<Arc<StandaloneDistribution>>>>;
 pub struct DistributionCache {
     cache: Mutex<HashMap<DistributionCacheKey, DistributionCacheValue>>,
     default_dest_dir: Option<PathBuf>,
+    /// Directory distributions that aren't extracted yet are extracted to.
+    ///
+    /// If set, extraction directories are left untouched. The directory is
+    /// deleted with the cache.
+    scratch_dir: Option<tempdir::TempDir>,
 }
 
 impl DistributionCache {
@@ -453,9 +767,21 @@ impl DistributionCache {
         Self {
             cache: Mutex::new(HashMap::new()),
             default_dest_dir: default_dest_dir.clone().map(|x| x.to_path_buf()),
+            scratch_dir: None,
         }
     }
 
+    /// Create an instance for dry runs, which don't extract distributions.
+    ///
+    /// Distributions already extracted are used as is. Others are
+    /// extracted to a temporary directory deleted with the cache.
+    pub fn new_dry_run(default_dest_dir: Option<&Path>) -> Result<Self> {
+        Ok(Self {
+            scratch_dir: Some(tempdir::TempDir::new("pyoxidizer-dry-run")?),
+            ..Self::new(default_dest_dir)
+        })
+    }
+
     /// Resolve a `PythonDistribution` given its source and storage locations.
     pub fn resolve_distribution(
         &self,
@@ -530,9 +856,17 @@ impl DistributionCache {
         if let Some(dist) = value {
             Ok(dist.clone())
         } else {
-            let dist = Arc::new(StandaloneDistribution::from_location(
-                logger, location, &dest_dir,
-            )?);
+            let _timer = PhaseTimer::new(PHASE_DISTRIBUTION);
+
+            let dist = Arc::new(match &self.scratch_dir {
+                Some(scratch_dir) => StandaloneDistribution::from_location_without_extracting(
+                    logger,
+                    location,
+                    dest_dir,
+                    scratch_dir.path(),
+                )?,
+                None => StandaloneDistribution::from_location(logger, location, &dest_dir)?,
+            });
 
             lock.replace(dist.clone());
 
@@ -559,11 +893,18 @@ pub fn resolve_distribution(
 pub fn default_distribution_location(
     flavor: &DistributionFlavor,
     target: &str,
-    python_major_minor_version: Option<&str>,
+    python_version: Option<&str>,
 ) -> Result<PythonDistributionLocation> {
     let dist = PYTHON_DISTRIBUTIONS
-        .find_distribution(target, flavor, python_major_minor_version)
-        .ok_or_else(|| anyhow!("could not find default Python distribution for {}", target))?;
+        .find_distribution(target, flavor, python_version)
+        .ok_or_else(|| match python_version {
+            Some(version) => anyhow!(
+                "could not find default Python {} distribution for {}",
+                version,
+                target
+            ),
+            None => anyhow!("could not find default Python distribution for {}", target),
+        })?;
 
     Ok(dist.location)
 }
@@ -578,4 +919,51 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_download_helpers() {
+        assert_eq!(download_backoff(1), Duration::from_secs(2));
+        assert_eq!(download_backoff(4), Duration::from_secs(16));
+        assert_eq!(download_backoff(10), MAX_DOWNLOAD_BACKOFF);
+
+        assert_eq!(
+            parse_content_range_start("bytes 1024-2047/2048"),
+            Some(1024)
+        );
+        assert_eq!(parse_content_range_start("bytes */2048"), None);
+        assert_eq!(parse_content_range_start("items 0-1/2"), None);
+
+        assert!(
+            describe_hash_mismatch("https://example.com/a", "00", "11", b"\n<!DOCTYPE html>")
+                .contains("HTML page")
+        );
+        assert!(
+            describe_hash_mismatch("https://example.com/a", "00", "11", b"\x28\xb5\x2f\xfd")
+                .contains("modified in transit")
+        );
+    }
+
+    #[test]
+    fn test_find_extracted_distributions() -> Result<()> {
+        let distribution = get_default_distribution()?;
+        let dir = distribution
+            .base_dir
+            .parent()
+            .ok_or_else(|| anyhow!("unable to determine parent directory"))?;
+
+        let extracted = find_extracted_distributions(dir)?;
+        let entry = extracted
+            .iter()
+            .find(|d| d.path == distribution.base_dir)
+            .ok_or_else(|| anyhow!("default distribution not found"))?;
+
+        let summary = entry.summary.as_ref().map_err(|e| anyhow!("{}", e))?;
+        assert_eq!(summary.python_version, distribution.version);
+        assert_eq!(summary.target_triple, distribution.target_triple);
+        assert!(entry.size > 0);
+
+        assert!(find_extracted_distributions(&dir.join("missing"))?.is_empty());
+
+        Ok(())
+    }
 }
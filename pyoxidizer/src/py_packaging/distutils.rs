@@ -126,6 +126,10 @@ struct DistutilsExtensionState {
     runtime_library_dirs: Vec<String>,
 }
 
+/// Read extension modules built by our modified distutils.
+///
+/// Object files are read into memory, as they live in temporary build
+/// directories removed before linking.
 pub fn read_built_extensions(state_dir: &Path) -> Result<Vec<PythonExtensionModule>> {
     let mut res = Vec::new();
 
@@ -137,7 +141,7 @@ pub fn read_built_extensions(state_dir: &Path) -> Result<Vec<PythonExtensionModu
     for entry in entries {
         let entry = entry?;
         let path = entry.path();
-        let file_name = path.file_name().unwrap().to_str().unwrap();
+        let file_name = entry.file_name().to_string_lossy().to_string();
 
         if !file_name.starts_with("extension.") || !file_name.ends_with(".json") {
             continue;
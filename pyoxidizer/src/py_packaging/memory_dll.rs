@@ -0,0 +1,272 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Compatibility of Windows DLLs with loading from memory.
+
+When `allow_in_memory_shared_library_loading` is enabled, extension modules
+and the shared libraries they depend on are loaded from memory at run-time.
+The loader in `pyembed` emulates the parts of the Windows loader most DLLs
+need. DLLs relying on other features fail to load or crash, so they are
+detected at build time and installed on the filesystem instead.
+*/
+
+use {
+    super::windows_runtime::{is_ucrt_dll, is_vc_runtime_dll},
+    anyhow::{Context, Result},
+    python_packaging::resource::PythonExtensionModule,
+    std::collections::BTreeSet,
+};
+
+/// DLLs that are part of Windows and are loaded from the system.
+const SYSTEM_DLLS: &[&str] = &[
+    "advapi32.dll",
+    "bcrypt.dll",
+    "comctl32.dll",
+    "comdlg32.dll",
+    "crypt32.dll",
+    "d3d11.dll",
+    "d3d9.dll",
+    "dbghelp.dll",
+    "dwmapi.dll",
+    "dxgi.dll",
+    "gdi32.dll",
+    "gdiplus.dll",
+    "imm32.dll",
+    "iphlpapi.dll",
+    "kernel32.dll",
+    "msvcrt.dll",
+    "mswsock.dll",
+    "ncrypt.dll",
+    "netapi32.dll",
+    "normaliz.dll",
+    "ntdll.dll",
+    "ole32.dll",
+    "oleaut32.dll",
+    "opengl32.dll",
+    "powrprof.dll",
+    "psapi.dll",
+    "rpcrt4.dll",
+    "secur32.dll",
+    "setupapi.dll",
+    "shell32.dll",
+    "shlwapi.dll",
+    "user32.dll",
+    "userenv.dll",
+    "uxtheme.dll",
+    "version.dll",
+    "winhttp.dll",
+    "wininet.dll",
+    "winmm.dll",
+    "winspool.drv",
+    "ws2_32.dll",
+    "wtsapi32.dll",
+];
+
+/// Whether a DLL is provided by Windows, the C runtime or Python.
+fn is_system_dll(name: &str) -> bool {
+    let name = name.to_lowercase();
+
+    SYSTEM_DLLS.contains(&name.as_str())
+        || name.starts_with("api-ms-win-")
+        || name.starts_with("ext-ms-win-")
+        || (name.starts_with("python3") && name.ends_with(".dll"))
+        || is_vc_runtime_dll(&name)
+        || is_ucrt_dll(&name)
+}
+
+/// A reason a DLL can't be loaded from memory.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MemoryLoadingIssue {
+    /// The DLL uses implicit thread local storage (`__declspec(thread)`).
+    ///
+    /// The loader doesn't allocate TLS slots for DLLs loaded from memory.
+    ThreadLocalStorage,
+
+    /// The DLL contains .NET code, which only the Windows loader can load.
+    ManagedCode,
+
+    /// The DLL has no relocations, so it can only be loaded at its
+    /// preferred address.
+    NoRelocations,
+
+    /// The DLL imports a DLL that isn't loaded from memory.
+    ///
+    /// The dependency is searched for next to the executable instead of
+    /// next to the DLL, so it likely isn't found.
+    UnresolvedDependency(String),
+}
+
+impl std::fmt::Display for MemoryLoadingIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::ThreadLocalStorage => write!(f, "uses thread local storage"),
+            Self::ManagedCode => write!(f, "contains .NET code"),
+            Self::NoRelocations => write!(f, "has no relocations"),
+            Self::UnresolvedDependency(name) => {
+                write!(f, "imports {}, which is not loaded from memory", name)
+            }
+        }
+    }
+}
+
+/// Features of a DLL relevant to loading it from memory.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DllFeatures {
+    /// Names of imported DLLs.
+    pub libraries: Vec<String>,
+
+    /// Whether the DLL has a TLS directory.
+    pub tls: bool,
+
+    /// Whether the DLL has a CLR runtime header.
+    pub clr: bool,
+
+    /// Whether the DLL has base relocations.
+    pub relocations: bool,
+}
+
+impl DllFeatures {
+    /// Parse features from PE data.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let pe = goblin::pe::PE::parse(data)?;
+
+        let mut features = Self {
+            libraries: pe.libraries.iter().map(|l| (*l).to_string()).collect(),
+            ..Self::default()
+        };
+
+        if let Some(optional_header) = &pe.header.optional_header {
+            let directories = &optional_header.data_directories;
+            let present = |directory: &Option<goblin::pe::data_directories::DataDirectory>| {
+                directory.map(|d| d.size > 0).unwrap_or(false)
+            };
+
+            features.tls = present(directories.get_tls_table());
+            features.clr = present(directories.get_clr_runtime_header());
+            features.relocations = present(directories.get_base_relocation_table());
+        }
+
+        Ok(features)
+    }
+
+    /// Find reasons the DLL can't be loaded from memory.
+    ///
+    /// `memory_libraries` are the filenames of DLLs that are loaded from
+    /// memory alongside it.
+    pub fn memory_loading_issues(
+        &self,
+        memory_libraries: &BTreeSet<String>,
+    ) -> Vec<MemoryLoadingIssue> {
+        let mut issues = vec![];
+
+        if self.tls {
+            issues.push(MemoryLoadingIssue::ThreadLocalStorage);
+        }
+        if self.clr {
+            issues.push(MemoryLoadingIssue::ManagedCode);
+        }
+        if !self.relocations {
+            issues.push(MemoryLoadingIssue::NoRelocations);
+        }
+
+        for library in &self.libraries {
+            if !is_system_dll(library) && !memory_libraries.contains(&library.to_lowercase()) {
+                issues.push(MemoryLoadingIssue::UnresolvedDependency(library.clone()));
+            }
+        }
+
+        issues
+    }
+}
+
+/// Find reasons an extension module can't be loaded from memory.
+///
+/// The extension module's shared library and the shared libraries it links
+/// are all checked, since they are loaded from memory together.
+pub fn extension_module_memory_loading_issues(
+    em: &PythonExtensionModule,
+) -> Result<Vec<MemoryLoadingIssue>> {
+    let libraries = em
+        .link_libraries
+        .iter()
+        .filter_map(|l| l.dynamic_library.as_ref().map(|data| (l, data)))
+        .collect::<Vec<_>>();
+
+    let memory_libraries = libraries
+        .iter()
+        .map(|(l, _)| match &l.dynamic_filename {
+            Some(filename) => filename
+                .file_name()
+                .map(|f| f.to_string_lossy().to_lowercase())
+                .unwrap_or_default(),
+            None => format!("{}.dll", l.name.to_lowercase()),
+        })
+        .collect::<BTreeSet<_>>();
+
+    let mut issues = vec![];
+
+    if let Some(shared_library) = &em.shared_library {
+        let data = shared_library.resolve()?;
+        issues.extend(
+            DllFeatures::parse(&data)
+                .with_context(|| format!("parsing shared library of {}", em.name))?
+                .memory_loading_issues(&memory_libraries),
+        );
+    }
+
+    for (library, data) in libraries {
+        let data = data.resolve()?;
+        issues.extend(
+            DllFeatures::parse(&data)
+                .with_context(|| format!("parsing {} linked by {}", library.name, em.name))?
+                .memory_loading_issues(&memory_libraries),
+        );
+    }
+
+    Ok(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_loading_issues() {
+        let memory_libraries = ["libcrypto-1_1.dll".to_string()]
+            .iter()
+            .cloned()
+            .collect::<BTreeSet<_>>();
+
+        let mut features = DllFeatures {
+            libraries: vec![
+                "KERNEL32.dll".to_string(),
+                "python39.dll".to_string(),
+                "VCRUNTIME140.dll".to_string(),
+                "api-ms-win-crt-runtime-l1-1-0.dll".to_string(),
+                "libcrypto-1_1.dll".to_string(),
+            ],
+            tls: false,
+            clr: false,
+            relocations: true,
+        };
+        assert!(features.memory_loading_issues(&memory_libraries).is_empty());
+
+        features
+            .libraries
+            .push("libopenblas.XYZ.gfortran-win_amd64.dll".to_string());
+        features.tls = true;
+        features.relocations = false;
+        assert_eq!(
+            features.memory_loading_issues(&memory_libraries),
+            vec![
+                MemoryLoadingIssue::ThreadLocalStorage,
+                MemoryLoadingIssue::NoRelocations,
+                MemoryLoadingIssue::UnresolvedDependency(
+                    "libopenblas.XYZ.gfortran-win_amd64.dll".to_string()
+                ),
+            ]
+        );
+    }
+}
@@ -14,7 +14,15 @@ pub mod distribution;
 pub mod distutils;
 pub mod filtering;
 pub mod libpython;
+pub mod memory_dll;
 pub mod packaging_tool;
+pub mod policy_file;
+pub mod portable;
+pub mod qt;
 pub mod resource;
+pub mod sandbox;
 pub mod standalone_builder;
 pub mod standalone_distribution;
+pub mod terminfo;
+pub mod wasi;
+pub mod windows_runtime;
@@ -11,16 +11,22 @@ use {
         binary::LibpythonLinkMode,
         distribution::{download_distribution, PythonDistribution},
         distutils::read_built_extensions,
+        sandbox::{resolve_sandbox_backend, SandboxSpec},
         standalone_distribution::resolve_python_paths,
     },
-    crate::python_distributions::GET_PIP_PY_19,
+    crate::{
+        environment::{is_secret_env_var, user_cache_dir, PYOXIDIZER_VERSION},
+        paths::long_path,
+        python_distributions::GET_PIP_PY_19,
+    },
     anyhow::{anyhow, Context, Result},
     duct::cmd,
     python_packaging::{
-        filesystem_scanning::find_python_resources, policy::PythonPackagingPolicy,
+        filesystem_scanning::find_python_resources, policy::PythonPackagingPolicy, pywin32,
         resource::PythonResource, wheel::WheelArchive,
     },
-    slog::warn,
+    sha2::{Digest, Sha256},
+    slog::{debug, warn},
     std::{
         collections::{hash_map::RandomState, HashMap},
         hash::BuildHasher,
@@ -82,7 +88,7 @@ pub fn bootstrap_packaging_tools(
     lib_dir: &Path,
 ) -> Result<()> {
     let get_pip_py_path =
-        download_distribution(&GET_PIP_PY_19.url, &GET_PIP_PY_19.sha256, cache_dir)?;
+        download_distribution(logger, &GET_PIP_PY_19.url, &GET_PIP_PY_19.sha256, cache_dir)?;
 
     let temp_dir = tempdir::TempDir::new("pyoxidizer-bootstrap-packaging")?;
 
@@ -178,6 +184,8 @@ pub fn bootstrap_packaging_tools(
 }
 
 /// Find resources installed as part of a packaging operation.
+///
+/// Errors if packages have licenses the policy doesn't allow.
 pub fn find_resources<'a>(
     dist: &dyn PythonDistribution,
     policy: &PythonPackagingPolicy,
@@ -196,8 +204,9 @@ pub fn find_resources<'a>(
         HashMap::new()
     };
 
+    // Deep site-packages trees exceed the path length limit of Windows.
     for r in find_python_resources(
-        &path,
+        &long_path(path),
         dist.cache_tag(),
         &dist.python_module_suffixes()?,
         policy.file_scanner_emit_files(),
@@ -220,9 +229,54 @@ pub fn find_resources<'a>(
         }
     }
 
+    // pywin32 relies on a .pth file and a post-install script to be usable.
+    if pywin32::has_pywin32(&res) {
+        let dlls = pywin32::read_system32_dlls(path)?
+            .into_iter()
+            .map(|mut file| {
+                file.data = file.data.to_memory()?;
+                Ok(file)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        res = pywin32::fixup_resources(res, &dlls);
+    }
+
+    policy.check_package_licenses(&res)?;
+
     Ok(res)
 }
 
+/// Run Python with `pip_args`, logging its output.
+fn run_pip(
+    logger: &slog::Logger,
+    host_dist: &dyn PythonDistribution,
+    pip_args: &[String],
+) -> Result<()> {
+    warn!(logger, "running python {:?}", pip_args);
+
+    let command = cmd(host_dist.python_exe_path(), pip_args)
+        .stderr_to_stdout()
+        .unchecked()
+        .reader()?;
+
+    {
+        let reader = BufReader::new(&command);
+        for line in reader.lines() {
+            warn!(logger, "{}", line?);
+        }
+    }
+
+    let output = command
+        .try_wait()?
+        .ok_or_else(|| anyhow!("unable to wait on command"))?;
+    if !output.status.success() {
+        return Err(anyhow!("error running pip"));
+    }
+
+    Ok(())
+}
+
 /// Run `pip download` and collect resources found from downloaded packages.
 ///
 /// `host_dist` is the Python distribution to use to run `pip`.
@@ -246,70 +300,93 @@ pub fn pip_download<'a>(
 
     host_dist.ensure_pip(logger)?;
 
-    let target_dir = temp_dir.path();
+    let pip_args = |target_dir: &Path, abi: Option<&str>| {
+        let mut pip_args = vec![
+            "-m".to_string(),
+            "pip".to_string(),
+            "--disable-pip-version-check".to_string(),
+        ];
 
-    warn!(logger, "pip downloading to {}", target_dir.display());
+        if verbose {
+            pip_args.push("--verbose".to_string());
+        }
 
-    let mut pip_args = vec![
-        "-m".to_string(),
-        "pip".to_string(),
-        "--disable-pip-version-check".to_string(),
-    ];
+        pip_args.extend(vec![
+            "download".to_string(),
+            // Download packages to our temporary directory.
+            "--dest".to_string(),
+            format!("{}", target_dir.display()),
+            // Only download wheels.
+            "--only-binary=:all:".to_string(),
+            // We download files compatible with the distribution we're targeting.
+            format!(
+                "--platform={}",
+                taget_dist.python_platform_compatibility_tag()
+            ),
+            format!("--python-version={}", taget_dist.python_version()),
+            format!(
+                "--implementation={}",
+                taget_dist.python_implementation_short()
+            ),
+        ]);
+
+        if let Some(abi) = abi {
+            pip_args.push(format!("--abi={}", abi));
+        }
 
-    if verbose {
-        pip_args.push("--verbose".to_string());
-    }
+        pip_args.extend(args.iter().cloned());
 
-    pip_args.extend(vec![
-        "download".to_string(),
-        // Download packages to our temporary directory.
-        "--dest".to_string(),
-        format!("{}", target_dir.display()),
-        // Only download wheels.
-        "--only-binary=:all:".to_string(),
-        // We download files compatible with the distribution we're targeting.
-        format!(
-            "--platform={}",
-            taget_dist.python_platform_compatibility_tag()
-        ),
-        format!("--python-version={}", taget_dist.python_version()),
-        format!(
-            "--implementation={}",
-            taget_dist.python_implementation_short()
-        ),
-    ]);
+        pip_args
+    };
 
-    if let Some(abi) = taget_dist.python_abi_tag() {
-        pip_args.push(format!("--abi={}", abi));
+    // pip accepts wheels for the stable ABI and pure Python wheels whatever
+    // ABI is requested, and picks any of them. So to prefer the stable ABI,
+    // first download while only accepting it and fall back to the ABI of
+    // the distribution if a requirement has no such wheel.
+    let mut target_dir = None;
+
+    if policy.prefer_abi3_extensions() {
+        let abi3_dir = temp_dir.path().join("abi3");
+        warn!(
+            logger,
+            "pip downloading abi3 wheels to {}",
+            abi3_dir.display()
+        );
+
+        match run_pip(logger, host_dist, &pip_args(&abi3_dir, Some("abi3"))) {
+            Ok(()) => {
+                target_dir = Some(abi3_dir);
+            }
+            Err(e) => {
+                warn!(
+                    logger,
+                    "unable to download only abi3 wheels ({}); downloading wheels for any ABI", e
+                );
+            }
+        }
     }
 
-    pip_args.extend(args.iter().cloned());
+    let target_dir = match target_dir {
+        Some(target_dir) => target_dir,
+        None => {
+            let target_dir = temp_dir.path().join("any");
+            warn!(logger, "pip downloading to {}", target_dir.display());
 
-    warn!(logger, "running python {:?}", pip_args);
-
-    let command = cmd(host_dist.python_exe_path(), &pip_args)
-        .stderr_to_stdout()
-        .reader()?;
+            run_pip(
+                logger,
+                host_dist,
+                &pip_args(&target_dir, taget_dist.python_abi_tag()),
+            )?;
 
-    {
-        let reader = BufReader::new(&command);
-        for line in reader.lines() {
-            warn!(logger, "{}", line?);
+            target_dir
         }
-    }
-
-    let output = command
-        .try_wait()?
-        .ok_or_else(|| anyhow!("unable to wait on command"))?;
-    if !output.status.success() {
-        return Err(anyhow!("error running pip"));
-    }
+    };
 
     // Since we used --only-binary=:all: above, we should only have .whl files
     // in the destination directory. Iterate over them and collect resources
     // from each.
 
-    let mut files = std::fs::read_dir(target_dir)?
+    let mut files = std::fs::read_dir(&target_dir)?
         .map(|entry| Ok(entry?.path()))
         .collect::<Result<Vec<_>>>()?;
     files.sort();
@@ -320,19 +397,160 @@ pub fn pip_download<'a>(
     for path in &files {
         let wheel = WheelArchive::from_path(path)?;
 
-        res.extend(wheel.python_resources(
+        let mut resources = wheel.python_resources(
             taget_dist.cache_tag(),
             &taget_dist.python_module_suffixes()?,
             policy.file_scanner_emit_files(),
             policy.file_scanner_classify_files(),
-        )?);
+        )?;
+
+        if pywin32::has_pywin32(&resources) {
+            let mut files = wheel.regular_files();
+            files.extend(wheel.purelib_files());
+            files.extend(wheel.platlib_files());
+
+            resources = pywin32::fixup_resources(resources, &pywin32::system32_dlls(&files));
+        }
+
+        res.extend(resources);
     }
 
+    policy.check_package_licenses(&res)?;
+
     Ok(res)
 }
 
-/// Run `pip install` and return found resources.
-pub fn pip_install<'a, S: BuildHasher>(
+/// A packaging tool invocation installing into a temporary prefix.
+struct PreparedInstall {
+    /// Holds the prefix. The directory is deleted when dropped.
+    temp_dir: tempdir::TempDir,
+    description: String,
+    program: PathBuf,
+    args: Vec<String>,
+    cwd: Option<PathBuf>,
+    env: HashMap<String, String>,
+    /// Directory to scan for installed resources.
+    scan_path: PathBuf,
+    state_dir: Option<PathBuf>,
+}
+
+impl PreparedInstall {
+    fn expression(&self) -> duct::Expression {
+        let expression = cmd(&self.program, &self.args).full_env(&self.env);
+
+        match &self.cwd {
+            Some(cwd) => expression.dir(cwd),
+            None => expression,
+        }
+    }
+
+    /// Run the command in a sandbox if the packaging policy asks for it.
+    ///
+    /// The sandboxed command can only write to the temporary directory of
+    /// the install and to `cwd`, which `setup.py` builds in. Environment
+    /// variables that look like credentials are removed, unless passed in
+    /// `extra_envs`.
+    fn sandbox<S: BuildHasher>(
+        &mut self,
+        logger: &slog::Logger,
+        policy: &PythonPackagingPolicy,
+        extra_envs: &HashMap<String, String, S>,
+    ) -> Result<()> {
+        let backend = match resolve_sandbox_backend(logger, policy.build_sandbox())? {
+            Some(backend) => backend,
+            None => return Ok(()),
+        };
+
+        let temp_path = self.temp_dir.path().to_path_buf();
+        let tmp_dir = temp_path.join("tmp");
+        std::fs::create_dir_all(&tmp_dir)
+            .with_context(|| format!("creating {}", tmp_dir.display()))?;
+
+        for key in &["TMPDIR", "TMP", "TEMP"] {
+            self.env
+                .insert(key.to_string(), tmp_dir.display().to_string());
+        }
+        // pip caches downloads in the user cache directory, which isn't writable.
+        self.env.insert(
+            "XDG_CACHE_HOME".to_string(),
+            temp_path.join("cache").display().to_string(),
+        );
+        self.env
+            .retain(|key, _| !is_secret_env_var(key) || extra_envs.contains_key(key));
+
+        let mut writable_paths = vec![temp_path.as_path()];
+        if let Some(cwd) = &self.cwd {
+            writable_paths.push(cwd);
+        }
+
+        let spec = SandboxSpec::new(policy.build_sandbox_allow_network(), &writable_paths);
+        let (program, args) = backend.wrap(&spec, &self.program, &self.args);
+
+        warn!(
+            logger,
+            "sandboxing {} (network {})",
+            self.description,
+            if spec.allow_network {
+                "allowed"
+            } else {
+                "denied"
+            }
+        );
+
+        self.program = program;
+        self.args = args;
+
+        Ok(())
+    }
+
+    /// Record the command in the build log.
+    fn log_command(&self, logger: &slog::Logger) {
+        debug!(
+            logger,
+            "running {} {:?} in {:?}",
+            self.program.display(),
+            self.args,
+            self.cwd
+        );
+    }
+
+    /// Run the command, logging its output as it is produced.
+    fn run(&self, logger: &slog::Logger) -> Result<()> {
+        self.log_command(logger);
+        let command = self.expression().stderr_to_stdout().reader()?;
+        {
+            let reader = BufReader::new(&command);
+            for line in reader.lines() {
+                warn!(logger, "{}", line?);
+            }
+        }
+
+        let output = command
+            .try_wait()?
+            .ok_or_else(|| anyhow!("unable to wait on command"))?;
+        if !output.status.success() {
+            return Err(anyhow!("error running {}", self.description));
+        }
+
+        Ok(())
+    }
+
+    fn find_resources<'a>(
+        &self,
+        logger: &slog::Logger,
+        dist: &dyn PythonDistribution,
+        policy: &PythonPackagingPolicy,
+    ) -> Result<Vec<PythonResource<'a>>> {
+        warn!(
+            logger,
+            "scanning {} for resources",
+            self.scan_path.display()
+        );
+        find_resources(dist, policy, &self.scan_path, self.state_dir.clone())
+    }
+}
+
+fn prepare_pip_install<S: BuildHasher>(
     logger: &slog::Logger,
     dist: &dyn PythonDistribution,
     policy: &PythonPackagingPolicy,
@@ -340,11 +558,10 @@ pub fn pip_install<'a, S: BuildHasher>(
     verbose: bool,
     install_args: &[String],
     extra_envs: &HashMap<String, String, S>,
-) -> Result<Vec<PythonResource<'a>>> {
+    cwd: &Path,
+) -> Result<PreparedInstall> {
     let temp_dir = tempdir::TempDir::new("pyoxidizer-pip-install")?;
 
-    dist.ensure_pip(logger)?;
-
     let mut env: HashMap<String, String, RandomState> = HashMap::from_iter(std::env::vars());
     for (k, v) in dist.resolve_distutils(logger, libpython_link_mode, temp_dir.path(), &[])? {
         env.insert(k, v);
@@ -376,30 +593,372 @@ pub fn pip_install<'a, S: BuildHasher>(
 
     pip_args.extend(install_args.iter().cloned());
 
-    let command = cmd(dist.python_exe_path(), &pip_args)
-        .full_env(&env)
-        .stderr_to_stdout()
-        .reader()?;
-    {
-        let reader = BufReader::new(&command);
-        for line in reader.lines() {
-            warn!(logger, "{}", line?);
+    let state_dir = env.get("PYOXIDIZER_DISTUTILS_STATE_DIR").map(PathBuf::from);
+
+    let mut install = PreparedInstall {
+        temp_dir,
+        description: format!("pip install {}", install_args.join(" ")),
+        program: dist.python_exe_path().to_path_buf(),
+        args: pip_args,
+        cwd: None,
+        env,
+        scan_path: target_dir,
+        state_dir,
+    };
+    install.sandbox(logger, policy, extra_envs)?;
+    // Set after sandboxing, as pip doesn't need to write to it.
+    install.cwd = Some(cwd.to_path_buf());
+
+    Ok(install)
+}
+
+/// Environment variable that disables reusing `pip install` results between builds.
+pub const NO_PIP_CACHE_ENV: &str = "PYOXIDIZER_NO_PIP_CACHE";
+
+/// `pip install` options taking a value in the following argument.
+const PIP_VALUE_OPTIONS: &[&str] = &[
+    "-i",
+    "--index-url",
+    "--extra-index-url",
+    "-f",
+    "--find-links",
+    "--no-binary",
+    "--only-binary",
+    "--platform",
+    "--python-version",
+    "--implementation",
+    "--abi",
+    "--trusted-host",
+    "--upgrade-strategy",
+    "--progress-bar",
+    "--global-option",
+    "--install-option",
+];
+
+/// Resolve the requirements and constraints files named by `pip install` arguments.
+///
+/// Relative paths are resolved against `cwd`. Returns `None` if the
+/// arguments install from local paths or in editable mode, since changes
+/// to those can't be detected.
+fn pip_argument_files(args: &[String], cwd: &Path) -> Option<Vec<PathBuf>> {
+    let mut files = vec![];
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let arg = arg.as_str();
+
+        if ["-r", "--requirement", "-c", "--constraint"].contains(&arg) {
+            files.push(cwd.join(args.next()?));
+        } else if let Some(path) = arg
+            .strip_prefix("--requirement=")
+            .or_else(|| arg.strip_prefix("--constraint="))
+            .or_else(|| arg.strip_prefix("-r"))
+            .or_else(|| arg.strip_prefix("-c"))
+        {
+            files.push(cwd.join(path));
+        } else if arg == "-e" || arg.starts_with("--editable") {
+            return None;
+        } else if !arg.starts_with('-') && cwd.join(arg).exists() {
+            return None;
         }
     }
 
-    let output = command
-        .try_wait()?
-        .ok_or_else(|| anyhow!("unable to wait on command"))?;
-    if !output.status.success() {
-        return Err(anyhow!("error running pip"));
+    Some(files)
+}
+
+/// Whether a requirement specifier pins an exact version.
+fn is_pinned_requirement(requirement: &str) -> bool {
+    let spec = requirement.split(';').next().unwrap_or_default();
+    let spec = spec.split(" --").next().unwrap_or_default().trim();
+
+    if spec.contains("===") {
+        true
+    } else {
+        spec.contains("==") && !spec.contains(|c| ['*', '<', '>', '~', '!', ','].contains(&c))
     }
+}
 
-    let state_dir = match env.get("PYOXIDIZER_DISTUTILS_STATE_DIR") {
-        Some(p) => Some(PathBuf::from(p)),
-        None => None,
+/// Whether every requirement in a requirements file pins an exact version.
+///
+/// Nested requirements, constraints and editable installs count as unpinned.
+fn requirements_file_pinned(path: &Path) -> bool {
+    let data = match std::fs::read_to_string(path) {
+        Ok(data) => data,
+        Err(_) => return false,
     };
 
-    find_resources(dist, policy, &target_dir, state_dir)
+    data.replace("\\\r\n", " ")
+        .replace("\\\n", " ")
+        .lines()
+        .map(|line| match line.find(" #") {
+            Some(pos) => &line[0..pos],
+            None if line.trim_start().starts_with('#') => "",
+            None => line,
+        })
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .all(|line| {
+            if line.starts_with('-') {
+                ![
+                    "-r",
+                    "--requirement",
+                    "-c",
+                    "--constraint",
+                    "-e",
+                    "--editable",
+                ]
+                .iter()
+                .any(|option| line.starts_with(option))
+            } else {
+                is_pinned_requirement(line)
+            }
+        })
+}
+
+/// Whether every requirement named by `pip install` arguments pins an exact version.
+///
+/// Requirements files named with `-r` are resolved against `cwd` and
+/// must only contain pinned requirements.
+fn pip_requirements_pinned(args: &[String], cwd: &Path) -> bool {
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let arg = arg.as_str();
+
+        if ["-r", "--requirement"].contains(&arg) {
+            match args.next() {
+                Some(path) if requirements_file_pinned(&cwd.join(path)) => {}
+                _ => return false,
+            }
+        } else if let Some(path) = arg
+            .strip_prefix("--requirement=")
+            .or_else(|| arg.strip_prefix("-r"))
+        {
+            if !requirements_file_pinned(&cwd.join(path)) {
+                return false;
+            }
+        } else if ["-c", "--constraint"].contains(&arg) || PIP_VALUE_OPTIONS.contains(&arg) {
+            args.next();
+        } else if !arg.starts_with('-') && !is_pinned_requirement(arg) {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// A `pip install` target directory kept in the user cache.
+///
+/// Installing the same pinned requirements with the same distribution
+/// produces the same files. So the target directory of an install is
+/// kept and later builds scan it instead of running pip again. Installs
+/// of unpinned requirements aren't cached because new releases wouldn't
+/// be detected. Caching is disabled with `NO_PIP_CACHE_ENV`. Entries are keyed by the
+/// pip arguments, the content of requirements files they name, the
+/// distribution, the target triple and the environment pip runs with.
+struct PipCacheEntry {
+    path: PathBuf,
+}
+
+impl PipCacheEntry {
+    /// Resolve the cache entry for a `pip install`, if it can be cached.
+    fn resolve<S: BuildHasher>(
+        logger: &slog::Logger,
+        dist: &dyn PythonDistribution,
+        libpython_link_mode: LibpythonLinkMode,
+        install_args: &[String],
+        extra_envs: &HashMap<String, String, S>,
+        cwd: &Path,
+    ) -> Result<Option<Self>> {
+        if std::env::var(NO_PIP_CACHE_ENV).is_ok() {
+            return Ok(None);
+        }
+
+        let files = match pip_argument_files(install_args, cwd) {
+            Some(files) => files,
+            None => {
+                warn!(
+                    logger,
+                    "not caching pip install of local paths: {}",
+                    install_args.join(" ")
+                );
+                return Ok(None);
+            }
+        };
+
+        if !pip_requirements_pinned(install_args, cwd) {
+            warn!(
+                logger,
+                "not caching pip install of unpinned requirements: {}",
+                install_args.join(" ")
+            );
+            return Ok(None);
+        }
+
+        let python_exe_data = std::fs::read(dist.python_exe_path())
+            .with_context(|| format!("reading {}", dist.python_exe_path().display()))?;
+
+        let mut inputs = vec![
+            format!("pyoxidizer={}", *PYOXIDIZER_VERSION),
+            format!("python={}", hex::encode(Sha256::digest(&python_exe_data))),
+            format!("target={}", dist.target_triple()),
+            format!("link-mode={:?}", libpython_link_mode),
+        ];
+        inputs.extend(install_args.iter().map(|arg| format!("arg={}", arg)));
+
+        for path in files {
+            let data =
+                std::fs::read(&path).with_context(|| format!("reading {}", path.display()))?;
+            inputs.push(format!(
+                "file={}={}",
+                path.display(),
+                hex::encode(Sha256::digest(&data))
+            ));
+        }
+
+        let mut envs = std::env::vars()
+            .filter(|(k, _)| k.starts_with("PIP_"))
+            .chain(extra_envs.iter().map(|(k, v)| (k.clone(), v.clone())))
+            .map(|(k, v)| format!("env={}={}", k, v))
+            .collect::<Vec<_>>();
+        envs.sort();
+        inputs.extend(envs);
+
+        let key = hex::encode(Sha256::digest(inputs.join("\n").as_bytes()));
+
+        Ok(Some(Self {
+            path: user_cache_dir()?.join("pip-installs").join(&key[0..16]),
+        }))
+    }
+
+    /// The cached pip target directory.
+    fn install_dir(&self) -> PathBuf {
+        self.path.join("install")
+    }
+
+    /// Whether the entry holds the result of a previous install.
+    fn is_populated(&self) -> bool {
+        self.install_dir().is_dir()
+    }
+
+    /// Find resources in the cached pip target directory.
+    fn find_resources<'a>(
+        &self,
+        logger: &slog::Logger,
+        dist: &dyn PythonDistribution,
+        policy: &PythonPackagingPolicy,
+    ) -> Result<Vec<PythonResource<'a>>> {
+        warn!(logger, "reusing pip install from {}", self.path.display());
+        find_resources(dist, policy, &self.install_dir(), None)
+    }
+
+    /// Copy the target directory of a completed install into the cache.
+    ///
+    /// Installs compiling extension modules aren't cached, as the compiled
+    /// object files live outside the target directory.
+    fn store(&self, logger: &slog::Logger, install: &PreparedInstall) -> Result<()> {
+        if let Some(state_dir) = &install.state_dir {
+            for entry in std::fs::read_dir(state_dir)? {
+                if entry?
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with("extension.")
+                {
+                    warn!(
+                        logger,
+                        "not caching pip install compiling extension modules: {}",
+                        install.description
+                    );
+                    return Ok(());
+                }
+            }
+        }
+
+        let parent = self
+            .path
+            .parent()
+            .ok_or_else(|| anyhow!("unable to determine parent directory"))?;
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("creating {}", parent.display()))?;
+
+        // Entries are populated in a staging directory and renamed into
+        // place, so concurrent builds never see a partial entry.
+        let staging = tempdir::TempDir::new_in(parent, "staging")?;
+        let staging_install = staging.path().join("install");
+
+        for entry in walkdir::WalkDir::new(&install.scan_path) {
+            let entry = entry?;
+            let dest_path = staging_install.join(entry.path().strip_prefix(&install.scan_path)?);
+
+            if entry.file_type().is_dir() {
+                std::fs::create_dir_all(&dest_path)
+                    .with_context(|| format!("creating {}", dest_path.display()))?;
+            } else {
+                std::fs::copy(entry.path(), &dest_path)
+                    .with_context(|| format!("copying {}", entry.path().display()))?;
+            }
+        }
+
+        let staging = staging.into_path();
+        if std::fs::rename(&staging, &self.path).is_err() {
+            // Another build populated the entry first.
+            std::fs::remove_dir_all(&staging)
+                .with_context(|| format!("removing {}", staging.display()))?;
+        } else {
+            warn!(logger, "cached pip install in {}", self.path.display());
+        }
+
+        Ok(())
+    }
+}
+
+/// Run `pip install` and return found resources.
+pub fn pip_install<'a, S: BuildHasher>(
+    logger: &slog::Logger,
+    dist: &dyn PythonDistribution,
+    policy: &PythonPackagingPolicy,
+    libpython_link_mode: LibpythonLinkMode,
+    verbose: bool,
+    install_args: &[String],
+    extra_envs: &HashMap<String, String, S>,
+    cwd: &Path,
+) -> Result<Vec<PythonResource<'a>>> {
+    let cache = PipCacheEntry::resolve(
+        logger,
+        dist,
+        libpython_link_mode,
+        install_args,
+        extra_envs,
+        cwd,
+    )?;
+
+    if let Some(cache) = &cache {
+        if cache.is_populated() {
+            return cache.find_resources(logger, dist, policy);
+        }
+    }
+
+    dist.ensure_pip(logger)?;
+
+    let install = prepare_pip_install(
+        logger,
+        dist,
+        policy,
+        libpython_link_mode,
+        verbose,
+        install_args,
+        extra_envs,
+        cwd,
+    )?;
+    install.run(logger)?;
+
+    if let Some(cache) = &cache {
+        if let Err(e) = cache.store(logger, &install) {
+            warn!(logger, "unable to cache pip install: {:?}", e);
+        }
+    }
+
+    find_resources(dist, policy, &install.scan_path, install.state_dir.clone())
 }
 
 /// Discover Python resources from a populated virtualenv directory.
@@ -413,8 +972,7 @@ pub fn read_virtualenv<'a>(
     find_resources(dist, policy, &python_paths.site_packages, None)
 }
 
-/// Run `setup.py install` against a path and return found resources.
-pub fn setup_py_install<'a, S: BuildHasher>(
+fn prepare_setup_py_install<S: BuildHasher>(
     logger: &slog::Logger,
     dist: &dyn PythonDistribution,
     policy: &PythonPackagingPolicy,
@@ -423,7 +981,7 @@ pub fn setup_py_install<'a, S: BuildHasher>(
     verbose: bool,
     extra_envs: &HashMap<String, String, S>,
     extra_global_arguments: &[String],
-) -> Result<Vec<PythonResource<'a>>> {
+) -> Result<PreparedInstall> {
     if !package_path.is_absolute() {
         return Err(anyhow!(
             "package_path must be absolute: got {:?}",
@@ -461,47 +1019,253 @@ pub fn setup_py_install<'a, S: BuildHasher>(
         target_dir_s
     );
 
-    let mut args = vec!["setup.py"];
+    let mut args = vec!["setup.py".to_string()];
 
     if verbose {
-        args.push("--verbose");
+        args.push("--verbose".to_string());
     }
 
-    for arg in extra_global_arguments {
-        args.push(arg);
-    }
+    args.extend(extra_global_arguments.iter().cloned());
 
-    args.extend(&["install", "--prefix", &target_dir_s, "--no-compile"]);
+    args.extend(vec![
+        "install".to_string(),
+        "--prefix".to_string(),
+        target_dir_s,
+        "--no-compile".to_string(),
+    ]);
 
-    let command = cmd(dist.python_exe_path(), &args)
-        .dir(package_path)
-        .full_env(&envs)
-        .stderr_to_stdout()
-        .reader()?;
+    let state_dir = envs
+        .get("PYOXIDIZER_DISTUTILS_STATE_DIR")
+        .map(PathBuf::from);
+
+    let mut install = PreparedInstall {
+        temp_dir,
+        description: format!("setup.py install in {}", package_path.display()),
+        program: dist.python_exe_path().to_path_buf(),
+        args,
+        cwd: Some(package_path.to_path_buf()),
+        env: envs,
+        scan_path: python_paths.site_packages,
+        state_dir,
+    };
+    install.sandbox(logger, policy, extra_envs)?;
+
+    Ok(install)
+}
+
+/// Run `setup.py install` against a path and return found resources.
+pub fn setup_py_install<'a, S: BuildHasher>(
+    logger: &slog::Logger,
+    dist: &dyn PythonDistribution,
+    policy: &PythonPackagingPolicy,
+    libpython_link_mode: LibpythonLinkMode,
+    package_path: &Path,
+    verbose: bool,
+    extra_envs: &HashMap<String, String, S>,
+    extra_global_arguments: &[String],
+) -> Result<Vec<PythonResource<'a>>> {
+    let install = prepare_setup_py_install(
+        logger,
+        dist,
+        policy,
+        libpython_link_mode,
+        package_path,
+        verbose,
+        extra_envs,
+        extra_global_arguments,
+    )?;
+    install.run(logger)?;
+
+    install.find_resources(logger, dist, policy)
+}
+
+/// A set of packages installed independently of other sets.
+#[derive(Clone, Debug, PartialEq)]
+pub enum InstallGroup {
+    /// Arguments to `pip install`.
+    Pip(Vec<String>),
+    /// Absolute path of a directory to run `setup.py install` in.
+    SetupPy(PathBuf),
+}
+
+/// Install several groups of packages concurrently and return found resources.
+///
+/// Each group is installed into its own temporary prefix. All commands are
+/// started at once and their output is logged, prefixed with the 1-based
+/// index of the group, after they exit. `pip install` groups are reused
+/// from the cache like `pip_install()` does. Resources are merged with
+/// `merge_group_resources()`, so the result doesn't depend on the order
+/// the commands finish in.
+pub fn install_groups<'a, S: BuildHasher>(
+    logger: &slog::Logger,
+    dist: &dyn PythonDistribution,
+    policy: &PythonPackagingPolicy,
+    libpython_link_mode: LibpythonLinkMode,
+    verbose: bool,
+    groups: &[InstallGroup],
+    extra_envs: &HashMap<String, String, S>,
+    cwd: &Path,
+) -> Result<Vec<PythonResource<'a>>> {
+    let caches = groups
+        .iter()
+        .map(|group| match group {
+            InstallGroup::Pip(args) => {
+                PipCacheEntry::resolve(logger, dist, libpython_link_mode, args, extra_envs, cwd)
+            }
+            InstallGroup::SetupPy(_) => Ok(None),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let is_cached = |i: usize| caches[i].as_ref().map(|c| c.is_populated()) == Some(true);
+
+    if groups
+        .iter()
+        .enumerate()
+        .any(|(i, g)| matches!(g, InstallGroup::Pip(_)) && !is_cached(i))
     {
-        let reader = BufReader::new(&command);
-        for line in reader.lines() {
-            warn!(logger, "{}", line.unwrap());
+        dist.ensure_pip(logger)?;
+    }
+
+    let installs = groups
+        .iter()
+        .enumerate()
+        .map(|(i, group)| match group {
+            _ if is_cached(i) => Ok(None),
+            InstallGroup::Pip(args) => prepare_pip_install(
+                logger,
+                dist,
+                policy,
+                libpython_link_mode,
+                verbose,
+                args,
+                extra_envs,
+                cwd,
+            )
+            .map(Some),
+            InstallGroup::SetupPy(package_path) => prepare_setup_py_install(
+                logger,
+                dist,
+                policy,
+                libpython_link_mode,
+                package_path,
+                verbose,
+                extra_envs,
+                &[],
+            )
+            .map(Some),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let handles = installs
+        .iter()
+        .flatten()
+        .map(|install| {
+            install.log_command(logger);
+            install
+                .expression()
+                .stderr_to_stdout()
+                .stdout_capture()
+                .unchecked()
+                .start()
+                .with_context(|| format!("starting {}", install.description))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut failed = vec![];
+    let mut handles = handles.iter();
+    for (i, install) in installs.iter().enumerate() {
+        let install = match install {
+            Some(install) => install,
+            None => continue,
+        };
+        let handle = handles
+            .next()
+            .ok_or_else(|| anyhow!("missing process handle"))?;
+
+        let output = handle
+            .wait()
+            .with_context(|| format!("waiting on {}", install.description))?;
+
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            warn!(logger, "[{}] {}", i + 1, line);
+        }
+
+        if !output.status.success() {
+            failed.push(format!("group {} ({})", i + 1, install.description));
         }
     }
 
-    let output = command
-        .try_wait()?
-        .ok_or_else(|| anyhow!("unable to wait on command"))?;
-    if !output.status.success() {
-        return Err(anyhow!("error running pip"));
+    if !failed.is_empty() {
+        return Err(anyhow!("error installing {}", failed.join(", ")));
     }
 
-    let state_dir = match envs.get("PYOXIDIZER_DISTUTILS_STATE_DIR") {
-        Some(p) => Some(PathBuf::from(p)),
-        None => None,
-    };
-    warn!(
-        logger,
-        "scanning {} for resources",
-        python_paths.site_packages.display()
-    );
-    find_resources(dist, policy, &python_paths.site_packages, state_dir)
+    let resources = installs
+        .iter()
+        .zip(caches.iter())
+        .map(|(install, cache)| match (install, cache) {
+            (Some(install), cache) => {
+                if let Some(cache) = cache {
+                    if let Err(e) = cache.store(logger, install) {
+                        warn!(logger, "unable to cache pip install: {:?}", e);
+                    }
+                }
+
+                install.find_resources(logger, dist, policy)
+            }
+            (None, Some(cache)) => cache.find_resources(logger, dist, policy),
+            (None, None) => Err(anyhow!("group was neither installed nor cached")),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(merge_group_resources(logger, resources))
+}
+
+/// Merge resources found by installing several groups.
+///
+/// Resources are ordered by group, then by the order they were found in.
+/// When several groups install a resource with the same name, the one from
+/// the first group is kept and a warning is logged if the others differ.
+pub fn merge_group_resources<'a>(
+    logger: &slog::Logger,
+    groups: Vec<Vec<PythonResource<'a>>>,
+) -> Vec<PythonResource<'a>> {
+    let mut merged: Vec<PythonResource<'a>> = vec![];
+    let mut seen = HashMap::new();
+
+    for (i, resources) in groups.into_iter().enumerate() {
+        for resource in resources {
+            let name = resource.full_name();
+
+            // Resources without a name can't conflict.
+            if name.is_empty() {
+                merged.push(resource);
+                continue;
+            }
+
+            let key = (std::mem::discriminant(&resource), name);
+
+            match seen.get(&key) {
+                Some(&(index, group)) => {
+                    if merged[index] != resource {
+                        warn!(
+                            logger,
+                            "{} is installed by groups {} and {}; using the one from group {}",
+                            key.1,
+                            group + 1,
+                            i + 1,
+                            group + 1
+                        );
+                    }
+                }
+                None => {
+                    seen.insert(key, (merged.len(), i));
+                    merged.push(resource);
+                }
+            }
+        }
+    }
+
+    merged
 }
 
 #[cfg(test)]
@@ -525,6 +1289,7 @@ mod tests {
             false,
             &["black==19.10b0".to_string()],
             &HashMap::new(),
+            &std::env::current_dir()?,
         )?;
 
         assert!(resources.iter().any(|r| r.full_name() == "appdirs"));
@@ -533,6 +1298,116 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_pip_argument_files() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let cwd = temp_dir.path();
+        std::fs::create_dir(cwd.join("pkg"))?;
+
+        let args = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            pip_argument_files(
+                &args(&[
+                    "--require-hashes",
+                    "-r",
+                    "a.txt",
+                    "--requirement=b.txt",
+                    "-cc.txt",
+                    "black==19.10b0",
+                ]),
+                cwd
+            ),
+            Some(vec![
+                cwd.join("a.txt"),
+                cwd.join("b.txt"),
+                cwd.join("c.txt")
+            ])
+        );
+        assert_eq!(pip_argument_files(&args(&["-r"]), cwd), None);
+        assert_eq!(
+            pip_argument_files(&args(&["-e", "git+https://x"]), cwd),
+            None
+        );
+        assert_eq!(pip_argument_files(&args(&["pkg"]), cwd), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pip_requirements_pinned() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let cwd = temp_dir.path();
+        std::fs::write(
+            cwd.join("pinned.txt"),
+            "# comment\n--index-url https://example.com\nblack==19.10b0 \\\n    --hash=sha256:00\nclick===7.1.2 ; python_version >= '3.8'  # trailing\n",
+        )?;
+        std::fs::write(cwd.join("unpinned.txt"), "black==19.10b0\nclick>=7\n")?;
+        std::fs::write(cwd.join("nested.txt"), "-r pinned.txt\n")?;
+
+        let args = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert!(pip_requirements_pinned(
+            &args(&[
+                "-i",
+                "https://example.com",
+                "black==19.10b0",
+                "-rpinned.txt"
+            ]),
+            cwd
+        ));
+        assert!(pip_requirements_pinned(
+            &args(&["-c", "unpinned.txt", "--requirement", "pinned.txt"]),
+            cwd
+        ));
+        assert!(!pip_requirements_pinned(&args(&["black"]), cwd));
+        assert!(!pip_requirements_pinned(&args(&["black==19.*"]), cwd));
+        assert!(!pip_requirements_pinned(&args(&["black>=19,==20"]), cwd));
+        assert!(!pip_requirements_pinned(
+            &args(&["-r", "unpinned.txt"]),
+            cwd
+        ));
+        assert!(!pip_requirements_pinned(&args(&["-r", "nested.txt"]), cwd));
+        assert!(!pip_requirements_pinned(&args(&["-r", "missing.txt"]), cwd));
+        assert!(!pip_requirements_pinned(&args(&["-r"]), cwd));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_group_resources() -> Result<()> {
+        let logger = get_logger()?;
+
+        let module = |name: &str, source: &[u8]| -> PythonResource {
+            python_packaging::resource::PythonModuleSource {
+                name: name.to_string(),
+                source: python_packaging::resource::DataLocation::Memory(source.to_vec()),
+                is_package: false,
+                cache_tag: "cpython-38".to_string(),
+                is_stdlib: false,
+                is_test: false,
+            }
+            .into()
+        };
+
+        let merged = merge_group_resources(
+            &logger,
+            vec![
+                vec![module("foo", b"first"), module("bar", b"")],
+                vec![module("baz", b""), module("foo", b"second")],
+                vec![module("bar", b"")],
+            ],
+        );
+
+        assert_eq!(
+            merged.iter().map(|r| r.full_name()).collect::<Vec<_>>(),
+            vec!["foo", "bar", "baz"]
+        );
+        assert_eq!(merged[0], module("foo", b"first"));
+
+        Ok(())
+    }
+
     #[test]
     #[cfg(windows)]
     fn test_install_cffi() -> Result<()> {
@@ -0,0 +1,425 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Reading and writing packaging policies as TOML files.
+
+This allows sharing a canonical policy across many configuration files.
+Policy files are applied on top of a base policy, normally the default
+policy of a Python distribution. Package hints registered on a policy, import traces and resource callbacks
+are not stored.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    python_packaging::{
+        location::ConcreteResourceLocation,
+        policy::{
+            BuildSandbox, ExtensionModuleFilter, PackageOverride, PythonPackagingPolicy,
+            PythonSecurityAction,
+        },
+    },
+    serde::{Deserialize, Serialize},
+    std::{collections::BTreeMap, convert::TryFrom, path::Path},
+};
+
+/// A `PackageOverride` as stored in a policy file.
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+struct PackageOverrideFile {
+    package: String,
+    include: Option<bool>,
+    include_sources: Option<bool>,
+    resources_location: Option<String>,
+    resources_location_fallback: Option<String>,
+}
+
+/// A `PythonPackagingPolicy` as stored in a policy file.
+///
+/// Settings missing from a file keep the value of the base policy. An
+/// empty string clears an optional setting.
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+    extension_module_filter: Option<String>,
+    resources_location: Option<String>,
+    resources_location_fallback: Option<String>,
+    allow_files: Option<bool>,
+    allow_in_memory_shared_library_loading: Option<bool>,
+    apply_package_hints: Option<bool>,
+    build_sandbox: Option<String>,
+    build_sandbox_allow_network: Option<bool>,
+    bytecode_optimize_level_zero: Option<bool>,
+    bytecode_optimize_level_one: Option<bool>,
+    bytecode_optimize_level_two: Option<bool>,
+    file_scanner_classify_files: Option<bool>,
+    file_scanner_emit_files: Option<bool>,
+    gettext_catalogs_location: Option<String>,
+    include_classified_resources: Option<bool>,
+    include_distribution_resources: Option<bool>,
+    include_distribution_sources: Option<bool>,
+    include_file_resources: Option<bool>,
+    include_non_distribution_sources: Option<bool>,
+    include_test: Option<bool>,
+    prefer_abi3_extensions: Option<bool>,
+    python_security_action: Option<String>,
+    python_security_baseline: Option<String>,
+    stdlib_profile: Option<String>,
+    strip_docstrings: Option<bool>,
+    #[serde(default)]
+    excluded_modules: Vec<String>,
+    #[serde(default)]
+    exclusions: Vec<String>,
+    #[serde(default)]
+    include_patterns: Vec<String>,
+    #[serde(default)]
+    exclude_patterns: Vec<String>,
+    #[serde(default)]
+    allowed_licenses: Vec<String>,
+    #[serde(default)]
+    denied_licenses: Vec<String>,
+    #[serde(default)]
+    preferred_extension_module_variants: BTreeMap<String, String>,
+    #[serde(default)]
+    broken_extensions: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    package_optimize_levels: BTreeMap<String, Vec<i32>>,
+    #[serde(default)]
+    package_overrides: Vec<PackageOverrideFile>,
+}
+
+fn parse_location(value: &str) -> Result<ConcreteResourceLocation> {
+    ConcreteResourceLocation::try_from(value).map_err(|e| anyhow!(e))
+}
+
+/// Store an optional setting, writing an empty string for `None`.
+fn optional_to_file(value: Option<String>) -> Option<String> {
+    Some(value.unwrap_or_default())
+}
+
+/// Resolve the change an optional setting in a file makes, if any.
+fn optional_from_file(value: &Option<String>) -> Option<Option<&str>> {
+    value
+        .as_deref()
+        .map(|value| if value.is_empty() { None } else { Some(value) })
+}
+
+/// Serialize a packaging policy to TOML.
+pub fn policy_to_toml(policy: &PythonPackagingPolicy) -> Result<String> {
+    let file = PolicyFile {
+        extension_module_filter: Some(policy.extension_module_filter().as_ref().to_string()),
+        resources_location: Some(policy.resources_location().to_string()),
+        resources_location_fallback: optional_to_file(
+            policy
+                .resources_location_fallback()
+                .as_ref()
+                .map(|l| l.to_string()),
+        ),
+        allow_files: Some(policy.allow_files()),
+        allow_in_memory_shared_library_loading: Some(
+            policy.allow_in_memory_shared_library_loading(),
+        ),
+        apply_package_hints: Some(policy.apply_package_hints()),
+        build_sandbox: Some(policy.build_sandbox().as_ref().to_string()),
+        build_sandbox_allow_network: Some(policy.build_sandbox_allow_network()),
+        bytecode_optimize_level_zero: Some(policy.bytecode_optimize_level_zero()),
+        bytecode_optimize_level_one: Some(policy.bytecode_optimize_level_one()),
+        bytecode_optimize_level_two: Some(policy.bytecode_optimize_level_two()),
+        file_scanner_classify_files: Some(policy.file_scanner_classify_files()),
+        file_scanner_emit_files: Some(policy.file_scanner_emit_files()),
+        gettext_catalogs_location: optional_to_file(
+            policy.gettext_catalogs_location().map(|l| l.to_string()),
+        ),
+        include_classified_resources: Some(policy.include_classified_resources()),
+        include_distribution_resources: Some(policy.include_distribution_resources()),
+        include_distribution_sources: Some(policy.include_distribution_sources()),
+        include_file_resources: Some(policy.include_file_resources()),
+        include_non_distribution_sources: Some(policy.include_non_distribution_sources()),
+        include_test: Some(policy.include_test()),
+        prefer_abi3_extensions: Some(policy.prefer_abi3_extensions()),
+        python_security_action: Some(policy.python_security_action().as_ref().to_string()),
+        python_security_baseline: optional_to_file(
+            policy.python_security_baseline().map(|s| s.to_string()),
+        ),
+        stdlib_profile: optional_to_file(policy.stdlib_profile().map(|s| s.to_string())),
+        strip_docstrings: Some(policy.strip_docstrings()),
+        excluded_modules: policy.excluded_modules().to_vec(),
+        exclusions: policy.exclusions().to_vec(),
+        include_patterns: policy.include_patterns().to_vec(),
+        exclude_patterns: policy.exclude_patterns().to_vec(),
+        allowed_licenses: policy.allowed_licenses().to_vec(),
+        denied_licenses: policy.denied_licenses().to_vec(),
+        preferred_extension_module_variants: policy
+            .preferred_extension_module_variants()
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect(),
+        broken_extensions: policy
+            .broken_extensions()
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect(),
+        package_optimize_levels: policy
+            .package_optimize_levels()
+            .map(|(package, levels)| (package.to_string(), levels.to_vec()))
+            .collect(),
+        package_overrides: policy
+            .package_overrides()
+            .map(|o| PackageOverrideFile {
+                package: o.package.clone(),
+                include: o.include,
+                include_sources: o.include_sources,
+                resources_location: o.resources_location.as_ref().map(|l| l.to_string()),
+                resources_location_fallback: o
+                    .resources_location_fallback
+                    .as_ref()
+                    .map(|l| l.to_string()),
+            })
+            .collect(),
+    };
+
+    // Going through `toml::Value` orders values before tables, as TOML
+    // requires.
+    Ok(toml::to_string(&toml::Value::try_from(&file)?)?)
+}
+
+/// Parse a packaging policy from TOML, applying it on top of `base`.
+///
+/// Lists and tables in the file are added to those of `base`, except
+/// allowed and denied licenses, which replace them when present.
+pub fn policy_from_toml(data: &str, base: PythonPackagingPolicy) -> Result<PythonPackagingPolicy> {
+    let file: PolicyFile = toml::from_str(data)?;
+    let mut policy = base;
+
+    if let Some(value) = &file.extension_module_filter {
+        policy.set_extension_module_filter(
+            ExtensionModuleFilter::try_from(value.as_str()).map_err(|e| anyhow!(e))?,
+        );
+    }
+    if let Some(value) = &file.resources_location {
+        policy.set_resources_location(parse_location(value)?);
+    }
+    if let Some(value) = optional_from_file(&file.resources_location_fallback) {
+        policy.set_resources_location_fallback(value.map(parse_location).transpose()?);
+    }
+    if let Some(value) = file.allow_files {
+        policy.set_allow_files(value);
+    }
+    if let Some(value) = file.allow_in_memory_shared_library_loading {
+        policy.set_allow_in_memory_shared_library_loading(value);
+    }
+    if let Some(value) = file.apply_package_hints {
+        policy.set_apply_package_hints(value);
+    }
+    if let Some(value) = &file.build_sandbox {
+        policy.set_build_sandbox(BuildSandbox::try_from(value.as_str()).map_err(|e| anyhow!(e))?);
+    }
+    if let Some(value) = file.build_sandbox_allow_network {
+        policy.set_build_sandbox_allow_network(value);
+    }
+    if let Some(value) = file.bytecode_optimize_level_zero {
+        policy.set_bytecode_optimize_level_zero(value);
+    }
+    if let Some(value) = file.bytecode_optimize_level_one {
+        policy.set_bytecode_optimize_level_one(value);
+    }
+    if let Some(value) = file.bytecode_optimize_level_two {
+        policy.set_bytecode_optimize_level_two(value);
+    }
+    if let Some(value) = file.file_scanner_classify_files {
+        policy.set_file_scanner_classify_files(value);
+    }
+    if let Some(value) = file.file_scanner_emit_files {
+        policy.set_file_scanner_emit_files(value);
+    }
+    if let Some(value) = optional_from_file(&file.gettext_catalogs_location) {
+        policy.set_gettext_catalogs_location(value.map(parse_location).transpose()?)?;
+    }
+    if let Some(value) = file.include_classified_resources {
+        policy.set_include_classified_resources(value);
+    }
+    if let Some(value) = file.include_distribution_resources {
+        policy.set_include_distribution_resources(value);
+    }
+    if let Some(value) = file.include_distribution_sources {
+        policy.set_include_distribution_sources(value);
+    }
+    if let Some(value) = file.include_file_resources {
+        policy.set_include_file_resources(value);
+    }
+    if let Some(value) = file.include_non_distribution_sources {
+        policy.set_include_non_distribution_sources(value);
+    }
+    if let Some(value) = file.include_test {
+        policy.set_include_test(value);
+    }
+    if let Some(value) = file.prefer_abi3_extensions {
+        policy.set_prefer_abi3_extensions(value);
+    }
+    if let Some(value) = &file.python_security_action {
+        policy.set_python_security_action(
+            PythonSecurityAction::try_from(value.as_str()).map_err(|e| anyhow!(e))?,
+        );
+    }
+    if let Some(value) = optional_from_file(&file.python_security_baseline) {
+        policy.set_python_security_baseline(value)?;
+    }
+    if let Some(value) = optional_from_file(&file.stdlib_profile) {
+        policy.set_stdlib_profile(value)?;
+    }
+    if let Some(value) = file.strip_docstrings {
+        policy.set_strip_docstrings(value);
+    }
+
+    for name in &file.excluded_modules {
+        policy.exclude_module(name)?;
+    }
+    for path in &file.exclusions {
+        policy.add_exclusion(path);
+    }
+    for pattern in &file.include_patterns {
+        policy.add_include_pattern(pattern)?;
+    }
+    for pattern in &file.exclude_patterns {
+        policy.add_exclude_pattern(pattern)?;
+    }
+    if !file.allowed_licenses.is_empty() {
+        policy.set_allowed_licenses(file.allowed_licenses.clone())?;
+    }
+    if !file.denied_licenses.is_empty() {
+        policy.set_denied_licenses(file.denied_licenses.clone())?;
+    }
+    for (extension, variant) in &file.preferred_extension_module_variants {
+        policy.set_preferred_extension_module_variant(extension, variant);
+    }
+    for (triple, extensions) in &file.broken_extensions {
+        for extension in extensions {
+            policy.register_broken_extension(triple, extension);
+        }
+    }
+    for (package, levels) in &file.package_optimize_levels {
+        policy.set_package_optimize_levels(package, levels)?;
+    }
+    for o in file.package_overrides {
+        policy.set_package_override(PackageOverride {
+            package: o.package,
+            include: o.include,
+            include_sources: o.include_sources,
+            resources_location: o
+                .resources_location
+                .as_deref()
+                .map(parse_location)
+                .transpose()?,
+            resources_location_fallback: o
+                .resources_location_fallback
+                .as_deref()
+                .map(parse_location)
+                .transpose()?,
+        })?;
+    }
+
+    Ok(policy)
+}
+
+/// Write a packaging policy to a TOML file.
+pub fn write_policy_file(policy: &PythonPackagingPolicy, path: &Path) -> Result<()> {
+    let data = policy_to_toml(policy)?;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("creating {}", parent.display()))?;
+    }
+
+    std::fs::write(path, data).with_context(|| format!("writing {}", path.display()))
+}
+
+/// Read a packaging policy from a TOML file, applying it on top of `base`.
+pub fn read_policy_file(path: &Path, base: PythonPackagingPolicy) -> Result<PythonPackagingPolicy> {
+    let data =
+        std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+
+    policy_from_toml(&data, base).with_context(|| format!("parsing {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+        policy.set_extension_module_filter(ExtensionModuleFilter::NoGPL);
+        policy.set_resources_location(ConcreteResourceLocation::InMemory);
+        policy.set_resources_location_fallback(Some(ConcreteResourceLocation::RelativePath(
+            "lib".to_string(),
+        )));
+        policy.set_include_test(true);
+        policy.set_bytecode_optimize_level_zero(false);
+        policy.set_preferred_extension_module_variant("foo", "bar");
+        policy.register_broken_extension("x86_64-unknown-linux-gnu", "_crypt");
+        policy.add_exclude_pattern("*.tests.*")?;
+        policy.set_denied_licenses(vec!["GPL*".to_string()])?;
+        policy.set_package_optimize_levels("mypkg", &[0, 2])?;
+        policy.exclude_module("tkinter")?;
+        policy.set_package_override(PackageOverride {
+            package: "numpy".to_string(),
+            include_sources: Some(false),
+            resources_location: Some(ConcreteResourceLocation::RelativePath("lib".to_string())),
+            ..PackageOverride::default()
+        })?;
+
+        let data = policy_to_toml(&policy)?;
+        assert_eq!(
+            policy_from_toml(&data, PythonPackagingPolicy::default())?,
+            policy
+        );
+
+        assert_eq!(
+            policy_from_toml(
+                &policy_to_toml(&PythonPackagingPolicy::default())?,
+                PythonPackagingPolicy::default()
+            )?,
+            PythonPackagingPolicy::default()
+        );
+
+        // Settings unset in the written policy are cleared in the base policy.
+        let mut base = PythonPackagingPolicy::default();
+        base.set_resources_location_fallback(Some(ConcreteResourceLocation::InMemory));
+        base.set_stdlib_profile(Some("cli-minimal"))?;
+        assert_eq!(
+            policy_from_toml(&policy_to_toml(&PythonPackagingPolicy::default())?, base)?,
+            PythonPackagingPolicy::default()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_partial() -> Result<()> {
+        let mut base = PythonPackagingPolicy::default();
+        base.set_resources_location_fallback(Some(ConcreteResourceLocation::RelativePath(
+            "lib".to_string(),
+        )));
+        base.register_broken_extension("x86_64-unknown-linux-gnu", "_crypt");
+        base.set_denied_licenses(vec!["GPL*".to_string()])?;
+
+        let policy = policy_from_toml(
+            "include_test = true\n\n[preferred_extension_module_variants]\nfoo = \"bar\"\n",
+            base.clone(),
+        )?;
+
+        let mut wanted = base.clone();
+        wanted.set_include_test(true);
+        wanted.set_preferred_extension_module_variant("foo", "bar");
+        assert_eq!(policy, wanted);
+
+        let policy = policy_from_toml("resources_location_fallback = \"\"", base.clone())?;
+        assert_eq!(policy.resources_location_fallback(), &None);
+
+        assert!(policy_from_toml("include_tests = true", base.clone()).is_err());
+        assert!(policy_from_toml("resources_location = \"memory\"", base).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,234 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Relocatable copies of a Python distribution.
+
+A portable Python is a distribution's installation of Python copied into a
+directory of its own, so the interpreter can be used as a toolchain without
+packaging an application. Distributions name the interpreter executable
+after its full version, e.g. `python3.9`, and provide other names via
+symlinks, which don't survive all archive formats and file systems. So
+launchers running the interpreter are installed next to it instead.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    std::{convert::TryFrom, path::Path},
+    tugger::{
+        file_resource::{FileContent, FileManifest},
+        shims::ShimPlatform,
+    },
+};
+
+/// Default names of launchers for the interpreter.
+pub const DEFAULT_LAUNCHERS: &[&str] = &["python", "python3"];
+
+/// Obtain the content of a launcher running an executable in its directory.
+///
+/// The Unix launcher resolves symlinks to itself first, so it can be linked
+/// from elsewhere, e.g. a directory in `PATH`.
+fn launcher_content(platform: ShimPlatform, executable: &str) -> String {
+    match platform {
+        ShimPlatform::Windows => format!(
+            "@echo off\r\n\
+             \"%~dp0{}\" %*\r\n\
+             exit /b %ERRORLEVEL%\r\n",
+            executable
+        ),
+        ShimPlatform::Unix => format!(
+            "#!/bin/sh\n\
+             self=\"$0\"\n\
+             while [ -L \"$self\" ]; do\n\
+             \x20   link=\"$(readlink \"$self\")\"\n\
+             \x20   case \"$link\" in\n\
+             \x20       /*) self=\"$link\" ;;\n\
+             \x20       *) self=\"$(dirname \"$self\")/$link\" ;;\n\
+             \x20   esac\n\
+             done\n\
+             exec \"$(dirname \"$self\")/{}\" \"$@\"\n",
+            executable
+        ),
+    }
+}
+
+/// Collect the files of a portable Python.
+///
+/// `install_dir` is the distribution's installation of Python and
+/// `python_exe` the path of the interpreter executable in it. Paths in the
+/// manifest are relative to `install_dir`.
+///
+/// Symlinks are copied as the file they point to, except when a launcher
+/// has the same name. Launchers named after the interpreter executable
+/// aren't installed, as the executable can be run by that name already.
+pub fn portable_python_manifest(
+    install_dir: &Path,
+    python_exe: &Path,
+    platform: ShimPlatform,
+    launchers: &[String],
+) -> Result<FileManifest> {
+    let python_exe = python_exe.strip_prefix(install_dir).with_context(|| {
+        format!(
+            "{} is not in {}",
+            python_exe.display(),
+            install_dir.display()
+        )
+    })?;
+    let exe_dir = python_exe.parent().unwrap_or_else(|| Path::new(""));
+    let exe_name = python_exe
+        .file_name()
+        .ok_or_else(|| anyhow!("{} has no filename", python_exe.display()))?
+        .to_string_lossy()
+        .to_string();
+
+    let mut launcher_paths = vec![];
+    for name in launchers {
+        if name.is_empty() || name.contains(&['/', '\\'][..]) {
+            return Err(anyhow!("invalid launcher name: {:?}", name));
+        }
+
+        if exe_name.strip_suffix(".exe").unwrap_or(&exe_name) == name {
+            continue;
+        }
+
+        launcher_paths.push(exe_dir.join(match platform {
+            ShimPlatform::Windows => format!("{}.cmd", name),
+            ShimPlatform::Unix => name.clone(),
+        }));
+    }
+
+    let mut manifest = FileManifest::default();
+
+    for entry in walkdir::WalkDir::new(install_dir) {
+        let entry = entry?;
+        let path = entry.path();
+
+        // Directories and dangling symlinks.
+        if !path.is_file() {
+            continue;
+        }
+
+        let rel_path = path.strip_prefix(install_dir)?;
+        if launcher_paths.iter().any(|p| p == rel_path) {
+            continue;
+        }
+
+        let content =
+            FileContent::try_from(path).with_context(|| format!("reading {}", path.display()))?;
+        manifest.add_file(rel_path, &content)?;
+    }
+
+    if !manifest.has_path(python_exe) {
+        return Err(anyhow!(
+            "{} does not contain {}",
+            install_dir.display(),
+            python_exe.display()
+        ));
+    }
+
+    for path in launcher_paths {
+        manifest.add_file(
+            &path,
+            &FileContent {
+                data: launcher_content(platform, &exe_name).into_bytes(),
+                executable: true,
+            },
+        )?;
+    }
+
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::path::PathBuf};
+
+    fn content<'a>(manifest: &'a FileManifest, path: &str) -> &'a FileContent {
+        manifest
+            .entries()
+            .find(|(p, _)| p.as_path() == Path::new(path))
+            .unwrap()
+            .1
+    }
+
+    #[test]
+    fn test_portable_python_manifest() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let install_dir = temp_dir.path().join("install");
+        let python_exe = install_dir.join("bin").join("python3.9");
+        std::fs::create_dir_all(python_exe.parent().unwrap())?;
+        std::fs::create_dir_all(install_dir.join("lib").join("python3.9"))?;
+        std::fs::write(&python_exe, "interpreter")?;
+        std::fs::write(install_dir.join("bin").join("python3"), "copy")?;
+        std::fs::write(install_dir.join("lib").join("python3.9").join("os.py"), "")?;
+
+        let launchers = DEFAULT_LAUNCHERS
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        let manifest =
+            portable_python_manifest(&install_dir, &python_exe, ShimPlatform::Unix, &launchers)?;
+
+        let paths = manifest
+            .entries()
+            .map(|(p, _)| p.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("bin/python"),
+                PathBuf::from("bin/python3"),
+                PathBuf::from("bin/python3.9"),
+                PathBuf::from("lib/python3.9/os.py"),
+            ]
+        );
+
+        let launcher = content(&manifest, "bin/python3");
+        assert!(launcher.executable);
+        let script = String::from_utf8(launcher.data.clone())?;
+        assert!(script.starts_with("#!/bin/sh\nself=\"$0\"\nwhile [ -L \"$self\" ]; do\n"));
+        assert!(script.ends_with("exec \"$(dirname \"$self\")/python3.9\" \"$@\"\n"));
+
+        assert!(portable_python_manifest(
+            &install_dir,
+            &python_exe,
+            ShimPlatform::Unix,
+            &["bin/python".to_string()]
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_portable_python_manifest_windows() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let install_dir = temp_dir.path().join("install");
+        let python_exe = install_dir.join("python.exe");
+        std::fs::create_dir_all(&install_dir)?;
+        std::fs::write(&python_exe, "interpreter")?;
+
+        let manifest = portable_python_manifest(
+            &install_dir,
+            &python_exe,
+            ShimPlatform::Windows,
+            &["python".to_string(), "python3".to_string()],
+        )?;
+
+        let paths = manifest
+            .entries()
+            .map(|(p, _)| p.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![PathBuf::from("python.exe"), PathBuf::from("python3.cmd")]
+        );
+        assert_eq!(
+            content(&manifest, "python3.cmd").data,
+            b"@echo off\r\n\"%~dp0python.exe\" %*\r\nexit /b %ERRORLEVEL%\r\n".to_vec()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Support for deploying the Qt Python bindings.
+
+Qt loads plugins (including the platform plugin needed to show any window)
+and QML modules from the filesystem, and its libraries locate each other via
+paths relative to their own location. So the data files shipped in the
+binding's package must be installed as files, in their original layout.
+*/
+
+use anyhow::{anyhow, Result};
+
+/// Describes how a Qt binding lays out Qt within its Python package.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QtBinding {
+    /// Name of the Python package providing the binding.
+    pub package: &'static str,
+
+    /// Directory holding Qt, relative to the package. Empty for the package root.
+    pub qt_dir: &'static str,
+
+    /// Environment variable Qt reads additional QML import paths from.
+    pub qml_import_path_env: &'static str,
+}
+
+impl QtBinding {
+    /// Resolve the layout of a binding for a target triple.
+    ///
+    /// `binding` is the lowercase name of the binding's package, e.g. `pyside6`.
+    pub fn resolve(binding: &str, target_triple: &str) -> Result<Self> {
+        let windows = target_triple.contains("-windows-");
+
+        // PySide wheels put Qt at the root of the package on Windows and
+        // in a `Qt` directory elsewhere. PyQt wheels use a versioned
+        // directory everywhere.
+        let (package, qt_dir, qml_import_path_env) = match binding {
+            "pyside2" => (
+                "PySide2",
+                if windows { "" } else { "Qt" },
+                "QML2_IMPORT_PATH",
+            ),
+            "pyside6" => (
+                "PySide6",
+                if windows { "" } else { "Qt" },
+                "QML_IMPORT_PATH",
+            ),
+            "pyqt5" => ("PyQt5", "Qt5", "QML2_IMPORT_PATH"),
+            "pyqt6" => ("PyQt6", "Qt6", "QML_IMPORT_PATH"),
+            _ => {
+                return Err(anyhow!(
+                    "unsupported Qt binding {}; must be one of pyside2, pyside6, pyqt5, pyqt6",
+                    binding
+                ))
+            }
+        };
+
+        Ok(Self {
+            package,
+            qt_dir,
+            qml_import_path_env,
+        })
+    }
+
+    /// Directory holding Qt plugins, relative to the package.
+    pub fn plugins_dir(&self) -> String {
+        self.qt_path("plugins")
+    }
+
+    /// Directory holding QML modules, relative to the package.
+    pub fn qml_dir(&self) -> String {
+        self.qt_path("qml")
+    }
+
+    fn qt_path(&self, name: &str) -> String {
+        if self.qt_dir.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", self.qt_dir, name)
+        }
+    }
+
+    /// Obtain environment variables pointing Qt at the installed package.
+    ///
+    /// `package_path` is the directory the package is installed in.
+    pub fn environment_variables(&self, package_path: &str) -> Vec<(String, String)> {
+        let plugins = format!("{}/{}", package_path, self.plugins_dir());
+
+        vec![
+            ("QT_PLUGIN_PATH".to_string(), plugins.clone()),
+            (
+                "QT_QPA_PLATFORM_PLUGIN_PATH".to_string(),
+                format!("{}/platforms", plugins),
+            ),
+            (
+                self.qml_import_path_env.to_string(),
+                format!("{}/{}", package_path, self.qml_dir()),
+            ),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() -> Result<()> {
+        let binding = QtBinding::resolve("pyside6", "x86_64-unknown-linux-gnu")?;
+        assert_eq!(binding.package, "PySide6");
+        assert_eq!(binding.plugins_dir(), "Qt/plugins");
+
+        let binding = QtBinding::resolve("pyside6", "x86_64-pc-windows-msvc")?;
+        assert_eq!(binding.plugins_dir(), "plugins");
+        assert_eq!(binding.qml_dir(), "qml");
+
+        let binding = QtBinding::resolve("pyqt5", "x86_64-apple-darwin")?;
+        assert_eq!(binding.package, "PyQt5");
+        assert_eq!(binding.qml_dir(), "Qt5/qml");
+
+        assert!(QtBinding::resolve("tkinter", "x86_64-unknown-linux-gnu").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_environment_variables() -> Result<()> {
+        let binding = QtBinding::resolve("pyside6", "x86_64-unknown-linux-gnu")?;
+
+        assert_eq!(
+            binding.environment_variables("$ORIGIN/lib/PySide6"),
+            vec![
+                (
+                    "QT_PLUGIN_PATH".to_string(),
+                    "$ORIGIN/lib/PySide6/Qt/plugins".to_string()
+                ),
+                (
+                    "QT_QPA_PLATFORM_PLUGIN_PATH".to_string(),
+                    "$ORIGIN/lib/PySide6/Qt/plugins/platforms".to_string()
+                ),
+                (
+                    "QML_IMPORT_PATH".to_string(),
+                    "$ORIGIN/lib/PySide6/Qt/qml".to_string()
+                ),
+            ]
+        );
+
+        Ok(())
+    }
+}
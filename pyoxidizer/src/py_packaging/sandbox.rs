@@ -0,0 +1,304 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Running package build backends in a sandbox.
+
+`pip install` and `setup.py` execute arbitrary code from the packages
+being installed. When enabled by the packaging policy, these commands are
+wrapped with the sandboxing tool of the platform: `bwrap` (bubblewrap) on
+Linux and `sandbox-exec` on macOS. Sandboxed commands can only write to
+their temporary directory, can't read well-known credential files and
+can't access the network unless allowed.
+*/
+
+use {
+    anyhow::{anyhow, Result},
+    lazy_static::lazy_static,
+    python_packaging::policy::BuildSandbox,
+    slog::warn,
+    std::path::{Path, PathBuf},
+};
+
+/// Files and directories in the home directory commonly holding credentials.
+///
+/// Sandboxed commands can't read these.
+const HOME_SECRET_PATHS: &[&str] = &[
+    ".aws",
+    ".azure",
+    ".cargo/credentials",
+    ".cargo/credentials.toml",
+    ".config/gcloud",
+    ".config/gh",
+    ".docker",
+    ".git-credentials",
+    ".gnupg",
+    ".kube",
+    ".netrc",
+    ".pypirc",
+    ".ssh",
+];
+
+/// What a sandboxed command may access.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SandboxSpec {
+    /// Whether the command may access the network.
+    pub allow_network: bool,
+
+    /// Directories the command may write to.
+    pub writable_paths: Vec<PathBuf>,
+
+    /// Files and directories the command can't read.
+    pub hidden_paths: Vec<PathBuf>,
+}
+
+impl SandboxSpec {
+    /// Construct a spec allowing writes to directories and hiding credential files.
+    pub fn new(allow_network: bool, writable_paths: &[&Path]) -> Self {
+        let hidden_paths = match std::env::var_os("HOME") {
+            Some(home) => HOME_SECRET_PATHS
+                .iter()
+                .map(|p| Path::new(&home).join(p))
+                .filter(|p| p.exists())
+                .collect(),
+            None => vec![],
+        };
+
+        Self {
+            allow_network,
+            writable_paths: writable_paths
+                .iter()
+                .map(|p| p.canonicalize().unwrap_or_else(|_| p.to_path_buf()))
+                .collect(),
+            hidden_paths,
+        }
+    }
+}
+
+/// A platform mechanism for sandboxing commands.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SandboxBackend {
+    /// `bwrap` at the given path.
+    Bubblewrap(PathBuf),
+    /// `sandbox-exec` at the given path.
+    SandboxExec(PathBuf),
+}
+
+/// Find an executable in `PATH`.
+fn find_executable(name: &str) -> Option<PathBuf> {
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(name))
+        .find(|p| p.is_file())
+}
+
+/// Find the sandboxing mechanism of this machine, checking that it works.
+fn probe_backend() -> Option<SandboxBackend> {
+    let backend = if cfg!(target_os = "linux") {
+        SandboxBackend::Bubblewrap(find_executable("bwrap")?)
+    } else if cfg!(target_os = "macos") {
+        let path = PathBuf::from("/usr/bin/sandbox-exec");
+        if !path.exists() {
+            return None;
+        }
+        SandboxBackend::SandboxExec(path)
+    } else {
+        return None;
+    };
+
+    // bwrap fails in containers without user namespaces. So try it once.
+    let (program, args) = backend.wrap(&SandboxSpec::default(), Path::new("true"), &[]);
+
+    match duct::cmd(program, args)
+        .stdout_null()
+        .stderr_null()
+        .unchecked()
+        .run()
+    {
+        Ok(output) if output.status.success() => Some(backend),
+        _ => None,
+    }
+}
+
+lazy_static! {
+    static ref SANDBOX_BACKEND: Option<SandboxBackend> = probe_backend();
+}
+
+/// Resolve the sandbox mechanism to use for a sandbox mode.
+///
+/// Returns `None` if commands shouldn't be sandboxed. Errors if a sandbox
+/// is required and the platform doesn't provide one.
+pub fn resolve_sandbox_backend(
+    logger: &slog::Logger,
+    mode: BuildSandbox,
+) -> Result<Option<SandboxBackend>> {
+    match (mode, SANDBOX_BACKEND.as_ref()) {
+        (BuildSandbox::Off, _) => Ok(None),
+        (_, Some(backend)) => Ok(Some(backend.clone())),
+        (BuildSandbox::Auto, None) => {
+            warn!(
+                logger,
+                "no working sandbox available (bwrap on Linux, sandbox-exec on macOS); running build backends unsandboxed"
+            );
+            Ok(None)
+        }
+        (BuildSandbox::Required, None) => Err(anyhow!(
+            "build_sandbox is \"required\" but no working sandbox is available; install bubblewrap (bwrap) on Linux, or use macOS"
+        )),
+    }
+}
+
+impl SandboxBackend {
+    /// Obtain the program and arguments running a command in the sandbox.
+    pub fn wrap(
+        &self,
+        spec: &SandboxSpec,
+        program: &Path,
+        args: &[String],
+    ) -> (PathBuf, Vec<String>) {
+        match self {
+            Self::Bubblewrap(bwrap) => (bwrap.clone(), bubblewrap_args(spec, program, args)),
+            Self::SandboxExec(sandbox_exec) => {
+                let mut res = vec!["-p".to_string(), sandbox_exec_profile(spec)];
+                res.push(program.display().to_string());
+                res.extend(args.iter().cloned());
+
+                (sandbox_exec.clone(), res)
+            }
+        }
+    }
+}
+
+/// Arguments to `bwrap` running a command in a sandbox.
+fn bubblewrap_args(spec: &SandboxSpec, program: &Path, args: &[String]) -> Vec<String> {
+    let mut res = vec![
+        "--ro-bind",
+        "/",
+        "/",
+        "--dev",
+        "/dev",
+        "--proc",
+        "/proc",
+        "--unshare-pid",
+        "--unshare-ipc",
+        "--unshare-uts",
+        "--die-with-parent",
+        "--new-session",
+    ]
+    .into_iter()
+    .map(|s| s.to_string())
+    .collect::<Vec<_>>();
+
+    if !spec.allow_network {
+        res.push("--unshare-net".to_string());
+    }
+
+    for path in &spec.hidden_paths {
+        let path = path.display().to_string();
+
+        if Path::new(&path).is_dir() {
+            res.extend(vec!["--tmpfs".to_string(), path]);
+        } else {
+            res.extend(vec!["--ro-bind".to_string(), "/dev/null".to_string(), path]);
+        }
+    }
+
+    for path in &spec.writable_paths {
+        let path = path.display().to_string();
+        res.extend(vec!["--bind".to_string(), path.clone(), path]);
+    }
+
+    res.push("--".to_string());
+    res.push(program.display().to_string());
+    res.extend(args.iter().cloned());
+
+    res
+}
+
+/// Quote a string for use in a `sandbox-exec` profile.
+fn sandbox_exec_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// A `sandbox-exec` profile describing a sandbox.
+fn sandbox_exec_profile(spec: &SandboxSpec) -> String {
+    let mut lines = vec![
+        "(version 1)".to_string(),
+        "(allow default)".to_string(),
+        "(deny file-write*)".to_string(),
+        "(allow file-write* (literal \"/dev/null\") (literal \"/dev/tty\") (regex #\"^/dev/fd/\"))"
+            .to_string(),
+    ];
+
+    for path in &spec.writable_paths {
+        lines.push(format!(
+            "(allow file-write* (subpath {}))",
+            sandbox_exec_string(&path.display().to_string())
+        ));
+    }
+
+    for path in &spec.hidden_paths {
+        lines.push(format!(
+            "(deny file-read* (subpath {}))",
+            sandbox_exec_string(&path.display().to_string())
+        ));
+    }
+
+    if !spec.allow_network {
+        lines.push("(deny network*)".to_string());
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(allow_network: bool) -> SandboxSpec {
+        SandboxSpec {
+            allow_network,
+            writable_paths: vec![PathBuf::from("/tmp/install")],
+            hidden_paths: vec![PathBuf::from("/home/user/.netrc")],
+        }
+    }
+
+    #[test]
+    fn test_bubblewrap_args() {
+        let args = bubblewrap_args(
+            &spec(false),
+            Path::new("/usr/bin/python3"),
+            &["-V".to_string()],
+        );
+
+        assert!(args.contains(&"--unshare-net".to_string()));
+        assert!(args
+            .windows(3)
+            .any(|w| w == ["--bind", "/tmp/install", "/tmp/install"]));
+        assert!(args
+            .windows(3)
+            .any(|w| w == ["--ro-bind", "/dev/null", "/home/user/.netrc"]));
+        assert_eq!(&args[args.len() - 3..], &["--", "/usr/bin/python3", "-V"]);
+
+        // Writable directories are bound after the read-only root.
+        let root = args.iter().position(|a| a == "--ro-bind").unwrap();
+        let bind = args.iter().position(|a| a == "--bind").unwrap();
+        assert!(root < bind);
+
+        let args = bubblewrap_args(&spec(true), Path::new("/usr/bin/python3"), &[]);
+        assert!(!args.contains(&"--unshare-net".to_string()));
+    }
+
+    #[test]
+    fn test_sandbox_exec_profile() {
+        let profile = sandbox_exec_profile(&spec(false));
+
+        assert!(profile.contains("(deny file-write*)"));
+        assert!(profile.contains("(allow file-write* (subpath \"/tmp/install\"))"));
+        assert!(profile.contains("(deny file-read* (subpath \"/home/user/.netrc\"))"));
+        assert!(profile.contains("(deny network*)"));
+        assert!(!sandbox_exec_profile(&spec(true)).contains("network"));
+
+        assert_eq!(sandbox_exec_string("a\"b\\c"), "\"a\\\"b\\\\c\"");
+    }
+}
@@ -5,34 +5,50 @@
 use {
     super::{
         binary::{
-            EmbeddedPythonContext, LibpythonLinkMode, PythonBinaryBuilder, PythonLinkingInfo,
-            ResourceAddCollectionContextCallback,
+            shared_library_install_dir, EmbeddedPythonContext, LibpythonLinkMode,
+            PythonBinaryBuilder, PythonLinkingInfo, ResourceAddCollectionContextCallback,
+            EXTERNAL_RUNTIME_PACKED_RESOURCES_FILENAME,
+        },
+        config::{
+            EmbeddedPythonConfig, ExternalAsset, PackedResourcesEmbedding, PackedResourcesFile,
         },
-        config::EmbeddedPythonConfig,
         distribution::{BinaryLibpythonLinkMode, PythonDistribution},
         filtering::{filter_btreemap, resolve_resource_names_from_files},
         libpython::link_libpython,
+        memory_dll::extension_module_memory_loading_issues,
         packaging_tool::{
-            find_resources, pip_download, pip_install, read_virtualenv, setup_py_install,
+            find_resources, install_groups, pip_download, pip_install, read_virtualenv,
+            setup_py_install, InstallGroup,
         },
+        qt::QtBinding,
         standalone_distribution::StandaloneDistribution,
+        terminfo::{resolve_minimal_terminfo_files, HOST_TERMINFO_DIRS},
+        windows_runtime::{WindowsRuntimeDllsMode, WindowsRuntimeRequirements},
+    },
+    crate::{
+        analyze::{find_macho_minimum_macos_version, MacOsVersion},
+        artifact_store::CachedBytecodeCompiler,
+        timing::{PhaseTimer, PHASE_COMPILE, PHASE_LINK, PHASE_PIP, PHASE_SCAN, PHASE_SERIALIZE},
     },
-    anyhow::{anyhow, Result},
+    anyhow::{anyhow, Context, Result},
     lazy_static::lazy_static,
     python_packaging::{
         bytecode::BytecodeCompiler,
-        interpreter::MemoryAllocatorBackend,
+        interpreter::{MemoryAllocatorBackend, TerminfoResolution, BUNDLED_TERMINFO_DIRECTORY},
         libpython::LibPythonBuildContext,
-        location::AbstractResourceLocation,
+        location::{AbstractResourceLocation, ConcreteResourceLocation, PACKAGE_PLACEHOLDER},
         policy::PythonPackagingPolicy,
         resource::{
-            DataLocation, FileData, PythonExtensionModule, PythonModuleSource,
-            PythonPackageDistributionResource, PythonPackageResource, PythonResource,
+            BytecodeOptimizationLevel, DataLocation, FileData, GettextCatalog,
+            PythonExtensionModule, PythonModuleSource, PythonPackageDistributionResource,
+            PythonPackageResource, PythonResource, SharedLibrary,
         },
         resource_collection::{
-            PrePackagedResource, PythonResourceAddCollectionContext, PythonResourceCollector,
+            CompiledResourcesCollection, PrePackagedResource, PythonResourceAddCollectionContext,
+            PythonResourceCollector,
         },
     },
+    sha2::{Digest, Sha256},
     slog::warn,
     std::{
         collections::{BTreeMap, BTreeSet, HashMap},
@@ -52,6 +68,9 @@ lazy_static! {
     static ref MACOS_IGNORE_LIBRARIES: Vec<&'static str> = vec!["dl", "m",];
 }
 
+/// Python package providing time zone data to `zoneinfo`.
+const TZDATA_PACKAGE: &str = "tzdata";
+
 /// Obtain a list of ignored libraries for a given target triple.
 fn ignored_libraries_for_target(target_triple: &str) -> Vec<&'static str> {
     if crate::environment::LINUX_TARGET_TRIPLES.contains(&target_triple) {
@@ -63,6 +82,66 @@ fn ignored_libraries_for_target(target_triple: &str) -> Vec<&'static str> {
     }
 }
 
+/// Whether data is a Mach-O binary.
+fn is_macho(data: &[u8]) -> bool {
+    matches!(
+        data.get(0..4),
+        Some([0xcf, 0xfa, 0xed, 0xfe])
+            | Some([0xce, 0xfa, 0xed, 0xfe])
+            | Some([0xca, 0xfe, 0xba, 0xbe])
+    )
+}
+
+/// Verify packaged binaries run on the macOS deployment target.
+///
+/// Extension modules and shared libraries built for a newer macOS than the
+/// deployment target would fail to load on older versions.
+fn check_macos_deployment_target(
+    deployment_target: MacOsVersion,
+    compiled_resources: &CompiledResourcesCollection,
+    extra_files: &FileManifest,
+) -> Result<()> {
+    let mut binaries = vec![];
+
+    for (name, resource) in &compiled_resources.resources {
+        for data in resource
+            .in_memory_extension_module_shared_library
+            .iter()
+            .chain(resource.in_memory_shared_library.iter())
+        {
+            binaries.push((name.clone(), data.as_ref()));
+        }
+    }
+
+    for (path, content) in extra_files.entries() {
+        if is_macho(&content.data) {
+            binaries.push((path.display().to_string(), content.data.as_slice()));
+        }
+    }
+
+    let mut too_new = vec![];
+
+    for (name, data) in binaries {
+        if let Some(version) =
+            find_macho_minimum_macos_version(data).with_context(|| format!("analyzing {}", name))?
+        {
+            if version > deployment_target {
+                too_new.push(format!("{} (requires {})", name, version));
+            }
+        }
+    }
+
+    if too_new.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "binaries require a newer macOS than the deployment target {}: {}",
+            deployment_target,
+            too_new.join(", ")
+        ))
+    }
+}
+
 /// A self-contained Python executable before it is compiled.
 #[derive(Clone)]
 pub struct StandalonePythonExecutableBuilder {
@@ -112,8 +191,48 @@ pub struct StandalonePythonExecutableBuilder {
     /// Value for the `windows_subsystem` Rust attribute for generated Rust projects.
     windows_subsystem: String,
 
+    /// Name of the Windows service the executable can run as.
+    windows_service_name: Option<String>,
+
+    /// Whether to install Visual C++ runtime DLLs next to built executables.
+    windows_runtime_dlls_mode: WindowsRuntimeDllsMode,
+
+    /// How packed resources data is stored in built executables.
+    packed_resources_embedding: PackedResourcesEmbedding,
+
+    /// Minimum macOS version built binaries support.
+    macos_deployment_target: Option<MacOsVersion>,
+
+    /// Oldest glibc version built executables must run with.
+    linux_minimum_glibc_version: Option<String>,
+
+    /// Maximum size in megabytes of built executables and their installed files.
+    max_size_mb: Option<u64>,
+
+    /// Version of the application.
+    version: Option<String>,
+
     /// Path to install tcl/tk files into.
     tcl_files_path: Option<String>,
+
+    /// Whether to bundle the `tzdata` package.
+    bundle_tzdata: bool,
+
+    /// Directory holding an external runtime, relative to the executable.
+    external_runtime_path: Option<String>,
+
+    /// Additional Cargo features to enable when building.
+    cargo_features: Vec<String>,
+
+    /// Environment variables to set when building.
+    build_env: BTreeMap<String, String>,
+
+    /// Native shared libraries to install, keyed by filename.
+    shared_libraries: BTreeMap<PathBuf, DataLocation>,
+
+    /// Extension modules moved from memory to the filesystem because they
+    /// can't be loaded from memory, with the reasons why.
+    relocated_extension_modules: BTreeMap<String, Vec<String>>,
 }
 
 impl StandalonePythonExecutableBuilder {
@@ -173,6 +292,19 @@ impl StandalonePythonExecutableBuilder {
         if let Some(fallback) = packaging_policy.resources_location_fallback() {
             allowed_locations.push(AbstractResourceLocation::from(fallback));
         }
+        // Package hints and import traces can send resources to the filesystem.
+        if (packaging_policy.package_hints_require_filesystem()
+            || packaging_policy.import_trace().is_some())
+            && !allowed_locations.contains(&AbstractResourceLocation::RelativePath)
+        {
+            allowed_locations.push(AbstractResourceLocation::RelativePath);
+        }
+        // Import traces send hot resources to memory.
+        if packaging_policy.import_trace().is_some()
+            && !allowed_locations.contains(&AbstractResourceLocation::InMemory)
+        {
+            allowed_locations.push(AbstractResourceLocation::InMemory);
+        }
 
         let mut allowed_extension_module_locations = vec![];
 
@@ -209,9 +341,26 @@ impl StandalonePythonExecutableBuilder {
             config,
             host_python_exe,
             windows_subsystem: "console".to_string(),
+            windows_service_name: None,
+            windows_runtime_dlls_mode: WindowsRuntimeDllsMode::default(),
+            packed_resources_embedding: PackedResourcesEmbedding::default(),
+            macos_deployment_target: None,
+            linux_minimum_glibc_version: None,
+            max_size_mb: None,
+            version: None,
             tcl_files_path: None,
+            bundle_tzdata: false,
+            external_runtime_path: None,
+            cargo_features: vec![],
+            build_env: BTreeMap::new(),
+            shared_libraries: BTreeMap::new(),
+            relocated_extension_modules: BTreeMap::new(),
         });
 
+        builder
+            .resources_collector
+            .set_strip_docstrings(packaging_policy.strip_docstrings());
+
         builder.add_distribution_core_state()?;
 
         Ok(builder)
@@ -286,6 +435,74 @@ impl StandalonePythonExecutableBuilder {
         Ok(())
     }
 
+    /// Whether resources of the `tzdata` package have been added.
+    fn has_tzdata(&self) -> bool {
+        self.resources_collector
+            .iter_resources()
+            .any(|(name, _)| name.split('.').next() == Some(TZDATA_PACKAGE))
+    }
+
+    /// Download the `tzdata` package and add its resources.
+    fn add_tzdata(&mut self, logger: &slog::Logger) -> Result<()> {
+        warn!(
+            logger,
+            "adding {} package to bundle time zone data", TZDATA_PACKAGE
+        );
+
+        let resources = {
+            let _timer = PhaseTimer::new(PHASE_PIP);
+
+            pip_download(
+                logger,
+                &*self.host_distribution,
+                &*self.target_distribution,
+                &self.packaging_policy,
+                false,
+                &[TZDATA_PACKAGE.to_string()],
+            )?
+        };
+
+        for resource in resources {
+            match &resource {
+                PythonResource::ModuleSource(module) => {
+                    self.add_python_module_source(module, None)?;
+                }
+                PythonResource::PackageResource(r) => {
+                    self.add_python_package_resource(r, None)?;
+                }
+                PythonResource::PackageDistributionResource(r) => {
+                    self.add_python_package_distribution_resource(r, None)?;
+                }
+                _ => {}
+            }
+        }
+
+        if !self.has_tzdata() {
+            return Err(anyhow!(
+                "{} package resources were not added; check the packaging policy",
+                TZDATA_PACKAGE
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Resolve the filesystem path of bundled time zone files, relative to the binary.
+    ///
+    /// Returns `None` if the files are loaded from memory.
+    fn tzdata_zoneinfo_path(&self) -> Option<PathBuf> {
+        self.resources_collector
+            .iter_resources()
+            .find(|(name, _)| name.as_str() == "tzdata.zoneinfo")
+            .and_then(|(_, entry)| entry.relative_path_package_resources.as_ref())
+            .and_then(|resources| resources.iter().next())
+            .and_then(|(name, (path, _))| {
+                path.ancestors()
+                    .nth(name.split('/').count())
+                    .map(|p| p.to_path_buf())
+            })
+    }
+
     /// Build a Python library suitable for linking.
     ///
     /// This will take the underlying distribution, resources, and
@@ -398,6 +615,179 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         Ok(())
     }
 
+    fn windows_service_name(&self) -> &Option<String> {
+        &self.windows_service_name
+    }
+
+    fn set_windows_service_name(&mut self, value: Option<String>) -> Result<()> {
+        if let Some(name) = &value {
+            // The name is embedded in generated Rust source code and the
+            // service control manager rejects names containing slashes.
+            if name.is_empty()
+                || name.len() > 256
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_. ".contains(c))
+            {
+                return Err(anyhow!(
+                    "invalid Windows service name: {}; only ASCII letters, digits, spaces, '-', '_', and '.' are allowed",
+                    name
+                ));
+            }
+        }
+
+        self.windows_service_name = value;
+
+        Ok(())
+    }
+
+    fn windows_runtime_dlls_mode(&self) -> WindowsRuntimeDllsMode {
+        self.windows_runtime_dlls_mode
+    }
+
+    fn set_windows_runtime_dlls_mode(&mut self, value: WindowsRuntimeDllsMode) {
+        self.windows_runtime_dlls_mode = value;
+    }
+
+    fn packed_resources_embedding(&self) -> PackedResourcesEmbedding {
+        self.packed_resources_embedding
+    }
+
+    fn set_packed_resources_embedding(&mut self, value: PackedResourcesEmbedding) {
+        self.packed_resources_embedding = value;
+    }
+
+    fn macos_deployment_target(&self) -> Option<MacOsVersion> {
+        self.macos_deployment_target
+    }
+
+    fn set_macos_deployment_target(&mut self, value: Option<MacOsVersion>) {
+        self.macos_deployment_target = value;
+    }
+
+    fn linux_minimum_glibc_version(&self) -> &Option<String> {
+        &self.linux_minimum_glibc_version
+    }
+
+    fn set_linux_minimum_glibc_version(&mut self, value: Option<String>) -> Result<()> {
+        if let Some(version) = &value {
+            if version.split('.').count() < 2
+                || !version
+                    .split('.')
+                    .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+            {
+                return Err(anyhow!(
+                    "{} is not a valid glibc version; expected a value like 2.17",
+                    version
+                ));
+            }
+        }
+
+        self.linux_minimum_glibc_version = value;
+
+        Ok(())
+    }
+
+    fn max_size_mb(&self) -> Option<u64> {
+        self.max_size_mb
+    }
+
+    fn set_max_size_mb(&mut self, value: Option<u64>) {
+        self.max_size_mb = value;
+    }
+
+    fn version(&self) -> &Option<String> {
+        &self.version
+    }
+
+    fn set_version(&mut self, value: Option<String>) -> Result<()> {
+        if let Some(version) = &value {
+            // The version is embedded in Windows resource scripts as a
+            // quoted string.
+            if version.is_empty()
+                || !version
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_.+".contains(c))
+            {
+                return Err(anyhow!(
+                    "invalid version: {}; only ASCII letters, digits, '-', '_', '.', and '+' are allowed",
+                    version
+                ));
+            }
+
+            crate::environment::windows_version_fields(version)?;
+        }
+
+        self.version = value;
+
+        Ok(())
+    }
+
+    fn bundle_tzdata(&self) -> bool {
+        self.bundle_tzdata
+    }
+
+    fn set_bundle_tzdata(&mut self, value: bool) {
+        self.bundle_tzdata = value;
+    }
+
+    fn external_runtime_path(&self) -> &Option<String> {
+        &self.external_runtime_path
+    }
+
+    fn set_external_runtime_path(&mut self, value: Option<String>) -> Result<()> {
+        if let Some(path) = &value {
+            if path.is_empty() || path.contains("..") || Path::new(path).is_absolute() {
+                return Err(anyhow!(
+                    "external runtime path must be a relative path without '..': {}",
+                    path
+                ));
+            }
+        }
+
+        self.external_runtime_path = value;
+
+        Ok(())
+    }
+
+    fn cargo_features(&self) -> &[String] {
+        &self.cargo_features
+    }
+
+    fn add_cargo_feature(&mut self, feature: &str) -> Result<()> {
+        if feature.is_empty()
+            || !feature
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_/".contains(c))
+        {
+            return Err(anyhow!("invalid Cargo feature name: {}", feature));
+        }
+
+        if !self.cargo_features.iter().any(|f| f == feature) {
+            self.cargo_features.push(feature.to_string());
+        }
+
+        Ok(())
+    }
+
+    fn build_env(&self) -> &BTreeMap<String, String> {
+        &self.build_env
+    }
+
+    fn set_build_env(&mut self, key: &str, value: &str) -> Result<()> {
+        if key.is_empty() || key.contains('=') || key.contains('\0') || value.contains('\0') {
+            return Err(anyhow!("invalid environment variable: {}", key));
+        }
+
+        self.build_env.insert(key.to_string(), value.to_string());
+
+        Ok(())
+    }
+
+    fn embedded_python_config(&self) -> &EmbeddedPythonConfig {
+        &self.config
+    }
+
     fn tcl_files_path(&self) -> &Option<String> {
         &self.tcl_files_path
     }
@@ -430,6 +820,8 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         verbose: bool,
         args: &[String],
     ) -> Result<Vec<PythonResource>> {
+        let _timer = PhaseTimer::new(PHASE_PIP);
+
         pip_download(
             logger,
             &*self.host_distribution,
@@ -446,7 +838,10 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         verbose: bool,
         install_args: &[String],
         extra_envs: &HashMap<String, String>,
+        cwd: &Path,
     ) -> Result<Vec<PythonResource>> {
+        let _timer = PhaseTimer::new(PHASE_PIP);
+
         pip_install(
             logger,
             &*self.target_distribution,
@@ -455,6 +850,29 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
             verbose,
             install_args,
             extra_envs,
+            cwd,
+        )
+    }
+
+    fn install_groups(
+        &self,
+        logger: &slog::Logger,
+        verbose: bool,
+        groups: &[InstallGroup],
+        extra_envs: &HashMap<String, String>,
+        cwd: &Path,
+    ) -> Result<Vec<PythonResource>> {
+        let _timer = PhaseTimer::new(PHASE_PIP);
+
+        install_groups(
+            logger,
+            &*self.target_distribution,
+            self.python_packaging_policy(),
+            self.link_mode,
+            verbose,
+            groups,
+            extra_envs,
+            cwd,
         )
     }
 
@@ -464,6 +882,8 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         path: &Path,
         packages: &[String],
     ) -> Result<Vec<PythonResource>> {
+        let _timer = PhaseTimer::new(PHASE_SCAN);
+
         Ok(find_resources(
             &*self.target_distribution,
             self.python_packaging_policy(),
@@ -482,6 +902,8 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
     }
 
     fn read_virtualenv(&self, _logger: &slog::Logger, path: &Path) -> Result<Vec<PythonResource>> {
+        let _timer = PhaseTimer::new(PHASE_SCAN);
+
         read_virtualenv(
             &*self.target_distribution,
             self.python_packaging_policy(),
@@ -497,6 +919,8 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         extra_envs: &HashMap<String, String>,
         extra_global_arguments: &[String],
     ) -> Result<Vec<PythonResource>> {
+        let _timer = PhaseTimer::new(PHASE_PIP);
+
         setup_py_install(
             logger,
             &*self.target_distribution,
@@ -544,6 +968,7 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
                 PythonResource::EggFile(_) => false,
                 PythonResource::PathExtension(_) => false,
                 PythonResource::File(_) => false,
+                PythonResource::GettextCatalog(_) => false,
             })
         {
             let mut add_context = self
@@ -615,11 +1040,44 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         extension_module: &PythonExtensionModule,
         add_context: Option<PythonResourceAddCollectionContext>,
     ) -> Result<()> {
-        let add_context = add_context.unwrap_or_else(|| {
+        if !extension_module.is_compatible_with_cache_tag(self.cache_tag()) {
+            return Err(anyhow!(
+                "extension module {} ({}) is not compatible with target Python interpreter {}",
+                extension_module.name,
+                extension_module.file_name(),
+                self.cache_tag()
+            ));
+        }
+
+        let mut add_context = add_context.unwrap_or_else(|| {
             self.packaging_policy
                 .derive_add_collection_context(&extension_module.into())
         });
 
+        // Extension modules whose DLLs can't be loaded from memory are
+        // installed next to the executable instead.
+        if add_context.location == ConcreteResourceLocation::InMemory
+            && self.supports_in_memory_dynamically_linked_extension_loading
+            && self
+                .packaging_policy
+                .allow_in_memory_shared_library_loading()
+            && self.target_distribution.is_extension_module_file_loadable()
+            && extension_module.shared_library.is_some()
+            && !extension_module.in_libpython()
+        {
+            let issues = extension_module_memory_loading_issues(extension_module)?;
+
+            if !issues.is_empty() {
+                add_context.location = self.packaging_policy.filesystem_location();
+                add_context.location_fallback = None;
+
+                self.relocated_extension_modules.insert(
+                    extension_module.name.clone(),
+                    issues.iter().map(|issue| issue.to_string()).collect(),
+                );
+            }
+        }
+
         if let Some(mut build_context) = self
             .resources_collector
             .add_python_extension_module_with_context(extension_module, &add_context)?
@@ -664,13 +1122,182 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
             .add_file_data_with_context(file, &add_context)
     }
 
+    fn add_gettext_catalog(
+        &mut self,
+        catalog: &GettextCatalog,
+        add_context: Option<PythonResourceAddCollectionContext>,
+    ) -> Result<()> {
+        let add_context = add_context.unwrap_or_else(|| {
+            self.packaging_policy
+                .derive_add_collection_context(&catalog.into())
+        });
+
+        if self.packaging_policy.gettext_catalogs_location().is_some() {
+            self.resources_collector
+                .add_gettext_catalog_with_context(catalog, &add_context)
+        } else {
+            self.resources_collector
+                .add_python_package_resource_with_context(
+                    &catalog.to_package_resource(),
+                    &add_context,
+                )
+        }
+    }
+
+    fn add_shared_library(
+        &mut self,
+        library: &SharedLibrary,
+        searched_by: &[String],
+    ) -> Result<()> {
+        let filename = library.filename.as_ref().ok_or_else(|| {
+            anyhow!(
+                "cannot add shared library {} without known filename",
+                library.name
+            )
+        })?;
+
+        for module in searched_by {
+            self.resources_collector
+                .add_extension_module_shared_library_dependency(module, &library.name)?;
+        }
+
+        self.shared_libraries
+            .insert(filename.clone(), library.data.clone());
+
+        Ok(())
+    }
+
+    fn add_qt_support(&mut self, binding: &str) -> Result<()> {
+        let binding = QtBinding::resolve(binding, &self.target_triple)?;
+
+        // Resources are installed in the location resolved for the binding's
+        // package, e.g. `lib/PySide6` with `lib/{package}`.
+        let prefix = std::iter::once(self.packaging_policy.resources_location())
+            .chain(self.packaging_policy.resources_location_fallback().iter())
+            .find_map(|location| match location {
+                ConcreteResourceLocation::RelativePath(prefix) => {
+                    Some(prefix.replace(PACKAGE_PLACEHOLDER, binding.package))
+                }
+                ConcreteResourceLocation::InMemory => None,
+            })
+            .ok_or_else(|| {
+                anyhow!(
+                    "Qt support requires a filesystem-relative resources location; \
+                     set resources_location_fallback on the packaging policy"
+                )
+            })?;
+
+        if self
+            .resources_collector
+            .relocate_package_resources(binding.package, &prefix)?
+            == 0
+        {
+            return Err(anyhow!(
+                "no resources of {} found; add them before enabling Qt support",
+                binding.package
+            ));
+        }
+
+        let prefix_path = ["$ORIGIN", prefix.as_str()]
+            .iter()
+            .filter(|p| !p.is_empty())
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("/");
+        let package_path = format!("{}/{}", prefix_path, binding.package);
+
+        for (key, value) in binding.environment_variables(&package_path) {
+            self.config.environment_variables.retain(|(k, _)| k != &key);
+            self.config.environment_variables.push((key, value));
+        }
+
+        // The filesystem importer and code locating the package via
+        // `sys.path` need the directory holding the package there.
+        let search_paths = self
+            .config
+            .config
+            .module_search_paths
+            .get_or_insert_with(Vec::new);
+        let prefix_path = PathBuf::from(prefix_path);
+        if !search_paths.contains(&prefix_path) {
+            search_paths.push(prefix_path);
+        }
+
+        Ok(())
+    }
+
+    fn add_external_asset(&mut self, asset: ExternalAsset) -> Result<()> {
+        if asset.sha256.len() != 64 || !asset.sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(anyhow!(
+                "SHA-256 of asset {} must be 64 hex characters; got {}",
+                asset.name,
+                asset.sha256
+            ));
+        }
+
+        if !asset.url.starts_with("https://") && !asset.url.starts_with("http://") {
+            return Err(anyhow!(
+                "URL of asset {} must be http:// or https://; got {}",
+                asset.name,
+                asset.url
+            ));
+        }
+
+        if self
+            .config
+            .external_assets
+            .iter()
+            .any(|a| a.name == asset.name)
+        {
+            return Err(anyhow!("asset {} already defined", asset.name));
+        }
+
+        self.config.external_assets.push(asset);
+
+        Ok(())
+    }
+
+    fn add_binary_metadata(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        if name.is_empty() {
+            return Err(anyhow!("binary metadata name cannot be empty"));
+        }
+
+        if self.config.binary_metadata.iter().any(|(n, _)| n == name) {
+            return Err(anyhow!("binary metadata {} already defined", name));
+        }
+
+        self.config
+            .binary_metadata
+            .push((name.to_string(), data.to_vec()));
+
+        Ok(())
+    }
+
     fn filter_resources_from_files(
         &mut self,
         logger: &slog::Logger,
         files: &[&Path],
         glob_patterns: &[&str],
     ) -> Result<()> {
-        let resource_names = resolve_resource_names_from_files(files, glob_patterns)?;
+        let mut resource_names = resolve_resource_names_from_files(files, glob_patterns)?;
+
+        // Modules imported dynamically by retained packages wouldn't be in
+        // lists of names derived from imports, so retain them too.
+        if let Some(hints) = self.packaging_policy.package_hints() {
+            let hidden_imports = self
+                .resources_collector
+                .iter_resources()
+                .map(|(name, _)| name)
+                .chain(self.extension_build_contexts.keys())
+                .filter(|name| hints.is_hidden_import(&resource_names, name))
+                .cloned()
+                .collect::<Vec<_>>();
+
+            for name in hidden_imports {
+                warn!(logger, "retaining {} imported dynamically", name);
+                resource_names.insert(name);
+            }
+        }
 
         warn!(logger, "filtering module entries");
 
@@ -693,11 +1320,35 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         self.config.raw_allocator == MemoryAllocatorBackend::Jemalloc
     }
 
+    fn compile_resources(&self) -> Result<CompiledResourcesCollection> {
+        let mut compiler = CachedBytecodeCompiler::wrap_default(Box::new(BytecodeCompiler::new(
+            self.host_python_exe_path(),
+        )?))?;
+        self.resources_collector
+            .compile_resources(compiler.as_mut())
+    }
+
     fn to_embedded_python_context(
         &self,
         logger: &slog::Logger,
         opt_level: &str,
     ) -> Result<EmbeddedPythonContext> {
+        if self.bundle_tzdata && !self.has_tzdata() {
+            let mut builder = self.clone();
+            builder.add_tzdata(logger)?;
+
+            return builder.to_embedded_python_context(logger, opt_level);
+        }
+
+        for (name, issues) in &self.relocated_extension_modules {
+            warn!(
+                logger,
+                "installing extension module {} on the filesystem because it can't be loaded from memory: {}",
+                name,
+                issues.join("; ")
+            );
+        }
+
         let mut file_seen = false;
         for module in self.resources_collector.find_dunder_file()? {
             file_seen = true;
@@ -716,11 +1367,71 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
             );
         }
 
+        if self.packaging_policy.stdlib_profile().is_some()
+            || !self.packaging_policy.excluded_modules().is_empty()
+        {
+            let packaged = self
+                .resources_collector
+                .iter_resources()
+                .map(|(name, _)| name.as_str())
+                .collect::<BTreeSet<_>>();
+
+            let broken = self.resources_collector.find_import_chains(|name| {
+                self.packaging_policy.is_stdlib_excluded(name) && !packaged.contains(name)
+            })?;
+
+            if !broken.is_empty() {
+                for chain in &broken {
+                    warn!(logger, "import chain: {}", chain.join(" -> "));
+                }
+
+                return Err(anyhow!(
+                    "{} imports of standard library modules excluded by the packaging policy \
+                    (e.g. {}); remove the importing modules or exclude fewer modules",
+                    broken.len(),
+                    broken[0].join(" -> ")
+                ));
+            }
+        }
+
+        self.config
+            .validate_for_python_version(self.target_distribution.python_capabilities())?;
+
         let compiled_resources = {
-            let mut compiler = BytecodeCompiler::new(self.host_python_exe_path())?;
-            self.resources_collector.compile_resources(&mut compiler)?
+            let _timer = PhaseTimer::new(PHASE_COMPILE);
+            self.compile_resources()?
         };
 
+        // Frozen modules are registered from in-memory bytecode at the
+        // interpreter's optimization level.
+        let optimize_level = self
+            .config
+            .config
+            .optimization_level
+            .unwrap_or(BytecodeOptimizationLevel::Zero);
+        for name in &self.config.frozen_modules {
+            let resource = compiled_resources
+                .resources
+                .get(name)
+                .ok_or_else(|| anyhow!("frozen module {} is not packaged", name))?;
+
+            let bytecode = match optimize_level {
+                BytecodeOptimizationLevel::Zero => &resource.in_memory_bytecode,
+                BytecodeOptimizationLevel::One => &resource.in_memory_bytecode_opt1,
+                BytecodeOptimizationLevel::Two => &resource.in_memory_bytecode_opt2,
+            };
+            if bytecode.is_none() {
+                return Err(anyhow!(
+                    "frozen module {} has no in-memory bytecode at optimization level {:?}; \
+                    ensure it is located in-memory and bytecode is generated at this level",
+                    name,
+                    optimize_level
+                ));
+            }
+        }
+
+        let package_sizes = compiled_resources.package_sizes()?;
+
         let mut extra_files = FileManifest::default();
 
         for (path, location, executable) in &compiled_resources.extra_files {
@@ -741,20 +1452,151 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
         }
 
         let mut resources = Vec::new();
-        compiled_resources.write_packed_resources(&mut resources)?;
+        {
+            let _timer = PhaseTimer::new(PHASE_SERIALIZE);
+            compiled_resources.write_packed_resources(&mut resources)?;
+        }
+
+        let mut config = self.config.clone();
+        config.packed_resources_embedding = self.packed_resources_embedding;
+        config.gettext_domains.extend(
+            compiled_resources
+                .gettext_domains
+                .iter()
+                .map(|(domain, path)| (domain.clone(), PathBuf::from("$ORIGIN").join(path))),
+        );
+
+        if let Some(version) = &self.version {
+            if config
+                .binary_metadata
+                .iter()
+                .any(|(name, _)| name == "build_info")
+            {
+                return Err(anyhow!(
+                    "binary metadata build_info is defined by the executable version"
+                ));
+            }
+
+            config.binary_metadata.push((
+                "build_info".to_string(),
+                serde_json::to_vec(&serde_json::json!({ "version": version }))?,
+            ));
+        }
 
-        let linking_info = self.resolve_python_linking_info(logger, opt_level)?;
+        // zoneinfo looks up time zones in its search path before falling back
+        // to the tzdata package. Point it at the bundled files when they are
+        // on the filesystem so system data, which may be missing or stale,
+        // isn't consulted. zoneinfo doesn't exist before Python 3.9.
+        if self.bundle_tzdata
+            && config.tzpath.is_none()
+            && self.target_distribution.python_major_minor_version() != "3.8"
+        {
+            if let Some(path) = self.tzdata_zoneinfo_path() {
+                config.tzpath = Some(PathBuf::from("$ORIGIN").join(path));
+            }
+        }
+
+        let mut linking_info = {
+            let _timer = PhaseTimer::new(PHASE_LINK);
+            self.resolve_python_linking_info(logger, opt_level)?
+        };
+
+        let install_dir = shared_library_install_dir(&self.target_triple);
+
+        // Native libraries, relative to the directory they are installed in.
+        let mut libraries = FileManifest::default();
+
+        for (filename, location) in &self.shared_libraries {
+            libraries.add_file(
+                &PathBuf::from(install_dir).join(filename),
+                &FileContent {
+                    data: location.resolve()?,
+                    executable: true,
+                },
+            )?;
+        }
 
         if self.link_mode == LibpythonLinkMode::Dynamic {
             if let Some(p) = &self.target_distribution.libpython_shared_library {
-                let manifest_path = Path::new(p.file_name().unwrap());
-                let content = FileContent {
-                    data: std::fs::read(&p)?,
-                    executable: false,
+                // Without an external runtime, libpython is installed next to
+                // the binary. In one, it is found via the search path.
+                let manifest_path = if self.external_runtime_path.is_some() {
+                    PathBuf::from(install_dir).join(p.file_name().unwrap())
+                } else {
+                    PathBuf::from(p.file_name().unwrap())
                 };
 
-                extra_files.add_file(&manifest_path, &content)?;
+                libraries.add_file(
+                    &manifest_path,
+                    &FileContent {
+                        data: std::fs::read(&p)?,
+                        executable: false,
+                    },
+                )?;
+            }
+        }
+
+        // Move resources and native libraries to a versioned directory of the
+        // external runtime path and pin the launcher to their content. The
+        // directory name is derived from the content, so launchers built from
+        // the same runtime share a directory and different runtimes can be
+        // installed side by side. Windows only loads DLLs imported by the
+        // binary from its own directory, so they stay next to the launcher.
+        let library_root = match &self.external_runtime_path {
+            Some(runtime_path) => {
+                let shares_libraries = !install_dir.is_empty();
+
+                let mut hasher = Sha256::new();
+                hasher.update(&resources);
+                if shares_libraries {
+                    for (path, content) in libraries.entries() {
+                        hasher.update(path.display().to_string().as_bytes());
+                        hasher.update(&content.data);
+                    }
+                }
+
+                let runtime_dir = PathBuf::from(runtime_path).join(format!(
+                    "{}-{}",
+                    self.target_distribution.python_major_minor_version(),
+                    &hex::encode(hasher.finalize())[0..16]
+                ));
+                let install_path = runtime_dir.join(EXTERNAL_RUNTIME_PACKED_RESOURCES_FILENAME);
+
+                config.packed_resources_files.push(PackedResourcesFile {
+                    path: PathBuf::from("$ORIGIN").join(&install_path),
+                    sha256: Some(hex::encode(Sha256::digest(&resources))),
+                });
+
+                extra_files.add_file(
+                    &install_path,
+                    &FileContent {
+                        data: std::mem::take(&mut resources),
+                        executable: false,
+                    },
+                )?;
+
+                if shares_libraries {
+                    runtime_dir
+                } else {
+                    PathBuf::new()
+                }
             }
+            None => PathBuf::new(),
+        };
+
+        for (path, content) in libraries.entries() {
+            extra_files.add_file(&library_root.join(path), content)?;
+        }
+
+        // Consumed by the build script of the generated Rust project, which
+        // adds the directory to the binary's run-time search path.
+        if !install_dir.is_empty()
+            && (!self.shared_libraries.is_empty() || !library_root.as_os_str().is_empty())
+        {
+            linking_info.cargo_metadata.push(format!(
+                "cargo:runtime-library-dir={}",
+                library_root.join(install_dir).display()
+            ));
         }
 
         if let Some(tcl_files_path) = self.tcl_files_path() {
@@ -771,12 +1613,63 @@ impl PythonBinaryBuilder for StandalonePythonExecutableBuilder {
             }
         }
 
+        if self.config.terminfo_resolution == TerminfoResolution::Bundled
+            && !crate::environment::WINDOWS_TARGET_TRIPLES.contains(&self.target_triple.as_str())
+        {
+            let files = resolve_minimal_terminfo_files(&HOST_TERMINFO_DIRS)?;
+
+            if files.is_empty() {
+                warn!(
+                    logger,
+                    "unable to find a terminfo database on the build host; not bundling one"
+                );
+            }
+
+            for (path, fs_path) in files {
+                extra_files.add_file(
+                    &PathBuf::from(BUNDLED_TERMINFO_DIRECTORY).join(path),
+                    &FileContent {
+                        data: std::fs::read(&fs_path)?,
+                        executable: false,
+                    },
+                )?;
+            }
+        }
+
+        if let Some(deployment_target) = &self.macos_deployment_target {
+            if self.target_triple.ends_with("-apple-darwin") {
+                check_macos_deployment_target(
+                    *deployment_target,
+                    &compiled_resources,
+                    &extra_files,
+                )?;
+            }
+        }
+
+        let mut windows_runtime_requirements = WindowsRuntimeRequirements::default();
+
+        if self.target_triple.contains("-windows-msvc") {
+            for resource in compiled_resources.resources.values() {
+                for data in resource
+                    .in_memory_extension_module_shared_library
+                    .iter()
+                    .chain(resource.in_memory_shared_library.iter())
+                {
+                    windows_runtime_requirements.add_binary(data)?;
+                }
+            }
+
+            windows_runtime_requirements.add_manifest(&extra_files)?;
+        }
+
         Ok(EmbeddedPythonContext {
-            config: self.config.clone(),
+            config,
             linking_info,
             module_names,
             resources,
             extra_files,
+            package_sizes,
+            windows_runtime_requirements,
             host_triple: self.host_triple.clone(),
             target_triple: self.target_triple.clone(),
         })
@@ -791,7 +1684,7 @@ pub mod tests {
         crate::python_distributions::PYTHON_DISTRIBUTIONS,
         crate::testutil::*,
         lazy_static::lazy_static,
-        python_packaging::{location::ConcreteResourceLocation, policy::ExtensionModuleFilter},
+        python_packaging::{policy::ExtensionModuleFilter, stdlib_profiles::stdlib_profile_names},
         std::collections::BTreeSet,
         std::iter::FromIterator,
         std::ops::DerefMut,
@@ -867,12 +1760,14 @@ pub mod tests {
         pub host_triple: String,
         pub target_triple: String,
         pub distribution_flavor: DistributionFlavor,
+        pub python_version: Option<String>,
         pub app_name: String,
         pub libpython_link_mode: BinaryLibpythonLinkMode,
         pub extension_module_filter: Option<ExtensionModuleFilter>,
         pub resources_location: Option<ConcreteResourceLocation>,
         pub resources_location_fallback: Option<Option<ConcreteResourceLocation>>,
         pub allow_in_memory_shared_library_loading: Option<bool>,
+        pub stdlib_profile: Option<String>,
     }
 
     impl Default for StandalonePythonExecutableBuilderOptions {
@@ -881,12 +1776,14 @@ pub mod tests {
                 host_triple: env!("HOST").to_string(),
                 target_triple: env!("HOST").to_string(),
                 distribution_flavor: DistributionFlavor::Standalone,
+                python_version: None,
                 app_name: "testapp".to_string(),
                 libpython_link_mode: BinaryLibpythonLinkMode::Default,
                 extension_module_filter: None,
                 resources_location: None,
                 resources_location_fallback: None,
                 allow_in_memory_shared_library_loading: None,
+                stdlib_profile: None,
             }
         }
     }
@@ -894,7 +1791,11 @@ pub mod tests {
     impl StandalonePythonExecutableBuilderOptions {
         pub fn new_builder(&self) -> Result<Box<StandalonePythonExecutableBuilder>> {
             let target_record = PYTHON_DISTRIBUTIONS
-                .find_distribution(&self.target_triple, &self.distribution_flavor, None)
+                .find_distribution(
+                    &self.target_triple,
+                    &self.distribution_flavor,
+                    self.python_version.as_deref(),
+                )
                 .ok_or_else(|| anyhow!("could not find target Python distribution"))?;
 
             let target_distribution = get_distribution(&target_record.location)?;
@@ -925,6 +1826,9 @@ pub mod tests {
             if let Some(value) = &self.allow_in_memory_shared_library_loading {
                 policy.set_allow_in_memory_shared_library_loading(*value);
             }
+            if let Some(name) = &self.stdlib_profile {
+                policy.set_stdlib_profile(Some(name))?;
+            }
 
             let config = EmbeddedPythonConfig::default();
 
@@ -1054,6 +1958,374 @@ pub mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_add_qt_support_package_location() -> Result<()> {
+        let logger = get_logger()?;
+        let location = ConcreteResourceLocation::RelativePath("lib/{package}".to_string());
+        let options = StandalonePythonExecutableBuilderOptions {
+            resources_location_fallback: Some(Some(location.clone())),
+            ..StandalonePythonExecutableBuilderOptions::default()
+        };
+        let mut exe = options.new_builder()?;
+
+        exe.add_python_module_source(
+            &PythonModuleSource {
+                name: "PySide6".to_string(),
+                source: DataLocation::Memory(
+                    b"import os\nPLUGINS = os.path.join(os.path.dirname(__file__), 'Qt', 'plugins')\n"
+                        .to_vec(),
+                ),
+                is_package: true,
+                cache_tag: exe.cache_tag().to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            Some(PythonResourceAddCollectionContext {
+                include: true,
+                location,
+                location_fallback: None,
+                store_source: true,
+                optimize_level_zero: false,
+                optimize_level_one: false,
+                optimize_level_two: false,
+            }),
+        )?;
+        exe.add_python_package_resource(
+            &PythonPackageResource {
+                leaf_package: "PySide6".to_string(),
+                relative_name: "Qt/plugins/platforms/libqxcb.so".to_string(),
+                data: DataLocation::Memory(b"plugin".to_vec()),
+                is_stdlib: false,
+                is_test: false,
+            },
+            None,
+        )?;
+        exe.add_qt_support("pyside6")?;
+
+        let embedded = exe.to_embedded_python_context(&logger, "0")?;
+        assert!(embedded.config.environment_variables.contains(&(
+            "QT_PLUGIN_PATH".to_string(),
+            "$ORIGIN/lib/PySide6/PySide6/Qt/plugins".to_string()
+        )));
+        assert_eq!(
+            embedded.config.config.module_search_paths,
+            Some(vec![PathBuf::from("$ORIGIN/lib/PySide6")])
+        );
+
+        // Import the installed package the way the filesystem importer would.
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        embedded.extra_files.write_to_path(temp_dir.path())?;
+
+        let search_paths = embedded
+            .config
+            .config
+            .module_search_paths
+            .unwrap()
+            .iter()
+            .map(|p| {
+                p.display()
+                    .to_string()
+                    .replace("$ORIGIN", &temp_dir.path().display().to_string())
+            })
+            .collect::<Vec<_>>();
+
+        let output = std::process::Command::new(exe.host_python_exe_path())
+            .arg("-S")
+            .arg("-c")
+            .arg(format!(
+                "import os, sys; sys.path[:0] = {:?}; import PySide6; \
+                 assert os.path.isfile(os.path.join(PySide6.PLUGINS, 'platforms', 'libqxcb.so'))",
+                search_paths
+            ))
+            .output()?;
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_external_runtime() -> Result<()> {
+        let logger = get_logger()?;
+        let options = StandalonePythonExecutableBuilderOptions::default();
+        let mut exe = options.new_builder()?;
+
+        assert!(exe
+            .set_external_runtime_path(Some("../runtime".to_string()))
+            .is_err());
+        exe.set_external_runtime_path(Some("runtimes".to_string()))?;
+        exe.add_shared_library(
+            &SharedLibrary {
+                name: "mylib".to_string(),
+                data: DataLocation::Memory(b"library".to_vec()),
+                filename: Some(PathBuf::from("libmylib.so")),
+            },
+            &[],
+        )?;
+
+        let runtime_dir = |embedded: &EmbeddedPythonContext| -> PathBuf {
+            let (path, _) = embedded
+                .extra_files
+                .entries()
+                .find(|(path, _)| path.ends_with(EXTERNAL_RUNTIME_PACKED_RESOURCES_FILENAME))
+                .expect("packed resources installed in runtime");
+
+            path.parent().unwrap().to_path_buf()
+        };
+
+        let embedded = exe.to_embedded_python_context(&logger, "0")?;
+        assert!(embedded.resources.is_empty());
+
+        let runtime = runtime_dir(&embedded);
+        assert_eq!(runtime.parent(), Some(Path::new("runtimes")));
+        assert!(runtime
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with(&format!(
+                "{}-",
+                exe.target_distribution.python_major_minor_version()
+            )));
+
+        let install_path = runtime.join(EXTERNAL_RUNTIME_PACKED_RESOURCES_FILENAME);
+        let (_, content) = embedded
+            .extra_files
+            .entries()
+            .find(|(path, _)| **path == install_path)
+            .unwrap();
+        assert_eq!(
+            embedded.config.packed_resources_files,
+            vec![PackedResourcesFile {
+                path: PathBuf::from("$ORIGIN").join(&install_path),
+                sha256: Some(hex::encode(Sha256::digest(&content.data))),
+            }]
+        );
+
+        // Native libraries are shared via the runtime, except on Windows.
+        let install_dir = shared_library_install_dir(&exe.target_triple);
+        let library_path = if install_dir.is_empty() {
+            PathBuf::from("libmylib.so")
+        } else {
+            assert!(embedded.linking_info.cargo_metadata.contains(&format!(
+                "cargo:runtime-library-dir={}",
+                runtime.join(install_dir).display()
+            )));
+
+            runtime.join(install_dir).join("libmylib.so")
+        };
+        assert!(embedded
+            .extra_files
+            .entries()
+            .any(|(path, _)| **path == library_path));
+
+        // Another launcher with the same runtime content uses the same runtime
+        // directory. Different content uses a different one.
+        let mut other = exe.clone();
+        other.set_windows_subsystem("windows")?;
+        assert_eq!(
+            runtime_dir(&other.to_embedded_python_context(&logger, "0")?),
+            runtime
+        );
+
+        other.add_python_module_source(
+            &PythonModuleSource {
+                name: "myapp".to_string(),
+                source: DataLocation::Memory(b"print('hello')".to_vec()),
+                is_package: false,
+                cache_tag: exe.cache_tag().to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            None,
+        )?;
+        assert_ne!(
+            runtime_dir(&other.to_embedded_python_context(&logger, "0")?),
+            runtime
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bundle_tzdata() -> Result<()> {
+        let logger = get_logger()?;
+        let options = StandalonePythonExecutableBuilderOptions {
+            // zoneinfo doesn't exist before Python 3.9.
+            python_version: Some("3.9".to_string()),
+            resources_location: Some(ConcreteResourceLocation::RelativePath("lib".to_string())),
+            resources_location_fallback: Some(None),
+            ..StandalonePythonExecutableBuilderOptions::default()
+        };
+        let mut exe = options.new_builder()?;
+
+        // Already added tzdata resources are used instead of downloading them.
+        exe.add_python_package_resource(
+            &PythonPackageResource {
+                leaf_package: "tzdata.zoneinfo".to_string(),
+                relative_name: "UTC".to_string(),
+                data: DataLocation::Memory(b"TZif".to_vec()),
+                is_stdlib: false,
+                is_test: false,
+            },
+            None,
+        )?;
+
+        let embedded = exe.to_embedded_python_context(&logger, "0")?;
+        assert!(embedded.config.tzpath.is_none());
+
+        exe.set_bundle_tzdata(true);
+        let embedded = exe.to_embedded_python_context(&logger, "0")?;
+        assert_eq!(
+            embedded.config.tzpath,
+            Some(
+                PathBuf::from("$ORIGIN")
+                    .join("lib")
+                    .join("tzdata")
+                    .join("zoneinfo")
+            )
+        );
+        // The search path isn't set via an environment variable, which child
+        // processes would inherit.
+        assert!(!embedded
+            .config
+            .environment_variables
+            .iter()
+            .any(|(key, _)| key == "PYTHONTZPATH"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stdlib_profiles() -> Result<()> {
+        let logger = get_logger()?;
+
+        for name in stdlib_profile_names() {
+            let options = StandalonePythonExecutableBuilderOptions {
+                stdlib_profile: Some(name.to_string()),
+                ..StandalonePythonExecutableBuilderOptions::default()
+            };
+            let exe = options.new_builder()?;
+
+            // Packaging fails if a retained module imports a trimmed one.
+            exe.to_embedded_python_context(&logger, "0")
+                .with_context(|| format!("packaging with stdlib profile {}", name))?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_shared_library() -> Result<()> {
+        let logger = get_logger()?;
+        let options = StandalonePythonExecutableBuilderOptions::default();
+        let mut exe = options.new_builder()?;
+
+        let library = SharedLibrary {
+            name: "mylib".to_string(),
+            data: DataLocation::Memory(b"library".to_vec()),
+            filename: Some(PathBuf::from("libmylib.so")),
+        };
+
+        assert!(exe
+            .add_shared_library(&library, &["missing".to_string()])
+            .is_err());
+        exe.add_shared_library(&library, &[])?;
+
+        let embedded = exe.to_embedded_python_context(&logger, "0")?;
+
+        let install_dir = shared_library_install_dir(&exe.target_triple);
+        let install_path = PathBuf::from(install_dir).join("libmylib.so");
+        assert!(embedded
+            .extra_files
+            .entries()
+            .any(|(path, _)| **path == install_path));
+
+        if !install_dir.is_empty() {
+            assert!(embedded
+                .linking_info
+                .cargo_metadata
+                .contains(&format!("cargo:runtime-library-dir={}", install_dir)));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_external_asset() -> Result<()> {
+        let options = StandalonePythonExecutableBuilderOptions::default();
+        let mut exe = options.new_builder()?;
+
+        let asset = ExternalAsset {
+            name: "model".to_string(),
+            url: "https://example.com/model.bin".to_string(),
+            sha256: "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824".to_string(),
+            path: PathBuf::from("$ORIGIN/assets/model"),
+        };
+
+        assert!(exe
+            .add_external_asset(ExternalAsset {
+                sha256: "abcd".to_string(),
+                ..asset.clone()
+            })
+            .is_err());
+        assert!(exe
+            .add_external_asset(ExternalAsset {
+                url: "ftp://example.com/model.bin".to_string(),
+                ..asset.clone()
+            })
+            .is_err());
+
+        exe.add_external_asset(asset.clone())?;
+        assert!(exe.add_external_asset(asset.clone()).is_err());
+        assert_eq!(exe.embedded_python_config().external_assets, vec![asset]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_binary_metadata() -> Result<()> {
+        let options = StandalonePythonExecutableBuilderOptions::default();
+        let mut exe = options.new_builder()?;
+
+        exe.add_binary_metadata("build", b"commit=abcdef")?;
+        assert!(exe.add_binary_metadata("build", b"other").is_err());
+        assert!(exe.add_binary_metadata("", b"other").is_err());
+        assert_eq!(
+            exe.embedded_python_config().binary_metadata,
+            vec![("build".to_string(), b"commit=abcdef".to_vec())]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_version() -> Result<()> {
+        let logger = get_logger()?;
+        let options = StandalonePythonExecutableBuilderOptions::default();
+        let mut exe = options.new_builder()?;
+
+        assert!(exe.set_version(Some("1.0\"".to_string())).is_err());
+        assert!(exe.set_version(Some("256.0".to_string())).is_err());
+        exe.set_version(Some("1.2.0+3.g1a2b3c4".to_string()))?;
+
+        let embedded = exe.to_embedded_python_context(&logger, "0")?;
+        assert_eq!(
+            embedded.config.binary_metadata,
+            vec![(
+                "build_info".to_string(),
+                b"{\"version\":\"1.2.0+3.g1a2b3c4\"}".to_vec()
+            )]
+        );
+
+        exe.add_binary_metadata("build_info", b"other")?;
+        assert!(exe.to_embedded_python_context(&logger, "0").is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_minimal_extensions_present() -> Result<()> {
         let options = StandalonePythonExecutableBuilderOptions::default();
@@ -2402,7 +3674,7 @@ pub mod tests {
 
             let resources = shared_libraries
                 .iter()
-                .map(|r| r.to_resource(compiler.deref_mut()))
+                .map(|r| r.to_resource(compiler.deref_mut(), false))
                 .collect::<Result<Vec<_>>>()?;
             assert_eq!(resources.len(), 2);
 
@@ -2736,6 +4008,7 @@ pub mod tests {
                 false,
                 &["pyyaml==5.3.1".to_string()],
                 &HashMap::new(),
+                &std::env::current_dir()?,
             )?;
 
             let extensions = resources
@@ -2801,4 +4074,19 @@ pub mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_extension_module_version_mismatch() -> Result<()> {
+        let options = StandalonePythonExecutableBuilderOptions::default();
+        let mut builder = options.new_builder()?;
+
+        let mut em = EXTENSION_MODULE_SHARED_LIBRARY_ONLY.clone();
+        em.extension_file_suffix = ".cpython-27-x86_64-linux-gnu.so".to_string();
+        assert!(builder.add_python_extension_module(&em, None).is_err());
+
+        em.extension_file_suffix = ".abi3.so".to_string();
+        builder.add_python_extension_module(&em, None)?;
+
+        Ok(())
+    }
 }
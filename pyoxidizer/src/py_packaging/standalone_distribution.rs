@@ -15,7 +15,13 @@ use {
         distutils::prepare_hacked_distutils,
         standalone_builder::StandalonePythonExecutableBuilder,
     },
-    crate::environment::{LINUX_TARGET_TRIPLES, MACOS_TARGET_TRIPLES},
+    crate::{
+        artifact_store::CachedBytecodeCompiler,
+        environment::{
+            check_disk_space, DISTRIBUTION_REQUIRED_SPACE, LINUX_TARGET_TRIPLES,
+            MACOS_TARGET_TRIPLES,
+        },
+    },
     anyhow::{anyhow, Context, Result},
     duct::cmd,
     lazy_static::lazy_static,
@@ -27,6 +33,7 @@ use {
         location::ConcreteResourceLocation,
         module_util::{is_package_from_path, PythonModuleSuffixes},
         policy::PythonPackagingPolicy,
+        python_version::{parse_major_minor, resolve_python_version, PythonVersionCapabilities},
         resource::{
             DataLocation, LibraryDependency, PythonExtensionModule, PythonExtensionModuleVariants,
             PythonModuleSource, PythonPackageResource, PythonResource,
@@ -187,7 +194,16 @@ fn parse_python_json(path: &Path) -> Result<PythonJsonMain> {
         return Err(anyhow!("PYTHON.json does not exist; are you using an up-to-date Python distribution that conforms with our requirements?"));
     }
 
-    let buf = std::fs::read(path)?;
+    // PYTHON.json describes every file in the distribution and is read by
+    // every build. Mapping it avoids copying it into memory.
+    let fh = std::fs::File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    // SAFETY: the file lives in a distribution extraction directory. It is
+    // only written while extracting, which holds the directory's extraction
+    // lock and completes before the directory is renamed into place and
+    // parsed. Nothing modifies it afterwards and cache cleanup only unlinks
+    // whole directories, which leaves existing mappings intact.
+    let buf = unsafe { memmap::Mmap::map(&fh) }
+        .with_context(|| format!("memory mapping {}", path.display()))?;
 
     let value: serde_json::Value = serde_json::from_slice(&buf)?;
     let o = value
@@ -220,6 +236,34 @@ fn parse_python_json_from_distribution(dist_dir: &Path) -> Result<PythonJsonMain
     parse_python_json(&python_json_path)
 }
 
+/// Summary of an extracted Python distribution.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DistributionSummary {
+    /// Python version, e.g. `3.8.6`.
+    pub python_version: String,
+
+    /// Rust target triple the distribution runs on.
+    pub target_triple: String,
+
+    /// How libpython is linked, `static` or `shared`.
+    pub libpython_link_mode: String,
+
+    /// Optimizations the distribution was built with, e.g. `pgo+lto`.
+    pub optimizations: String,
+}
+
+/// Read the summary of an extracted Python distribution.
+pub fn read_distribution_summary(dist_dir: &Path) -> Result<DistributionSummary> {
+    let pi = parse_python_json_from_distribution(dist_dir)?;
+
+    Ok(DistributionSummary {
+        python_version: pi.python_version,
+        target_triple: pi.target_triple,
+        libpython_link_mode: pi.libpython_link_mode,
+        optimizations: pi.optimizations,
+    })
+}
+
 /// Resolve the path to a `python` executable in a Python distribution.
 pub fn python_exe_path(dist_dir: &Path) -> Result<PathBuf> {
     let pi = parse_python_json_from_distribution(dist_dir)?;
@@ -258,9 +302,12 @@ pub fn resolve_python_paths(base: &Path, python_version: &str) -> PythonPaths {
     let mut pyoxidizer_state_dir = p.clone();
     pyoxidizer_state_dir.extend(PYOXIDIZER_STATE_DIR.split('/'));
 
-    let unix_lib_dir = p
-        .join("lib")
-        .join(format!("python{}", &python_version[0..3]));
+    let stdlib_dir_name = match parse_major_minor(python_version) {
+        Some((major, minor)) => format!("python{}.{}", major, minor),
+        None => format!("python{}", python_version),
+    };
+
+    let unix_lib_dir = p.join("lib").join(stdlib_dir_name);
 
     let stdlib = if unix_lib_dir.exists() {
         unix_lib_dir
@@ -367,6 +414,9 @@ pub struct StandaloneDistribution {
     /// Python version string.
     pub version: String,
 
+    /// Properties of this distribution's Python version.
+    pub capabilities: &'static PythonVersionCapabilities,
+
     /// Path to Python interpreter executable.
     pub python_exe: PathBuf,
 
@@ -459,6 +509,92 @@ pub struct StandaloneDistribution {
     module_suffixes: PythonModuleSuffixes,
 }
 
+/// Extract the members of a distribution archive into an existing directory.
+#[allow(clippy::unnecessary_unwrap)]
+fn extract_archive<R: Read>(tf: &mut tar::Archive<R>, dest_dir: &Path) -> Result<()> {
+    let absolute_path = std::fs::canonicalize(dest_dir)?;
+
+    let mut symlinks = vec![];
+
+    for entry in tf.entries()? {
+        let mut entry = entry.map_err(|e| anyhow!("failed to iterate over archive: {}", e))?;
+
+        // Windows doesn't support symlinks without special permissions.
+        // So we track symlinks explicitly and copy files post extract if
+        // running on that platform.
+        let link_name = entry.link_name().unwrap_or(None);
+
+        if link_name.is_some() && cfg!(target_family = "windows") {
+            // The entry's path is the file to write, relative to the archive's
+            // root. We need to expand to an absolute path to facilitate copying.
+
+            // The link name is the file to symlink to, or the file we're copying.
+            // This path is relative to the entry path. So we need join with the
+            // entry's directory and canonicalize. There is also a security issue
+            // at play: archives could contain bogus symlinks pointing outside the
+            // archive. So we detect this, just in case.
+
+            let mut dest = absolute_path.clone();
+            dest.extend(entry.path()?.components());
+            let dest = dest
+                .parse_dot()
+                .with_context(|| "dedotting symlinked source")?
+                .to_path_buf();
+
+            let mut source = dest
+                .parent()
+                .ok_or_else(|| anyhow!("unable to resolve parent"))?
+                .to_path_buf();
+            source.extend(link_name.unwrap().components());
+            let source = source
+                .parse_dot()
+                .with_context(|| "dedotting symlink destination")?
+                .to_path_buf();
+
+            if !source.starts_with(&absolute_path) {
+                return Err(anyhow!("malicious symlink detected in archive"));
+            }
+
+            symlinks.push((source, dest));
+        } else {
+            entry
+                .unpack_in(&absolute_path)
+                .with_context(|| "unable to extract tar member")?;
+        }
+    }
+
+    for (source, dest) in symlinks {
+        std::fs::copy(&source, &dest).with_context(|| {
+            format!(
+                "copying symlinked file {} -> {}",
+                source.display(),
+                dest.display(),
+            )
+        })?;
+    }
+
+    // Ensure unpacked files are writable. We've had issues where we
+    // consume archives with read-only file permissions. When we later
+    // copy these files, we can run into trouble overwriting a read-only
+    // file.
+    let walk = walkdir::WalkDir::new(&absolute_path);
+    for entry in walk.into_iter() {
+        let entry = entry?;
+
+        let metadata = entry.metadata()?;
+        let mut permissions = metadata.permissions();
+
+        if permissions.readonly() {
+            permissions.set_readonly(false);
+            std::fs::set_permissions(entry.path(), permissions).with_context(|| {
+                format!("unable to mark {} as writable", entry.path().display())
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
 impl StandaloneDistribution {
     pub fn from_location(
         logger: &slog::Logger,
@@ -471,6 +607,38 @@ impl StandaloneDistribution {
         Self::from_tar_zst_file(logger, &archive_path, &extract_path)
     }
 
+    /// Create an instance from a location without extracting it to its extraction directory.
+    ///
+    /// If the distribution was already extracted, the extraction is used.
+    /// Otherwise it is extracted under `scratch_dir`.
+    pub fn from_location_without_extracting(
+        logger: &slog::Logger,
+        location: &PythonDistributionLocation,
+        distributions_dir: &Path,
+        scratch_dir: &Path,
+    ) -> Result<Self> {
+        let (archive_path, extract_path) =
+            resolve_python_distribution_from_location(logger, location, distributions_dir)?;
+
+        if extract_path.join("python").join("PYTHON.json").exists() {
+            return Self::from_directory(&extract_path);
+        }
+
+        let scratch_path = scratch_dir.join(
+            extract_path
+                .file_name()
+                .ok_or_else(|| anyhow!("unable to determine directory name"))?,
+        );
+        warn!(
+            logger,
+            "dry run: would extract Python distribution to {}; extracting to {} instead",
+            extract_path.display(),
+            scratch_path.display()
+        );
+
+        Self::from_tar_zst_file(logger, &archive_path, &scratch_path)
+    }
+
     /// Create an instance from a .tar.zst file.
     ///
     /// The distribution will be extracted to ``extract_dir`` if necessary.
@@ -494,109 +662,60 @@ impl StandaloneDistribution {
         let reader = BufReader::new(fh);
         warn!(logger, "reading data from Python distribution...");
 
-        Self::from_tar_zst(reader, &extract_dir)
+        Self::from_tar_zst(logger, reader, &extract_dir)
     }
 
     /// Extract and analyze a standalone distribution from a zstd compressed tar stream.
-    pub fn from_tar_zst<R: Read>(source: R, extract_dir: &Path) -> Result<Self> {
+    pub fn from_tar_zst<R: Read>(
+        logger: &slog::Logger,
+        source: R,
+        extract_dir: &Path,
+    ) -> Result<Self> {
         let dctx = zstd::stream::Decoder::new(source)?;
 
-        Self::from_tar(dctx, extract_dir)
+        Self::from_tar(logger, dctx, extract_dir)
     }
 
     /// Extract and analyze a standalone distribution from a tar stream.
-    #[allow(clippy::unnecessary_unwrap)]
-    pub fn from_tar<R: Read>(source: R, extract_dir: &Path) -> Result<Self> {
+    pub fn from_tar<R: Read>(logger: &slog::Logger, source: R, extract_dir: &Path) -> Result<Self> {
         let mut tf = tar::Archive::new(source);
 
         {
-            let _lock = DistributionExtractLock::new(extract_dir)?;
+            let _lock = DistributionExtractLock::new(logger, extract_dir)?;
 
             // The content of the distribution could change between runs. But caching
             // the extraction does keep things fast.
             let test_path = extract_dir.join("python").join("PYTHON.json");
             if !test_path.exists() {
-                std::fs::create_dir_all(extract_dir)?;
-                let absolute_path = std::fs::canonicalize(extract_dir)?;
-
-                let mut symlinks = vec![];
-
-                for entry in tf.entries()? {
-                    let mut entry =
-                        entry.map_err(|e| anyhow!("failed to iterate over archive: {}", e))?;
-
-                    // Windows doesn't support symlinks without special permissions.
-                    // So we track symlinks explicitly and copy files post extract if
-                    // running on that platform.
-                    let link_name = entry.link_name().unwrap_or(None);
-
-                    if link_name.is_some() && cfg!(target_family = "windows") {
-                        // The entry's path is the file to write, relative to the archive's
-                        // root. We need to expand to an absolute path to facilitate copying.
-
-                        // The link name is the file to symlink to, or the file we're copying.
-                        // This path is relative to the entry path. So we need join with the
-                        // entry's directory and canonicalize. There is also a security issue
-                        // at play: archives could contain bogus symlinks pointing outside the
-                        // archive. So we detect this, just in case.
-
-                        let mut dest = absolute_path.clone();
-                        dest.extend(entry.path()?.components());
-                        let dest = dest
-                            .parse_dot()
-                            .with_context(|| "dedotting symlinked source")?
-                            .to_path_buf();
-
-                        let mut source = dest
-                            .parent()
-                            .ok_or_else(|| anyhow!("unable to resolve parent"))?
-                            .to_path_buf();
-                        source.extend(link_name.unwrap().components());
-                        let source = source
-                            .parse_dot()
-                            .with_context(|| "dedotting symlink destination")?
-                            .to_path_buf();
-
-                        if !source.starts_with(&absolute_path) {
-                            return Err(anyhow!("malicious symlink detected in archive"));
-                        }
-
-                        symlinks.push((source, dest));
-                    } else {
-                        entry
-                            .unpack_in(&absolute_path)
-                            .with_context(|| "unable to extract tar member")?;
-                    }
-                }
-
-                for (source, dest) in symlinks {
-                    std::fs::copy(&source, &dest).with_context(|| {
+                check_disk_space(
+                    extract_dir,
+                    DISTRIBUTION_REQUIRED_SPACE,
+                    "extracting the Python distribution",
+                )?;
+
+                if extract_dir.exists() {
+                    extract_archive(&mut tf, extract_dir)?;
+                } else {
+                    // Extract next to the destination and rename into place,
+                    // so an interrupted extraction never leaves a partial
+                    // distribution behind for later builds to pick up.
+                    let parent = extract_dir
+                        .parent()
+                        .ok_or_else(|| anyhow!("unable to determine parent directory"))?;
+                    std::fs::create_dir_all(parent)?;
+
+                    let staging = tempdir::TempDir::new_in(parent, "extract")?;
+                    extract_archive(&mut tf, staging.path())?;
+
+                    let staging = staging.into_path();
+                    std::fs::rename(&staging, extract_dir).with_context(|| {
                         format!(
-                            "copying symlinked file {} -> {}",
-                            source.display(),
-                            dest.display(),
+                            "renaming {} to {}",
+                            staging.display(),
+                            extract_dir.display()
                         )
                     })?;
                 }
-
-                // Ensure unpacked files are writable. We've had issues where we
-                // consume archives with read-only file permissions. When we later
-                // copy these files, we can run into trouble overwriting a read-only
-                // file.
-                let walk = walkdir::WalkDir::new(&absolute_path);
-                for entry in walk.into_iter() {
-                    let entry = entry?;
-
-                    let metadata = entry.metadata()?;
-                    let mut permissions = metadata.permissions();
-
-                    if permissions.readonly() {
-                        permissions.set_readonly(false);
-                        std::fs::set_permissions(entry.path(), permissions).with_context(|| {
-                            format!("unable to mark {} as writable", entry.path().display())
-                        })?;
-                    }
-                }
             }
         }
 
@@ -658,6 +777,14 @@ impl StandaloneDistribution {
 
         let pi = parse_python_json_from_distribution(dist_dir)?;
 
+        let capabilities = resolve_python_version(&pi.python_version)?;
+
+        if pi.python_config_vars.get("Py_GIL_DISABLED") == Some(&"1".to_string()) {
+            return Err(anyhow!(
+                "free-threaded Python distributions are not supported"
+            ));
+        }
+
         if let Some(ref python_license_path) = pi.license_path {
             let license_path = python_path.join(python_license_path);
             let license_text = std::fs::read_to_string(&license_path).with_context(|| {
@@ -867,6 +994,7 @@ impl StandaloneDistribution {
                 PythonResource::EggFile(_) => {}
                 PythonResource::PathExtension(_) => {}
                 PythonResource::File(_) => {}
+                PythonResource::GettextCatalog(_) => {}
             };
         }
 
@@ -893,6 +1021,7 @@ impl StandaloneDistribution {
             python_abi_tag: pi.python_abi_tag,
             python_platform_tag: pi.python_platform_tag,
             version: pi.python_version.clone(),
+            capabilities,
             python_exe: python_exe_path(dist_dir)?,
             stdlib_path,
             stdlib_test_packages: pi.python_stdlib_test_packages,
@@ -1095,12 +1224,20 @@ impl PythonDistribution for StandaloneDistribution {
         &self.python_exe
     }
 
+    fn python_install_dir(&self) -> PathBuf {
+        self.base_dir.join("python").join("install")
+    }
+
     fn python_version(&self) -> &str {
         &self.version
     }
 
     fn python_major_minor_version(&self) -> String {
-        self.version[0..3].to_string()
+        self.capabilities.major_minor()
+    }
+
+    fn python_capabilities(&self) -> &'static PythonVersionCapabilities {
+        self.capabilities
     }
 
     fn python_implementation(&self) -> &str {
@@ -1169,7 +1306,7 @@ impl PythonDistribution for StandaloneDistribution {
     }
 
     fn create_bytecode_compiler(&self) -> Result<Box<dyn PythonBytecodeCompiler>> {
-        Ok(Box::new(BytecodeCompiler::new(&self.python_exe)?))
+        CachedBytecodeCompiler::wrap_default(Box::new(BytecodeCompiler::new(&self.python_exe)?))
     }
 
     fn create_packaging_policy(&self) -> Result<PythonPackagingPolicy> {
@@ -1292,6 +1429,9 @@ impl PythonDistribution for StandaloneDistribution {
 
         let pip_path = python_paths.bin_dir.join(PIP_EXE_BASENAME);
 
+        // Extracted distributions are shared by concurrent builds.
+        let _lock = DistributionExtractLock::new(logger, &self.base_dir)?;
+
         if !pip_path.exists() {
             warn!(logger, "{} doesnt exist", pip_path.display().to_string());
             invoke_python(&python_paths, &logger, &["-m", "ensurepip"]);
@@ -0,0 +1,131 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Functionality for bundling a `terminfo` database.
+*/
+
+use {
+    anyhow::Result,
+    lazy_static::lazy_static,
+    std::path::{Path, PathBuf},
+};
+
+lazy_static! {
+    /// Directories on the build host to look for a `terminfo` database in.
+    pub static ref HOST_TERMINFO_DIRS: Vec<PathBuf> = vec![
+        PathBuf::from("/etc/terminfo"),
+        PathBuf::from("/lib/terminfo"),
+        PathBuf::from("/usr/share/terminfo"),
+        PathBuf::from("/usr/lib/terminfo"),
+        PathBuf::from("/usr/share/misc/terminfo"),
+    ];
+}
+
+/// Terminal entries constituting a minimal `terminfo` database.
+///
+/// This covers the terminals and terminal emulators commonly encountered.
+pub const MINIMAL_TERMINFO_ENTRIES: &[&str] = &[
+    "dumb",
+    "linux",
+    "rxvt",
+    "rxvt-unicode",
+    "rxvt-unicode-256color",
+    "screen",
+    "screen-256color",
+    "tmux",
+    "tmux-256color",
+    "vt100",
+    "vt220",
+    "xterm",
+    "xterm-256color",
+    "xterm-color",
+];
+
+/// Resolve the path of a `terminfo` entry relative to a database directory.
+///
+/// Entries are stored in a directory named after the first character of the
+/// entry name. Some systems (notably macOS) use the hex value of that
+/// character instead. Both layouts are probed.
+fn find_entry(dir: &Path, entry: &str) -> Option<PathBuf> {
+    let first = entry.chars().next()?;
+
+    for prefix in &[first.to_string(), format!("{:02x}", first as u32)] {
+        let rel_path = PathBuf::from(prefix).join(entry);
+
+        if dir.join(&rel_path).is_file() {
+            return Some(rel_path);
+        }
+    }
+
+    None
+}
+
+/// Resolve files constituting a minimal `terminfo` database.
+///
+/// Entries in `MINIMAL_TERMINFO_ENTRIES` are searched for in `dirs`, in order.
+/// The first found instance of each entry is used.
+///
+/// Returns tuples of (path relative to the database root, filesystem path).
+/// Entries that can't be found are silently ignored.
+pub fn resolve_minimal_terminfo_files(dirs: &[PathBuf]) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let mut res = vec![];
+
+    for entry in MINIMAL_TERMINFO_ENTRIES {
+        for dir in dirs {
+            if let Some(rel_path) = find_entry(dir, entry) {
+                let fs_path = dir.join(&rel_path).canonicalize()?;
+                res.push((rel_path, fs_path));
+                break;
+            }
+        }
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_minimal_terminfo_files() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+
+        let first = temp_dir.path().join("first");
+        let second = temp_dir.path().join("second");
+
+        std::fs::create_dir_all(first.join("x"))?;
+        std::fs::create_dir_all(second.join("78"))?;
+        std::fs::create_dir_all(second.join("v"))?;
+
+        std::fs::write(first.join("x").join("xterm"), b"first")?;
+        std::fs::write(second.join("78").join("xterm"), b"second")?;
+        std::fs::write(second.join("78").join("xterm-256color"), b"second")?;
+        std::fs::write(second.join("v").join("vt100"), b"second")?;
+        std::fs::write(second.join("v").join("unknown"), b"second")?;
+
+        let files = resolve_minimal_terminfo_files(&[first.clone(), second.clone()])?;
+
+        assert_eq!(
+            files,
+            vec![
+                (
+                    PathBuf::from("v").join("vt100"),
+                    second.join("v").join("vt100").canonicalize()?
+                ),
+                (
+                    PathBuf::from("x").join("xterm"),
+                    first.join("x").join("xterm").canonicalize()?
+                ),
+                (
+                    PathBuf::from("78").join("xterm-256color"),
+                    second.join("78").join("xterm-256color").canonicalize()?
+                ),
+            ]
+        );
+
+        Ok(())
+    }
+}
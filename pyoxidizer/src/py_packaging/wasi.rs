@@ -0,0 +1,441 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Bundling Python resources with a WebAssembly System Interface (WASI) interpreter.
+
+A WASI bundle pairs the resources collected for a binary with a `wasm32-wasi`
+build of CPython. The result is a directory that can be run in a sandbox by a
+WASI runtime such as wasmtime.
+
+WASI interpreters cannot load native code, so bundles are limited to pure
+Python resources. And since `oxidized_importer` is not available to a WASI
+interpreter, resources are materialized as files which the standard
+importer can load.
+*/
+
+use {
+    super::binary::PythonBinaryBuilder,
+    crate::paths::write_file_manifest,
+    anyhow::{anyhow, Context, Result},
+    python_packaging::{
+        interpreter::PythonInterpreterConfig,
+        module_util::resolve_path_for_module,
+        python_version::{resolve_python_version, PythonVersionCapabilities},
+    },
+    python_packed_resources::data::Resource,
+    slog::warn,
+    std::{
+        collections::BTreeSet,
+        path::{Path, PathBuf},
+    },
+    tugger::file_resource::{FileContent, FileManifest},
+};
+
+/// Rust target triple of WASI interpreters.
+pub const WASI_TARGET_TRIPLE: &str = "wasm32-wasi";
+
+/// Filename of the WebAssembly module holding the Python interpreter.
+pub const WASI_PYTHON_FILENAME: &str = "python.wasm";
+
+/// Directory in a bundle holding resources materialized from memory.
+pub const WASI_APP_DIR: &str = "app";
+
+/// Filename of the script used to run a bundle.
+pub const WASI_LAUNCHER_FILENAME: &str = "run.sh";
+
+/// Describes a `wasm32-wasi` build of CPython.
+#[derive(Clone, Debug)]
+pub struct WasiPythonInterpreter {
+    /// Path to the WebAssembly module holding the interpreter.
+    pub wasm_path: PathBuf,
+
+    /// Path to the directory holding the standard library.
+    ///
+    /// e.g. `lib/python3.9`.
+    pub stdlib_path: PathBuf,
+
+    /// Capabilities of the interpreter's Python version.
+    pub capabilities: &'static PythonVersionCapabilities,
+}
+
+impl WasiPythonInterpreter {
+    /// Resolve a WASI interpreter from a directory.
+    ///
+    /// The directory must contain a `python.wasm` file and the standard
+    /// library in a `lib/pythonX.Y` directory.
+    pub fn from_directory(path: &Path) -> Result<Self> {
+        let wasm_path = path.join(WASI_PYTHON_FILENAME);
+        if !wasm_path.is_file() {
+            return Err(anyhow!(
+                "{} does not contain {}",
+                path.display(),
+                WASI_PYTHON_FILENAME
+            ));
+        }
+
+        let lib_path = path.join("lib");
+        let mut candidates = Vec::new();
+
+        for entry in std::fs::read_dir(&lib_path)
+            .with_context(|| format!("reading {}", lib_path.display()))?
+        {
+            let entry = entry?;
+
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+
+            let name = entry.file_name().to_string_lossy().to_string();
+
+            if let Some(version) = name.strip_prefix("python") {
+                candidates.push((entry.path(), version.to_string()));
+            }
+        }
+
+        if candidates.len() != 1 {
+            return Err(anyhow!(
+                "expected exactly 1 pythonX.Y directory in {}; found {}",
+                lib_path.display(),
+                candidates.len()
+            ));
+        }
+
+        let (stdlib_path, version) = candidates.remove(0);
+
+        Ok(Self {
+            wasm_path,
+            stdlib_path,
+            capabilities: resolve_python_version(&version)?,
+        })
+    }
+
+    /// Whether the interpreter's standard library provides a module.
+    pub fn stdlib_has_module(&self, name: &str, is_package: bool) -> bool {
+        self.stdlib_path
+            .join(resolve_path_for_module("", name, is_package, None))
+            .exists()
+    }
+}
+
+/// Obtain interpreter arguments defining what code a bundle runs.
+///
+/// Mirrors the precedence CPython applies to `PyConfig`: a command, then a
+/// module, then a file. No arguments means the interpreter starts a REPL.
+pub fn wasi_run_args(config: &PythonInterpreterConfig) -> Vec<String> {
+    if let Some(command) = &config.run_command {
+        vec!["-c".to_string(), command.clone()]
+    } else if let Some(module) = &config.run_module {
+        vec!["-m".to_string(), module.clone()]
+    } else if let Some(filename) = &config.run_filename {
+        vec![filename.display().to_string()]
+    } else {
+        vec![]
+    }
+}
+
+/// Quote a string for use in a POSIX shell script.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Generate the shell script used to run a bundle with wasmtime.
+///
+/// The bundle directory is mapped to `/` in the guest.
+pub fn wasi_launcher_script(python_paths: &[String], args: &[String]) -> String {
+    let python_path = python_paths
+        .iter()
+        .map(|p| format!("/{}", p))
+        .collect::<Vec<_>>()
+        .join(":");
+
+    let mut command = vec![
+        "exec \"${WASMTIME:-wasmtime}\" run".to_string(),
+        "--dir \"$HERE::/\"".to_string(),
+        "--env PYTHONHOME=/".to_string(),
+        format!("--env PYTHONPATH={}", shell_quote(&python_path)),
+        format!("\"$HERE/{}\"", WASI_PYTHON_FILENAME),
+    ];
+    command.extend(args.iter().map(|a| shell_quote(a)));
+    command.push("\"$@\"".to_string());
+
+    format!(
+        "#!/bin/sh\n\
+        # Runs this bundle with wasmtime. Set WASMTIME to use a different wasmtime binary.\n\
+        HERE=\"$(cd \"$(dirname \"$0\")\" && pwd)\"\n\
+        {}\n",
+        command.join(" \\\n  ")
+    )
+}
+
+/// Materialize the in-memory data of a resource as files under `root`.
+fn materialize_resource(
+    resource: &Resource<u8>,
+    root: &str,
+    manifest: &mut FileManifest,
+) -> Result<()> {
+    if resource.is_module {
+        if let Some(source) = &resource.in_memory_source {
+            manifest.add_file(
+                resolve_path_for_module(root, &resource.name, resource.is_package, None),
+                &FileContent {
+                    data: source.to_vec(),
+                    executable: false,
+                },
+            )?;
+        } else if resource.in_memory_bytecode.is_some() {
+            return Err(anyhow!(
+                "module {} has no source code; WASI bundles require module source",
+                resource.name
+            ));
+        }
+
+        if let Some(resources) = &resource.in_memory_package_resources {
+            let package_path = resource
+                .name
+                .split('.')
+                .fold(PathBuf::from(root), |path, part| path.join(part));
+
+            for (name, data) in resources {
+                manifest.add_file(
+                    package_path.join(name.as_ref()),
+                    &FileContent {
+                        data: data.to_vec(),
+                        executable: false,
+                    },
+                )?;
+            }
+        }
+    }
+
+    if resource.is_utf8_filename_data {
+        if let Some(data) = &resource.file_data_embedded {
+            manifest.add_file(
+                Path::new(resource.name.as_ref()),
+                &FileContent {
+                    data: data.to_vec(),
+                    executable: resource.file_executable,
+                },
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Write a WASI bundle for resources collected by a binary builder.
+///
+/// Returns the path to the script that runs the bundle.
+pub fn write_wasi_bundle(
+    logger: &slog::Logger,
+    exe: &dyn PythonBinaryBuilder,
+    interpreter: &WasiPythonInterpreter,
+    dest_dir: &Path,
+) -> Result<PathBuf> {
+    if exe.cache_tag() != interpreter.capabilities.cache_tag() {
+        return Err(anyhow!(
+            "WASI interpreter is Python {} but resources were collected for {}",
+            interpreter.capabilities.major_minor(),
+            exe.cache_tag()
+        ));
+    }
+
+    let mut native = Vec::new();
+    let mut python_paths = BTreeSet::new();
+    python_paths.insert(WASI_APP_DIR.to_string());
+
+    for (name, resource) in exe.iter_resources() {
+        if (resource.is_extension_module && !resource.is_builtin_extension_module)
+            || resource.is_shared_library
+        {
+            native.push(name.clone());
+        }
+
+        if let Some((prefix, _)) = &resource.relative_path_module_source {
+            python_paths.insert(prefix.clone());
+        }
+        if let Some((prefix, _, _)) = &resource.relative_path_bytecode {
+            python_paths.insert(prefix.clone());
+        }
+    }
+
+    if !native.is_empty() {
+        return Err(anyhow!(
+            "WASI bundles cannot contain native code; remove the following extension modules and shared libraries: {}",
+            native.join(", ")
+        ));
+    }
+
+    let compiled = exe.compile_resources()?;
+
+    let mut manifest = FileManifest::default();
+
+    manifest.add_file(
+        Path::new(WASI_PYTHON_FILENAME),
+        &FileContent {
+            data: std::fs::read(&interpreter.wasm_path)
+                .with_context(|| format!("reading {}", interpreter.wasm_path.display()))?,
+            executable: false,
+        },
+    )?;
+
+    for entry in walkdir::WalkDir::new(&interpreter.stdlib_path) {
+        let entry = entry?;
+
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let rel_path = entry.path().strip_prefix(&interpreter.stdlib_path)?;
+
+        manifest.add_file(
+            Path::new("lib")
+                .join(interpreter.capabilities.stdlib_dir_name())
+                .join(rel_path),
+            &FileContent {
+                data: std::fs::read(entry.path())?,
+                executable: false,
+            },
+        )?;
+    }
+
+    for (path, location, executable) in &compiled.extra_files {
+        manifest.add_file(
+            path,
+            &FileContent {
+                data: location.resolve()?,
+                executable: *executable,
+            },
+        )?;
+    }
+
+    let mut stdlib_count = 0;
+
+    for resource in compiled.resources.values() {
+        if resource.is_module && interpreter.stdlib_has_module(&resource.name, resource.is_package)
+        {
+            stdlib_count += 1;
+            continue;
+        }
+
+        materialize_resource(resource, WASI_APP_DIR, &mut manifest)?;
+    }
+
+    if stdlib_count > 0 {
+        warn!(
+            logger,
+            "{} modules are provided by the WASI interpreter's standard library and were not copied",
+            stdlib_count
+        );
+    }
+
+    let python_paths = python_paths.into_iter().collect::<Vec<_>>();
+    let args = wasi_run_args(&exe.embedded_python_config().config);
+
+    manifest.add_file(
+        Path::new(WASI_LAUNCHER_FILENAME),
+        &FileContent {
+            data: wasi_launcher_script(&python_paths, &args).into_bytes(),
+            executable: true,
+        },
+    )?;
+
+    warn!(
+        logger,
+        "writing {} bundle to {}",
+        WASI_TARGET_TRIPLE,
+        dest_dir.display()
+    );
+    write_file_manifest(&manifest, dest_dir)?;
+
+    Ok(dest_dir.join(WASI_LAUNCHER_FILENAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::borrow::Cow};
+
+    #[test]
+    fn test_wasi_run_args() {
+        let mut config = PythonInterpreterConfig::default();
+        assert!(wasi_run_args(&config).is_empty());
+
+        config.run_filename = Some(PathBuf::from("main.py"));
+        assert_eq!(wasi_run_args(&config), vec!["main.py"]);
+
+        config.run_module = Some("foo".to_string());
+        assert_eq!(wasi_run_args(&config), vec!["-m", "foo"]);
+
+        config.run_command = Some("import foo".to_string());
+        assert_eq!(wasi_run_args(&config), vec!["-c", "import foo"]);
+    }
+
+    #[test]
+    fn test_wasi_launcher_script() {
+        let script = wasi_launcher_script(
+            &["app".to_string(), "lib".to_string()],
+            &["-c".to_string(), "print('hi')".to_string()],
+        );
+
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains("--env PYTHONPATH='/app:/lib'"));
+        assert!(script.contains("\"$HERE/python.wasm\" \\\n  '-c' \\\n  'print('\\''hi'\\'')'"));
+        assert!(script.ends_with("\"$@\"\n"));
+    }
+
+    #[test]
+    fn test_wasi_interpreter_from_directory() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let root = temp_dir.path();
+
+        assert!(WasiPythonInterpreter::from_directory(root).is_err());
+
+        std::fs::write(root.join(WASI_PYTHON_FILENAME), b"")?;
+        std::fs::create_dir_all(root.join("lib").join("python3.9").join("json"))?;
+        std::fs::write(
+            root.join("lib")
+                .join("python3.9")
+                .join("json")
+                .join("__init__.py"),
+            b"",
+        )?;
+
+        let interpreter = WasiPythonInterpreter::from_directory(root)?;
+        assert_eq!(interpreter.capabilities.major_minor(), "3.9");
+        assert!(interpreter.stdlib_has_module("json", true));
+        assert!(!interpreter.stdlib_has_module("foo", false));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_materialize_resource() -> Result<()> {
+        let mut resource = Resource {
+            name: Cow::Borrowed("foo.bar"),
+            is_module: true,
+            is_package: true,
+            in_memory_source: Some(Cow::Owned(b"import os".to_vec())),
+            in_memory_package_resources: Some(
+                vec![(Cow::Borrowed("data.txt"), Cow::Owned(b"data".to_vec()))]
+                    .into_iter()
+                    .collect(),
+            ),
+            ..Resource::default()
+        };
+
+        let mut manifest = FileManifest::default();
+        materialize_resource(&resource, WASI_APP_DIR, &mut manifest)?;
+
+        assert!(manifest.has_path(Path::new("app/foo/bar/__init__.py")));
+        assert!(manifest.has_path(Path::new("app/foo/bar/data.txt")));
+
+        resource.in_memory_source = None;
+        resource.in_memory_bytecode = Some(Cow::Owned(vec![]));
+        assert!(
+            materialize_resource(&resource, WASI_APP_DIR, &mut FileManifest::default()).is_err()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,382 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Windows C runtime requirements of built binaries.
+
+Binaries built with MSVC import the Visual C++ runtime (`vcruntime140.dll`,
+`msvcp140.dll`, etc) and the Universal C Runtime (UCRT). The UCRT is a
+component of Windows 10 and is installed by Windows Update on older
+versions. The Visual C++ runtime is not part of Windows: Microsoft allows
+installing its DLLs next to the application or installing the Visual C++
+Redistributable system-wide.
+*/
+
+use {
+    crate::analyze::find_pe_dependencies,
+    anyhow::{anyhow, Result},
+    slog::{info, warn},
+    std::{
+        collections::BTreeSet,
+        convert::TryFrom,
+        path::{Path, PathBuf},
+    },
+    tugger::{
+        file_resource::{FileContent, FileManifest},
+        vc_redist::{
+            find_visual_cpp_redist_dir, visual_cpp_redistributable_dlls, VcRedistPlatform,
+        },
+    },
+};
+
+/// Filename prefixes of DLLs provided by the Visual C++ Redistributable.
+const VC_RUNTIME_DLL_PREFIXES: &[&str] = &[
+    "concrt140",
+    "mfc140",
+    "msvcp140",
+    "vccorlib140",
+    "vcomp140",
+    "vcruntime140",
+];
+
+/// Whether a DLL is provided by the Visual C++ Redistributable.
+pub(crate) fn is_vc_runtime_dll(name: &str) -> bool {
+    let name = name.to_lowercase();
+
+    name.ends_with(".dll")
+        && VC_RUNTIME_DLL_PREFIXES
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+}
+
+/// Whether a DLL is provided by the Universal C Runtime.
+pub(crate) fn is_ucrt_dll(name: &str) -> bool {
+    let name = name.to_lowercase();
+
+    name.starts_with("api-ms-win-crt-") || name == "ucrtbase.dll"
+}
+
+/// Whether to install Visual C++ runtime DLLs next to built binaries.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WindowsRuntimeDllsMode {
+    /// Never install the DLLs. They must be installed on the system.
+    Never,
+
+    /// Install the DLLs if they can be found on the build machine.
+    WhenPresent,
+
+    /// Always install the DLLs. Building fails if they cannot be found.
+    Always,
+}
+
+impl Default for WindowsRuntimeDllsMode {
+    fn default() -> Self {
+        Self::WhenPresent
+    }
+}
+
+impl ToString for WindowsRuntimeDllsMode {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Never => "never",
+            Self::WhenPresent => "when-present",
+            Self::Always => "always",
+        }
+        .to_string()
+    }
+}
+
+impl TryFrom<&str> for WindowsRuntimeDllsMode {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "never" => Ok(Self::Never),
+            "when-present" => Ok(Self::WhenPresent),
+            "always" => Ok(Self::Always),
+            _ => Err(format!(
+                "{} is not a valid Windows runtime DLLs mode; use never, when-present, or always",
+                value
+            )),
+        }
+    }
+}
+
+/// C runtime DLLs required by a set of binaries.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WindowsRuntimeRequirements {
+    /// Visual C++ runtime DLLs, normalized to lowercase.
+    pub vc_runtime_dlls: BTreeSet<String>,
+
+    /// Whether the Universal C Runtime is required.
+    pub ucrt: bool,
+}
+
+impl WindowsRuntimeRequirements {
+    /// Record the requirements of DLL imports.
+    pub fn add_imports<S: AsRef<str>>(&mut self, names: impl Iterator<Item = S>) {
+        for name in names {
+            let name = name.as_ref();
+
+            if is_vc_runtime_dll(name) {
+                self.vc_runtime_dlls.insert(name.to_lowercase());
+            } else if is_ucrt_dll(name) {
+                self.ucrt = true;
+            }
+        }
+    }
+
+    /// Record the requirements of a PE binary.
+    pub fn add_binary(&mut self, data: &[u8]) -> Result<()> {
+        self.add_imports(find_pe_dependencies(data)?.iter());
+
+        Ok(())
+    }
+
+    /// Record the requirements of DLLs and extension modules in a manifest.
+    pub fn add_manifest(&mut self, manifest: &FileManifest) -> Result<()> {
+        for (path, content) in manifest.entries() {
+            let is_library = path
+                .extension()
+                .map(|ext| ext.eq_ignore_ascii_case("dll") || ext.eq_ignore_ascii_case("pyd"))
+                .unwrap_or(false);
+
+            if is_library {
+                self.add_binary(&content.data)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Resolve the files providing the Visual C++ runtime DLLs in `requirements`.
+///
+/// Files are taken from the Visual C++ redistributable directory at
+/// `redist_dir`, or from a Visual Studio installation if not given. The
+/// returned manifest installs them next to the binary.
+pub fn resolve_windows_runtime_dlls(
+    logger: &slog::Logger,
+    mode: WindowsRuntimeDllsMode,
+    target_triple: &str,
+    requirements: &WindowsRuntimeRequirements,
+    redist_dir: Option<&Path>,
+) -> Result<FileManifest> {
+    let mut manifest = FileManifest::default();
+
+    if requirements.ucrt {
+        info!(
+            logger,
+            "binary requires the Universal C Runtime, which is part of Windows 10 and newer"
+        );
+    }
+
+    if requirements.vc_runtime_dlls.is_empty() {
+        return Ok(manifest);
+    }
+
+    let required = requirements
+        .vc_runtime_dlls
+        .iter()
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    if mode == WindowsRuntimeDllsMode::Never {
+        warn!(
+            logger,
+            "binary requires {}; not installing them because windows_runtime_dlls_mode is never",
+            required
+        );
+        warn!(
+            logger,
+            "the Visual C++ Redistributable must be installed on machines running the binary"
+        );
+        return Ok(manifest);
+    }
+
+    let resolve = || -> Result<Vec<PathBuf>> {
+        let platform = VcRedistPlatform::from_target_triple(target_triple)
+            .ok_or_else(|| anyhow!("no Visual C++ runtime files for {}", target_triple))?;
+
+        let redist_dir = match redist_dir {
+            Some(path) => path.to_path_buf(),
+            None => find_visual_cpp_redist_dir()?,
+        };
+
+        let files = visual_cpp_redistributable_dlls(&redist_dir, platform)?;
+
+        requirements
+            .vc_runtime_dlls
+            .iter()
+            .map(|name| {
+                files
+                    .iter()
+                    .find(|path| {
+                        path.file_name()
+                            .map(|f| f.to_string_lossy().to_lowercase() == *name)
+                            .unwrap_or(false)
+                    })
+                    .cloned()
+                    .ok_or_else(|| anyhow!("{} not found in {}", name, redist_dir.display()))
+            })
+            .collect()
+    };
+
+    let paths = match resolve() {
+        Ok(paths) => paths,
+        Err(e) if mode == WindowsRuntimeDllsMode::WhenPresent => {
+            warn!(
+                logger,
+                "binary requires {} but they could not be located: {}", required, e
+            );
+            warn!(
+                logger,
+                "the Visual C++ Redistributable must be installed on machines running the binary"
+            );
+            return Ok(manifest);
+        }
+        Err(e) => {
+            return Err(anyhow!(
+                "unable to locate Visual C++ runtime DLLs ({}): {}",
+                required,
+                e
+            ));
+        }
+    };
+
+    for path in paths {
+        warn!(
+            logger,
+            "installing Visual C++ runtime DLL {}",
+            path.display()
+        );
+
+        manifest.add_file(
+            Path::new(path.file_name().unwrap()),
+            &FileContent {
+                data: std::fs::read(&path)?,
+                executable: true,
+            },
+        )?;
+    }
+
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::testutil::get_logger};
+
+    #[test]
+    fn test_requirements() {
+        let mut requirements = WindowsRuntimeRequirements::default();
+        requirements.add_imports(
+            [
+                "KERNEL32.dll",
+                "VCRUNTIME140.dll",
+                "api-ms-win-crt-runtime-l1-1-0.dll",
+                "python39.dll",
+            ]
+            .iter(),
+        );
+        requirements.add_imports(["MSVCP140.dll", "vcruntime140_1.dll"].iter());
+
+        assert!(requirements.ucrt);
+        assert_eq!(
+            requirements.vc_runtime_dlls.iter().collect::<Vec<_>>(),
+            vec!["msvcp140.dll", "vcruntime140.dll", "vcruntime140_1.dll"]
+        );
+
+        let mut requirements = WindowsRuntimeRequirements::default();
+        requirements.add_imports(["KERNEL32.dll", "libcrypto-1_1.dll"].iter());
+        assert_eq!(requirements, WindowsRuntimeRequirements::default());
+    }
+
+    #[test]
+    fn test_mode() {
+        for mode in &["never", "when-present", "always"] {
+            assert_eq!(
+                WindowsRuntimeDllsMode::try_from(*mode).unwrap().to_string(),
+                *mode
+            );
+        }
+        assert!(WindowsRuntimeDllsMode::try_from("sometimes").is_err());
+    }
+
+    #[test]
+    fn test_resolve() -> Result<()> {
+        let logger = get_logger()?;
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let redist_dir = temp_dir.path();
+
+        let crt = redist_dir.join("x64").join("Microsoft.VC142.CRT");
+        std::fs::create_dir_all(&crt)?;
+        std::fs::write(crt.join("vcruntime140.dll"), b"vcruntime")?;
+        std::fs::write(crt.join("vcruntime140_1.dll"), b"vcruntime_1")?;
+        std::fs::write(crt.join("msvcp140.dll"), b"msvcp")?;
+
+        let mut requirements = WindowsRuntimeRequirements::default();
+        requirements.add_imports(["VCRUNTIME140.dll", "VCRUNTIME140_1.dll"].iter());
+
+        let manifest = resolve_windows_runtime_dlls(
+            &logger,
+            WindowsRuntimeDllsMode::WhenPresent,
+            "x86_64-pc-windows-msvc",
+            &requirements,
+            Some(redist_dir),
+        )?;
+        assert_eq!(
+            manifest
+                .entries()
+                .map(|(path, content)| (path.to_path_buf(), content.data.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (PathBuf::from("vcruntime140.dll"), b"vcruntime".to_vec()),
+                (PathBuf::from("vcruntime140_1.dll"), b"vcruntime_1".to_vec()),
+            ]
+        );
+
+        let manifest = resolve_windows_runtime_dlls(
+            &logger,
+            WindowsRuntimeDllsMode::Never,
+            "x86_64-pc-windows-msvc",
+            &requirements,
+            Some(redist_dir),
+        )?;
+        assert_eq!(manifest.entries().count(), 0);
+
+        // There are no x86 files.
+        for mode in &[
+            WindowsRuntimeDllsMode::WhenPresent,
+            WindowsRuntimeDllsMode::Always,
+        ] {
+            let res = resolve_windows_runtime_dlls(
+                &logger,
+                *mode,
+                "i686-pc-windows-msvc",
+                &requirements,
+                Some(redist_dir),
+            );
+
+            if *mode == WindowsRuntimeDllsMode::Always {
+                assert!(res.is_err());
+            } else {
+                assert_eq!(res?.entries().count(), 0);
+            }
+        }
+
+        requirements.add_imports(["vcomp140.dll"].iter());
+        assert!(resolve_windows_runtime_dlls(
+            &logger,
+            WindowsRuntimeDllsMode::Always,
+            "x86_64-pc-windows-msvc",
+            &requirements,
+            Some(redist_dir),
+        )
+        .is_err());
+
+        Ok(())
+    }
+}
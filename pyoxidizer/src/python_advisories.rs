@@ -0,0 +1,191 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Defines known security vulnerabilities in CPython.
+
+use {
+    anyhow::{anyhow, Result},
+    python_packaging::{policy::PythonSecurityAction, python_version::parse_major_minor},
+    slog::warn,
+};
+
+/// Describes a security vulnerability in CPython.
+pub struct PythonAdvisory {
+    /// Identifier of the vulnerability. e.g. `CVE-2021-3177`.
+    pub id: &'static str,
+
+    /// `YYYY-MM-DD` date the vulnerability was published.
+    pub published: &'static str,
+
+    /// First `X.Y.Z` version of each release series containing a fix.
+    ///
+    /// Release series not listed are not considered affected.
+    pub fixed: &'static [&'static str],
+}
+
+/// Known vulnerabilities in CPython.
+///
+/// This list is not exhaustive. It records notable vulnerabilities affecting
+/// the Python versions PyOxidizer supports.
+pub const PYTHON_ADVISORIES: &[PythonAdvisory] = &[
+    PythonAdvisory {
+        id: "CVE-2021-3177",
+        published: "2021-01-19",
+        fixed: &["3.8.8", "3.9.2"],
+    },
+    PythonAdvisory {
+        id: "CVE-2021-23336",
+        published: "2021-02-15",
+        fixed: &["3.8.8", "3.9.2"],
+    },
+    PythonAdvisory {
+        id: "CVE-2022-45061",
+        published: "2022-11-09",
+        fixed: &["3.8.16", "3.9.16", "3.10.9", "3.11.1"],
+    },
+    PythonAdvisory {
+        id: "CVE-2023-40217",
+        published: "2023-08-25",
+        fixed: &["3.8.18", "3.9.18", "3.10.13", "3.11.5"],
+    },
+    PythonAdvisory {
+        id: "CVE-2024-0450",
+        published: "2024-03-19",
+        fixed: &["3.8.19", "3.9.19", "3.10.14", "3.11.8", "3.12.2"],
+    },
+    PythonAdvisory {
+        id: "CVE-2024-6923",
+        published: "2024-08-01",
+        fixed: &["3.8.20", "3.9.20", "3.10.15", "3.11.10", "3.12.5"],
+    },
+];
+
+/// Parse the patch component of an `X.Y.Z` version string.
+fn parse_patch(version: &str) -> Option<u16> {
+    let patch = version.split('.').nth(2)?;
+
+    patch
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or(patch, |i| &patch[0..i])
+        .parse::<u16>()
+        .ok()
+}
+
+impl PythonAdvisory {
+    /// Whether this vulnerability affects an `X.Y.Z` Python version.
+    pub fn affects(&self, version: &str) -> bool {
+        let (series, patch) = match (parse_major_minor(version), parse_patch(version)) {
+            (Some(series), Some(patch)) => (series, patch),
+            _ => return false,
+        };
+
+        self.fixed.iter().any(|fixed| {
+            parse_major_minor(fixed) == Some(series)
+                && match parse_patch(fixed) {
+                    Some(fixed_patch) => patch < fixed_patch,
+                    None => false,
+                }
+        })
+    }
+}
+
+/// Find known vulnerabilities affecting a Python version published on or after a date.
+pub fn find_advisories(version: &str, since: &str) -> Vec<&'static PythonAdvisory> {
+    PYTHON_ADVISORIES
+        .iter()
+        .filter(|advisory| advisory.published >= since && advisory.affects(version))
+        .collect::<Vec<_>>()
+}
+
+/// Check a Python version against known vulnerabilities.
+///
+/// Vulnerabilities published on or after `baseline` are reported as
+/// warnings or an error, depending on `action`.
+pub fn check_python_version(
+    logger: &slog::Logger,
+    version: &str,
+    baseline: &str,
+    action: PythonSecurityAction,
+) -> Result<()> {
+    let advisories = find_advisories(version, baseline);
+
+    if advisories.is_empty() {
+        return Ok(());
+    }
+
+    for advisory in &advisories {
+        let fixed = advisory
+            .fixed
+            .iter()
+            .find(|fixed| parse_major_minor(fixed) == parse_major_minor(version))
+            .unwrap_or(&"?");
+
+        warn!(
+            logger,
+            "Python {} is affected by {} (published {}; fixed in {})",
+            version,
+            advisory.id,
+            advisory.published,
+            fixed
+        );
+    }
+
+    match action {
+        PythonSecurityAction::Warn => Ok(()),
+        PythonSecurityAction::Error => Err(anyhow!(
+            "Python {} is affected by {} known vulnerabilities published since {}",
+            version,
+            advisories.len(),
+            baseline
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::testutil::get_logger};
+
+    #[test]
+    fn test_affects() {
+        let advisory = &PYTHON_ADVISORIES[0];
+        assert_eq!(advisory.id, "CVE-2021-3177");
+
+        assert!(advisory.affects("3.8.6"));
+        assert!(advisory.affects("3.9.0"));
+        assert!(!advisory.affects("3.8.8"));
+        assert!(!advisory.affects("3.9.2"));
+        assert!(!advisory.affects("3.10.0"));
+        assert!(!advisory.affects("3.9"));
+    }
+
+    #[test]
+    fn test_find_advisories() {
+        let ids =
+            |advisories: Vec<&PythonAdvisory>| advisories.iter().map(|a| a.id).collect::<Vec<_>>();
+
+        assert_eq!(
+            ids(find_advisories("3.10.14", "2000-01-01")),
+            vec!["CVE-2024-6923"]
+        );
+        assert_eq!(
+            ids(find_advisories("3.9.0", "2023-01-01")),
+            vec!["CVE-2023-40217", "CVE-2024-0450", "CVE-2024-6923"]
+        );
+        assert!(find_advisories("3.12.5", "2000-01-01").is_empty());
+    }
+
+    #[test]
+    fn test_check_python_version() -> Result<()> {
+        let logger = get_logger()?;
+
+        check_python_version(&logger, "3.9.0", "2021-01-01", PythonSecurityAction::Warn)?;
+        check_python_version(&logger, "3.12.5", "2021-01-01", PythonSecurityAction::Error)?;
+        assert!(
+            check_python_version(&logger, "3.9.0", "2021-01-01", PythonSecurityAction::Error)
+                .is_err()
+        );
+
+        Ok(())
+    }
+}
@@ -27,19 +27,27 @@ impl PythonDistributionCollection {
     ///
     /// `target_triple` is the Rust machine triple the distribution is built for.
     /// `flavor` is the type of Python distribution.
-    /// `python_major_minor_version` is an optional `X.Y` version string being
-    /// requested. If `None`, `3.8` is assumed.
+    /// `python_version` is an optional `X.Y` or `X.Y.Z` version string being
+    /// requested. `X.Y.Z` values only match that exact version. If `None`,
+    /// `3.8` is assumed.
     pub fn find_distribution(
         &self,
         target_triple: &str,
         flavor: &DistributionFlavor,
-        python_major_minor_version: Option<&str>,
+        python_version: Option<&str>,
     ) -> Option<PythonDistributionRecord> {
-        let python_major_minor_version = python_major_minor_version.unwrap_or("3.8");
+        let python_version = python_version.unwrap_or("3.8");
+        let exact = python_version.split('.').count() > 2;
 
         self.dists
             .iter()
-            .filter(|dist| dist.python_major_minor_version == python_major_minor_version)
+            .filter(|dist| {
+                if exact {
+                    dist.python_version == python_version
+                } else {
+                    dist.python_major_minor_version == python_version
+                }
+            })
             .filter(|dist| dist.target_triple == target_triple)
             .filter(|dist| match flavor {
                 DistributionFlavor::Standalone => true,
@@ -73,6 +81,7 @@ lazy_static! {
             // Linux glibc linked.
             PythonDistributionRecord {
                 python_major_minor_version: "3.8".to_string(),
+                python_version: "3.8.6".to_string(),
                 location: PythonDistributionLocation::Url {
                     url: "https://github.com/indygreg/python-build-standalone/releases/download/20201020/cpython-3.8.6-x86_64-unknown-linux-gnu-pgo-20201020T0627.tar.zst".to_string(),
                     sha256: "789f58ece3ab4ee599e9fd7f6bd9665157ba1a57dca210739df0687ce3757b55".to_string(),
@@ -82,6 +91,7 @@ lazy_static! {
             },
             PythonDistributionRecord {
                 python_major_minor_version: "3.9".to_string(),
+                python_version: "3.9.0".to_string(),
                 location: PythonDistributionLocation::Url {
                     url: "https://github.com/indygreg/python-build-standalone/releases/download/20201020/cpython-3.9.0-x86_64-unknown-linux-gnu-pgo-20201020T0627.tar.zst".to_string(),
                     sha256: "d66a271932b763b6aad36a7f23e15263d67248d4828ef90b0278de959938eadc".to_string(),
@@ -93,6 +103,7 @@ lazy_static! {
             // Linux musl.
             PythonDistributionRecord {
                 python_major_minor_version: "3.8".to_string(),
+                python_version: "3.8.6".to_string(),
                 location: PythonDistributionLocation::Url {
                     url: "https://github.com/indygreg/python-build-standalone/releases/download/20201020/cpython-3.8.6-x86_64-unknown-linux-musl-noopt-20201020T0627.tar.zst".to_string(),
                     sha256: "1dec303ad821b4e54d6562f7d5f85fedf9ac6e7519be60130cdb135c80bd42a5".to_string(),
@@ -102,6 +113,7 @@ lazy_static! {
             },
             PythonDistributionRecord {
                 python_major_minor_version: "3.9".to_string(),
+                python_version: "3.9.0".to_string(),
                 location: PythonDistributionLocation::Url {
                     url: "https://github.com/indygreg/python-build-standalone/releases/download/20201020/cpython-3.9.0-x86_64-unknown-linux-musl-noopt-20201020T0627.tar.zst".to_string(),
                     sha256: "bc2fe6bae62c66552f003049501af1bce4ae0fbe5b7411b45e728828e514b5b1".to_string(),
@@ -120,6 +132,7 @@ lazy_static! {
             // Windows shared.
             PythonDistributionRecord {
                 python_major_minor_version: "3.8".to_string(),
+                python_version: "3.8.6".to_string(),
                 location: PythonDistributionLocation::Url {
                     url: "https://github.com/indygreg/python-build-standalone/releases/download/20201020/cpython-3.8.6-i686-pc-windows-msvc-shared-pgo-20201021T0233.tar.zst".to_string(),
                     sha256: "64209ccf373aba15e7577f538e20df9b65bf3c7fb4086d79e2cd5d93ff8ec8fd".to_string(),
@@ -129,6 +142,7 @@ lazy_static! {
             },
             PythonDistributionRecord {
                 python_major_minor_version: "3.9".to_string(),
+                python_version: "3.9.0".to_string(),
                 location: PythonDistributionLocation::Url {
                     url: "https://github.com/indygreg/python-build-standalone/releases/download/20201020/cpython-3.9.0-i686-pc-windows-msvc-shared-pgo-20201021T0245.tar.zst".to_string(),
                     sha256: "c91d0c7de157b871aaf4a34cfee4008fe6467b8b2a73e0c518f3fcff42c39752".to_string(),
@@ -138,6 +152,7 @@ lazy_static! {
             },
             PythonDistributionRecord {
                 python_major_minor_version: "3.8".to_string(),
+                python_version: "3.8.6".to_string(),
                 location: PythonDistributionLocation::Url {
                     url: "https://github.com/indygreg/python-build-standalone/releases/download/20201020/cpython-3.8.6-x86_64-pc-windows-msvc-shared-pgo-20201021T0232.tar.zst".to_string(),
                     sha256: "b842ddc51a3611b574bd5aba8e233bb9a7a52b0479388187f31723ece09bf898".to_string(),
@@ -147,6 +162,7 @@ lazy_static! {
             },
             PythonDistributionRecord {
                 python_major_minor_version: "3.9".to_string(),
+                python_version: "3.9.0".to_string(),
                 location: PythonDistributionLocation::Url {
                     url: "https://github.com/indygreg/python-build-standalone/releases/download/20201020/cpython-3.9.0-x86_64-pc-windows-msvc-shared-pgo-20201021T0245.tar.zst".to_string(),
                     sha256: "0690cc61ce749b2188cc9380955378a83e2ca95247fe92c754e5c8256c1d32c6".to_string(),
@@ -158,6 +174,7 @@ lazy_static! {
             // Windows static.
             PythonDistributionRecord {
                 python_major_minor_version: "3.8".to_string(),
+                python_version: "3.8.6".to_string(),
                 location: PythonDistributionLocation::Url {
                     url: "https://github.com/indygreg/python-build-standalone/releases/download/20201020/cpython-3.8.6-i686-pc-windows-msvc-static-noopt-20201021T0259.tar.zst".to_string(),
                     sha256: "eb053159e476915c7d019628b5449bbfb54122838f527a4a37fa48a53ce301cc".to_string(),
@@ -167,6 +184,7 @@ lazy_static! {
             },
             PythonDistributionRecord {
                 python_major_minor_version: "3.9".to_string(),
+                python_version: "3.9.0".to_string(),
                 location: PythonDistributionLocation::Url {
                     url: "https://github.com/indygreg/python-build-standalone/releases/download/20201020/cpython-3.9.0-i686-pc-windows-msvc-static-noopt-20201021T0302.tar.zst".to_string(),
                     sha256: "0978b73e586d949d762db519f040080797cbc68619f5726353e087febda5ba19".to_string(),
@@ -176,6 +194,7 @@ lazy_static! {
             },
             PythonDistributionRecord {
                 python_major_minor_version: "3.8".to_string(),
+                python_version: "3.8.6".to_string(),
                 location: PythonDistributionLocation::Url {
                     url: "https://github.com/indygreg/python-build-standalone/releases/download/20201020/cpython-3.8.6-x86_64-pc-windows-msvc-static-noopt-20201021T0259.tar.zst".to_string(),
                     sha256: "85454da6108609d6cd2518cad72d7a0023a6a684c7a5f69e9291da9fd2360e32".to_string(),
@@ -185,6 +204,7 @@ lazy_static! {
             },
             PythonDistributionRecord {
                 python_major_minor_version: "3.9".to_string(),
+                python_version: "3.9.0".to_string(),
                 location: PythonDistributionLocation::Url {
                     url: "https://github.com/indygreg/python-build-standalone/releases/download/20201020/cpython-3.9.0-x86_64-pc-windows-msvc-static-noopt-20201021T0303.tar.zst".to_string(),
                     sha256: "71f5c3b77048dffbfee16791d0018f87a4537e68c9c85d8a9c55367e82c42cf9".to_string(),
@@ -196,6 +216,7 @@ lazy_static! {
             // macOS.
             PythonDistributionRecord {
                 python_major_minor_version: "3.8".to_string(),
+                python_version: "3.8.6".to_string(),
                 location: PythonDistributionLocation::Url {
                     url: "https://github.com/indygreg/python-build-standalone/releases/download/20201020/cpython-3.8.6-x86_64-apple-darwin-pgo-20201020T0626.tar.zst".to_string(),
                     sha256: "e06807ed4d68928634b2690a86ea7a13d339c0fff3808816e98c646bbdc1f79a".to_string(),
@@ -205,6 +226,7 @@ lazy_static! {
             },
             PythonDistributionRecord {
                 python_major_minor_version: "3.9".to_string(),
+                python_version: "3.9.0".to_string(),
                 location: PythonDistributionLocation::Url {
                     url: "https://github.com/indygreg/python-build-standalone/releases/download/20201020/cpython-3.9.0-x86_64-apple-darwin-pgo-20201020T0626.tar.zst".to_string(),
                     sha256: "d1ec23459f6eebf881a7c5bf0e77377c67d60add67d57ece6316e425abe69494".to_string(),
@@ -232,6 +254,35 @@ lazy_static! {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_find_distribution_exact_version() {
+        let dist = PYTHON_DISTRIBUTIONS
+            .find_distribution(
+                "x86_64-unknown-linux-gnu",
+                &DistributionFlavor::Standalone,
+                Some("3.9.0"),
+            )
+            .unwrap();
+        assert_eq!(dist.python_version, "3.9.0");
+
+        let dist = PYTHON_DISTRIBUTIONS
+            .find_distribution(
+                "x86_64-unknown-linux-gnu",
+                &DistributionFlavor::Standalone,
+                Some("3.9"),
+            )
+            .unwrap();
+        assert_eq!(dist.python_version, "3.9.0");
+
+        assert!(PYTHON_DISTRIBUTIONS
+            .find_distribution(
+                "x86_64-unknown-linux-gnu",
+                &DistributionFlavor::Standalone,
+                Some("3.9.1"),
+            )
+            .is_none());
+    }
+
     #[test]
     fn test_all_target_triples() {
         assert_eq!(
@@ -0,0 +1,369 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Produce binary patches between releases of an application.
+//!
+//! Patches are zstd frames compressed using the previous version of a file
+//! as a raw content dictionary. This is the same format produced by
+//! `zstd --patch-from`, so patches can be applied with
+//! `zstd -d --long=30 --patch-from=<old> <patch>` as well as with
+//! `apply_patch()`.
+
+use {
+    crate::paths::replace_file_manifest,
+    anyhow::{anyhow, Context, Result},
+    serde::Serialize,
+    sha2::{Digest, Sha256},
+    slog::warn,
+    std::{
+        collections::BTreeMap,
+        convert::TryFrom,
+        io::Write,
+        path::{Path, PathBuf},
+    },
+    tugger::file_resource::{FileContent, FileManifest},
+    zstd::stream::{
+        raw::{CParameter, DParameter, Decoder, Encoder},
+        zio::Writer,
+    },
+};
+
+/// Filename of the JSON document describing a release patch.
+pub const RELEASE_PATCH_MANIFEST_FILENAME: &str = "patch-manifest.json";
+
+/// zstd compression level used for patches and full files.
+const COMPRESSION_LEVEL: i32 = 19;
+
+/// Largest window zstd will use on all platforms.
+const MAX_WINDOW_LOG: u32 = 30;
+
+/// Describes how to produce a file in the new release.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PatchAction {
+    /// The file is identical in both releases.
+    Unchanged,
+    /// The file is produced by applying a patch to its previous version.
+    Patch,
+    /// The file changed but is shipped in full because a patch is no smaller.
+    Replace,
+    /// The file is new and is shipped in full.
+    Add,
+    /// The file no longer exists.
+    Remove,
+}
+
+/// Describes a single file in a release patch.
+#[derive(Clone, Debug, Serialize)]
+pub struct PatchEntry {
+    /// Path of the file, relative to the install directory.
+    pub path: String,
+
+    pub action: PatchAction,
+
+    /// Path of the patch or zstd compressed file, relative to the patch directory.
+    pub artifact: Option<String>,
+
+    /// SHA-256 of the file in the previous release.
+    pub source_sha256: Option<String>,
+
+    /// SHA-256 of the file in the new release.
+    pub target_sha256: Option<String>,
+
+    pub executable: bool,
+}
+
+/// Describes all files in a release patch.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PatchManifest {
+    pub files: Vec<PatchEntry>,
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// Compute the zstd window log needed to reference all of `size` bytes.
+fn window_log(size: usize) -> u32 {
+    let mut log = 10;
+
+    while log < MAX_WINDOW_LOG && (1usize << log) < size {
+        log += 1;
+    }
+
+    log
+}
+
+/// Create a patch transforming `old` into `new`.
+pub fn create_patch(old: &[u8], new: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = Encoder::with_dictionary(COMPRESSION_LEVEL, old)?;
+    encoder.set_parameter(CParameter::WindowLog(window_log(std::cmp::max(
+        old.len(),
+        new.len(),
+    ))))?;
+    encoder.set_parameter(CParameter::EnableLongDistanceMatching(true))?;
+
+    let mut writer = Writer::new(Vec::new(), encoder);
+    writer.write_all(new)?;
+    writer.finish()?;
+
+    Ok(writer.into_inner().0)
+}
+
+/// Apply a patch produced by `create_patch()` to `old`.
+pub fn apply_patch(old: &[u8], patch: &[u8]) -> Result<Vec<u8>> {
+    let mut decoder = Decoder::with_dictionary(old)?;
+    decoder.set_parameter(DParameter::WindowLogMax(MAX_WINDOW_LOG))?;
+
+    let mut writer = Writer::new(Vec::new(), decoder);
+    writer.write_all(patch)?;
+    writer.finish()?;
+
+    Ok(writer.into_inner().0)
+}
+
+/// Convert a relative path to the `/` delimited form used in patch manifests.
+fn manifest_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Read all files under a directory, keyed by their relative path.
+fn read_directory(path: &Path) -> Result<BTreeMap<PathBuf, FileContent>> {
+    let mut res = BTreeMap::new();
+
+    for entry in walkdir::WalkDir::new(path) {
+        let entry = entry?;
+
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let rel_path = entry.path().strip_prefix(path)?.to_path_buf();
+        let content = FileContent::try_from(entry.path())
+            .with_context(|| format!("reading {}", entry.path().display()))?;
+
+        res.insert(rel_path, content);
+    }
+
+    Ok(res)
+}
+
+/// Write a patch transforming the files in `previous_path` into `current`.
+///
+/// Every patch is applied after it is produced and the result compared
+/// against the new file, so a successful return means the patch
+/// reproduces `current` exactly.
+pub fn write_release_patch(
+    logger: &slog::Logger,
+    previous_path: &Path,
+    current: &FileManifest,
+    dest_path: &Path,
+) -> Result<PatchManifest> {
+    if !previous_path.is_dir() {
+        return Err(anyhow!(
+            "previous release {} is not a directory",
+            previous_path.display()
+        ));
+    }
+
+    let previous = read_directory(previous_path)?;
+    let mut manifest = PatchManifest::default();
+    let mut artifacts = FileManifest::default();
+
+    for (path, content) in current.entries() {
+        let rel_path = manifest_path(path);
+        let target_sha256 = sha256_hex(&content.data);
+
+        let (action, artifact, source_sha256) = match previous.get(path) {
+            Some(old) if old.data == content.data => {
+                (PatchAction::Unchanged, None, Some(target_sha256.clone()))
+            }
+            Some(old) => {
+                let patch = create_patch(&old.data, &content.data)?;
+                let full = zstd::encode_all(content.data.as_slice(), COMPRESSION_LEVEL)?;
+
+                if sha256_hex(&apply_patch(&old.data, &patch)?) != target_sha256 {
+                    return Err(anyhow!("patch for {} failed verification", rel_path));
+                }
+
+                let (action, artifact, data) = if patch.len() < full.len() {
+                    (PatchAction::Patch, format!("{}.patch", rel_path), patch)
+                } else {
+                    (PatchAction::Replace, format!("{}.zst", rel_path), full)
+                };
+
+                warn!(
+                    logger,
+                    "{}: {} bytes -> {} bytes",
+                    artifact,
+                    content.data.len(),
+                    data.len()
+                );
+                artifacts.add_file(
+                    Path::new(&artifact),
+                    &FileContent {
+                        data,
+                        executable: false,
+                    },
+                )?;
+
+                (action, Some(artifact), Some(sha256_hex(&old.data)))
+            }
+            None => {
+                let artifact = format!("{}.zst", rel_path);
+                let data = zstd::encode_all(content.data.as_slice(), COMPRESSION_LEVEL)?;
+
+                if sha256_hex(&zstd::decode_all(data.as_slice())?) != target_sha256 {
+                    return Err(anyhow!("compressed {} failed verification", rel_path));
+                }
+
+                artifacts.add_file(
+                    Path::new(&artifact),
+                    &FileContent {
+                        data,
+                        executable: false,
+                    },
+                )?;
+
+                (PatchAction::Add, Some(artifact), None)
+            }
+        };
+
+        manifest.files.push(PatchEntry {
+            path: rel_path,
+            action,
+            artifact,
+            source_sha256,
+            target_sha256: Some(target_sha256),
+            executable: content.executable,
+        });
+    }
+
+    for (path, old) in &previous {
+        if current.has_path(path) {
+            continue;
+        }
+
+        manifest.files.push(PatchEntry {
+            path: manifest_path(path),
+            action: PatchAction::Remove,
+            artifact: None,
+            source_sha256: Some(sha256_hex(&old.data)),
+            target_sha256: None,
+            executable: old.executable,
+        });
+    }
+
+    artifacts.add_file(
+        Path::new(RELEASE_PATCH_MANIFEST_FILENAME),
+        &FileContent {
+            data: serde_json::to_vec_pretty(&manifest)?,
+            executable: false,
+        },
+    )?;
+
+    replace_file_manifest(&artifacts, dest_path)?;
+
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::testutil::*};
+
+    /// Produce incompressible data so patches are smaller than full files.
+    fn random_data(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_patch_roundtrip() -> Result<()> {
+        let old = random_data(400_000);
+        let mut new = old.clone();
+        new[5000..5010].copy_from_slice(b"0123456789");
+        new.extend_from_slice(b"appended");
+
+        let patch = create_patch(&old, &new)?;
+        assert!(patch.len() < 1000);
+        assert_eq!(apply_patch(&old, &patch)?, new);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_release_patch() -> Result<()> {
+        let logger = get_logger()?;
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+
+        let previous_path = temp_dir.path().join("previous");
+        let mut previous = FileManifest::default();
+        for (path, data) in &[
+            ("lib/unchanged.txt", b"same".to_vec()),
+            ("lib/changed.bin", random_data(65536)),
+            ("removed.txt", b"gone".to_vec()),
+        ] {
+            previous.add_file(
+                Path::new(path),
+                &FileContent {
+                    data: data.clone(),
+                    executable: false,
+                },
+            )?;
+        }
+        previous.write_to_path(&previous_path)?;
+
+        let mut changed = random_data(65536);
+        changed[1000] = 0;
+
+        let mut current = FileManifest::default();
+        for (path, data) in &[
+            ("lib/unchanged.txt", b"same".to_vec()),
+            ("lib/changed.bin", changed.clone()),
+            ("added.txt", b"new".to_vec()),
+        ] {
+            current.add_file(
+                Path::new(path),
+                &FileContent {
+                    data: data.clone(),
+                    executable: false,
+                },
+            )?;
+        }
+
+        let dest_path = temp_dir.path().join("patch");
+        let manifest = write_release_patch(&logger, &previous_path, &current, &dest_path)?;
+
+        let actions = manifest
+            .files
+            .iter()
+            .map(|e| (e.path.as_str(), e.action))
+            .collect::<BTreeMap<_, _>>();
+        assert_eq!(
+            actions.get("lib/unchanged.txt"),
+            Some(&PatchAction::Unchanged)
+        );
+        assert_eq!(actions.get("lib/changed.bin"), Some(&PatchAction::Patch));
+        assert_eq!(actions.get("added.txt"), Some(&PatchAction::Add));
+        assert_eq!(actions.get("removed.txt"), Some(&PatchAction::Remove));
+
+        let patch = std::fs::read(dest_path.join("lib/changed.bin.patch"))?;
+        assert_eq!(apply_patch(&random_data(65536), &patch)?, changed);
+        assert!(dest_path.join("added.txt.zst").exists());
+        assert!(dest_path.join(RELEASE_PATCH_MANIFEST_FILENAME).exists());
+
+        Ok(())
+    }
+}
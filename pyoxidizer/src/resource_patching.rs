@@ -0,0 +1,500 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Replacing resource data in built executables.
+
+Executables built with `packed_resources_embedding = "overlay"` carry their
+packed resources data at the end of the file. That data can be rewritten
+without rebuilding the executable, which allows shipping fixes to data files
+quickly.
+
+Rewriting an executable invalidates its code signature. Authenticode
+signatures are stored after the overlay, so they are removed. The PE
+checksum is recomputed if the executable has one. Before the executable is
+written, we check that its new overlay is found and holds the patched data.
+*/
+
+use {
+    anyhow::{anyhow, Result},
+    python_packed_resources::{
+        data::Resource,
+        overlay::{append_overlay, find_overlay},
+        parser::{load_resources, verify_resources},
+        writer::write_packed_resources_v3,
+    },
+    slog::warn,
+    std::{borrow::Cow, path::Path},
+};
+
+/// Mach-O load command holding a code signature.
+const LC_CODE_SIGNATURE: u32 = 0x1d;
+
+/// Offset of the `CheckSum` field within the PE optional header.
+const PE_CHECKSUM_OFFSET: usize = 64;
+
+/// Index of the certificate table in the PE data directories.
+const PE_CERTIFICATE_TABLE_INDEX: usize = 4;
+
+/// Location of fields in a PE file relevant to patching.
+struct PeLayout {
+    /// Offset of the `CheckSum` field.
+    checksum_offset: usize,
+
+    /// Offset of the certificate table data directory entry.
+    certificate_table_entry_offset: usize,
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset.checked_add(2)?)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset.checked_add(4)?)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn write_u32(data: &mut [u8], offset: usize, value: u32) -> Result<()> {
+    offset
+        .checked_add(4)
+        .and_then(|end| data.get_mut(offset..end))
+        .ok_or_else(|| anyhow!("offset {} is beyond the end of the file", offset))?
+        .copy_from_slice(&value.to_le_bytes());
+
+    Ok(())
+}
+
+/// Resolve the layout of a PE file, if data is one.
+fn pe_layout(data: &[u8]) -> Option<PeLayout> {
+    if !data.starts_with(b"MZ") {
+        return None;
+    }
+
+    let pe_offset = read_u32(data, 0x3c)? as usize;
+    if data.get(pe_offset..pe_offset.checked_add(4)?)? != b"PE\0\0" {
+        return None;
+    }
+
+    // The optional header follows the 4 byte signature and 20 byte COFF header.
+    let optional_header = pe_offset.checked_add(24)?;
+
+    let data_directories = match read_u16(data, optional_header)? {
+        0x10b => optional_header.checked_add(96)?,
+        0x20b => optional_header.checked_add(112)?,
+        _ => return None,
+    };
+
+    let certificate_table_entry_offset =
+        data_directories.checked_add(PE_CERTIFICATE_TABLE_INDEX * 8)?;
+    if certificate_table_entry_offset.checked_add(8)? > data.len() {
+        return None;
+    }
+
+    // The checksum field precedes the data directories, so it is in bounds.
+    Some(PeLayout {
+        checksum_offset: optional_header + PE_CHECKSUM_OFFSET,
+        certificate_table_entry_offset,
+    })
+}
+
+/// Compute the checksum of a PE file.
+///
+/// This is the algorithm of `CheckSumMappedFile()`: a folded sum of 16-bit
+/// words, skipping the checksum field, plus the file length.
+fn pe_checksum(data: &[u8], checksum_offset: usize) -> u32 {
+    let mut sum: u64 = 0;
+
+    for (i, chunk) in data.chunks(2).enumerate() {
+        let offset = i * 2;
+        if offset == checksum_offset || offset == checksum_offset + 2 {
+            continue;
+        }
+
+        sum += u64::from(chunk[0]) | (u64::from(*chunk.get(1).unwrap_or(&0)) << 8);
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+
+    sum = (sum & 0xffff) + (sum >> 16);
+
+    (sum as u32).wrapping_add(data.len() as u32)
+}
+
+/// Remove an Authenticode signature from PE data.
+///
+/// Returns whether a signature was removed.
+fn remove_authenticode_signature(data: &mut Vec<u8>, layout: &PeLayout) -> Result<bool> {
+    let (offset, size) = match (
+        read_u32(data, layout.certificate_table_entry_offset),
+        read_u32(data, layout.certificate_table_entry_offset + 4),
+    ) {
+        (Some(offset), Some(size)) => (offset as usize, size as usize),
+        _ => return Err(anyhow!("PE certificate table entry is truncated")),
+    };
+
+    if offset == 0 || size == 0 {
+        return Ok(false);
+    }
+
+    let end = offset
+        .checked_add(size)
+        .filter(|end| *end <= data.len())
+        .ok_or_else(|| anyhow!("certificate table extends beyond the end of the file"))?;
+
+    // The certificate table is at the end of the file. Padding may follow it.
+    if end < data.len().saturating_sub(7) {
+        return Err(anyhow!(
+            "certificate table is not at the end of the file; cannot remove signature"
+        ));
+    }
+
+    data.truncate(offset);
+    write_u32(data, layout.certificate_table_entry_offset, 0)?;
+    write_u32(data, layout.certificate_table_entry_offset + 4, 0)?;
+
+    Ok(true)
+}
+
+/// Whether Mach-O data has a code signature.
+fn has_macho_code_signature(data: &[u8]) -> bool {
+    match goblin::mach::Mach::parse(data) {
+        Ok(goblin::mach::Mach::Binary(macho)) => macho
+            .load_commands
+            .iter()
+            .any(|command| command.command.cmd() == LC_CODE_SIGNATURE),
+        _ => false,
+    }
+}
+
+/// Find the resource holding the data for a path and replace it.
+///
+/// `path` is either the path of a package resource, i.e. the package's
+/// directory followed by the resource's name within it, or the path of
+/// a file installed in memory.
+///
+/// Returns whether a resource was found.
+fn replace_resource_data(resources: &mut [Resource<u8>], path: &str, data: &[u8]) -> bool {
+    let components = path.split('/').collect::<Vec<_>>();
+
+    // The deepest package wins.
+    for split in (1..components.len()).rev() {
+        let package = components[0..split].join(".");
+        let name = components[split..].join("/");
+
+        let resource = resources
+            .iter_mut()
+            .filter(|r| r.is_module && r.name == package)
+            .filter_map(|r| r.in_memory_package_resources.as_mut())
+            .find(|resources| resources.contains_key(name.as_str()));
+
+        if let Some(package_resources) = resource {
+            package_resources.insert(Cow::Owned(name), Cow::Owned(data.to_vec()));
+            return true;
+        }
+    }
+
+    for resource in resources.iter_mut() {
+        if resource.is_utf8_filename_data
+            && resource.name == path
+            && resource.file_data_embedded.is_some()
+        {
+            resource.file_data_embedded = Some(Cow::Owned(data.to_vec()));
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Replace data of resources in packed resources data.
+///
+/// Returns new packed resources data.
+pub fn patch_packed_resources(data: &[u8], replacements: &[(String, Vec<u8>)]) -> Result<Vec<u8>> {
+    let mut resources = load_resources(data)
+        .map_err(|e| anyhow!("unable to load packed resources: {}", e))?
+        .map(|r| r.map(|r| r.to_owned()))
+        .collect::<Result<Vec<_>, &'static str>>()
+        .map_err(|e| anyhow!("unable to load packed resources: {}", e))?;
+
+    for (path, data) in replacements {
+        if !replace_resource_data(&mut resources, path, data) {
+            return Err(anyhow!(
+                "no in-memory resource {}; only existing package resources and files can be replaced",
+                path
+            ));
+        }
+    }
+
+    let mut res = vec![];
+    write_packed_resources_v3(&resources, &mut res, None)?;
+
+    verify_resources(&res).map_err(|e| anyhow!("patched resources are invalid: {}", e))?;
+
+    Ok(res)
+}
+
+/// Replace resource data in an executable having a packed resources overlay.
+///
+/// `replacements` maps resource paths like `mypkg/config.json` to files
+/// holding their new data.
+pub fn patch_executable_resources(
+    logger: &slog::Logger,
+    path: &Path,
+    replacements: &[(String, &Path)],
+) -> Result<()> {
+    let mut data = std::fs::read(path)?;
+
+    let pe = pe_layout(&data);
+
+    if let Some(layout) = &pe {
+        if remove_authenticode_signature(&mut data, layout)? {
+            warn!(
+                logger,
+                "removed the Authenticode signature of {}; sign it again before distributing it",
+                path.display()
+            );
+        }
+    }
+
+    if has_macho_code_signature(&data) {
+        warn!(
+            logger,
+            "{} is code signed; patching invalidates its signature, which must be reapplied",
+            path.display()
+        );
+    }
+
+    let overlay = find_overlay(&data).ok_or_else(|| {
+        anyhow!(
+            "{} does not contain a packed resources overlay; only executables built with packed_resources_embedding = \"overlay\" can be patched",
+            path.display()
+        )
+    })?;
+    let overlay_start = overlay.as_ptr() as usize - data.as_ptr() as usize;
+
+    let replacements = replacements
+        .iter()
+        .map(|(name, source)| Ok((name.clone(), std::fs::read(source)?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let resources = patch_packed_resources(overlay, &replacements)?;
+
+    data.truncate(overlay_start);
+    append_overlay(&mut data, &resources);
+
+    if let Some(layout) = &pe {
+        if read_u32(&data, layout.checksum_offset) != Some(0) {
+            let checksum = pe_checksum(&data, layout.checksum_offset);
+            write_u32(&mut data, layout.checksum_offset, checksum)?;
+        }
+    }
+
+    // Make sure the patched executable's overlay is found and intact
+    // before replacing the original.
+    match find_overlay(&data) {
+        Some(overlay) if overlay == resources.as_slice() => {}
+        _ => {
+            return Err(anyhow!(
+                "patched packed resources overlay of {} cannot be found; not writing it",
+                path.display()
+            ));
+        }
+    }
+
+    std::fs::write(path, &data)?;
+
+    for (name, _) in &replacements {
+        warn!(logger, "replaced {}", name);
+    }
+
+    Ok(())
+}
+
+/// Parse a `NAME=FILE` replacement argument.
+pub fn parse_replacement(value: &str) -> Result<(String, &Path)> {
+    let mut parts = value.splitn(2, '=');
+
+    match (parts.next(), parts.next()) {
+        (Some(name), Some(file)) if !name.is_empty() && !file.is_empty() => {
+            Ok((name.trim_matches('/').to_string(), Path::new(file)))
+        }
+        _ => Err(anyhow!(
+            "invalid replacement {}; expected RESOURCE=FILE",
+            value
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::testutil::get_logger, std::collections::HashMap};
+
+    fn packed_resources() -> Result<Vec<u8>> {
+        let mut package_resources = HashMap::new();
+        package_resources.insert(Cow::from("config.json"), Cow::from(b"{}".to_vec()));
+        package_resources.insert(Cow::from("data/x.txt"), Cow::from(b"x".to_vec()));
+
+        let resources = vec![
+            Resource {
+                name: Cow::from("mypkg"),
+                is_module: true,
+                is_package: true,
+                in_memory_package_resources: Some(package_resources),
+                ..Resource::default()
+            },
+            Resource {
+                name: Cow::from("etc/app.conf"),
+                is_utf8_filename_data: true,
+                file_data_embedded: Some(Cow::from(b"old".to_vec())),
+                ..Resource::default()
+            },
+        ];
+
+        let mut data = vec![];
+        write_packed_resources_v3(&resources, &mut data, None)?;
+
+        Ok(data)
+    }
+
+    #[test]
+    fn test_patch_packed_resources() -> Result<()> {
+        let data = patch_packed_resources(
+            &packed_resources()?,
+            &[
+                (
+                    "mypkg/config.json".to_string(),
+                    b"{\"debug\": true}".to_vec(),
+                ),
+                ("mypkg/data/x.txt".to_string(), b"y".to_vec()),
+                ("etc/app.conf".to_string(), b"new".to_vec()),
+            ],
+        )?;
+
+        let resources = load_resources(&data)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let package_resources = resources[0].in_memory_package_resources.as_ref().unwrap();
+        assert_eq!(
+            package_resources.get("config.json").unwrap().as_ref(),
+            b"{\"debug\": true}"
+        );
+        assert_eq!(package_resources.get("data/x.txt").unwrap().as_ref(), b"y");
+        assert_eq!(
+            resources[1].file_data_embedded.as_ref().unwrap().as_ref(),
+            b"new"
+        );
+
+        assert!(patch_packed_resources(
+            &packed_resources()?,
+            &[("mypkg/missing.json".to_string(), vec![])]
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_patch_executable_resources() -> Result<()> {
+        let logger = get_logger()?;
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+
+        let exe_path = temp_dir.path().join("app");
+        let mut exe = b"not really an executable".to_vec();
+        append_overlay(&mut exe, &packed_resources()?);
+        std::fs::write(&exe_path, &exe)?;
+
+        let new_path = temp_dir.path().join("config.json");
+        std::fs::write(&new_path, b"[]")?;
+
+        patch_executable_resources(
+            &logger,
+            &exe_path,
+            &[("mypkg/config.json".to_string(), new_path.as_path())],
+        )?;
+
+        let data = std::fs::read(&exe_path)?;
+        assert!(data.starts_with(b"not really an executable"));
+        let resources = load_resources(find_overlay(&data).unwrap())
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            resources[0]
+                .in_memory_package_resources
+                .as_ref()
+                .unwrap()
+                .get("config.json")
+                .unwrap()
+                .as_ref(),
+            b"[]"
+        );
+
+        std::fs::write(&exe_path, b"no overlay")?;
+        assert!(patch_executable_resources(
+            &logger,
+            &exe_path,
+            &[("mypkg/config.json".to_string(), new_path.as_path())],
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pe() -> Result<()> {
+        let mut data = vec![0u8; 0x100];
+        data[0..2].copy_from_slice(b"MZ");
+        write_u32(&mut data, 0x3c, 0x40)?;
+        data[0x40..0x44].copy_from_slice(b"PE\0\0");
+        data[0x58..0x5a].copy_from_slice(&0x20bu16.to_le_bytes());
+
+        let layout = pe_layout(&data).unwrap();
+        assert_eq!(layout.checksum_offset, 0x98);
+        assert_eq!(layout.certificate_table_entry_offset, 0xe8);
+
+        // The checksum field doesn't contribute to the checksum.
+        let checksum = pe_checksum(&data, layout.checksum_offset);
+        write_u32(&mut data, layout.checksum_offset, 0xdead_beef)?;
+        assert_eq!(pe_checksum(&data, layout.checksum_offset), checksum);
+
+        assert!(!remove_authenticode_signature(&mut data, &layout)?);
+
+        write_u32(&mut data, layout.certificate_table_entry_offset, 0x100)?;
+        write_u32(&mut data, layout.certificate_table_entry_offset + 4, 16)?;
+        data.extend_from_slice(&[1; 16]);
+        assert!(remove_authenticode_signature(&mut data, &layout)?);
+        assert_eq!(data.len(), 0x100);
+        assert_eq!(
+            read_u32(&data, layout.certificate_table_entry_offset),
+            Some(0)
+        );
+
+        // Certificate tables extending beyond the file are rejected.
+        let mut signed = data.clone();
+        write_u32(&mut signed, layout.certificate_table_entry_offset, 0x100)?;
+        write_u32(
+            &mut signed,
+            layout.certificate_table_entry_offset + 4,
+            u32::MAX,
+        )?;
+        assert!(remove_authenticode_signature(&mut signed, &layout).is_err());
+        write_u32(&mut signed, layout.certificate_table_entry_offset, u32::MAX)?;
+        write_u32(&mut signed, layout.certificate_table_entry_offset + 4, 16)?;
+        assert!(remove_authenticode_signature(&mut signed, &layout).is_err());
+
+        assert!(write_u32(&mut data, usize::MAX - 1, 0).is_err());
+
+        assert!(pe_layout(b"MZ").is_none());
+        assert!(pe_layout(b"\x7fELF").is_none());
+
+        // A PE header offset near the end of the address space.
+        let mut truncated = vec![0u8; 0x40];
+        truncated[0..2].copy_from_slice(b"MZ");
+        write_u32(&mut truncated, 0x3c, u32::MAX)?;
+        assert!(pe_layout(&truncated).is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,734 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Machine readable description of the Starlark API.
+
+`pyoxidizer dump-starlark-api` emits the global functions and types
+available to configuration files so editor plugins and external validators
+can stay in sync with the `pyoxidizer` binary.
+
+Functions, methods and their parameters are read from the Starlark
+environment. Attributes are implemented by `get_attr()` and can't be
+enumerated, so they and their types are listed here. Tests verify these
+lists against the attributes types actually have.
+*/
+
+use {
+    super::{
+        python_distribution::PythonDistributionValue,
+        python_embedded_resources::PythonEmbeddedResources,
+        python_executable::PythonExecutable,
+        python_interpreter_config::PythonInterpreterConfigValue,
+        python_library::PythonLibrary,
+        python_packaging_policy::{PythonPackagingPolicyValue, ResourceCallbackContextValue},
+        python_resource::{
+            FileValue, GettextCatalogValue, PythonExtensionModuleValue, PythonModuleSourceValue,
+            PythonPackageDistributionResourceValue, PythonPackageResourceValue,
+        },
+        python_resources_snapshot::PythonResourcesSnapshot,
+        python_wasi_bundle::PythonWasiBundle,
+        release::Release,
+        release_patch::ReleasePatch,
+    },
+    anyhow::{anyhow, Result},
+    serde::Serialize,
+    starlark::{
+        environment::{Environment, TypeValues},
+        values::{Immutable, TypedValue, Value},
+    },
+    std::marker::PhantomData,
+    tugger::starlark::{
+        code_signing::CodeSigningCertificateValue,
+        file_resource::{FileContentValue, FileManifestValue},
+        macos_pkg::MacOsPkgBuilderValue,
+        package_managers::PackageManagerManifestsValue,
+        secrets::SecretValue,
+    },
+};
+
+/// Global functions available to configuration files.
+const GLOBAL_FUNCTIONS: &[&str] = &[
+    "FileManifest",
+    "MacOsPkgBuilder",
+    "PackageManagerManifests",
+    "PythonDistribution",
+    "Release",
+    "code_signing_certificates",
+    "default_python_distribution",
+    "glob",
+    "parse_json",
+    "parse_toml",
+    "parse_yaml",
+    "print",
+    "register_app",
+    "register_target",
+    "resolve_target",
+    "resolve_targets",
+    "secret_from_command",
+    "secret_from_env",
+    "secret_from_file",
+    "secret_from_keychain",
+    "set_build_path",
+    "set_temp_dir",
+    "struct",
+    "target_output_path",
+    "vcs_version",
+];
+
+/// Global variables available to configuration files and their types.
+const GLOBAL_VARIABLES: &[(&str, &str)] = &[
+    ("BUILD_TARGET_TRIPLE", "string"),
+    ("CONFIG_PATH", "string"),
+    ("CWD", "string"),
+];
+
+const CODE_SIGNING_CERTIFICATE_ATTRIBUTES: &[(&str, &str)] = &[
+    ("not_after", "Optional[string]"),
+    ("store", "string"),
+    ("subject", "string"),
+    ("thumbprint", "string"),
+];
+
+const FILE_ATTRIBUTES: &[(&str, &str)] = &[("path", "string"), ("is_executable", "bool")];
+
+const GETTEXT_CATALOG_ATTRIBUTES: &[(&str, &str)] = &[
+    ("package", "string"),
+    ("name", "string"),
+    ("locale", "string"),
+    ("domain", "string"),
+];
+
+const PYTHON_EXECUTABLE_ATTRIBUTES: &[(&str, &str)] = &[
+    ("bundle_tzdata", "bool"),
+    ("external_runtime_path", "Optional[string]"),
+    ("linux_minimum_glibc_version", "Optional[string]"),
+    ("macos_deployment_target", "Optional[string]"),
+    ("max_size_mb", "Optional[int]"),
+    ("packed_resources_embedding", "string"),
+    ("tcl_files_path", "Optional[string]"),
+    ("version", "Optional[string]"),
+    ("windows_service_name", "Optional[string]"),
+    ("windows_runtime_dlls_mode", "string"),
+    ("windows_subsystem", "string"),
+];
+
+const PYTHON_EXTENSION_MODULE_ATTRIBUTES: &[(&str, &str)] =
+    &[("name", "string"), ("is_stdlib", "bool")];
+
+const PYTHON_MODULE_SOURCE_ATTRIBUTES: &[(&str, &str)] = &[
+    ("name", "string"),
+    ("source", "string"),
+    ("is_package", "bool"),
+    ("is_stdlib", "bool"),
+];
+
+const PYTHON_PACKAGE_DISTRIBUTION_RESOURCE_ATTRIBUTES: &[(&str, &str)] = &[
+    ("package", "string"),
+    ("name", "string"),
+    ("is_stdlib", "bool"),
+];
+
+const PYTHON_PACKAGE_RESOURCE_ATTRIBUTES: &[(&str, &str)] = &[
+    ("package", "string"),
+    ("name", "string"),
+    ("is_stdlib", "bool"),
+];
+
+const PYTHON_INTERPRETER_CONFIG_ATTRIBUTES: &[(&str, &str)] = &[
+    ("allocator", "Optional[string]"),
+    ("raw_allocator", "string"),
+    ("argv0", "Optional[string]"),
+    ("argvb", "bool"),
+    ("asyncio_event_loop_policy", "Optional[string]"),
+    ("base_exec_prefix", "Optional[string]"),
+    ("base_executable", "Optional[string]"),
+    ("base_prefix", "Optional[string]"),
+    ("buffered_stdio", "Optional[bool]"),
+    ("bytes_warning", "Optional[string]"),
+    ("capabilities", "list[string]"),
+    ("capabilities_env", "Optional[string]"),
+    ("check_hash_pycs_mode", "Optional[string]"),
+    ("coerce_c_locale", "Optional[string]"),
+    ("coerce_c_locale_warn", "Optional[bool]"),
+    ("config_profile", "string"),
+    ("configure_c_stdio", "Optional[bool]"),
+    ("debug_shell", "bool"),
+    ("configure_locale", "Optional[bool]"),
+    ("dev_source_roots", "list[string]"),
+    ("development_mode", "Optional[bool]"),
+    ("dump_refs", "Optional[bool]"),
+    ("entry_points", "dict[string, string]"),
+    ("exec_prefix", "Optional[string]"),
+    ("executable", "Optional[string]"),
+    ("fault_handler", "Optional[bool]"),
+    ("filesystem_encoding", "Optional[string]"),
+    ("filesystem_errors", "Optional[string]"),
+    ("filesystem_importer", "bool"),
+    ("frozen_modules", "list[string]"),
+    ("hash_seed", "Optional[int]"),
+    ("home", "Optional[string]"),
+    ("import_time", "Optional[bool]"),
+    ("inspect", "Optional[bool]"),
+    ("install_signal_handlers", "Optional[bool]"),
+    ("interactive", "Optional[bool]"),
+    ("isolated", "Optional[bool]"),
+    ("legacy_windows_fs_encoding", "Optional[bool]"),
+    ("legacy_windows_stdio", "Optional[bool]"),
+    ("malloc_stats", "Optional[bool]"),
+    ("module_search_paths", "Optional[list[string]]"),
+    ("module_search_paths_set", "Optional[bool]"),
+    ("optimization_level", "Optional[int]"),
+    ("oxidized_importer", "bool"),
+    ("parse_argv", "Optional[bool]"),
+    ("parser_debug", "Optional[bool]"),
+    ("pathconfig_warnings", "Optional[bool]"),
+    ("platlibdir", "Optional[string]"),
+    ("pre_run_modules", "list[string]"),
+    ("prefix", "Optional[string]"),
+    ("preload_modules", "list[string]"),
+    ("prepend_args", "list[string]"),
+    ("program_name", "Optional[string]"),
+    ("pycache_prefix", "Optional[string]"),
+    ("python_path_env", "Optional[string]"),
+    ("quiet", "Optional[bool]"),
+    ("repl_banner", "Optional[string]"),
+    ("repl_ps1", "Optional[string]"),
+    ("repl_ps2", "Optional[string]"),
+    ("repl_startup_code", "Optional[string]"),
+    ("resource_dirs", "list[string]"),
+    ("resource_dirs_flag", "Optional[string]"),
+    ("restricted_packages", "dict[string, string]"),
+    ("run_command", "Optional[string]"),
+    ("run_filename", "Optional[string]"),
+    ("run_module", "Optional[string]"),
+    ("show_alloc_count", "Optional[bool]"),
+    ("show_ref_count", "Optional[bool]"),
+    ("site_import", "Optional[bool]"),
+    ("skip_first_source_line", "Optional[bool]"),
+    ("stdio_encoding", "Optional[string]"),
+    ("stdio_errors", "Optional[string]"),
+    ("sys_frozen", "bool"),
+    ("sys_meipass", "bool"),
+    ("terminfo_resolution", "string"),
+    ("thread_stack_size", "Optional[int]"),
+    ("tracemalloc", "Optional[bool]"),
+    ("use_environment", "Optional[bool]"),
+    ("user_site_directory", "Optional[bool]"),
+    ("utf8_mode", "Optional[bool]"),
+    ("verbose", "Optional[bool]"),
+    ("warn_options", "Optional[list[string]]"),
+    ("write_bytecode", "Optional[bool]"),
+    ("write_modules_directory_env", "Optional[string]"),
+    ("x_options", "Optional[list[string]]"),
+];
+
+const PYTHON_PACKAGING_POLICY_ATTRIBUTES: &[(&str, &str)] = &[
+    ("allow_files", "bool"),
+    ("allow_in_memory_shared_library_loading", "bool"),
+    ("allowed_licenses", "list[string]"),
+    ("apply_package_hints", "bool"),
+    ("build_sandbox", "string"),
+    ("build_sandbox_allow_network", "bool"),
+    ("bytecode_optimize_level_zero", "bool"),
+    ("bytecode_optimize_level_one", "bool"),
+    ("bytecode_optimize_level_two", "bool"),
+    ("denied_licenses", "list[string]"),
+    ("exclude_patterns", "list[string]"),
+    ("excluded_modules", "list[string]"),
+    ("extension_module_filter", "string"),
+    ("file_scanner_classify_files", "bool"),
+    ("file_scanner_emit_files", "bool"),
+    ("gettext_catalogs_location", "Optional[string]"),
+    ("include_distribution_sources", "bool"),
+    ("include_distribution_resources", "bool"),
+    ("include_classified_resources", "bool"),
+    ("include_file_resources", "bool"),
+    ("include_non_distribution_sources", "bool"),
+    ("include_patterns", "list[string]"),
+    ("include_test", "bool"),
+    ("prefer_abi3_extensions", "bool"),
+    (
+        "preferred_extension_module_variants",
+        "dict[string, string]",
+    ),
+    ("python_security_action", "string"),
+    ("python_security_baseline", "Optional[string]"),
+    ("resources_location", "string"),
+    ("resources_location_fallback", "Optional[string]"),
+    ("stdlib_profile", "Optional[string]"),
+    ("strip_docstrings", "bool"),
+];
+
+const RELEASE_ATTRIBUTES: &[(&str, &str)] = &[("dry_run", "bool"), ("verify_signatures", "bool")];
+
+const RESOURCE_CALLBACK_CONTEXT_ATTRIBUTES: &[(&str, &str)] = &[
+    ("distribution_name", "Optional[string]"),
+    ("distribution_version", "Optional[string]"),
+    ("origin_path", "Optional[string]"),
+    ("requires_dist", "list[string]"),
+];
+
+const ADD_COLLECTION_CONTEXT_ATTRIBUTES: &[(&str, &str)] = &[
+    ("add_include", "bool"),
+    ("add_location", "string"),
+    ("add_location_fallback", "Optional[string]"),
+    ("add_source", "bool"),
+    ("add_bytecode_optimization_level_zero", "bool"),
+    ("add_bytecode_optimization_level_one", "bool"),
+    ("add_bytecode_optimization_level_two", "bool"),
+];
+
+/// How a parameter receives arguments.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParameterKind {
+    /// Receives a single positional or keyword argument.
+    Normal,
+    /// Receives extra positional arguments (`*args`).
+    Args,
+    /// Receives extra keyword arguments (`**kwargs`).
+    Kwargs,
+}
+
+/// Describes a parameter of a function or method.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ParameterDescription {
+    pub name: String,
+    pub kind: ParameterKind,
+    /// Whether an argument must be passed.
+    pub required: bool,
+    /// Representation of the default value, if there is one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+}
+
+/// Describes a function or method.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FunctionDescription {
+    pub name: String,
+    pub parameters: Vec<ParameterDescription>,
+}
+
+/// Describes an attribute of a type or a global variable.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AttributeDescription {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub value_type: String,
+}
+
+/// Describes a type.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TypeDescription {
+    pub name: String,
+    pub attributes: Vec<AttributeDescription>,
+    pub methods: Vec<FunctionDescription>,
+}
+
+/// Describes the Starlark API available to configuration files.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ApiDescription {
+    /// Version of PyOxidizer providing the API.
+    pub version: String,
+    pub functions: Vec<FunctionDescription>,
+    pub variables: Vec<AttributeDescription>,
+    pub types: Vec<TypeDescription>,
+}
+
+/// Stands in for instances of `T` when looking up type values.
+///
+/// Methods are registered per type name, so looking them up requires a
+/// value of the type. Instances of most types can't be created without
+/// building something.
+struct TypeProbe<T>(PhantomData<fn() -> T>);
+
+impl<T: TypedValue> TypedValue for TypeProbe<T> {
+    type Holder = Immutable<TypeProbe<T>>;
+    const TYPE: &'static str = T::TYPE;
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+}
+
+/// Split the parameters of a function signature on top-level commas.
+fn split_parameters(s: &str) -> Vec<String> {
+    let mut parameters = vec![];
+    let mut current = String::new();
+    let mut depth = 0;
+    let mut quote = None;
+    let mut escaped = false;
+
+    for c in s.chars() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+        } else {
+            match c {
+                '"' | '\'' => quote = Some(c),
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth -= 1,
+                ',' if depth == 0 => {
+                    parameters.push(current.trim().to_string());
+                    current.clear();
+                    continue;
+                }
+                _ => {}
+            }
+        }
+
+        current.push(c);
+    }
+
+    if !current.trim().is_empty() {
+        parameters.push(current.trim().to_string());
+    }
+
+    parameters
+}
+
+/// Parse the string form of a native function, e.g. `f(a, ?b, c = 1, *args)`.
+fn parse_signature(signature: &str) -> Result<FunctionDescription> {
+    let open = signature
+        .find('(')
+        .ok_or_else(|| anyhow!("malformed function signature: {}", signature))?;
+    if !signature.ends_with(')') {
+        return Err(anyhow!("malformed function signature: {}", signature));
+    }
+
+    let parameters = split_parameters(&signature[open + 1..signature.len() - 1])
+        .into_iter()
+        // Marks preceding parameters as positional-only.
+        .filter(|p| p != "/")
+        .map(|p| {
+            if let Some(name) = p.strip_prefix("**") {
+                ParameterDescription {
+                    name: name.to_string(),
+                    kind: ParameterKind::Kwargs,
+                    required: false,
+                    default: None,
+                }
+            } else if let Some(name) = p.strip_prefix('*') {
+                ParameterDescription {
+                    name: name.to_string(),
+                    kind: ParameterKind::Args,
+                    required: false,
+                    default: None,
+                }
+            } else if let Some(name) = p.strip_prefix('?') {
+                ParameterDescription {
+                    name: name.to_string(),
+                    kind: ParameterKind::Normal,
+                    required: false,
+                    default: None,
+                }
+            } else if let Some(pos) = p.find(" = ") {
+                ParameterDescription {
+                    name: p[..pos].to_string(),
+                    kind: ParameterKind::Normal,
+                    required: false,
+                    default: Some(p[pos + 3..].to_string()),
+                }
+            } else {
+                ParameterDescription {
+                    name: p,
+                    kind: ParameterKind::Normal,
+                    required: true,
+                    default: None,
+                }
+            }
+        })
+        .collect();
+
+    Ok(FunctionDescription {
+        name: signature[..open].to_string(),
+        parameters,
+    })
+}
+
+fn describe_attributes(attributes: &[&[(&str, &str)]]) -> Vec<AttributeDescription> {
+    attributes
+        .iter()
+        .flat_map(|attributes| attributes.iter())
+        .map(|(name, value_type)| AttributeDescription {
+            name: name.to_string(),
+            value_type: value_type.to_string(),
+        })
+        .collect()
+}
+
+/// Describe a type from the methods registered for it.
+fn describe_type<T: TypedValue>(
+    type_values: &TypeValues,
+    attributes: &[&[(&str, &str)]],
+) -> Result<TypeDescription> {
+    let probe = Value::new(TypeProbe::<T>(PhantomData));
+
+    let mut names = type_values.list_type_value(&probe);
+    names.sort();
+
+    let mut methods = vec![];
+    for name in names {
+        let method = type_values
+            .get_type_value(&probe, &name)
+            .ok_or_else(|| anyhow!("unable to resolve {}.{}", T::TYPE, name))?;
+
+        let mut method = parse_signature(&method.to_str())?;
+        // The first parameter receives the instance the method is called on.
+        if !method.parameters.is_empty() {
+            method.parameters.remove(0);
+        }
+
+        methods.push(method);
+    }
+
+    Ok(TypeDescription {
+        name: T::TYPE.to_string(),
+        attributes: describe_attributes(attributes),
+        methods,
+    })
+}
+
+/// Describe the Starlark API of an environment.
+pub fn describe_api(env: &Environment, type_values: &TypeValues) -> Result<ApiDescription> {
+    let functions = GLOBAL_FUNCTIONS
+        .iter()
+        .map(|name| {
+            let value = env
+                .get(name)
+                .map_err(|_| anyhow!("global function {} is not defined", name))?;
+
+            parse_signature(&value.to_str())
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let resource = ADD_COLLECTION_CONTEXT_ATTRIBUTES;
+
+    let types = vec![
+        describe_type::<CodeSigningCertificateValue>(
+            type_values,
+            &[CODE_SIGNING_CERTIFICATE_ATTRIBUTES],
+        )?,
+        describe_type::<FileValue>(type_values, &[FILE_ATTRIBUTES, resource])?,
+        describe_type::<FileContentValue>(type_values, &[])?,
+        describe_type::<FileManifestValue>(type_values, &[])?,
+        describe_type::<GettextCatalogValue>(type_values, &[GETTEXT_CATALOG_ATTRIBUTES, resource])?,
+        describe_type::<MacOsPkgBuilderValue>(type_values, &[])?,
+        describe_type::<PackageManagerManifestsValue>(type_values, &[])?,
+        describe_type::<PythonDistributionValue>(type_values, &[])?,
+        describe_type::<PythonEmbeddedResources>(type_values, &[])?,
+        describe_type::<PythonExecutable>(type_values, &[PYTHON_EXECUTABLE_ATTRIBUTES])?,
+        describe_type::<PythonExtensionModuleValue>(
+            type_values,
+            &[PYTHON_EXTENSION_MODULE_ATTRIBUTES, resource],
+        )?,
+        describe_type::<PythonInterpreterConfigValue>(
+            type_values,
+            &[PYTHON_INTERPRETER_CONFIG_ATTRIBUTES],
+        )?,
+        describe_type::<PythonLibrary>(type_values, &[])?,
+        describe_type::<PythonModuleSourceValue>(
+            type_values,
+            &[PYTHON_MODULE_SOURCE_ATTRIBUTES, resource],
+        )?,
+        describe_type::<PythonPackageDistributionResourceValue>(
+            type_values,
+            &[PYTHON_PACKAGE_DISTRIBUTION_RESOURCE_ATTRIBUTES, resource],
+        )?,
+        describe_type::<PythonPackageResourceValue>(
+            type_values,
+            &[PYTHON_PACKAGE_RESOURCE_ATTRIBUTES, resource],
+        )?,
+        describe_type::<PythonPackagingPolicyValue>(
+            type_values,
+            &[PYTHON_PACKAGING_POLICY_ATTRIBUTES],
+        )?,
+        describe_type::<PythonResourcesSnapshot>(type_values, &[])?,
+        describe_type::<PythonWasiBundle>(type_values, &[])?,
+        describe_type::<Release>(type_values, &[RELEASE_ATTRIBUTES])?,
+        describe_type::<ReleasePatch>(type_values, &[])?,
+        describe_type::<ResourceCallbackContextValue>(
+            type_values,
+            &[RESOURCE_CALLBACK_CONTEXT_ATTRIBUTES],
+        )?,
+        describe_type::<SecretValue>(type_values, &[])?,
+    ];
+
+    Ok(ApiDescription {
+        version: crate::environment::PYOXIDIZER_VERSION.to_string(),
+        functions,
+        variables: describe_attributes(&[GLOBAL_VARIABLES]),
+        types,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::super::testutil::*, super::*};
+
+    #[test]
+    fn test_parse_signature() -> Result<()> {
+        let f = parse_signature("f(this, /, a, ?b, c = \"x, y\", d = None, *args, **kwargs)")?;
+
+        assert_eq!(f.name, "f");
+        assert_eq!(
+            f.parameters
+                .iter()
+                .map(|p| (p.name.as_str(), p.kind, p.required, p.default.as_deref()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("this", ParameterKind::Normal, true, None),
+                ("a", ParameterKind::Normal, true, None),
+                ("b", ParameterKind::Normal, false, None),
+                ("c", ParameterKind::Normal, false, Some("\"x, y\"")),
+                ("d", ParameterKind::Normal, false, Some("None")),
+                ("args", ParameterKind::Args, false, None),
+                ("kwargs", ParameterKind::Kwargs, false, None),
+            ]
+        );
+
+        assert_eq!(parse_signature("g()")?.parameters, vec![]);
+        assert!(parse_signature("g").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_describe_api() -> Result<()> {
+        let env = StarlarkEnvironment::new()?;
+        let api = describe_api(&env.env, &env.type_values)?;
+
+        let register_target = api
+            .functions
+            .iter()
+            .find(|f| f.name == "register_target")
+            .unwrap();
+        assert_eq!(register_target.parameters[0].name, "target");
+        assert!(register_target.parameters[0].required);
+
+        let exe = api
+            .types
+            .iter()
+            .find(|t| t.name == "PythonExecutable")
+            .unwrap();
+        let pip_install = exe
+            .methods
+            .iter()
+            .find(|m| m.name == "pip_install")
+            .unwrap();
+        assert_eq!(
+            pip_install
+                .parameters
+                .iter()
+                .map(|p| p.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["args", "extra_envs"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_attributes_exist() -> Result<()> {
+        let mut env = StarlarkEnvironment::new_with_exe()?;
+        env.eval("config = dist.make_python_interpreter_config()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+
+        for (variable, attributes) in &[
+            ("exe", PYTHON_EXECUTABLE_ATTRIBUTES),
+            ("config", PYTHON_INTERPRETER_CONFIG_ATTRIBUTES),
+            ("policy", PYTHON_PACKAGING_POLICY_ATTRIBUTES),
+        ] {
+            let value = env.eval(variable)?;
+
+            for (name, _) in attributes.iter() {
+                assert!(
+                    value.has_attr(name).unwrap(),
+                    "{}.{} exists",
+                    variable,
+                    name
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Collect the names matched by `has_attr()` of the types defined in a source file.
+    fn has_attr_names(source: &str) -> Vec<(String, Vec<String>)> {
+        let mut res = vec![];
+        let mut type_name = None;
+        let mut names = None;
+
+        for line in source.lines() {
+            let line = line.trim();
+
+            if let Some(name) = line.strip_prefix("const TYPE: &'static str = \"") {
+                type_name = Some(name.trim_end_matches("\";").to_string());
+            } else if line.starts_with("fn has_attr(") {
+                names = Some(vec![]);
+            } else if let Some(names) = names.as_mut() {
+                if line == "}" {
+                    res.push((type_name.clone().unwrap(), std::mem::take(names)));
+                } else if !line.starts_with("//") {
+                    names.extend(
+                        line.split('"')
+                            .skip(1)
+                            .step_by(2)
+                            .map(|name| name.to_string()),
+                    );
+                }
+            }
+
+            if line == "}" {
+                names = None;
+            }
+        }
+
+        res
+    }
+
+    #[test]
+    fn test_attributes_listed() -> Result<()> {
+        let env = StarlarkEnvironment::new()?;
+        let api = describe_api(&env.env, &env.type_values)?;
+
+        let sources = [
+            include_str!("python_executable.rs"),
+            include_str!("python_interpreter_config.rs"),
+            include_str!("python_packaging_policy.rs"),
+            include_str!("python_resource.rs"),
+            include_str!("release.rs"),
+        ];
+
+        let mut checked = 0;
+        for (type_name, names) in sources.iter().flat_map(|s| has_attr_names(s)) {
+            let description = api
+                .types
+                .iter()
+                .find(|t| t.name == type_name)
+                .unwrap_or_else(|| panic!("{} is described", type_name));
+
+            for name in names {
+                assert!(
+                    description.attributes.iter().any(|a| a.name == name),
+                    "{}.{} is listed",
+                    type_name,
+                    name
+                );
+            }
+
+            checked += 1;
+        }
+        assert_eq!(checked, 11);
+
+        Ok(())
+    }
+}
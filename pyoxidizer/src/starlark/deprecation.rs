@@ -0,0 +1,431 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Deprecated parts of the Starlark API.
+
+Deprecated functions, methods and attributes keep working. Uses are recorded
+while a configuration file is evaluated and reported as warnings afterwards,
+naming the version the item was deprecated in and its replacement.
+
+To deprecate an item, add it to `DEPRECATIONS`. Implementations of
+deprecated functions and methods call `record_deprecated_call()`.
+`TypedValue` implementations with deprecated attributes pass attribute
+names through `resolve_attribute()` in `get_attr()` and `set_attr()` and
+accept the deprecated names in `has_attr()`.
+
+Deprecated items having a drop-in replacement can be rewritten
+mechanically by `pyoxidizer migrate-config`.
+*/
+
+use std::cell::RefCell;
+
+/// The kind of a deprecated item.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DeprecatedItem {
+    /// A global function.
+    Function,
+    /// A method of a type.
+    Method,
+    /// An attribute of a type.
+    Attribute,
+}
+
+/// Describes a deprecated part of the Starlark API.
+#[derive(Debug, PartialEq)]
+pub struct Deprecation {
+    /// Type the item belongs to. Empty for global functions.
+    pub type_name: &'static str,
+
+    /// Name of the deprecated item.
+    pub name: &'static str,
+
+    /// What kind of item is deprecated.
+    pub item: DeprecatedItem,
+
+    /// PyOxidizer version the item was deprecated in.
+    pub since: &'static str,
+
+    /// Name of an item with the same behavior to use instead.
+    ///
+    /// Uses of items without a replacement must be migrated by hand.
+    pub replacement: Option<&'static str>,
+}
+
+impl Deprecation {
+    /// The qualified name of the deprecated item.
+    pub fn qualified_name(&self) -> String {
+        qualify(self.type_name, self.name)
+    }
+
+    /// A warning message describing the deprecation.
+    pub fn message(&self) -> String {
+        match self.replacement {
+            Some(replacement) => format!(
+                "{} is deprecated since {}; use {} instead",
+                self.qualified_name(),
+                self.since,
+                qualify(self.type_name, replacement)
+            ),
+            None => format!(
+                "{} is deprecated since {} and will be removed",
+                self.qualified_name(),
+                self.since
+            ),
+        }
+    }
+}
+
+fn qualify(type_name: &str, name: &str) -> String {
+    if type_name.is_empty() {
+        format!("{}()", name)
+    } else {
+        format!("{}.{}", type_name, name)
+    }
+}
+
+/// Deprecated parts of the Starlark API.
+pub const DEPRECATIONS: &[Deprecation] = &[];
+
+thread_local! {
+    /// Deprecated items used since the last call to `take_deprecated_uses()`.
+    static DEPRECATED_USES: RefCell<Vec<&'static Deprecation>> = const { RefCell::new(Vec::new()) };
+}
+
+fn find_in(
+    deprecations: &'static [Deprecation],
+    type_name: &str,
+    name: &str,
+    item: DeprecatedItem,
+) -> Option<&'static Deprecation> {
+    deprecations
+        .iter()
+        .find(|d| d.type_name == type_name && d.name == name && d.item == item)
+}
+
+/// Record a use of a deprecated item.
+pub fn record_deprecated_use(deprecation: &'static Deprecation) {
+    DEPRECATED_USES.with(|uses| {
+        let mut uses = uses.borrow_mut();
+
+        if !uses.contains(&deprecation) {
+            uses.push(deprecation);
+        }
+    });
+}
+
+/// Obtain and clear the deprecated items used on this thread.
+pub fn take_deprecated_uses() -> Vec<&'static Deprecation> {
+    DEPRECATED_USES.with(|uses| uses.replace(vec![]))
+}
+
+fn resolve_attribute_in<'a>(
+    deprecations: &'static [Deprecation],
+    type_name: &str,
+    attribute: &'a str,
+) -> &'a str {
+    match find_in(
+        deprecations,
+        type_name,
+        attribute,
+        DeprecatedItem::Attribute,
+    ) {
+        Some(deprecation) => {
+            record_deprecated_use(deprecation);
+            deprecation.replacement.unwrap_or(attribute)
+        }
+        None => attribute,
+    }
+}
+
+/// Resolve the name of an attribute being accessed.
+///
+/// Deprecated attribute names are recorded as used and mapped to their
+/// replacement. Other names are returned unchanged.
+#[allow(unused)]
+pub fn resolve_attribute<'a>(type_name: &str, attribute: &'a str) -> &'a str {
+    resolve_attribute_in(DEPRECATIONS, type_name, attribute)
+}
+
+fn record_deprecated_call_in(deprecations: &'static [Deprecation], type_name: &str, name: &str) {
+    let item = if type_name.is_empty() {
+        DeprecatedItem::Function
+    } else {
+        DeprecatedItem::Method
+    };
+
+    if let Some(deprecation) = find_in(deprecations, type_name, name, item) {
+        record_deprecated_use(deprecation);
+    }
+}
+
+/// Record a call of a function or method, if it is deprecated.
+///
+/// `type_name` is empty for global functions. Calls of items that aren't
+/// deprecated are ignored.
+#[allow(unused)]
+pub fn record_deprecated_call(type_name: &str, name: &str) {
+    record_deprecated_call_in(DEPRECATIONS, type_name, name)
+}
+
+/// Uses of deprecated items found in configuration file source code.
+#[derive(Debug, Default, PartialEq)]
+pub struct Migration {
+    /// The rewritten source code.
+    pub source: String,
+
+    /// Items that were rewritten, with the 1-based line of each use.
+    pub rewritten: Vec<(usize, &'static Deprecation)>,
+
+    /// Items without replacement, with the 1-based line of each use.
+    pub manual: Vec<(usize, &'static Deprecation)>,
+}
+
+/// Find the deprecation matching an identifier in source code.
+///
+/// Source code isn't typed, so items of types are matched by name alone.
+fn find_source_deprecation(
+    deprecations: &'static [Deprecation],
+    name: &str,
+    after_dot: bool,
+) -> Option<&'static Deprecation> {
+    deprecations.iter().find(|d| {
+        d.name == name
+            && match d.item {
+                DeprecatedItem::Function => !after_dot,
+                DeprecatedItem::Method | DeprecatedItem::Attribute => after_dot,
+            }
+    })
+}
+
+/// Rewrite uses of deprecated items in configuration file source code.
+///
+/// Identifiers in string literals and comments are left alone.
+pub fn migrate_source(source: &str) -> Migration {
+    migrate_source_in(DEPRECATIONS, source)
+}
+
+fn migrate_source_in(deprecations: &'static [Deprecation], source: &str) -> Migration {
+    let mut migration = Migration::default();
+    let chars = source.chars().collect::<Vec<_>>();
+    let mut line = 1;
+    let mut i = 0;
+    // The last character outside of whitespace, strings and comments.
+    let mut previous = None;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c == '#' {
+            while i < chars.len() && chars[i] != '\n' {
+                migration.source.push(chars[i]);
+                i += 1;
+            }
+        } else if c == '"' || c == '\'' {
+            let triple = chars.get(i + 1) == Some(&c) && chars.get(i + 2) == Some(&c);
+            let quote_len = if triple { 3 } else { 1 };
+
+            for _ in 0..quote_len {
+                migration.source.push(chars[i]);
+                i += 1;
+            }
+
+            while i < chars.len() {
+                if chars[i] == '\\' && i + 1 < chars.len() {
+                    if chars[i + 1] == '\n' {
+                        line += 1;
+                    }
+                    migration.source.push(chars[i]);
+                    migration.source.push(chars[i + 1]);
+                    i += 2;
+                    continue;
+                }
+
+                if chars[i] == c && (!triple || chars[i..].starts_with(&[c, c, c])) {
+                    for _ in 0..quote_len {
+                        migration.source.push(chars[i]);
+                        i += 1;
+                    }
+                    break;
+                }
+
+                if chars[i] == '\n' {
+                    line += 1;
+                }
+                migration.source.push(chars[i]);
+                i += 1;
+            }
+
+            previous = Some(c);
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let name = chars[start..i].iter().collect::<String>();
+
+            match find_source_deprecation(deprecations, &name, previous == Some('.')) {
+                Some(deprecation) => match deprecation.replacement {
+                    Some(replacement) => {
+                        migration.source.push_str(replacement);
+                        migration.rewritten.push((line, deprecation));
+                    }
+                    None => {
+                        migration.source.push_str(&name);
+                        migration.manual.push((line, deprecation));
+                    }
+                },
+                None => migration.source.push_str(&name),
+            }
+
+            previous = Some('a');
+        } else {
+            if c == '\n' {
+                line += 1;
+            }
+            if !c.is_whitespace() {
+                previous = Some(c);
+            }
+            migration.source.push(c);
+            i += 1;
+        }
+    }
+
+    migration
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_DEPRECATIONS: &[Deprecation] = &[
+        Deprecation {
+            type_name: "Foo",
+            name: "old_attr",
+            item: DeprecatedItem::Attribute,
+            since: "0.10.0",
+            replacement: Some("new_attr"),
+        },
+        Deprecation {
+            type_name: "Foo",
+            name: "old_method",
+            item: DeprecatedItem::Method,
+            since: "0.10.0",
+            replacement: Some("new_method"),
+        },
+        Deprecation {
+            type_name: "",
+            name: "old_function",
+            item: DeprecatedItem::Function,
+            since: "0.10.0",
+            replacement: None,
+        },
+    ];
+
+    #[test]
+    fn test_resolve_attribute() {
+        take_deprecated_uses();
+
+        assert_eq!(
+            resolve_attribute_in(TEST_DEPRECATIONS, "Foo", "new_attr"),
+            "new_attr"
+        );
+        assert_eq!(
+            resolve_attribute_in(TEST_DEPRECATIONS, "Bar", "old_attr"),
+            "old_attr"
+        );
+        assert_eq!(
+            resolve_attribute_in(TEST_DEPRECATIONS, "Foo", "old_method"),
+            "old_method"
+        );
+        assert!(take_deprecated_uses().is_empty());
+
+        assert_eq!(
+            resolve_attribute_in(TEST_DEPRECATIONS, "Foo", "old_attr"),
+            "new_attr"
+        );
+        assert_eq!(
+            resolve_attribute_in(TEST_DEPRECATIONS, "Foo", "old_attr"),
+            "new_attr"
+        );
+        let uses = take_deprecated_uses();
+        assert_eq!(uses.len(), 1);
+        assert_eq!(
+            uses[0].message(),
+            "Foo.old_attr is deprecated since 0.10.0; use Foo.new_attr instead"
+        );
+    }
+
+    #[test]
+    fn test_record_deprecated_call() {
+        take_deprecated_uses();
+
+        record_deprecated_call_in(TEST_DEPRECATIONS, "Foo", "new_method");
+        record_deprecated_call_in(TEST_DEPRECATIONS, "Foo", "old_attr");
+        record_deprecated_call_in(TEST_DEPRECATIONS, "Foo", "old_function");
+        assert!(take_deprecated_uses().is_empty());
+
+        record_deprecated_call_in(TEST_DEPRECATIONS, "Foo", "old_method");
+        record_deprecated_call_in(TEST_DEPRECATIONS, "", "old_function");
+        let uses = take_deprecated_uses();
+        assert_eq!(
+            uses.iter().map(|d| d.message()).collect::<Vec<_>>(),
+            vec![
+                "Foo.old_method is deprecated since 0.10.0; use Foo.new_method instead",
+                "old_function() is deprecated since 0.10.0 and will be removed",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_migrate_source() {
+        let source = "\
+def make_config(dist):
+    # config.old_attr is set below
+    config = dist.make_python_interpreter_config()
+    config.old_attr = \"old_attr\"
+    print('''config.old_attr
+''', config . old_attr)
+    old_attr = config.old_method()
+    old_function()
+";
+
+        let migration = migrate_source_in(TEST_DEPRECATIONS, source);
+
+        assert_eq!(
+            migration.source,
+            "\
+def make_config(dist):
+    # config.old_attr is set below
+    config = dist.make_python_interpreter_config()
+    config.new_attr = \"old_attr\"
+    print('''config.old_attr
+''', config . new_attr)
+    old_attr = config.new_method()
+    old_function()
+"
+        );
+        assert_eq!(
+            migration
+                .rewritten
+                .iter()
+                .map(|(line, _)| *line)
+                .collect::<Vec<_>>(),
+            vec![4, 6, 7]
+        );
+        assert_eq!(
+            migration
+                .manual
+                .iter()
+                .map(|(line, d)| (*line, d.name))
+                .collect::<Vec<_>>(),
+            vec![(8, "old_function")]
+        );
+
+        assert_eq!(
+            migrate_source_in(TEST_DEPRECATIONS, "x = 'a\\'b'\n").source,
+            "x = 'a\\'b'\n"
+        );
+    }
+}
@@ -3,19 +3,29 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use {
-    crate::py_packaging::distribution::DistributionCache,
+    crate::{
+        build_layout::BuildLayout, environment, py_packaging::distribution::DistributionCache,
+        trust::TrustPolicy,
+    },
     anyhow::{Context, Result},
     starlark::{
         environment::{Environment, EnvironmentError, TypeValues},
         values::{
             error::{RuntimeError, ValueError},
+            none::NoneType,
             {Mutable, TypedValue, Value, ValueResult},
         },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
     },
     starlark_dialect_build_targets::{
-        build_targets_module, get_context_value, BuildContext, EnvironmentContext, GetStateError,
+        build_targets_module, get_context_value, optional_bool_arg, optional_str_arg, BuildContext,
+        EnvironmentContext, GetStateError,
     },
     std::{
+        collections::BTreeMap,
         path::{Path, PathBuf},
         sync::Arc,
     },
@@ -49,11 +59,35 @@ pub struct PyOxidizerEnvironmentContext {
     /// Optimization level when building binaries.
     pub build_opt_level: String,
 
+    /// Whether to evaluate without building anything.
+    ///
+    /// When set, operations that would compile binaries or install Python
+    /// packages are skipped and Python distributions aren't extracted to
+    /// their extraction directory.
+    pub dry_run: bool,
+
+    /// Pattern of resource names whose packaging decisions should be logged.
+    pub trace_resources: Option<glob::Pattern>,
+
+    /// Whether resources snapshots are written instead of compared.
+    pub update_snapshots: bool,
+
     /// Cache of ready-to-clone Python distribution objects.
     ///
     /// This exists because constructing a new instance can take a
     /// few seconds in debug builds. And this adds up, especially in tests!
     pub distribution_cache: Arc<DistributionCache>,
+
+    /// Items the configuration file may execute, if restricted by a trust file.
+    pub trust: Option<TrustPolicy>,
+
+    /// Directories of applications registered by a workspace configuration file.
+    pub apps: BTreeMap<String, PathBuf>,
+
+    /// Directory holding caches shared by the applications of a workspace.
+    ///
+    /// If `None`, caches are kept in the build path.
+    pub shared_cache_path: Option<PathBuf>,
 }
 
 impl PyOxidizerEnvironmentContext {
@@ -81,6 +115,8 @@ impl PyOxidizerEnvironmentContext {
         let distribution_cache =
             distribution_cache.unwrap_or_else(|| Arc::new(DistributionCache::new(None)));
 
+        let trust = TrustPolicy::for_project(&parent)?;
+
         Ok(PyOxidizerEnvironmentContext {
             logger: logger.clone(),
             verbose,
@@ -90,7 +126,13 @@ impl PyOxidizerEnvironmentContext {
             build_target_triple: build_target_triple.to_string(),
             build_release,
             build_opt_level: build_opt_level.to_string(),
+            dry_run: false,
+            trace_resources: None,
+            update_snapshots: false,
             distribution_cache,
+            trust,
+            apps: BTreeMap::new(),
+            shared_cache_path: None,
         })
     }
 
@@ -98,6 +140,33 @@ impl PyOxidizerEnvironmentContext {
         &self.logger
     }
 
+    /// Whether packaging decisions for a named resource should be logged.
+    pub fn trace_resource(&self, name: &str) -> bool {
+        if let Some(pattern) = &self.trace_resources {
+            pattern.matches(name)
+        } else {
+            false
+        }
+    }
+
+    /// Check an action against the trust policy of the project, if any.
+    pub fn check_trust(
+        &self,
+        label: &str,
+        check: impl FnOnce(&TrustPolicy) -> Result<()>,
+    ) -> Result<(), ValueError> {
+        match &self.trust {
+            Some(trust) => check(trust).map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: "UNTRUSTED",
+                    message: format!("{:#}", e),
+                    label: label.to_string(),
+                })
+            }),
+            None => Ok(()),
+        }
+    }
+
     pub fn build_path(&self, type_values: &TypeValues) -> Result<PathBuf, ValueError> {
         let build_targets_context_value = get_context_value(type_values)?;
         let context = build_targets_context_value
@@ -107,11 +176,25 @@ impl PyOxidizerEnvironmentContext {
         Ok(context.build_path().to_path_buf())
     }
 
+    /// Layout of the build directory, for the target triple and profile built.
+    pub fn build_layout(&self, type_values: &TypeValues) -> Result<BuildLayout, ValueError> {
+        Ok(BuildLayout::new(
+            &self.build_path(type_values)?,
+            &self.build_target_triple,
+            self.build_release,
+        ))
+    }
+
     pub fn python_distributions_path(
         &self,
         type_values: &TypeValues,
     ) -> Result<PathBuf, ValueError> {
-        Ok(self.build_path(type_values)?.join("python_distributions"))
+        let cache_path = match &self.shared_cache_path {
+            Some(path) => path.clone(),
+            None => self.build_path(type_values)?,
+        };
+
+        Ok(cache_path.join("python_distributions"))
     }
 }
 
@@ -143,6 +226,7 @@ impl TypedValue for PyOxidizerContext {
 }
 
 /// Holds the build context for PyOxidizer's Starlark types.
+#[derive(Clone)]
 pub struct PyOxidizerBuildContext {
     /// Logger where messages can be written.
     pub logger: slog::Logger,
@@ -207,11 +291,158 @@ pub fn get_context(type_values: &TypeValues) -> ValueResult {
         })
 }
 
+/// set_temp_dir(path)
+fn starlark_set_temp_dir(type_values: &TypeValues, path: String) -> ValueResult {
+    let pyoxidizer_context_value = get_context(type_values)?;
+    let pyoxidizer_context = pyoxidizer_context_value
+        .downcast_ref::<PyOxidizerEnvironmentContext>()
+        .ok_or(ValueError::IncorrectParameterType)?;
+
+    let path = pyoxidizer_context.cwd.join(path);
+
+    environment::set_temp_dir(&path)
+        .and_then(|_| {
+            environment::check_disk_space(
+                &path,
+                environment::TEMP_DIR_REQUIRED_SPACE,
+                "the temporary directory",
+            )
+        })
+        .map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_BUILD",
+                message: format!("{:#}", e),
+                label: "set_temp_dir()".to_string(),
+            })
+        })?;
+
+    Ok(Value::new(NoneType::None))
+}
+
+/// register_app(name, path)
+fn starlark_register_app(type_values: &TypeValues, name: String, path: String) -> ValueResult {
+    let pyoxidizer_context_value = get_context(type_values)?;
+    let mut pyoxidizer_context = pyoxidizer_context_value
+        .downcast_mut::<PyOxidizerEnvironmentContext>()?
+        .ok_or(ValueError::IncorrectParameterType)?;
+
+    if pyoxidizer_context.apps.contains_key(&name) {
+        return Err(ValueError::from(RuntimeError {
+            code: "PYOXIDIZER_BUILD",
+            message: format!("application {} is already registered", name),
+            label: "register_app()".to_string(),
+        }));
+    }
+
+    let path = pyoxidizer_context.cwd.join(path);
+    pyoxidizer_context.apps.insert(name, path);
+
+    Ok(Value::new(NoneType::None))
+}
+
+/// target_output_path(target, target_triple=None, release=None)
+fn starlark_target_output_path(
+    type_values: &TypeValues,
+    target: String,
+    target_triple: &Value,
+    release: &Value,
+) -> ValueResult {
+    let target_triple = optional_str_arg("target_triple", target_triple)?;
+    let release = optional_bool_arg("release", release)?;
+
+    let pyoxidizer_context_value = get_context(type_values)?;
+    let pyoxidizer_context = pyoxidizer_context_value
+        .downcast_ref::<PyOxidizerEnvironmentContext>()
+        .ok_or(ValueError::IncorrectParameterType)?;
+
+    let layout = BuildLayout::new(
+        &pyoxidizer_context.build_path(type_values)?,
+        target_triple
+            .as_deref()
+            .unwrap_or(&pyoxidizer_context.build_target_triple),
+        release.unwrap_or(pyoxidizer_context.build_release),
+    );
+
+    Ok(Value::from(
+        layout.target_output_path(&target).display().to_string(),
+    ))
+}
+
+/// vcs_version(path=None, tag_prefix="v", numeric=False)
+fn starlark_vcs_version(
+    type_values: &TypeValues,
+    path: &Value,
+    tag_prefix: String,
+    numeric: bool,
+) -> ValueResult {
+    let path = optional_str_arg("path", path)?;
+
+    let pyoxidizer_context_value = get_context(type_values)?;
+    let pyoxidizer_context = pyoxidizer_context_value
+        .downcast_ref::<PyOxidizerEnvironmentContext>()
+        .ok_or(ValueError::IncorrectParameterType)?;
+
+    let path = match path {
+        Some(path) => pyoxidizer_context.cwd.join(path),
+        None => pyoxidizer_context.cwd.clone(),
+    };
+
+    let version = environment::vcs_version(&path, &tag_prefix)
+        .and_then(|version| {
+            if numeric {
+                environment::numeric_version(&version)
+            } else {
+                Ok(version)
+            }
+        })
+        .map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_BUILD",
+                message: format!("{:#}", e),
+                label: "vcs_version()".to_string(),
+            })
+        })?;
+
+    Ok(Value::from(version))
+}
+
+starlark_module! { env_module =>
+    register_app(env env, name: String, path: String) {
+        starlark_register_app(&env, name, path)
+    }
+
+    set_temp_dir(env env, path: String) {
+        starlark_set_temp_dir(&env, path)
+    }
+
+    target_output_path(
+        env env,
+        target: String,
+        target_triple = NoneType::None,
+        release = NoneType::None
+    ) {
+        starlark_target_output_path(&env, target, &target_triple, &release)
+    }
+
+    vcs_version(
+        env env,
+        path = NoneType::None,
+        tag_prefix: String = "v".to_string(),
+        numeric: bool = false
+    ) {
+        starlark_vcs_version(&env, &path, tag_prefix, numeric)
+    }
+}
+
 /// Obtain a Starlark environment for evaluating PyOxidizer configurations.
+///
+/// If `parent` is defined, the environment is a child of it, so globals
+/// defined by a workspace configuration file are visible to applications.
 pub fn global_environment(
     context: PyOxidizerEnvironmentContext,
     resolve_targets: Option<Vec<String>>,
     build_script_mode: bool,
+    parent: Option<&Environment>,
 ) -> Result<(Environment, TypeValues), EnvironmentError> {
     let mut build_targets_context = EnvironmentContext::new(context.logger(), context.cwd.clone());
 
@@ -221,7 +452,11 @@ pub fn global_environment(
 
     build_targets_context.build_script_mode = build_script_mode;
 
-    let (mut env, mut type_values) = starlark::stdlib::global_environment();
+    let (env, mut type_values) = starlark::stdlib::global_environment();
+    let mut env = match parent {
+        Some(parent) => parent.child(&context.config_path.display().to_string()),
+        None => env,
+    };
 
     starlark_dialect_build_targets::populate_environment(
         &mut env,
@@ -230,11 +465,15 @@ pub fn global_environment(
     )?;
 
     build_targets_module(&mut env, &mut type_values);
+    env_module(&mut env, &mut type_values);
     tugger::starlark::populate_environment(&mut env, &mut type_values)?;
     super::file_resource::file_resource_env(&mut env, &mut type_values);
     super::python_distribution::python_distribution_module(&mut env, &mut type_values);
     super::python_executable::python_executable_env(&mut env, &mut type_values);
+    super::python_interpreter_config::python_interpreter_config_module(&mut env, &mut type_values);
     super::python_packaging_policy::python_packaging_policy_module(&mut env, &mut type_values);
+    super::release::release_module(&mut env, &mut type_values);
+    super::structured_data::structured_data_module(&mut env, &mut type_values);
 
     env.set("CWD", Value::from(context.cwd.display().to_string()))?;
     env.set(
@@ -280,4 +519,74 @@ pub mod tests {
     fn test_print() {
         starlark_ok("print('hello, world')");
     }
+
+    #[test]
+    fn test_register_app() -> anyhow::Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+        env.eval("register_app('app1', 'apps/app1')")?;
+        assert!(env.eval("register_app('app1', 'apps/other')").is_err());
+
+        let context_value = super::get_context(&env.type_values).unwrap();
+        let context = context_value
+            .downcast_ref::<super::PyOxidizerEnvironmentContext>()
+            .unwrap();
+        assert_eq!(
+            context.apps.get("app1"),
+            Some(&std::env::current_dir()?.join("apps/app1"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_target_output_path() -> anyhow::Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        let value = env.eval("target_output_path('exe')")?;
+        assert!(std::path::Path::new(&value.to_str())
+            .ends_with(std::path::Path::new(crate::project_building::HOST).join("debug/exe")));
+
+        let value = env.eval(
+            "target_output_path('msi', target_triple = 'x86_64-pc-windows-msvc', release = True)",
+        )?;
+        assert!(
+            std::path::Path::new(&value.to_str()).ends_with("x86_64-pc-windows-msvc/release/msi")
+        );
+
+        assert!(env
+            .eval("target_output_path('exe', release = 'yes')")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_vcs_version() {
+        starlark_nok("vcs_version(tag_prefix = 'no-such-tag-prefix-')");
+        starlark_nok("vcs_version(tag_prefix = 'no-such-tag-prefix-', numeric = True)");
+    }
+
+    #[test]
+    fn test_trace_resource() -> anyhow::Result<()> {
+        let logger = slog::Logger::root(slog::Discard, slog::o!());
+        let mut context = super::PyOxidizerEnvironmentContext::new(
+            &logger,
+            false,
+            &std::env::current_dir()?.join("dummy"),
+            crate::project_building::HOST,
+            crate::project_building::HOST,
+            false,
+            "0",
+            None,
+        )?;
+
+        assert!(!context.trace_resource("foo"));
+
+        context.trace_resources = Some(glob::Pattern::new("foo.*")?);
+        assert!(context.trace_resource("foo.bar"));
+        assert!(!context.trace_resource("foo"));
+        assert!(!context.trace_resource("bar.foo"));
+
+        Ok(())
+    }
 }
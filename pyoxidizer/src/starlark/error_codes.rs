@@ -0,0 +1,267 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Explanations of error codes reported when evaluating configuration files.
+
+Errors raised by Starlark functions and methods carry a code, which is
+printed next to the error message (e.g. `error[PYOXIDIZER_BUILD]`).
+`pyoxidizer --explain <CODE>` prints the explanation of a code.
+*/
+
+/// Describes an error code.
+pub struct ErrorCode {
+    /// The code, as printed in error messages.
+    pub code: &'static str,
+
+    /// One line summary of what the code means.
+    pub summary: &'static str,
+
+    /// What causes the error and how to fix it.
+    pub explanation: &'static str,
+}
+
+/// Error codes with explanations.
+pub const ERROR_CODES: &[ErrorCode] = &[
+    ErrorCode {
+        code: "BUILD_TARGETS",
+        summary: "a target could not be registered or resolved",
+        explanation: "\
+A function managing targets (register_target(), resolve_target(),
+set_build_path()) failed.
+
+Common causes:
+
+* resolve_target() or depends= names a target that was never registered.
+  Target names are case sensitive; `pyoxidizer list-targets` prints the
+  registered targets.
+* A target is resolved before register_target() is called for it.
+  resolve_targets() should be the last call in the configuration file.
+* set_build_path() received a path that could not be normalized.
+",
+    },
+    ErrorCode {
+        code: "PACKAGE_ROOT_ERROR",
+        summary: "resources could not be read from a package root",
+        explanation: "\
+PythonExecutable.read_package_root() failed to scan a directory for
+Python resources.
+
+Check that the path exists, is a directory and is readable. Relative
+paths are resolved against the directory of the configuration file.
+The packages argument must name packages directly below that directory.
+",
+    },
+    ErrorCode {
+        code: "PIP_INSTALL_ERROR",
+        summary: "pip failed to download or install packages",
+        explanation: "\
+pip_install(), pip_download() or install_groups() ran pip and it failed.
+pip's output is printed above the error. install_groups() prefixes the
+output of each group with its 1-based index and names the groups that
+failed.
+
+Common causes:
+
+* A package has no wheel for the target platform and building it from
+  source failed. pip_download() only accepts binary wheels.
+* A requirement can't be satisfied for the Python version of the
+  distribution.
+* pip couldn't reach the package index. Pass extra_envs to pip_install()
+  or set PIP_* environment variables to configure proxies or alternative
+  indexes.
+",
+    },
+    ErrorCode {
+        code: "PYOXIDIZER",
+        summary: "the PyOxidizer context is missing",
+        explanation: "\
+The internal state PyOxidizer keeps while evaluating the configuration
+file couldn't be found. This indicates a bug in PyOxidizer; please report
+it.
+",
+    },
+    ErrorCode {
+        code: "PYOXIDIZER_BUILD",
+        summary: "a PyOxidizer function or method failed",
+        explanation: "\
+A function, method or attribute implemented by PyOxidizer rejected its
+arguments or failed while performing its action. The message describes
+the specific problem and the highlighted call is where it happened.
+
+Common causes:
+
+* An attribute was assigned a value of the wrong type or outside of its
+  allowed values, e.g. an unknown resources_location string. The
+  documentation of each type lists the accepted values.
+* A resource was added to a location the packaging policy doesn't allow.
+  Adjust PythonPackagingPolicy.resources_location and
+  resources_location_fallback, or set the location on the resource.
+* An extension module isn't compatible with the target distribution.
+  Extension modules must be built for the same Python version, ABI and
+  platform.
+* A file referenced by path doesn't exist. Relative paths are resolved
+  against the directory of the configuration file.
+",
+    },
+    ErrorCode {
+        code: "PYOXIDIZER_INSTALL",
+        summary: "files could not be installed",
+        explanation: "\
+Materializing files onto the filesystem failed. Check that the
+destination directory is writable and that there is enough disk space.
+",
+    },
+    ErrorCode {
+        code: "PYOXIDIZER_SOURCE_ERROR",
+        summary: "Python source code could not be read",
+        explanation: "\
+Accessing PythonModuleSource.source failed. Either the file holding the
+source code couldn't be read, or the source code isn't valid UTF-8.
+Source code in other encodings can't be accessed as a string.
+",
+    },
+    ErrorCode {
+        code: "RUNTIME_ERROR",
+        summary: "resources could not be filtered",
+        explanation: "\
+PythonExecutable.filter_resources_from_files() failed. Check that the
+files and glob patterns exist and that the files list resource names,
+one per line.
+",
+    },
+    ErrorCode {
+        code: "SETUP_PY_ERROR",
+        summary: "running setup.py failed",
+        explanation: "\
+setup_py_install() ran `python setup.py install` and it failed. The
+output of setup.py is printed above the error.
+
+Check that package_path points to a directory containing setup.py and
+that any compilers or libraries needed to build extension modules are
+available. Set extra_envs to pass environment variables to the build.
+",
+    },
+    ErrorCode {
+        code: "STARLARK_BUILD_CONTEXT",
+        summary: "the build context is missing",
+        explanation: "\
+The internal state of the configuration evaluator couldn't be found. This
+indicates a bug in PyOxidizer; please report it.
+",
+    },
+    ErrorCode {
+        code: "UNTRUSTED",
+        summary: "the trust file doesn't allow an action",
+        explanation: "\
+The project has a pyoxidizer.trust file next to its configuration file, or
+PYOXIDIZER_REQUIRE_TRUST is set, and the configuration file tried to load a
+Starlark file, run a package's setup.py or install packages from an index
+the trust file doesn't allow.
+
+Review the file, package or index named in the message. If it is fine to
+run, add the line printed in the message to pyoxidizer.trust. Files and
+packages are identified by the SHA-256 of their content, so they must be
+reviewed and allowed again after every change.
+",
+    },
+    ErrorCode {
+        code: "VIRTUALENV_ERROR",
+        summary: "resources could not be read from a virtualenv",
+        explanation: "\
+PythonExecutable.read_virtualenv() failed to find resources in a virtualenv.
+
+Check that the path is the root directory of a virtualenv (it contains
+lib/pythonX.Y/site-packages or Lib/site-packages) created with the same
+Python version as the distribution.
+",
+    },
+    ErrorCode {
+        code: "CF00",
+        summary: "a required argument is missing",
+        explanation: "\
+A function was called without all of its required arguments. Check the
+function's documentation for its signature.
+",
+    },
+    ErrorCode {
+        code: "CF01",
+        summary: "an unknown argument name was passed",
+        explanation: "\
+A function was called with a keyword argument it doesn't accept. Check
+the spelling of the argument against the function's documentation.
+",
+    },
+    ErrorCode {
+        code: "CF05",
+        summary: "too many arguments were passed",
+        explanation: "\
+A function was called with more positional arguments than it accepts.
+",
+    },
+    ErrorCode {
+        code: "CM01",
+        summary: "a variable is not defined",
+        explanation: "\
+The configuration file references a name that isn't defined. Check the
+spelling and that the name is defined before it is used. Functions and
+types provided by PyOxidizer are documented in the configuration file
+API reference.
+",
+    },
+    ErrorCode {
+        code: "CV00",
+        summary: "an operation isn't supported by a type",
+        explanation: "\
+An operator, method or attribute was used on a value that doesn't support
+it. This often means an attribute name is misspelled or a value has a
+different type than expected, e.g. None returned by a function lacking a
+return statement.
+",
+    },
+    ErrorCode {
+        code: "CV01",
+        summary: "a value can't be mutated",
+        explanation: "\
+A frozen value was modified. Values defined at the top level of a
+configuration file become immutable once the file is evaluated. Create
+and modify values inside target functions instead.
+",
+    },
+    ErrorCode {
+        code: "CV02",
+        summary: "an argument has the wrong type",
+        explanation: "\
+A function or attribute received a value of a type it doesn't accept,
+e.g. a string where a list of strings is expected. The message names the
+argument and the expected type.
+",
+    },
+];
+
+/// Find the explanation of an error code.
+///
+/// Codes are matched case-insensitively.
+pub fn find_error_code(code: &str) -> Option<&'static ErrorCode> {
+    ERROR_CODES
+        .iter()
+        .find(|entry| entry.code.eq_ignore_ascii_case(code))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::collections::BTreeSet};
+
+    #[test]
+    fn test_error_codes() {
+        let codes = ERROR_CODES.iter().map(|e| e.code).collect::<BTreeSet<_>>();
+        assert_eq!(codes.len(), ERROR_CODES.len());
+
+        assert_eq!(
+            find_error_code("pyoxidizer_build").map(|e| e.code),
+            Some("PYOXIDIZER_BUILD")
+        );
+        assert!(find_error_code("UNKNOWN").is_none());
+    }
+}
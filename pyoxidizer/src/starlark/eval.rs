@@ -3,29 +3,507 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use {
-    crate::starlark::{
-        env::{
-            get_context, global_environment, PyOxidizerBuildContext, PyOxidizerEnvironmentContext,
+    crate::{
+        build_layout::BuildLayout,
+        file_lock::{sibling_lock_path, FileLock},
+        project_building::{
+            executable_filename, generate_rust_project, library_filenames, run_post_build_test,
         },
-        python_embedded_resources::PythonEmbeddedResources,
-        python_executable::PythonExecutable,
+        py_packaging::{
+            distribution::DistributionCache,
+            wasi::{WASI_APP_DIR, WASI_LAUNCHER_FILENAME, WASI_PYTHON_FILENAME},
+        },
+        release_patch::RELEASE_PATCH_MANIFEST_FILENAME,
+        starlark::{
+            api::{describe_api, ApiDescription},
+            deprecation::take_deprecated_uses,
+            env::{
+                get_context, global_environment, PyOxidizerBuildContext,
+                PyOxidizerEnvironmentContext,
+            },
+            error_codes::find_error_code,
+            python_embedded_resources::PythonEmbeddedResources,
+            python_executable::{PythonBinaryKind, PythonExecutable},
+            python_library::PythonLibrary,
+            python_resources_snapshot::{
+                resources_inventory, PythonResourcesSnapshot, SNAPSHOT_OUTPUT_FILENAME,
+            },
+            python_wasi_bundle::PythonWasiBundle,
+            release::Release,
+            release_patch::ReleasePatch,
+        },
+        trust::{TrustKind, TrustPolicy},
     },
     anyhow::{anyhow, Context, Result},
     codemap::CodeMap,
-    codemap_diagnostic::Diagnostic,
+    codemap_diagnostic::{Diagnostic, Level},
+    slog::warn,
     starlark::{
         environment::{Environment, TypeValues},
+        eval::{EvalException, FileLoader},
         syntax::dialect::Dialect,
         values::Value,
     },
-    starlark_dialect_build_targets::{BuildTarget, EnvironmentContext, ResolvedTarget},
+    starlark_dialect_build_targets::{BuildTarget, EnvironmentContext, ResolvedTarget, RunMode},
     std::{
-        path::Path,
+        collections::HashMap,
+        ops::Deref,
+        path::{Path, PathBuf},
         sync::{Arc, Mutex},
     },
-    tugger::starlark::file_resource::FileManifestValue,
+    tugger::starlark::{
+        file_resource::FileManifestValue, macos_pkg::MacOsPkgBuilderValue,
+        package_managers::PackageManagerManifestsValue,
+    },
 };
 
+/// Types of values that can be built.
+const BUILDABLE_TYPES: &[&str] = &[
+    "FileManifest",
+    "MacOsPkgBuilder",
+    "PackageManagerManifests",
+    "PythonExecutable",
+    "PythonEmbeddedResources",
+    "PythonLibrary",
+    "PythonResourcesSnapshot",
+    "PythonWasiBundle",
+    "Release",
+    "ReleasePatch",
+];
+
+/// Buildable fields of a `struct` returned by a target, in definition order.
+fn struct_buildable_fields(value: &Value) -> Result<Vec<(String, Value)>> {
+    let mut fields = vec![];
+
+    for name in value
+        .dir_attr()
+        .map_err(|e| anyhow!("listing struct fields: {:?}", e))?
+    {
+        let field = value
+            .get_attr(name.as_str())
+            .map_err(|e| anyhow!("reading struct field {}: {:?}", name, e))?;
+
+        if BUILDABLE_TYPES.contains(&field.get_type()) {
+            fields.push((name.to_string(), field));
+        }
+    }
+
+    if fields.is_empty() {
+        Err(anyhow!("struct has no buildable fields"))
+    } else {
+        Ok(fields)
+    }
+}
+
+/// Build a value returned by a target.
+///
+/// Buildable fields of a `struct` are built into subdirectories named after
+/// the fields. The struct is runnable if one of its fields is; the first
+/// runnable field is run.
+fn build_value(value: &Value, build_context: &PyOxidizerBuildContext) -> Result<ResolvedTarget> {
+    // TODO surely this can use dynamic dispatch.
+    match value.get_type() {
+        "FileManifest" => value
+            .downcast_mut::<FileManifestValue>()
+            .map_err(|_| anyhow!("object isn't mutable"))?
+            .ok_or_else(|| anyhow!("invalid cast"))?
+            .build(build_context),
+        "MacOsPkgBuilder" => value
+            .downcast_mut::<MacOsPkgBuilderValue>()
+            .map_err(|_| anyhow!("object isn't mutable"))?
+            .ok_or_else(|| anyhow!("invalid cast"))?
+            .build(build_context),
+        "PackageManagerManifests" => value
+            .downcast_mut::<PackageManagerManifestsValue>()
+            .map_err(|_| anyhow!("object isn't mutable"))?
+            .ok_or_else(|| anyhow!("invalid cast"))?
+            .build(build_context),
+        "PythonExecutable" => value
+            .downcast_mut::<PythonExecutable>()
+            .map_err(|_| anyhow!("object isn't mutable"))?
+            .ok_or_else(|| anyhow!("invalid cast"))?
+            .build(build_context),
+        "PythonEmbeddedResources" => value
+            .downcast_mut::<PythonEmbeddedResources>()
+            .map_err(|_| anyhow!("object isn't mutable"))?
+            .ok_or_else(|| anyhow!("invalid cast"))?
+            .build(build_context),
+        "PythonLibrary" => value
+            .downcast_mut::<PythonLibrary>()
+            .map_err(|_| anyhow!("object isn't mutable"))?
+            .ok_or_else(|| anyhow!("invalid cast"))?
+            .build(build_context),
+        "PythonResourcesSnapshot" => value
+            .downcast_mut::<PythonResourcesSnapshot>()
+            .map_err(|_| anyhow!("object isn't mutable"))?
+            .ok_or_else(|| anyhow!("invalid cast"))?
+            .build(build_context),
+        "PythonWasiBundle" => value
+            .downcast_mut::<PythonWasiBundle>()
+            .map_err(|_| anyhow!("object isn't mutable"))?
+            .ok_or_else(|| anyhow!("invalid cast"))?
+            .build(build_context),
+        "Release" => value
+            .downcast_mut::<Release>()
+            .map_err(|_| anyhow!("object isn't mutable"))?
+            .ok_or_else(|| anyhow!("invalid cast"))?
+            .build(build_context),
+        "ReleasePatch" => value
+            .downcast_mut::<ReleasePatch>()
+            .map_err(|_| anyhow!("object isn't mutable"))?
+            .ok_or_else(|| anyhow!("invalid cast"))?
+            .build(build_context),
+        "struct" => {
+            let mut run_mode = RunMode::None;
+
+            for (name, field) in struct_buildable_fields(value)? {
+                let output_path = build_context.output_path.join(&name);
+                std::fs::create_dir_all(&output_path).context("creating output path")?;
+
+                let field_context = PyOxidizerBuildContext {
+                    output_path,
+                    ..build_context.clone()
+                };
+
+                let built = build_value(&field, &field_context)
+                    .with_context(|| format!("building struct field {}", name))?;
+
+                if let (RunMode::None, RunMode::Path { .. }) = (&run_mode, &built.run_mode) {
+                    run_mode = built.run_mode;
+                }
+            }
+
+            Ok(ResolvedTarget {
+                run_mode,
+                output_path: build_context.output_path.clone(),
+            })
+        }
+        _ => Err(anyhow!("could not determine type of target")),
+    }
+}
+
+/// Resolve the artifacts building a value returned by a target writes.
+///
+/// Artifacts are paired with the type of the value writing them, which
+/// differs from the type of `value` for fields of a `struct`. If `describe`
+/// is set, what the build would do is printed.
+fn value_artifacts(
+    value: &Value,
+    output_path: &Path,
+    target_triple: &str,
+    describe: bool,
+) -> Result<Vec<(PathBuf, &'static str)>> {
+    let paths = match value.get_type() {
+        "FileManifest" => {
+            let manifest = value
+                .downcast_ref::<FileManifestValue>()
+                .ok_or_else(|| anyhow!("invalid cast"))?;
+
+            manifest
+                .manifest
+                .entries()
+                .map(|(path, _)| output_path.join(path))
+                .collect::<Vec<_>>()
+        }
+        "MacOsPkgBuilder" => {
+            let pkg = value
+                .downcast_ref::<MacOsPkgBuilderValue>()
+                .ok_or_else(|| anyhow!("invalid cast"))?;
+
+            if describe {
+                for component in pkg.builder.components() {
+                    println!(
+                        "  component: {} -> {}",
+                        component.identifier, component.install_location
+                    );
+                }
+            }
+
+            vec![output_path.join(pkg.filename())]
+        }
+        "PackageManagerManifests" => {
+            let manifests = value
+                .downcast_ref::<PackageManagerManifestsValue>()
+                .ok_or_else(|| anyhow!("invalid cast"))?;
+
+            if describe {
+                for path in manifests.artifact_paths() {
+                    println!("  artifact: {}", path.display());
+                }
+            }
+
+            manifests
+                .output_paths()?
+                .into_iter()
+                .map(|path| output_path.join(path))
+                .collect::<Vec<_>>()
+        }
+        "PythonExecutable" => {
+            let exe = value
+                .downcast_ref::<PythonExecutable>()
+                .ok_or_else(|| anyhow!("invalid cast"))?;
+
+            if describe {
+                for (_, resource) in exe.exe.iter_resources() {
+                    println!("  resource: {}", resource.describe());
+                }
+            }
+
+            match exe.kind {
+                PythonBinaryKind::Executable => {
+                    vec![output_path.join(executable_filename(&exe.exe.name(), target_triple))]
+                }
+                PythonBinaryKind::SharedLibrary => {
+                    let lib_name = exe.exe.name().replace('-', "_");
+                    let (_, shared_filename) = library_filenames(&lib_name, target_triple);
+
+                    vec![
+                        output_path.join(shared_filename),
+                        output_path.join(format!("{}.h", lib_name)),
+                    ]
+                }
+            }
+        }
+        "PythonEmbeddedResources" => {
+            let embedded = value
+                .downcast_ref::<PythonEmbeddedResources>()
+                .ok_or_else(|| anyhow!("invalid cast"))?;
+
+            if describe {
+                for (_, resource) in embedded.exe.iter_resources() {
+                    println!("  resource: {}", resource.describe());
+                }
+            }
+
+            vec![
+                output_path.join("py-module-names"),
+                output_path.join("packed-resources"),
+                output_path.join("default_python_config.rs"),
+            ]
+        }
+        "PythonLibrary" => {
+            let library = value
+                .downcast_ref::<PythonLibrary>()
+                .ok_or_else(|| anyhow!("invalid cast"))?;
+
+            if describe {
+                for (_, resource) in library.exe.iter_resources() {
+                    println!("  resource: {}", resource.describe());
+                }
+            }
+
+            let lib_name = library.exe.name().replace('-', "_");
+            let (static_filename, shared_filename) = library_filenames(&lib_name, target_triple);
+
+            let mut paths = vec![];
+            if library.static_library {
+                paths.push(output_path.join(static_filename));
+            }
+            paths.push(output_path.join(shared_filename));
+            paths.push(output_path.join(format!("{}.h", lib_name)));
+
+            paths
+        }
+        "PythonResourcesSnapshot" => {
+            let snapshot = value
+                .downcast_ref::<PythonResourcesSnapshot>()
+                .ok_or_else(|| anyhow!("invalid cast"))?;
+
+            if describe {
+                for entry in resources_inventory(snapshot.exe.deref()) {
+                    println!("  resource: {}", entry);
+                }
+            }
+
+            vec![output_path.join(SNAPSHOT_OUTPUT_FILENAME)]
+        }
+        "PythonWasiBundle" => {
+            let bundle = value
+                .downcast_ref::<PythonWasiBundle>()
+                .ok_or_else(|| anyhow!("invalid cast"))?;
+
+            if describe {
+                for (_, resource) in bundle.exe.iter_resources() {
+                    println!("  resource: {}", resource.describe());
+                }
+            }
+
+            vec![
+                output_path.join(WASI_PYTHON_FILENAME),
+                output_path.join(WASI_APP_DIR),
+                output_path.join(WASI_LAUNCHER_FILENAME),
+            ]
+        }
+        "Release" => {
+            let release = value
+                .downcast_ref::<Release>()
+                .ok_or_else(|| anyhow!("invalid cast"))?;
+
+            if describe {
+                let targets_path = output_path.parent().unwrap_or(output_path);
+                let sources = release
+                    .targets
+                    .iter()
+                    .flat_map(|(target, patterns)| {
+                        patterns
+                            .iter()
+                            .map(move |pattern| targets_path.join(target).join(pattern))
+                    })
+                    .chain(release.artifacts.iter().cloned());
+                for source in sources {
+                    println!("  artifact: {}", source.display());
+                }
+                for destination in &release.destinations {
+                    println!("  upload: {}", destination.describe("<artifact>"));
+                }
+            }
+
+            // Nothing is written to the output directory.
+            vec![]
+        }
+        "ReleasePatch" => {
+            let patch = value
+                .downcast_ref::<ReleasePatch>()
+                .ok_or_else(|| anyhow!("invalid cast"))?;
+
+            if describe {
+                println!("  previous release: {}", patch.previous_path.display());
+            }
+
+            vec![output_path.join(RELEASE_PATCH_MANIFEST_FILENAME)]
+        }
+        "struct" => {
+            let mut artifacts = vec![];
+
+            for (name, field) in struct_buildable_fields(value)? {
+                if describe {
+                    println!("  {} ({})", name, field.get_type());
+                }
+
+                artifacts.extend(value_artifacts(
+                    &field,
+                    &output_path.join(&name),
+                    target_triple,
+                    describe,
+                )?);
+            }
+
+            return Ok(artifacts);
+        }
+        _ => return Err(anyhow!("could not determine type of target")),
+    };
+
+    Ok(paths
+        .into_iter()
+        .map(|path| (path, value.get_type()))
+        .collect())
+}
+
+/// Whether building a value returned by a target publishes artifacts.
+fn value_publishes(value: &Value) -> Result<bool> {
+    match value.get_type() {
+        "Release" | "ReleasePatch" => Ok(true),
+        "struct" => {
+            for (_, field) in struct_buildable_fields(value)? {
+                if value_publishes(&field)? {
+                    return Ok(true);
+                }
+            }
+
+            Ok(false)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Render a diagnostic with the source code it points at.
+///
+/// Codes having an explanation get a hint to look it up.
+fn render_diagnostic(map: &Arc<Mutex<CodeMap>>, diagnostic: &Diagnostic) -> String {
+    let mut msg = Vec::new();
+    let raw_map = map.lock().unwrap();
+    {
+        let mut emitter = codemap_diagnostic::Emitter::vec(&mut msg, Some(&raw_map));
+        emitter.emit(&[diagnostic.clone()]);
+    }
+
+    let mut msg = String::from_utf8_lossy(&msg).trim_end().to_string();
+
+    if let Some(code) = diagnostic.code.as_deref().and_then(find_error_code) {
+        msg.push_str(&format!(
+            "\n\nhelp: {}; run `pyoxidizer --explain {}` for how to fix it",
+            code.summary, code.code
+        ));
+    }
+
+    msg
+}
+
+/// A Starlark `FileLoader` rejecting files not allowed by the trust policy.
+///
+/// Otherwise behaves like `starlark::eval::simple::SimpleFileLoader`.
+struct TrustedFileLoader {
+    loaded: Arc<Mutex<HashMap<String, Environment>>>,
+    parent_env: Environment,
+    codemap: Arc<Mutex<CodeMap>>,
+    trust: Option<TrustPolicy>,
+}
+
+impl TrustedFileLoader {
+    fn new(
+        codemap: &Arc<Mutex<CodeMap>>,
+        parent_env: Environment,
+        trust: Option<TrustPolicy>,
+    ) -> Self {
+        Self {
+            loaded: Arc::new(Mutex::new(HashMap::new())),
+            parent_env,
+            codemap: codemap.clone(),
+            trust,
+        }
+    }
+}
+
+impl FileLoader for TrustedFileLoader {
+    fn load(&self, path: &str, type_values: &TypeValues) -> Result<Environment, EvalException> {
+        if let Some(env) = self.loaded.lock().unwrap().get(path) {
+            return Ok(env.clone());
+        }
+
+        if let Some(trust) = &self.trust {
+            trust
+                .check_file(TrustKind::Load, Path::new(path))
+                .map_err(|e| {
+                    EvalException::DiagnosedError(Diagnostic {
+                        level: Level::Error,
+                        message: format!("{:#}", e),
+                        code: Some("UNTRUSTED".to_string()),
+                        spans: vec![],
+                    })
+                })?;
+        }
+
+        let mut env = self.parent_env.child(path);
+        starlark::eval::eval_file(
+            &self.codemap,
+            path,
+            Dialect::Bzl,
+            &mut env,
+            type_values,
+            self,
+        )
+        .map_err(EvalException::DiagnosedError)?;
+        env.freeze();
+
+        self.loaded
+            .lock()
+            .unwrap()
+            .insert(path.to_string(), env.clone());
+
+        Ok(env)
+    }
+}
+
 /// Represents a running Starlark environment.
 pub struct EvaluationContext {
     env: Environment,
@@ -54,50 +532,266 @@ impl EvaluationContext {
             None,
         )?;
 
-        let (env, type_values) = global_environment(context, resolve_targets, build_script_mode)
-            .map_err(|e| anyhow!("error creating Starlark environment: {:?}", e))?;
+        let (env, type_values) =
+            global_environment(context, resolve_targets, build_script_mode, None)
+                .map_err(|e| anyhow!("error creating Starlark environment: {:?}", e))?;
 
         Ok(Self { env, type_values })
     }
 
-    /// Evaluate a Starlark configuration file, returning a Diagnostic on error.
-    pub fn evaluate_file_diagnostic(&mut self, config_path: &Path) -> Result<(), Diagnostic> {
+    /// Obtain a context for an application registered by a workspace configuration file.
+    ///
+    /// This must be called after the workspace configuration file is evaluated.
+    /// The application's configuration file is `pyoxidizer.bzl` in its directory.
+    /// It sees the globals of the workspace configuration file and shares its
+    /// Python distributions. Its build path defaults to `apps/<name>` in the
+    /// workspace build path.
+    ///
+    /// Returns the context and the path of the application's configuration file.
+    pub fn app_context(
+        &self,
+        logger: &slog::Logger,
+        name: &str,
+        build_target_triple: &str,
+        release: bool,
+        verbose: bool,
+        resolve_targets: Option<Vec<String>>,
+    ) -> Result<(Self, PathBuf)> {
+        let (app_path, distribution_cache) = {
+            let workspace_value = self.pyoxidizer_context_value()?;
+            let workspace = workspace_value
+                .downcast_ref::<PyOxidizerEnvironmentContext>()
+                .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+            let app_path = workspace.apps.get(name).cloned().ok_or_else(|| {
+                if workspace.apps.is_empty() {
+                    anyhow!(
+                        "{} doesn't register applications with register_app()",
+                        workspace.config_path.display()
+                    )
+                } else {
+                    anyhow!(
+                        "unknown application: {} (known applications: {})",
+                        name,
+                        workspace
+                            .apps
+                            .keys()
+                            .cloned()
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                }
+            })?;
+
+            (app_path, workspace.distribution_cache.clone())
+        };
+
+        let config_path = app_path.join("pyoxidizer.bzl");
+        if !config_path.exists() {
+            return Err(anyhow!(
+                "application {} has no configuration file at {}",
+                name,
+                config_path.display()
+            ));
+        }
+
+        let workspace_build_path = self.build_path()?;
+
+        let mut context = PyOxidizerEnvironmentContext::new(
+            logger,
+            verbose,
+            &config_path,
+            crate::project_building::HOST,
+            build_target_triple,
+            release,
+            "0",
+            Some(distribution_cache),
+        )?;
+        context.shared_cache_path = Some(workspace_build_path.clone());
+
+        // Applications can't modify values shared with other applications.
+        self.env.freeze();
+
+        let (env, type_values) =
+            global_environment(context, resolve_targets, false, Some(&self.env))
+                .map_err(|e| anyhow!("error creating Starlark environment: {:?}", e))?;
+
+        let mut res = Self { env, type_values };
+        res.set_build_path(&workspace_build_path.join("apps").join(name))?;
+
+        Ok((res, config_path))
+    }
+
+    /// Evaluate a Starlark configuration file, returning the `CodeMap` on error.
+    fn evaluate_file_with_code_map(
+        &mut self,
+        config_path: &Path,
+    ) -> Result<(), (Diagnostic, Arc<Mutex<CodeMap>>)> {
         let map = Arc::new(Mutex::new(CodeMap::new()));
-        let file_loader_env = self.env.clone();
+        let file_loader = TrustedFileLoader::new(&map, self.env.clone(), self.trust_policy());
 
-        starlark::eval::simple::eval_file(
+        let res = starlark::eval::eval_file(
             &map,
             &config_path.display().to_string(),
             Dialect::Bzl,
             &mut self.env,
             &self.type_values,
-            file_loader_env,
+            &file_loader,
         )
-        .map_err(|e| {
-            if let Ok(raw_context) = self.build_targets_context_value() {
-                if let Some(context) = raw_context.downcast_ref::<EnvironmentContext>() {
-                    let mut msg = Vec::new();
-                    let raw_map = map.lock().unwrap();
-                    {
-                        let mut emitter =
-                            codemap_diagnostic::Emitter::vec(&mut msg, Some(&raw_map));
-                        emitter.emit(&[e.clone()]);
-                    }
+        .map_err(|e| (e, map.clone()));
+
+        self.warn_deprecated_uses();
+
+        res.map(|_| ())
+    }
+
+    /// Log a warning for each deprecated item used since the last call.
+    fn warn_deprecated_uses(&self) {
+        let uses = take_deprecated_uses();
+        if uses.is_empty() {
+            return;
+        }
+
+        if let Ok(raw_context) = self.build_targets_context_value() {
+            if let Some(context) = raw_context.downcast_ref::<EnvironmentContext>() {
+                for deprecation in &uses {
+                    warn!(context.logger(), "{}", deprecation.message());
+                }
 
-                    slog::error!(context.logger(), "{}", String::from_utf8_lossy(&msg));
+                if uses.iter().any(|d| d.replacement.is_some()) {
+                    warn!(
+                        context.logger(),
+                        "run `pyoxidizer migrate-config` to update the configuration file"
+                    );
                 }
             }
+        }
+    }
 
-            e
-        })?;
+    /// Evaluate a Starlark configuration file, returning a Diagnostic on error.
+    pub fn evaluate_file_diagnostic(&mut self, config_path: &Path) -> Result<(), Diagnostic> {
+        self.evaluate_file_with_code_map(config_path)
+            .map_err(|(e, map)| {
+                if let Ok(raw_context) = self.build_targets_context_value() {
+                    if let Some(context) = raw_context.downcast_ref::<EnvironmentContext>() {
+                        slog::error!(context.logger(), "{}", render_diagnostic(&map, &e));
+                    }
+                }
 
-        Ok(())
+                e
+            })
     }
 
     /// Evaluate a Starlark configuration file, returning an anyhow Result.
+    ///
+    /// The error shows where in the file evaluation failed.
     pub fn evaluate_file(&mut self, config_path: &Path) -> Result<()> {
-        self.evaluate_file_diagnostic(config_path)
-            .map_err(|d| anyhow!(d.message))
+        self.evaluate_file_with_code_map(config_path)
+            .map_err(|(e, map)| anyhow!("{}", render_diagnostic(&map, &e)))
+    }
+
+    /// Evaluate a snippet of Starlark code, returning the resulting `Value`.
+    pub fn evaluate_code(&mut self, code: &str) -> Result<Value> {
+        let map = Arc::new(Mutex::new(CodeMap::new()));
+        let file_loader = TrustedFileLoader::new(&map, self.env.clone(), self.trust_policy());
+
+        starlark::eval::eval(
+            &map,
+            "<eval>",
+            code,
+            Dialect::Bzl,
+            &mut self.env,
+            &self.type_values,
+            &file_loader,
+        )
+        .map_err(|e| anyhow!("{}", render_diagnostic(&map, &e)))
+    }
+
+    /// Set whether evaluation should avoid building anything.
+    ///
+    /// This must be called before evaluating a file to have an effect.
+    pub fn set_dry_run(&mut self, dry_run: bool) -> Result<()> {
+        let raw_context = self.pyoxidizer_context_value()?;
+        let mut context = raw_context
+            .downcast_mut::<PyOxidizerEnvironmentContext>()
+            .map_err(|_| anyhow!("unable to obtain mutable context"))?
+            .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+        context.dry_run = dry_run;
+        if dry_run {
+            context.distribution_cache = Arc::new(DistributionCache::new_dry_run(None)?);
+        }
+
+        Ok(())
+    }
+
+    /// Set whether resources snapshots are written instead of compared.
+    ///
+    /// This must be called before evaluating a file to have an effect.
+    pub fn set_update_snapshots(&mut self, update_snapshots: bool) -> Result<()> {
+        let raw_context = self.pyoxidizer_context_value()?;
+        let mut context = raw_context
+            .downcast_mut::<PyOxidizerEnvironmentContext>()
+            .map_err(|_| anyhow!("unable to obtain mutable context"))?
+            .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+        context.update_snapshots = update_snapshots;
+
+        Ok(())
+    }
+
+    /// Set a pattern of resource names whose packaging decisions should be logged.
+    ///
+    /// This must be called before evaluating a file to have an effect.
+    pub fn set_trace_resources(&mut self, pattern: Option<&str>) -> Result<()> {
+        let pattern = if let Some(pattern) = pattern {
+            Some(glob::Pattern::new(pattern).context("parsing resource trace pattern")?)
+        } else {
+            None
+        };
+
+        let raw_context = self.pyoxidizer_context_value()?;
+        let mut context = raw_context
+            .downcast_mut::<PyOxidizerEnvironmentContext>()
+            .map_err(|_| anyhow!("unable to obtain mutable context"))?
+            .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+        context.trace_resources = pattern;
+
+        Ok(())
+    }
+
+    /// Describe the Starlark API available to configuration files.
+    pub fn describe_api(&self) -> Result<ApiDescription> {
+        describe_api(&self.env, &self.type_values)
+    }
+
+    /// Register functions useful for testing configuration files.
+    ///
+    /// This defines `assert_*()` functions in the global namespace.
+    pub fn register_testing_functions(&mut self) {
+        super::testing::testing_module(&mut self.env, &mut self.type_values);
+    }
+
+    /// Set the path build artifacts should be written to.
+    pub fn set_build_path(&mut self, path: &Path) -> Result<()> {
+        let raw_context = self.build_targets_context_value()?;
+        let mut context = raw_context
+            .downcast_mut::<EnvironmentContext>()
+            .map_err(|_| anyhow!("unable to obtain mutable context"))?
+            .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+        context.set_build_path(path)
+    }
+
+    /// Obtain the path build artifacts are written to.
+    pub fn build_path(&self) -> Result<PathBuf> {
+        let raw_context = self.build_targets_context_value()?;
+        let context = raw_context
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+        Ok(context.build_path().to_path_buf())
     }
 
     /// Obtain the `Value` for the build targets context.
@@ -111,6 +805,14 @@ impl EvaluationContext {
         get_context(&self.type_values).map_err(|_| anyhow!("could not obtain context"))
     }
 
+    /// The trust policy restricting what the configuration file may execute.
+    fn trust_policy(&self) -> Option<TrustPolicy> {
+        let value = self.pyoxidizer_context_value().ok()?;
+        let context = value.downcast_ref::<PyOxidizerEnvironmentContext>()?;
+
+        context.trust.clone()
+    }
+
     pub fn default_target(&self) -> Result<Option<String>> {
         let raw_context = self.build_targets_context_value()?;
         let context = raw_context
@@ -143,7 +845,64 @@ impl EvaluationContext {
         Ok(context.targets_to_resolve())
     }
 
+    /// Whether building a resolved target publishes artifacts.
+    ///
+    /// `Release` and `ReleasePatch` targets operate on released artifacts
+    /// and are skipped by commands only producing artifacts.
+    pub fn target_publishes(&self, target: &str) -> Result<bool> {
+        let raw_context = self.build_targets_context_value()?;
+        let context = raw_context
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+        let resolved_value = match context.get_target(target) {
+            Some(t) => t
+                .resolved_value
+                .clone()
+                .ok_or_else(|| anyhow!("target {} is not resolved", target))?,
+            None => return Err(anyhow!("target {} is not registered", target)),
+        };
+
+        value_publishes(&resolved_value)
+    }
+
+    /// Targets whose artifacts a `Release` target uploads.
+    ///
+    /// These need to be built before the release.
+    fn release_dependencies(&self, target: &str) -> Result<Vec<String>> {
+        let raw_context = self.build_targets_context_value()?;
+        let context = raw_context
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+        let value = match context.get_target(target) {
+            Some(t) if t.built_target.is_none() => t.resolved_value.clone(),
+            _ => None,
+        };
+
+        Ok(match value {
+            Some(value) if value.get_type() == "Release" => value
+                .downcast_ref::<Release>()
+                .ok_or_else(|| anyhow!("invalid cast"))?
+                .targets
+                .iter()
+                .map(|(dependency, _)| dependency.clone())
+                .filter(|dependency| dependency != target)
+                .collect(),
+            _ => vec![],
+        })
+    }
+
     pub fn build_resolved_target(&mut self, target: &str) -> Result<ResolvedTarget> {
+        for dependency in self.release_dependencies(target)? {
+            self.build_resolved_target(&dependency)?;
+        }
+
+        // Resolving the layout reads the context, so it can't be borrowed
+        // mutably yet.
+        let layout = self.build_layout()?;
+        let output_path = layout.target_output_path(target);
+
         let raw_context = self.build_targets_context_value()?;
         let mut context = raw_context
             .downcast_mut::<EnvironmentContext>()
@@ -169,16 +928,9 @@ impl EvaluationContext {
             .downcast_ref::<PyOxidizerEnvironmentContext>()
             .ok_or_else(|| anyhow!("context has incorrect type"))?;
 
-        let output_path = pyoxidizer_context
-            .build_path(&self.type_values)
-            .map_err(|_| anyhow!("unable to resolve build path"))?
-            .join(&pyoxidizer_context.build_target_triple)
-            .join(if pyoxidizer_context.build_release {
-                "release"
-            } else {
-                "debug"
-            })
-            .join(target);
+        // Concurrent builds of the same project would otherwise write the
+        // same output directory.
+        let _lock = FileLock::acquire(context.logger(), &sibling_lock_path(&output_path))?;
 
         std::fs::create_dir_all(&output_path).context("creating output path")?;
 
@@ -191,31 +943,164 @@ impl EvaluationContext {
             output_path,
         };
 
-        // TODO surely this can use dynamic dispatch.
-        let resolved_target: ResolvedTarget = match resolved_value.get_type() {
-            "FileManifest" => resolved_value
-                .downcast_mut::<FileManifestValue>()
-                .map_err(|_| anyhow!("object isn't mutable"))?
-                .ok_or_else(|| anyhow!("invalid cast"))?
-                .build(&build_context),
-            "PythonExecutable" => resolved_value
-                .downcast_mut::<PythonExecutable>()
-                .map_err(|_| anyhow!("object isn't mutable"))?
-                .ok_or_else(|| anyhow!("invalid cast"))?
-                .build(&build_context),
-            "PythonEmbeddedResources" => resolved_value
-                .downcast_mut::<PythonEmbeddedResources>()
-                .map_err(|_| anyhow!("object isn't mutable"))?
-                .ok_or_else(|| anyhow!("invalid cast"))?
-                .build(&build_context),
-            _ => Err(anyhow!("could not determine type of target")),
-        }?;
+        let resolved_target = build_value(&resolved_value, &build_context)?;
+
+        if let Some(entry_point) = &context.get_target(target).unwrap().post_build_test {
+            if pyoxidizer_context.build_host_triple != pyoxidizer_context.build_target_triple {
+                warn!(
+                    context.logger(),
+                    "skipping post build test of {} because target triple {} can not run on host {}",
+                    target,
+                    pyoxidizer_context.build_target_triple,
+                    pyoxidizer_context.build_host_triple
+                );
+            } else if let RunMode::Path { path } = &resolved_target.run_mode {
+                run_post_build_test(context.logger(), path, entry_point)?;
+            } else {
+                return Err(anyhow!(
+                    "target {} defines a post build test but is not runnable",
+                    target
+                ));
+            }
+        }
 
         context.get_target_mut(target).unwrap().built_target = Some(resolved_target.clone());
 
         Ok(resolved_target)
     }
 
+    /// Obtain the layout of the build directory.
+    pub fn build_layout(&self) -> Result<BuildLayout> {
+        let pyoxidizer_context_value = self.pyoxidizer_context_value()?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+        pyoxidizer_context
+            .build_layout(&self.type_values)
+            .map_err(|_| anyhow!("unable to resolve build path"))
+    }
+
+    /// Resolve the directory that artifacts for a target are written to.
+    fn target_output_path(&self, target: &str) -> Result<PathBuf> {
+        Ok(self.build_layout()?.target_output_path(target))
+    }
+
+    /// Describe what building a resolved target would do, without building it.
+    ///
+    /// The resources and artifacts the target would produce are printed.
+    pub fn dry_run_resolved_target(&self, target: &str) -> Result<()> {
+        let raw_context = self.build_targets_context_value()?;
+        let context = raw_context
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+        let resolved_value = match context.get_target(target) {
+            Some(t) => t
+                .resolved_value
+                .clone()
+                .ok_or_else(|| anyhow!("target {} is not resolved", target))?,
+            None => return Err(anyhow!("target {} is not registered", target)),
+        };
+
+        let pyoxidizer_context_value = self.pyoxidizer_context_value()?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+        let output_path = self.target_output_path(target)?;
+
+        println!("{} ({})", target, resolved_value.get_type());
+
+        let artifacts = value_artifacts(
+            &resolved_value,
+            &output_path,
+            &pyoxidizer_context.build_target_triple,
+            true,
+        )?;
+
+        for (path, _) in artifacts {
+            println!("  artifact: {}", path.display());
+        }
+
+        Ok(())
+    }
+
+    /// Resolve the artifacts a resolved target writes when built.
+    ///
+    /// Artifacts are paired with the type of the value writing them.
+    pub fn target_artifacts(&self, target: &str) -> Result<Vec<(PathBuf, &'static str)>> {
+        let raw_context = self.build_targets_context_value()?;
+        let context = raw_context
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+        let resolved_value = match context.get_target(target) {
+            Some(t) => t
+                .resolved_value
+                .clone()
+                .ok_or_else(|| anyhow!("target {} is not resolved", target))?,
+            None => return Err(anyhow!("target {} is not registered", target)),
+        };
+
+        let pyoxidizer_context_value = self.pyoxidizer_context_value()?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+        value_artifacts(
+            &resolved_value,
+            &self.target_output_path(target)?,
+            &pyoxidizer_context.build_target_triple,
+            false,
+        )
+    }
+
+    /// Write the Rust project used to build a resolved `PythonExecutable` target.
+    ///
+    /// Returns the environment variables `cargo build` must be run with.
+    pub fn generate_rust_project(
+        &self,
+        target: &str,
+        project_path: &Path,
+    ) -> Result<Vec<(String, String)>> {
+        let raw_context = self.build_targets_context_value()?;
+        let context = raw_context
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+        let resolved_value = match context.get_target(target) {
+            Some(t) => t
+                .resolved_value
+                .clone()
+                .ok_or_else(|| anyhow!("target {} is not resolved", target))?,
+            None => return Err(anyhow!("target {} is not registered", target)),
+        };
+
+        let exe = match resolved_value.downcast_ref::<PythonExecutable>() {
+            Some(exe) if exe.kind == PythonBinaryKind::Executable => exe,
+            _ => {
+                return Err(anyhow!(
+                    "target {} is not an executable PythonExecutable; got {}",
+                    target,
+                    resolved_value.get_type()
+                ))
+            }
+        };
+
+        let pyoxidizer_context_value = self.pyoxidizer_context_value()?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or_else(|| anyhow!("context has incorrect type"))?;
+
+        generate_rust_project(
+            pyoxidizer_context.logger(),
+            exe.exe.deref(),
+            project_path,
+            &pyoxidizer_context.build_opt_level,
+        )
+    }
+
     /// Evaluate a target and run it, if possible.
     pub fn run_resolved_target(&mut self, target: &str) -> Result<()> {
         let resolved_target = self.build_resolved_target(target)?;
@@ -240,3 +1125,79 @@ impl EvaluationContext {
         self.run_resolved_target(&target)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::testutil::get_logger};
+
+    #[test]
+    fn test_app_context() -> Result<()> {
+        let logger = get_logger()?;
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let workspace_path = temp_dir.path().join("workspace");
+        let config_path = workspace_path.join("pyoxidizer.bzl");
+        std::fs::create_dir_all(workspace_path.join("app1"))?;
+        std::fs::create_dir_all(workspace_path.join("app2"))?;
+        std::fs::write(
+            &config_path,
+            "SHARED = 42\nregister_app('app1', 'app1')\nregister_app('app2', 'app2')\n",
+        )?;
+        let app_config_path = workspace_path.join("app1").join("pyoxidizer.bzl");
+        std::fs::write(&app_config_path, "VALUE = SHARED + 1\n")?;
+
+        let mut workspace = EvaluationContext::new(
+            &logger,
+            &config_path,
+            crate::project_building::HOST,
+            false,
+            false,
+            Some(vec![]),
+            false,
+        )?;
+        workspace.evaluate_file(&config_path)?;
+
+        let (mut app, path) = workspace.app_context(
+            &logger,
+            "app1",
+            crate::project_building::HOST,
+            false,
+            false,
+            None,
+        )?;
+        assert_eq!(path, app_config_path);
+
+        // Build artifacts, including the build log, are kept per application.
+        let app_build_path = workspace_path.join("build").join("apps").join("app1");
+        assert_eq!(app.build_path()?, app_build_path);
+        assert!(app
+            .build_layout()?
+            .build_log_path()
+            .starts_with(&app_build_path));
+
+        app.evaluate_file(&path)?;
+        assert_eq!(app.evaluate_code("VALUE")?.to_int().unwrap(), 43);
+
+        assert!(workspace
+            .app_context(
+                &logger,
+                "app2",
+                crate::project_building::HOST,
+                false,
+                false,
+                None
+            )
+            .is_err());
+        assert!(workspace
+            .app_context(
+                &logger,
+                "missing",
+                crate::project_building::HOST,
+                false,
+                false,
+                None
+            )
+            .is_err());
+
+        Ok(())
+    }
+}
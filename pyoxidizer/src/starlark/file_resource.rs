@@ -7,15 +7,17 @@ use {
         env::{get_context, PyOxidizerEnvironmentContext},
         python_executable::PythonExecutable,
         python_resource::{
-            PythonExtensionModuleValue, PythonModuleSourceValue,
+            GettextCatalogValue, PythonExtensionModuleValue, PythonModuleSourceValue,
             PythonPackageDistributionResourceValue, PythonPackageResourceValue,
         },
+        release_patch::ReleasePatch,
     },
     crate::{
-        project_building::build_python_executable,
+        paths::check_case_collisions,
+        project_building::{build_python_executable, executable_filename},
         py_packaging::{binary::PythonBinaryBuilder, resource::AddToFileManifest},
     },
-    anyhow::Result,
+    anyhow::{anyhow, Result},
     slog::warn,
     starlark::{
         environment::TypeValues,
@@ -29,7 +31,10 @@ use {
             starlark_signature_extraction, starlark_signatures,
         },
     },
-    std::{ops::Deref, path::Path},
+    std::{
+        ops::Deref,
+        path::{Path, PathBuf},
+    },
     tugger::{
         file_resource::{FileContent, FileManifest},
         starlark::file_resource::FileManifestValue,
@@ -45,7 +50,26 @@ fn file_manifest_add_python_executable(
     target: &str,
     release: bool,
     opt_level: &str,
+    dry_run: bool,
 ) -> Result<()> {
+    if dry_run {
+        warn!(logger, "dry run: not building executable {}", exe.name());
+
+        // Install an empty placeholder so the manifest reflects the files
+        // that would be installed.
+        let path = Path::new(&prefix).join(executable_filename(&exe.name(), target));
+        manifest.manifest.add_file(
+            &path,
+            &FileContent {
+                data: vec![],
+                executable: true,
+            },
+        )?;
+        manifest.run_path = Some(path);
+
+        return Ok(());
+    }
+
     let build = build_python_executable(logger, &exe.name(), exe, target, opt_level, release)?;
 
     let content = FileContent {
@@ -64,8 +88,37 @@ fn file_manifest_add_python_executable(
         extra_files.add_file(&Path::new(prefix).join(path), &content)?;
     }
 
+    // Runtime directories are named after their content, so executables
+    // sharing one must agree on it. Guard against digest collisions, which
+    // would make one of them load resources it wasn't built against.
+    if let Some(runtime_path) = exe.external_runtime_path() {
+        let runtime_path = Path::new(prefix).join(runtime_path);
+
+        for (path, content) in extra_files.entries() {
+            if !path.starts_with(&runtime_path) {
+                continue;
+            }
+
+            if let Some((_, existing)) = manifest.manifest.entries().find(|(p, _)| *p == path) {
+                if existing != content {
+                    return Err(anyhow!(
+                        "{} conflicts with a file installed by another executable sharing its external runtime",
+                        path.display()
+                    ));
+                }
+            }
+        }
+    }
+
     manifest.manifest.add_manifest(&extra_files)?;
 
+    // Files differing only by case overwrite each other when installed on
+    // Windows and macOS.
+    check_case_collisions(
+        target,
+        manifest.manifest.entries().map(|(p, _)| p.as_path()),
+    )?;
+
     // Make the last added Python executable the default run target.
     manifest.run_path = Some(path);
 
@@ -99,7 +152,7 @@ pub fn file_manifest_add_python_resource(
                 .map_err(|e| {
                     ValueError::from(RuntimeError {
                         code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
-                        message: e.to_string(),
+                        message: format!("{:#}", e),
                         label: e.to_string(),
                     })
                 })
@@ -120,7 +173,29 @@ pub fn file_manifest_add_python_resource(
                 .map_err(|e| {
                     RuntimeError {
                         code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
-                        message: e.to_string(),
+                        message: format!("{:#}", e),
+                        label: e.to_string(),
+                    }
+                    .into()
+                })
+        }
+        "GettextCatalog" => {
+            let m = match resource.downcast_ref::<GettextCatalogValue>() {
+                Some(m) => Ok(m.inner.to_package_resource()),
+                None => Err(ValueError::IncorrectParameterType),
+            }?;
+
+            warn!(
+                pyoxidizer_context.logger(),
+                "adding gettext catalog {} to {}",
+                m.symbolic_name(),
+                prefix
+            );
+            m.add_to_file_manifest(&mut manifest.manifest, &prefix)
+                .map_err(|e| {
+                    RuntimeError {
+                        code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                        message: format!("{:#}", e),
                         label: e.to_string(),
                     }
                     .into()
@@ -139,7 +214,7 @@ pub fn file_manifest_add_python_resource(
                 .map_err(|e| {
                     ValueError::from(RuntimeError {
                         code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
-                        message: e.to_string(),
+                        message: format!("{:#}", e),
                         label: e.to_string(),
                     })
                 })
@@ -159,7 +234,7 @@ pub fn file_manifest_add_python_resource(
                 .map_err(|e| {
                     ValueError::from(RuntimeError {
                         code: "PYOXIDIZER_BUILD",
-                        message: e.to_string(),
+                        message: format!("{:#}", e),
                         label: "add_python_resource".to_string(),
                     })
                 })
@@ -181,11 +256,12 @@ pub fn file_manifest_add_python_resource(
                     &pyoxidizer_context.build_target_triple,
                     pyoxidizer_context.build_release,
                     &pyoxidizer_context.build_opt_level,
+                    pyoxidizer_context.dry_run,
                 )
                 .map_err(|e| {
                     ValueError::from(RuntimeError {
                         code: "PYOXIDIZER_BUILD",
-                        message: e.to_string(),
+                        message: format!("{:#}", e),
                         label: "add_python_resource".to_string(),
                     })
                 })
@@ -217,6 +293,30 @@ pub fn file_manifest_add_python_resources(
     Ok(Value::new(NoneType::None))
 }
 
+/// FileManifest.to_release_patch(previous_path)
+pub fn file_manifest_to_release_patch(
+    manifest: &FileManifestValue,
+    type_values: &TypeValues,
+    previous_path: String,
+) -> ValueResult {
+    let pyoxidizer_context_value = get_context(type_values)?;
+    let pyoxidizer_context = pyoxidizer_context_value
+        .downcast_ref::<PyOxidizerEnvironmentContext>()
+        .ok_or(ValueError::IncorrectParameterType)?;
+
+    let path = PathBuf::from(previous_path);
+    let previous_path = if path.is_absolute() {
+        path
+    } else {
+        pyoxidizer_context.cwd.join(path)
+    };
+
+    Ok(Value::new(ReleasePatch {
+        manifest: manifest.manifest.clone(),
+        previous_path,
+    }))
+}
+
 starlark_module! { file_resource_env =>
     FileManifest.add_python_resource(env env, this, prefix: String, resource) {
         match this.clone().downcast_mut::<FileManifestValue>()? {
@@ -231,6 +331,13 @@ starlark_module! { file_resource_env =>
             None => Err(ValueError::IncorrectParameterType),
         }
     }
+
+    FileManifest.to_release_patch(env env, this, previous_path: String) {
+        match this.clone().downcast_ref::<FileManifestValue>() {
+            Some(manifest) => file_manifest_to_release_patch(&manifest, &env, previous_path),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -411,4 +518,20 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_to_release_patch() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        let value = env.eval("FileManifest().to_release_patch('previous')")?;
+        assert_eq!(value.get_type(), "ReleasePatch");
+
+        let patch = value.downcast_ref::<ReleasePatch>().unwrap();
+        assert_eq!(
+            patch.previous_path,
+            std::env::current_dir()?.join("previous")
+        );
+
+        Ok(())
+    }
 }
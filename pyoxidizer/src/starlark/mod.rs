@@ -8,15 +8,25 @@ The `starlark` module and related sub-modules define the
 define Oxidized Python binaries.
 */
 
+pub mod api;
+pub mod deprecation;
 pub mod env;
+pub mod error_codes;
 pub mod eval;
 pub mod file_resource;
 pub mod python_distribution;
 pub mod python_embedded_resources;
 pub mod python_executable;
 pub mod python_interpreter_config;
+pub mod python_library;
 pub mod python_packaging_policy;
 pub mod python_resource;
+pub mod python_resources_snapshot;
+pub mod python_wasi_bundle;
+pub mod release;
+pub mod release_patch;
+pub mod structured_data;
+pub mod testing;
 #[cfg(test)]
 mod testutil;
 pub mod util;
@@ -5,23 +5,30 @@
 use {
     super::{
         env::{get_context, PyOxidizerEnvironmentContext},
-        python_executable::PythonExecutable,
+        python_executable::{PythonBinaryKind, PythonExecutable},
         python_interpreter_config::PythonInterpreterConfigValue,
         python_packaging_policy::PythonPackagingPolicyValue,
         python_resource::{add_context_for_value, python_resource_to_value},
     },
-    crate::py_packaging::{
-        distribution::BinaryLibpythonLinkMode,
-        distribution::{
-            default_distribution_location, DistributionFlavor, PythonDistribution,
-            PythonDistributionLocation,
+    crate::{
+        py_packaging::{
+            distribution::BinaryLibpythonLinkMode,
+            distribution::{
+                default_distribution_location, DistributionFlavor, PythonDistribution,
+                PythonDistributionLocation,
+            },
+            policy_file::read_policy_file,
+            portable::{portable_python_manifest, DEFAULT_LAUNCHERS},
         },
+        python_advisories::check_python_version,
     },
     anyhow::{anyhow, Result},
     python_packaging::{
-        policy::PythonPackagingPolicy, resource::PythonResource,
+        policy::PythonPackagingPolicy,
+        resource::{BytecodeOptimizationLevel, PythonResource},
         resource_collection::PythonResourceAddCollectionContext,
     },
+    slog::warn,
     starlark::{
         environment::TypeValues,
         eval::call_stack::CallStack,
@@ -35,10 +42,98 @@ use {
             starlark_signature_extraction, starlark_signatures,
         },
     },
-    starlark_dialect_build_targets::{optional_str_arg, optional_type_arg},
-    std::{convert::TryFrom, sync::Arc},
+    starlark_dialect_build_targets::{optional_list_arg, optional_str_arg, optional_type_arg},
+    std::{cell::RefCell, convert::TryFrom, sync::Arc},
+    tugger::{shims::ShimPlatform, starlark::file_resource::FileManifestValue},
 };
 
+/// Python code running pytest with `args` followed by the command line arguments.
+fn pytest_run_command(args: &[String]) -> String {
+    // JSON string arrays are valid Python list literals.
+    format!(
+        "import sys, pytest; sys.exit(pytest.main({} + sys.argv[1:]))",
+        serde_json::to_string(args).expect("strings should serialize to JSON")
+    )
+}
+
+/// Name of the module starting code coverage measurement.
+const COVERAGE_MODULE: &str = "_pyoxidizer_coverage";
+
+/// Python code of a module measuring code coverage with coverage.py.
+///
+/// Measurement starts when the environment variable `data_file_env` holds
+/// the path of the coverage data file, which is written at exit.
+fn coverage_module_source(data_file_env: &str) -> String {
+    format!(
+        r#"import os
+
+_data_file = os.environ.get({})
+
+if _data_file:
+    import atexit
+    import coverage
+
+    _coverage = coverage.Coverage(data_file=_data_file)
+    _coverage.start()
+
+    def _save():
+        _coverage.stop()
+        _coverage.save()
+
+    atexit.register(_save)
+"#,
+        serde_json::to_string(data_file_env).expect("strings should serialize to JSON")
+    )
+}
+
+/// The packaging policy passed as `value`, or the distribution's default.
+fn packaging_policy_or_default(
+    dist: &Arc<dyn PythonDistribution>,
+    value: &Value,
+    label: &str,
+) -> Result<PythonPackagingPolicyValue, ValueError> {
+    if value.get_type() == "NoneType" {
+        Ok(PythonPackagingPolicyValue::new(
+            dist.create_packaging_policy().map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: format!("{:#}", e),
+                    label: label.to_string(),
+                })
+            })?,
+        ))
+    } else {
+        Ok(value
+            .downcast_ref::<PythonPackagingPolicyValue>()
+            .ok_or(ValueError::IncorrectParameterType)?
+            .clone())
+    }
+}
+
+/// The interpreter config passed as `value`, or the distribution's default.
+fn interpreter_config_or_default(
+    dist: &Arc<dyn PythonDistribution>,
+    value: &Value,
+    label: &str,
+) -> Result<PythonInterpreterConfigValue, ValueError> {
+    if value.get_type() == "NoneType" {
+        Ok(PythonInterpreterConfigValue::new(
+            dist.create_python_interpreter_config().map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: format!("{:#}", e),
+                    label: label.to_string(),
+                })
+            })?,
+        ))
+    } else {
+        Ok(value
+            .downcast_ref::<PythonInterpreterConfigValue>()
+            .ok_or(ValueError::IncorrectParameterType)?
+            .clone())
+    }
+}
+
 /// A Starlark Value wrapper for `PythonDistribution` traits.
 pub struct PythonDistributionValue {
     /// Where the distribution should be obtained from.
@@ -82,7 +177,7 @@ impl PythonDistributionValue {
                     .map_err(|e| {
                         ValueError::from(RuntimeError {
                             code: "PYOXIDIZER_BUILD",
-                            message: e.to_string(),
+                            message: format!("{:#}", e),
                             label: label.to_string(),
                         })
                     })?
@@ -146,7 +241,7 @@ impl PythonDistributionValue {
             .map_err(|e| {
                 ValueError::from(RuntimeError {
                     code: "PYOXIDIZER_BUILD",
-                    message: e.to_string(),
+                    message: format!("{:#}", e),
                     label: "default_python_distribution()".to_string(),
                 })
             })?;
@@ -202,7 +297,7 @@ impl PythonDistributionValue {
         let policy = dist.create_packaging_policy().map_err(|e| {
             ValueError::from(RuntimeError {
                 code: "PYOXIDIZER_BUILD",
-                message: e.to_string(),
+                message: format!("{:#}", e),
                 label: "make_python_packaging_policy()".to_string(),
             })
         })?;
@@ -210,6 +305,35 @@ impl PythonDistributionValue {
         Ok(Value::new(PythonPackagingPolicyValue::new(policy)))
     }
 
+    /// PythonDistribution.load_python_packaging_policy(path)
+    fn load_python_packaging_policy_starlark(
+        &mut self,
+        type_values: &TypeValues,
+        path: String,
+    ) -> ValueResult {
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let path = pyoxidizer_context.cwd.join(path);
+
+        let dist = self.resolve_distribution(type_values, "resolve_distribution()")?;
+
+        let policy = dist
+            .create_packaging_policy()
+            .and_then(|base| read_policy_file(&path, base))
+            .map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: format!("{:#}", e),
+                    label: "load_python_packaging_policy()".to_string(),
+                })
+            })?;
+
+        Ok(Value::new(PythonPackagingPolicyValue::new(policy)))
+    }
+
     /// PythonDistribution.make_python_interpreter_config()
     fn make_python_interpreter_config_starlark(&mut self, type_values: &TypeValues) -> ValueResult {
         let dist = self.resolve_distribution(type_values, "resolve_distribution()")?;
@@ -217,7 +341,7 @@ impl PythonDistributionValue {
         let config = dist.create_python_interpreter_config().map_err(|e| {
             ValueError::from(RuntimeError {
                 code: "PYOXIDIZER_BUILD",
-                message: e.to_string(),
+                message: format!("{:#}", e),
                 label: "make_python_packaging_policy()".to_string(),
             })
         })?;
@@ -230,6 +354,9 @@ impl PythonDistributionValue {
     ///     packaging_policy=None,
     ///     config=None,
     /// )
+    ///
+    /// Also backs PythonDistribution.to_python_shared_library(), with `kind`
+    /// controlling what the returned `PythonExecutable` builds.
     #[allow(
         clippy::ptr_arg,
         clippy::too_many_arguments,
@@ -242,6 +369,7 @@ impl PythonDistributionValue {
         name: String,
         packaging_policy: &Value,
         config: &Value,
+        kind: PythonBinaryKind,
     ) -> ValueResult {
         optional_type_arg(
             "packaging_policy",
@@ -257,7 +385,7 @@ impl PythonDistributionValue {
                 dist.create_packaging_policy().map_err(|e| {
                     ValueError::from(RuntimeError {
                         code: "PYOXIDIZER_BUILD",
-                        message: e.to_string(),
+                        message: format!("{:#}", e),
                         label: "to_python_executable_starlark()".to_string(),
                     })
                 })?,
@@ -274,7 +402,7 @@ impl PythonDistributionValue {
                 dist.create_python_interpreter_config().map_err(|e| {
                     ValueError::from(RuntimeError {
                         code: "PYOXIDIZER_BUILD",
-                        message: e.to_string(),
+                        message: format!("{:#}", e),
                         label: "to_python_executable_starlark()".to_string(),
                     })
                 })?,
@@ -291,6 +419,26 @@ impl PythonDistributionValue {
             .downcast_ref::<PyOxidizerEnvironmentContext>()
             .ok_or(ValueError::IncorrectParameterType)?;
 
+        // Development source roots point at the machine performing the build
+        // and have no business in release artifacts.
+        let mut config = config;
+        if pyoxidizer_context.build_release {
+            if !config.inner.dev_source_roots.is_empty() {
+                warn!(
+                    pyoxidizer_context.logger(),
+                    "ignoring dev_source_roots in release build of {}", name
+                );
+                config.inner.dev_source_roots.clear();
+            }
+        } else {
+            config.inner.dev_source_roots = config
+                .inner
+                .dev_source_roots
+                .iter()
+                .map(|p| pyoxidizer_context.cwd.join(p))
+                .collect();
+        }
+
         let python_distributions_path =
             pyoxidizer_context.python_distributions_path(type_values)?;
 
@@ -333,6 +481,22 @@ impl PythonDistributionValue {
             )
         };
 
+        if let Some(baseline) = policy.inner.python_security_baseline() {
+            check_python_version(
+                pyoxidizer_context.logger(),
+                dist.python_version(),
+                baseline,
+                policy.inner.python_security_action(),
+            )
+            .map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: format!("{:#}", e),
+                    label: "to_python_executable()".to_string(),
+                })
+            })?;
+        }
+
         let mut builder = dist
             .as_python_executable_builder(
                 pyoxidizer_context.logger(),
@@ -348,11 +512,14 @@ impl PythonDistributionValue {
             .map_err(|e| {
                 ValueError::from(RuntimeError {
                     code: "PYOXIDIZER_BUILD",
-                    message: e.to_string(),
+                    message: format!("{:#}", e),
                     label: "to_python_executable()".to_string(),
                 })
             })?;
 
+        // Values of resources the callback adds, exposed via exe.resources().
+        let added = RefCell::new(vec![]);
+
         let callback = Box::new(
             |_policy: &PythonPackagingPolicy,
              resource: &PythonResource,
@@ -370,8 +537,11 @@ impl PythonDistributionValue {
                 // When we call python_resource_to_value(), the Starlark
                 // callbacks are automatically called.
 
-                let value = python_resource_to_value(&type_values, &mut cs, resource, &policy)
-                    .map_err(|e| anyhow!("error converting PythonResource to Value: {:?}", e))?;
+                let value =
+                    python_resource_to_value(&type_values, &mut cs, resource, &policy, None)
+                        .map_err(|e| {
+                            anyhow!("error converting PythonResource to Value: {:?}", e)
+                        })?;
 
                 let new_add_context = add_context_for_value(&value, "to_python_executable")
                     .map_err(|e| anyhow!("error obtaining add context from Value: {:?}", e))?
@@ -379,6 +549,10 @@ impl PythonDistributionValue {
 
                 add_context.replace(&new_add_context);
 
+                if new_add_context.include {
+                    added.borrow_mut().push(value);
+                }
+
                 Ok(())
             },
         );
@@ -388,12 +562,231 @@ impl PythonDistributionValue {
             .map_err(|e| {
                 ValueError::from(RuntimeError {
                     code: "PYOXIDIZER_BUILD",
-                    message: e.to_string(),
+                    message: format!("{:#}", e),
                     label: "to_python_executable()".to_string(),
                 })
             })?;
 
-        Ok(Value::new(PythonExecutable::new(builder, policy)))
+        let mut exe = PythonExecutable::new(builder, policy);
+        exe.kind = kind;
+        exe.resources = added.into_inner();
+
+        Ok(Value::new(exe))
+    }
+
+    /// PythonDistribution.to_portable_python(launchers=None)
+    #[allow(clippy::wrong_self_convention)]
+    fn to_portable_python_starlark(
+        &mut self,
+        type_values: &TypeValues,
+        launchers: &Value,
+    ) -> ValueResult {
+        optional_list_arg("launchers", "string", launchers)?;
+
+        let launchers = match launchers.get_type() {
+            "list" => launchers
+                .iter()?
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>(),
+            _ => DEFAULT_LAUNCHERS.iter().map(|x| x.to_string()).collect(),
+        };
+
+        let dist = self.resolve_distribution(type_values, "to_portable_python()")?;
+
+        let install_dir = dist.python_install_dir();
+        let manifest = portable_python_manifest(
+            &install_dir,
+            dist.python_exe_path(),
+            ShimPlatform::from_target_triple(dist.target_triple()),
+            &launchers,
+        )
+        .map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_BUILD",
+                message: format!("{:#}", e),
+                label: "to_portable_python()".to_string(),
+            })
+        })?;
+
+        Ok(Value::new(FileManifestValue {
+            manifest,
+            run_path: dist
+                .python_exe_path()
+                .strip_prefix(&install_dir)
+                .ok()
+                .map(|p| p.to_path_buf()),
+        }))
+    }
+
+    /// PythonDistribution.to_python_test_executable(
+    ///     name,
+    ///     pytest_args=None,
+    ///     packaging_policy=None,
+    ///     config=None,
+    /// )
+    #[allow(clippy::ptr_arg, clippy::wrong_self_convention)]
+    fn to_python_test_executable_starlark(
+        &mut self,
+        type_values: &TypeValues,
+        call_stack: &mut CallStack,
+        name: String,
+        pytest_args: &Value,
+        packaging_policy: &Value,
+        config: &Value,
+    ) -> ValueResult {
+        optional_list_arg("pytest_args", "string", pytest_args)?;
+        optional_type_arg(
+            "packaging_policy",
+            "PythonPackagingPolicy",
+            &packaging_policy,
+        )?;
+        optional_type_arg("config", "PythonInterpreterConfig", &config)?;
+
+        let pytest_args = match pytest_args.get_type() {
+            "list" => pytest_args
+                .iter()?
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>(),
+            _ => vec![],
+        };
+
+        let dist = self.resolve_distribution(type_values, "resolve_distribution()")?;
+
+        let mut policy =
+            packaging_policy_or_default(&dist, packaging_policy, "to_python_test_executable()")?;
+
+        // pytest collects tests from the filesystem and rewrites assertions
+        // in their source code. Only the application's tests are wanted, so
+        // the standard library's test packages stay excluded.
+        policy.inner.set_include_non_distribution_sources(true);
+        let location = policy.inner.filesystem_location();
+        policy.inner.set_resources_location(location);
+        policy.inner.set_resources_location_fallback(None);
+
+        let mut config =
+            interpreter_config_or_default(&dist, config, "to_python_test_executable()")?;
+
+        config.inner.config.run_command = Some(pytest_run_command(&pytest_args));
+
+        let exe_value = self.to_python_executable_starlark(
+            type_values,
+            call_stack,
+            name,
+            &Value::new(policy),
+            &Value::new(config),
+            PythonBinaryKind::Executable,
+        )?;
+
+        let resources = exe_value
+            .downcast_ref::<PythonExecutable>()
+            .ok_or(ValueError::IncorrectParameterType)?
+            .starlark_pip_install(
+                type_values,
+                call_stack,
+                &Value::from(vec!["pytest".to_string()]),
+                &Value::from(NoneType::None),
+            )?;
+
+        exe_value
+            .downcast_mut::<PythonExecutable>()?
+            .ok_or(ValueError::IncorrectParameterType)?
+            .starlark_add_python_resources(type_values, &resources)?;
+
+        Ok(exe_value)
+    }
+
+    /// PythonDistribution.to_python_coverage_executable(
+    ///     name,
+    ///     data_file_env="COVERAGE_FILE",
+    ///     packaging_policy=None,
+    ///     config=None,
+    /// )
+    #[allow(clippy::ptr_arg, clippy::wrong_self_convention)]
+    fn to_python_coverage_executable_starlark(
+        &mut self,
+        type_values: &TypeValues,
+        call_stack: &mut CallStack,
+        name: String,
+        data_file_env: String,
+        packaging_policy: &Value,
+        config: &Value,
+    ) -> ValueResult {
+        optional_type_arg(
+            "packaging_policy",
+            "PythonPackagingPolicy",
+            &packaging_policy,
+        )?;
+        optional_type_arg("config", "PythonInterpreterConfig", &config)?;
+
+        let dist = self.resolve_distribution(type_values, "resolve_distribution()")?;
+
+        let mut policy = packaging_policy_or_default(
+            &dist,
+            packaging_policy,
+            "to_python_coverage_executable()",
+        )?;
+
+        // coverage.py maps measured code to source files through `__file__`
+        // and its line numbers must match the source.
+        policy.inner.set_include_non_distribution_sources(true);
+        let location = policy.inner.filesystem_location();
+        policy.inner.set_resources_location(location);
+        policy.inner.set_resources_location_fallback(None);
+        policy.inner.set_bytecode_optimize_level_zero(true);
+        policy.inner.set_bytecode_optimize_level_one(false);
+        policy.inner.set_bytecode_optimize_level_two(false);
+
+        let mut config =
+            interpreter_config_or_default(&dist, config, "to_python_coverage_executable()")?;
+
+        config.inner.config.optimization_level = Some(BytecodeOptimizationLevel::Zero);
+        // Measure the code of other pre-run modules too.
+        config
+            .inner
+            .pre_run_modules
+            .insert(0, COVERAGE_MODULE.to_string());
+
+        let exe_value = self.to_python_executable_starlark(
+            type_values,
+            call_stack,
+            name,
+            &Value::new(policy),
+            &Value::new(config),
+            PythonBinaryKind::Executable,
+        )?;
+
+        let resources = exe_value
+            .downcast_ref::<PythonExecutable>()
+            .ok_or(ValueError::IncorrectParameterType)?
+            .starlark_pip_install(
+                type_values,
+                call_stack,
+                &Value::from(vec!["coverage".to_string()]),
+                &Value::from(NoneType::None),
+            )?;
+
+        let module = exe_value
+            .downcast_ref::<PythonExecutable>()
+            .ok_or(ValueError::IncorrectParameterType)?
+            .starlark_make_python_module_source(
+                type_values,
+                call_stack,
+                COVERAGE_MODULE.to_string(),
+                coverage_module_source(&data_file_env),
+                false,
+            )?;
+
+        {
+            let mut exe = exe_value
+                .downcast_mut::<PythonExecutable>()?
+                .ok_or(ValueError::IncorrectParameterType)?;
+            exe.starlark_add_python_resources(type_values, &resources)?;
+            exe.starlark_add_python_resource(type_values, &module, "add_python_resource()")?;
+        }
+
+        Ok(exe_value)
     }
 
     pub fn python_resources_starlark(
@@ -406,7 +799,7 @@ impl PythonDistributionValue {
             PythonPackagingPolicyValue::new(dist.create_packaging_policy().map_err(|e| {
                 ValueError::from(RuntimeError {
                     code: "PYOXIDIZER_BUILD",
-                    message: e.to_string(),
+                    message: format!("{:#}", e),
                     label: "python_resources()".to_string(),
                 })
             })?);
@@ -414,7 +807,9 @@ impl PythonDistributionValue {
         let values = dist
             .python_resources()
             .iter()
-            .map(|resource| python_resource_to_value(type_values, call_stack, resource, &policy))
+            .map(|resource| {
+                python_resource_to_value(type_values, call_stack, resource, &policy, None)
+            })
             .collect::<Result<Vec<Value>, ValueError>>()?;
 
         Ok(Value::from(values))
@@ -434,6 +829,13 @@ starlark_module! { python_distribution_module =>
         }
     }
 
+    PythonDistribution.load_python_packaging_policy(env env, this, path: String) {
+        match this.clone().downcast_mut::<PythonDistributionValue>()? {
+            Some(mut dist) => dist.load_python_packaging_policy_starlark(&env, path),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
     PythonDistribution.make_python_interpreter_config(env env, this) {
         match this.clone().downcast_mut::<PythonDistributionValue>()? {
             Some(mut dist) => dist.make_python_interpreter_config_starlark(&env),
@@ -464,6 +866,81 @@ starlark_module! { python_distribution_module =>
                 name,
                 &packaging_policy,
                 &config,
+                PythonBinaryKind::Executable,
+            ),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    #[allow(non_snake_case, clippy::ptr_arg)]
+    PythonDistribution.to_python_shared_library(
+        env env,
+        call_stack cs,
+        this,
+        name: String,
+        packaging_policy=NoneType::None,
+        config=NoneType::None
+    ) {
+        match this.clone().downcast_mut::<PythonDistributionValue>()? {
+            Some(mut dist) =>dist.to_python_executable_starlark(
+                &env,
+                cs,
+                name,
+                &packaging_policy,
+                &config,
+                PythonBinaryKind::SharedLibrary,
+            ),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    #[allow(non_snake_case, clippy::ptr_arg)]
+    PythonDistribution.to_python_coverage_executable(
+        env env,
+        call_stack cs,
+        this,
+        name: String,
+        data_file_env: String = "COVERAGE_FILE".to_string(),
+        packaging_policy=NoneType::None,
+        config=NoneType::None
+    ) {
+        match this.clone().downcast_mut::<PythonDistributionValue>()? {
+            Some(mut dist) =>dist.to_python_coverage_executable_starlark(
+                &env,
+                cs,
+                name,
+                data_file_env,
+                &packaging_policy,
+                &config,
+            ),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    PythonDistribution.to_portable_python(env env, this, launchers=NoneType::None) {
+        match this.clone().downcast_mut::<PythonDistributionValue>()? {
+            Some(mut dist) => dist.to_portable_python_starlark(&env, &launchers),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    PythonDistribution.to_python_test_executable(
+        env env,
+        call_stack cs,
+        this,
+        name: String,
+        pytest_args=NoneType::None,
+        packaging_policy=NoneType::None,
+        config=NoneType::None
+    ) {
+        match this.clone().downcast_mut::<PythonDistributionValue>()? {
+            Some(mut dist) =>dist.to_python_test_executable_starlark(
+                &env,
+                cs,
+                name,
+                &pytest_args,
+                &packaging_policy,
+                &config,
             ),
             None => Err(ValueError::IncorrectParameterType),
         }
@@ -607,6 +1084,117 @@ mod tests {
         assert_eq!(x.source, wanted);
     }
 
+    #[test]
+    fn test_pytest_run_command() {
+        assert_eq!(
+            pytest_run_command(&["-x".to_string(), "--pyargs".to_string(), "my\"app".to_string()]),
+            "import sys, pytest; sys.exit(pytest.main([\"-x\",\"--pyargs\",\"my\\\"app\"] + sys.argv[1:]))"
+        );
+    }
+
+    #[test]
+    fn test_to_python_test_executable() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        env.eval("dist = default_python_distribution()")?;
+        assert!(env
+            .eval("dist.to_python_test_executable('tests', pytest_args = '-x')")
+            .is_err());
+
+        let exe_value = env
+            .eval("dist.to_python_test_executable('tests', pytest_args = ['--pyargs', 'myapp'])")?;
+        let exe = exe_value.downcast_ref::<PythonExecutable>().unwrap();
+
+        let policy = exe.python_packaging_policy();
+        assert!(!policy.inner.include_test());
+        assert!(policy.inner.include_non_distribution_sources());
+        assert_eq!(
+            policy.inner.resources_location(),
+            &policy.inner.filesystem_location()
+        );
+        assert_eq!(
+            exe.exe.embedded_python_config().config.run_command,
+            Some(pytest_run_command(&[
+                "--pyargs".to_string(),
+                "myapp".to_string()
+            ]))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_coverage_module_source() {
+        let source = coverage_module_source("MYAPP_COVERAGE");
+        assert!(source
+            .as_str()
+            .contains("_data_file = os.environ.get(\"MYAPP_COVERAGE\")\n"));
+        assert!(source
+            .as_str()
+            .contains("    _coverage = coverage.Coverage(data_file=_data_file)\n"));
+        assert!(source.as_str().ends_with("    atexit.register(_save)\n"));
+    }
+
+    #[test]
+    fn test_to_python_coverage_executable() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("config = dist.make_python_interpreter_config()")?;
+        env.eval("config.run_module = 'myapp'")?;
+        env.eval("config.pre_run_modules = ['myapp._patches']")?;
+
+        let exe_value = env.eval("dist.to_python_coverage_executable('myapp', config = config)")?;
+        let exe = exe_value.downcast_ref::<PythonExecutable>().unwrap();
+
+        let policy = exe.python_packaging_policy();
+        assert!(policy.inner.include_non_distribution_sources());
+        assert!(!policy.inner.bytecode_optimize_level_two());
+        assert_eq!(
+            policy.inner.resources_location(),
+            &policy.inner.filesystem_location()
+        );
+
+        let embedded = exe.exe.embedded_python_config();
+        assert_eq!(embedded.config.run_module, Some("myapp".to_string()));
+        assert_eq!(
+            embedded.config.optimization_level,
+            Some(BytecodeOptimizationLevel::Zero)
+        );
+        assert_eq!(
+            embedded.pre_run_modules,
+            vec![COVERAGE_MODULE.to_string(), "myapp._patches".to_string()]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_portable_python() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        env.eval("dist = default_python_distribution()")?;
+
+        let value = env.eval("dist.to_portable_python()")?;
+        let manifest = value.downcast_ref::<FileManifestValue>().unwrap();
+        let run_path = manifest.run_path.clone().unwrap();
+        assert!(manifest.manifest.has_path(&run_path));
+
+        let launcher_dir = run_path.parent().unwrap();
+        let launcher = if cfg!(windows) {
+            "python3.cmd"
+        } else {
+            "python3"
+        };
+        assert!(manifest.manifest.has_path(&launcher_dir.join(launcher)));
+
+        assert!(env
+            .eval("dist.to_portable_python(launchers = ['bin/python'])")
+            .is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_make_python_packaging_policy() {
         let policy = starlark_ok("default_python_distribution().make_python_packaging_policy()");
@@ -6,18 +6,33 @@ use {
     super::{
         env::{get_context, PyOxidizerEnvironmentContext},
         python_embedded_resources::PythonEmbeddedResources,
+        python_library::PythonLibrary,
         python_packaging_policy::PythonPackagingPolicyValue,
         python_resource::{
-            is_resource_starlark_compatible, python_resource_to_value, FileValue,
+            add_context_for_value, python_resources_to_values, FileValue, GettextCatalogValue,
             PythonExtensionModuleValue, PythonModuleSourceValue,
             PythonPackageDistributionResourceValue, PythonPackageResourceValue,
             ResourceCollectionContext,
         },
-        util::ToOptional,
+        python_resources_snapshot::PythonResourcesSnapshot,
+        python_wasi_bundle::PythonWasiBundle,
+        util::{ToOptional, TryToOptional},
+    },
+    crate::{
+        analyze::MacOsVersion,
+        project_building::{build_python_executable, executable_filename},
+        py_packaging::{
+            binary::PythonBinaryBuilder,
+            config::{ExternalAsset, PackedResourcesEmbedding},
+            packaging_tool::InstallGroup,
+            windows_runtime::WindowsRuntimeDllsMode,
+        },
     },
-    crate::{project_building::build_python_executable, py_packaging::binary::PythonBinaryBuilder},
     anyhow::{Context, Result},
-    python_packaging::resource::{DataLocation, PythonModuleSource},
+    python_packaging::{
+        resource::{DataLocation, PythonModuleSource, SharedLibrary},
+        resource_collection::PythonResourceAddCollectionContext,
+    },
     slog::{info, warn},
     starlark::{
         environment::TypeValues,
@@ -35,33 +50,116 @@ use {
         },
     },
     starlark_dialect_build_targets::{
-        optional_dict_arg, optional_list_arg, required_list_arg, BuildContext, BuildTarget,
-        ResolvedTarget, RunMode,
+        optional_dict_arg, optional_list_arg, optional_str_arg, required_dict_arg,
+        required_list_arg, BuildContext, BuildTarget, ResolvedTarget, RunMode,
     },
     std::{
-        collections::HashMap,
+        collections::{HashMap, HashSet},
+        convert::TryFrom,
         io::Write,
         ops::Deref,
         path::{Path, PathBuf},
     },
+    tugger::{
+        file_resource::FileManifest,
+        shims::{ConsoleScriptShims, ShimPlatform},
+        starlark::file_resource::FileManifestValue,
+    },
 };
 
+/// The kind of binary a `PythonExecutable` produces when built.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PythonBinaryKind {
+    /// An executable program.
+    Executable,
+
+    /// A shared library exporting a C API for embedding Python.
+    SharedLibrary,
+}
+
 /// Represents a builder for a Python executable.
 pub struct PythonExecutable {
     pub exe: Box<dyn PythonBinaryBuilder>,
 
+    /// What to produce when this target is built.
+    pub kind: PythonBinaryKind,
+
     /// The Starlark Value for the Python packaging policy.
     // This is stored as a Vec because I couldn't figure out how to implement
     // values_for_descendant_check_and_freeze() without the borrow checker
     // complaining due to a temporary vec/array.
     policy: Vec<Value>,
+
+    /// Resource values added to the resource collection, in the order they were added.
+    ///
+    /// Values are copies taken when added, so later changes to the added
+    /// values aren't reflected. Adding a resource again replaces the value
+    /// of the earlier addition, as it does in the collection.
+    pub resources: Vec<Value>,
+}
+
+/// Obtain the key of the resource collection entry a resource value is added to.
+fn resource_collection_key(value: &Value) -> Option<String> {
+    match value.get_type() {
+        FileValue::TYPE => value
+            .downcast_ref::<FileValue>()
+            .map(|v| v.inner.path_string()),
+        PythonModuleSourceValue::TYPE => value
+            .downcast_ref::<PythonModuleSourceValue>()
+            .map(|v| v.inner.name.clone()),
+        PythonPackageResourceValue::TYPE => value
+            .downcast_ref::<PythonPackageResourceValue>()
+            .map(|v| v.inner.leaf_package.clone()),
+        PythonPackageDistributionResourceValue::TYPE => value
+            .downcast_ref::<PythonPackageDistributionResourceValue>()
+            .map(|v| v.inner.package.clone()),
+        PythonExtensionModuleValue::TYPE => value
+            .downcast_ref::<PythonExtensionModuleValue>()
+            .map(|v| v.inner.name.clone()),
+        GettextCatalogValue::TYPE => value
+            .downcast_ref::<GettextCatalogValue>()
+            .map(|v| v.inner.leaf_package.clone()),
+        _ => None,
+    }
+}
+
+/// Obtain the identity of the resource a resource value describes.
+///
+/// Unlike collection keys, which are shared by all resources of a package,
+/// this distinguishes each resource of an entry.
+fn resource_value_identity(value: &Value) -> Option<(&'static str, String)> {
+    let name = match value.get_type() {
+        FileValue::TYPE => value
+            .downcast_ref::<FileValue>()
+            .map(|v| v.as_python_resource().full_name()),
+        PythonModuleSourceValue::TYPE => value
+            .downcast_ref::<PythonModuleSourceValue>()
+            .map(|v| v.as_python_resource().full_name()),
+        PythonPackageResourceValue::TYPE => value
+            .downcast_ref::<PythonPackageResourceValue>()
+            .map(|v| v.as_python_resource().full_name()),
+        PythonPackageDistributionResourceValue::TYPE => value
+            .downcast_ref::<PythonPackageDistributionResourceValue>()
+            .map(|v| v.as_python_resource().full_name()),
+        PythonExtensionModuleValue::TYPE => value
+            .downcast_ref::<PythonExtensionModuleValue>()
+            .map(|v| v.as_python_resource().full_name()),
+        GettextCatalogValue::TYPE => value
+            .downcast_ref::<GettextCatalogValue>()
+            .map(|v| v.as_python_resource().full_name()),
+        _ => None,
+    }?;
+
+    Some((value.get_type(), name))
 }
 
 impl PythonExecutable {
     pub fn new(exe: Box<dyn PythonBinaryBuilder>, policy: PythonPackagingPolicyValue) -> Self {
         Self {
             exe,
+            kind: PythonBinaryKind::Executable,
             policy: vec![Value::new(policy)],
+            resources: vec![],
         }
     }
 
@@ -81,15 +179,46 @@ impl TypedValue for PythonExecutable {
     fn values_for_descendant_check_and_freeze<'a>(
         &'a self,
     ) -> Box<dyn Iterator<Item = Value> + 'a> {
-        Box::new(self.policy.iter().cloned())
+        Box::new(self.policy.iter().chain(self.resources.iter()).cloned())
     }
 
     fn get_attr(&self, attribute: &str) -> ValueResult {
         match attribute {
+            "bundle_tzdata" => Ok(Value::from(self.exe.bundle_tzdata())),
+            "external_runtime_path" => match self.exe.external_runtime_path() {
+                Some(value) => Ok(Value::from(value.to_string())),
+                None => Ok(Value::from(NoneType::None)),
+            },
+            "linux_minimum_glibc_version" => match self.exe.linux_minimum_glibc_version() {
+                Some(value) => Ok(Value::from(value.to_string())),
+                None => Ok(Value::from(NoneType::None)),
+            },
+            "macos_deployment_target" => match self.exe.macos_deployment_target() {
+                Some(value) => Ok(Value::from(value.to_string())),
+                None => Ok(Value::from(NoneType::None)),
+            },
+            "max_size_mb" => match self.exe.max_size_mb() {
+                Some(value) => Ok(Value::from(value as i64)),
+                None => Ok(Value::from(NoneType::None)),
+            },
+            "packed_resources_embedding" => Ok(Value::from(
+                self.exe.packed_resources_embedding().to_string(),
+            )),
             "tcl_files_path" => match self.exe.tcl_files_path() {
                 Some(value) => Ok(Value::from(value.to_string())),
                 None => Ok(Value::from(NoneType::None)),
             },
+            "version" => match self.exe.version() {
+                Some(value) => Ok(Value::from(value.to_string())),
+                None => Ok(Value::from(NoneType::None)),
+            },
+            "windows_service_name" => match self.exe.windows_service_name() {
+                Some(value) => Ok(Value::from(value.to_string())),
+                None => Ok(Value::from(NoneType::None)),
+            },
+            "windows_runtime_dlls_mode" => Ok(Value::from(
+                self.exe.windows_runtime_dlls_mode().to_string(),
+            )),
             "windows_subsystem" => Ok(Value::from(self.exe.windows_subsystem())),
             _ => Err(ValueError::OperationNotSupported {
                 op: UnsupportedOperation::GetAttr(attribute.to_string()),
@@ -101,7 +230,16 @@ impl TypedValue for PythonExecutable {
 
     fn has_attr(&self, attribute: &str) -> Result<bool, ValueError> {
         Ok(match attribute {
+            "bundle_tzdata" => true,
+            "external_runtime_path" => true,
+            "linux_minimum_glibc_version" => true,
+            "macos_deployment_target" => true,
+            "max_size_mb" => true,
+            "packed_resources_embedding" => true,
             "tcl_files_path" => true,
+            "version" => true,
+            "windows_service_name" => true,
+            "windows_runtime_dlls_mode" => true,
             "windows_subsystem" => true,
             _ => false,
         })
@@ -109,18 +247,140 @@ impl TypedValue for PythonExecutable {
 
     fn set_attr(&mut self, attribute: &str, value: Value) -> Result<(), ValueError> {
         match attribute {
+            "bundle_tzdata" => {
+                self.exe.set_bundle_tzdata(value.to_bool());
+
+                Ok(())
+            }
+            "external_runtime_path" => {
+                self.exe
+                    .set_external_runtime_path(value.to_optional())
+                    .map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                            message: e.to_string(),
+                            label: format!("{}.{}", Self::TYPE, attribute),
+                        })
+                    })?;
+
+                Ok(())
+            }
+            "linux_minimum_glibc_version" => {
+                self.exe
+                    .set_linux_minimum_glibc_version(value.to_optional())
+                    .map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                            message: format!("{:#}", e),
+                            label: format!("{}.{}", Self::TYPE, attribute),
+                        })
+                    })?;
+
+                Ok(())
+            }
+            "macos_deployment_target" => {
+                let value: Option<String> = value.to_optional();
+
+                let version = match value {
+                    Some(value) => Some(MacOsVersion::try_from(value.as_str()).map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                            message: e,
+                            label: format!("{}.{}", Self::TYPE, attribute),
+                        })
+                    })?),
+                    None => None,
+                };
+
+                self.exe.set_macos_deployment_target(version);
+
+                Ok(())
+            }
+            "max_size_mb" => {
+                let value: Option<i32> = value.try_to_optional()?;
+
+                let value = match value {
+                    Some(value) if value > 0 => {
+                        Some(u64::try_from(value).map_err(|_| ValueError::IntegerOverflow)?)
+                    }
+                    Some(_) => {
+                        return Err(ValueError::from(RuntimeError {
+                            code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                            message: "max_size_mb must be positive".to_string(),
+                            label: format!("{}.{}", Self::TYPE, attribute),
+                        }));
+                    }
+                    None => None,
+                };
+
+                self.exe.set_max_size_mb(value);
+
+                Ok(())
+            }
+            "packed_resources_embedding" => {
+                let embedding = PackedResourcesEmbedding::try_from(value.to_string().as_str())
+                    .map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                            message: e,
+                            label: format!("{}.{}", Self::TYPE, attribute),
+                        })
+                    })?;
+
+                self.exe.set_packed_resources_embedding(embedding);
+
+                Ok(())
+            }
             "tcl_files_path" => {
                 self.exe.set_tcl_files_path(value.to_optional());
 
                 Ok(())
             }
+            "version" => {
+                self.exe.set_version(value.to_optional()).map_err(|e| {
+                    ValueError::from(RuntimeError {
+                        code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                        message: format!("{:#}", e),
+                        label: format!("{}.{}", Self::TYPE, attribute),
+                    })
+                })?;
+
+                Ok(())
+            }
+            "windows_service_name" => {
+                self.exe
+                    .set_windows_service_name(value.to_optional())
+                    .map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                            message: format!("{:#}", e),
+                            label: format!("{}.{}", Self::TYPE, attribute),
+                        })
+                    })?;
+
+                Ok(())
+            }
+            "windows_runtime_dlls_mode" => {
+                let mode =
+                    WindowsRuntimeDllsMode::try_from(value.to_string().as_str()).map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                            message: e,
+                            label: format!("{}.{}", Self::TYPE, attribute),
+                        })
+                    })?;
+
+                self.exe.set_windows_runtime_dlls_mode(mode);
+
+                Ok(())
+            }
             "windows_subsystem" => {
                 self.exe
                     .set_windows_subsystem(value.to_string().as_str())
                     .map_err(|e| {
                         ValueError::from(RuntimeError {
                             code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
-                            message: e.to_string(),
+                            message: format!("{:#}", e),
                             label: format!("{}.{}", Self::TYPE, attribute),
                         })
                     })?;
@@ -138,6 +398,14 @@ impl TypedValue for PythonExecutable {
 
 impl BuildTarget for PythonExecutable {
     fn build(&mut self, context: &dyn BuildContext) -> Result<ResolvedTarget> {
+        if self.kind == PythonBinaryKind::SharedLibrary {
+            return PythonLibrary {
+                exe: self.exe.clone_trait(),
+                static_library: false,
+            }
+            .build(context);
+        }
+
         // Build an executable by writing out a temporary Rust project
         // and building it.
         let build = build_python_executable(
@@ -181,7 +449,7 @@ impl PythonExecutable {
         source: String,
         is_package: bool,
     ) -> ValueResult {
-        let module = PythonModuleSource {
+        let mut module = PythonModuleSource {
             name,
             source: DataLocation::Memory(source.into_bytes()),
             is_package,
@@ -190,9 +458,11 @@ impl PythonExecutable {
             is_test: false,
         };
 
+        let policy = self.python_packaging_policy();
+        policy.transform_module_source(type_values, call_stack, &mut module)?;
+
         let mut value = PythonModuleSourceValue::new(module);
-        self.python_packaging_policy()
-            .apply_to_resource(type_values, call_stack, &mut value)?;
+        policy.apply_to_resource(type_values, call_stack, &mut value, None)?;
 
         Ok(Value::new(value))
     }
@@ -213,6 +483,15 @@ impl PythonExecutable {
             .downcast_ref::<PyOxidizerEnvironmentContext>()
             .ok_or(ValueError::IncorrectParameterType)?;
 
+        if pyoxidizer_context.dry_run {
+            warn!(
+                pyoxidizer_context.logger(),
+                "dry run: would run pip download {}",
+                args.join(" ")
+            );
+            return Ok(Value::from(Vec::<Value>::new()));
+        }
+
         let resources = self
             .exe
             .pip_download(
@@ -226,18 +505,16 @@ impl PythonExecutable {
                     message: format!("error running pip install: {}", e),
                     label: "pip_install()".to_string(),
                 })
-            })?
-            .iter()
-            .filter(|r| is_resource_starlark_compatible(r))
-            .map(|r| {
-                python_resource_to_value(
-                    type_values,
-                    call_stack,
-                    r,
-                    &self.python_packaging_policy(),
-                )
-            })
-            .collect::<Result<Vec<Value>, ValueError>>()?;
+            })?;
+
+        let resources = python_resources_to_values(
+            type_values,
+            call_stack,
+            &resources,
+            &self.python_packaging_policy(),
+            None,
+            "pip_download()",
+        )?;
 
         Ok(Value::from(resources))
     }
@@ -274,6 +551,19 @@ impl PythonExecutable {
             .downcast_ref::<PyOxidizerEnvironmentContext>()
             .ok_or(ValueError::IncorrectParameterType)?;
 
+        pyoxidizer_context.check_trust("pip_install()", |trust| {
+            trust.check_pip_install(&args, &extra_envs, &pyoxidizer_context.cwd)
+        })?;
+
+        if pyoxidizer_context.dry_run {
+            warn!(
+                pyoxidizer_context.logger(),
+                "dry run: would run pip install {}",
+                args.join(" ")
+            );
+            return Ok(Value::from(Vec::<Value>::new()));
+        }
+
         let resources = self
             .exe
             .pip_install(
@@ -281,6 +571,7 @@ impl PythonExecutable {
                 pyoxidizer_context.verbose,
                 &args,
                 &extra_envs,
+                &pyoxidizer_context.cwd,
             )
             .map_err(|e| {
                 ValueError::from(RuntimeError {
@@ -288,22 +579,342 @@ impl PythonExecutable {
                     message: format!("error running pip install: {}", e),
                     label: "pip_install()".to_string(),
                 })
-            })?
+            })?;
+
+        let resources = python_resources_to_values(
+            type_values,
+            call_stack,
+            &resources,
+            &self.python_packaging_policy(),
+            None,
+            "pip_install()",
+        )?;
+
+        Ok(Value::from(resources))
+    }
+
+    /// PythonExecutable.install_groups(groups, extra_envs=None)
+    pub fn starlark_install_groups(
+        &self,
+        type_values: &TypeValues,
+        call_stack: &mut CallStack,
+        groups: &Value,
+        extra_envs: &Value,
+    ) -> ValueResult {
+        if groups.get_type() != "list" {
+            return Err(ValueError::from(RuntimeError {
+                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                message: format!(
+                    "function expects a list for groups; got type {}",
+                    groups.get_type()
+                ),
+                label: format!("expected type list; got {}", groups.get_type()),
+            }));
+        }
+        optional_dict_arg("extra_envs", "string", "string", &extra_envs)?;
+
+        let extra_envs = match extra_envs.get_type() {
+            "dict" => extra_envs
+                .iter()?
+                .iter()
+                .map(|key| {
+                    let k = key.to_string();
+                    let v = extra_envs.at(key).unwrap().to_string();
+                    (k, v)
+                })
+                .collect(),
+            "NoneType" => HashMap::new(),
+            _ => panic!("should have validated type above"),
+        };
+
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let groups = groups
+            .iter()?
             .iter()
-            .filter(|r| is_resource_starlark_compatible(r))
-            .map(|r| {
-                python_resource_to_value(
-                    type_values,
-                    call_stack,
-                    r,
-                    &self.python_packaging_policy(),
-                )
+            .map(|group| match group.get_type() {
+                "list" => {
+                    required_list_arg("groups", "string", &group)?;
+
+                    Ok(InstallGroup::Pip(
+                        group.iter()?.iter().map(|x| x.to_string()).collect(),
+                    ))
+                }
+                "string" => {
+                    let path = PathBuf::from(group.to_string());
+
+                    Ok(InstallGroup::SetupPy(if path.is_absolute() {
+                        path
+                    } else {
+                        PathBuf::from(&pyoxidizer_context.cwd).join(path)
+                    }))
+                }
+                t => Err(ValueError::from(RuntimeError {
+                    code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                    message: format!(
+                        "groups must contain lists of pip arguments or setup.py directories; got {}",
+                        t
+                    ),
+                    label: "install_groups()".to_string(),
+                })),
             })
-            .collect::<Result<Vec<Value>, ValueError>>()?;
+            .collect::<Result<Vec<_>, ValueError>>()?;
+
+        pyoxidizer_context.check_trust("install_groups()", |trust| {
+            for group in &groups {
+                match group {
+                    InstallGroup::Pip(args) => {
+                        trust.check_pip_install(args, &extra_envs, &pyoxidizer_context.cwd)?
+                    }
+                    InstallGroup::SetupPy(path) => trust.check_package_dir(path)?,
+                }
+            }
+
+            Ok(())
+        })?;
+
+        if pyoxidizer_context.dry_run {
+            for group in &groups {
+                match group {
+                    InstallGroup::Pip(args) => warn!(
+                        pyoxidizer_context.logger(),
+                        "dry run: would run pip install {}",
+                        args.join(" ")
+                    ),
+                    InstallGroup::SetupPy(path) => warn!(
+                        pyoxidizer_context.logger(),
+                        "dry run: would run setup.py install in {}",
+                        path.display()
+                    ),
+                }
+            }
+            return Ok(Value::from(Vec::<Value>::new()));
+        }
+
+        let resources = self
+            .exe
+            .install_groups(
+                pyoxidizer_context.logger(),
+                pyoxidizer_context.verbose,
+                &groups,
+                &extra_envs,
+                &pyoxidizer_context.cwd,
+            )
+            .map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: "PIP_INSTALL_ERROR",
+                    message: format!("error installing groups: {}", e),
+                    label: "install_groups()".to_string(),
+                })
+            })?;
+
+        let resources = python_resources_to_values(
+            type_values,
+            call_stack,
+            &resources,
+            &self.python_packaging_policy(),
+            None,
+            "install_groups()",
+        )?;
 
         Ok(Value::from(resources))
     }
 
+    /// PythonExecutable.cargo_features(features)
+    pub fn starlark_cargo_features(&mut self, features: &Value) -> ValueResult {
+        required_list_arg("features", "string", &features)?;
+
+        for feature in features.iter()?.iter() {
+            self.exe
+                .add_cargo_feature(&feature.to_string())
+                .map_err(|e| {
+                    ValueError::from(RuntimeError {
+                        code: "PYOXIDIZER_BUILD",
+                        message: format!("{:#}", e),
+                        label: "cargo_features()".to_string(),
+                    })
+                })?;
+        }
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// PythonExecutable.build_env(env)
+    pub fn starlark_build_env(&mut self, env: &Value) -> ValueResult {
+        required_dict_arg("env", "string", "string", &env)?;
+
+        for key in env.iter()?.iter() {
+            let value = env.at(key.clone())?;
+
+            self.exe
+                .set_build_env(&key.to_string(), &value.to_string())
+                .map_err(|e| {
+                    ValueError::from(RuntimeError {
+                        code: "PYOXIDIZER_BUILD",
+                        message: format!("{:#}", e),
+                        label: "build_env()".to_string(),
+                    })
+                })?;
+        }
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// PythonExecutable.add_qt_support(binding="pyside6")
+    pub fn starlark_add_qt_support(&mut self, binding: String) -> ValueResult {
+        self.exe.add_qt_support(&binding).map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_BUILD",
+                message: format!("{:#}", e),
+                label: "add_qt_support()".to_string(),
+            })
+        })?;
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// PythonExecutable.add_external_asset(name, url, sha256, path=None)
+    pub fn starlark_add_external_asset(
+        &mut self,
+        name: String,
+        url: String,
+        sha256: String,
+        path: &Value,
+    ) -> ValueResult {
+        let path =
+            optional_str_arg("path", path)?.unwrap_or_else(|| format!("$ORIGIN/assets/{}", name));
+
+        self.exe
+            .add_external_asset(ExternalAsset {
+                name,
+                url,
+                sha256,
+                path: PathBuf::from(path),
+            })
+            .map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: format!("{:#}", e),
+                    label: "add_external_asset()".to_string(),
+                })
+            })?;
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// PythonExecutable.add_binary_metadata(name, data=None, path=None)
+    pub fn starlark_add_binary_metadata(
+        &mut self,
+        type_values: &TypeValues,
+        name: String,
+        data: &Value,
+        path: &Value,
+    ) -> ValueResult {
+        let data = optional_str_arg("data", data)?;
+        let path = optional_str_arg("path", path)?;
+
+        let data = match (data, path) {
+            (Some(data), None) => data.into_bytes(),
+            (None, Some(path)) => {
+                let pyoxidizer_context_value = get_context(type_values)?;
+                let pyoxidizer_context = pyoxidizer_context_value
+                    .downcast_ref::<PyOxidizerEnvironmentContext>()
+                    .ok_or(ValueError::IncorrectParameterType)?;
+
+                let path = pyoxidizer_context.cwd.join(path);
+
+                std::fs::read(&path).map_err(|e| {
+                    ValueError::from(RuntimeError {
+                        code: "PYOXIDIZER_BUILD",
+                        message: format!("reading {}: {}", path.display(), e),
+                        label: "add_binary_metadata()".to_string(),
+                    })
+                })?
+            }
+            _ => {
+                return Err(ValueError::from(RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: "exactly one of data or path must be specified".to_string(),
+                    label: "add_binary_metadata()".to_string(),
+                }));
+            }
+        };
+
+        self.exe.add_binary_metadata(&name, &data).map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_BUILD",
+                message: format!("{:#}", e),
+                label: "add_binary_metadata()".to_string(),
+            })
+        })?;
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// PythonExecutable.add_shared_library(path, searched_by=None)
+    pub fn starlark_add_shared_library(
+        &mut self,
+        type_values: &TypeValues,
+        path: String,
+        searched_by: &Value,
+    ) -> ValueResult {
+        optional_list_arg("searched_by", "string", &searched_by)?;
+
+        let searched_by = match searched_by.get_type() {
+            "list" => searched_by.iter()?.iter().map(|x| x.to_string()).collect(),
+            "NoneType" => Vec::new(),
+            _ => panic!("type should have been validated above"),
+        };
+
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let path = pyoxidizer_context.cwd.join(path);
+
+        let filename = match path.file_name() {
+            Some(filename) if path.is_file() => PathBuf::from(filename),
+            _ => {
+                return Err(ValueError::from(RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: format!("{} is not a file", path.display()),
+                    label: "add_shared_library()".to_string(),
+                }));
+            }
+        };
+
+        // The library name is the filename without its `lib` prefix and
+        // extensions, e.g. `libfoo.so.1` is `foo`.
+        let stem = filename.to_string_lossy();
+        let stem = stem.split('.').next().unwrap_or_default();
+        let name = match stem.strip_prefix("lib") {
+            Some(name) if !name.is_empty() => name,
+            _ => stem,
+        };
+
+        let library = SharedLibrary {
+            name: name.to_string(),
+            data: DataLocation::Path(path),
+            filename: Some(filename),
+        };
+
+        self.exe
+            .add_shared_library(&library, &searched_by)
+            .map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: format!("{:#}", e),
+                    label: "add_shared_library()".to_string(),
+                })
+            })?;
+
+        Ok(Value::new(NoneType::None))
+    }
+
     /// PythonExecutable.read_package_root(path, packages)
     pub fn starlark_read_package_root(
         &self,
@@ -334,18 +945,16 @@ impl PythonExecutable {
                     message: format!("could not find resources: {}", e),
                     label: "read_package_root()".to_string(),
                 })
-            })?
-            .iter()
-            .filter(|r| is_resource_starlark_compatible(r))
-            .map(|r| {
-                python_resource_to_value(
-                    type_values,
-                    call_stack,
-                    r,
-                    &self.python_packaging_policy(),
-                )
-            })
-            .collect::<Result<Vec<Value>, ValueError>>()?;
+            })?;
+
+        let resources = python_resources_to_values(
+            type_values,
+            call_stack,
+            &resources,
+            &self.python_packaging_policy(),
+            Some(Path::new(&path)),
+            "read_package_root()",
+        )?;
 
         Ok(Value::from(resources))
     }
@@ -371,18 +980,16 @@ impl PythonExecutable {
                     message: format!("could not find resources: {}", e),
                     label: "read_virtualenv()".to_string(),
                 })
-            })?
-            .iter()
-            .filter(|r| is_resource_starlark_compatible(r))
-            .map(|r| {
-                python_resource_to_value(
-                    type_values,
-                    call_stack,
-                    r,
-                    &self.python_packaging_policy(),
-                )
-            })
-            .collect::<Result<Vec<Value>, ValueError>>()?;
+            })?;
+
+        let resources = python_resources_to_values(
+            type_values,
+            call_stack,
+            &resources,
+            &self.python_packaging_policy(),
+            Some(Path::new(&path)),
+            "read_virtualenv()",
+        )?;
 
         Ok(Value::from(resources))
     }
@@ -435,6 +1042,19 @@ impl PythonExecutable {
             PathBuf::from(&pyoxidizer_context.cwd).join(package_path)
         };
 
+        pyoxidizer_context.check_trust("setup_py_install()", |trust| {
+            trust.check_package_dir(&package_path)
+        })?;
+
+        if pyoxidizer_context.dry_run {
+            warn!(
+                pyoxidizer_context.logger(),
+                "dry run: would run setup.py install in {}",
+                package_path.display()
+            );
+            return Ok(Value::from(Vec::<Value>::new()));
+        }
+
         let resources = self
             .exe
             .setup_py_install(
@@ -447,21 +1067,19 @@ impl PythonExecutable {
             .map_err(|e| {
                 ValueError::from(RuntimeError {
                     code: "SETUP_PY_ERROR",
-                    message: e.to_string(),
+                    message: format!("{:#}", e),
                     label: "setup_py_install()".to_string(),
                 })
-            })?
-            .iter()
-            .filter(|r| is_resource_starlark_compatible(r))
-            .map(|r| {
-                python_resource_to_value(
-                    type_values,
-                    call_stack,
-                    r,
-                    &self.python_packaging_policy(),
-                )
-            })
-            .collect::<Result<Vec<Value>, ValueError>>()?;
+            })?;
+
+        let resources = python_resources_to_values(
+            type_values,
+            call_stack,
+            &resources,
+            &self.python_packaging_policy(),
+            None,
+            "setup_py_install()",
+        )?;
 
         warn!(
             pyoxidizer_context.logger(),
@@ -472,6 +1090,47 @@ impl PythonExecutable {
         Ok(Value::from(resources))
     }
 
+    /// Log where a resource ended up if it is being traced.
+    fn trace_added_resource(
+        &self,
+        context: &PyOxidizerEnvironmentContext,
+        name: &str,
+        collection_key: &str,
+        add_context: &Option<PythonResourceAddCollectionContext>,
+    ) {
+        if !context.trace_resource(name) {
+            return;
+        }
+
+        match add_context {
+            Some(add_context) if !add_context.include => {
+                warn!(
+                    context.logger(),
+                    "trace {}: not added because add context excludes it", name
+                );
+            }
+            _ => {
+                if let Some((_, resource)) = self
+                    .exe
+                    .iter_resources()
+                    .find(|(key, _)| key.as_str() == collection_key)
+                {
+                    warn!(
+                        context.logger(),
+                        "trace {}: collected as {}",
+                        name,
+                        resource.describe()
+                    );
+                } else {
+                    warn!(
+                        context.logger(),
+                        "trace {}: not present in resource collection", name
+                    );
+                }
+            }
+        }
+    }
+
     pub fn add_python_module_source(
         &mut self,
         context: &PyOxidizerEnvironmentContext,
@@ -487,11 +1146,18 @@ impl PythonExecutable {
             .map_err(|e| {
                 ValueError::from(RuntimeError {
                     code: "PYOXIDIZER_BUILD",
-                    message: e.to_string(),
+                    message: format!("{:#}", e),
                     label: label.to_string(),
                 })
             })?;
 
+        self.trace_added_resource(
+            context,
+            &module.as_python_resource().full_name(),
+            &module.inner.name,
+            module.add_collection_context(),
+        );
+
         Ok(Value::new(NoneType::None))
     }
 
@@ -511,11 +1177,18 @@ impl PythonExecutable {
             .map_err(|e| {
                 ValueError::from(RuntimeError {
                     code: "PYOXIDIZER_BUILD",
-                    message: e.to_string(),
+                    message: format!("{:#}", e),
                     label: label.to_string(),
                 })
             })?;
 
+        self.trace_added_resource(
+            context,
+            &resource.as_python_resource().full_name(),
+            &resource.inner.leaf_package,
+            resource.add_collection_context(),
+        );
+
         Ok(Value::new(NoneType::None))
     }
 
@@ -539,11 +1212,18 @@ impl PythonExecutable {
             .map_err(|e| {
                 ValueError::from(RuntimeError {
                     code: "PYOXIDIZER_BUILD",
-                    message: e.to_string(),
+                    message: format!("{:#}", e),
                     label: label.to_string(),
                 })
             })?;
 
+        self.trace_added_resource(
+            context,
+            &resource.as_python_resource().full_name(),
+            &resource.inner.package,
+            resource.add_collection_context(),
+        );
+
         Ok(Value::new(NoneType::None))
     }
 
@@ -562,11 +1242,18 @@ impl PythonExecutable {
             .map_err(|e| {
                 ValueError::from(RuntimeError {
                     code: "PYOXIDIZER_BUILD",
-                    message: e.to_string(),
+                    message: format!("{:#}", e),
                     label: label.to_string(),
                 })
             })?;
 
+        self.trace_added_resource(
+            context,
+            &module.as_python_resource().full_name(),
+            &module.inner.name,
+            module.add_collection_context(),
+        );
+
         Ok(Value::new(NoneType::None))
     }
 
@@ -585,11 +1272,49 @@ impl PythonExecutable {
             .map_err(|e| {
                 ValueError::from(RuntimeError {
                     code: "PYOXIDIZER_BUILD",
-                    message: e.to_string(),
+                    message: format!("{:#}", e),
                     label: label.to_string(),
                 })
             })?;
 
+        self.trace_added_resource(
+            context,
+            &file.as_python_resource().full_name(),
+            &file.inner.path_string(),
+            file.add_collection_context(),
+        );
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    pub fn add_gettext_catalog(
+        &mut self,
+        context: &PyOxidizerEnvironmentContext,
+        label: &str,
+        catalog: &GettextCatalogValue,
+    ) -> ValueResult {
+        info!(
+            context.logger(),
+            "adding gettext catalog {}",
+            catalog.inner.symbolic_name()
+        );
+        self.exe
+            .add_gettext_catalog(&catalog.inner, catalog.add_collection_context().clone())
+            .map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: format!("{:#}", e),
+                    label: label.to_string(),
+                })
+            })?;
+
+        self.trace_added_resource(
+            context,
+            &catalog.as_python_resource().full_name(),
+            &catalog.inner.leaf_package,
+            catalog.add_collection_context(),
+        );
+
         Ok(Value::new(NoneType::None))
     }
 
@@ -605,20 +1330,23 @@ impl PythonExecutable {
             .downcast_ref::<PyOxidizerEnvironmentContext>()
             .ok_or(ValueError::IncorrectParameterType)?;
 
-        match resource.get_type() {
+        let added = match resource.get_type() {
             FileValue::TYPE => {
                 let file = resource.downcast_ref::<FileValue>().unwrap();
-                self.add_file_data(pyoxidizer_context.deref(), label, file.deref())
+                self.add_file_data(pyoxidizer_context.deref(), label, file.deref())?;
+                Value::new(file.deref().clone())
             }
             PythonModuleSourceValue::TYPE => {
                 let module = resource.downcast_ref::<PythonModuleSourceValue>().unwrap();
-                self.add_python_module_source(pyoxidizer_context.deref(), label, module.deref())
+                self.add_python_module_source(pyoxidizer_context.deref(), label, module.deref())?;
+                Value::new(module.deref().clone())
             }
             PythonPackageResourceValue::TYPE => {
                 let r = resource
                     .downcast_ref::<PythonPackageResourceValue>()
                     .unwrap();
-                self.add_python_package_resource(pyoxidizer_context.deref(), label, r.deref())
+                self.add_python_package_resource(pyoxidizer_context.deref(), label, r.deref())?;
+                Value::new(r.deref().clone())
             }
             PythonPackageDistributionResourceValue::TYPE => {
                 let r = resource
@@ -628,20 +1356,42 @@ impl PythonExecutable {
                     pyoxidizer_context.deref(),
                     label,
                     r.deref(),
-                )
+                )?;
+                Value::new(r.deref().clone())
             }
             PythonExtensionModuleValue::TYPE => {
                 let module = resource
                     .downcast_ref::<PythonExtensionModuleValue>()
                     .unwrap();
-                self.add_python_extension_module(pyoxidizer_context.deref(), label, module.deref())
+                self.add_python_extension_module(
+                    pyoxidizer_context.deref(),
+                    label,
+                    module.deref(),
+                )?;
+                Value::new(module.deref().clone())
             }
-            _ => Err(ValueError::from(RuntimeError {
-                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
-                message: "resource argument must be a Python resource type".to_string(),
-                label: ".add_python_resource()".to_string(),
-            })),
+            GettextCatalogValue::TYPE => {
+                let catalog = resource.downcast_ref::<GettextCatalogValue>().unwrap();
+                self.add_gettext_catalog(pyoxidizer_context.deref(), label, catalog.deref())?;
+                Value::new(catalog.deref().clone())
+            }
+            _ => {
+                return Err(ValueError::from(RuntimeError {
+                    code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                    message: "resource argument must be a Python resource type".to_string(),
+                    label: ".add_python_resource()".to_string(),
+                }))
+            }
+        };
+
+        if add_context_for_value(&added, label)?.map_or(true, |context| context.include) {
+            let identity = resource_value_identity(&added);
+            self.resources
+                .retain(|value| identity.is_none() || resource_value_identity(value) != identity);
+            self.resources.push(added);
         }
+
+        Ok(Value::new(NoneType::None))
     }
 
     /// PythonExecutable.add_python_resources(resources)
@@ -657,6 +1407,27 @@ impl PythonExecutable {
         Ok(Value::new(NoneType::None))
     }
 
+    /// PythonExecutable.resources()
+    pub fn starlark_resources(&self) -> ValueResult {
+        let keys = self
+            .exe
+            .iter_resources()
+            .map(|(key, _)| key.clone())
+            .collect::<HashSet<_>>();
+
+        // Resources may have been removed from the collection since they were
+        // added, e.g. by filter_resources_from_files().
+        Ok(Value::from(
+            self.resources
+                .iter()
+                .filter(|value| {
+                    resource_collection_key(value).map_or(false, |key| keys.contains(&key))
+                })
+                .cloned()
+                .collect::<Vec<_>>(),
+        ))
+    }
+
     /// PythonExecutable.to_embedded_resources()
     pub fn starlark_to_embedded_resources(&self) -> ValueResult {
         Ok(Value::new(PythonEmbeddedResources {
@@ -664,6 +1435,139 @@ impl PythonExecutable {
         }))
     }
 
+    /// PythonExecutable.to_library()
+    pub fn starlark_to_library(&self) -> ValueResult {
+        Ok(Value::new(PythonLibrary {
+            exe: self.exe.clone_trait(),
+            static_library: true,
+        }))
+    }
+
+    /// PythonExecutable.to_resources_snapshot(path)
+    pub fn starlark_to_resources_snapshot(
+        &self,
+        type_values: &TypeValues,
+        path: String,
+    ) -> ValueResult {
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let path = PathBuf::from(path);
+        let snapshot_path = if path.is_absolute() {
+            path
+        } else {
+            pyoxidizer_context.cwd.join(path)
+        };
+
+        Ok(Value::new(PythonResourcesSnapshot {
+            exe: self.exe.clone_trait(),
+            snapshot_path,
+            update: pyoxidizer_context.update_snapshots,
+        }))
+    }
+
+    /// PythonExecutable.to_console_script_shims(commands=None, bin_dir="/usr/local/bin", install_dir=None)
+    pub fn starlark_to_console_script_shims(
+        &self,
+        type_values: &TypeValues,
+        commands: &Value,
+        bin_dir: String,
+        install_dir: &Value,
+    ) -> ValueResult {
+        optional_list_arg("commands", "string", commands)?;
+        let install_dir = optional_str_arg("install_dir", install_dir)?;
+
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let entry_points = &self.exe.embedded_python_config().entry_points;
+
+        let commands = match commands.get_type() {
+            "list" => commands
+                .iter()?
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>(),
+            _ => entry_points.iter().map(|(name, _)| name.clone()).collect(),
+        };
+
+        let error = |message: String| {
+            ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_BUILD",
+                message,
+                label: "to_console_script_shims()".to_string(),
+            })
+        };
+
+        if entry_points.is_empty() {
+            return Err(error(
+                "the interpreter config defines no entry points to create shims for".to_string(),
+            ));
+        }
+
+        for command in &commands {
+            if !entry_points.iter().any(|(name, _)| name == command) {
+                return Err(error(format!(
+                    "{} is not an entry point of the interpreter config",
+                    command
+                )));
+            }
+        }
+
+        let target_triple = &pyoxidizer_context.build_target_triple;
+
+        let mut shims = ConsoleScriptShims::new(
+            &executable_filename(&self.exe.name(), target_triple),
+            commands,
+        )
+        .map_err(|e| error(e.to_string()))?;
+        shims.bin_dir = bin_dir;
+        shims.install_dir = install_dir;
+
+        let mut manifest = FileManifest::default();
+        shims
+            .add_to_manifest(
+                &mut manifest,
+                ShimPlatform::from_target_triple(target_triple),
+                Path::new(""),
+                Path::new("installer"),
+            )
+            .map_err(|e| error(e.to_string()))?;
+
+        Ok(Value::new(FileManifestValue {
+            manifest,
+            run_path: None,
+        }))
+    }
+
+    /// PythonExecutable.to_wasi_bundle(interpreter_path)
+    pub fn starlark_to_wasi_bundle(
+        &self,
+        type_values: &TypeValues,
+        interpreter_path: String,
+    ) -> ValueResult {
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let path = PathBuf::from(interpreter_path);
+        let interpreter_path = if path.is_absolute() {
+            path
+        } else {
+            pyoxidizer_context.cwd.join(path)
+        };
+
+        Ok(Value::new(PythonWasiBundle {
+            exe: self.exe.clone_trait(),
+            interpreter_path,
+        }))
+    }
+
     /// PythonExecutable.filter_resources_from_files(files=None, glob_files=None)
     pub fn starlark_filter_resources_from_files(
         &mut self,
@@ -703,7 +1607,7 @@ impl PythonExecutable {
             .map_err(|e| {
                 ValueError::from(RuntimeError {
                     code: "RUNTIME_ERROR",
-                    message: e.to_string(),
+                    message: format!("{:#}", e),
                     label: "filter_from_files()".to_string(),
                 })
             })?;
@@ -755,6 +1659,80 @@ starlark_module! { python_executable_env =>
         }
     }
 
+    #[allow(non_snake_case, clippy::ptr_arg)]
+    PythonExecutable.install_groups(
+        env env,
+        call_stack cs,
+        this,
+        groups,
+        extra_envs=NoneType::None
+    ) {
+        match this.clone().downcast_ref::<PythonExecutable>() {
+            Some(exe) => exe.starlark_install_groups(&env, cs, &groups, &extra_envs),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    PythonExecutable.build_env(this, env) {
+        match this.clone().downcast_mut::<PythonExecutable>()? {
+            Some(mut exe) => exe.starlark_build_env(&env),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    PythonExecutable.cargo_features(this, features) {
+        match this.clone().downcast_mut::<PythonExecutable>()? {
+            Some(mut exe) => exe.starlark_cargo_features(&features),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    PythonExecutable.add_qt_support(this, binding: String = "pyside6".to_string()) {
+        match this.clone().downcast_mut::<PythonExecutable>()? {
+            Some(mut exe) => exe.starlark_add_qt_support(binding),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    PythonExecutable.add_external_asset(
+        this,
+        name: String,
+        url: String,
+        sha256: String,
+        path=NoneType::None
+    ) {
+        match this.clone().downcast_mut::<PythonExecutable>()? {
+            Some(mut exe) => exe.starlark_add_external_asset(name, url, sha256, &path),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    PythonExecutable.add_binary_metadata(
+        env env,
+        this,
+        name: String,
+        data=NoneType::None,
+        path=NoneType::None
+    ) {
+        match this.clone().downcast_mut::<PythonExecutable>()? {
+            Some(mut exe) => exe.starlark_add_binary_metadata(&env, name, &data, &path),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    #[allow(clippy::ptr_arg)]
+    PythonExecutable.add_shared_library(
+        env env,
+        this,
+        path: String,
+        searched_by=NoneType::None
+    ) {
+        match this.clone().downcast_mut::<PythonExecutable>()? {
+            Some(mut exe) => exe.starlark_add_shared_library(&env, path, &searched_by),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
     #[allow(non_snake_case, clippy::ptr_arg)]
     PythonExecutable.read_package_root(
         env env,
@@ -841,6 +1819,13 @@ starlark_module! { python_executable_env =>
         }
     }
 
+    PythonExecutable.resources(this) {
+        match this.clone().downcast_ref::<PythonExecutable>() {
+            Some(exe) => exe.starlark_resources(),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
     #[allow(clippy::ptr_arg)]
     PythonExecutable.to_embedded_resources(this) {
         match this.clone().downcast_ref::<PythonExecutable>() {
@@ -848,6 +1833,42 @@ starlark_module! { python_executable_env =>
             None => Err(ValueError::IncorrectParameterType),
         }
     }
+
+    PythonExecutable.to_library(this) {
+        match this.clone().downcast_ref::<PythonExecutable>() {
+            Some(exe) => exe.starlark_to_library(),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    #[allow(clippy::ptr_arg)]
+    PythonExecutable.to_resources_snapshot(env env, this, path: String) {
+        match this.clone().downcast_ref::<PythonExecutable>() {
+            Some(exe) => exe.starlark_to_resources_snapshot(&env, path),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    PythonExecutable.to_console_script_shims(
+        env env,
+        this,
+        commands = NoneType::None,
+        bin_dir: String = "/usr/local/bin".to_string(),
+        install_dir = NoneType::None
+    ) {
+        match this.clone().downcast_ref::<PythonExecutable>() {
+            Some(exe) => exe.starlark_to_console_script_shims(&env, &commands, bin_dir, &install_dir),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    #[allow(clippy::ptr_arg)]
+    PythonExecutable.to_wasi_bundle(env env, this, interpreter_path: String) {
+        match this.clone().downcast_ref::<PythonExecutable>() {
+            Some(exe) => exe.starlark_to_wasi_bundle(&env, interpreter_path),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -870,6 +1891,21 @@ mod tests {
             .exe
             .iter_resources()
             .all(|(_, r)| r.in_memory_resources.is_none()));
+        assert_eq!(exe.kind, PythonBinaryKind::Executable);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_shared_library() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        env.eval("dist = default_python_distribution()")?;
+        let exe = env.eval("dist.to_python_shared_library('testapp')")?;
+
+        assert_eq!(exe.get_type(), "PythonExecutable");
+        let exe = exe.downcast_ref::<PythonExecutable>().unwrap();
+        assert_eq!(exe.kind, PythonBinaryKind::SharedLibrary);
 
         Ok(())
     }
@@ -936,6 +1972,41 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_resources() -> Result<()> {
+        let mut env = StarlarkEnvironment::new_with_exe()?;
+
+        // The distribution's resources are added by to_python_executable().
+        env.eval_assert("'os' in [r.name for r in exe.resources()]")?;
+        let count = env.eval("len(exe.resources())")?.to_int().unwrap();
+
+        env.eval("m = exe.make_python_module_source('foo', 'import bar')")?;
+        env.eval("exe.add_python_resource(m)")?;
+        // Changes after adding aren't reflected.
+        env.eval("m.add_include = False")?;
+        env.eval("excluded = exe.make_python_module_source('baz', '')")?;
+        env.eval("excluded.add_include = False")?;
+        env.eval("exe.add_python_resource(excluded)")?;
+
+        assert_eq!(
+            env.eval("len(exe.resources())")?.to_int().unwrap(),
+            count + 1
+        );
+        env.eval_assert("exe.resources()[-1].name == 'foo'")?;
+        env.eval_assert("exe.resources()[-1].add_include")?;
+
+        // Adding a resource again replaces the earlier value.
+        env.eval("m2 = exe.make_python_module_source('foo', 'import baz')")?;
+        env.eval("exe.add_python_resource(m2)")?;
+        assert_eq!(
+            env.eval("len(exe.resources())")?.to_int().unwrap(),
+            count + 1
+        );
+        env.eval_assert("exe.resources()[-1].source == 'import baz'")?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_pip_download_pyflakes() -> Result<()> {
         for target_triple in PYTHON_DISTRIBUTIONS.all_target_triples() {
@@ -985,6 +2056,62 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_pip_install_dry_run() -> Result<()> {
+        let mut env = StarlarkEnvironment::new_with_exe()?;
+
+        get_context(&env.type_values)
+            .unwrap()
+            .downcast_mut::<PyOxidizerEnvironmentContext>()
+            .unwrap()
+            .unwrap()
+            .dry_run = true;
+
+        for code in &[
+            "exe.pip_install(['pyflakes==2.1.1'])",
+            "exe.pip_download(['pyflakes==2.1.1'])",
+            "exe.install_groups([['pyflakes==2.1.1']])",
+        ] {
+            let resources = env.eval(code)?;
+            assert_eq!(resources.get_type(), "list");
+            assert_eq!(resources.length().unwrap(), 0);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_install_groups() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+        env.eval("policy.include_distribution_sources = False")?;
+        env.eval("exe = dist.to_python_executable('testapp', packaging_policy = policy)")?;
+
+        assert!(env.eval("exe.install_groups('pyflakes==2.1.1')").is_err());
+        assert!(env.eval("exe.install_groups([1])").is_err());
+        assert!(env.eval("exe.install_groups([[1]])").is_err());
+
+        let resources =
+            env.eval("exe.install_groups([['pyflakes==2.1.1'], ['pyflakes==2.1.1', 'six']])")?;
+        let names = resources
+            .iter()
+            .unwrap()
+            .iter()
+            .filter_map(|v| {
+                v.downcast_ref::<PythonModuleSourceValue>()
+                    .map(|x| x.inner.name.clone())
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(names[0], "pyflakes");
+        assert!(names.contains(&"six".to_string()));
+        assert_eq!(names.iter().filter(|n| *n == "pyflakes").count(), 1);
+
+        Ok(())
+    }
+
     #[test]
     fn test_read_package_root_simple() -> Result<()> {
         let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
@@ -1053,6 +2180,368 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_windows_service_name() -> Result<()> {
+        let mut env = StarlarkEnvironment::new_with_exe()?;
+
+        let value = env.eval("exe.windows_service_name")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        let value = env.eval("exe.windows_service_name = 'My App'; exe.windows_service_name")?;
+        assert_eq!(value.to_string(), "My App");
+
+        assert!(env.eval("exe.windows_service_name = 'bad/name'").is_err());
+        assert!(env.eval("exe.windows_service_name = 'bad\"name'").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_linux_minimum_glibc_version() -> Result<()> {
+        let mut env = StarlarkEnvironment::new_with_exe()?;
+
+        let value = env.eval("exe.linux_minimum_glibc_version")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        let value =
+            env.eval("exe.linux_minimum_glibc_version = '2.17'; exe.linux_minimum_glibc_version")?;
+        assert_eq!(value.to_string(), "2.17");
+
+        assert!(env.eval("exe.linux_minimum_glibc_version = '2'").is_err());
+        assert!(env
+            .eval("exe.linux_minimum_glibc_version = 'GLIBC_2.17'")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_version() -> Result<()> {
+        let mut env = StarlarkEnvironment::new_with_exe()?;
+
+        let value = env.eval("exe.version")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        let value = env.eval("exe.version = '1.2.0+3.g1a2b3c4'; exe.version")?;
+        assert_eq!(value.to_string(), "1.2.0+3.g1a2b3c4");
+
+        assert!(env.eval("exe.version = '1.0 beta'").is_err());
+        assert!(env.eval("exe.version = '1.2.3.4'").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_size_mb() -> Result<()> {
+        let mut env = StarlarkEnvironment::new_with_exe()?;
+
+        let value = env.eval("exe.max_size_mb")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        let value = env.eval("exe.max_size_mb = 100; exe.max_size_mb")?;
+        assert_eq!(value.to_int().unwrap(), 100);
+
+        assert!(env.eval("exe.max_size_mb = 0").is_err());
+        assert!(env.eval("exe.max_size_mb = '100'").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_console_script_shims() -> Result<()> {
+        let mut env = StarlarkEnvironment::new_with_exe()?;
+
+        // Shims require entry points.
+        assert!(env.eval("exe.to_console_script_shims()").is_err());
+
+        env.eval("config = dist.make_python_interpreter_config()")?;
+        env.eval("config.entry_points = {'tool-a': 'myapp.a', 'tool-b': 'myapp.b:main'}")?;
+        env.eval("exe = dist.to_python_executable('testapp', config=config)")?;
+
+        assert!(env
+            .eval("exe.to_console_script_shims(commands=['tool-c'])")
+            .is_err());
+
+        let value = env.eval("exe.to_console_script_shims(commands=['tool-a'])")?;
+        assert_eq!(value.get_type(), "FileManifest");
+
+        let manifest = value.downcast_ref::<FileManifestValue>().unwrap();
+        let paths = manifest
+            .manifest
+            .entries()
+            .map(|(path, _)| path.to_path_buf())
+            .collect::<Vec<_>>();
+
+        if crate::environment::WINDOWS_TARGET_TRIPLES.contains(&env!("HOST")) {
+            assert_eq!(
+                paths,
+                vec![
+                    PathBuf::from("installer/console-scripts.wxs"),
+                    PathBuf::from("tool-a.cmd"),
+                ]
+            );
+        } else {
+            assert_eq!(
+                paths,
+                vec![
+                    PathBuf::from("installer/scripts/postinstall"),
+                    PathBuf::from("installer/scripts/uninstall"),
+                    PathBuf::from("tool-a"),
+                ]
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bundle_tzdata() -> Result<()> {
+        let mut env = StarlarkEnvironment::new_with_exe()?;
+
+        let value = env.eval("exe.bundle_tzdata")?;
+        assert_eq!(value.get_type(), "bool");
+        assert!(!value.to_bool());
+
+        let value = env.eval("exe.bundle_tzdata = True; exe.bundle_tzdata")?;
+        assert!(value.to_bool());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_macos_deployment_target() -> Result<()> {
+        let mut env = StarlarkEnvironment::new_with_exe()?;
+
+        let value = env.eval("exe.macos_deployment_target")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        let value =
+            env.eval("exe.macos_deployment_target = '10.15'; exe.macos_deployment_target")?;
+        assert_eq!(value.to_string(), "10.15");
+
+        assert!(env
+            .eval("exe.macos_deployment_target = 'catalina'")
+            .is_err());
+
+        let value = env.eval("exe.macos_deployment_target = None; exe.macos_deployment_target")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_packed_resources_embedding() -> Result<()> {
+        let mut env = StarlarkEnvironment::new_with_exe()?;
+
+        let value = env.eval("exe.packed_resources_embedding")?;
+        assert_eq!(value.to_string(), "array");
+
+        let value =
+            env.eval("exe.packed_resources_embedding = 'overlay'; exe.packed_resources_embedding")?;
+        assert_eq!(value.to_string(), "overlay");
+
+        assert!(env.eval("exe.packed_resources_embedding = 'file'").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_windows_runtime_dlls_mode() -> Result<()> {
+        let mut env = StarlarkEnvironment::new_with_exe()?;
+
+        let value = env.eval("exe.windows_runtime_dlls_mode")?;
+        assert_eq!(value.to_string(), "when-present");
+
+        let value =
+            env.eval("exe.windows_runtime_dlls_mode = 'never'; exe.windows_runtime_dlls_mode")?;
+        assert_eq!(value.to_string(), "never");
+
+        assert!(env
+            .eval("exe.windows_runtime_dlls_mode = 'sometimes'")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cargo_features() -> Result<()> {
+        let mut env = StarlarkEnvironment::new_with_exe()?;
+
+        env.eval("exe.cargo_features(['jemalloc', 'pyembed/serialization', 'jemalloc'])")?;
+        assert!(env.eval("exe.cargo_features(['bad feature'])").is_err());
+        assert!(env.eval("exe.cargo_features('jemalloc')").is_err());
+
+        let exe = env.eval("exe")?;
+        let exe = exe.downcast_ref::<PythonExecutable>().unwrap();
+        assert_eq!(
+            exe.exe.cargo_features(),
+            &["jemalloc".to_string(), "pyembed/serialization".to_string()]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_env() -> Result<()> {
+        let mut env = StarlarkEnvironment::new_with_exe()?;
+
+        env.eval("exe.build_env({'OPENSSL_DIR': '/opt/openssl', 'FOO': 'bar'})")?;
+        env.eval("exe.build_env({'FOO': 'baz'})")?;
+        assert!(env.eval("exe.build_env({'A=B': 'value'})").is_err());
+
+        let exe = env.eval("exe")?;
+        let exe = exe.downcast_ref::<PythonExecutable>().unwrap();
+        let build_env = exe.exe.build_env();
+        assert_eq!(build_env.len(), 2);
+        assert_eq!(
+            build_env.get("OPENSSL_DIR"),
+            Some(&"/opt/openssl".to_string())
+        );
+        assert_eq!(build_env.get("FOO"), Some(&"baz".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_qt_support() -> Result<()> {
+        let mut env = StarlarkEnvironment::new_with_exe()?;
+
+        assert!(env.eval("exe.add_qt_support('tkinter')").is_err());
+        // The binding's resources haven't been added.
+        assert!(env.eval("exe.add_qt_support()").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_external_asset() -> Result<()> {
+        let mut env = StarlarkEnvironment::new_with_exe()?;
+
+        env.eval("exe.add_external_asset('model', 'https://example.com/model.bin', '2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824')")?;
+        assert!(env
+            .eval("exe.add_external_asset('other', 'https://example.com/other.bin', 'abcd')")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_binary_metadata() -> Result<()> {
+        let mut env = StarlarkEnvironment::new_with_exe()?;
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let license_path = temp_dir.path().join("LICENSE");
+        std::fs::write(&license_path, "license text")?;
+
+        env.eval("exe.add_binary_metadata('build', data = 'commit=abcdef')")?;
+        env.eval(&format!(
+            "exe.add_binary_metadata('license', path = {:?})",
+            license_path.display().to_string()
+        ))?;
+        assert!(env.eval("exe.add_binary_metadata('other')").is_err());
+        assert!(env
+            .eval("exe.add_binary_metadata('build', data = 'commit=123456')")
+            .is_err());
+
+        let exe = env.eval("exe")?;
+        let exe = exe.downcast_ref::<PythonExecutable>().unwrap();
+        assert_eq!(
+            exe.exe.embedded_python_config().binary_metadata,
+            vec![
+                ("build".to_string(), b"commit=abcdef".to_vec()),
+                ("license".to_string(), b"license text".to_vec())
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_shared_library() -> Result<()> {
+        let mut env = StarlarkEnvironment::new_with_exe()?;
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+
+        let path = temp_dir.path().join("libmylib.so");
+        std::fs::write(&path, b"library")?;
+        let path = path.display().to_string().replace('\\', "/");
+
+        env.eval(&format!("exe.add_shared_library('{}')", path))?;
+        assert!(env
+            .eval(&format!(
+                "exe.add_shared_library('{}', searched_by=['missing'])",
+                path
+            ))
+            .is_err());
+        assert!(env
+            .eval("exe.add_shared_library('does-not-exist.so')")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_library() -> Result<()> {
+        let mut env = StarlarkEnvironment::new_with_exe()?;
+
+        let value = env.eval("exe.to_library()")?;
+        assert_eq!(value.get_type(), "PythonLibrary");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_resources_snapshot() -> Result<()> {
+        let mut env = StarlarkEnvironment::new_with_exe()?;
+
+        let value = env.eval("exe.to_resources_snapshot('resources.txt')")?;
+        assert_eq!(value.get_type(), "PythonResourcesSnapshot");
+
+        let snapshot = value.downcast_ref::<PythonResourcesSnapshot>().unwrap();
+        assert_eq!(
+            snapshot.snapshot_path,
+            std::env::current_dir()?.join("resources.txt")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_wasi_bundle() -> Result<()> {
+        let mut env = StarlarkEnvironment::new_with_exe()?;
+
+        let value = env.eval("exe.to_wasi_bundle('python-wasi')")?;
+        assert_eq!(value.get_type(), "PythonWasiBundle");
+
+        let bundle = value.downcast_ref::<PythonWasiBundle>().unwrap();
+        assert_eq!(
+            bundle.interpreter_path,
+            std::env::current_dir()?.join("python-wasi")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_external_runtime_path() -> Result<()> {
+        let mut env = StarlarkEnvironment::new_with_exe()?;
+
+        let value = env.eval("exe.external_runtime_path")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        let value =
+            env.eval("exe.external_runtime_path = 'runtimes'; exe.external_runtime_path")?;
+        assert_eq!(value.get_type(), "string");
+        assert_eq!(value.to_string(), "runtimes");
+
+        assert!(env
+            .eval("exe.external_runtime_path = '../runtime'")
+            .is_err());
+
+        let value = env.eval("exe.external_runtime_path = None; exe.external_runtime_path")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        Ok(())
+    }
+
     #[test]
     fn test_tcl_files_path() -> Result<()> {
         let mut env = StarlarkEnvironment::new_with_exe()?;
@@ -3,23 +3,32 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use {
-    super::util::{ToOptional, ToValue, TryToOptional},
+    super::{
+        env::{get_context, PyOxidizerEnvironmentContext},
+        util::{ToOptional, ToValue, TryToOptional},
+    },
     crate::py_packaging::config::EmbeddedPythonConfig,
     python_packaging::{
+        import_trace::ImportTrace,
         interpreter::{
             Allocator, BytesWarning, CheckHashPYCsMode, CoerceCLocale, MemoryAllocatorBackend,
             PythonInterpreterProfile, TerminfoResolution,
         },
         resource::BytecodeOptimizationLevel,
     },
-    starlark::values::{
-        error::{
-            RuntimeError, UnsupportedOperation, ValueError, INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+    starlark::{
+        environment::TypeValues,
+        starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+        starlark_signature_extraction, starlark_signatures,
+        values::{
+            error::{
+                RuntimeError, UnsupportedOperation, ValueError, INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+            },
+            none::NoneType,
+            {Mutable, TypedValue, Value, ValueResult},
         },
-        none::NoneType,
-        {Mutable, TypedValue, Value, ValueResult},
     },
-    std::convert::TryFrom,
+    std::{collections::HashMap, convert::TryFrom, path::PathBuf},
 };
 
 impl ToValue for PythonInterpreterProfile {
@@ -104,6 +113,29 @@ impl TryToOptional<BytecodeOptimizationLevel> for Value {
     }
 }
 
+/// Smallest thread stack size Python accepts.
+const THREAD_STACK_SIZE_MIN: i32 = 32768;
+
+/// Whether a string is a Python identifier.
+fn is_identifier(s: &str) -> bool {
+    !s.is_empty()
+        && !s.starts_with(|c: char| c.is_ascii_digit())
+        && s.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Whether a string is a dotted Python module name.
+fn is_valid_module_name(name: &str) -> bool {
+    name.split('.').all(is_identifier)
+}
+
+/// Whether a string is a `module` or `module:function` entry point target.
+fn is_valid_entry_point_target(target: &str) -> bool {
+    let mut parts = target.splitn(2, ':');
+    let module_valid = parts.next().map(is_valid_module_name).unwrap_or(false);
+
+    module_valid && parts.next().map(is_identifier).unwrap_or(true)
+}
+
 #[derive(Debug, Clone)]
 pub struct PythonInterpreterConfigValue {
     pub inner: EmbeddedPythonConfig,
@@ -113,6 +145,25 @@ impl PythonInterpreterConfigValue {
     pub fn new(inner: EmbeddedPythonConfig) -> Self {
         Self { inner }
     }
+
+    /// Validate the module names assigned to a list attribute.
+    fn validate_module_names(
+        &self,
+        attribute: &str,
+        modules: Option<Vec<String>>,
+    ) -> Result<Vec<String>, ValueError> {
+        let modules = modules.unwrap_or_default();
+
+        if let Some(name) = modules.iter().find(|name| !is_valid_module_name(name)) {
+            return Err(ValueError::from(RuntimeError {
+                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                message: format!("invalid module name: {:?}", name),
+                label: format!("{}.{}", Self::TYPE, attribute),
+            }));
+        }
+
+        Ok(modules)
+    }
 }
 
 impl TypedValue for PythonInterpreterConfigValue {
@@ -166,9 +217,11 @@ impl TypedValue for PythonInterpreterConfigValue {
             "legacy_windows_stdio" => self.inner.config.legacy_windows_stdio.to_value(),
             "malloc_stats" => self.inner.config.malloc_stats.to_value(),
             "module_search_paths" => self.inner.config.module_search_paths.to_value(),
+            "module_search_paths_set" => self.inner.config.module_search_paths_set.to_value(),
             "optimization_level" => self.inner.config.optimization_level.to_value(),
             "parser_debug" => self.inner.config.parser_debug.to_value(),
             "pathconfig_warnings" => self.inner.config.pathconfig_warnings.to_value(),
+            "platlibdir" => self.inner.config.platlibdir.to_value(),
             "prefix" => self.inner.config.prefix.to_value(),
             "program_name" => self.inner.config.program_name.to_value(),
             "pycache_prefix" => self.inner.config.pycache_prefix.to_value(),
@@ -192,11 +245,46 @@ impl TypedValue for PythonInterpreterConfigValue {
             "raw_allocator" => self.inner.raw_allocator.to_value(),
             "oxidized_importer" => Value::from(self.inner.oxidized_importer),
             "filesystem_importer" => Value::from(self.inner.filesystem_importer),
+            "dev_source_roots" => Some(self.inner.dev_source_roots.clone()).to_value(),
+            "resource_dirs" => Some(self.inner.resource_dirs.clone()).to_value(),
+            "resource_dirs_flag" => self.inner.resource_dirs_flag.to_value(),
             "argvb" => Value::from(self.inner.argvb),
+            "argv0" => self.inner.argv0.to_value(),
+            "prepend_args" => Some(self.inner.prepend_args.clone()).to_value(),
+            "entry_points" => Value::try_from(
+                self.inner
+                    .entry_points
+                    .iter()
+                    .cloned()
+                    .collect::<HashMap<String, String>>(),
+            )?,
             "sys_frozen" => Value::from(self.inner.sys_frozen),
             "sys_meipass" => Value::from(self.inner.sys_meipass),
+            "pre_run_modules" => Some(self.inner.pre_run_modules.clone()).to_value(),
+            "frozen_modules" => Some(self.inner.frozen_modules.clone()).to_value(),
+            "preload_modules" => Some(self.inner.preload_modules.clone()).to_value(),
+            "thread_stack_size" => self
+                .inner
+                .thread_stack_size
+                .map(|size| size as i32)
+                .to_value(),
+            "asyncio_event_loop_policy" => self.inner.asyncio_event_loop_policy.to_value(),
+            "repl_banner" => self.inner.repl_banner.to_value(),
+            "repl_startup_code" => self.inner.repl_startup_code.to_value(),
+            "repl_ps1" => self.inner.repl_ps1.to_value(),
+            "repl_ps2" => self.inner.repl_ps2.to_value(),
+            "debug_shell" => Value::from(self.inner.debug_shell),
             "terminfo_resolution" => self.inner.terminfo_resolution.to_value(),
             "write_modules_directory_env" => self.inner.write_modules_directory_env.to_value(),
+            "restricted_packages" => Value::try_from(
+                self.inner
+                    .restricted_packages
+                    .iter()
+                    .cloned()
+                    .collect::<HashMap<String, String>>(),
+            )?,
+            "capabilities" => Some(self.inner.capabilities.clone()).to_value(),
+            "capabilities_env" => self.inner.capabilities_env.to_value(),
             attr => {
                 return Err(ValueError::OperationNotSupported {
                     op: UnsupportedOperation::GetAttr(attr.to_string()),
@@ -244,9 +332,11 @@ impl TypedValue for PythonInterpreterConfigValue {
             "legacy_windows_stdio" => true,
             "malloc_stats" => true,
             "module_search_paths" => true,
+            "module_search_paths_set" => true,
             "optimization_level" => true,
             "parser_debug" => true,
             "pathconfig_warnings" => true,
+            "platlibdir" => true,
             "prefix" => true,
             "program_name" => true,
             "pycache_prefix" => true,
@@ -270,11 +360,30 @@ impl TypedValue for PythonInterpreterConfigValue {
             "raw_allocator" => true,
             "oxidized_importer" => true,
             "filesystem_importer" => true,
+            "dev_source_roots" => true,
+            "resource_dirs" => true,
+            "resource_dirs_flag" => true,
             "argvb" => true,
+            "argv0" => true,
+            "prepend_args" => true,
+            "entry_points" => true,
             "sys_frozen" => true,
             "sys_meipass" => true,
+            "pre_run_modules" => true,
+            "frozen_modules" => true,
+            "preload_modules" => true,
+            "thread_stack_size" => true,
+            "asyncio_event_loop_policy" => true,
+            "repl_banner" => true,
+            "repl_startup_code" => true,
+            "repl_ps1" => true,
+            "repl_ps2" => true,
+            "debug_shell" => true,
             "terminfo_resolution" => true,
             "write_modules_directory_env" => true,
+            "restricted_packages" => true,
+            "capabilities" => true,
+            "capabilities_env" => true,
             _ => false,
         })
     }
@@ -454,6 +563,9 @@ impl TypedValue for PythonInterpreterConfigValue {
             "pathconfig_warnings" => {
                 self.inner.config.pathconfig_warnings = value.to_optional();
             }
+            "platlibdir" => {
+                self.inner.config.platlibdir = value.to_optional();
+            }
             "prefix" => {
                 self.inner.config.prefix = value.to_optional();
             }
@@ -530,15 +642,103 @@ impl TypedValue for PythonInterpreterConfigValue {
             "filesystem_importer" => {
                 self.inner.filesystem_importer = value.to_bool();
             }
+            "dev_source_roots" => {
+                let paths: Option<Vec<PathBuf>> = value.try_to_optional()?;
+                self.inner.dev_source_roots = paths.unwrap_or_default();
+            }
+            "resource_dirs" => {
+                let paths: Option<Vec<PathBuf>> = value.try_to_optional()?;
+                self.inner.resource_dirs = paths.unwrap_or_default();
+            }
+            "resource_dirs_flag" => {
+                self.inner.resource_dirs_flag = value.to_optional();
+            }
             "argvb" => {
                 self.inner.argvb = value.to_bool();
             }
+            "argv0" => {
+                self.inner.argv0 = value.to_optional();
+            }
+            "prepend_args" => {
+                let args: Option<Vec<String>> = value.try_to_optional()?;
+                self.inner.prepend_args = args.unwrap_or_default();
+            }
+            "entry_points" => {
+                let entry_points: Option<Vec<(String, String)>> = value.try_to_optional()?;
+                let entry_points = entry_points.unwrap_or_default();
+
+                for (name, target) in &entry_points {
+                    if !is_valid_entry_point_target(target) {
+                        return Err(ValueError::from(RuntimeError {
+                            code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                            message: format!(
+                                "invalid entry point {} = {}; targets must be of the form module or module:function",
+                                name, target
+                            ),
+                            label: format!("{}.{}", Self::TYPE, attribute),
+                        }));
+                    }
+                }
+
+                self.inner.entry_points = entry_points;
+            }
             "sys_frozen" => {
                 self.inner.sys_frozen = value.to_bool();
             }
             "sys_meipass" => {
                 self.inner.sys_meipass = value.to_bool();
             }
+            "pre_run_modules" => {
+                let modules: Option<Vec<String>> = value.try_to_optional()?;
+                self.inner.pre_run_modules = self.validate_module_names(attribute, modules)?;
+            }
+            "frozen_modules" => {
+                let modules: Option<Vec<String>> = value.try_to_optional()?;
+                self.inner.frozen_modules = self.validate_module_names(attribute, modules)?;
+            }
+            "preload_modules" => {
+                let modules: Option<Vec<String>> = value.try_to_optional()?;
+                self.inner.preload_modules = self.validate_module_names(attribute, modules)?;
+            }
+            "thread_stack_size" => {
+                let size: Option<i32> = value.try_to_optional()?;
+
+                if let Some(size) = size {
+                    if size < THREAD_STACK_SIZE_MIN {
+                        return Err(ValueError::from(RuntimeError {
+                            code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                            message: format!(
+                                "thread stack size must be at least {} bytes; got {}",
+                                THREAD_STACK_SIZE_MIN, size
+                            ),
+                            label: format!("{}.{}", Self::TYPE, attribute),
+                        }));
+                    }
+                }
+
+                self.inner.thread_stack_size = size
+                    .map(usize::try_from)
+                    .transpose()
+                    .map_err(|_| ValueError::IntegerOverflow)?;
+            }
+            "asyncio_event_loop_policy" => {
+                self.inner.asyncio_event_loop_policy = value.to_optional();
+            }
+            "repl_banner" => {
+                self.inner.repl_banner = value.to_optional();
+            }
+            "repl_startup_code" => {
+                self.inner.repl_startup_code = value.to_optional();
+            }
+            "repl_ps1" => {
+                self.inner.repl_ps1 = value.to_optional();
+            }
+            "repl_ps2" => {
+                self.inner.repl_ps2 = value.to_optional();
+            }
+            "debug_shell" => {
+                self.inner.debug_shell = value.to_bool();
+            }
             "terminfo_resolution" => {
                 self.inner.terminfo_resolution =
                     TerminfoResolution::try_from(value.to_string().as_str()).map_err(|e| {
@@ -552,6 +752,17 @@ impl TypedValue for PythonInterpreterConfigValue {
             "write_modules_directory_env" => {
                 self.inner.write_modules_directory_env = value.to_optional();
             }
+            "restricted_packages" => {
+                let packages: Option<Vec<(String, String)>> = value.try_to_optional()?;
+                self.inner.restricted_packages = packages.unwrap_or_default();
+            }
+            "capabilities" => {
+                let capabilities: Option<Vec<String>> = value.try_to_optional()?;
+                self.inner.capabilities = capabilities.unwrap_or_default();
+            }
+            "capabilities_env" => {
+                self.inner.capabilities_env = value.to_optional();
+            }
             attr => {
                 return Err(ValueError::OperationNotSupported {
                     op: UnsupportedOperation::SetAttr(attr.to_string()),
@@ -565,9 +776,62 @@ impl TypedValue for PythonInterpreterConfigValue {
     }
 }
 
+// Starlark methods.
+impl PythonInterpreterConfigValue {
+    fn starlark_add_preload_trace(
+        &mut self,
+        type_values: &TypeValues,
+        path: String,
+    ) -> ValueResult {
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let path = PathBuf::from(path);
+        let path = if path.is_absolute() {
+            path
+        } else {
+            pyoxidizer_context.cwd.join(path)
+        };
+
+        let trace = std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|data| ImportTrace::from_importtime_output(&data))
+            .map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: format!("unable to load import trace {}: {}", path.display(), e),
+                    label: "add_preload_trace()".to_string(),
+                })
+            })?;
+
+        for name in trace.modules() {
+            if !self.inner.preload_modules.iter().any(|m| m == name) {
+                self.inner.preload_modules.push(name.to_string());
+            }
+        }
+
+        Ok(Value::from(NoneType::None))
+    }
+}
+
+starlark_module! { python_interpreter_config_module =>
+    PythonInterpreterConfig.add_preload_trace(env env, this, path: String) {
+        match this.clone().downcast_mut::<PythonInterpreterConfigValue>()? {
+            Some(mut config) => config.starlark_add_preload_trace(&env, path),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use {super::super::testutil::*, anyhow::Result};
+    use {
+        super::super::testutil::*,
+        super::{is_valid_entry_point_target, is_valid_module_name},
+        anyhow::Result,
+    };
 
     // TODO instantiating a new distribution every call is expensive. Can we cache this?
     fn get_env() -> Result<StarlarkEnvironment> {
@@ -916,6 +1180,18 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_module_search_paths_set() -> Result<()> {
+        let mut env = get_env()?;
+
+        env.eval_assert("config.module_search_paths_set == None")?;
+
+        env.eval("config.module_search_paths_set = True")?;
+        env.eval_assert("config.module_search_paths_set == True")?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_optimization_level() -> Result<()> {
         let mut env = get_env()?;
@@ -952,6 +1228,18 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_platlibdir() -> Result<()> {
+        let mut env = get_env()?;
+
+        env.eval_assert("config.platlibdir == None")?;
+
+        env.eval("config.platlibdir = 'lib64'")?;
+        env.eval_assert("config.platlibdir == 'lib64'")?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_prefix() -> Result<()> {
         let mut env = get_env()?;
@@ -1165,6 +1453,40 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_dev_source_roots() -> Result<()> {
+        let mut env = get_env()?;
+
+        env.eval_assert("config.dev_source_roots == []")?;
+
+        env.eval("config.dev_source_roots = ['src']")?;
+        env.eval_assert("config.dev_source_roots == ['src']")?;
+
+        env.eval("config.dev_source_roots = None")?;
+        env.eval_assert("config.dev_source_roots == []")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resource_dirs() -> Result<()> {
+        let mut env = get_env()?;
+
+        env.eval_assert("config.resource_dirs == []")?;
+        env.eval_assert("config.resource_dirs_flag == None")?;
+
+        env.eval("config.resource_dirs = ['$ORIGIN/site-packages']")?;
+        env.eval_assert("config.resource_dirs == ['$ORIGIN/site-packages']")?;
+
+        env.eval("config.resource_dirs = None")?;
+        env.eval_assert("config.resource_dirs == []")?;
+
+        env.eval("config.resource_dirs_flag = '--extensions'")?;
+        env.eval_assert("config.resource_dirs_flag == '--extensions'")?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_argvb() -> Result<()> {
         let mut env = get_env()?;
@@ -1174,6 +1496,77 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_is_valid_module_name() {
+        assert!(is_valid_module_name("myapp"));
+        assert!(is_valid_module_name("myapp._patches"));
+        assert!(!is_valid_module_name(""));
+        assert!(!is_valid_module_name("myapp."));
+        assert!(!is_valid_module_name(".myapp"));
+        assert!(!is_valid_module_name("my-app"));
+        assert!(!is_valid_module_name("1app"));
+    }
+
+    #[test]
+    fn test_is_valid_entry_point_target() {
+        assert!(is_valid_entry_point_target("myapp"));
+        assert!(is_valid_entry_point_target("myapp.cli"));
+        assert!(is_valid_entry_point_target("myapp.cli:main"));
+        assert!(!is_valid_entry_point_target(""));
+        assert!(!is_valid_entry_point_target("myapp."));
+        assert!(!is_valid_entry_point_target("myapp:"));
+        assert!(!is_valid_entry_point_target("myapp:main:x"));
+        assert!(!is_valid_entry_point_target("my app"));
+        assert!(!is_valid_entry_point_target("1app"));
+    }
+
+    #[test]
+    fn test_argv0() -> Result<()> {
+        let mut env = get_env()?;
+
+        env.eval_assert("config.argv0 == None")?;
+
+        env.eval("config.argv0 = 'myapp'")?;
+        env.eval_assert("config.argv0 == 'myapp'")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prepend_args() -> Result<()> {
+        let mut env = get_env()?;
+
+        env.eval_assert("config.prepend_args == []")?;
+
+        env.eval("config.prepend_args = ['--config', 'default.toml']")?;
+        env.eval_assert("config.prepend_args == ['--config', 'default.toml']")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_entry_points() -> Result<()> {
+        let mut env = get_env()?;
+
+        env.eval_assert("config.entry_points == {}")?;
+
+        env.eval("config.entry_points = {'tool-a': 'myapp.a', 'tool-b': 'myapp.b:main'}")?;
+        env.eval_assert("config.entry_points == {'tool-a': 'myapp.a', 'tool-b': 'myapp.b:main'}")?;
+
+        assert!(env
+            .eval("config.entry_points = {'tool-c': 'myapp c'}")
+            .is_err());
+        assert!(env
+            .eval("config.entry_points = {'tool-c': 'myapp:'}")
+            .is_err());
+        assert!(env.eval("config.entry_points = ['myapp']").is_err());
+
+        env.eval("config.entry_points = None")?;
+        env.eval_assert("config.entry_points == {}")?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_sys_frozen() -> Result<()> {
         let mut env = get_env()?;
@@ -1192,6 +1585,165 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_pre_run_modules() -> Result<()> {
+        let mut env = get_env()?;
+
+        env.eval_assert("config.pre_run_modules == []")?;
+
+        env.eval("config.pre_run_modules = ['myapp._patches']")?;
+        env.eval_assert("config.pre_run_modules == ['myapp._patches']")?;
+
+        assert!(env.eval("config.pre_run_modules = ['myapp.']").is_err());
+        assert!(env.eval("config.pre_run_modules = ['my-app']").is_err());
+
+        env.eval("config.pre_run_modules = None")?;
+        env.eval_assert("config.pre_run_modules == []")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_frozen_modules() -> Result<()> {
+        let mut env = get_env()?;
+
+        env.eval_assert("config.frozen_modules == []")?;
+
+        env.eval("config.frozen_modules = ['myapp', 'myapp.cli']")?;
+        env.eval_assert("config.frozen_modules == ['myapp', 'myapp.cli']")?;
+
+        assert!(env.eval("config.frozen_modules = ['']").is_err());
+        assert!(env.eval("config.frozen_modules = ['myapp/cli']").is_err());
+
+        env.eval("config.frozen_modules = None")?;
+        env.eval_assert("config.frozen_modules == []")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_preload_modules() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let trace = temp_dir.path().join("trace.log");
+        std::fs::write(
+            &trace,
+            "import time: 10 | 10 | json.decoder\nimport time: 20 | 30 | json\n",
+        )?;
+
+        let mut env = get_env()?;
+
+        env.eval_assert("config.preload_modules == []")?;
+
+        env.eval("config.preload_modules = ['myapp.ui']")?;
+        env.eval_assert("config.preload_modules == ['myapp.ui']")?;
+
+        assert!(env.eval("config.preload_modules = ['1ui']").is_err());
+
+        env.eval(&format!(
+            "config.add_preload_trace({:?})",
+            trace.display().to_string()
+        ))?;
+        env.eval_assert("config.preload_modules == ['myapp.ui', 'json.decoder', 'json']")?;
+
+        env.eval("config.preload_modules = None")?;
+        env.eval_assert("config.preload_modules == []")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_thread_stack_size() -> Result<()> {
+        let mut env = get_env()?;
+
+        env.eval_assert("config.thread_stack_size == None")?;
+
+        env.eval("config.thread_stack_size = 4194304")?;
+        env.eval_assert("config.thread_stack_size == 4194304")?;
+
+        assert!(env.eval("config.thread_stack_size = 1024").is_err());
+        assert!(env.eval("config.thread_stack_size = 4294967296").is_err());
+
+        env.eval("config.thread_stack_size = None")?;
+        env.eval_assert("config.thread_stack_size == None")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_asyncio_event_loop_policy() -> Result<()> {
+        let mut env = get_env()?;
+
+        env.eval_assert("config.asyncio_event_loop_policy == None")?;
+
+        env.eval("config.asyncio_event_loop_policy = 'WindowsSelectorEventLoopPolicy'")?;
+        env.eval_assert("config.asyncio_event_loop_policy == 'WindowsSelectorEventLoopPolicy'")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_repl_banner() -> Result<()> {
+        let mut env = get_env()?;
+
+        env.eval_assert("config.repl_banner == None")?;
+
+        env.eval("config.repl_banner = 'My Console'")?;
+        env.eval_assert("config.repl_banner == 'My Console'")?;
+
+        env.eval("config.repl_banner = None")?;
+        env.eval_assert("config.repl_banner == None")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_repl_startup_code() -> Result<()> {
+        let mut env = get_env()?;
+
+        env.eval_assert("config.repl_startup_code == None")?;
+
+        env.eval("config.repl_startup_code = 'import os'")?;
+        env.eval_assert("config.repl_startup_code == 'import os'")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_repl_ps1() -> Result<()> {
+        let mut env = get_env()?;
+
+        env.eval_assert("config.repl_ps1 == None")?;
+
+        env.eval("config.repl_ps1 = 'app> '")?;
+        env.eval_assert("config.repl_ps1 == 'app> '")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_repl_ps2() -> Result<()> {
+        let mut env = get_env()?;
+
+        env.eval_assert("config.repl_ps2 == None")?;
+
+        env.eval("config.repl_ps2 = '... '")?;
+        env.eval_assert("config.repl_ps2 == '... '")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_debug_shell() -> Result<()> {
+        let mut env = get_env()?;
+
+        env.eval_assert("config.debug_shell == False")?;
+
+        env.eval("config.debug_shell = True")?;
+        env.eval_assert("config.debug_shell == True")?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_terminfo_resolution() -> Result<()> {
         let mut env = get_env()?;
@@ -1204,6 +1756,12 @@ mod tests {
         env.eval("config.terminfo_resolution = 'static:foo'")?;
         env.eval_assert("config.terminfo_resolution == 'static:foo'")?;
 
+        env.eval("config.terminfo_resolution = 'bundled'")?;
+        env.eval_assert("config.terminfo_resolution == 'bundled'")?;
+
+        env.eval("config.terminfo_resolution = 'system'")?;
+        env.eval_assert("config.terminfo_resolution == 'dynamic'")?;
+
         Ok(())
     }
 
@@ -1215,4 +1773,27 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_restricted_packages() -> Result<()> {
+        let mut env = get_env()?;
+
+        env.eval_assert("config.restricted_packages == {}")?;
+        env.eval_assert("config.capabilities == []")?;
+        env.eval_assert("config.capabilities_env == None")?;
+
+        env.eval("config.restricted_packages = {'myapp.pro': 'pro'}")?;
+        env.eval_assert("config.restricted_packages == {'myapp.pro': 'pro'}")?;
+        assert!(env
+            .eval("config.restricted_packages = {'myapp.pro': 1}")
+            .is_err());
+
+        env.eval("config.capabilities = ['basic']")?;
+        env.eval_assert("config.capabilities == ['basic']")?;
+
+        env.eval("config.capabilities_env = 'MYAPP_TIER'")?;
+        env.eval_assert("config.capabilities_env == 'MYAPP_TIER'")?;
+
+        Ok(())
+    }
 }
@@ -0,0 +1,74 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    crate::{
+        paths::write_file_manifest,
+        project_building::{build_python_library, library_c_header},
+        py_packaging::binary::PythonBinaryBuilder,
+    },
+    anyhow::{Context, Result},
+    slog::warn,
+    starlark::values::{Mutable, TypedValue, Value},
+    starlark_dialect_build_targets::{BuildContext, BuildTarget, ResolvedTarget, RunMode},
+    std::{ops::Deref, sync::Arc},
+};
+
+/// A library embedding a Python interpreter and resources.
+pub struct PythonLibrary {
+    pub exe: Arc<dyn PythonBinaryBuilder>,
+
+    /// Whether to build a static library in addition to the shared library.
+    pub static_library: bool,
+}
+
+impl TypedValue for PythonLibrary {
+    type Holder = Mutable<PythonLibrary>;
+    const TYPE: &'static str = "PythonLibrary";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+}
+
+impl BuildTarget for PythonLibrary {
+    fn build(&mut self, context: &dyn BuildContext) -> Result<ResolvedTarget> {
+        let build = build_python_library(
+            context.logger(),
+            &self.exe.name(),
+            self.exe.deref(),
+            context.get_state_string("target_triple")?,
+            context.get_state_string("opt_level")?,
+            context.get_state_bool("release")?,
+            self.static_library,
+        )?;
+
+        let output_path = context.get_state_path("output_path")?;
+
+        let header = (
+            format!("{}.h", build.name),
+            library_c_header(&build.name).into_bytes(),
+        );
+
+        let files = build
+            .static_library
+            .into_iter()
+            .chain(vec![build.shared_library, header]);
+
+        for (filename, data) in files {
+            let dest_path = output_path.join(filename);
+            warn!(context.logger(), "writing {}", dest_path.display());
+            std::fs::write(&dest_path, data)
+                .with_context(|| format!("writing {}", dest_path.display()))?;
+        }
+
+        write_file_manifest(&build.binary_data.extra_files, output_path)
+            .context("writing extra files")?;
+
+        Ok(ResolvedTarget {
+            run_mode: RunMode::None,
+            output_path: output_path.to_path_buf(),
+        })
+    }
+}
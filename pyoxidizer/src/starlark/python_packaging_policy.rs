@@ -3,12 +3,30 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use {
-    super::python_resource::ResourceCollectionContext,
+    super::{
+        env::{get_context, PyOxidizerEnvironmentContext},
+        python_resource::{
+            FileValue, GettextCatalogValue, PythonExtensionModuleValue, PythonModuleSourceValue,
+            PythonPackageDistributionResourceValue, PythonPackageResourceValue,
+            ResourceCollectionContext,
+        },
+        structured_data::json_to_value,
+    },
+    crate::py_packaging::policy_file::write_policy_file,
+    anyhow::Context,
     linked_hash_map::LinkedHashMap,
     python_packaging::{
+        import_trace::ImportTrace,
         location::ConcreteResourceLocation,
-        policy::{ExtensionModuleFilter, PythonPackagingPolicy, ResourceHandlingMode},
+        package_distribution::PackageDistribution,
+        package_hints::PackageHint,
+        policy::{
+            AddCollectionContextRules, BuildSandbox, ExtensionModuleFilter, PackageOverride,
+            PythonPackagingPolicy, PythonSecurityAction, ResourceHandlingMode,
+        },
+        resource::{DataLocation, PythonModuleSource},
     },
+    slog::warn,
     starlark::{
         environment::TypeValues,
         eval::call_stack::CallStack,
@@ -17,12 +35,16 @@ use {
         values::{
             error::{RuntimeError, UnsupportedOperation, ValueError},
             none::NoneType,
-            Mutable, TypedValue, Value, ValueResult,
+            Immutable, Mutable, TypedValue, Value, ValueResult,
         },
     },
-    starlark_dialect_build_targets::required_type_arg,
+    starlark_dialect_build_targets::{
+        optional_bool_arg, optional_list_arg, optional_str_arg, required_list_arg,
+        required_type_arg,
+    },
     std::convert::TryFrom,
     std::ops::Deref,
+    std::path::PathBuf,
 };
 
 #[derive(Debug, Clone)]
@@ -30,7 +52,70 @@ pub struct PythonPackagingPolicyValue {
     pub inner: PythonPackagingPolicy,
 
     /// Starlark functions to influence PythonResourceAddCollectionContext creation.
-    derive_context_callbacks: Vec<Value>,
+    ///
+    /// Functions are paired with their priority and sorted by it, so
+    /// functions with a higher priority run last. The flag indicates whether
+    /// the function receives a `ResourceCallbackContext`.
+    derive_context_callbacks: Vec<(i64, Value, bool)>,
+
+    /// Starlark functions rewriting the source code of Python modules.
+    ///
+    /// Functions run in registration order, each receiving the output of
+    /// the previous one.
+    source_transforms: Vec<Value>,
+}
+
+/// Describes where a resource passed to a resource callback originates from.
+#[derive(Debug, Clone)]
+pub struct ResourceCallbackContextValue {
+    pub distribution: Option<PackageDistribution>,
+}
+
+impl TypedValue for ResourceCallbackContextValue {
+    type Holder = Immutable<ResourceCallbackContextValue>;
+    const TYPE: &'static str = "ResourceCallbackContext";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+
+    fn get_attr(&self, attribute: &str) -> ValueResult {
+        let distribution = self.distribution.as_ref();
+
+        Ok(match attribute {
+            "distribution_name" => match distribution {
+                Some(d) => Value::from(d.name.as_str()),
+                None => Value::from(NoneType::None),
+            },
+            "distribution_version" => match distribution {
+                Some(d) => Value::from(d.version.as_str()),
+                None => Value::from(NoneType::None),
+            },
+            "origin_path" => match distribution.and_then(|d| d.origin.as_ref()) {
+                Some(path) => Value::from(format!("{}", path.display())),
+                None => Value::from(NoneType::None),
+            },
+            "requires_dist" => Value::from(
+                distribution
+                    .map(|d| d.requires_dist.clone())
+                    .unwrap_or_default(),
+            ),
+            attr => {
+                return Err(ValueError::OperationNotSupported {
+                    op: UnsupportedOperation::GetAttr(attr.to_string()),
+                    left: Self::TYPE.to_string(),
+                    right: None,
+                })
+            }
+        })
+    }
+
+    fn has_attr(&self, attribute: &str) -> Result<bool, ValueError> {
+        Ok(matches!(
+            attribute,
+            "distribution_name" | "distribution_version" | "origin_path" | "requires_dist"
+        ))
+    }
 }
 
 impl PythonPackagingPolicyValue {
@@ -38,7 +123,86 @@ impl PythonPackagingPolicyValue {
         Self {
             inner,
             derive_context_callbacks: vec![],
+            source_transforms: vec![],
+        }
+    }
+
+    /// Whether a registered resource callback wants a context.
+    pub fn has_context_callbacks(&self) -> bool {
+        self.derive_context_callbacks
+            .iter()
+            .any(|(_, _, wants_context)| *wants_context)
+    }
+
+    /// Rewrite the source code of a Python module with registered source transforms.
+    ///
+    /// This is a no-op if no transforms are registered.
+    pub fn transform_module_source(
+        &self,
+        type_values: &TypeValues,
+        call_stack: &mut CallStack,
+        module: &mut PythonModuleSource,
+    ) -> Result<(), ValueError> {
+        if self.source_transforms.is_empty() {
+            return Ok(());
+        }
+
+        let label = "register_source_transform()".to_string();
+
+        let source = module.source.resolve().map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_SOURCE_ERROR",
+                message: format!("error resolving source code of {}: {}", module.name, e),
+                label: label.clone(),
+            })
+        })?;
+        let mut source = String::from_utf8(source).map_err(|_| {
+            ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_SOURCE_ERROR",
+                message: format!("error converting source code of {} to UTF-8", module.name),
+                label: label.clone(),
+            })
+        })?;
+        let original = source.clone();
+
+        for func in &self.source_transforms {
+            let res = func.call(
+                call_stack,
+                type_values,
+                vec![
+                    Value::from(module.name.as_str()),
+                    Value::from(source.as_str()),
+                ],
+                LinkedHashMap::new(),
+                None,
+                None,
+            )?;
+
+            match res.get_type() {
+                "NoneType" => {}
+                "string" => {
+                    source = res.to_string();
+                }
+                t => {
+                    return Err(ValueError::from(RuntimeError {
+                        code: "PYOXIDIZER_BUILD",
+                        message: format!(
+                            "source transform {} returned {} for {}; expected string or None",
+                            func.to_str(),
+                            t,
+                            module.name
+                        ),
+                        label,
+                    }));
+                }
+            }
         }
+
+        if source != original {
+            module.source = DataLocation::Memory(source.into_bytes());
+        }
+
+        Ok(())
     }
 
     /// Apply this policy to a resource.
@@ -46,21 +210,69 @@ impl PythonPackagingPolicyValue {
     /// This has the effect of replacing the `PythonResourceAddCollectionContext`
     /// instance with a fresh one derived from the policy. If no context is
     /// currently defined on the resource, a new one will be created so there is.
+    ///
+    /// `distribution` is the package distribution the resource originates
+    /// from, if known. It is exposed to callbacks wanting a context.
     pub fn apply_to_resource<T>(
         &self,
         type_values: &TypeValues,
         call_stack: &mut CallStack,
         value: &mut T,
+        distribution: Option<&PackageDistribution>,
     ) -> ValueResult
     where
         T: TypedValue + ResourceCollectionContext + Clone,
     {
-        let new_context = self
+        self.explain_apply_to_resource(type_values, call_stack, value, distribution)?;
+
+        Ok(Value::from(NoneType::None))
+    }
+
+    /// Apply this policy to a resource, returning the rules which decided its context.
+    ///
+    /// Callbacks changing a setting of the context are reported as
+    /// `callback:<function>`.
+    fn explain_apply_to_resource<T>(
+        &self,
+        type_values: &TypeValues,
+        call_stack: &mut CallStack,
+        value: &mut T,
+        distribution: Option<&PackageDistribution>,
+    ) -> Result<AddCollectionContextRules, ValueError>
+    where
+        T: TypedValue + ResourceCollectionContext + Clone,
+    {
+        let name = value.as_python_resource().full_name();
+
+        // Resolve the logger up front so the context isn't borrowed when
+        // callbacks run.
+        let trace_logger = {
+            let pyoxidizer_context_value = get_context(type_values)?;
+            let pyoxidizer_context = pyoxidizer_context_value
+                .downcast_ref::<PyOxidizerEnvironmentContext>()
+                .ok_or(ValueError::IncorrectParameterType)?;
+
+            if pyoxidizer_context.trace_resource(&name) {
+                Some(pyoxidizer_context.logger().clone())
+            } else {
+                None
+            }
+        };
+
+        let (new_context, mut rules) = self
             .inner
-            .derive_add_collection_context(&value.as_python_resource());
+            .explain_add_collection_context(&value.as_python_resource());
+
+        if let Some(logger) = &trace_logger {
+            warn!(
+                logger,
+                "trace {}: policy derived add context {:?}", name, new_context
+            );
+        }
+
         value.add_collection_context_mut().replace(new_context);
 
-        for func in &self.derive_context_callbacks {
+        for (_, func, wants_context) in &self.derive_context_callbacks {
             // This is a bit wonky. We pass in a `TypeValue`, which isn't a `Value`.
             // To go from `TypeValue` to `Value`, we need to construct a `Value`, which
             // takes ownership of the `TypeValue`. But we need to move a `Value` as an
@@ -74,10 +286,17 @@ impl PythonPackagingPolicyValue {
             // this solution works.
             let temp_value = Value::new(value.clone());
 
+            let mut args = vec![Value::new(self.clone()), temp_value.clone()];
+            if *wants_context {
+                args.push(Value::new(ResourceCallbackContextValue {
+                    distribution: distribution.cloned(),
+                }));
+            }
+
             func.call(
                 call_stack,
                 type_values,
-                vec![Value::new(self.clone()), temp_value.clone()],
+                args,
                 LinkedHashMap::new(),
                 None,
                 None,
@@ -85,12 +304,77 @@ impl PythonPackagingPolicyValue {
 
             let downcast_value = temp_value.downcast_ref::<T>().unwrap();
             let inner: &T = downcast_value.deref();
-            value
-                .add_collection_context_mut()
-                .replace(inner.add_collection_context().as_ref().unwrap().clone());
+            let new_context = inner.add_collection_context().as_ref().unwrap().clone();
+
+            if let Some(old_context) = value.add_collection_context() {
+                let rule = format!("callback:{}", func.to_str());
+
+                if old_context.include != new_context.include {
+                    rules.include = rule.clone();
+                }
+                if old_context.location != new_context.location
+                    || old_context.location_fallback != new_context.location_fallback
+                {
+                    rules.location = rule.clone();
+                }
+                if old_context.store_source != new_context.store_source {
+                    rules.store_source = rule;
+                }
+            }
+
+            if let Some(logger) = &trace_logger {
+                if value.add_collection_context().as_ref() == Some(&new_context) {
+                    warn!(
+                        logger,
+                        "trace {}: callback {} left add context unchanged",
+                        name,
+                        func.to_str()
+                    );
+                } else {
+                    warn!(
+                        logger,
+                        "trace {}: callback {} changed add context to {:?}",
+                        name,
+                        func.to_str(),
+                        new_context
+                    );
+                }
+            }
+
+            value.add_collection_context_mut().replace(new_context);
         }
 
-        Ok(Value::from(NoneType::None))
+        Ok(rules)
+    }
+
+    /// Describe how this policy adds a resource, as JSON.
+    fn explain_resource<T>(
+        &self,
+        type_values: &TypeValues,
+        call_stack: &mut CallStack,
+        value: &T,
+    ) -> Result<serde_json::Value, ValueError>
+    where
+        T: TypedValue + ResourceCollectionContext + Clone,
+    {
+        let mut value = value.clone();
+        let rules = self.explain_apply_to_resource(type_values, call_stack, &mut value, None)?;
+        let context = value
+            .add_collection_context()
+            .clone()
+            .expect("add context should have been populated by policy");
+
+        Ok(serde_json::json!({
+            "resource": value.as_python_resource().full_name(),
+            "type": T::TYPE,
+            "include": context.include,
+            "location": context.location.to_string(),
+            "location_fallback": context.location_fallback.map(|l| l.to_string()),
+            "store_source": context.store_source,
+            "include_rule": rules.include,
+            "location_rule": rules.location,
+            "store_source_rule": rules.store_source,
+        }))
     }
 }
 
@@ -101,23 +385,39 @@ impl TypedValue for PythonPackagingPolicyValue {
     fn values_for_descendant_check_and_freeze<'a>(
         &'a self,
     ) -> Box<dyn Iterator<Item = Value> + 'a> {
-        Box::new(self.derive_context_callbacks.iter().cloned())
+        Box::new(
+            self.derive_context_callbacks
+                .iter()
+                .map(|(_, func, _)| func.clone())
+                .chain(self.source_transforms.iter().cloned()),
+        )
     }
 
     fn get_attr(&self, attribute: &str) -> ValueResult {
         let v = match attribute {
             "allow_files" => Value::from(self.inner.allow_files()),
+            "allowed_licenses" => Value::from(self.inner.allowed_licenses().to_vec()),
             "allow_in_memory_shared_library_loading" => {
                 Value::from(self.inner.allow_in_memory_shared_library_loading())
             }
+            "apply_package_hints" => Value::from(self.inner.apply_package_hints()),
+            "build_sandbox" => Value::from(self.inner.build_sandbox().as_ref()),
+            "build_sandbox_allow_network" => Value::from(self.inner.build_sandbox_allow_network()),
             "bytecode_optimize_level_zero" => {
                 Value::from(self.inner.bytecode_optimize_level_zero())
             }
             "bytecode_optimize_level_one" => Value::from(self.inner.bytecode_optimize_level_one()),
             "bytecode_optimize_level_two" => Value::from(self.inner.bytecode_optimize_level_two()),
+            "denied_licenses" => Value::from(self.inner.denied_licenses().to_vec()),
+            "exclude_patterns" => Value::from(self.inner.exclude_patterns().to_vec()),
+            "excluded_modules" => Value::from(self.inner.excluded_modules().to_vec()),
             "extension_module_filter" => Value::from(self.inner.extension_module_filter().as_ref()),
             "file_scanner_classify_files" => Value::from(self.inner.file_scanner_classify_files()),
             "file_scanner_emit_files" => Value::from(self.inner.file_scanner_emit_files()),
+            "gettext_catalogs_location" => match self.inner.gettext_catalogs_location() {
+                Some(location) => Value::from(location.to_string()),
+                None => Value::from(NoneType::None),
+            },
             "include_distribution_sources" => {
                 Value::from(self.inner.include_distribution_sources())
             }
@@ -131,15 +431,27 @@ impl TypedValue for PythonPackagingPolicyValue {
             "include_non_distribution_sources" => {
                 Value::from(self.inner.include_non_distribution_sources())
             }
+            "include_patterns" => Value::from(self.inner.include_patterns().to_vec()),
             "include_test" => Value::from(self.inner.include_test()),
+            "prefer_abi3_extensions" => Value::from(self.inner.prefer_abi3_extensions()),
             "preferred_extension_module_variants" => {
                 Value::try_from(self.inner.preferred_extension_module_variants().clone())?
             }
+            "python_security_action" => Value::from(self.inner.python_security_action().as_ref()),
+            "python_security_baseline" => match self.inner.python_security_baseline() {
+                Some(date) => Value::from(date),
+                None => Value::from(NoneType::None),
+            },
             "resources_location" => Value::from(self.inner.resources_location().to_string()),
             "resources_location_fallback" => match self.inner.resources_location_fallback() {
                 Some(location) => Value::from(location.to_string()),
                 None => Value::from(NoneType::None),
             },
+            "stdlib_profile" => match self.inner.stdlib_profile() {
+                Some(name) => Value::from(name),
+                None => Value::from(NoneType::None),
+            },
+            "strip_docstrings" => Value::from(self.inner.strip_docstrings()),
             attr => {
                 return Err(ValueError::OperationNotSupported {
                     op: UnsupportedOperation::GetAttr(attr.to_string()),
@@ -155,22 +467,36 @@ impl TypedValue for PythonPackagingPolicyValue {
     fn has_attr(&self, attribute: &str) -> Result<bool, ValueError> {
         Ok(match attribute {
             "allow_files" => true,
+            "allowed_licenses" => true,
             "allow_in_memory_shared_library_loading" => true,
+            "apply_package_hints" => true,
+            "build_sandbox" => true,
+            "build_sandbox_allow_network" => true,
             "bytecode_optimize_level_zero" => true,
             "bytecode_optimize_level_one" => true,
             "bytecode_optimize_level_two" => true,
+            "denied_licenses" => true,
+            "exclude_patterns" => true,
+            "excluded_modules" => true,
             "extension_module_filter" => true,
             "file_scanner_classify_files" => true,
             "file_scanner_emit_files" => true,
+            "gettext_catalogs_location" => true,
             "include_distribution_sources" => true,
             "include_distribution_resources" => true,
             "include_classified_resources" => true,
             "include_file_resources" => true,
             "include_non_distribution_sources" => true,
+            "include_patterns" => true,
             "include_test" => true,
+            "prefer_abi3_extensions" => true,
             "preferred_extension_module_variants" => true,
+            "python_security_action" => true,
+            "python_security_baseline" => true,
             "resources_location" => true,
             "resources_location_fallback" => true,
+            "stdlib_profile" => true,
+            "strip_docstrings" => true,
             _ => false,
         })
     }
@@ -180,10 +506,49 @@ impl TypedValue for PythonPackagingPolicyValue {
             "allow_files" => {
                 self.inner.set_allow_files(value.to_bool());
             }
+            "allowed_licenses" | "denied_licenses" => {
+                required_list_arg(attribute, "string", &value)?;
+                let patterns = value
+                    .iter()?
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<_>>();
+
+                let res = if attribute == "allowed_licenses" {
+                    self.inner.set_allowed_licenses(patterns)
+                } else {
+                    self.inner.set_denied_licenses(patterns)
+                };
+
+                res.map_err(|e| {
+                    ValueError::from(RuntimeError {
+                        code: "PYOXIDIZER_BUILD",
+                        message: format!("{:#}", e),
+                        label: format!("{}.{}", Self::TYPE, attribute),
+                    })
+                })?;
+            }
             "allow_in_memory_shared_library_loading" => {
                 self.inner
                     .set_allow_in_memory_shared_library_loading(value.to_bool());
             }
+            "apply_package_hints" => {
+                self.inner.set_apply_package_hints(value.to_bool());
+            }
+            "build_sandbox" => {
+                self.inner.set_build_sandbox(
+                    BuildSandbox::try_from(value.to_string().as_str()).map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: "PYOXIDIZER_BUILD",
+                            message: e,
+                            label: format!("{}.{} = {}", Self::TYPE, attribute, value),
+                        })
+                    })?,
+                );
+            }
+            "build_sandbox_allow_network" => {
+                self.inner.set_build_sandbox_allow_network(value.to_bool());
+            }
             "bytecode_optimize_level_zero" => {
                 self.inner.set_bytecode_optimize_level_zero(value.to_bool());
             }
@@ -211,6 +576,33 @@ impl TypedValue for PythonPackagingPolicyValue {
             "file_scanner_emit_files" => {
                 self.inner.set_file_scanner_emit_files(value.to_bool());
             }
+            "gettext_catalogs_location" => {
+                let location = if value.get_type() == "NoneType" {
+                    None
+                } else {
+                    Some(
+                        ConcreteResourceLocation::try_from(value.to_string().as_str()).map_err(
+                            |e| {
+                                ValueError::from(RuntimeError {
+                                    code: "PYOXIDIZER_BUILD",
+                                    message: e,
+                                    label: format!("{}.{} = {}", Self::TYPE, attribute, value),
+                                })
+                            },
+                        )?,
+                    )
+                };
+
+                self.inner
+                    .set_gettext_catalogs_location(location)
+                    .map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: "PYOXIDIZER_BUILD",
+                            message: format!("{:#}", e),
+                            label: format!("{}.{} = {}", Self::TYPE, attribute, value),
+                        })
+                    })?;
+            }
             "include_classified_resources" => {
                 self.inner.set_include_classified_resources(value.to_bool());
             }
@@ -231,6 +623,37 @@ impl TypedValue for PythonPackagingPolicyValue {
             "include_test" => {
                 self.inner.set_include_test(value.to_bool());
             }
+            "prefer_abi3_extensions" => {
+                self.inner.set_prefer_abi3_extensions(value.to_bool());
+            }
+            "python_security_action" => {
+                self.inner.set_python_security_action(
+                    PythonSecurityAction::try_from(value.to_string().as_str()).map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: "PYOXIDIZER_BUILD",
+                            message: e,
+                            label: format!("{}.{} = {}", Self::TYPE, attribute, value),
+                        })
+                    })?,
+                );
+            }
+            "python_security_baseline" => {
+                let date = if value.get_type() == "NoneType" {
+                    None
+                } else {
+                    Some(value.to_string())
+                };
+
+                self.inner
+                    .set_python_security_baseline(date.as_deref())
+                    .map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: "PYOXIDIZER_BUILD",
+                            message: format!("{:#}", e),
+                            label: format!("{}.{} = {}", Self::TYPE, attribute, value),
+                        })
+                    })?;
+            }
             "resources_location" => {
                 self.inner.set_resources_location(
                     ConcreteResourceLocation::try_from(value.to_string().as_str()).map_err(
@@ -271,6 +694,26 @@ impl TypedValue for PythonPackagingPolicyValue {
                     ));
                 }
             }
+            "stdlib_profile" => {
+                let name = if value.get_type() == "NoneType" {
+                    None
+                } else {
+                    Some(value.to_string())
+                };
+
+                self.inner
+                    .set_stdlib_profile(name.as_deref())
+                    .map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: "PYOXIDIZER_BUILD",
+                            message: format!("{:#}", e),
+                            label: format!("{}.{} = {}", Self::TYPE, attribute, value),
+                        })
+                    })?;
+            }
+            "strip_docstrings" => {
+                self.inner.set_strip_docstrings(value.to_bool());
+            }
             attr => {
                 return Err(ValueError::OperationNotSupported {
                     op: UnsupportedOperation::SetAttr(attr.to_string()),
@@ -286,101 +729,550 @@ impl TypedValue for PythonPackagingPolicyValue {
 
 // Starlark methods.
 impl PythonPackagingPolicyValue {
-    fn starlark_register_resource_callback(&mut self, func: &Value) -> ValueResult {
-        required_type_arg("func", "function", func)?;
-
-        self.derive_context_callbacks.push(func.clone());
+    fn starlark_add_import_trace(&mut self, type_values: &TypeValues, path: String) -> ValueResult {
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let path = PathBuf::from(path);
+        let path = if path.is_absolute() {
+            path
+        } else {
+            pyoxidizer_context.cwd.join(path)
+        };
 
-        Ok(Value::from(NoneType::None))
-    }
+        let trace = std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|data| ImportTrace::from_importtime_output(&data))
+            .map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: format!("unable to load import trace {}: {}", path.display(), e),
+                    label: "add_import_trace()".to_string(),
+                })
+            })?;
 
-    fn starlark_set_preferred_extension_module_variant(
-        &mut self,
-        name: String,
-        value: String,
-    ) -> ValueResult {
-        self.inner
-            .set_preferred_extension_module_variant(&name, &value);
+        let mut merged = self.inner.import_trace().cloned().unwrap_or_default();
+        merged.merge(&trace);
+        self.inner.set_import_trace(Some(merged));
 
         Ok(Value::from(NoneType::None))
     }
 
-    fn starlark_set_resource_handling_mode(&mut self, value: String) -> ValueResult {
-        let mode = ResourceHandlingMode::try_from(value.as_str()).map_err(|e| {
+    fn starlark_add_exclude_pattern(&mut self, pattern: String) -> ValueResult {
+        self.inner.add_exclude_pattern(&pattern).map_err(|e| {
             ValueError::from(RuntimeError {
                 code: "PYOXIDIZER_BUILD",
-                message: e,
-                label: "set_resource_handling_mode()".to_string(),
+                message: format!("{:#}", e),
+                label: "add_exclude_pattern()".to_string(),
             })
         })?;
 
-        self.inner.set_resource_handling_mode(mode);
-
         Ok(Value::from(NoneType::None))
     }
-}
 
-starlark_module! { python_packaging_policy_module =>
-    PythonPackagingPolicy.register_resource_callback(this, func) {
-        match this.clone().downcast_mut::<PythonPackagingPolicyValue>()? {
-            Some(mut policy) => policy.starlark_register_resource_callback(&func),
-            None => Err(ValueError::IncorrectParameterType),
-        }
-    }
+    fn starlark_exclude_modules(&mut self, names: &Value) -> ValueResult {
+        required_list_arg("names", "string", names)?;
 
-    PythonPackagingPolicy.set_preferred_extension_module_variant(
-        this,
-        name: String,
-        value: String
-    ) {
-        match this.clone().downcast_mut::<PythonPackagingPolicyValue>()? {
-            Some(mut policy) => policy.starlark_set_preferred_extension_module_variant(name, value),
-            None => Err(ValueError::IncorrectParameterType),
+        for name in names.iter()?.iter() {
+            self.inner.exclude_module(&name.to_string()).map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: format!("{:#}", e),
+                    label: "exclude_modules()".to_string(),
+                })
+            })?;
         }
+
+        Ok(Value::from(NoneType::None))
     }
 
-    PythonPackagingPolicy.set_resource_handling_mode(this, mode: String) {
-        match this.clone().downcast_mut::<PythonPackagingPolicyValue>()? {
-            Some(mut policy) => policy.starlark_set_resource_handling_mode(mode),
-            None => Err(ValueError::IncorrectParameterType),
-        }
+    fn starlark_add_include_pattern(&mut self, pattern: String) -> ValueResult {
+        self.inner.add_include_pattern(&pattern).map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_BUILD",
+                message: format!("{:#}", e),
+                label: "add_include_pattern()".to_string(),
+            })
+        })?;
+
+        Ok(Value::from(NoneType::None))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use {
-        super::super::python_distribution::PythonDistributionValue, super::super::testutil::*,
-        super::*, anyhow::Result,
-    };
+    fn starlark_apply_profile(&mut self, name: String) -> ValueResult {
+        self.inner.apply_profile(&name).map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_BUILD",
+                message: format!("{:#}", e),
+                label: "apply_profile()".to_string(),
+            })
+        })?;
 
-    #[test]
-    fn test_basic() -> Result<()> {
-        let mut env = StarlarkEnvironment::new()?;
+        Ok(Value::from(NoneType::None))
+    }
 
-        env.eval("dist = default_python_distribution()")?;
-        env.eval("policy = dist.make_python_packaging_policy()")?;
+    fn starlark_register_resource_callback(
+        &mut self,
+        func: &Value,
+        priority: i64,
+        context: bool,
+    ) -> ValueResult {
+        required_type_arg("func", "function", func)?;
 
-        let dist_value = env.eval("dist")?;
-        let dist = dist_value
-            .downcast_ref::<PythonDistributionValue>()
-            .unwrap();
-        let dist_ref = dist.distribution.as_ref().unwrap();
+        // Functions registered with the same priority run in registration order.
+        let index = self
+            .derive_context_callbacks
+            .iter()
+            .position(|(p, _, _)| *p > priority)
+            .unwrap_or_else(|| self.derive_context_callbacks.len());
+        self.derive_context_callbacks
+            .insert(index, (priority, func.clone(), context));
 
-        let policy = dist_ref.create_packaging_policy().unwrap();
+        Ok(Value::from(NoneType::None))
+    }
 
-        // Need value to go out of scope to avoid double borrow.
-        {
-            let policy_value = env.eval("policy")?;
-            assert_eq!(policy_value.get_type(), "PythonPackagingPolicy");
+    fn starlark_register_source_transform(&mut self, func: &Value) -> ValueResult {
+        required_type_arg("func", "function", func)?;
 
-            let x = policy_value
-                .downcast_ref::<PythonPackagingPolicyValue>()
-                .unwrap();
+        self.source_transforms.push(func.clone());
 
-            // Distribution method should return a policy equivalent to what Starlark gives us.
-            assert_eq!(policy, x.inner);
-        }
+        Ok(Value::from(NoneType::None))
+    }
+
+    fn starlark_unregister_resource_callback(&mut self, func: &Value) -> ValueResult {
+        required_type_arg("func", "function", func)?;
+
+        let count = self.derive_context_callbacks.len();
+        self.derive_context_callbacks
+            .retain(|(_, f, _)| f.data_ptr() != func.data_ptr());
+
+        if self.derive_context_callbacks.len() == count {
+            return Err(ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_BUILD",
+                message: format!("{} is not a registered resource callback", func.to_str()),
+                label: "unregister_resource_callback()".to_string(),
+            }));
+        }
+
+        Ok(Value::from(NoneType::None))
+    }
+
+    fn starlark_register_package_hint(
+        &mut self,
+        package: String,
+        hidden_imports: &Value,
+        requires_filesystem: bool,
+        include_resources: bool,
+    ) -> ValueResult {
+        optional_list_arg("hidden_imports", "string", hidden_imports)?;
+
+        let hidden_imports = match hidden_imports.get_type() {
+            "list" => hidden_imports
+                .iter()?
+                .iter()
+                .map(|x| x.to_string())
+                .collect(),
+            _ => vec![],
+        };
+
+        self.inner.register_package_hint(PackageHint {
+            package,
+            hidden_imports,
+            requires_filesystem,
+            include_resources,
+        });
+
+        Ok(Value::from(NoneType::None))
+    }
+
+    fn starlark_set_package_optimize_levels(
+        &mut self,
+        package: String,
+        levels: &Value,
+    ) -> ValueResult {
+        required_list_arg("levels", "int", levels)?;
+
+        let levels = levels
+            .iter()?
+            .iter()
+            // Out of range values are rejected as invalid levels.
+            .map(|x| {
+                x.to_int()
+                    .map(|level| i32::try_from(level).unwrap_or(i32::MAX))
+            })
+            .collect::<Result<Vec<_>, ValueError>>()?;
+
+        self.inner
+            .set_package_optimize_levels(&package, &levels)
+            .map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: format!("{:#}", e),
+                    label: "set_package_optimize_levels()".to_string(),
+                })
+            })?;
+
+        Ok(Value::from(NoneType::None))
+    }
+
+    fn starlark_set_package_override(
+        &mut self,
+        package: String,
+        include: &Value,
+        include_sources: &Value,
+        resources_location: &Value,
+        resources_location_fallback: &Value,
+    ) -> ValueResult {
+        let include = optional_bool_arg("include", include)?;
+        let include_sources = optional_bool_arg("include_sources", include_sources)?;
+
+        let parse_location = |name: &str, value: &Value| -> Result<_, ValueError> {
+            optional_str_arg(name, value)?
+                .map(|value| {
+                    ConcreteResourceLocation::try_from(value.as_str()).map_err(|e| {
+                        ValueError::from(RuntimeError {
+                            code: "PYOXIDIZER_BUILD",
+                            message: e,
+                            label: "set_package_override()".to_string(),
+                        })
+                    })
+                })
+                .transpose()
+        };
+
+        let resources_location = parse_location("resources_location", resources_location)?;
+        let resources_location_fallback =
+            parse_location("resources_location_fallback", resources_location_fallback)?;
+
+        self.inner
+            .set_package_override(PackageOverride {
+                package,
+                include,
+                include_sources,
+                resources_location,
+                resources_location_fallback,
+            })
+            .map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: format!("{:#}", e),
+                    label: "set_package_override()".to_string(),
+                })
+            })?;
+
+        Ok(Value::from(NoneType::None))
+    }
+
+    fn starlark_set_preferred_extension_module_variant(
+        &mut self,
+        name: String,
+        value: String,
+    ) -> ValueResult {
+        self.inner
+            .set_preferred_extension_module_variant(&name, &value);
+
+        Ok(Value::from(NoneType::None))
+    }
+
+    fn starlark_set_resource_handling_mode(&mut self, value: String) -> ValueResult {
+        let mode = ResourceHandlingMode::try_from(value.as_str()).map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_BUILD",
+                message: e,
+                label: "set_resource_handling_mode()".to_string(),
+            })
+        })?;
+
+        self.inner.set_resource_handling_mode(mode);
+
+        Ok(Value::from(NoneType::None))
+    }
+
+    fn starlark_explain(
+        &self,
+        type_values: &TypeValues,
+        call_stack: &mut CallStack,
+        resources: &Value,
+        path: &Value,
+    ) -> ValueResult {
+        required_type_arg("resources", "list", resources)?;
+        let path = optional_str_arg("path", path)?;
+
+        let mut entries = vec![];
+
+        for resource in &resources.iter()? {
+            entries.push(match resource.get_type() {
+                "PythonModuleSource" => self.explain_resource(
+                    type_values,
+                    call_stack,
+                    &*resource.downcast_ref::<PythonModuleSourceValue>().unwrap(),
+                )?,
+                "PythonPackageResource" => self.explain_resource(
+                    type_values,
+                    call_stack,
+                    &*resource
+                        .downcast_ref::<PythonPackageResourceValue>()
+                        .unwrap(),
+                )?,
+                "PythonPackageDistributionResource" => self.explain_resource(
+                    type_values,
+                    call_stack,
+                    &*resource
+                        .downcast_ref::<PythonPackageDistributionResourceValue>()
+                        .unwrap(),
+                )?,
+                "PythonExtensionModule" => self.explain_resource(
+                    type_values,
+                    call_stack,
+                    &*resource
+                        .downcast_ref::<PythonExtensionModuleValue>()
+                        .unwrap(),
+                )?,
+                "File" => self.explain_resource(
+                    type_values,
+                    call_stack,
+                    &*resource.downcast_ref::<FileValue>().unwrap(),
+                )?,
+                "GettextCatalog" => self.explain_resource(
+                    type_values,
+                    call_stack,
+                    &*resource.downcast_ref::<GettextCatalogValue>().unwrap(),
+                )?,
+                t => {
+                    return Err(ValueError::from(RuntimeError {
+                        code: "PYOXIDIZER_BUILD",
+                        message: format!("unable to explain how {} is added", t),
+                        label: "explain()".to_string(),
+                    }))
+                }
+            });
+        }
+
+        let report = serde_json::Value::Array(entries);
+
+        if let Some(path) = path {
+            let pyoxidizer_context_value = get_context(type_values)?;
+            let pyoxidizer_context = pyoxidizer_context_value
+                .downcast_ref::<PyOxidizerEnvironmentContext>()
+                .ok_or(ValueError::IncorrectParameterType)?;
+
+            let path = pyoxidizer_context.cwd.join(path);
+
+            serde_json::to_string_pretty(&report)
+                .map_err(anyhow::Error::from)
+                .and_then(|data| {
+                    std::fs::write(&path, data)
+                        .with_context(|| format!("writing {}", path.display()))
+                })
+                .map_err(|e| {
+                    ValueError::from(RuntimeError {
+                        code: "PYOXIDIZER_BUILD",
+                        message: format!("{:#}", e),
+                        label: "explain()".to_string(),
+                    })
+                })?;
+        }
+
+        json_to_value(&report)
+    }
+
+    fn starlark_to_file(&self, type_values: &TypeValues, path: String) -> ValueResult {
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let path = pyoxidizer_context.cwd.join(path);
+
+        write_policy_file(&self.inner, &path).map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_BUILD",
+                message: format!("{:#}", e),
+                label: "to_file()".to_string(),
+            })
+        })?;
+
+        Ok(Value::from(NoneType::None))
+    }
+}
+
+starlark_module! { python_packaging_policy_module =>
+    PythonPackagingPolicy.add_exclude_pattern(this, pattern: String) {
+        match this.clone().downcast_mut::<PythonPackagingPolicyValue>()? {
+            Some(mut policy) => policy.starlark_add_exclude_pattern(pattern),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    PythonPackagingPolicy.add_import_trace(env env, this, path: String) {
+        match this.clone().downcast_mut::<PythonPackagingPolicyValue>()? {
+            Some(mut policy) => policy.starlark_add_import_trace(&env, path),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    PythonPackagingPolicy.add_include_pattern(this, pattern: String) {
+        match this.clone().downcast_mut::<PythonPackagingPolicyValue>()? {
+            Some(mut policy) => policy.starlark_add_include_pattern(pattern),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    PythonPackagingPolicy.apply_profile(this, name: String) {
+        match this.clone().downcast_mut::<PythonPackagingPolicyValue>()? {
+            Some(mut policy) => policy.starlark_apply_profile(name),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    PythonPackagingPolicy.exclude_modules(this, names) {
+        match this.clone().downcast_mut::<PythonPackagingPolicyValue>()? {
+            Some(mut policy) => policy.starlark_exclude_modules(&names),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    PythonPackagingPolicy.explain(env env, call_stack cs, this, resources, path=NoneType::None) {
+        match this.clone().downcast_ref::<PythonPackagingPolicyValue>() {
+            Some(policy) => policy.starlark_explain(&env, cs, &resources, &path),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    PythonPackagingPolicy.register_resource_callback(
+        this,
+        func,
+        priority: i64 = 0,
+        context: bool = false
+    ) {
+        match this.clone().downcast_mut::<PythonPackagingPolicyValue>()? {
+            Some(mut policy) => {
+                policy.starlark_register_resource_callback(&func, priority, context)
+            }
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    PythonPackagingPolicy.register_package_hint(
+        this,
+        package: String,
+        hidden_imports=NoneType::None,
+        requires_filesystem: bool = false,
+        include_resources: bool = false
+    ) {
+        match this.clone().downcast_mut::<PythonPackagingPolicyValue>()? {
+            Some(mut policy) => policy.starlark_register_package_hint(
+                package,
+                &hidden_imports,
+                requires_filesystem,
+                include_resources,
+            ),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    PythonPackagingPolicy.register_source_transform(this, func) {
+        match this.clone().downcast_mut::<PythonPackagingPolicyValue>()? {
+            Some(mut policy) => policy.starlark_register_source_transform(&func),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    PythonPackagingPolicy.set_package_optimize_levels(this, package: String, levels) {
+        match this.clone().downcast_mut::<PythonPackagingPolicyValue>()? {
+            Some(mut policy) => policy.starlark_set_package_optimize_levels(package, &levels),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    PythonPackagingPolicy.set_package_override(
+        this,
+        package: String,
+        include=NoneType::None,
+        include_sources=NoneType::None,
+        resources_location=NoneType::None,
+        resources_location_fallback=NoneType::None
+    ) {
+        match this.clone().downcast_mut::<PythonPackagingPolicyValue>()? {
+            Some(mut policy) => policy.starlark_set_package_override(
+                package,
+                &include,
+                &include_sources,
+                &resources_location,
+                &resources_location_fallback,
+            ),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    PythonPackagingPolicy.set_preferred_extension_module_variant(
+        this,
+        name: String,
+        value: String
+    ) {
+        match this.clone().downcast_mut::<PythonPackagingPolicyValue>()? {
+            Some(mut policy) => policy.starlark_set_preferred_extension_module_variant(name, value),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    PythonPackagingPolicy.set_resource_handling_mode(this, mode: String) {
+        match this.clone().downcast_mut::<PythonPackagingPolicyValue>()? {
+            Some(mut policy) => policy.starlark_set_resource_handling_mode(mode),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    PythonPackagingPolicy.to_file(env env, this, path: String) {
+        match this.clone().downcast_ref::<PythonPackagingPolicyValue>() {
+            Some(policy) => policy.starlark_to_file(&env, path),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    PythonPackagingPolicy.unregister_resource_callback(this, func) {
+        match this.clone().downcast_mut::<PythonPackagingPolicyValue>()? {
+            Some(mut policy) => policy.starlark_unregister_resource_callback(&func),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::super::python_distribution::PythonDistributionValue, super::super::testutil::*,
+        super::*, anyhow::Result,
+    };
+
+    #[test]
+    fn test_basic() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+
+        let dist_value = env.eval("dist")?;
+        let dist = dist_value
+            .downcast_ref::<PythonDistributionValue>()
+            .unwrap();
+        let dist_ref = dist.distribution.as_ref().unwrap();
+
+        let policy = dist_ref.create_packaging_policy().unwrap();
+
+        // Need value to go out of scope to avoid double borrow.
+        {
+            let policy_value = env.eval("policy")?;
+            assert_eq!(policy_value.get_type(), "PythonPackagingPolicy");
+
+            let x = policy_value
+                .downcast_ref::<PythonPackagingPolicyValue>()
+                .unwrap();
+
+            // Distribution method should return a policy equivalent to what Starlark gives us.
+            assert_eq!(policy, x.inner);
+        }
 
         // attributes work
         let value = env.eval("policy.extension_module_filter")?;
@@ -481,6 +1373,65 @@ mod tests {
         let value = env.eval("policy.include_test = True; policy.include_test")?;
         assert!(value.to_bool());
 
+        let value = env.eval("policy.build_sandbox")?;
+        assert_eq!(value.to_string(), "off");
+
+        let value = env.eval("policy.build_sandbox = 'required'; policy.build_sandbox")?;
+        assert_eq!(value.to_string(), "required");
+
+        assert!(env.eval("policy.build_sandbox = 'on'").is_err());
+
+        let value = env.eval("policy.build_sandbox_allow_network")?;
+        assert_eq!(value.get_type(), "bool");
+        assert!(!value.to_bool());
+
+        let value = env.eval(
+            "policy.build_sandbox_allow_network = True; policy.build_sandbox_allow_network",
+        )?;
+        assert!(value.to_bool());
+
+        let value = env.eval("policy.prefer_abi3_extensions")?;
+        assert_eq!(value.get_type(), "bool");
+        assert!(!value.to_bool());
+
+        let value =
+            env.eval("policy.prefer_abi3_extensions = True; policy.prefer_abi3_extensions")?;
+        assert!(value.to_bool());
+
+        let value = env.eval("policy.python_security_baseline")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        let value = env.eval(
+            "policy.python_security_baseline = '2021-01-01'; policy.python_security_baseline",
+        )?;
+        assert_eq!(value.to_string(), "2021-01-01");
+
+        assert!(env
+            .eval("policy.python_security_baseline = 'January'")
+            .is_err());
+
+        let value = env.eval("policy.python_security_action")?;
+        assert_eq!(value.to_string(), "warn");
+
+        let value =
+            env.eval("policy.python_security_action = 'error'; policy.python_security_action")?;
+        assert_eq!(value.to_string(), "error");
+
+        assert!(env
+            .eval("policy.python_security_action = 'ignore'")
+            .is_err());
+
+        let value = env.eval("policy.stdlib_profile")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        let value = env.eval("policy.stdlib_profile = 'no-gui'; policy.stdlib_profile")?;
+        assert_eq!(value.to_string(), "no-gui");
+
+        assert!(env.eval("policy.stdlib_profile = 'no-network'").is_err());
+
+        let value = env.eval("policy.stdlib_profile = None; policy.stdlib_profile")?;
+        assert_eq!(value.get_type(), "NoneType");
+
         let value = env.eval("policy.resources_location")?;
         assert_eq!(value.get_type(), "string");
         assert_eq!(value.to_string(), "in-memory");
@@ -490,6 +1441,14 @@ mod tests {
         )?;
         assert_eq!(value.to_string(), "filesystem-relative:lib");
 
+        let value = env.eval(
+            "policy.resources_location = 'filesystem-relative:lib/{package}'; policy.resources_location",
+        )?;
+        assert_eq!(value.to_string(), "filesystem-relative:lib/{package}");
+        assert!(env
+            .eval("policy.resources_location = 'filesystem-relative:lib/{pkg}'")
+            .is_err());
+
         let value = env.eval("policy.resources_location_fallback")?;
         if dist_ref.supports_in_memory_shared_library_loading() {
             assert_eq!(value.get_type(), "string");
@@ -506,6 +1465,22 @@ mod tests {
         )?;
         assert_eq!(value.get_type(), "NoneType");
 
+        let value = env.eval("policy.gettext_catalogs_location")?;
+        assert_eq!(value.get_type(), "NoneType");
+
+        let value = env.eval(
+            "policy.gettext_catalogs_location = 'filesystem-relative:share'; policy.gettext_catalogs_location",
+        )?;
+        assert_eq!(value.to_string(), "filesystem-relative:share");
+
+        assert!(env
+            .eval("policy.gettext_catalogs_location = 'in-memory'")
+            .is_err());
+
+        let value =
+            env.eval("policy.gettext_catalogs_location = None; policy.gettext_catalogs_location")?;
+        assert_eq!(value.get_type(), "NoneType");
+
         let value = env.eval("policy.allow_files")?;
         assert_eq!(value.get_type(), "bool");
         assert!(!value.to_bool());
@@ -514,6 +1489,23 @@ mod tests {
         assert_eq!(value.get_type(), "bool");
         assert!(value.to_bool());
 
+        let value = env.eval("policy.allowed_licenses")?;
+        assert_eq!(value.to_str(), "[]");
+
+        let value = env.eval(
+            "policy.allowed_licenses = ['MIT', 'BSD*']; policy.denied_licenses = ['GPL*']; policy.allowed_licenses + policy.denied_licenses",
+        )?;
+        assert_eq!(value.to_str(), "[\"MIT\", \"BSD*\", \"GPL*\"]");
+        assert!(env.eval("policy.denied_licenses = ['']").is_err());
+        assert!(env.eval("policy.denied_licenses = 'GPL*'").is_err());
+
+        let value = env.eval("policy.strip_docstrings")?;
+        assert_eq!(value.get_type(), "bool");
+        assert!(!value.to_bool());
+
+        let value = env.eval("policy.strip_docstrings = True; policy.strip_docstrings")?;
+        assert!(value.to_bool());
+
         let value = env.eval("policy.allow_in_memory_shared_library_loading")?;
         assert_eq!(value.get_type(), "bool");
         assert!(!value.to_bool());
@@ -522,6 +1514,13 @@ mod tests {
         assert_eq!(value.get_type(), "bool");
         assert!(value.to_bool());
 
+        let value = env.eval("policy.apply_package_hints")?;
+        assert_eq!(value.get_type(), "bool");
+        assert!(value.to_bool());
+
+        let value = env.eval("policy.apply_package_hints = False; policy.apply_package_hints")?;
+        assert!(!value.to_bool());
+
         // bytecode_optimize_level_zero
         let value = env.eval("policy.bytecode_optimize_level_zero")?;
         assert_eq!(value.get_type(), "bool");
@@ -607,13 +1606,367 @@ mod tests {
             .unwrap();
         assert_eq!(policy.derive_context_callbacks.len(), 1);
 
-        let func = policy.derive_context_callbacks[0].clone();
+        let func = policy.derive_context_callbacks[0].1.clone();
         assert_eq!(func.get_type(), "function");
         assert_eq!(func.to_str(), "my_func(policy, resource)");
 
         Ok(())
     }
 
+    #[test]
+    fn test_resource_callback_priority() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+        env.eval("def base(policy, resource):\n    resource.add_include = False")?;
+        env.eval("def first(policy, resource):\n    resource.add_include = False")?;
+        env.eval("def project(policy, resource):\n    resource.add_include = True")?;
+        env.eval("def second(policy, resource):\n    return None")?;
+
+        env.eval("policy.register_resource_callback(project, priority = 10)")?;
+        env.eval("policy.register_resource_callback(base)")?;
+        env.eval("policy.register_resource_callback(first, priority = -1)")?;
+        env.eval("policy.register_resource_callback(second)")?;
+
+        let policy_value = env.eval("policy")?;
+        let policy = policy_value
+            .downcast_ref::<PythonPackagingPolicyValue>()
+            .unwrap();
+        assert_eq!(
+            policy
+                .derive_context_callbacks
+                .iter()
+                .map(|(p, f, _)| (*p, f.to_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (-1, "first(policy, resource)".to_string()),
+                (0, "base(policy, resource)".to_string()),
+                (0, "second(policy, resource)".to_string()),
+                (10, "project(policy, resource)".to_string()),
+            ]
+        );
+        drop(policy);
+
+        env.eval("exe = dist.to_python_executable('myapp', packaging_policy = policy)")?;
+        env.eval_assert("exe.make_python_module_source('foo', '').add_include")?;
+
+        env.eval("policy.unregister_resource_callback(project)")?;
+        assert!(env
+            .eval("policy.unregister_resource_callback(project)")
+            .is_err());
+        env.eval("exe = dist.to_python_executable('myapp', packaging_policy = policy)")?;
+        env.eval_assert("not exe.make_python_module_source('foo', '').add_include")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resource_callback_context() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let root = temp_dir.path();
+        let dist_info = root.join("foo-1.0.dist-info");
+        std::fs::create_dir_all(root.join("foo"))?;
+        std::fs::create_dir_all(&dist_info)?;
+        std::fs::write(root.join("foo").join("__init__.py"), "")?;
+        std::fs::write(
+            dist_info.join("METADATA"),
+            "Metadata-Version: 2.1\nName: foo\nVersion: 1.0\nRequires-Dist: bar\n\n",
+        )?;
+        std::fs::write(
+            dist_info.join("RECORD"),
+            "foo/__init__.py,,\nfoo-1.0.dist-info/METADATA,,\n",
+        )?;
+
+        let mut env = StarlarkEnvironment::new()?;
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+        env.eval(&format!(
+            "def on_filesystem(policy, resource, context):\n    \
+                 if context.distribution_name == 'foo' and context.distribution_version == '1.0' \
+                 and context.requires_dist == ['bar'] and context.origin_path == {:?}:\n        \
+                     resource.add_location = 'filesystem-relative:lib'",
+            format!("{}", dist_info.display())
+        ))?;
+        env.eval("policy.register_resource_callback(on_filesystem, context = True)")?;
+        env.eval("exe = dist.to_python_executable('myapp', packaging_policy = policy)")?;
+
+        env.eval(&format!(
+            "resources = exe.read_package_root({:?}, packages = ['foo'])",
+            format!("{}", root.display())
+        ))?;
+        // The module and the METADATA and RECORD distribution resources.
+        env.eval_assert("len(resources) == 3")?;
+        env.eval_assert("[r.add_location for r in resources] == ['filesystem-relative:lib'] * 3")?;
+
+        // Resources without a known distribution get an empty context.
+        env.eval(
+            "def no_distribution(policy, resource, context):\n    \
+                      resource.add_include = context.distribution_name == None \
+                      and context.requires_dist == []",
+        )?;
+        env.eval("policy.register_resource_callback(no_distribution, context = True)")?;
+        env.eval("exe = dist.to_python_executable('myapp', packaging_policy = policy)")?;
+        env.eval_assert("exe.make_python_module_source('baz', '').add_include")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_register_source_transform() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+        env.eval(
+            "def add_version(name, source):\n    \
+                 if name == 'foo':\n        \
+                     return source.replace('VERSION', '\"1.0\"')",
+        )?;
+        env.eval("def add_comment(name, source):\n    return '# ' + name + '\\n' + source")?;
+        env.eval("policy.register_source_transform(add_version)")?;
+        env.eval("policy.register_source_transform(add_comment)")?;
+        env.eval("exe = dist.to_python_executable('myapp', packaging_policy = policy)")?;
+
+        env.eval_assert(
+            "exe.make_python_module_source('foo', 'version = VERSION').source \
+             == '# foo\\nversion = \"1.0\"'",
+        )?;
+        env.eval_assert("exe.make_python_module_source('bar', 'x').source == '# bar\\nx'")?;
+
+        env.eval("def bad(name, source):\n    return 42")?;
+        env.eval("policy.register_source_transform(bad)")?;
+        env.eval("exe = dist.to_python_executable('myapp', packaging_policy = policy)")?;
+        assert!(env
+            .eval("exe.make_python_module_source('foo', '')")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_profile() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+
+        assert!(env.eval("policy.apply_profile('invalid')").is_err());
+        env.eval("policy.apply_profile('pytorch')")?;
+
+        let value = env.eval("policy.allow_in_memory_shared_library_loading")?;
+        assert!(!value.to_bool());
+
+        let policy_value = env.eval("policy")?;
+        let policy = policy_value
+            .downcast_ref::<PythonPackagingPolicyValue>()
+            .unwrap();
+        assert!(
+            policy
+                .inner
+                .package_hints()
+                .unwrap()
+                .find("torch.nn")
+                .unwrap()
+                .requires_filesystem
+        );
+        assert!(policy
+            .inner
+            .exclusions()
+            .contains(&"torch/include".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_include_exclude_patterns() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+
+        let value = env.eval("policy.exclude_patterns")?;
+        assert_eq!(value.to_str(), "[]");
+
+        env.eval("policy.add_exclude_pattern('numpy.tests.*')")?;
+        env.eval("policy.add_include_pattern('numpy.tests.conftest')")?;
+        assert!(env.eval("policy.add_exclude_pattern('')").is_err());
+
+        let value = env.eval("policy.exclude_patterns")?;
+        assert_eq!(value.to_str(), "[\"numpy.tests.*\"]");
+        let value = env.eval("policy.include_patterns")?;
+        assert_eq!(value.to_str(), "[\"numpy.tests.conftest\"]");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_register_package_hint() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+
+        env.eval("policy.register_package_hint('foo', hidden_imports=['foo.plugins'], requires_filesystem=True)")?;
+        env.eval("policy.register_package_hint('certifi')")?;
+        assert!(env
+            .eval("policy.register_package_hint('bar', hidden_imports='bar.baz')")
+            .is_err());
+
+        let policy_value = env.eval("policy")?;
+        let policy = policy_value
+            .downcast_ref::<PythonPackagingPolicyValue>()
+            .unwrap();
+        let hints = policy.inner.package_hints().unwrap();
+
+        assert_eq!(
+            hints.find("foo.bar"),
+            Some(&PackageHint {
+                package: "foo".to_string(),
+                hidden_imports: vec!["foo.plugins".to_string()],
+                requires_filesystem: true,
+                include_resources: false,
+            })
+        );
+        assert!(!hints.find("certifi").unwrap().requires_filesystem);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_package_optimize_levels() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+
+        env.eval("policy.set_package_optimize_levels('mypkg', [0, 2])")?;
+        assert!(env
+            .eval("policy.set_package_optimize_levels('mypkg', [3])")
+            .is_err());
+        assert!(env
+            .eval("policy.set_package_optimize_levels('mypkg', ['0'])")
+            .is_err());
+
+        let policy_value = env.eval("policy")?;
+        let policy = policy_value
+            .downcast_ref::<PythonPackagingPolicyValue>()
+            .unwrap();
+
+        assert_eq!(
+            policy.inner.find_package_optimize_levels("mypkg.internal"),
+            Some(&[0, 2][..])
+        );
+        assert_eq!(policy.inner.find_package_optimize_levels("requests"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exclude_modules() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+
+        env.eval("policy.exclude_modules(['tkinter', 'idlelib'])")?;
+        assert!(env.eval("policy.exclude_modules(['.foo'])").is_err());
+        assert!(env.eval("policy.exclude_modules('tkinter')").is_err());
+
+        let value = env.eval("policy.excluded_modules")?;
+        assert_eq!(value.to_str(), "[\"tkinter\", \"idlelib\"]");
+
+        let policy_value = env.eval("policy")?;
+        let policy = policy_value
+            .downcast_ref::<PythonPackagingPolicyValue>()
+            .unwrap();
+        assert!(policy.inner.is_stdlib_excluded("tkinter.ttk"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_package_override() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+
+        env.eval("policy.set_package_override('numpy', resources_location='filesystem-relative:lib', include_sources=False)")?;
+        assert!(env
+            .eval("policy.set_package_override('foo', resources_location='bad')")
+            .is_err());
+        assert!(env
+            .eval("policy.set_package_override('foo', include='yes')")
+            .is_err());
+
+        let policy_value = env.eval("policy")?;
+        let policy = policy_value
+            .downcast_ref::<PythonPackagingPolicyValue>()
+            .unwrap();
+
+        assert_eq!(
+            policy.inner.find_package_override("numpy.core"),
+            Some(&PackageOverride {
+                package: "numpy".to_string(),
+                include: None,
+                include_sources: Some(false),
+                resources_location: Some(ConcreteResourceLocation::RelativePath("lib".to_string())),
+                resources_location_fallback: None,
+            })
+        );
+        assert!(policy.inner.find_package_override("foo").is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_import_trace() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let first = temp_dir.path().join("first.log");
+        let second = temp_dir.path().join("second.log");
+        let invalid = temp_dir.path().join("invalid.log");
+        std::fs::write(&first, "import time: 10 | 10 | foo\n")?;
+        std::fs::write(&second, "import time: 20 | 20 | bar\n")?;
+        std::fs::write(&invalid, "no imports\n")?;
+
+        let mut env = StarlarkEnvironment::new()?;
+
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+
+        env.eval(&format!(
+            "policy.add_import_trace({:?})",
+            first.display().to_string()
+        ))?;
+        env.eval(&format!(
+            "policy.add_import_trace({:?})",
+            second.display().to_string()
+        ))?;
+        assert!(env
+            .eval(&format!(
+                "policy.add_import_trace({:?})",
+                invalid.display().to_string()
+            ))
+            .is_err());
+
+        let policy_value = env.eval("policy")?;
+        let policy = policy_value
+            .downcast_ref::<PythonPackagingPolicyValue>()
+            .unwrap();
+        assert_eq!(
+            policy
+                .inner
+                .import_trace()
+                .unwrap()
+                .modules()
+                .collect::<Vec<_>>(),
+            vec!["foo", "bar"]
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_set_resource_handling_mode() -> Result<()> {
         let mut env = StarlarkEnvironment::new()?;
@@ -630,4 +1983,123 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_to_file() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let path = temp_dir.path().join("policy.toml");
+
+        let mut env = StarlarkEnvironment::new()?;
+
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+        env.eval("policy.resources_location = 'filesystem-relative:lib'")?;
+        env.eval("policy.resources_location_fallback = 'in-memory'")?;
+        env.eval("policy.set_preferred_extension_module_variant('foo', 'bar')")?;
+        env.eval(&format!("policy.to_file({:?})", path.display().to_string()))?;
+        env.eval(&format!(
+            "loaded = dist.load_python_packaging_policy({:?})",
+            path.display().to_string()
+        ))?;
+
+        let policy_value = env.eval("policy")?;
+        let policy = policy_value
+            .downcast_ref::<PythonPackagingPolicyValue>()
+            .unwrap();
+        let loaded_value = env.eval("loaded")?;
+        let loaded = loaded_value
+            .downcast_ref::<PythonPackagingPolicyValue>()
+            .unwrap();
+        assert_eq!(
+            loaded.inner.preferred_extension_module_variants(),
+            policy.inner.preferred_extension_module_variants()
+        );
+        assert_eq!(
+            loaded.inner.resources_location(),
+            &ConcreteResourceLocation::RelativePath("lib".to_string())
+        );
+        assert_eq!(
+            loaded.inner.resources_location_fallback(),
+            &Some(ConcreteResourceLocation::InMemory)
+        );
+        assert_eq!(
+            loaded.inner.extension_module_filter(),
+            policy.inner.extension_module_filter()
+        );
+
+        assert!(env
+            .eval(&format!(
+                "dist.load_python_packaging_policy({:?})",
+                temp_dir.path().join("missing.toml").display().to_string()
+            ))
+            .is_err());
+
+        // Settings missing from the file keep the distribution's defaults.
+        let partial_path = temp_dir.path().join("partial.toml");
+        std::fs::write(&partial_path, "include_test = true\n")?;
+        env.eval("base = dist.make_python_packaging_policy()")?;
+        env.eval(&format!(
+            "partial = dist.load_python_packaging_policy({:?})",
+            partial_path.display().to_string()
+        ))?;
+
+        let base_value = env.eval("base")?;
+        let mut wanted = base_value
+            .downcast_ref::<PythonPackagingPolicyValue>()
+            .unwrap()
+            .inner
+            .clone();
+        wanted.set_include_test(true);
+        let partial_value = env.eval("partial")?;
+        assert_eq!(
+            partial_value
+                .downcast_ref::<PythonPackagingPolicyValue>()
+                .unwrap()
+                .inner,
+            wanted
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let path = temp_dir.path().join("report.json");
+
+        let mut env = StarlarkEnvironment::new()?;
+
+        env.eval("dist = default_python_distribution()")?;
+        env.eval("policy = dist.make_python_packaging_policy()")?;
+        env.eval("exe = dist.to_python_executable('myapp', packaging_policy = policy)")?;
+        env.eval("foo = exe.make_python_module_source('foo', '')")?;
+        env.eval("bar = exe.make_python_module_source('bar', '')")?;
+        env.eval("policy.add_exclude_pattern('bar')")?;
+        env.eval(
+            "def keep_source(policy, resource):\n    resource.add_source = not resource.add_source",
+        )?;
+        env.eval("policy.register_resource_callback(keep_source)")?;
+
+        assert!(env.eval("policy.explain(foo)").is_err());
+
+        let report = env.eval(&format!(
+            "policy.explain([foo, bar], path = {:?})",
+            path.display().to_string()
+        ))?;
+        assert_eq!(report.length().unwrap(), 2);
+
+        let data: serde_json::Value = serde_json::from_slice(&std::fs::read(&path)?)?;
+        assert_eq!(data[0]["resource"], "foo");
+        assert_eq!(data[0]["type"], "PythonModuleSource");
+        assert_eq!(data[0]["include"], true);
+        assert_eq!(data[0]["include_rule"], "settings");
+        assert_eq!(
+            data[0]["store_source_rule"],
+            "callback:keep_source(policy, resource)"
+        );
+        assert_eq!(data[1]["include"], false);
+        assert_eq!(data[1]["include_rule"], "exclude_patterns");
+
+        Ok(())
+    }
 }
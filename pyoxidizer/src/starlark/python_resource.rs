@@ -3,15 +3,20 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use {
-    super::python_packaging_policy::PythonPackagingPolicyValue,
+    super::{
+        env::{get_context, PyOxidizerEnvironmentContext},
+        python_packaging_policy::PythonPackagingPolicyValue,
+    },
     python_packaging::{
         location::ConcreteResourceLocation,
+        package_distribution::{PackageDistribution, PackageDistributionIndex},
         resource::{
-            FileData, PythonExtensionModule, PythonModuleSource, PythonPackageDistributionResource,
-            PythonPackageResource, PythonResource,
+            FileData, GettextCatalog, PythonExtensionModule, PythonModuleSource,
+            PythonPackageDistributionResource, PythonPackageResource, PythonResource,
         },
         resource_collection::PythonResourceAddCollectionContext,
     },
+    slog::warn,
     starlark::{
         environment::TypeValues,
         eval::call_stack::CallStack,
@@ -23,7 +28,10 @@ use {
             {Mutable, TypedValue, Value, ValueResult},
         },
     },
-    std::convert::{TryFrom, TryInto},
+    std::{
+        convert::{TryFrom, TryInto},
+        path::Path,
+    },
 };
 
 #[derive(Clone, Debug)]
@@ -54,9 +62,16 @@ impl TryFrom<&str> for OptionalResourceLocation {
                 inner: Some(ConcreteResourceLocation::InMemory),
             })
         } else if s.starts_with("filesystem-relative:") {
-            let prefix = s.split_at("filesystem-relative:".len()).1;
+            let location = ConcreteResourceLocation::try_from(s).map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                    message: e,
+                    label: "invalid resource location".to_string(),
+                })
+            })?;
+
             Ok(OptionalResourceLocation {
-                inner: Some(ConcreteResourceLocation::RelativePath(prefix.to_string())),
+                inner: Some(location),
             })
         } else {
             Err(ValueError::from(RuntimeError {
@@ -423,6 +438,95 @@ impl TypedValue for PythonPackageResourceValue {
     }
 }
 
+/// Starlark `Value` wrapper for `GettextCatalog`.
+#[derive(Debug, Clone)]
+pub struct GettextCatalogValue {
+    pub inner: GettextCatalog,
+    pub add_context: Option<PythonResourceAddCollectionContext>,
+}
+
+impl GettextCatalogValue {
+    pub fn new(catalog: GettextCatalog) -> Self {
+        Self {
+            inner: catalog,
+            add_context: None,
+        }
+    }
+}
+
+impl ResourceCollectionContext for GettextCatalogValue {
+    fn add_collection_context(&self) -> &Option<PythonResourceAddCollectionContext> {
+        &self.add_context
+    }
+
+    fn add_collection_context_mut(&mut self) -> &mut Option<PythonResourceAddCollectionContext> {
+        &mut self.add_context
+    }
+
+    fn as_python_resource(&self) -> PythonResource<'_> {
+        PythonResource::from(&self.inner)
+    }
+}
+
+impl TypedValue for GettextCatalogValue {
+    type Holder = Mutable<GettextCatalogValue>;
+    const TYPE: &'static str = "GettextCatalog";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+
+    fn to_str(&self) -> String {
+        format!(
+            "{}<package={}, locale={}, domain={}>",
+            Self::TYPE,
+            self.inner.leaf_package,
+            self.inner.locale,
+            self.inner.domain
+        )
+    }
+
+    fn to_repr(&self) -> String {
+        self.to_str()
+    }
+
+    fn get_attr(&self, attribute: &str) -> ValueResult {
+        let v = match attribute {
+            "package" => Value::new(self.inner.leaf_package.clone()),
+            "name" => Value::new(self.inner.relative_name.clone()),
+            "locale" => Value::new(self.inner.locale.clone()),
+            "domain" => Value::new(self.inner.domain.clone()),
+            attr => {
+                return if self.add_collection_context_attrs().contains(&attr) {
+                    self.get_attr_add_collection_context(attr)
+                } else {
+                    Err(ValueError::OperationNotSupported {
+                        op: UnsupportedOperation::GetAttr(attr.to_string()),
+                        left: Self::TYPE.to_string(),
+                        right: None,
+                    })
+                };
+            }
+        };
+
+        Ok(v)
+    }
+
+    fn has_attr(&self, attribute: &str) -> Result<bool, ValueError> {
+        Ok(match attribute {
+            "package" => true,
+            "name" => true,
+            "locale" => true,
+            "domain" => true,
+            attr => self.add_collection_context_attrs().contains(&attr),
+        })
+    }
+
+    fn set_attr(&mut self, attribute: &str, value: Value) -> Result<(), ValueError> {
+        self.set_attr_add_collection_context(attribute, value)
+    }
+}
+
 /// Starlark `Value` wrapper for `PythonPackageDistributionResource`.
 #[derive(Debug, Clone)]
 pub struct PythonPackageDistributionResourceValue {
@@ -690,47 +794,69 @@ pub fn is_resource_starlark_compatible(resource: &PythonResource) -> bool {
         PythonResource::EggFile(_) => false,
         PythonResource::PathExtension(_) => false,
         PythonResource::File(_) => true,
+        PythonResource::GettextCatalog(_) => true,
     }
 }
 
+/// Convert a `PythonResource` to a Starlark value with the policy applied.
+///
+/// `distribution` is the package distribution the resource originates from.
 pub fn python_resource_to_value(
     type_values: &TypeValues,
     call_stack: &mut CallStack,
     resource: &PythonResource,
     policy: &PythonPackagingPolicyValue,
+    distribution: Option<&PackageDistribution>,
 ) -> ValueResult {
     match resource {
         PythonResource::ModuleSource(sm) => {
             let mut m = PythonModuleSourceValue::new(sm.clone().into_owned());
-            policy.apply_to_resource(type_values, call_stack, &mut m)?;
+            policy.apply_to_resource(type_values, call_stack, &mut m, distribution)?;
 
             Ok(Value::new(m))
         }
 
         PythonResource::PackageResource(data) => {
             let mut r = PythonPackageResourceValue::new(data.clone().into_owned());
-            policy.apply_to_resource(type_values, call_stack, &mut r)?;
+            policy.apply_to_resource(type_values, call_stack, &mut r, distribution)?;
 
             Ok(Value::new(r))
         }
 
         PythonResource::PackageDistributionResource(resource) => {
             let mut r = PythonPackageDistributionResourceValue::new(resource.clone().into_owned());
-            policy.apply_to_resource(type_values, call_stack, &mut r)?;
+            policy.apply_to_resource(type_values, call_stack, &mut r, distribution)?;
 
             Ok(Value::new(r))
         }
 
         PythonResource::ExtensionModule(em) => {
             let mut em = PythonExtensionModuleValue::new(em.clone().into_owned());
-            policy.apply_to_resource(type_values, call_stack,&mut em)?;
+            policy.apply_to_resource(type_values, call_stack, &mut em, distribution)?;
 
             Ok(Value::new(em))
-        },
+        }
 
         PythonResource::File(f) => {
             let mut value = FileValue::new(f.clone().into_owned());
-            policy.apply_to_resource(type_values, call_stack, &mut value)?;
+            policy.apply_to_resource(type_values, call_stack, &mut value, distribution)?;
+
+            Ok(Value::new(value))
+        }
+
+        // Catalogs are only distinguished if they are installed for gettext.
+        PythonResource::GettextCatalog(catalog)
+            if policy.inner.gettext_catalogs_location().is_none() =>
+        {
+            let mut r = PythonPackageResourceValue::new(catalog.to_package_resource());
+            policy.apply_to_resource(type_values, call_stack, &mut r, distribution)?;
+
+            Ok(Value::new(r))
+        }
+
+        PythonResource::GettextCatalog(catalog) => {
+            let mut value = GettextCatalogValue::new(catalog.clone().into_owned());
+            policy.apply_to_resource(type_values, call_stack, &mut value, distribution)?;
 
             Ok(Value::new(value))
         }
@@ -741,6 +867,90 @@ pub fn python_resource_to_value(
     }
 }
 
+/// Index the package distributions providing resources.
+///
+/// Distribution metadata which can't be read or parsed is only exposed to
+/// resource callbacks, so it isn't fatal. A warning is logged and `None` is
+/// returned instead.
+fn resolve_package_distributions(
+    logger: &slog::Logger,
+    resources: &[PythonResource],
+    root: Option<&Path>,
+    label: &str,
+) -> Option<PackageDistributionIndex> {
+    let index = PackageDistributionIndex::from_resources(resources).and_then(|mut index| {
+        if let Some(root) = root {
+            index.resolve_origins(root)?;
+        }
+
+        Ok(index)
+    });
+
+    match index {
+        Ok(index) => Some(index),
+        Err(e) => {
+            warn!(
+                logger,
+                "{}: unable to resolve package distributions; resource callbacks won't see them: {:#}",
+                label,
+                e
+            );
+            None
+        }
+    }
+}
+
+/// Convert the Starlark compatible resources of a collection to Starlark values.
+///
+/// Resources are attributed to the package distributions in the collection
+/// providing them. `root` is the directory the resources were read from, if
+/// it outlives the call. Distribution metadata directories are found in it.
+///
+/// Distributions are only resolved if a resource callback wanting a context
+/// is registered on `policy`, as nothing else consumes them.
+pub fn python_resources_to_values(
+    type_values: &TypeValues,
+    call_stack: &mut CallStack,
+    resources: &[PythonResource],
+    policy: &PythonPackagingPolicyValue,
+    root: Option<&Path>,
+    label: &str,
+) -> Result<Vec<Value>, ValueError> {
+    let distributions = if policy.has_context_callbacks() {
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        resolve_package_distributions(pyoxidizer_context.logger(), resources, root, label)
+    } else {
+        None
+    };
+
+    resources
+        .iter()
+        .filter(|r| is_resource_starlark_compatible(r))
+        .map(|r| {
+            let distribution = distributions.as_ref().and_then(|index| index.find(r));
+
+            if let PythonResource::ModuleSource(module) = r {
+                let mut module = module.clone().into_owned();
+                policy.transform_module_source(type_values, call_stack, &mut module)?;
+
+                python_resource_to_value(
+                    type_values,
+                    call_stack,
+                    &module.into(),
+                    policy,
+                    distribution,
+                )
+            } else {
+                python_resource_to_value(type_values, call_stack, r, policy, distribution)
+            }
+        })
+        .collect()
+}
+
 /// Attempt to resolve the `PythonResourceAddCollectionContext` for a Value.
 pub fn add_context_for_value(
     value: &Value,
@@ -772,6 +982,11 @@ pub fn add_context_for_value(
             .unwrap()
             .add_collection_context()
             .clone()),
+        "GettextCatalog" => Ok(value
+            .downcast_ref::<GettextCatalogValue>()
+            .unwrap()
+            .add_collection_context()
+            .clone()),
         t => Err(ValueError::from(RuntimeError {
             code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
             message: format!("unable to obtain add collection context from {}", t),
@@ -786,6 +1001,50 @@ mod tests {
     use super::super::testutil::*;
     use super::*;
     use anyhow::Result;
+    use python_packaging::{
+        location::ConcreteResourceLocation,
+        policy::PythonPackagingPolicy,
+        resource::{DataLocation, PythonPackageDistributionResourceFlavor},
+    };
+
+    #[test]
+    fn test_resolve_package_distributions() -> Result<()> {
+        let logger = crate::testutil::get_logger()?;
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+
+        let metadata = |data: DataLocation| {
+            PythonResource::from(PythonPackageDistributionResource {
+                location: PythonPackageDistributionResourceFlavor::DistInfo,
+                package: "foo".to_string(),
+                version: "1.0".to_string(),
+                name: "METADATA".to_string(),
+                data,
+            })
+        };
+
+        let index = resolve_package_distributions(
+            &logger,
+            &[metadata(DataLocation::Memory(
+                b"Name: foo\nVersion: 1.0\n\n".to_vec(),
+            ))],
+            None,
+            "test",
+        );
+        assert!(index.is_some());
+
+        // Unreadable metadata degrades to no distributions.
+        let index = resolve_package_distributions(
+            &logger,
+            &[metadata(DataLocation::Path(
+                temp_dir.path().join("missing").join("METADATA"),
+            ))],
+            None,
+            "test",
+        );
+        assert!(index.is_none());
+
+        Ok(())
+    }
 
     #[test]
     fn test_source_module_attrs() -> Result<()> {
@@ -938,4 +1197,39 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_gettext_catalog_to_value() -> Result<()> {
+        let env = StarlarkEnvironment::new()?;
+        let mut call_stack = CallStack::default();
+
+        let resource = PythonResource::from(GettextCatalog {
+            leaf_package: "foo".to_string(),
+            relative_name: "locale/de/LC_MESSAGES/foo.mo".to_string(),
+            locale: "de".to_string(),
+            domain: "foo".to_string(),
+            data: DataLocation::Memory(vec![]),
+        });
+
+        // Catalogs are ordinary package resources unless they have a location.
+        let mut policy = PythonPackagingPolicyValue::new(PythonPackagingPolicy::default());
+        let value =
+            python_resource_to_value(&env.type_values, &mut call_stack, &resource, &policy, None)
+                .unwrap();
+        assert_eq!(value.get_type(), "PythonPackageResource");
+        assert_eq!(
+            value.get_attr("name").unwrap().to_string(),
+            "locale/de/LC_MESSAGES/foo.mo"
+        );
+
+        policy.inner.set_gettext_catalogs_location(Some(
+            ConcreteResourceLocation::RelativePath("share".to_string()),
+        ))?;
+        let value =
+            python_resource_to_value(&env.type_values, &mut call_stack, &resource, &policy, None)
+                .unwrap();
+        assert_eq!(value.get_type(), "GettextCatalog");
+
+        Ok(())
+    }
 }
@@ -0,0 +1,251 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    crate::py_packaging::binary::PythonBinaryBuilder,
+    anyhow::{anyhow, Context, Result},
+    slog::warn,
+    starlark::values::{Mutable, TypedValue, Value},
+    starlark_dialect_build_targets::{BuildContext, BuildTarget, ResolvedTarget, RunMode},
+    std::{
+        collections::BTreeSet,
+        ops::Deref,
+        path::{Path, PathBuf},
+        sync::Arc,
+    },
+};
+
+/// Filename of the inventory written to a target's output directory.
+pub const SNAPSHOT_OUTPUT_FILENAME: &str = "resources-snapshot.txt";
+
+/// Obtain a normalized inventory of resources collected by a binary builder.
+///
+/// Each entry describes a resource and where it will be loaded from.
+/// Entries are sorted so the inventory is deterministic.
+pub fn resources_inventory(exe: &dyn PythonBinaryBuilder) -> Vec<String> {
+    exe.iter_resources()
+        .map(|(_, resource)| resource.describe())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>()
+}
+
+/// Parse a previously written inventory.
+///
+/// Empty lines and lines beginning with `#` are ignored.
+pub fn parse_inventory(data: &str) -> BTreeSet<String> {
+    data.lines()
+        .map(|line| line.trim_end())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.to_string())
+        .collect::<BTreeSet<_>>()
+}
+
+/// Compare an expected inventory against an actual one.
+///
+/// Returns entries that were added and entries that were removed.
+pub fn compare_inventories(
+    expected: &BTreeSet<String>,
+    actual: &BTreeSet<String>,
+) -> (Vec<String>, Vec<String>) {
+    (
+        actual.difference(expected).cloned().collect::<Vec<_>>(),
+        expected.difference(actual).cloned().collect::<Vec<_>>(),
+    )
+}
+
+/// Compares the resources collected by a binary against a checked-in snapshot.
+pub struct PythonResourcesSnapshot {
+    pub exe: Arc<dyn PythonBinaryBuilder>,
+
+    /// Path to the snapshot file to compare against.
+    pub snapshot_path: PathBuf,
+
+    /// Whether to write the snapshot file instead of failing when it is missing or differs.
+    pub update: bool,
+}
+
+impl TypedValue for PythonResourcesSnapshot {
+    type Holder = Mutable<PythonResourcesSnapshot>;
+    const TYPE: &'static str = "PythonResourcesSnapshot";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+}
+
+impl PythonResourcesSnapshot {
+    /// Write the current inventory and compare it against the snapshot.
+    fn write_and_compare(&self, logger: &slog::Logger, output_path: &Path) -> Result<()> {
+        let inventory = resources_inventory(self.exe.deref());
+
+        let mut data = inventory.join("\n");
+        data.push('\n');
+
+        let dest_path = output_path.join(SNAPSHOT_OUTPUT_FILENAME);
+        warn!(
+            logger,
+            "writing resources inventory to {}",
+            dest_path.display()
+        );
+        std::fs::write(&dest_path, &data)
+            .with_context(|| format!("writing {}", dest_path.display()))?;
+
+        if !self.snapshot_path.exists() {
+            if !self.update {
+                return Err(anyhow!(
+                    "resources snapshot {} does not exist; build with --update-snapshots to create it",
+                    self.snapshot_path.display()
+                ));
+            }
+
+            warn!(
+                logger,
+                "resources snapshot {} does not exist; creating it",
+                self.snapshot_path.display()
+            );
+            return self.write_snapshot(&data);
+        }
+
+        let expected = parse_inventory(
+            &std::fs::read_to_string(&self.snapshot_path)
+                .with_context(|| format!("reading {}", self.snapshot_path.display()))?,
+        );
+        let actual = inventory.into_iter().collect::<BTreeSet<_>>();
+
+        let (added, removed) = compare_inventories(&expected, &actual);
+
+        if added.is_empty() && removed.is_empty() {
+            warn!(
+                logger,
+                "resources match snapshot {}",
+                self.snapshot_path.display()
+            );
+            return Ok(());
+        }
+
+        for entry in &added {
+            warn!(logger, "+ {}", entry);
+        }
+        for entry in &removed {
+            warn!(logger, "- {}", entry);
+        }
+
+        if self.update {
+            warn!(
+                logger,
+                "updating resources snapshot {}",
+                self.snapshot_path.display()
+            );
+            return self.write_snapshot(&data);
+        }
+
+        Err(anyhow!(
+            "resources differ from snapshot {} ({} added, {} removed); if this is expected, build with --update-snapshots",
+            self.snapshot_path.display(),
+            added.len(),
+            removed.len(),
+        ))
+    }
+
+    /// Replace the snapshot file with an inventory.
+    fn write_snapshot(&self, data: &str) -> Result<()> {
+        if let Some(parent) = self.snapshot_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.snapshot_path, data)
+            .with_context(|| format!("writing {}", self.snapshot_path.display()))
+    }
+}
+
+impl BuildTarget for PythonResourcesSnapshot {
+    fn build(&mut self, context: &dyn BuildContext) -> Result<ResolvedTarget> {
+        let output_path = context.get_state_path("output_path")?;
+
+        self.write_and_compare(context.logger(), output_path)?;
+
+        Ok(ResolvedTarget {
+            run_mode: RunMode::None,
+            output_path: output_path.to_path_buf(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            py_packaging::standalone_builder::tests::StandalonePythonExecutableBuilderOptions,
+            testutil::get_logger,
+        },
+    };
+
+    #[test]
+    fn test_update_snapshot() -> Result<()> {
+        let logger = get_logger()?;
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let exe = StandalonePythonExecutableBuilderOptions::default().new_builder()?;
+
+        let mut snapshot = PythonResourcesSnapshot {
+            exe: exe.clone_trait(),
+            snapshot_path: temp_dir.path().join("snapshot.txt"),
+            update: false,
+        };
+
+        // A missing snapshot is only created when updating.
+        assert!(snapshot
+            .write_and_compare(&logger, temp_dir.path())
+            .is_err());
+        assert!(!snapshot.snapshot_path.exists());
+
+        snapshot.update = true;
+        snapshot.write_and_compare(&logger, temp_dir.path())?;
+        assert_eq!(
+            std::fs::read(&snapshot.snapshot_path)?,
+            std::fs::read(temp_dir.path().join(SNAPSHOT_OUTPUT_FILENAME))?
+        );
+
+        // A differing snapshot fails unless updating.
+        std::fs::write(&snapshot.snapshot_path, "module foo\n")?;
+        snapshot.update = false;
+        assert!(snapshot
+            .write_and_compare(&logger, temp_dir.path())
+            .is_err());
+        snapshot.update = true;
+        snapshot.write_and_compare(&logger, temp_dir.path())?;
+        snapshot.update = false;
+        snapshot.write_and_compare(&logger, temp_dir.path())?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_inventory() {
+        let inventory =
+            parse_inventory("# comment\nmodule foo\n\nmodule bar (in-memory source)  \n");
+
+        assert_eq!(
+            inventory.into_iter().collect::<Vec<_>>(),
+            vec![
+                "module bar (in-memory source)".to_string(),
+                "module foo".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compare_inventories() {
+        let expected = parse_inventory("module bar\nmodule foo\n");
+        let actual = parse_inventory("module baz\nmodule foo\n");
+
+        let (added, removed) = compare_inventories(&expected, &actual);
+        assert_eq!(added, vec!["module baz".to_string()]);
+        assert_eq!(removed, vec!["module bar".to_string()]);
+
+        let (added, removed) = compare_inventories(&expected, &expected);
+        assert!(added.is_empty());
+        assert!(removed.is_empty());
+    }
+}
@@ -0,0 +1,52 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    crate::py_packaging::{
+        binary::PythonBinaryBuilder,
+        wasi::{write_wasi_bundle, WasiPythonInterpreter},
+    },
+    anyhow::Result,
+    starlark::values::{Mutable, TypedValue, Value},
+    starlark_dialect_build_targets::{BuildContext, BuildTarget, ResolvedTarget, RunMode},
+    std::{ops::Deref, path::PathBuf, sync::Arc},
+};
+
+/// Runs the resources collected by a binary with a WASI Python interpreter.
+pub struct PythonWasiBundle {
+    pub exe: Arc<dyn PythonBinaryBuilder>,
+
+    /// Path to the directory containing the WASI Python interpreter.
+    pub interpreter_path: PathBuf,
+}
+
+impl TypedValue for PythonWasiBundle {
+    type Holder = Mutable<PythonWasiBundle>;
+    const TYPE: &'static str = "PythonWasiBundle";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+}
+
+impl BuildTarget for PythonWasiBundle {
+    fn build(&mut self, context: &dyn BuildContext) -> Result<ResolvedTarget> {
+        let output_path = context.get_state_path("output_path")?;
+
+        let interpreter = WasiPythonInterpreter::from_directory(&self.interpreter_path)?;
+        let launcher_path = write_wasi_bundle(
+            context.logger(),
+            self.exe.deref(),
+            &interpreter,
+            output_path,
+        )?;
+
+        Ok(ResolvedTarget {
+            run_mode: RunMode::Path {
+                path: launcher_path,
+            },
+            output_path: output_path.to_path_buf(),
+        })
+    }
+}
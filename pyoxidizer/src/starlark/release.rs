@@ -0,0 +1,494 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    super::env::{get_context, PyOxidizerEnvironmentContext},
+    anyhow::{anyhow, Result},
+    slog::warn,
+    starlark::{
+        environment::TypeValues,
+        values::{
+            error::{RuntimeError, UnsupportedOperation, ValueError},
+            none::NoneType,
+            {Mutable, TypedValue, Value, ValueResult},
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+    starlark_dialect_build_targets::{
+        optional_list_arg, optional_str_arg, optional_type_arg, BuildContext, BuildTarget,
+        ResolvedTarget, RunMode,
+    },
+    std::{
+        collections::BTreeMap,
+        path::{Path, PathBuf},
+    },
+    tugger::{
+        glob::evaluate_glob,
+        release::{verify_signature, UploadDestination},
+        secrets::Secret,
+        starlark::secrets::SecretValue,
+    },
+};
+
+/// Whether a file is a detached signature of another artifact.
+fn is_detached_signature(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("asc") | Some("sig")
+    )
+}
+
+/// Resolve the token of an upload destination.
+///
+/// A `Secret` passed as `token` takes precedence over `token_env`.
+fn upload_token(token_env: Option<String>, token: &Value) -> Result<Option<Secret>, ValueError> {
+    optional_type_arg("token", "Secret", token)?;
+
+    Ok(match token.downcast_ref::<SecretValue>() {
+        Some(secret) => Some(secret.inner.clone()),
+        None => token_env.map(Secret::Env),
+    })
+}
+
+/// Artifacts of other targets uploaded to destinations.
+#[derive(Clone, Debug)]
+pub struct Release {
+    /// Targets whose artifacts are released and patterns selecting them.
+    pub targets: Vec<(String, Vec<String>)>,
+
+    /// Additional artifacts to release.
+    pub artifacts: Vec<PathBuf>,
+
+    /// Where artifacts are uploaded to.
+    pub destinations: Vec<UploadDestination>,
+
+    /// Whether to verify signatures of artifacts before uploading.
+    pub verify_signatures: bool,
+
+    /// Whether to skip uploading.
+    pub dry_run: bool,
+}
+
+impl Release {
+    /// Resolve the artifacts to release.
+    ///
+    /// `output_path` is the output directory of the release target. Output
+    /// directories of other targets are its siblings.
+    pub fn resolve_artifacts(&self, output_path: &Path) -> Result<Vec<PathBuf>> {
+        let targets_path = output_path
+            .parent()
+            .ok_or_else(|| anyhow!("unable to resolve target output directories"))?;
+
+        let mut artifacts = BTreeMap::new();
+
+        let mut add = |path: PathBuf| -> Result<()> {
+            let filename = path
+                .file_name()
+                .ok_or_else(|| anyhow!("invalid artifact path: {}", path.display()))?
+                .to_os_string();
+
+            if let Some(existing) = artifacts.insert(filename, path.clone()) {
+                if existing != path {
+                    return Err(anyhow!(
+                        "artifacts {} and {} have the same filename",
+                        existing.display(),
+                        path.display()
+                    ));
+                }
+            }
+
+            Ok(())
+        };
+
+        for (target, patterns) in &self.targets {
+            let target_path = targets_path.join(target);
+
+            for pattern in patterns {
+                let paths = evaluate_glob(&target_path, pattern)?;
+                if paths.is_empty() {
+                    return Err(anyhow!(
+                        "no artifacts of target {} match {}",
+                        target,
+                        pattern
+                    ));
+                }
+
+                for path in paths {
+                    add(path)?;
+                }
+            }
+        }
+
+        for path in &self.artifacts {
+            if !path.is_file() {
+                return Err(anyhow!("artifact {} does not exist", path.display()));
+            }
+
+            add(path.clone())?;
+        }
+
+        Ok(artifacts.into_iter().map(|(_, path)| path).collect())
+    }
+}
+
+impl BuildTarget for Release {
+    fn build(&mut self, context: &dyn BuildContext) -> Result<ResolvedTarget> {
+        let output_path = context.get_state_path("output_path")?;
+        let logger = context.logger();
+
+        if self.destinations.is_empty() {
+            return Err(anyhow!("release has no upload destinations"));
+        }
+
+        let artifacts = self.resolve_artifacts(output_path)?;
+        if artifacts.is_empty() {
+            return Err(anyhow!("release has no artifacts"));
+        }
+
+        if self.verify_signatures {
+            for path in artifacts.iter().filter(|p| !is_detached_signature(p)) {
+                verify_signature(logger, path)?;
+            }
+        }
+
+        for destination in &self.destinations {
+            if self.dry_run {
+                for path in &artifacts {
+                    let filename = path.file_name().unwrap().to_string_lossy();
+                    warn!(
+                        logger,
+                        "dry run: would upload {} to {}",
+                        path.display(),
+                        destination.describe(&filename)
+                    );
+                }
+            } else {
+                destination.upload(logger, &artifacts)?;
+            }
+        }
+
+        Ok(ResolvedTarget {
+            run_mode: RunMode::None,
+            output_path: output_path.to_path_buf(),
+        })
+    }
+}
+
+impl TypedValue for Release {
+    type Holder = Mutable<Release>;
+    const TYPE: &'static str = "Release";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+
+    fn get_attr(&self, attribute: &str) -> ValueResult {
+        match attribute {
+            "dry_run" => Ok(Value::from(self.dry_run)),
+            "verify_signatures" => Ok(Value::from(self.verify_signatures)),
+            _ => Err(ValueError::OperationNotSupported {
+                op: UnsupportedOperation::GetAttr(attribute.to_string()),
+                left: Self::TYPE.to_string(),
+                right: None,
+            }),
+        }
+    }
+
+    fn has_attr(&self, attribute: &str) -> Result<bool, ValueError> {
+        Ok(matches!(attribute, "dry_run" | "verify_signatures"))
+    }
+
+    fn set_attr(&mut self, attribute: &str, value: Value) -> Result<(), ValueError> {
+        match attribute {
+            "dry_run" => {
+                self.dry_run = value.to_bool();
+            }
+            "verify_signatures" => {
+                self.verify_signatures = value.to_bool();
+            }
+            _ => {
+                return Err(ValueError::OperationNotSupported {
+                    op: UnsupportedOperation::SetAttr(attribute.to_string()),
+                    left: Self::TYPE.to_string(),
+                    right: None,
+                })
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Starlark functions.
+impl Release {
+    /// Release()
+    fn new_from_args() -> ValueResult {
+        Ok(Value::new(Release {
+            targets: vec![],
+            artifacts: vec![],
+            destinations: vec![],
+            verify_signatures: true,
+            dry_run: false,
+        }))
+    }
+
+    /// Release.add_target(target, include=None)
+    pub fn starlark_add_target(&mut self, target: String, include: &Value) -> ValueResult {
+        optional_list_arg("include", "string", include)?;
+
+        let patterns = match include.get_type() {
+            "list" => include.iter()?.iter().map(|x| x.to_string()).collect(),
+            _ => vec!["*".to_string()],
+        };
+
+        self.targets.push((target, patterns));
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// Release.add_artifact(path)
+    pub fn starlark_add_artifact(&mut self, type_values: &TypeValues, path: String) -> ValueResult {
+        let pyoxidizer_context_value = get_context(type_values)?;
+        let pyoxidizer_context = pyoxidizer_context_value
+            .downcast_ref::<PyOxidizerEnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let path = PathBuf::from(path);
+        let path = if path.is_absolute() {
+            path
+        } else {
+            pyoxidizer_context.cwd.join(path)
+        };
+
+        self.artifacts.push(path);
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// Release.add_github_release(repository, tag, token_env="GITHUB_TOKEN", token=None)
+    pub fn starlark_add_github_release(
+        &mut self,
+        repository: String,
+        tag: String,
+        token_env: String,
+        token: &Value,
+    ) -> ValueResult {
+        let token = upload_token(Some(token_env), token)?.unwrap();
+
+        if repository.split('/').filter(|s| !s.is_empty()).count() != 2 {
+            return Err(ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_BUILD",
+                message: format!(
+                    "invalid repository: {}; use the form <owner>/<name>",
+                    repository
+                ),
+                label: "add_github_release()".to_string(),
+            }));
+        }
+
+        self.destinations.push(UploadDestination::GitHubRelease {
+            repository,
+            tag,
+            token,
+        });
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// Release.add_s3_bucket(bucket, prefix="")
+    pub fn starlark_add_s3_bucket(&mut self, bucket: String, prefix: String) -> ValueResult {
+        self.destinations
+            .push(UploadDestination::S3 { bucket, prefix });
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// Release.add_http_put(url, token_env=None, token=None)
+    pub fn starlark_add_http_put(
+        &mut self,
+        url: String,
+        token_env: &Value,
+        token: &Value,
+    ) -> ValueResult {
+        let token = upload_token(optional_str_arg("token_env", token_env)?, token)?;
+
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            return Err(ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_BUILD",
+                message: format!("invalid URL: {}", url),
+                label: "add_http_put()".to_string(),
+            }));
+        }
+
+        // Don't send credentials in the clear.
+        if url.starts_with("http://") && token.is_some() {
+            return Err(ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_BUILD",
+                message: format!("refusing to send a token to a non-HTTPS URL: {}", url),
+                label: "add_http_put()".to_string(),
+            }));
+        }
+
+        self.destinations
+            .push(UploadDestination::HttpPut { url, token });
+
+        Ok(Value::new(NoneType::None))
+    }
+}
+
+starlark_module! { release_module =>
+    #[allow(non_snake_case)]
+    Release(env _env) {
+        Release::new_from_args()
+    }
+
+    Release.add_target(this, target: String, include = NoneType::None) {
+        match this.clone().downcast_mut::<Release>()? {
+            Some(mut release) => release.starlark_add_target(target, &include),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    Release.add_artifact(env env, this, path: String) {
+        match this.clone().downcast_mut::<Release>()? {
+            Some(mut release) => release.starlark_add_artifact(&env, path),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    Release.add_github_release(
+        this,
+        repository: String,
+        tag: String,
+        token_env: String = "GITHUB_TOKEN".to_string(),
+        token = NoneType::None
+    ) {
+        match this.clone().downcast_mut::<Release>()? {
+            Some(mut release) => {
+                release.starlark_add_github_release(repository, tag, token_env, &token)
+            }
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    Release.add_s3_bucket(this, bucket: String, prefix: String = "".to_string()) {
+        match this.clone().downcast_mut::<Release>()? {
+            Some(mut release) => release.starlark_add_s3_bucket(bucket, prefix),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    Release.add_http_put(
+        this,
+        url: String,
+        token_env = NoneType::None,
+        token = NoneType::None
+    ) {
+        match this.clone().downcast_mut::<Release>()? {
+            Some(mut release) => release.starlark_add_http_put(url, &token_env, &token),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::super::testutil::*, super::*};
+
+    #[test]
+    fn test_release() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+        env.eval("r = Release()")?;
+        env.eval("r.add_target('msi', include = ['*.msi'])")?;
+        env.eval("r.add_github_release('example/myapp', 'v1.0')")?;
+        env.eval("r.add_s3_bucket('releases', prefix = 'myapp')")?;
+        env.eval("r.add_http_put('https://example.com/uploads', token_env = 'UPLOAD_TOKEN')")?;
+        env.eval("r.verify_signatures = False")?;
+        env.eval("r.dry_run = True")?;
+
+        {
+            let r = env.eval("r")?;
+            let r = r.downcast_ref::<Release>().unwrap();
+            assert_eq!(
+                r.targets,
+                vec![("msi".to_string(), vec!["*.msi".to_string()])]
+            );
+            assert_eq!(r.destinations.len(), 3);
+            assert_eq!(
+                r.destinations[2],
+                UploadDestination::HttpPut {
+                    url: "https://example.com/uploads".to_string(),
+                    token: Some(Secret::Env("UPLOAD_TOKEN".to_string())),
+                }
+            );
+            assert!(!r.verify_signatures);
+            assert!(r.dry_run);
+        }
+
+        assert!(env.eval("r.add_github_release('myapp', 'v1.0')").is_err());
+        assert!(env.eval("r.add_http_put('example.com')").is_err());
+        assert!(env
+            .eval("r.add_http_put('http://example.com', token_env = 'UPLOAD_TOKEN')")
+            .is_err());
+        assert!(env
+            .eval("r.add_http_put('https://example.com', token = 'hunter2')")
+            .is_err());
+
+        env.eval(
+            "r.add_github_release('example/myapp', 'v1.0', token = secret_from_file('token.txt'))",
+        )?;
+        let r = env.eval("r")?;
+        let r = r.downcast_ref::<Release>().unwrap();
+        assert_eq!(
+            r.destinations[3],
+            UploadDestination::GitHubRelease {
+                repository: "example/myapp".to_string(),
+                tag: "v1.0".to_string(),
+                token: Secret::File(std::env::current_dir()?.join("token.txt")),
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_artifacts() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let msi_path = temp_dir.path().join("msi");
+        std::fs::create_dir_all(&msi_path)?;
+        std::fs::write(msi_path.join("myapp.msi"), b"")?;
+        std::fs::write(msi_path.join("myapp.wixobj"), b"")?;
+
+        let mut release = Release {
+            targets: vec![("msi".to_string(), vec!["*.msi".to_string()])],
+            artifacts: vec![],
+            destinations: vec![],
+            verify_signatures: true,
+            dry_run: false,
+        };
+
+        let output_path = temp_dir.path().join("release");
+        assert_eq!(
+            release.resolve_artifacts(&output_path)?,
+            vec![msi_path.join("myapp.msi")]
+        );
+
+        release.artifacts.push(temp_dir.path().join("myapp.msi"));
+        assert!(release.resolve_artifacts(&output_path).is_err());
+
+        release.artifacts = vec![];
+        release
+            .targets
+            .push(("pkg".to_string(), vec!["*.pkg".to_string()]));
+        assert!(release.resolve_artifacts(&output_path).is_err());
+
+        assert!(is_detached_signature(Path::new("myapp.tar.gz.asc")));
+        assert!(!is_detached_signature(Path::new("myapp.msi")));
+
+        Ok(())
+    }
+}
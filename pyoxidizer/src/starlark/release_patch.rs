@@ -0,0 +1,47 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    crate::release_patch::write_release_patch,
+    anyhow::Result,
+    starlark::values::{Mutable, TypedValue, Value},
+    starlark_dialect_build_targets::{BuildContext, BuildTarget, ResolvedTarget, RunMode},
+    std::path::PathBuf,
+    tugger::file_resource::FileManifest,
+};
+
+/// Binary patches transforming a previous release into a FileManifest.
+pub struct ReleasePatch {
+    pub manifest: FileManifest,
+
+    /// Path to the directory containing the previous release.
+    pub previous_path: PathBuf,
+}
+
+impl TypedValue for ReleasePatch {
+    type Holder = Mutable<ReleasePatch>;
+    const TYPE: &'static str = "ReleasePatch";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+}
+
+impl BuildTarget for ReleasePatch {
+    fn build(&mut self, context: &dyn BuildContext) -> Result<ResolvedTarget> {
+        let output_path = context.get_state_path("output_path")?;
+
+        write_release_patch(
+            context.logger(),
+            &self.previous_path,
+            &self.manifest,
+            output_path,
+        )?;
+
+        Ok(ResolvedTarget {
+            run_mode: RunMode::None,
+            output_path: output_path.to_path_buf(),
+        })
+    }
+}
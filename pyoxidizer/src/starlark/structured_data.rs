@@ -0,0 +1,209 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Reading JSON, TOML and YAML files from Starlark.
+
+This allows configuration files to derive values like the application
+version from existing project metadata, such as `pyproject.toml`.
+*/
+
+use {
+    super::env::{get_context, PyOxidizerEnvironmentContext},
+    anyhow::{anyhow, Context, Result},
+    starlark::{
+        environment::TypeValues,
+        values::{
+            dict::Dictionary,
+            error::{RuntimeError, ValueError},
+            none::NoneType,
+            Value, ValueResult,
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+    std::path::PathBuf,
+};
+
+/// Convert a JSON value to a Starlark value.
+///
+/// Starlark has no floating point type and its integers are 64-bit signed,
+/// so floats and larger integers become strings.
+pub(crate) fn json_to_value(value: &serde_json::Value) -> Result<Value, ValueError> {
+    Ok(match value {
+        serde_json::Value::Null => Value::from(NoneType::None),
+        serde_json::Value::Bool(v) => Value::from(*v),
+        serde_json::Value::Number(v) => match v.as_i64() {
+            Some(v) => Value::from(v),
+            None => Value::from(v.to_string()),
+        },
+        serde_json::Value::String(v) => Value::from(v.as_str()),
+        serde_json::Value::Array(v) => Value::from(
+            v.iter()
+                .map(json_to_value)
+                .collect::<Result<Vec<_>, ValueError>>()?,
+        ),
+        serde_json::Value::Object(v) => {
+            let mut dict = Dictionary::default();
+            for (key, value) in v {
+                dict.insert(Value::from(key.as_str()), json_to_value(value)?)?;
+            }
+            Value::new(dict)
+        }
+    })
+}
+
+/// Convert a TOML value to a Starlark value.
+///
+/// Floats and datetimes become strings.
+fn toml_to_value(value: &toml::Value) -> Result<Value, ValueError> {
+    Ok(match value {
+        toml::Value::String(v) => Value::from(v.as_str()),
+        toml::Value::Integer(v) => Value::from(*v),
+        toml::Value::Float(v) => Value::from(v.to_string()),
+        toml::Value::Boolean(v) => Value::from(*v),
+        toml::Value::Datetime(v) => Value::from(v.to_string()),
+        toml::Value::Array(v) => Value::from(
+            v.iter()
+                .map(toml_to_value)
+                .collect::<Result<Vec<_>, ValueError>>()?,
+        ),
+        toml::Value::Table(v) => {
+            let mut dict = Dictionary::default();
+            for (key, value) in v {
+                dict.insert(Value::from(key.as_str()), toml_to_value(value)?)?;
+            }
+            Value::new(dict)
+        }
+    })
+}
+
+/// A format of structured data files.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DataFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+/// Parse structured data into a Starlark value.
+fn parse_data(format: DataFormat, data: &str) -> Result<Result<Value, ValueError>> {
+    Ok(match format {
+        DataFormat::Json => json_to_value(&serde_json::from_str(data)?),
+        DataFormat::Toml => toml_to_value(&toml::from_str(data)?),
+        DataFormat::Yaml => {
+            // YAML allows non-string keys, which serde_json rejects.
+            let value: serde_json::Value = serde_yaml::from_str(data)
+                .map_err(|e| anyhow!("{}; only string keys are supported", e))?;
+            json_to_value(&value)
+        }
+    })
+}
+
+/// parse_json(path), parse_toml(path) and parse_yaml(path)
+fn starlark_parse_file(
+    type_values: &TypeValues,
+    format: DataFormat,
+    label: &str,
+    path: String,
+) -> ValueResult {
+    let pyoxidizer_context_value = get_context(type_values)?;
+    let pyoxidizer_context = pyoxidizer_context_value
+        .downcast_ref::<PyOxidizerEnvironmentContext>()
+        .ok_or(ValueError::IncorrectParameterType)?;
+
+    let path = pyoxidizer_context.cwd.join(PathBuf::from(path));
+
+    std::fs::read_to_string(&path)
+        .with_context(|| format!("reading {}", path.display()))
+        .and_then(|data| {
+            parse_data(format, &data).with_context(|| format!("parsing {}", path.display()))
+        })
+        .map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_BUILD",
+                message: format!("{:#}", e),
+                label: label.to_string(),
+            })
+        })?
+}
+
+starlark_module! { structured_data_module =>
+    parse_json(env env, path: String) {
+        starlark_parse_file(&env, DataFormat::Json, "parse_json()", path)
+    }
+
+    parse_toml(env env, path: String) {
+        starlark_parse_file(&env, DataFormat::Toml, "parse_toml()", path)
+    }
+
+    parse_yaml(env env, path: String) {
+        starlark_parse_file(&env, DataFormat::Yaml, "parse_yaml()", path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::super::testutil::*, super::*};
+
+    #[test]
+    fn test_parse_data() -> Result<()> {
+        let value = parse_data(
+            DataFormat::Toml,
+            "[project]\nname = 'myapp'\nversion = '1.0'\nscore = 1.5\nkeywords = ['a', 'b']\n",
+        )?
+        .unwrap();
+        assert_eq!(
+            value.to_str(),
+            "{\"project\": {\"keywords\": [\"a\", \"b\"], \"name\": \"myapp\", \"score\": \"1.5\", \"version\": \"1.0\"}}"
+        );
+
+        let value = parse_data(
+            DataFormat::Json,
+            "{\"version\": \"1.0\", \"build\": 42, \"debug\": false, \"extra\": null}",
+        )?
+        .unwrap();
+        assert_eq!(
+            value.to_str(),
+            "{\"build\": 42, \"debug\": False, \"extra\": None, \"version\": \"1.0\"}"
+        );
+
+        let value = parse_data(DataFormat::Yaml, "name: myapp\nfiles:\n  - a.txt\n")?.unwrap();
+        assert_eq!(
+            value.to_str(),
+            "{\"files\": [\"a.txt\"], \"name\": \"myapp\"}"
+        );
+
+        assert!(parse_data(DataFormat::Json, "{").is_err());
+        assert!(parse_data(DataFormat::Toml, "version = ").is_err());
+        assert!(parse_data(DataFormat::Yaml, "[1, 2]: a").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_toml() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let path = temp_dir.path().join("pyproject.toml");
+        std::fs::write(&path, "[tool.poetry]\nversion = \"0.1.0\"\n")?;
+
+        let mut env = StarlarkEnvironment::new()?;
+        let value = env.eval(&format!(
+            "parse_toml({:?})['tool']['poetry']['version']",
+            path.display().to_string()
+        ))?;
+        assert_eq!(value.to_str(), "0.1.0");
+
+        assert!(env
+            .eval(&format!(
+                "parse_json({:?})",
+                temp_dir.path().join("missing.json").display().to_string()
+            ))
+            .is_err());
+
+        Ok(())
+    }
+}
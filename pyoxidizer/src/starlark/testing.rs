@@ -0,0 +1,164 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Starlark functionality for testing configuration files.
+*/
+
+use starlark::{
+    values::{
+        error::{RuntimeError, ValueError},
+        none::NoneType,
+        {Value, ValueResult},
+    },
+    {
+        starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+        starlark_signature_extraction, starlark_signatures,
+    },
+};
+
+/// Error code for failed assertions.
+pub const ASSERTION_FAILED_ERROR_CODE: &str = "PYOXIDIZER_TEST";
+
+/// Prefix of functions that are treated as tests.
+pub const TEST_FUNCTION_PREFIX: &str = "test_";
+
+/// Obtain the names of test functions defined in Starlark source code.
+///
+/// Test functions are top-level functions whose name begins with `test_`.
+pub fn find_test_functions(source: &str) -> Vec<String> {
+    source
+        .lines()
+        .filter_map(|line| {
+            let name = line.strip_prefix("def ")?.trim_start();
+
+            if !name.starts_with(TEST_FUNCTION_PREFIX) {
+                return None;
+            }
+
+            let name = &name[0..name.find('(')?];
+
+            Some(name.trim_end().to_string())
+        })
+        .collect::<Vec<_>>()
+}
+
+fn assertion_error(label: &str, message: String, msg: &Value) -> ValueError {
+    let message = if msg.get_type() == "NoneType" {
+        message
+    } else {
+        format!("{}: {}", msg.to_str(), message)
+    };
+
+    ValueError::from(RuntimeError {
+        code: ASSERTION_FAILED_ERROR_CODE,
+        message,
+        label: label.to_string(),
+    })
+}
+
+/// assert_eq(left, right, msg=None)
+fn starlark_assert_eq(left: &Value, right: &Value, msg: &Value) -> ValueResult {
+    if left.equals(right)? {
+        Ok(Value::new(NoneType::None))
+    } else {
+        Err(assertion_error(
+            "assert_eq()",
+            format!("{} != {}", left.to_repr(), right.to_repr()),
+            msg,
+        ))
+    }
+}
+
+/// assert_ne(left, right, msg=None)
+fn starlark_assert_ne(left: &Value, right: &Value, msg: &Value) -> ValueResult {
+    if left.equals(right)? {
+        Err(assertion_error(
+            "assert_ne()",
+            format!("{} == {}", left.to_repr(), right.to_repr()),
+            msg,
+        ))
+    } else {
+        Ok(Value::new(NoneType::None))
+    }
+}
+
+/// assert_true(value, msg=None)
+fn starlark_assert_true(value: &Value, msg: &Value) -> ValueResult {
+    if value.to_bool() {
+        Ok(Value::new(NoneType::None))
+    } else {
+        Err(assertion_error(
+            "assert_true()",
+            format!("{} is not true", value.to_repr()),
+            msg,
+        ))
+    }
+}
+
+/// assert_false(value, msg=None)
+fn starlark_assert_false(value: &Value, msg: &Value) -> ValueResult {
+    if value.to_bool() {
+        Err(assertion_error(
+            "assert_false()",
+            format!("{} is not false", value.to_repr()),
+            msg,
+        ))
+    } else {
+        Ok(Value::new(NoneType::None))
+    }
+}
+
+starlark_module! { testing_module =>
+    assert_eq(left, right, msg = NoneType::None) {
+        starlark_assert_eq(&left, &right, &msg)
+    }
+
+    assert_ne(left, right, msg = NoneType::None) {
+        starlark_assert_ne(&left, &right, &msg)
+    }
+
+    assert_true(value, msg = NoneType::None) {
+        starlark_assert_true(&value, &msg)
+    }
+
+    assert_false(value, msg = NoneType::None) {
+        starlark_assert_false(&value, &msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::super::testutil::*, super::*, anyhow::Result};
+
+    #[test]
+    fn test_find_test_functions() {
+        let source = "def make_exe():\n    pass\n\ndef test_foo():\n    pass\n\ndef  test_bar (x):\n    pass\n\n    def test_nested():\n        pass\n";
+
+        assert_eq!(
+            find_test_functions(source),
+            vec!["test_foo".to_string(), "test_bar".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_assertions() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+        testing_module(&mut env.env, &mut env.type_values);
+
+        env.eval("assert_eq(1, 1)")?;
+        assert!(env.eval("assert_eq(1, 2)").is_err());
+        env.eval("assert_ne(1, 2)")?;
+        assert!(env.eval("assert_ne('a', 'a')").is_err());
+        env.eval("assert_true(True)")?;
+        assert!(env.eval("assert_true([], 'list is empty')").is_err());
+        env.eval("assert_false(None)")?;
+        assert!(env.eval("assert_false(1)").is_err());
+
+        let err = env.eval("assert_eq(1, 2, 'values differ')").unwrap_err();
+        assert!(err.to_string().contains("values differ: 1 != 2"));
+
+        Ok(())
+    }
+}
@@ -51,7 +51,7 @@ impl StarlarkEnvironment {
             Some(DISTRIBUTION_CACHE.clone()),
         )?;
 
-        let (env, type_values) = global_environment(context, None, false)
+        let (env, type_values) = global_environment(context, None, false, None)
             .map_err(|e| anyhow!("error creating Starlark environment: {:?}", e))?;
 
         Ok(Self { env, type_values })
@@ -4,7 +4,7 @@
 
 use {
     starlark::values::{error::ValueError, none::NoneType, Value},
-    std::{os::raw::c_ulong, path::PathBuf},
+    std::{convert::TryFrom, os::raw::c_ulong, path::PathBuf},
 };
 
 pub trait ToValue {
@@ -47,6 +47,15 @@ impl ToValue for Option<c_ulong> {
     }
 }
 
+impl ToValue for Option<i32> {
+    fn to_value(&self) -> Value {
+        match self {
+            Some(value) => Value::from(*value),
+            None => Value::from(NoneType::None),
+        }
+    }
+}
+
 impl ToValue for Option<Vec<String>> {
     fn to_value(&self) -> Value {
         match self {
@@ -118,6 +127,18 @@ impl TryToOptional<c_ulong> for Value {
     }
 }
 
+impl TryToOptional<i32> for Value {
+    fn try_to_optional(&self) -> Result<Option<i32>, ValueError> {
+        if self.get_type() == "NoneType" {
+            Ok(None)
+        } else {
+            Ok(Some(
+                i32::try_from(self.to_int()?).map_err(|_| ValueError::IntegerOverflow)?,
+            ))
+        }
+    }
+}
+
 impl TryToOptional<Vec<String>> for Value {
     fn try_to_optional(&self) -> Result<Option<Vec<String>>, ValueError> {
         if self.get_type() == "NoneType" {
@@ -132,6 +153,31 @@ impl TryToOptional<Vec<String>> for Value {
     }
 }
 
+/// Converts a `dict` of strings to its items, in order.
+impl TryToOptional<Vec<(String, String)>> for Value {
+    fn try_to_optional(&self) -> Result<Option<Vec<(String, String)>>, ValueError> {
+        match self.get_type() {
+            "NoneType" => Ok(None),
+            "dict" => {
+                let mut items = vec![];
+
+                for key in self.iter()?.iter() {
+                    let value = self.at(key.clone())?;
+
+                    if key.get_type() != "string" || value.get_type() != "string" {
+                        return Err(ValueError::IncorrectParameterType);
+                    }
+
+                    items.push((key.to_string(), value.to_string()));
+                }
+
+                Ok(Some(items))
+            }
+            _ => Err(ValueError::IncorrectParameterType),
+        }
+    }
+}
+
 impl TryToOptional<Vec<PathBuf>> for Value {
     fn try_to_optional(&self) -> Result<Option<Vec<PathBuf>>, ValueError> {
         if self.get_type() == "NoneType" {
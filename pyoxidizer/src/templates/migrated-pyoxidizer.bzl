@@ -0,0 +1,172 @@
+# This file defines how PyOxidizer application building and packaging is
+# performed. See the pyoxidizer crate's documentation for extensive
+# documentation on this file format.
+#
+# It was generated from the {{{ source_kind }}} {{{ source_filename }}} by
+# `pyoxidizer {{{ command }}}`. Review it before building.
+{{~#if notes}}
+#
+# The following could not be migrated automatically:
+#
+{{~#each notes}}
+# * {{{ this }}}
+{{~/each}}
+{{~/if}}
+{{~#if excludes}}
+
+# Modules excluded from the application, from `excludes`.
+EXCLUDES = [
+{{~#each excludes}}
+    {{{ this }}},
+{{~/each}}
+]
+
+def exclude_resources(policy, resource):
+    if type(resource) in ("PythonModuleSource", "PythonModuleBytecode", "PythonExtensionModule"):
+        name = resource.name
+    elif type(resource) in ("PythonPackageResource", "PythonPackageDistributionResource"):
+        name = resource.package
+    else:
+        return
+
+    for exclude in EXCLUDES:
+        if name == exclude or name.startswith(exclude + "."):
+            resource.add_include = False
+{{~/if}}
+
+def make_dist():
+    return default_python_distribution()
+{{~#each executables}}
+
+def make_{{{ this.target }}}(dist):
+    policy = dist.make_python_packaging_policy()
+{{~#if @root.excludes}}
+    policy.register_resource_callback(exclude_resources)
+{{~/if}}
+
+    # Applications commonly locate files relative to their modules'
+    # `__file__`, which isn't set for modules loaded from memory. If your
+    # application does this, load its modules from the filesystem instead:
+    # policy.resources_location = "filesystem-relative:lib"
+
+    python_config = dist.make_python_interpreter_config()
+{{~#if @root.sys_frozen}}
+
+    # Emulate the run-time environment of the original tool.
+    python_config.sys_frozen = True
+{{~#if @root.sys_meipass}}
+    # `sys._MEIPASS` is the directory of the executable, where data files
+    # are installed.
+    python_config.sys_meipass = True
+{{~/if}}
+{{~/if}}
+{{~#if this.main_module}}
+
+    # Run the application's main module as `__main__`.
+    python_config.run_module = {{{ this.main_module }}}
+{{~/if}}
+
+    exe = dist.to_python_executable(
+        name="{{{ this.program_name }}}",
+        packaging_policy=policy,
+        config=python_config,
+    )
+{{~#if this.windows_subsystem}}
+
+    exe.windows_subsystem = "{{{ this.windows_subsystem }}}"
+{{~/if}}
+{{~#if this.icon}}
+
+    # PyOxidizer can't set the executable's icon ({{{ this.icon }}}) yet.
+{{~/if}}
+{{~#if this.pip_install}}
+
+    # Install the application's dependencies.
+{{~#each this.pip_install}}
+{{~#if this.condition}}
+    if {{{ this.condition }}}:
+        exe.add_python_resources(exe.pip_install([{{{ this.args }}}]))
+{{~else}}
+    exe.add_python_resources(exe.pip_install([{{{ this.args }}}]))
+{{~/if}}
+{{~/each}}
+{{~/if}}
+{{~#if this.package_root}}
+
+    # Add the application's packages. Remove packages which aren't part of
+    # the application.
+    exe.add_python_resources(exe.read_package_root(
+        path={{{ this.package_root }}},
+        packages=[
+{{~#each this.packages}}
+            {{{ this }}},
+{{~/each}}
+        ],
+    ))
+{{~/if}}
+{{~#if this.search_paths}}
+
+    # These paths were searched for imports. Add the packages the
+    # application uses from them with `read_package_root()`:
+{{~#each this.search_paths}}
+    # {{{ this }}}
+{{~/each}}
+{{~/if}}
+{{~#if this.required_modules}}
+
+    # All modules of the packages added above are included, so listing
+    # modules to include isn't needed. Make sure these modules are provided
+    # by an added package or the standard library:
+{{~#each this.required_modules}}
+    # {{{ this }}}
+{{~/each}}
+{{~/if}}
+
+    return exe
+{{~/each}}
+
+def make_embedded_resources(exe):
+    return exe.to_embedded_resources()
+
+def make_install({{#each executables}}{{{ this.target }}}{{#unless @last}}, {{/unless}}{{/each}}):
+    files = FileManifest()
+{{#each executables}}
+    files.add_python_resource(".", {{{ this.target }}})
+{{~/each}}
+{{~#if datas}}
+
+    # Data files.
+{{~#each datas}}
+    files.add_manifest(glob([{{{ this.include }}}], strip_prefix={{{ this.strip_prefix }}}))
+{{~/each}}
+{{~/if}}
+{{~#if unmigrated_datas}}
+
+    # These data files must be installed to their destination by hand:
+{{~#each unmigrated_datas}}
+    # {{{ this }}}
+{{~/each}}
+{{~/if}}
+
+    return files
+
+register_target("dist", make_dist)
+{{~#each executables}}
+register_target("{{{ this.target }}}", make_{{{ this.target }}}, depends=["dist"])
+{{~/each}}
+{{~#each executables}}
+{{~#if @first}}
+register_target("resources", make_embedded_resources, depends=["{{{ this.target }}}"], default_build_script=True)
+{{~/if}}
+{{~/each}}
+register_target("install", make_install, depends=[{{#each executables}}"{{{ this.target }}}"{{#unless @last}}, {{/unless}}{{/each}}], default=True)
+
+resolve_targets()
+
+# END OF COMMON USER-ADJUSTED SETTINGS.
+#
+# Everything below this is typically managed by PyOxidizer and doesn't need
+# to be updated by people.
+
+PYOXIDIZER_VERSION = "{{{ pyoxidizer_version }}}"
+PYOXIDIZER_COMMIT = "{{{ pyoxidizer_commit }}}"
@@ -26,4 +26,23 @@ fn main() {
     } else {
         panic!("unable to find build artifacts generated by pyembed crate");
     }
+
+    // Shared libraries added to the executable are installed in a directory
+    // relative to it. Add that directory to the run-time library search path.
+    if let Ok(dir) = std::env::var("DEP_PYTHONXY_RUNTIME_LIBRARY_DIR") {
+        let target_os =
+            std::env::var("CARGO_CFG_TARGET_OS").expect("CARGO_CFG_TARGET_OS not defined");
+
+        if target_os == "macos" || target_os == "ios" {
+            println!(
+                "cargo:rustc-link-arg-bins=-Wl,-rpath,@executable_path/{}",
+                dir
+            );
+        } else if target_family == "unix" {
+            // Emit DT_RPATH instead of DT_RUNPATH, as only the former is
+            // consulted for libraries needed by extension modules.
+            println!("cargo:rustc-link-arg-bins=-Wl,--disable-new-dtags");
+            println!("cargo:rustc-link-arg-bins=-Wl,-rpath,$ORIGIN/{}", dir);
+        }
+    }
 }
@@ -0,0 +1,176 @@
+use {
+    once_cell::sync::Lazy,
+    pyembed::MainPythonInterpreter,
+    std::{
+        ffi::{CStr, OsString},
+        os::raw::{c_char, c_int},
+        sync::{Mutex, MutexGuard},
+    },
+};
+
+// Include an auto-generated file containing the default
+// `pyembed::OxidizedPythonInterpreterConfig` derived by the PyOxidizer
+// configuration file.
+//
+// If you do not want to use PyOxidizer to generate this file, simply
+// remove this line and instantiate your own instance of
+// `pyembed::OxidizedPythonInterpreterConfig`.
+include!(env!("PYOXIDIZER_DEFAULT_PYTHON_CONFIG_RS"));
+
+type Interpreter = MainPythonInterpreter<'static, 'static, 'static>;
+
+/// Holds the interpreter created by `pyoxidizer_init()`, if any.
+struct InterpreterSlot(Mutex<Option<Box<Interpreter>>>);
+
+// The interpreter is only accessed with the mutex held.
+unsafe impl Send for InterpreterSlot {}
+unsafe impl Sync for InterpreterSlot {}
+
+static INTERPRETER: Lazy<InterpreterSlot> = Lazy::new(|| InterpreterSlot(Mutex::new(None)));
+
+/// Lock the interpreter created by `pyoxidizer_init()`.
+fn lock_interpreter() -> MutexGuard<'static, Option<Box<Interpreter>>> {
+    // A panic while holding the lock doesn't leave the slot inconsistent.
+    INTERPRETER.0.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Initialize the embedded Python interpreter.
+///
+/// As with `Py_Initialize()`, the calling thread holds the GIL once this
+/// returns. Calling this when the interpreter is already initialized does
+/// nothing. Concurrent calls initialize the interpreter once.
+///
+/// Returns 0 on success.
+#[no_mangle]
+pub extern "C" fn pyoxidizer_init() -> c_int {
+    let mut interpreter = lock_interpreter();
+
+    if interpreter.is_some() {
+        return 0;
+    }
+
+    // Load the default Python configuration as derived by the PyOxidizer config
+    // file used at build time.
+    let config = default_python_config();
+
+    match MainPythonInterpreter::new(config) {
+        Ok(interp) => {
+            *interpreter = Some(Box::new(interp));
+            0
+        }
+        Err(msg) => {
+            eprintln!("{}", msg);
+            1
+        }
+    }
+}
+
+/// Run the code configured to run when the interpreter starts.
+///
+/// The interpreter is initialized if necessary and finalized afterwards.
+///
+/// Returns the exit code of the Python code that was run.
+#[no_mangle]
+pub extern "C" fn pyoxidizer_run() -> c_int {
+    if pyoxidizer_init() != 0 {
+        return 1;
+    }
+
+    // The interpreter is dropped at the end of this block, finalizing it.
+    let interp = lock_interpreter().take();
+
+    match interp {
+        Some(mut interp) => interp.run_as_main(),
+        None => 1,
+    }
+}
+
+/// Convert a C string argument to an `OsString`.
+fn c_arg_to_os_string(arg: &CStr) -> OsString {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        std::ffi::OsStr::from_bytes(arg.to_bytes()).to_os_string()
+    }
+
+    #[cfg(not(unix))]
+    {
+        OsString::from(arg.to_string_lossy().into_owned())
+    }
+}
+
+/// Run the code configured to run when the interpreter starts with the given
+/// process arguments, as if it were the `main()` of a program.
+///
+/// `argv` holds `argc` NUL terminated strings, the first being the program
+/// name. The interpreter is initialized by this function and finalized
+/// afterwards, so it must not already be initialized.
+///
+/// Returns the exit code of the Python code that was run.
+#[no_mangle]
+pub extern "C" fn pyoxidizer_run_main(argc: c_int, argv: *const *const c_char) -> c_int {
+    // Holding the lock prevents the interpreter from being initialized
+    // concurrently.
+    let interpreter = lock_interpreter();
+
+    if interpreter.is_some() {
+        eprintln!("pyoxidizer_run_main() called with interpreter already initialized");
+        return 1;
+    }
+
+    let mut config = default_python_config();
+
+    if !argv.is_null() {
+        config.argv = Some(
+            (0..argc as isize)
+                .map(|i| c_arg_to_os_string(unsafe { CStr::from_ptr(*argv.offset(i)) }))
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    match MainPythonInterpreter::new(config) {
+        Ok(mut interp) => interp.run_as_main(),
+        Err(msg) => {
+            eprintln!("{}", msg);
+            1
+        }
+    }
+}
+
+/// Evaluate Python source code in the `__main__` module.
+///
+/// The interpreter is initialized if necessary and remains initialized
+/// afterwards, so state persists between calls. Uncaught exceptions are
+/// printed to `sys.stderr`. This must not be called from the evaluated code.
+///
+/// Returns 0 on success and -1 if an exception was raised.
+#[no_mangle]
+pub extern "C" fn pyoxidizer_eval(code: *const c_char) -> c_int {
+    if code.is_null() || pyoxidizer_init() != 0 {
+        return -1;
+    }
+
+    let code = match unsafe { CStr::from_ptr(code) }.to_str() {
+        Ok(code) => code,
+        Err(_) => {
+            eprintln!("Python source code is not valid UTF-8");
+            return -1;
+        }
+    };
+
+    match lock_interpreter().as_mut().map(|interp| interp.run_code(code)) {
+        Some(Ok(())) => 0,
+        _ => -1,
+    }
+}
+
+/// Finalize the interpreter created by `pyoxidizer_init()`.
+///
+/// The calling thread must hold the GIL. Calling this when the interpreter
+/// is not initialized does nothing.
+#[no_mangle]
+pub extern "C" fn pyoxidizer_finalize() {
+    let interp = lock_interpreter().take();
+
+    drop(interp);
+}
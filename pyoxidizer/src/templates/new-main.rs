@@ -12,6 +12,15 @@ use pyembed::MainPythonInterpreter;
 include!(env!("PYOXIDIZER_DEFAULT_PYTHON_CONFIG_RS"));
 
 fn main() {
+{{~#if windows_service_name}}
+    // Handle the command line flags for managing the Windows service.
+    #[cfg(windows)]
+    {
+        if let Some(code) = windows_service::handle_command_line() {
+            std::process::exit(code);
+        }
+    }
+{{/if}}
     // The following code is in a block so the MainPythonInterpreter is destroyed in an
     // orderly manner, before process exit.
     let code = {
@@ -39,3 +48,245 @@ fn main() {
     // And exit the process according to code execution results.
     std::process::exit(code);
 }
+{{~#if windows_service_name}}
+
+/// Run the executable as a Windows service.
+///
+/// `--install-service` and `--uninstall-service` register and unregister the
+/// current executable with the service control manager. The service control
+/// manager starts the executable with `--run-service`.
+#[cfg(windows)]
+mod windows_service {
+    use {
+        pyembed::MainPythonInterpreter,
+        std::{
+            ffi::OsStr,
+            os::windows::ffi::OsStrExt,
+            ptr,
+            sync::atomic::{AtomicI32, AtomicPtr, Ordering},
+        },
+        winapi::{
+            shared::{
+                minwindef::{DWORD, LPVOID},
+                winerror::{ERROR_CALL_NOT_IMPLEMENTED, ERROR_SERVICE_SPECIFIC_ERROR, NO_ERROR},
+            },
+            um::{
+                winnt::{
+                    DELETE, LPWSTR, SERVICE_AUTO_START, SERVICE_ERROR_NORMAL,
+                    SERVICE_WIN32_OWN_PROCESS,
+                },
+                winsvc::{
+                    CloseServiceHandle, CreateServiceW, DeleteService, OpenSCManagerW,
+                    OpenServiceW, RegisterServiceCtrlHandlerExW, SetServiceStatus,
+                    StartServiceCtrlDispatcherW, SC_MANAGER_CONNECT, SC_MANAGER_CREATE_SERVICE,
+                    SERVICE_ACCEPT_SHUTDOWN, SERVICE_ACCEPT_STOP, SERVICE_ALL_ACCESS,
+                    SERVICE_CONTROL_INTERROGATE, SERVICE_CONTROL_SHUTDOWN, SERVICE_CONTROL_STOP,
+                    SERVICE_RUNNING, SERVICE_STATUS, SERVICE_STATUS_HANDLE, SERVICE_STOPPED,
+                    SERVICE_STOP_PENDING, SERVICE_TABLE_ENTRYW,
+                },
+            },
+        },
+    };
+
+    const SERVICE_NAME: &str = "{{{ windows_service_name }}}";
+
+    /// Exit code of the Python code run by the service.
+    static EXIT_CODE: AtomicI32 = AtomicI32::new(0);
+
+    /// Handle used to report the status of the service.
+    static STATUS_HANDLE: AtomicPtr<std::ffi::c_void> = AtomicPtr::new(ptr::null_mut());
+
+    fn to_wide(value: &str) -> Vec<u16> {
+        OsStr::new(value).encode_wide().chain(Some(0)).collect()
+    }
+
+    fn last_error(context: &str) -> String {
+        format!("{}: {}", context, std::io::Error::last_os_error())
+    }
+
+    /// Handle the service management flags, returning an exit code if one was passed.
+    pub fn handle_command_line() -> Option<i32> {
+        let arg = std::env::args_os().nth(1)?;
+
+        let res = match arg.to_str()? {
+            "--install-service" => install(),
+            "--uninstall-service" => uninstall(),
+            "--run-service" => run(),
+            _ => return None,
+        };
+
+        Some(match res {
+            Ok(code) => code,
+            Err(msg) => {
+                eprintln!("{}", msg);
+                1
+            }
+        })
+    }
+
+    fn install() -> Result<i32, String> {
+        let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+        let command = to_wide(&format!("\"{}\" --run-service", exe.display()));
+        let name = to_wide(SERVICE_NAME);
+
+        unsafe {
+            let manager = OpenSCManagerW(ptr::null(), ptr::null(), SC_MANAGER_CREATE_SERVICE);
+            if manager.is_null() {
+                return Err(last_error("opening service control manager"));
+            }
+
+            let service = CreateServiceW(
+                manager,
+                name.as_ptr(),
+                name.as_ptr(),
+                SERVICE_ALL_ACCESS,
+                SERVICE_WIN32_OWN_PROCESS,
+                SERVICE_AUTO_START,
+                SERVICE_ERROR_NORMAL,
+                command.as_ptr(),
+                ptr::null(),
+                ptr::null_mut(),
+                ptr::null(),
+                ptr::null(),
+                ptr::null(),
+            );
+            let res = if service.is_null() {
+                Err(last_error("creating service"))
+            } else {
+                CloseServiceHandle(service);
+                println!("installed service {}", SERVICE_NAME);
+                Ok(0)
+            };
+
+            CloseServiceHandle(manager);
+
+            res
+        }
+    }
+
+    fn uninstall() -> Result<i32, String> {
+        let name = to_wide(SERVICE_NAME);
+
+        unsafe {
+            let manager = OpenSCManagerW(ptr::null(), ptr::null(), SC_MANAGER_CONNECT);
+            if manager.is_null() {
+                return Err(last_error("opening service control manager"));
+            }
+
+            let service = OpenServiceW(manager, name.as_ptr(), DELETE);
+            let res = if service.is_null() {
+                Err(last_error("opening service"))
+            } else {
+                let res = if DeleteService(service) == 0 {
+                    Err(last_error("deleting service"))
+                } else {
+                    println!("uninstalled service {}", SERVICE_NAME);
+                    Ok(0)
+                };
+                CloseServiceHandle(service);
+                res
+            };
+
+            CloseServiceHandle(manager);
+
+            res
+        }
+    }
+
+    fn run() -> Result<i32, String> {
+        let mut name = to_wide(SERVICE_NAME);
+        let table = [
+            SERVICE_TABLE_ENTRYW {
+                lpServiceName: name.as_mut_ptr(),
+                lpServiceProc: Some(service_main),
+            },
+            SERVICE_TABLE_ENTRYW {
+                lpServiceName: ptr::null_mut(),
+                lpServiceProc: None,
+            },
+        ];
+
+        // Blocks until the service has stopped.
+        if unsafe { StartServiceCtrlDispatcherW(table.as_ptr()) } == 0 {
+            return Err(last_error("connecting to service control manager"));
+        }
+
+        Ok(EXIT_CODE.load(Ordering::SeqCst))
+    }
+
+    fn set_status(state: DWORD, exit_code: i32) {
+        let mut status = SERVICE_STATUS {
+            dwServiceType: SERVICE_WIN32_OWN_PROCESS,
+            dwCurrentState: state,
+            dwControlsAccepted: if state == SERVICE_RUNNING {
+                SERVICE_ACCEPT_STOP | SERVICE_ACCEPT_SHUTDOWN
+            } else {
+                0
+            },
+            dwWin32ExitCode: if exit_code == 0 {
+                NO_ERROR
+            } else {
+                ERROR_SERVICE_SPECIFIC_ERROR
+            },
+            dwServiceSpecificExitCode: exit_code as DWORD,
+            dwCheckPoint: 0,
+            dwWaitHint: 0,
+        };
+
+        unsafe {
+            SetServiceStatus(
+                STATUS_HANDLE.load(Ordering::SeqCst) as SERVICE_STATUS_HANDLE,
+                &mut status,
+            );
+        }
+    }
+
+    unsafe extern "system" fn control_handler(
+        control: DWORD,
+        _event_type: DWORD,
+        _event_data: LPVOID,
+        _context: LPVOID,
+    ) -> DWORD {
+        match control {
+            SERVICE_CONTROL_STOP | SERVICE_CONTROL_SHUTDOWN => {
+                set_status(SERVICE_STOP_PENDING, 0);
+                // Python code sees a KeyboardInterrupt and can clean up.
+                pyembed::interrupt_main();
+                NO_ERROR
+            }
+            SERVICE_CONTROL_INTERROGATE => NO_ERROR,
+            _ => ERROR_CALL_NOT_IMPLEMENTED,
+        }
+    }
+
+    unsafe extern "system" fn service_main(_argc: DWORD, _argv: *mut LPWSTR) {
+        let name = to_wide(SERVICE_NAME);
+        let handle =
+            RegisterServiceCtrlHandlerExW(name.as_ptr(), Some(control_handler), ptr::null_mut());
+        if handle.is_null() {
+            return;
+        }
+        STATUS_HANDLE.store(handle as *mut std::ffi::c_void, Ordering::SeqCst);
+
+        set_status(SERVICE_RUNNING, 0);
+
+        let code = {
+            let mut config = super::default_python_config();
+            // Don't expose --run-service to Python.
+            config.argv = Some(std::env::args_os().take(1).collect());
+            // Stopping the service relies on Python's SIGINT handler.
+            config.interpreter_config.install_signal_handlers = Some(true);
+
+            match MainPythonInterpreter::new(config) {
+                Ok(mut interp) => interp.run_as_main(),
+                Err(msg) => {
+                    eprintln!("{}", msg);
+                    1
+                }
+            }
+        };
+
+        EXIT_CODE.store(code, Ordering::SeqCst);
+        set_status(SERVICE_STOPPED, code);
+    }
+}{{/if}}
@@ -148,6 +148,14 @@ def make_exe(dist):
     # Set `sys.meipass`
     # python_config.sys_meipass = True
 
+    # Import modules before running the code configured below. e.g. to
+    # apply monkeypatches.
+    # python_config.pre_run_modules = ["myapp._patches"]
+
+    # Provide several tools from one executable. The tool to run is chosen
+    # by the name the executable is invoked as or by its first argument.
+    # python_config.entry_points = {"tool-a": "myapp.a:main", "tool-b": "myapp.b"}
+
     # Write files containing loaded modules to the directory specified
     # by the given environment variable.
     # python_config.write_modules_directory_env = "/tmp/oxidized/loaded_modules"
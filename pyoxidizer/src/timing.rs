@@ -0,0 +1,151 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Measure where time goes during a build.
+//!
+//! Phases of the build pipeline are timed with `PhaseTimer` and
+//! accumulated in a process-wide registry, as they occur deep inside code
+//! that has no handle on the command being run.
+
+use {
+    lazy_static::lazy_static,
+    serde::Serialize,
+    std::{
+        sync::Mutex,
+        time::{Duration, Instant},
+    },
+};
+
+/// Resolving (downloading and extracting) Python distributions.
+pub const PHASE_DISTRIBUTION: &str = "distribution resolve";
+/// Running pip and `setup.py` to obtain Python packages.
+pub const PHASE_PIP: &str = "pip";
+/// Scanning the filesystem for Python resources.
+pub const PHASE_SCAN: &str = "scan";
+/// Compiling Python source to bytecode.
+pub const PHASE_COMPILE: &str = "compile";
+/// Serializing resources into the packed resources format.
+pub const PHASE_SERIALIZE: &str = "serialize";
+/// Producing the libpython library the binary links against.
+pub const PHASE_LINK: &str = "link libpython";
+/// Running `cargo build`.
+pub const PHASE_CARGO_BUILD: &str = "cargo build";
+
+/// Time spent in a build phase.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PhaseTiming {
+    pub phase: String,
+
+    /// Total time spent in the phase, in seconds.
+    pub seconds: f64,
+
+    /// How many times the phase ran.
+    pub count: usize,
+}
+
+lazy_static! {
+    static ref TIMINGS: Mutex<Vec<(&'static str, Duration, usize)>> = Mutex::new(vec![]);
+}
+
+/// Records time spent in a phase when dropped.
+pub struct PhaseTimer {
+    phase: &'static str,
+    start: Instant,
+}
+
+impl PhaseTimer {
+    /// Start timing a phase.
+    pub fn new(phase: &'static str) -> Self {
+        Self {
+            phase,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Drop for PhaseTimer {
+    fn drop(&mut self) {
+        record_phase(self.phase, self.start.elapsed());
+    }
+}
+
+/// Add time spent in a phase.
+pub fn record_phase(phase: &'static str, duration: Duration) {
+    let mut timings = TIMINGS.lock().unwrap();
+
+    if let Some(entry) = timings.iter_mut().find(|(p, _, _)| *p == phase) {
+        entry.1 += duration;
+        entry.2 += 1;
+    } else {
+        timings.push((phase, duration, 1));
+    }
+}
+
+/// Forget all recorded timings.
+pub fn reset_timings() {
+    TIMINGS.lock().unwrap().clear();
+}
+
+/// Obtain recorded timings, in the order phases first ran.
+pub fn phase_timings() -> Vec<PhaseTiming> {
+    TIMINGS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(phase, duration, count)| PhaseTiming {
+            phase: phase.to_string(),
+            seconds: duration.as_secs_f64(),
+            count: *count,
+        })
+        .collect()
+}
+
+/// Format timings as a human readable table.
+pub fn format_timings(timings: &[PhaseTiming]) -> String {
+    let width = timings.iter().map(|t| t.phase.len()).max().unwrap_or(0);
+
+    timings
+        .iter()
+        .map(|t| {
+            format!(
+                "{:width$}  {:>8.2}s{}\n",
+                t.phase,
+                t.seconds,
+                if t.count > 1 {
+                    format!(" ({} runs)", t.count)
+                } else {
+                    "".to_string()
+                },
+                width = width
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_timings() {
+        let timings = vec![
+            PhaseTiming {
+                phase: PHASE_PIP.to_string(),
+                seconds: 12.5,
+                count: 2,
+            },
+            PhaseTiming {
+                phase: PHASE_CARGO_BUILD.to_string(),
+                seconds: 61.0,
+                count: 1,
+            },
+        ];
+
+        assert_eq!(
+            format_timings(&timings),
+            "pip             12.50s (2 runs)\n\
+             cargo build     61.00s\n"
+        );
+    }
+}
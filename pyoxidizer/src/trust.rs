@@ -0,0 +1,818 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Allowlisting code a configuration file may execute.
+
+Configuration files can load other Starlark files and run package build
+scripts (`setup.py`, or the build backends `pip install` runs). A project
+can restrict these with a trust file, `pyoxidizer.trust`, next to its
+configuration file. Each line of the trust file allows one item:
+
+```text
+# Starlark files loaded with load(), by SHA-256 of their content.
+load 9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08 common.bzl
+# Packages installed with setup_py_install() or from local paths by
+# pip_install(), by SHA-256 digest of the files in their directory.
+setup-py 60303ae22b998861bce3b28f33eec1be758a213c86c93c076dbe9f558c11c752 vendor/foo
+# Package indexes and URLs pip_install() may install from, by domain.
+# `*.example.com` matches subdomains of example.com.
+pip-index pypi.org
+```
+
+Text after the value is a comment, typically naming the item. When the
+trust file exists, or `PYOXIDIZER_REQUIRE_TRUST` is set, items not
+allowed by it are rejected. Changes to the trust file can then be
+reviewed like any other change granting code execution.
+
+Indexes are resolved from `pip install` arguments, requirements and
+constraints files (including nested ones), `PIP_*` environment variables
+and pip configuration files. Relative paths are resolved against the
+project directory, which `pip` runs in.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    sha2::{Digest, Sha256},
+    std::{
+        collections::HashMap,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Name of the trust file, next to the configuration file.
+pub const TRUST_FILENAME: &str = "pyoxidizer.trust";
+
+/// Environment variable requiring a trust file.
+///
+/// If set and a project has no trust file, nothing is trusted.
+pub const REQUIRE_TRUST_ENV: &str = "PYOXIDIZER_REQUIRE_TRUST";
+
+/// The index `pip install` uses unless told otherwise.
+const DEFAULT_PIP_INDEX: &str = "https://pypi.org/simple";
+
+/// Kinds of items a trust file allows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrustKind {
+    /// A Starlark file loaded with `load()`.
+    Load,
+    /// A package whose build script is run.
+    SetupPy,
+    /// A domain pip installs packages from.
+    PipIndex,
+}
+
+impl TrustKind {
+    fn keyword(self) -> &'static str {
+        match self {
+            Self::Load => "load",
+            Self::SetupPy => "setup-py",
+            Self::PipIndex => "pip-index",
+        }
+    }
+}
+
+/// Items allowed by a project trust file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrustPolicy {
+    /// Path of the trust file.
+    path: PathBuf,
+
+    /// Allowed items.
+    entries: Vec<(TrustKind, String)>,
+}
+
+impl TrustPolicy {
+    /// Parse the content of a trust file.
+    pub fn parse(path: &Path, data: &str) -> Result<Self> {
+        let mut entries = vec![];
+
+        for (i, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            let keyword = words.next().unwrap();
+            let value = words.next().ok_or_else(|| {
+                anyhow!(
+                    "{}:{}: missing value for {}",
+                    path.display(),
+                    i + 1,
+                    keyword
+                )
+            })?;
+
+            let kind = match keyword {
+                "load" => TrustKind::Load,
+                "setup-py" => TrustKind::SetupPy,
+                "pip-index" => TrustKind::PipIndex,
+                _ => {
+                    return Err(anyhow!(
+                        "{}:{}: unknown trust entry {}; use load, setup-py or pip-index",
+                        path.display(),
+                        i + 1,
+                        keyword
+                    ))
+                }
+            };
+
+            if kind != TrustKind::PipIndex
+                && (value.len() != 64 || !value.chars().all(|c| c.is_ascii_hexdigit()))
+            {
+                return Err(anyhow!(
+                    "{}:{}: {} is not a SHA-256 hex digest",
+                    path.display(),
+                    i + 1,
+                    value
+                ));
+            }
+
+            entries.push((kind, value.to_lowercase()));
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries,
+        })
+    }
+
+    /// Resolve the trust policy of the project in a directory.
+    ///
+    /// Returns `None` if the project has no trust file and one isn't
+    /// required by `REQUIRE_TRUST_ENV`.
+    pub fn for_project(project_dir: &Path) -> Result<Option<Self>> {
+        let path = project_dir.join(TRUST_FILENAME);
+
+        if path.exists() {
+            let data = std::fs::read_to_string(&path)
+                .with_context(|| format!("reading {}", path.display()))?;
+
+            Ok(Some(Self::parse(&path, &data)?))
+        } else if std::env::var(REQUIRE_TRUST_ENV).is_ok() {
+            Ok(Some(Self {
+                path,
+                entries: vec![],
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn allows(&self, kind: TrustKind, value: &str) -> bool {
+        self.entries.iter().any(|(k, v)| {
+            *k == kind
+                && match kind {
+                    TrustKind::PipIndex => match v.strip_prefix("*.") {
+                        Some(parent) => value.ends_with(&format!(".{}", parent)),
+                        None => v == value,
+                    },
+                    _ => v == value,
+                }
+        })
+    }
+
+    fn untrusted(&self, what: &str, kind: TrustKind, value: &str, comment: &str) -> anyhow::Error {
+        anyhow!(
+            "{} is not trusted by {}; after reviewing it, add this line to the trust file:\n\n    {} {}{}",
+            what,
+            self.path.display(),
+            kind.keyword(),
+            value,
+            if comment.is_empty() {
+                "".to_string()
+            } else {
+                format!(" {}", comment)
+            }
+        )
+    }
+
+    /// Check that a file may be used as an item of a kind.
+    pub fn check_file(&self, kind: TrustKind, path: &Path) -> Result<()> {
+        let data = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+        let digest = hex::encode(Sha256::digest(&data));
+
+        if self.allows(kind, &digest) {
+            Ok(())
+        } else {
+            Err(self.untrusted(
+                &format!("{}", path.display()),
+                kind,
+                &digest,
+                &path.display().to_string(),
+            ))
+        }
+    }
+
+    /// Check that the build script of a package in a directory may run.
+    ///
+    /// The build script can run any file of the package, so the digest
+    /// covers all of them. See `package_dir_digest()`.
+    pub fn check_package_dir(&self, package_dir: &Path) -> Result<()> {
+        if !["setup.py", "pyproject.toml"]
+            .iter()
+            .any(|name| package_dir.join(name).exists())
+        {
+            return Err(anyhow!(
+                "{} has no setup.py or pyproject.toml to verify against {}",
+                package_dir.display(),
+                self.path.display()
+            ));
+        }
+
+        let digest = package_dir_digest(package_dir)?;
+
+        if self.allows(TrustKind::SetupPy, &digest) {
+            Ok(())
+        } else {
+            Err(self.untrusted(
+                &format!("package {}", package_dir.display()),
+                TrustKind::SetupPy,
+                &digest,
+                &package_dir.display().to_string(),
+            ))
+        }
+    }
+
+    /// Check that pip may install from a URL.
+    pub fn check_url(&self, url: &str) -> Result<()> {
+        match url_host(url) {
+            Some(host) if !self.allows(TrustKind::PipIndex, &host) => {
+                Err(self.untrusted(url, TrustKind::PipIndex, &host, ""))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that `pip install` may run with arguments and extra environment variables.
+    ///
+    /// Packages must come from allowed indexes and URLs. Local package
+    /// directories must be allowed like `setup.py` packages. `cwd` is the
+    /// directory pip runs in.
+    pub fn check_pip_install(
+        &self,
+        args: &[String],
+        extra_envs: &HashMap<String, String>,
+        cwd: &Path,
+    ) -> Result<()> {
+        let mut envs = std::env::vars()
+            .filter(|(k, _)| k.starts_with("PIP_"))
+            .collect::<HashMap<_, _>>();
+        envs.extend(extra_envs.clone());
+
+        let sources = PipSources::resolve(args, &envs, &pip_config_files(&envs), cwd)?;
+
+        for url in &sources.urls {
+            self.check_url(url)?;
+        }
+
+        for path in &sources.package_dirs {
+            self.check_package_dir(path)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether a directory entry is left out of package directory digests.
+///
+/// These are version control metadata and files written by building the
+/// package, which would otherwise change the digest on every build.
+fn is_ignored_package_entry(name: &str) -> bool {
+    name.starts_with('.')
+        || name == "__pycache__"
+        || name == "build"
+        || name == "dist"
+        || name.ends_with(".egg-info")
+}
+
+/// Compute the SHA-256 digest of the files in a package directory.
+///
+/// The digest covers the relative path and content of every regular file,
+/// in path order, except for entries matched by
+/// `is_ignored_package_entry()`. Symlinks are not followed.
+pub fn package_dir_digest(package_dir: &Path) -> Result<String> {
+    let mut files = vec![];
+
+    for entry in walkdir::WalkDir::new(package_dir)
+        .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0 || !is_ignored_package_entry(&entry.file_name().to_string_lossy())
+        })
+    {
+        let entry = entry.with_context(|| format!("walking {}", package_dir.display()))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let rel = entry.path().strip_prefix(package_dir)?;
+        let rel = rel
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let data = std::fs::read(entry.path())
+            .with_context(|| format!("reading {}", entry.path().display()))?;
+        files.push(format!("{} {}\n", hex::encode(Sha256::digest(&data)), rel));
+    }
+
+    Ok(hex::encode(Sha256::digest(files.concat().as_bytes())))
+}
+
+/// Obtain the host of a URL.
+///
+/// Returns `None` for strings that aren't URLs or don't name a host, such
+/// as `file://` URLs.
+fn url_host(url: &str) -> Option<String> {
+    let rest = &url[url.find("://")? + 3..];
+    let authority = &rest[..rest.find(&['/', '?', '#'][..]).unwrap_or(rest.len())];
+    let host = match authority.rfind('@') {
+        Some(at) => &authority[at + 1..],
+        None => authority,
+    };
+    let host = match host.rfind(':') {
+        Some(colon) if !host.ends_with(']') => &host[..colon],
+        _ => host,
+    };
+
+    if host.is_empty() {
+        None
+    } else {
+        Some(host.to_lowercase())
+    }
+}
+
+/// Split a pip option from its value when they are in one word.
+///
+/// Handles `--option=value` and short options with an attached value,
+/// such as `-ihttps://...` or `-rrequirements.txt`.
+fn split_pip_option(word: &str) -> (&str, Option<&str>) {
+    if word.starts_with("--") {
+        match word.find('=') {
+            Some(idx) => (&word[..idx], Some(&word[idx + 1..])),
+            None => (word, None),
+        }
+    } else if word.len() > 2
+        && word.starts_with('-')
+        && word.is_char_boundary(2)
+        && ["-i", "-f", "-r", "-c", "-e"].contains(&&word[..2])
+    {
+        (&word[..2], Some(&word[2..]))
+    } else {
+        (word, None)
+    }
+}
+
+/// Split the content of a requirements file into words.
+///
+/// Comments start with `#` at the start of a line or after whitespace, so
+/// URL fragments like `#egg=` are kept. Lines ending in `\` continue on
+/// the next line.
+fn requirements_words(data: &str) -> Vec<String> {
+    data.replace("\\\r\n", " ")
+        .replace("\\\n", " ")
+        .lines()
+        .flat_map(|line| {
+            line.split_whitespace()
+                .take_while(|word| !word.starts_with('#'))
+                .map(|word| word.to_string())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Paths of the pip configuration files pip reads.
+///
+/// `PIP_CONFIG_FILE` is read in addition to the global and user files,
+/// unless it names the null device, which disables configuration files.
+fn pip_config_files(envs: &HashMap<String, String>) -> Vec<PathBuf> {
+    let env = |key: &str| std::env::var(key).ok().map(PathBuf::from);
+
+    let mut res = vec![];
+
+    if cfg!(windows) {
+        res.extend(env("PROGRAMDATA").map(|p| p.join("pip").join("pip.ini")));
+        res.extend(env("APPDATA").map(|p| p.join("pip").join("pip.ini")));
+    } else {
+        res.push(PathBuf::from("/etc/pip.conf"));
+        let config_dirs =
+            std::env::var("XDG_CONFIG_DIRS").unwrap_or_else(|_| "/etc/xdg".to_string());
+        res.extend(
+            config_dirs
+                .split(':')
+                .filter(|dir| !dir.is_empty())
+                .map(|dir| Path::new(dir).join("pip").join("pip.conf")),
+        );
+
+        if let Some(home) = env("HOME") {
+            res.push(home.join(".pip").join("pip.conf"));
+            if cfg!(target_os = "macos") {
+                res.push(
+                    home.join("Library")
+                        .join("Application Support")
+                        .join("pip")
+                        .join("pip.conf"),
+                );
+            }
+            res.push(
+                env("XDG_CONFIG_HOME")
+                    .unwrap_or_else(|| home.join(".config"))
+                    .join("pip")
+                    .join("pip.conf"),
+            );
+        }
+    }
+
+    match envs.get("PIP_CONFIG_FILE") {
+        Some(path) if path == "/dev/null" || path.eq_ignore_ascii_case("nul") => vec![],
+        Some(path) => {
+            res.push(PathBuf::from(path));
+            res
+        }
+        None => res,
+    }
+}
+
+/// Obtain the settings of a pip configuration file.
+///
+/// Returns `(key, value)` pairs from all sections. Values continued on
+/// indented lines are joined with spaces.
+fn parse_pip_config(data: &str) -> Vec<(String, String)> {
+    let mut res: Vec<(String, String)> = vec![];
+
+    for line in data.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with(';') {
+            continue;
+        }
+
+        if line.starts_with(char::is_whitespace) {
+            if let Some((_, value)) = res.last_mut() {
+                value.push(' ');
+                value.push_str(trimmed);
+            }
+        } else if trimmed.starts_with('[') {
+            continue;
+        } else if let Some(idx) = trimmed.find(&['=', ':'][..]) {
+            res.push((
+                trimmed[..idx].trim().to_lowercase().replace('_', "-"),
+                trimmed[idx + 1..].trim().to_string(),
+            ));
+        }
+    }
+
+    res
+}
+
+/// The locations a `pip install` may fetch and build packages from.
+#[derive(Debug, Default, PartialEq)]
+struct PipSources {
+    /// Package indexes, find-links locations and packages named by URL.
+    urls: Vec<String>,
+
+    /// Local package directories.
+    package_dirs: Vec<PathBuf>,
+
+    custom_index: bool,
+    no_index: bool,
+    requirements_files: Vec<PathBuf>,
+}
+
+impl PipSources {
+    /// Resolve the sources of a `pip install`.
+    ///
+    /// This covers arguments, requirements and constraints files they
+    /// name, `PIP_*` environment variables and pip configuration files.
+    /// Relative paths in arguments are resolved against `cwd`. Nested
+    /// requirements files are resolved against the file naming them.
+    fn resolve(
+        args: &[String],
+        envs: &HashMap<String, String>,
+        config_files: &[PathBuf],
+        cwd: &Path,
+    ) -> Result<Self> {
+        let mut res = Self::default();
+
+        for path in config_files.iter().filter(|path| path.is_file()) {
+            let data = std::fs::read_to_string(path)
+                .with_context(|| format!("reading {}", path.display()))?;
+            for (key, value) in parse_pip_config(&data) {
+                res.setting(&key, &value);
+            }
+        }
+
+        let mut envs = envs.iter().collect::<Vec<_>>();
+        envs.sort();
+        for (key, value) in envs {
+            if let Some(key) = key.strip_prefix("PIP_") {
+                res.setting(&key.to_lowercase().replace('_', "-"), value);
+            }
+        }
+
+        res.words(args, cwd, cwd)?;
+
+        if !res.custom_index && !res.no_index {
+            res.urls.push(DEFAULT_PIP_INDEX.to_string());
+        }
+
+        Ok(res)
+    }
+
+    /// Apply a setting from a configuration file or environment variable.
+    fn setting(&mut self, key: &str, value: &str) {
+        match key {
+            "index-url" => {
+                self.custom_index = true;
+                self.urls.push(value.to_string());
+            }
+            "extra-index-url" | "find-links" => {
+                self.urls
+                    .extend(value.split_whitespace().map(|s| s.to_string()));
+            }
+            "no-index" => {
+                self.no_index = ["1", "true", "yes", "on"].contains(&value.to_lowercase().as_str())
+            }
+            _ => {}
+        }
+    }
+
+    /// Process words of arguments or a requirements file.
+    ///
+    /// Requirements files are resolved against `base` and local packages
+    /// against `cwd`.
+    fn words(&mut self, words: &[String], base: &Path, cwd: &Path) -> Result<()> {
+        let mut words = words.iter();
+
+        while let Some(word) = words.next() {
+            let (option, value) = split_pip_option(word);
+            let mut value = || value.or_else(|| words.next().map(|s| s.as_str()));
+
+            match option {
+                "-i" | "--index-url" | "--extra-index-url" | "-f" | "--find-links" => {
+                    if option == "-i" || option == "--index-url" {
+                        self.custom_index = true;
+                    }
+
+                    if let Some(value) = value() {
+                        self.urls.push(value.to_string());
+                    }
+                }
+                "-r" | "--requirement" | "-c" | "--constraint" => {
+                    if let Some(value) = value() {
+                        if value.contains("://") {
+                            self.urls.push(value.to_string());
+                        } else {
+                            self.requirements_file(&base.join(value), cwd)?;
+                        }
+                    }
+                }
+                "-e" | "--editable" => {
+                    if let Some(value) = value() {
+                        self.requirement(value, cwd);
+                    }
+                }
+                "--no-index" => self.no_index = true,
+                _ if !word.starts_with('-') => self.requirement(word, cwd),
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Process a requirements file and the files it names.
+    fn requirements_file(&mut self, path: &Path, cwd: &Path) -> Result<()> {
+        if self.requirements_files.iter().any(|p| p == path) {
+            return Ok(());
+        }
+        self.requirements_files.push(path.to_path_buf());
+
+        let data =
+            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        let base = path.parent().unwrap_or(cwd).to_path_buf();
+
+        self.words(&requirements_words(&data), &base, cwd)
+    }
+
+    /// Process a requirement, which may be a URL or a local path.
+    fn requirement(&mut self, requirement: &str, cwd: &Path) {
+        if let Some(path) = requirement.strip_prefix("file://") {
+            self.local_path(path, cwd);
+        } else if requirement.contains("://") {
+            // Requirements like `name @ https://...` or `git+https://...`.
+            self.urls.push(requirement.to_string());
+        } else {
+            self.local_path(requirement, cwd);
+        }
+    }
+
+    /// Record a requirement if it names a local package directory.
+    fn local_path(&mut self, path: &str, cwd: &Path) {
+        // Strip extras, as in `./foo[bar]`.
+        let path = match path.find('[') {
+            Some(idx) if path.ends_with(']') => &path[..idx],
+            _ => path,
+        };
+
+        let path = cwd.join(path);
+        if path.is_dir() && !self.package_dirs.contains(&path) {
+            self.package_dirs.push(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        let path = Path::new(TRUST_FILENAME);
+        let hash = "A".repeat(64);
+
+        let policy = TrustPolicy::parse(
+            path,
+            &format!(
+                "# comment\n\nload {} common.bzl\npip-index pypi.org\npip-index *.example.com\n",
+                hash
+            ),
+        )?;
+
+        assert!(policy.allows(TrustKind::Load, &"a".repeat(64)));
+        assert!(!policy.allows(TrustKind::SetupPy, &"a".repeat(64)));
+        assert!(policy.allows(TrustKind::PipIndex, "pypi.org"));
+        assert!(policy.allows(TrustKind::PipIndex, "pkgs.example.com"));
+        assert!(!policy.allows(TrustKind::PipIndex, "example.com"));
+        assert!(!policy.allows(TrustKind::PipIndex, "evil-pypi.org"));
+
+        assert!(TrustPolicy::parse(path, "load abc").is_err());
+        assert!(TrustPolicy::parse(path, "pip-index").is_err());
+        assert!(TrustPolicy::parse(path, "run foo").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_file() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let path = temp_dir.path().join("common.bzl");
+        std::fs::write(&path, "x = 1\n")?;
+
+        let policy = TrustPolicy::parse(Path::new(TRUST_FILENAME), "")?;
+        let err = policy.check_file(TrustKind::Load, &path).unwrap_err();
+        let digest = hex::encode(Sha256::digest(b"x = 1\n"));
+        assert!(format!("{}", err).contains(&format!("load {} ", digest)));
+
+        let policy = TrustPolicy::parse(Path::new(TRUST_FILENAME), &format!("load {}", digest))?;
+        policy.check_file(TrustKind::Load, &path)?;
+        assert!(policy.check_file(TrustKind::SetupPy, &path).is_err());
+        assert!(policy.check_package_dir(temp_dir.path()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_url_host() {
+        assert_eq!(
+            url_host("https://pypi.org/simple"),
+            Some("pypi.org".to_string())
+        );
+        assert_eq!(
+            url_host("git+https://user:pw@GitHub.com:443/foo/bar.git"),
+            Some("github.com".to_string())
+        );
+        assert_eq!(url_host("file:///tmp/wheels"), None);
+        assert_eq!(url_host("requests"), None);
+    }
+
+    #[test]
+    fn test_pip_sources() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let cwd = temp_dir.path();
+        let args = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let urls = |args: &[String], envs: &HashMap<String, String>, config: &[PathBuf]| {
+            PipSources::resolve(args, envs, config, cwd).map(|s| s.urls)
+        };
+        let no_envs = HashMap::new();
+
+        assert_eq!(
+            urls(&args(&["requests"]), &no_envs, &[])?,
+            vec![DEFAULT_PIP_INDEX]
+        );
+        assert_eq!(
+            urls(
+                &args(&[
+                    "-i",
+                    "https://a/simple",
+                    "--extra-index-url=https://b/",
+                    "foo"
+                ]),
+                &no_envs,
+                &[]
+            )?,
+            vec!["https://a/simple", "https://b/"]
+        );
+        assert_eq!(
+            urls(&args(&["-ihttps://a/simple", "foo"]), &no_envs, &[])?,
+            vec!["https://a/simple"]
+        );
+        assert_eq!(
+            urls(
+                &args(&["--no-index", "-f", "wheels", "foo @ https://c/foo.whl"]),
+                &no_envs,
+                &[]
+            )?,
+            vec!["wheels", "foo @ https://c/foo.whl"]
+        );
+
+        let mut envs = HashMap::new();
+        envs.insert("PIP_INDEX_URL".to_string(), "https://d/simple".to_string());
+        assert_eq!(urls(&args(&["foo"]), &envs, &[])?, vec!["https://d/simple"]);
+
+        // Nested requirements files are relative to the file naming them.
+        std::fs::create_dir(cwd.join("reqs"))?;
+        std::fs::write(
+            cwd.join("reqs").join("base.txt"),
+            "-r nested.txt # comment\nfoo @ git+https://e/foo.git#egg=foo\n",
+        )?;
+        std::fs::write(
+            cwd.join("reqs").join("nested.txt"),
+            "--extra-index-url https://f/simple\n-c base.txt\n",
+        )?;
+        assert_eq!(
+            urls(&args(&["-rreqs/base.txt"]), &no_envs, &[])?,
+            vec![
+                "https://f/simple",
+                "git+https://e/foo.git#egg=foo",
+                DEFAULT_PIP_INDEX
+            ]
+        );
+        assert!(urls(&args(&["-r", "missing.txt"]), &no_envs, &[]).is_err());
+
+        let config = cwd.join("pip.conf");
+        std::fs::write(
+            &config,
+            "[global]\nindex-url = https://g/simple\nextra-index-url =\n    https://h/simple\n    https://i/simple\n",
+        )?;
+        assert_eq!(
+            urls(&args(&["foo"]), &no_envs, &[config])?,
+            vec!["https://g/simple", "https://h/simple", "https://i/simple"]
+        );
+
+        let mut envs = HashMap::new();
+        envs.insert(
+            "PIP_CONFIG_FILE".to_string(),
+            "/etc/pip-custom.conf".to_string(),
+        );
+        assert_eq!(
+            pip_config_files(&envs).last(),
+            Some(&PathBuf::from("/etc/pip-custom.conf"))
+        );
+        envs.insert("PIP_CONFIG_FILE".to_string(), "/dev/null".to_string());
+        assert!(pip_config_files(&envs).is_empty());
+
+        // Local package directories are relative to the project.
+        std::fs::create_dir(cwd.join("pkg"))?;
+        let sources = PipSources::resolve(
+            &args(&["-e", "pkg[extra]", "./pkg", "requests"]),
+            &no_envs,
+            &[],
+            cwd,
+        )?;
+        assert_eq!(sources.package_dirs, vec![cwd.join("pkg")]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_package_dir() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("pyoxidizer-test")?;
+        let package_dir = temp_dir.path().join("pkg");
+        std::fs::create_dir_all(package_dir.join("foo"))?;
+        std::fs::write(package_dir.join("setup.py"), "import foo.build\n")?;
+        std::fs::write(package_dir.join("foo").join("build.py"), "x = 1\n")?;
+
+        let digest = package_dir_digest(&package_dir)?;
+        let policy = TrustPolicy::parse(
+            Path::new(TRUST_FILENAME),
+            &format!("setup-py {} pkg", digest),
+        )?;
+        policy.check_package_dir(&package_dir)?;
+
+        // Build outputs don't change the digest.
+        std::fs::create_dir_all(package_dir.join("build").join("lib"))?;
+        std::fs::write(package_dir.join("build").join("lib").join("x.py"), "")?;
+        std::fs::create_dir(package_dir.join("foo.egg-info"))?;
+        assert_eq!(package_dir_digest(&package_dir)?, digest);
+
+        // Any other file does.
+        std::fs::write(package_dir.join("foo").join("build.py"), "x = 2\n")?;
+        assert!(policy.check_package_dir(&package_dir).is_err());
+
+        Ok(())
+    }
+}
@@ -24,11 +24,15 @@ pub trait PythonBytecodeCompiler {
     fn get_magic_number(&self) -> u32;
 
     /// Compile Python source into bytecode with an optimization level.
+    ///
+    /// If `strip_docstrings` is true, docstrings are removed regardless of
+    /// the optimization level.
     fn compile(
         &mut self,
         source: &[u8],
         filename: &str,
         optimize: BytecodeOptimizationLevel,
+        strip_docstrings: bool,
         output_mode: CompileMode,
     ) -> Result<Vec<u8>>;
 }
@@ -109,6 +113,7 @@ impl PythonBytecodeCompiler for BytecodeCompiler {
         source: &[u8],
         filename: &str,
         optimize: BytecodeOptimizationLevel,
+        strip_docstrings: bool,
         output_mode: CompileMode,
     ) -> Result<Vec<u8>> {
         let stdin = self.command.stdin.as_mut().expect("failed to get stdin");
@@ -123,6 +128,8 @@ impl PythonBytecodeCompiler for BytecodeCompiler {
         stdin.write_all(b"\n")?;
         stdin.write_all(i32::from(optimize).to_string().as_bytes())?;
         stdin.write_all(b"\n")?;
+        stdin.write_all(if strip_docstrings { b"1" } else { b"0" })?;
+        stdin.write_all(b"\n")?;
         stdin.write_all(match output_mode {
             CompileMode::Bytecode => b"bytecode",
             CompileMode::PycCheckedHash => b"pyc-checked-hash",
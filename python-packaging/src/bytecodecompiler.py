@@ -7,6 +7,7 @@
 # When invoked, we start a server that listens for commands. We then
 # react to those commands and send results to the caller.
 
+import ast
 import importlib._bootstrap_external
 import importlib.util
 import marshal
@@ -25,6 +26,25 @@ stdin = sys.__stdin__.buffer
 stdout = sys.__stdout__.buffer
 
 
+def strip_docstrings(tree):
+    """Remove docstrings from an AST, like optimization level 2 does."""
+    for node in ast.walk(tree):
+        if not isinstance(
+            node, (ast.Module, ast.ClassDef, ast.FunctionDef, ast.AsyncFunctionDef)
+        ):
+            continue
+
+        if ast.get_docstring(node, clean=False) is None:
+            continue
+
+        # Bodies can't be empty. And nothing but a docstring may precede
+        # __future__ imports, so only replace the docstring if it's alone.
+        if len(node.body) > 1:
+            del node.body[0]
+        else:
+            node.body[0] = ast.copy_location(ast.Pass(), node.body[0])
+
+
 while True:
     command = stdin.readline().rstrip()
 
@@ -37,6 +57,7 @@ while True:
         name_len = stdin.readline().rstrip()
         source_len = stdin.readline().rstrip()
         optimize_level = stdin.readline().rstrip()
+        strip = stdin.readline().rstrip() == b"1"
         output_mode = stdin.readline().rstrip()
 
         name_len = int(name_len)
@@ -69,7 +90,12 @@ while True:
         source_bytes = source
         source = source.decode(encoding)
 
-        code = compile(source, name, "exec", optimize=optimize_level)
+        if strip:
+            tree = ast.parse(source, name)
+            strip_docstrings(tree)
+            code = compile(tree, name, "exec", optimize=optimize_level)
+        else:
+            code = compile(source, name, "exec", optimize=optimize_level)
         bytecode = marshal.dumps(code)
 
         if output_mode == b"bytecode":
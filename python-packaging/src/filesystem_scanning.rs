@@ -8,10 +8,10 @@ Scanning the filesystem for Python resources.
 
 use {
     crate::{
-        module_util::{is_package_from_path, PythonModuleSuffixes},
+        module_util::{is_package_from_path, normalize_module_name, PythonModuleSuffixes},
         package_metadata::PythonPackageMetadata,
         resource::{
-            BytecodeOptimizationLevel, DataLocation, FileData, PythonEggFile,
+            BytecodeOptimizationLevel, DataLocation, FileData, GettextCatalog, PythonEggFile,
             PythonExtensionModule, PythonModuleBytecode, PythonModuleSource,
             PythonPackageDistributionResource, PythonPackageDistributionResourceFlavor,
             PythonPackageResource, PythonPathExtension, PythonResource,
@@ -204,11 +204,13 @@ impl<'a> PythonResourceIterator<'a> {
         let mut rel_path = path
             .strip_prefix(&self.root_path)
             .expect("unable to strip path prefix");
-        let mut rel_str = rel_path.to_str().expect("could not convert path to str");
+        // Module and resource names are strings. Paths which aren't valid
+        // Unicode can only be represented as files.
+        let mut rel_str = rel_path.to_str()?;
         let mut components = rel_path
             .iter()
-            .map(|p| p.to_str().expect("unable to get path as str"))
-            .collect::<Vec<_>>();
+            .map(|p| p.to_str())
+            .collect::<Option<Vec<_>>>()?;
 
         // Files in .dist-info and .egg-info directories are distribution metadata files.
         // Parsing the package name out of the directory name can be a bit wonky, as
@@ -276,11 +278,11 @@ impl<'a> PythonResourceIterator<'a> {
                 .strip_prefix(sp_path)
                 .expect("unable to strip site-packages prefix");
 
-            rel_str = rel_path.to_str().expect("could not convert path to str");
+            rel_str = rel_path.to_str()?;
             components = rel_path
                 .iter()
-                .map(|p| p.to_str().expect("unable to get path as str"))
-                .collect::<Vec<_>>();
+                .map(|p| p.to_str())
+                .collect::<Option<Vec<_>>>()?;
 
             true
         } else {
@@ -313,8 +315,8 @@ impl<'a> PythonResourceIterator<'a> {
                 .expect("unable to strip egg prefix");
             components = rel_path
                 .iter()
-                .map(|p| p.to_str().expect("unable to get path as str"))
-                .collect::<Vec<_>>();
+                .map(|p| p.to_str())
+                .collect::<Option<Vec<_>>>()?;
 
             // Ignore EGG-INFO directory, as it is just packaging metadata.
             if components[0] == "EGG-INFO" {
@@ -337,13 +339,14 @@ impl<'a> PythonResourceIterator<'a> {
                     full_module_name.push(module_name);
                 }
 
-                let full_module_name = itertools::join(full_module_name, ".");
+                let full_module_name =
+                    normalize_module_name(&itertools::join(full_module_name, "."));
 
                 if package.is_empty() {
                     package = full_module_name.clone();
                 }
 
-                self.seen_packages.insert(package);
+                self.seen_packages.insert(normalize_module_name(&package));
 
                 let module_components = full_module_name.split('.').collect::<Vec<_>>();
                 let final_name = module_components[module_components.len() - 1];
@@ -392,13 +395,13 @@ impl<'a> PythonResourceIterator<'a> {
                 full_module_name.push(module_name);
             }
 
-            let full_module_name = itertools::join(full_module_name, ".");
+            let full_module_name = normalize_module_name(&itertools::join(full_module_name, "."));
 
             if package.is_empty() {
                 package = full_module_name.clone();
             }
 
-            self.seen_packages.insert(package);
+            self.seen_packages.insert(normalize_module_name(&package));
 
             return Some(PathItem::PythonResource(
                 PythonModuleSource {
@@ -482,13 +485,13 @@ impl<'a> PythonResourceIterator<'a> {
                 full_module_name.push(&module_name);
             }
 
-            let full_module_name = itertools::join(full_module_name, ".");
+            let full_module_name = normalize_module_name(&itertools::join(full_module_name, "."));
 
             if package.is_empty() {
                 package = full_module_name.clone();
             }
 
-            self.seen_packages.insert(package);
+            self.seen_packages.insert(normalize_module_name(&package));
 
             return Some(PathItem::PythonResource(
                 PythonModuleBytecode::from_path(
@@ -639,7 +642,8 @@ impl<'a> Iterator for PythonResourceIterator<'a> {
                     let mut relative_name = None;
 
                     while !components.is_empty() {
-                        let candidate_package = itertools::join(&components, ".");
+                        let candidate_package =
+                            normalize_module_name(&itertools::join(&components, "."));
 
                         if self.seen_packages.contains(&candidate_package) {
                             package = Some(candidate_package);
@@ -665,14 +669,21 @@ impl<'a> Iterator for PythonResourceIterator<'a> {
             let leaf_package = leaf_package.unwrap();
             let relative_name = relative_name.unwrap();
 
-            return Some(Ok(PythonPackageResource {
+            let resource = PythonPackageResource {
                 leaf_package,
                 relative_name,
                 data: self.resolve_data_location(&resource.full_path),
                 is_stdlib: false,
                 is_test: false,
-            }
-            .into()));
+            };
+
+            return Some(Ok(
+                if let Some(catalog) = GettextCatalog::from_package_resource(&resource) {
+                    catalog.into()
+                } else {
+                    resource.into()
+                },
+            ));
         }
     }
 }
@@ -1442,6 +1453,53 @@ mod tests {
         Ok(())
     }
 
+    /// Gettext catalogs in packages are detected.
+    #[test]
+    fn test_gettext_catalog() -> Result<()> {
+        let td = tempdir::TempDir::new("pyoxidizer-test")?;
+        let tp = td.path();
+
+        let package_dir = tp.join("foo");
+        let messages_dir = package_dir.join("locale").join("de").join("LC_MESSAGES");
+        create_dir_all(&messages_dir)?;
+
+        write(&package_dir.join("__init__.py"), "")?;
+        let catalog_path = messages_dir.join("foo.mo");
+        write(&catalog_path, "")?;
+        let source_path = messages_dir.join("foo.po");
+        write(&source_path, "")?;
+
+        let resources =
+            PythonResourceIterator::new(tp, DEFAULT_CACHE_TAG, &DEFAULT_SUFFIXES, false, true)
+                .collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(resources.len(), 3);
+        assert_eq!(
+            resources[1],
+            GettextCatalog {
+                leaf_package: "foo".to_string(),
+                relative_name: "locale/de/LC_MESSAGES/foo.mo".to_string(),
+                locale: "de".to_string(),
+                domain: "foo".to_string(),
+                data: DataLocation::Path(catalog_path),
+            }
+            .into()
+        );
+        assert_eq!(
+            resources[2],
+            PythonPackageResource {
+                leaf_package: "foo".to_string(),
+                relative_name: "locale/de/LC_MESSAGES/foo.po".to_string(),
+                data: DataLocation::Path(source_path),
+                is_stdlib: false,
+                is_test: false,
+            }
+            .into()
+        );
+
+        Ok(())
+    }
+
     /// Resource files next to a package are detected.
     #[test]
     fn test_relative_package_resource() -> Result<()> {
@@ -1536,6 +1594,96 @@ mod tests {
     }
 
     /// .dist-info directory ignored if METADATA file not present.
+    /// Non-ASCII names are normalized like Python identifiers.
+    #[test]
+    fn test_non_ascii_names() -> Result<()> {
+        let td = tempdir::TempDir::new("pyoxidizer-test")?;
+        let tp = td.path();
+
+        // Decomposed, as stored by macOS.
+        let package_dir = tp.join("cafe\u{301}");
+        create_dir_all(&package_dir)?;
+
+        let init_path = package_dir.join("__init__.py");
+        write(&init_path, "")?;
+        let module_path = package_dir.join("データ.py");
+        write(&module_path, "")?;
+        let resource_path = package_dir.join("données.txt");
+        write(&resource_path, "content")?;
+
+        let resources =
+            PythonResourceIterator::new(tp, DEFAULT_CACHE_TAG, &DEFAULT_SUFFIXES, false, true)
+                .collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(resources.len(), 3);
+        assert!(resources.contains(
+            &PythonModuleSource {
+                name: "caf\u{e9}".to_string(),
+                source: DataLocation::Path(init_path),
+                is_package: true,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            }
+            .into()
+        ));
+        assert!(resources.contains(
+            &PythonModuleSource {
+                name: "caf\u{e9}.データ".to_string(),
+                source: DataLocation::Path(module_path),
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            }
+            .into()
+        ));
+        assert!(resources.contains(
+            &PythonPackageResource {
+                leaf_package: "caf\u{e9}".to_string(),
+                relative_name: "données.txt".to_string(),
+                data: DataLocation::Path(resource_path),
+                is_stdlib: false,
+                is_test: false,
+            }
+            .into()
+        ));
+
+        Ok(())
+    }
+
+    /// Paths which aren't valid Unicode are only emitted as files.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_non_unicode_path() -> Result<()> {
+        use std::os::unix::ffi::OsStrExt;
+
+        let td = tempdir::TempDir::new("pyoxidizer-test")?;
+        let tp = td.path();
+
+        let package_dir = tp.join("foo");
+        create_dir_all(&package_dir)?;
+        write(package_dir.join("__init__.py"), "")?;
+        let rel_path = Path::new("foo").join(OsStr::from_bytes(b"caf\xe9.py"));
+        write(tp.join(&rel_path), "")?;
+
+        let resources =
+            PythonResourceIterator::new(tp, DEFAULT_CACHE_TAG, &DEFAULT_SUFFIXES, true, true)
+                .collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(resources.len(), 3);
+        assert!(resources.iter().any(|r| match r {
+            PythonResource::File(file) => file.path == rel_path,
+            _ => false,
+        }));
+        assert!(resources.iter().all(|r| match r {
+            PythonResource::ModuleSource(m) => m.name == "foo",
+            _ => true,
+        }));
+
+        Ok(())
+    }
+
     #[test]
     fn test_distinfo_missing_metadata() -> Result<()> {
         let td = tempdir::TempDir::new("pyoxidizer-test")?;
@@ -0,0 +1,197 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Traces of modules imported by a running application.
+
+Traces are produced by running an application with Python's import time
+tracing enabled (`-X importtime`, `PYTHONPROFILEIMPORTTIME=1` or the
+`import_time` interpreter config option) and capturing its stderr. Each
+import is reported on a line like:
+
+```text
+import time: self [us] | cumulative | imported package
+import time:       306 |        306 |   _io
+import time:       924 |       1230 | encodings
+```
+
+Modules in a trace are considered hot: they are needed by real usage of
+the application. Other modules are cold.
+
+Lines are written when imports complete, so a module is preceded by the
+modules it imports.
+*/
+
+use {
+    crate::resource::PythonResource,
+    anyhow::{anyhow, Result},
+    std::collections::BTreeSet,
+};
+
+/// Prefix of lines written by Python's import time tracing.
+const IMPORT_TIME_PREFIX: &str = "import time:";
+
+/// A set of modules imported by an application.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImportTrace {
+    /// Module names in the order imports completed.
+    order: Vec<String>,
+    modules: BTreeSet<String>,
+}
+
+impl ImportTrace {
+    /// Parse the output of Python's import time tracing.
+    ///
+    /// Lines not written by the tracing are ignored, so the complete stderr
+    /// of a process can be parsed.
+    pub fn from_importtime_output(data: &str) -> Result<Self> {
+        let mut trace = Self::default();
+
+        for line in data.lines() {
+            let line = match line.trim_start().strip_prefix(IMPORT_TIME_PREFIX) {
+                Some(line) => line,
+                None => continue,
+            };
+
+            let fields = line.split('|').map(|s| s.trim()).collect::<Vec<_>>();
+            if fields.len() != 3 {
+                return Err(anyhow!("malformed import time line: {}", line));
+            }
+
+            // The header line.
+            if fields[2] == "imported package" {
+                continue;
+            }
+
+            if fields[0].parse::<u64>().is_err() || fields[1].parse::<u64>().is_err() {
+                return Err(anyhow!("malformed import time line: {}", line));
+            }
+
+            trace.add_module(fields[2]);
+        }
+
+        if trace.order.is_empty() {
+            return Err(anyhow!(
+                "no imports found in trace; was it collected with -X importtime?"
+            ));
+        }
+
+        Ok(trace)
+    }
+
+    /// Add a module, unless it is already in the trace.
+    fn add_module(&mut self, name: &str) {
+        if self.modules.insert(name.to_string()) {
+            self.order.push(name.to_string());
+        }
+    }
+
+    /// Add the modules of another trace to this one.
+    ///
+    /// Modules not in this trace are appended in the order of the other trace.
+    pub fn merge(&mut self, other: &ImportTrace) {
+        for name in &other.order {
+            self.add_module(name);
+        }
+    }
+
+    /// Names of modules in the trace, in the order they were imported.
+    pub fn modules(&self) -> impl Iterator<Item = &str> {
+        self.order.iter().map(|s| s.as_str())
+    }
+
+    /// Whether a module was imported.
+    pub fn contains(&self, name: &str) -> bool {
+        self.modules.contains(name)
+    }
+
+    /// Whether a resource is hot.
+    ///
+    /// Returns `None` for resources whose location isn't governed by
+    /// imports. Package resources are hot if their package was imported.
+    pub fn is_hot(&self, resource: &PythonResource) -> Option<bool> {
+        match resource {
+            PythonResource::ModuleSource(module) => Some(self.contains(&module.name)),
+            PythonResource::ModuleBytecodeRequest(module) => Some(self.contains(&module.name)),
+            PythonResource::ExtensionModule(em) if !em.is_stdlib => Some(self.contains(&em.name)),
+            PythonResource::PackageResource(resource) => {
+                Some(self.contains(&resource.leaf_package))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::resource::{DataLocation, PythonModuleSource, PythonPackageResource},
+    };
+
+    const TRACE: &str = "\
+import time: self [us] | cumulative | imported package
+import time:       306 |        306 |   _io
+import time:       924 |       1230 | encodings
+warning: something unrelated
+import time:        51 |         51 |     foo.bar
+";
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        let trace = ImportTrace::from_importtime_output(TRACE)?;
+        assert_eq!(
+            trace.modules().collect::<Vec<_>>(),
+            vec!["_io", "encodings", "foo.bar"]
+        );
+
+        assert!(ImportTrace::from_importtime_output("").is_err());
+        assert!(ImportTrace::from_importtime_output("import time: 1 | foo").is_err());
+        assert!(ImportTrace::from_importtime_output("import time: a | b | foo").is_err());
+
+        let mut other = ImportTrace::from_importtime_output(
+            "import time: 1 | 1 | baz\nimport time: 1 | 1 | _io",
+        )?;
+        other.merge(&trace);
+        assert_eq!(
+            other.modules().collect::<Vec<_>>(),
+            vec!["baz", "_io", "encodings", "foo.bar"]
+        );
+        assert!(other.contains("foo.bar"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_hot() -> Result<()> {
+        let trace = ImportTrace::from_importtime_output(TRACE)?;
+
+        let module = PythonModuleSource {
+            name: "foo.bar".to_string(),
+            source: DataLocation::Memory(vec![]),
+            is_package: false,
+            cache_tag: "cpython-38".to_string(),
+            is_stdlib: false,
+            is_test: false,
+        };
+        assert_eq!(trace.is_hot(&module.clone().into()), Some(true));
+
+        let cold = PythonModuleSource {
+            name: "foo.baz".to_string(),
+            ..module
+        };
+        assert_eq!(trace.is_hot(&cold.into()), Some(false));
+
+        let resource = PythonPackageResource {
+            leaf_package: "foo.bar".to_string(),
+            relative_name: "data.txt".to_string(),
+            data: DataLocation::Memory(vec![]),
+            is_stdlib: false,
+            is_test: false,
+        };
+        assert_eq!(trace.is_hot(&resource.into()), Some(true));
+
+        Ok(())
+    }
+}
@@ -54,6 +54,9 @@ impl TryFrom<&str> for PythonInterpreterProfile {
     }
 }
 
+/// Directory relative to the executable holding a bundled `terminfo` database.
+pub const BUNDLED_TERMINFO_DIRECTORY: &str = "terminfo";
+
 /// Defines `terminfo`` database resolution semantics.
 #[derive(Clone, Debug, PartialEq)]
 pub enum TerminfoResolution {
@@ -63,6 +66,11 @@ pub enum TerminfoResolution {
     None,
     /// Use a specified string as the `TERMINFO_DIRS` value.
     Static(String),
+    /// Use a `terminfo` database bundled next to the executable.
+    ///
+    /// The bundled database lives in `BUNDLED_TERMINFO_DIRECTORY` and is
+    /// consulted before the database resolved for the current OS.
+    Bundled,
 }
 
 impl ToString for TerminfoResolution {
@@ -71,6 +79,7 @@ impl ToString for TerminfoResolution {
             Self::Dynamic => "dynamic".to_string(),
             Self::None => "none".to_string(),
             Self::Static(value) => format!("static:{}", value),
+            Self::Bundled => "bundled".to_string(),
         }
     }
 }
@@ -79,10 +88,13 @@ impl TryFrom<&str> for TerminfoResolution {
     type Error = String;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        if value == "dynamic" {
+        // `system` is an alias for `dynamic`.
+        if value == "dynamic" || value == "system" {
             Ok(Self::Dynamic)
         } else if value == "none" {
             Ok(Self::None)
+        } else if value == "bundled" {
+            Ok(Self::Bundled)
         } else if value.starts_with("static:") {
             let suffix = &value[7..];
             Ok(Self::Static(suffix.to_string()))
@@ -446,6 +458,11 @@ pub struct PythonInterpreterConfig {
     /// See https://docs.python.org/3/c-api/init_config.html#c.PyConfig.module_search_paths.
     pub module_search_paths: Option<Vec<PathBuf>>,
 
+    /// See https://docs.python.org/3/c-api/init_config.html#c.PyConfig.module_search_paths_set.
+    ///
+    /// Setting `module_search_paths` implies this.
+    pub module_search_paths_set: Option<bool>,
+
     /// See https://docs.python.org/3/c-api/init_config.html#c.PyConfig.optimization_level.
     pub optimization_level: Option<BytecodeOptimizationLevel>,
 
@@ -455,6 +472,12 @@ pub struct PythonInterpreterConfig {
     /// See https://docs.python.org/3/c-api/init_config.html#c.PyConfig.pathconfig_warnings.
     pub pathconfig_warnings: Option<bool>,
 
+    /// See https://docs.python.org/3/c-api/init_config.html#c.PyConfig.platlibdir.
+    ///
+    /// Applied via the `PYTHONPLATLIBDIR` environment variable, which is
+    /// only set while the interpreter is initialized.
+    pub platlibdir: Option<String>,
+
     /// See https://docs.python.org/3/c-api/init_config.html#c.PyConfig.prefix.
     pub prefix: Option<PathBuf>,
 
@@ -10,16 +10,23 @@ and packaging facilities.
 
 pub mod bytecode;
 pub mod filesystem_scanning;
+pub mod import_trace;
 pub mod interpreter;
 pub mod libpython;
 pub mod licensing;
 pub mod location;
 pub mod module_util;
+pub mod package_distribution;
+pub mod package_hints;
 pub mod package_metadata;
 pub mod policy;
+pub mod profiles;
 pub mod python_source;
+pub mod python_version;
+pub mod pywin32;
 pub mod resource;
 pub mod resource_collection;
+pub mod stdlib_profiles;
 
 #[cfg(feature = "wheel")]
 pub mod wheel;
@@ -26,3 +26,184 @@ pub struct LicenseInfo {
     /// Text of the license.
     pub license_text: String,
 }
+
+/// A parsed SPDX license expression, e.g. `MIT OR (Apache-2.0 AND BSD-3-Clause)`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LicenseExpression {
+    /// A license identifier. Exceptions added with `WITH` are dropped.
+    License(String),
+    /// Both expressions apply.
+    And(Box<LicenseExpression>, Box<LicenseExpression>),
+    /// Either expression may be chosen.
+    Or(Box<LicenseExpression>, Box<LicenseExpression>),
+}
+
+impl LicenseExpression {
+    /// Parse an SPDX license expression.
+    ///
+    /// `AND`, `OR` and `WITH` are accepted in upper or lower case. Returns
+    /// `None` if the value isn't a valid expression, as is the case for
+    /// free-form license names like `BSD License`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let tokens = value
+            .replace('(', " ( ")
+            .replace(')', " ) ")
+            .split_whitespace()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+
+        let mut pos = 0;
+        let expression = Self::parse_or(&tokens, &mut pos)?;
+
+        if pos == tokens.len() {
+            Some(expression)
+        } else {
+            None
+        }
+    }
+
+    fn is_operator(token: &str, operator: &str) -> bool {
+        token == operator || token == operator.to_lowercase()
+    }
+
+    fn parse_or(tokens: &[String], pos: &mut usize) -> Option<Self> {
+        let mut expression = Self::parse_and(tokens, pos)?;
+
+        while *pos < tokens.len() && Self::is_operator(&tokens[*pos], "OR") {
+            *pos += 1;
+            let right = Self::parse_and(tokens, pos)?;
+            expression = Self::Or(Box::new(expression), Box::new(right));
+        }
+
+        Some(expression)
+    }
+
+    fn parse_and(tokens: &[String], pos: &mut usize) -> Option<Self> {
+        let mut expression = Self::parse_atom(tokens, pos)?;
+
+        while *pos < tokens.len() && Self::is_operator(&tokens[*pos], "AND") {
+            *pos += 1;
+            let right = Self::parse_atom(tokens, pos)?;
+            expression = Self::And(Box::new(expression), Box::new(right));
+        }
+
+        Some(expression)
+    }
+
+    fn parse_atom(tokens: &[String], pos: &mut usize) -> Option<Self> {
+        let token = tokens.get(*pos)?;
+        *pos += 1;
+
+        if token == "(" {
+            let expression = Self::parse_or(tokens, pos)?;
+
+            if tokens.get(*pos).map(|s| s.as_str()) != Some(")") {
+                return None;
+            }
+            *pos += 1;
+
+            return Some(expression);
+        }
+
+        if !Self::is_identifier(token) {
+            return None;
+        }
+
+        if *pos < tokens.len() && Self::is_operator(&tokens[*pos], "WITH") {
+            if !Self::is_identifier(tokens.get(*pos + 1)?) {
+                return None;
+            }
+            *pos += 2;
+        }
+
+        Some(Self::License(token.clone()))
+    }
+
+    fn is_identifier(token: &str) -> bool {
+        !["AND", "OR", "WITH"]
+            .iter()
+            .any(|operator| Self::is_operator(token, operator))
+            && token
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-.+:".contains(c))
+    }
+
+    /// The license identifiers in the expression.
+    pub fn licenses(&self) -> Vec<&str> {
+        match self {
+            Self::License(license) => vec![license.as_str()],
+            Self::And(left, right) | Self::Or(left, right) => {
+                let mut res = left.licenses();
+                res.extend(right.licenses());
+                res
+            }
+        }
+    }
+
+    /// Whether the expression can be satisfied using only accepted licenses.
+    pub fn is_satisfied_by(&self, accepted: &dyn Fn(&str) -> bool) -> bool {
+        match self {
+            Self::License(license) => accepted(license),
+            Self::And(left, right) => {
+                left.is_satisfied_by(accepted) && right.is_satisfied_by(accepted)
+            }
+            Self::Or(left, right) => {
+                left.is_satisfied_by(accepted) || right.is_satisfied_by(accepted)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn license(name: &str) -> Box<LicenseExpression> {
+        Box::new(LicenseExpression::License(name.to_string()))
+    }
+
+    #[test]
+    fn test_parse_license_expression() {
+        assert_eq!(LicenseExpression::parse("MIT"), Some(*license("MIT")));
+        assert_eq!(
+            LicenseExpression::parse("MIT OR Apache-2.0 AND BSD-3-Clause"),
+            Some(LicenseExpression::Or(
+                license("MIT"),
+                Box::new(LicenseExpression::And(
+                    license("Apache-2.0"),
+                    license("BSD-3-Clause")
+                ))
+            ))
+        );
+        assert_eq!(
+            LicenseExpression::parse(
+                "(MIT or Apache-2.0) and GPL-2.0-only WITH Classpath-exception-2.0"
+            ),
+            Some(LicenseExpression::And(
+                Box::new(LicenseExpression::Or(license("MIT"), license("Apache-2.0"))),
+                license("GPL-2.0-only")
+            ))
+        );
+        assert_eq!(
+            LicenseExpression::parse("LicenseRef-Proprietary"),
+            Some(*license("LicenseRef-Proprietary"))
+        );
+
+        assert_eq!(LicenseExpression::parse("BSD License"), None);
+        assert_eq!(LicenseExpression::parse("MIT OR"), None);
+        assert_eq!(LicenseExpression::parse("(MIT"), None);
+        assert_eq!(LicenseExpression::parse("MIT)"), None);
+        assert_eq!(LicenseExpression::parse("GPL, v2"), None);
+        assert_eq!(LicenseExpression::parse(""), None);
+    }
+
+    #[test]
+    fn test_license_expression_satisfied() {
+        let expression = LicenseExpression::parse("MIT OR (Apache-2.0 AND GPL-3.0)").unwrap();
+
+        assert_eq!(expression.licenses(), vec!["MIT", "Apache-2.0", "GPL-3.0"]);
+        assert!(expression.is_satisfied_by(&|l| l == "MIT"));
+        assert!(!expression.is_satisfied_by(&|l| l == "Apache-2.0"));
+        assert!(expression.is_satisfied_by(&|l| l != "MIT"));
+    }
+}
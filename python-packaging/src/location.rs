@@ -39,6 +39,12 @@ impl TryFrom<&str> for AbstractResourceLocation {
     }
 }
 
+/// Placeholder in a relative path prefix replaced by a resource's package.
+///
+/// e.g. `filesystem-relative:lib/{package}` installs the files of each
+/// top-level package in its own directory under `lib`.
+pub const PACKAGE_PLACEHOLDER: &str = "{package}";
+
 /// Describes the concrete location of a Python resource.
 #[derive(Clone, Debug, PartialEq)]
 pub enum ConcreteResourceLocation {
@@ -48,6 +54,26 @@ pub enum ConcreteResourceLocation {
     RelativePath(String),
 }
 
+impl ConcreteResourceLocation {
+    /// Resolve the location of a resource belonging to a top-level package.
+    ///
+    /// `PACKAGE_PLACEHOLDER` in relative path prefixes is replaced by the
+    /// package name.
+    pub fn resolve_for_package(&self, package: &str) -> Self {
+        match self {
+            Self::InMemory => Self::InMemory,
+            Self::RelativePath(prefix) => {
+                Self::RelativePath(prefix.replace(PACKAGE_PLACEHOLDER, package))
+            }
+        }
+    }
+}
+
+/// Obtain the top-level package of a dotted resource name.
+pub fn top_level_package(name: &str) -> &str {
+    name.split('.').next().unwrap_or(name)
+}
+
 impl From<&ConcreteResourceLocation> for AbstractResourceLocation {
     fn from(l: &ConcreteResourceLocation) -> Self {
         match l {
@@ -90,7 +116,19 @@ impl TryFrom<&str> for ConcreteResourceLocation {
                 let suffix = parts[1];
 
                 if prefix == "filesystem-relative" {
-                    Ok(Self::RelativePath(suffix.to_string()))
+                    // Catch typos in placeholders, which would otherwise end
+                    // up as directory names.
+                    if suffix
+                        .replace(PACKAGE_PLACEHOLDER, "")
+                        .contains(&['{', '}'][..])
+                    {
+                        Err(format!(
+                            "{} is not a valid resource location: {} is the only supported placeholder",
+                            value, PACKAGE_PLACEHOLDER
+                        ))
+                    } else {
+                        Ok(Self::RelativePath(suffix.to_string()))
+                    }
                 } else {
                     Err(format!("{} is not a valid resource location", value))
                 }
@@ -127,7 +165,30 @@ mod tests {
             ConcreteResourceLocation::try_from("filesystem-relative:lib"),
             Ok(ConcreteResourceLocation::RelativePath("lib".to_string()))
         );
+        assert_eq!(
+            ConcreteResourceLocation::try_from("filesystem-relative:lib/{package}"),
+            Ok(ConcreteResourceLocation::RelativePath(
+                "lib/{package}".to_string()
+            ))
+        );
+        assert!(ConcreteResourceLocation::try_from("filesystem-relative:lib/{pkg}").is_err());
 
         Ok(())
     }
+
+    #[test]
+    fn test_resolve_for_package() {
+        let location = ConcreteResourceLocation::RelativePath("lib/{package}".to_string());
+
+        assert_eq!(
+            location.resolve_for_package("foo"),
+            ConcreteResourceLocation::RelativePath("lib/foo".to_string())
+        );
+        assert_eq!(
+            ConcreteResourceLocation::InMemory.resolve_for_package("foo"),
+            ConcreteResourceLocation::InMemory
+        );
+        assert_eq!(top_level_package("foo.bar.baz"), "foo");
+        assert_eq!(top_level_package("foo"), "foo");
+    }
 }
@@ -4,7 +4,10 @@
 
 /*! Utility functions related to Python modules. */
 
-use std::{collections::BTreeSet, path::Path, path::PathBuf};
+use {
+    std::{collections::BTreeSet, path::Path, path::PathBuf},
+    unicode_normalization::UnicodeNormalization,
+};
 
 /// Represents file name suffixes for Python modules.
 #[derive(Clone, Debug, PartialEq)]
@@ -106,8 +109,23 @@ pub fn resolve_path_for_module(
 }
 
 pub fn is_package_from_path(path: &Path) -> bool {
-    let file_name = path.file_name().unwrap().to_str().unwrap();
-    file_name.starts_with("__init__.")
+    match path.file_name() {
+        Some(file_name) => file_name.to_string_lossy().starts_with("__init__."),
+        None => false,
+    }
+}
+
+/// Normalize a module name the way Python normalizes identifiers (NFKC).
+///
+/// Python normalizes the names in `import` statements, but not file names.
+/// e.g. macOS stores decomposed file names, so a `café.py` file found on disk
+/// needs normalizing to be importable as `café`.
+pub fn normalize_module_name(name: &str) -> String {
+    if name.is_ascii() {
+        name.to_string()
+    } else {
+        name.nfkc().collect()
+    }
 }
 
 #[cfg(test)]
@@ -126,6 +144,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_normalize_module_name() {
+        assert_eq!(normalize_module_name("foo.bar"), "foo.bar");
+        // Decomposed e + combining acute accent becomes a single character.
+        assert_eq!(normalize_module_name("cafe\u{301}"), "caf\u{e9}");
+        // Compatibility characters are replaced: the "fi" ligature.
+        assert_eq!(normalize_module_name("\u{fb01}le"), "file");
+        assert_eq!(normalize_module_name("日本.モジュール"), "日本.モジュール");
+    }
+
+    #[test]
+    fn test_is_package_from_path() {
+        assert!(is_package_from_path(Path::new("foo/__init__.py")));
+        assert!(!is_package_from_path(Path::new("foo/bar.py")));
+        assert!(!is_package_from_path(Path::new("/")));
+    }
+
     #[test]
     fn test_resolve_path_for_module() {
         assert_eq!(
@@ -0,0 +1,419 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Attributing resources to the package distributions providing them.
+
+Installers record the files of each distribution in its metadata directory:
+`RECORD` for `.dist-info` directories and `installed-files.txt` for
+`.egg-info` directories. Paths in these files are mapped to the dotted
+names of the modules and packages they define, so resources can be
+attributed to their distribution by name.
+*/
+
+use {
+    crate::{
+        package_metadata::PythonPackageMetadata,
+        resource::{DataLocation, PythonPackageDistributionResource, PythonResource},
+    },
+    anyhow::{Context, Result},
+    std::{
+        collections::BTreeMap,
+        path::{Path, PathBuf},
+    },
+};
+
+/// A Python package distribution, as described by its metadata.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackageDistribution {
+    /// Name of the distribution.
+    pub name: String,
+
+    /// Version of the distribution.
+    pub version: String,
+
+    /// `Requires-Dist` entries of the distribution's metadata.
+    pub requires_dist: Vec<String>,
+
+    /// Directory holding the distribution's metadata, if known.
+    ///
+    /// Resources are typically read into memory, so this is only known for
+    /// resources read from the filesystem or after `resolve_origins()`.
+    pub origin: Option<PathBuf>,
+}
+
+/// Resolves the package distributions resources originate from.
+#[derive(Clone, Debug, Default)]
+pub struct PackageDistributionIndex {
+    distributions: Vec<PackageDistribution>,
+
+    /// Dotted names of modules and packages to the distribution providing them.
+    ///
+    /// `None` means multiple distributions provide the name, as happens for
+    /// namespace packages.
+    owners: BTreeMap<String, Option<usize>>,
+}
+
+/// Obtain the dotted name a file installed by a distribution defines.
+///
+/// `path` is relative to the directory the distribution is installed in.
+fn dotted_name_for_path(path: &str) -> Option<String> {
+    let components = path
+        .split(&['/', '\\'][..])
+        .filter(|c| !c.is_empty())
+        .collect::<Vec<_>>();
+    let (filename, dirs) = components.split_last()?;
+
+    if dirs
+        .iter()
+        .any(|c| *c == ".." || *c == "__pycache__" || c.contains('.'))
+    {
+        return None;
+    }
+
+    let stem = filename.split('.').next().unwrap_or(filename);
+    let mut parts = dirs.to_vec();
+    if stem != "__init__" && !stem.is_empty() {
+        parts.push(stem);
+    }
+
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join("."))
+    }
+}
+
+/// Normalize a distribution name and version for comparison with metadata directory names.
+///
+/// Installers escape names in different ways, e.g. `zope.interface` and
+/// `Zope_Interface`.
+fn normalize_distribution_stem(value: &str) -> String {
+    value.to_lowercase().replace(&['-', '.'][..], "_")
+}
+
+/// Parse the paths out of a `RECORD` file.
+fn record_paths(data: &str) -> Vec<String> {
+    data.lines()
+        .filter_map(|line| {
+            if let Some(quoted) = line.strip_prefix('"') {
+                quoted.split('"').next().map(|p| p.to_string())
+            } else {
+                line.rsplitn(3, ',').nth(2).map(|p| p.to_string())
+            }
+        })
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+impl PackageDistributionIndex {
+    /// Construct an instance from the distribution resources in a collection.
+    pub fn from_resources<'a>(
+        resources: impl IntoIterator<Item = &'a PythonResource<'a>>,
+    ) -> Result<Self> {
+        let mut grouped: BTreeMap<(String, String), Vec<&PythonPackageDistributionResource>> =
+            BTreeMap::new();
+
+        for resource in resources {
+            if let PythonResource::PackageDistributionResource(r) = resource {
+                grouped
+                    .entry((r.package.clone(), r.version.clone()))
+                    .or_default()
+                    .push(r);
+            }
+        }
+
+        let mut index = Self::default();
+
+        for ((name, version), files) in grouped {
+            let mut distribution = PackageDistribution {
+                name,
+                version,
+                requires_dist: vec![],
+                origin: None,
+            };
+            let mut provided = vec![];
+
+            for file in files {
+                let read = || -> Result<String> {
+                    Ok(String::from_utf8_lossy(&file.data.resolve()?).to_string())
+                };
+                let context = || format!("reading {}:{}", file.package, file.name);
+
+                match file.name.as_str() {
+                    "METADATA" | "PKG-INFO" => {
+                        let metadata = PythonPackageMetadata::from_metadata(&file.data.resolve()?)
+                            .with_context(context)?;
+                        distribution.requires_dist = metadata
+                            .requires_dist()
+                            .into_iter()
+                            .map(|s| s.to_string())
+                            .collect();
+
+                        if let DataLocation::Path(path) = &file.data {
+                            distribution.origin = path.parent().map(|p| p.to_path_buf());
+                        }
+                    }
+                    "RECORD" => {
+                        provided.extend(
+                            record_paths(&read().with_context(context)?)
+                                .iter()
+                                .filter_map(|p| dotted_name_for_path(p)),
+                        );
+                    }
+                    "installed-files.txt" => {
+                        // Paths are relative to the .egg-info directory.
+                        provided.extend(read().with_context(context)?.lines().filter_map(|p| {
+                            p.trim().strip_prefix("../").and_then(dotted_name_for_path)
+                        }));
+                    }
+                    "top_level.txt" => {
+                        provided.extend(
+                            read()
+                                .with_context(context)?
+                                .lines()
+                                .map(|l| l.trim().replace('/', "."))
+                                .filter(|l| !l.is_empty()),
+                        );
+                    }
+                    _ => {}
+                }
+            }
+
+            let offset = index.distributions.len();
+            index.distributions.push(distribution);
+
+            provided.sort();
+            provided.dedup();
+            for name in provided {
+                // Register parent packages too, so resources of packages
+                // without modules are attributed.
+                let parts = name.split('.').collect::<Vec<_>>();
+                for i in 1..=parts.len() {
+                    let entry = index
+                        .owners
+                        .entry(parts[0..i].join("."))
+                        .or_insert(Some(offset));
+
+                    if *entry != Some(offset) {
+                        *entry = None;
+                    }
+                }
+            }
+        }
+
+        Ok(index)
+    }
+
+    /// Resolve the metadata directories of distributions installed under a directory.
+    ///
+    /// Metadata directories are searched up to the depth of a virtualenv's
+    /// `site-packages` directory. Distributions without one keep their
+    /// origin.
+    pub fn resolve_origins(&mut self, root: &Path) -> Result<()> {
+        let mut walker = walkdir::WalkDir::new(root).max_depth(5).into_iter();
+
+        while let Some(entry) = walker.next() {
+            let entry = entry?;
+            let filename = entry.file_name().to_string_lossy();
+
+            let stem = match filename
+                .strip_suffix(".dist-info")
+                .or_else(|| filename.strip_suffix(".egg-info"))
+            {
+                Some(stem) if entry.file_type().is_dir() => normalize_distribution_stem(stem),
+                _ => continue,
+            };
+            walker.skip_current_dir();
+
+            for distribution in &mut self.distributions {
+                let wanted = normalize_distribution_stem(&format!(
+                    "{}-{}",
+                    distribution.name, distribution.version
+                ));
+
+                // Egg metadata directories may carry a Python version suffix.
+                if stem == wanted || stem.starts_with(&format!("{}_py", wanted)) {
+                    distribution.origin = Some(entry.path().to_path_buf());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Distributions in this index.
+    pub fn distributions(&self) -> &[PackageDistribution] {
+        &self.distributions
+    }
+
+    /// Find the distribution a dotted module or package name originates from.
+    ///
+    /// The most specific name provided by a distribution wins.
+    pub fn find_name(&self, name: &str) -> Option<&PackageDistribution> {
+        let mut candidate = name;
+
+        loop {
+            if let Some(owner) = self.owners.get(candidate) {
+                return owner.map(|i| &self.distributions[i]);
+            }
+
+            candidate = &candidate[0..candidate.rfind('.')?];
+        }
+    }
+
+    /// Find the distribution a resource originates from.
+    pub fn find(&self, resource: &PythonResource) -> Option<&PackageDistribution> {
+        match resource {
+            PythonResource::ModuleSource(m) => self.find_name(&m.name),
+            PythonResource::ModuleBytecode(m) => self.find_name(&m.name),
+            PythonResource::ModuleBytecodeRequest(m) => self.find_name(&m.name),
+            PythonResource::PackageResource(r) => self.find_name(&r.leaf_package),
+            PythonResource::PackageDistributionResource(r) => self
+                .distributions
+                .iter()
+                .find(|d| d.name == r.package && d.version == r.version),
+            PythonResource::ExtensionModule(em) => self.find_name(&em.name),
+            PythonResource::File(f) => self.find_name(&dotted_name_for_path(f.path.to_str()?)?),
+            PythonResource::GettextCatalog(c) => self.find_name(&c.leaf_package),
+            PythonResource::EggFile(_) | PythonResource::PathExtension(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::resource::{PythonModuleSource, PythonPackageDistributionResourceFlavor},
+    };
+
+    fn distribution_resource(
+        package: &str,
+        version: &str,
+        name: &str,
+        data: &str,
+    ) -> PythonResource<'static> {
+        PythonPackageDistributionResource {
+            location: PythonPackageDistributionResourceFlavor::DistInfo,
+            package: package.to_string(),
+            version: version.to_string(),
+            name: name.to_string(),
+            data: DataLocation::Memory(data.as_bytes().to_vec()),
+        }
+        .into()
+    }
+
+    fn module(name: &str) -> PythonResource<'static> {
+        PythonModuleSource {
+            name: name.to_string(),
+            source: DataLocation::Memory(vec![]),
+            is_package: false,
+            cache_tag: "cpython-39".to_string(),
+            is_stdlib: false,
+            is_test: false,
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_dotted_name_for_path() {
+        assert_eq!(dotted_name_for_path("six.py"), Some("six".to_string()));
+        assert_eq!(
+            dotted_name_for_path("foo/__init__.py"),
+            Some("foo".to_string())
+        );
+        assert_eq!(
+            dotted_name_for_path("foo/_speedups.cpython-39-x86_64-linux-gnu.so"),
+            Some("foo._speedups".to_string())
+        );
+        assert_eq!(dotted_name_for_path("foo/__pycache__/bar.pyc"), None);
+        assert_eq!(dotted_name_for_path("foo-1.0.dist-info/RECORD"), None);
+        assert_eq!(dotted_name_for_path("../../bin/foo"), None);
+    }
+
+    #[test]
+    fn test_find() -> Result<()> {
+        let resources = vec![
+            distribution_resource(
+                "foo",
+                "1.0",
+                "METADATA",
+                "Metadata-Version: 2.1\nName: foo\nVersion: 1.0\nRequires-Dist: bar (>=2)\n\n",
+            ),
+            distribution_resource(
+                "foo",
+                "1.0",
+                "RECORD",
+                "foo/__init__.py,sha256=abc,10\n\
+                 ns/foo/__init__.py,,\n\
+                 \"foo/a,b.py\",,\n\
+                 foo-1.0.dist-info/RECORD,,\n",
+            ),
+            distribution_resource("bar", "1.0", "top_level.txt", "bar\nns\n"),
+            module("foo.sub"),
+        ];
+
+        let index = PackageDistributionIndex::from_resources(&resources)?;
+        assert_eq!(index.distributions().len(), 2);
+
+        let foo = index.find(&module("foo.sub")).unwrap();
+        assert_eq!(foo.name, "foo");
+        assert_eq!(foo.version, "1.0");
+        assert_eq!(foo.requires_dist, vec!["bar (>=2)".to_string()]);
+        assert_eq!(foo.origin, None);
+        assert_eq!(index.find(&resources[1]), Some(foo));
+
+        assert_eq!(index.find_name("foo.a,b"), Some(foo));
+        assert_eq!(index.find_name("ns.foo.x"), Some(foo));
+        assert_eq!(index.find_name("bar").unwrap().name, "bar");
+        // ns is provided by both.
+        assert_eq!(index.find_name("ns.other"), None);
+        assert_eq!(index.find_name("unknown"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_origins() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("python-packaging-test")?;
+        let site_packages = temp_dir
+            .path()
+            .join("lib")
+            .join("python3.9")
+            .join("site-packages");
+        let dist_info = site_packages.join("Zope_Interface-5.1.dist-info");
+        let egg_info = site_packages.join("bar-2.0-py3.9.egg-info");
+        std::fs::create_dir_all(&dist_info)?;
+        std::fs::create_dir_all(&egg_info)?;
+
+        let mut index = PackageDistributionIndex::from_resources(&[
+            distribution_resource(
+                "zope.interface",
+                "5.1",
+                "METADATA",
+                "Name: zope.interface\n\n",
+            ),
+            distribution_resource("bar", "2.0", "PKG-INFO", "Name: bar\n\n"),
+            distribution_resource("missing", "1.0", "METADATA", "Name: missing\n\n"),
+        ])?;
+
+        index.resolve_origins(temp_dir.path())?;
+
+        let origins = index
+            .distributions()
+            .iter()
+            .map(|d| (d.name.as_str(), d.origin.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            origins,
+            vec![
+                ("bar", Some(egg_info)),
+                ("missing", None),
+                ("zope.interface", Some(dist_info)),
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,319 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Known packaging requirements of Python packages.
+
+Some packages import modules dynamically (e.g. via `importlib`) or locate
+their data files relative to `__file__`. Collecting them like any other
+package leads to failures at run-time. A hint records what a package
+needs so packaging can accommodate it automatically, similar to
+PyInstaller's hooks.
+*/
+
+use {
+    crate::pywin32,
+    std::collections::{BTreeMap, BTreeSet},
+};
+
+/// Describes the packaging requirements of a Python package.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PackageHint {
+    /// Name of the package the hint applies to.
+    ///
+    /// The hint also applies to the package's submodules.
+    pub package: String,
+
+    /// Modules or packages the package imports dynamically.
+    ///
+    /// These are retained when resources are filtered to a set of names, as
+    /// long as the package is. Packages are retained with their submodules.
+    pub hidden_imports: Vec<String>,
+
+    /// Whether the package's resources must be installed on the filesystem.
+    ///
+    /// This is the case for packages locating their files via `__file__`.
+    pub requires_filesystem: bool,
+
+    /// Whether the package's resources must be included even if the policy
+    /// excludes resources of the Python distribution.
+    pub include_resources: bool,
+}
+
+impl PackageHint {
+    /// Whether the hint applies to a module or package name.
+    pub fn applies_to(&self, name: &str) -> bool {
+        is_in_package(name, &self.package)
+    }
+}
+
+/// Whether `name` is `package` or one of its submodules.
+pub(crate) fn is_in_package(name: &str, package: &str) -> bool {
+    name == package
+        || (name.starts_with(package) && name.as_bytes().get(package.len()) == Some(&b'.'))
+}
+
+/// A package hint defined as static data.
+pub(crate) struct BuiltinHint {
+    pub package: &'static str,
+    pub hidden_imports: &'static [&'static str],
+    pub requires_filesystem: bool,
+    pub include_resources: bool,
+}
+
+impl BuiltinHint {
+    pub fn to_hint(&self) -> PackageHint {
+        PackageHint {
+            package: self.package.to_string(),
+            hidden_imports: self.hidden_imports.iter().map(|s| s.to_string()).collect(),
+            requires_filesystem: self.requires_filesystem,
+            include_resources: self.include_resources,
+        }
+    }
+}
+
+/// Hints for packages whose requirements are well known.
+const BUILTIN_HINTS: &[BuiltinHint] = &[
+    BuiltinHint {
+        package: "babel",
+        hidden_imports: &[],
+        // Locale data is located via `__file__`.
+        requires_filesystem: true,
+        include_resources: false,
+    },
+    BuiltinHint {
+        package: "certifi",
+        hidden_imports: &[],
+        // `certifi.where()` returns the path of the CA bundle.
+        requires_filesystem: true,
+        include_resources: false,
+    },
+    BuiltinHint {
+        package: "Crypto",
+        hidden_imports: &[],
+        // Native code is loaded with ctypes from paths relative to `__file__`.
+        requires_filesystem: true,
+        include_resources: false,
+    },
+    BuiltinHint {
+        package: "docutils",
+        hidden_imports: &[
+            "docutils.languages",
+            "docutils.parsers",
+            "docutils.readers",
+            "docutils.writers",
+        ],
+        requires_filesystem: true,
+        include_resources: false,
+    },
+    BuiltinHint {
+        package: "encodings",
+        // Codecs are looked up by name at run-time.
+        hidden_imports: &["encodings"],
+        requires_filesystem: false,
+        include_resources: false,
+    },
+    BuiltinHint {
+        package: "keyring",
+        hidden_imports: &["keyring.backends"],
+        requires_filesystem: false,
+        include_resources: false,
+    },
+    BuiltinHint {
+        package: "lib2to3",
+        hidden_imports: &[],
+        // Grammar files are loaded from the package directory.
+        requires_filesystem: true,
+        include_resources: true,
+    },
+    BuiltinHint {
+        package: "matplotlib",
+        hidden_imports: &[],
+        // `mpl-data` is located via `__file__`.
+        requires_filesystem: true,
+        include_resources: false,
+    },
+    BuiltinHint {
+        package: "multiprocessing",
+        // Start methods are imported on demand.
+        hidden_imports: &["multiprocessing"],
+        requires_filesystem: false,
+        include_resources: false,
+    },
+    BuiltinHint {
+        package: "numpy",
+        hidden_imports: &["numpy.core._dtype_ctypes"],
+        requires_filesystem: false,
+        include_resources: false,
+    },
+    BuiltinHint {
+        package: "passlib",
+        hidden_imports: &["passlib.handlers"],
+        requires_filesystem: false,
+        include_resources: false,
+    },
+    BuiltinHint {
+        package: "pkg_resources",
+        hidden_imports: &["pkg_resources._vendor", "pkg_resources.extern"],
+        requires_filesystem: false,
+        include_resources: false,
+    },
+    BuiltinHint {
+        package: "pydoc",
+        hidden_imports: &["pydoc_data"],
+        requires_filesystem: false,
+        include_resources: false,
+    },
+    BuiltinHint {
+        package: "pygments",
+        // Lexers, formatters and styles are looked up by name.
+        hidden_imports: &["pygments.formatters", "pygments.lexers", "pygments.styles"],
+        requires_filesystem: false,
+        include_resources: false,
+    },
+    BuiltinHint {
+        package: "pytz",
+        hidden_imports: &[],
+        // Time zone files are opened relative to `__file__`.
+        requires_filesystem: true,
+        include_resources: false,
+    },
+    BuiltinHint {
+        package: "setuptools",
+        hidden_imports: &["setuptools._vendor", "setuptools.extern"],
+        requires_filesystem: false,
+        include_resources: false,
+    },
+    BuiltinHint {
+        package: "sqlalchemy",
+        hidden_imports: &["sqlalchemy.sql.default_comparator"],
+        requires_filesystem: false,
+        include_resources: false,
+    },
+];
+
+/// A collection of package hints, indexed by package.
+///
+/// The default collection holds hints for well known packages.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackageHints {
+    hints: BTreeMap<String, PackageHint>,
+}
+
+impl Default for PackageHints {
+    fn default() -> Self {
+        Self {
+            hints: BUILTIN_HINTS
+                .iter()
+                .map(|hint| (hint.package.to_string(), hint.to_hint()))
+                // pywin32's extension modules link against its DLLs, which
+                // the Windows loader only finds on the filesystem.
+                .chain(pywin32::EXTENSION_MODULES.iter().map(|name| {
+                    (
+                        name.to_string(),
+                        PackageHint {
+                            package: name.to_string(),
+                            requires_filesystem: true,
+                            ..Default::default()
+                        },
+                    )
+                }))
+                .collect(),
+        }
+    }
+}
+
+impl PackageHints {
+    /// Obtain an empty collection.
+    pub fn empty() -> Self {
+        Self {
+            hints: BTreeMap::new(),
+        }
+    }
+
+    /// Iterate over hints, ordered by package.
+    pub fn iter(&self) -> impl Iterator<Item = &PackageHint> {
+        self.hints.values()
+    }
+
+    /// Add a hint, replacing any existing hint for the same package.
+    pub fn register(&mut self, hint: PackageHint) {
+        self.hints.insert(hint.package.clone(), hint);
+    }
+
+    /// Find the hint applying to a module or package name.
+    ///
+    /// The hint of the most specific package wins.
+    pub fn find(&self, name: &str) -> Option<&PackageHint> {
+        self.hints
+            .values()
+            .filter(|hint| hint.applies_to(name))
+            .max_by_key(|hint| hint.package.len())
+    }
+
+    /// Whether a name is imported dynamically by a package among `names`.
+    pub fn is_hidden_import(&self, names: &BTreeSet<String>, name: &str) -> bool {
+        self.hints.values().any(|hint| {
+            hint.hidden_imports
+                .iter()
+                .any(|hidden| is_in_package(name, hidden))
+                && names.iter().any(|n| hint.applies_to(n))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find() {
+        let mut hints = PackageHints::default();
+
+        assert!(hints.find("certifi").unwrap().requires_filesystem);
+        assert_eq!(hints.find("certifi.core").unwrap().package, "certifi");
+        assert!(hints.find("certifix").is_none());
+        assert!(hints.find("foo").is_none());
+
+        hints.register(PackageHint {
+            package: "certifi.core".to_string(),
+            ..Default::default()
+        });
+        assert_eq!(hints.find("certifi.core").unwrap().package, "certifi.core");
+        assert_eq!(hints.find("certifi").unwrap().package, "certifi");
+
+        hints.register(PackageHint {
+            package: "certifi".to_string(),
+            ..Default::default()
+        });
+        assert!(!hints.find("certifi").unwrap().requires_filesystem);
+
+        assert!(
+            hints
+                .find("win32comext.shell.shell")
+                .unwrap()
+                .requires_filesystem
+        );
+        assert!(hints.find("win32").is_none());
+
+        assert!(PackageHints::empty().find("certifi").is_none());
+    }
+
+    #[test]
+    fn test_is_hidden_import() {
+        let hints = PackageHints::default();
+
+        let names = ["pydoc".to_string(), "sqlalchemy.orm".to_string()]
+            .iter()
+            .cloned()
+            .collect::<BTreeSet<_>>();
+
+        assert!(hints.is_hidden_import(&names, "pydoc_data"));
+        assert!(hints.is_hidden_import(&names, "pydoc_data.topics"));
+        assert!(hints.is_hidden_import(&names, "sqlalchemy.sql.default_comparator"));
+        assert!(!hints.is_hidden_import(&names, "sqlalchemy.sql"));
+        assert!(!hints.is_hidden_import(&names, "pygments.lexers"));
+        assert!(!hints.is_hidden_import(&BTreeSet::new(), "pydoc_data"));
+    }
+}
@@ -56,10 +56,46 @@ impl PythonPackageMetadata {
         self.find_first_header("Version")
     }
 
-    #[allow(unused)]
     pub fn license(&self) -> Option<&str> {
         self.find_first_header("License")
     }
+
+    /// Requirements of the package, from `Requires-Dist` headers.
+    pub fn requires_dist(&self) -> Vec<&str> {
+        self.find_all_headers("Requires-Dist")
+    }
+
+    /// Licenses declared by the package.
+    ///
+    /// These are the `License-Expression` header, the `License` header if it
+    /// is a single line (it sometimes holds the full license text) and the
+    /// last component of `License ::` classifiers, e.g. `MIT License` for
+    /// `License :: OSI Approved :: MIT License`.
+    pub fn licenses(&self) -> Vec<&str> {
+        let mut res = vec![];
+
+        if let Some(expression) = self.find_first_header("License-Expression") {
+            res.push(expression.trim());
+        }
+
+        if let Some(license) = self.license() {
+            let license = license.trim();
+
+            if !license.is_empty() && !license.contains('\n') && license != "UNKNOWN" {
+                res.push(license);
+            }
+        }
+
+        for classifier in self.find_all_headers("Classifier") {
+            if classifier.starts_with("License ::") {
+                if let Some(name) = classifier.rsplit("::").next() {
+                    res.push(name.trim());
+                }
+            }
+        }
+
+        res
+    }
 }
 
 #[cfg(test)]
@@ -92,10 +128,35 @@ mod tests {
         assert_eq!(m.version(), Some("19.10b0"));
         assert_eq!(m.license(), Some("MIT"));
         assert_eq!(
-            m.find_all_headers("Requires-Dist"),
+            m.requires_dist(),
             vec!["click (>=6.5)", "attrs (>=18.1.0)", "appdirs"]
         );
         assert_eq!(m.find_first_header("Missing"), None);
+        assert_eq!(m.licenses(), vec!["MIT"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_licenses() -> Result<()> {
+        let data = concat!(
+            "Metadata-Version: 2.1\n",
+            "Name: foo\n",
+            "Version: 1.0\n",
+            "License: UNKNOWN\n",
+            "Classifier: Programming Language :: Python :: 3\n",
+            "Classifier: License :: OSI Approved :: GNU General Public License v3 (GPLv3)\n",
+            "\n",
+        )
+        .as_bytes();
+
+        let m = PythonPackageMetadata::from_metadata(data)?;
+        assert_eq!(m.licenses(), vec!["GNU General Public License v3 (GPLv3)"]);
+
+        let m = PythonPackageMetadata::from_metadata(
+            b"Metadata-Version: 2.4\nName: foo\nLicense-Expression: MIT OR Apache-2.0\n\n",
+        )?;
+        assert_eq!(m.licenses(), vec!["MIT OR Apache-2.0"]);
 
         Ok(())
     }
@@ -8,15 +8,62 @@ Functionality for defining how Python resources should be packaged.
 
 use {
     crate::{
-        licensing::NON_GPL_LICENSES,
+        import_trace::ImportTrace,
+        licensing::{LicenseExpression, NON_GPL_LICENSES},
         location::ConcreteResourceLocation,
+        package_hints::{is_in_package, PackageHint, PackageHints},
+        package_metadata::PythonPackageMetadata,
+        profiles::{find_profile, profile_names},
         resource::{PythonExtensionModule, PythonExtensionModuleVariants, PythonResource},
         resource_collection::PythonResourceAddCollectionContext,
+        stdlib_profiles::{find_stdlib_profile, stdlib_profile_names},
+    },
+    anyhow::{anyhow, Result},
+    std::{
+        collections::{BTreeMap, HashMap},
+        convert::TryFrom,
+        iter::FromIterator,
     },
-    anyhow::Result,
-    std::{collections::HashMap, convert::TryFrom, iter::FromIterator},
 };
 
+/// Whether a resource name matches a glob pattern.
+///
+/// `*` matches any sequence of characters, including `.`, and `?` matches
+/// a single character. A pattern ending in `.*` also matches the name
+/// before it, so `foo.tests.*` matches the `foo.tests` package itself.
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    if let Some(prefix) = pattern.strip_suffix(".*") {
+        if name == prefix {
+            return true;
+        }
+    }
+
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` and the name position it was tried at.
+    let mut star = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
 /// Denotes methods to filter extension modules.
 #[derive(Clone, Debug, PartialEq)]
 pub enum ExtensionModuleFilter {
@@ -85,6 +132,132 @@ impl AsRef<str> for ResourceHandlingMode {
     }
 }
 
+/// Describes how to react to a Python interpreter with known vulnerabilities.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PythonSecurityAction {
+    /// Emit a warning.
+    Warn,
+
+    /// Fail the build.
+    Error,
+}
+
+impl TryFrom<&str> for PythonSecurityAction {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, String> {
+        match value {
+            "warn" => Ok(PythonSecurityAction::Warn),
+            "error" => Ok(PythonSecurityAction::Error),
+            _ => Err(format!(
+                "{} is not a valid security action; use \"warn\" or \"error\"",
+                value
+            )),
+        }
+    }
+}
+
+impl AsRef<str> for PythonSecurityAction {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Warn => "warn",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// Describes whether package build backends run in a sandbox.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BuildSandbox {
+    /// Run build backends without restrictions.
+    Off,
+
+    /// Sandbox build backends if the platform supports it.
+    Auto,
+
+    /// Sandbox build backends and fail if the platform doesn't support it.
+    Required,
+}
+
+impl TryFrom<&str> for BuildSandbox {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, String> {
+        match value {
+            "off" => Ok(BuildSandbox::Off),
+            "auto" => Ok(BuildSandbox::Auto),
+            "required" => Ok(BuildSandbox::Required),
+            _ => Err(format!(
+                "{} is not a valid build sandbox mode; use \"off\", \"auto\" or \"required\"",
+                value
+            )),
+        }
+    }
+}
+
+impl AsRef<str> for BuildSandbox {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Off => "off",
+            Self::Auto => "auto",
+            Self::Required => "required",
+        }
+    }
+}
+
+/// Settings of a package deviating from the policy.
+///
+/// Fields which are `None` use the policy's setting.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PackageOverride {
+    /// Name of the package the override applies to.
+    ///
+    /// The override also applies to the package's submodules.
+    pub package: String,
+
+    /// Whether to include the package's resources.
+    pub include: Option<bool>,
+
+    /// Whether to include the source code of the package's modules.
+    pub include_sources: Option<bool>,
+
+    /// Where to install the package's resources.
+    pub resources_location: Option<ConcreteResourceLocation>,
+
+    /// Fallback location for the package's resources.
+    ///
+    /// Only used when `resources_location` is set, in which case it replaces
+    /// the policy's fallback.
+    pub resources_location_fallback: Option<ConcreteResourceLocation>,
+}
+
+/// The rules of a policy which decided how a resource is added.
+///
+/// Rules are named after the policy setting they come from, e.g.
+/// `exclusions` or `package_override:numpy`. `settings` means the general
+/// settings of the policy, like `include_test`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AddCollectionContextRules {
+    /// Rule deciding whether the resource is included.
+    pub include: String,
+
+    /// Rule deciding where the resource is installed.
+    pub location: String,
+
+    /// Rule deciding whether the source code of a module is included.
+    pub store_source: String,
+}
+
+impl Default for AddCollectionContextRules {
+    fn default() -> Self {
+        Self {
+            include: "settings".to_string(),
+            location: "settings".to_string(),
+            store_source: "settings".to_string(),
+        }
+    }
+}
+
 /// Defines how Python resources should be packaged.
 #[derive(Clone, Debug, PartialEq)]
 pub struct PythonPackagingPolicy {
@@ -117,6 +290,13 @@ pub struct PythonPackagingPolicy {
     /// `PythonPackageResource`, etc).
     allow_files: bool,
 
+    /// Whether to prefer extension modules targeting the stable ABI.
+    ///
+    /// If true, Python packaging tools will prefer `abi3` wheels and
+    /// extension modules built against `Py_LIMITED_API` over variants
+    /// targeting a specific Python version.
+    prefer_abi3_extensions: bool,
+
     /// Whether file scanning should emit `PythonResource::File` variants.
     ///
     /// If true, this resource variant is emitted when scanning for
@@ -168,6 +348,70 @@ pub struct PythonPackagingPolicy {
 
     /// Whether to write Python bytecode at optimization level 2.
     bytecode_optimize_level_two: bool,
+
+    /// Whether to remove docstrings from bytecode at all optimization levels.
+    strip_docstrings: bool,
+
+    /// `YYYY-MM-DD` date after which known vulnerabilities in the Python
+    /// interpreter are reported.
+    ///
+    /// If `None`, the Python interpreter is not checked for vulnerabilities.
+    python_security_baseline: Option<String>,
+
+    /// What to do when the Python interpreter has known vulnerabilities.
+    python_security_action: PythonSecurityAction,
+
+    /// Whether `pip install` and `setup.py` run in a sandbox.
+    build_sandbox: BuildSandbox,
+
+    /// Whether sandboxed build backends may access the network.
+    build_sandbox_allow_network: bool,
+
+    /// Whether to accommodate known requirements of packages.
+    apply_package_hints: bool,
+
+    /// Known requirements of packages.
+    package_hints: PackageHints,
+
+    /// Paths of resources to exclude, relative to the package root.
+    exclusions: Vec<String>,
+
+    /// Glob patterns of resource names to include regardless of other settings.
+    include_patterns: Vec<String>,
+
+    /// Glob patterns of resource names to exclude.
+    exclude_patterns: Vec<String>,
+
+    /// Glob patterns of licenses packages must declare one of.
+    ///
+    /// If empty, packages can have any license not denied.
+    allowed_licenses: Vec<String>,
+
+    /// Glob patterns of licenses packages must not declare.
+    denied_licenses: Vec<String>,
+
+    /// Settings of packages deviating from the policy, keyed by package.
+    package_overrides: BTreeMap<String, PackageOverride>,
+
+    /// Bytecode optimization levels of packages, keyed by package.
+    ///
+    /// These replace the `bytecode_optimize_level_*` settings for the
+    /// package and its submodules.
+    package_optimize_levels: BTreeMap<String, Vec<i32>>,
+
+    /// Name of the preset defining standard library modules to exclude.
+    stdlib_profile: Option<String>,
+
+    /// Standard library modules to exclude, along with their submodules.
+    excluded_modules: Vec<String>,
+
+    /// Where gettext catalogs are installed in their locale directory layout.
+    ///
+    /// If `None`, catalogs are handled like other package resources.
+    gettext_catalogs_location: Option<ConcreteResourceLocation>,
+
+    /// Trace of imported modules used to locate resources.
+    import_trace: Option<ImportTrace>,
 }
 
 impl Default for PythonPackagingPolicy {
@@ -179,6 +423,7 @@ impl Default for PythonPackagingPolicy {
             resources_location_fallback: None,
             allow_in_memory_shared_library_loading: false,
             allow_files: false,
+            prefer_abi3_extensions: false,
             file_scanner_emit_files: false,
             file_scanner_classify_files: true,
             include_classified_resources: true,
@@ -191,6 +436,24 @@ impl Default for PythonPackagingPolicy {
             bytecode_optimize_level_zero: true,
             bytecode_optimize_level_one: false,
             bytecode_optimize_level_two: false,
+            strip_docstrings: false,
+            python_security_baseline: None,
+            python_security_action: PythonSecurityAction::Warn,
+            build_sandbox: BuildSandbox::Off,
+            build_sandbox_allow_network: false,
+            apply_package_hints: true,
+            package_hints: PackageHints::default(),
+            exclusions: vec![],
+            include_patterns: vec![],
+            exclude_patterns: vec![],
+            allowed_licenses: vec![],
+            denied_licenses: vec![],
+            package_overrides: BTreeMap::new(),
+            package_optimize_levels: BTreeMap::new(),
+            stdlib_profile: None,
+            excluded_modules: vec![],
+            gettext_catalogs_location: None,
+            import_trace: None,
         }
     }
 }
@@ -252,6 +515,16 @@ impl PythonPackagingPolicy {
         self.allow_files = value;
     }
 
+    /// Whether to prefer extension modules targeting the stable ABI.
+    pub fn prefer_abi3_extensions(&self) -> bool {
+        self.prefer_abi3_extensions
+    }
+
+    /// Set whether to prefer extension modules targeting the stable ABI.
+    pub fn set_prefer_abi3_extensions(&mut self, value: bool) {
+        self.prefer_abi3_extensions = value;
+    }
+
     /// Whether file scanning should emit `PythonResource::File` variants.
     pub fn file_scanner_emit_files(&self) -> bool {
         self.file_scanner_emit_files
@@ -342,6 +615,16 @@ impl PythonPackagingPolicy {
         self.include_classified_resources = value;
     }
 
+    /// Whether to remove docstrings from bytecode at all optimization levels.
+    pub fn strip_docstrings(&self) -> bool {
+        self.strip_docstrings
+    }
+
+    /// Set whether to remove docstrings from bytecode at all optimization levels.
+    pub fn set_strip_docstrings(&mut self, value: bool) {
+        self.strip_docstrings = value;
+    }
+
     /// Whether to write bytecode at optimization level 0.
     pub fn bytecode_optimize_level_zero(&self) -> bool {
         self.bytecode_optimize_level_zero
@@ -372,6 +655,481 @@ impl PythonPackagingPolicy {
         self.bytecode_optimize_level_two = value;
     }
 
+    /// Obtain the date after which Python interpreter vulnerabilities are reported.
+    pub fn python_security_baseline(&self) -> Option<&str> {
+        self.python_security_baseline.as_deref()
+    }
+
+    /// Set the date after which Python interpreter vulnerabilities are reported.
+    ///
+    /// The date must be in `YYYY-MM-DD` format.
+    pub fn set_python_security_baseline(&mut self, value: Option<&str>) -> Result<()> {
+        if let Some(date) = value {
+            let valid = date.len() == 10
+                && date.char_indices().all(|(i, c)| match i {
+                    4 | 7 => c == '-',
+                    _ => c.is_ascii_digit(),
+                });
+
+            if !valid {
+                return Err(anyhow!(
+                    "{} is not a valid security baseline; use a YYYY-MM-DD date",
+                    date
+                ));
+            }
+        }
+
+        self.python_security_baseline = value.map(|x| x.to_string());
+
+        Ok(())
+    }
+
+    /// Obtain the action to take when the Python interpreter has known vulnerabilities.
+    pub fn python_security_action(&self) -> PythonSecurityAction {
+        self.python_security_action
+    }
+
+    /// Set the action to take when the Python interpreter has known vulnerabilities.
+    pub fn set_python_security_action(&mut self, action: PythonSecurityAction) {
+        self.python_security_action = action;
+    }
+
+    /// Obtain whether build backends run in a sandbox.
+    pub fn build_sandbox(&self) -> BuildSandbox {
+        self.build_sandbox
+    }
+
+    /// Set whether build backends run in a sandbox.
+    pub fn set_build_sandbox(&mut self, value: BuildSandbox) {
+        self.build_sandbox = value;
+    }
+
+    /// Whether sandboxed build backends may access the network.
+    pub fn build_sandbox_allow_network(&self) -> bool {
+        self.build_sandbox_allow_network
+    }
+
+    /// Set whether sandboxed build backends may access the network.
+    pub fn set_build_sandbox_allow_network(&mut self, value: bool) {
+        self.build_sandbox_allow_network = value;
+    }
+
+    /// Whether to accommodate known requirements of packages.
+    pub fn apply_package_hints(&self) -> bool {
+        self.apply_package_hints
+    }
+
+    /// Set whether to accommodate known requirements of packages.
+    pub fn set_apply_package_hints(&mut self, value: bool) {
+        self.apply_package_hints = value;
+    }
+
+    /// Obtain the package hints to apply.
+    ///
+    /// Returns `None` if hints aren't applied.
+    pub fn package_hints(&self) -> Option<&PackageHints> {
+        if self.apply_package_hints {
+            Some(&self.package_hints)
+        } else {
+            None
+        }
+    }
+
+    /// Register a package hint, replacing any hint for the same package.
+    pub fn register_package_hint(&mut self, hint: PackageHint) {
+        self.package_hints.register(hint);
+    }
+
+    /// Paths of resources to exclude, relative to the package root.
+    pub fn exclusions(&self) -> &[String] {
+        &self.exclusions
+    }
+
+    /// Exclude resources at or below a path relative to the package root.
+    ///
+    /// Module names map to paths by replacing `.` with `/`. So `foo/tests`
+    /// excludes the `foo.tests` package as well as the resources of package
+    /// `foo` in its `tests` directory.
+    pub fn add_exclusion(&mut self, path: &str) {
+        if !self.exclusions.iter().any(|x| x == path) {
+            self.exclusions.push(path.to_string());
+        }
+    }
+
+    /// Glob patterns of resource names to include regardless of other settings.
+    pub fn include_patterns(&self) -> &[String] {
+        &self.include_patterns
+    }
+
+    /// Include resources whose name matches a glob pattern.
+    ///
+    /// Resources matching an include pattern are included even if other
+    /// settings, including exclude patterns, would exclude them.
+    pub fn add_include_pattern(&mut self, pattern: &str) -> Result<()> {
+        if pattern.is_empty() {
+            return Err(anyhow!("include pattern cannot be empty"));
+        }
+
+        if !self.include_patterns.iter().any(|x| x == pattern) {
+            self.include_patterns.push(pattern.to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Glob patterns of resource names to exclude.
+    pub fn exclude_patterns(&self) -> &[String] {
+        &self.exclude_patterns
+    }
+
+    /// Exclude resources whose name matches a glob pattern.
+    ///
+    /// Names are those of `PythonResource::full_name()`. e.g. `foo.tests.*`
+    /// excludes the `foo.tests` package, its modules and its resources.
+    pub fn add_exclude_pattern(&mut self, pattern: &str) -> Result<()> {
+        if pattern.is_empty() {
+            return Err(anyhow!("exclude pattern cannot be empty"));
+        }
+
+        if !self.exclude_patterns.iter().any(|x| x == pattern) {
+            self.exclude_patterns.push(pattern.to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Glob patterns of licenses packages must declare one of.
+    pub fn allowed_licenses(&self) -> &[String] {
+        &self.allowed_licenses
+    }
+
+    /// Set glob patterns of licenses packages must declare one of.
+    ///
+    /// Packages not declaring a license matching a pattern are rejected. An
+    /// empty list allows all licenses.
+    pub fn set_allowed_licenses(&mut self, patterns: Vec<String>) -> Result<()> {
+        if patterns.iter().any(|p| p.is_empty()) {
+            return Err(anyhow!("license pattern cannot be empty"));
+        }
+
+        self.allowed_licenses = patterns;
+
+        Ok(())
+    }
+
+    /// Glob patterns of licenses packages must not declare.
+    pub fn denied_licenses(&self) -> &[String] {
+        &self.denied_licenses
+    }
+
+    /// Set glob patterns of licenses packages must not declare.
+    ///
+    /// Packages declaring a license matching a pattern are rejected, even if
+    /// they also declare an allowed license.
+    pub fn set_denied_licenses(&mut self, patterns: Vec<String>) -> Result<()> {
+        if patterns.iter().any(|p| p.is_empty()) {
+            return Err(anyhow!("license pattern cannot be empty"));
+        }
+
+        self.denied_licenses = patterns;
+
+        Ok(())
+    }
+
+    /// Why a package declaring licenses isn't allowed by license rules.
+    ///
+    /// Returns `None` if the package is allowed. Declared licenses which
+    /// are SPDX license expressions are parsed and patterns are matched
+    /// against each license identifier in them, ignoring case. A package
+    /// is denied if every choice an expression allows includes a denied
+    /// license. Other declared licenses are matched as a whole.
+    pub fn license_violation(&self, licenses: &[&str]) -> Option<String> {
+        let matching = |patterns: &[String], license: &str| {
+            patterns
+                .iter()
+                .find(|p| matches_pattern(&p.to_lowercase(), &license.to_lowercase()))
+                .cloned()
+        };
+
+        let expressions = licenses
+            .iter()
+            .map(|license| {
+                LicenseExpression::parse(license)
+                    .unwrap_or_else(|| LicenseExpression::License(license.to_string()))
+            })
+            .collect::<Vec<_>>();
+
+        for (license, expression) in licenses.iter().zip(&expressions) {
+            if !expression.is_satisfied_by(&|id| matching(&self.denied_licenses, id).is_none()) {
+                let pattern = expression
+                    .licenses()
+                    .into_iter()
+                    .find_map(|id| matching(&self.denied_licenses, id))
+                    .unwrap_or_default();
+
+                return Some(format!(
+                    "license {} matches denied license {}",
+                    license, pattern
+                ));
+            }
+        }
+
+        if self.allowed_licenses.is_empty() {
+            None
+        } else if licenses.is_empty() {
+            Some("no license is declared and allowed_licenses is set".to_string())
+        } else if expressions.iter().any(|expression| {
+            expression.is_satisfied_by(&|id| matching(&self.allowed_licenses, id).is_some())
+        }) {
+            None
+        } else {
+            Some(format!(
+                "none of the licenses {} match allowed_licenses",
+                licenses.join(", ")
+            ))
+        }
+    }
+
+    /// Verify packages of resources have licenses allowed by the policy.
+    ///
+    /// Licenses are read from the `METADATA` (or `PKG-INFO`) distribution
+    /// resource of each package. Packages without one aren't checked. The
+    /// error lists all rejected packages.
+    pub fn check_package_licenses(&self, resources: &[PythonResource]) -> Result<()> {
+        if self.allowed_licenses.is_empty() && self.denied_licenses.is_empty() {
+            return Ok(());
+        }
+
+        let mut violations = vec![];
+
+        for resource in resources {
+            if let PythonResource::PackageDistributionResource(r) = resource {
+                if r.name != "METADATA" && r.name != "PKG-INFO" {
+                    continue;
+                }
+
+                let metadata = PythonPackageMetadata::from_metadata(&r.data.resolve()?)?;
+
+                if let Some(reason) = self.license_violation(&metadata.licenses()) {
+                    violations.push(format!("  {} {}: {}", r.package, r.version, reason));
+                }
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "packages with disallowed licenses:\n{}",
+                violations.join("\n")
+            ))
+        }
+    }
+
+    /// Settings of packages deviating from the policy.
+    pub fn package_overrides(&self) -> impl Iterator<Item = &PackageOverride> {
+        self.package_overrides.values()
+    }
+
+    /// Set the settings of a package deviating from the policy.
+    ///
+    /// This replaces any override for the same package. When overrides for
+    /// a package and one of its parents both apply, the most specific wins.
+    pub fn set_package_override(&mut self, value: PackageOverride) -> Result<()> {
+        if value.package.is_empty() {
+            return Err(anyhow!("package override requires a package name"));
+        }
+
+        self.package_overrides.insert(value.package.clone(), value);
+
+        Ok(())
+    }
+
+    /// Find the override applying to a module or package name.
+    pub fn find_package_override(&self, name: &str) -> Option<&PackageOverride> {
+        self.package_overrides
+            .values()
+            .filter(|o| is_in_package(name, &o.package))
+            .max_by_key(|o| o.package.len())
+    }
+
+    /// Bytecode optimization levels of packages deviating from the policy.
+    pub fn package_optimize_levels(&self) -> impl Iterator<Item = (&str, &[i32])> {
+        self.package_optimize_levels
+            .iter()
+            .map(|(package, levels)| (package.as_str(), levels.as_slice()))
+    }
+
+    /// Set the bytecode optimization levels of a package and its submodules.
+    ///
+    /// This replaces any levels set for the same package. An empty list of
+    /// levels means no bytecode is generated for the package.
+    pub fn set_package_optimize_levels(&mut self, package: &str, levels: &[i32]) -> Result<()> {
+        if package.is_empty() {
+            return Err(anyhow!("package optimize levels require a package name"));
+        }
+
+        if let Some(level) = levels.iter().find(|level| !(0..=2).contains(*level)) {
+            return Err(anyhow!(
+                "invalid bytecode optimization level {}; must be 0, 1 or 2",
+                level
+            ));
+        }
+
+        let mut levels = levels.to_vec();
+        levels.sort_unstable();
+        levels.dedup();
+
+        self.package_optimize_levels
+            .insert(package.to_string(), levels);
+
+        Ok(())
+    }
+
+    /// Find the bytecode optimization levels applying to a module name.
+    ///
+    /// The levels of the most specific package containing the module win.
+    pub fn find_package_optimize_levels(&self, name: &str) -> Option<&[i32]> {
+        self.package_optimize_levels
+            .iter()
+            .filter(|(package, _)| is_in_package(name, package))
+            .max_by_key(|(package, _)| package.len())
+            .map(|(_, levels)| levels.as_slice())
+    }
+
+    /// Obtain the name of the preset defining standard library modules to exclude.
+    pub fn stdlib_profile(&self) -> Option<&str> {
+        self.stdlib_profile.as_deref()
+    }
+
+    /// Set the preset defining standard library modules to exclude.
+    ///
+    /// See the `stdlib_profiles` module for available presets.
+    pub fn set_stdlib_profile(&mut self, value: Option<&str>) -> Result<()> {
+        if let Some(name) = value {
+            if find_stdlib_profile(name).is_none() {
+                return Err(anyhow!(
+                    "unknown standard library profile: {} (known profiles: {})",
+                    name,
+                    stdlib_profile_names().join(", ")
+                ));
+            }
+        }
+
+        self.stdlib_profile = value.map(|x| x.to_string());
+
+        Ok(())
+    }
+
+    /// Whether a standard library module is excluded by the standard library profile.
+    pub fn is_stdlib_trimmed(&self, name: &str) -> bool {
+        self.stdlib_profile
+            .as_deref()
+            .and_then(find_stdlib_profile)
+            .map(|profile| profile.excludes(name))
+            .unwrap_or(false)
+    }
+
+    /// Standard library modules to exclude, along with their submodules.
+    pub fn excluded_modules(&self) -> &[String] {
+        &self.excluded_modules
+    }
+
+    /// Exclude a standard library module and its submodules.
+    ///
+    /// Unlike a standard library profile, this names individual modules.
+    /// Packaging fails if a packaged module imports an excluded module at
+    /// module level.
+    pub fn exclude_module(&mut self, name: &str) -> Result<()> {
+        if name.is_empty() || name.starts_with('.') || name.ends_with('.') {
+            return Err(anyhow!("invalid module name: {:?}", name));
+        }
+
+        if !self.excluded_modules.iter().any(|x| x == name) {
+            self.excluded_modules.push(name.to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Whether a standard library module is excluded by the standard library profile or `exclude_module()`.
+    pub fn is_stdlib_excluded(&self, name: &str) -> bool {
+        self.is_stdlib_trimmed(name)
+            || self
+                .excluded_modules
+                .iter()
+                .any(|module| is_in_package(name, module))
+    }
+
+    /// Obtain the location gettext catalogs are installed to.
+    pub fn gettext_catalogs_location(&self) -> Option<&ConcreteResourceLocation> {
+        self.gettext_catalogs_location.as_ref()
+    }
+
+    /// Set the location gettext catalogs are installed to.
+    ///
+    /// Catalogs are installed in a `locale` directory under the location
+    /// and their domains are bound to it at run-time. `gettext` can only
+    /// load catalogs from the filesystem, so the location must be a
+    /// filesystem path. If `None`, catalogs are handled like other package
+    /// resources.
+    pub fn set_gettext_catalogs_location(
+        &mut self,
+        location: Option<ConcreteResourceLocation>,
+    ) -> Result<()> {
+        if location == Some(ConcreteResourceLocation::InMemory) {
+            return Err(anyhow!(
+                "gettext catalogs cannot be loaded from memory; use a filesystem-relative location"
+            ));
+        }
+
+        self.gettext_catalogs_location = location;
+
+        Ok(())
+    }
+
+    /// Obtain the import trace used to locate resources.
+    pub fn import_trace(&self) -> Option<&ImportTrace> {
+        self.import_trace.as_ref()
+    }
+
+    /// Set the import trace used to locate resources.
+    ///
+    /// Resources of modules in the trace are hot and are loaded from memory,
+    /// falling back to the filesystem. Resources of other modules are cold
+    /// and are installed on the filesystem, keeping them out of the binary.
+    /// Package hints requiring the filesystem take precedence.
+    pub fn set_import_trace(&mut self, trace: Option<ImportTrace>) {
+        self.import_trace = trace;
+    }
+
+    /// Apply the packaging profile with the given name.
+    ///
+    /// This registers the profile's package hints and exclusions. See the
+    /// `profiles` module for available profiles.
+    pub fn apply_profile(&mut self, name: &str) -> Result<()> {
+        let profile = find_profile(name).ok_or_else(|| {
+            anyhow!(
+                "unknown packaging profile: {} (known profiles: {})",
+                name,
+                profile_names().join(", ")
+            )
+        })?;
+
+        for hint in profile.hints() {
+            self.register_package_hint(hint);
+        }
+        for path in profile.exclusions {
+            self.add_exclusion(path);
+        }
+
+        // The profiles' shared libraries locate each other on the filesystem.
+        self.apply_package_hints = true;
+        self.allow_in_memory_shared_library_loading = false;
+
+        Ok(())
+    }
+
     /// Set the resource handling mode of the policy.
     ///
     /// This is a convenience function for mapping a `ResourceHandlingMode`
@@ -395,6 +1153,11 @@ impl PythonPackagingPolicy {
         }
     }
 
+    /// Extensions marked as broken, keyed by target triple.
+    pub fn broken_extensions(&self) -> &HashMap<String, Vec<String>> {
+        &self.broken_extensions
+    }
+
     /// Obtain broken extensions for a target triple.
     pub fn broken_extensions_for_triple(&self, target_triple: &str) -> Option<&Vec<String>> {
         self.broken_extensions.get(target_triple)
@@ -421,9 +1184,22 @@ impl PythonPackagingPolicy {
         &self,
         resource: &PythonResource,
     ) -> PythonResourceAddCollectionContext {
-        let include = self.filter_python_resource(resource);
+        self.explain_add_collection_context(resource).0
+    }
 
-        let store_source = match resource {
+    /// Derive a `PythonResourceAddCollectionContext` and the rules deciding it.
+    pub fn explain_add_collection_context(
+        &self,
+        resource: &PythonResource,
+    ) -> (
+        PythonResourceAddCollectionContext,
+        AddCollectionContextRules,
+    ) {
+        let mut rules = AddCollectionContextRules::default();
+
+        let mut include = self.filter_python_resource(resource);
+
+        let mut store_source = match resource {
             PythonResource::ModuleSource(ref module) => {
                 if module.is_stdlib {
                     self.include_distribution_sources
@@ -434,17 +1210,240 @@ impl PythonPackagingPolicy {
             _ => false,
         };
 
-        let location = self.resources_location.clone();
-        let location_fallback = self.resources_location_fallback.clone();
+        let mut location = self.resources_location.clone();
+        let mut location_fallback = self.resources_location_fallback.clone();
+
+        if let Some(hot) = self
+            .import_trace
+            .as_ref()
+            .and_then(|trace| trace.is_hot(resource))
+        {
+            if hot {
+                location = ConcreteResourceLocation::InMemory;
+                location_fallback = Some(self.filesystem_location());
+            } else {
+                location = self.filesystem_location();
+                location_fallback = None;
+            }
+            rules.location = "import_trace".to_string();
+        }
+
+        let hint = match resource {
+            PythonResource::ModuleSource(module) => Some(&module.name),
+            PythonResource::ModuleBytecodeRequest(module) => Some(&module.name),
+            PythonResource::PackageResource(resource) => Some(&resource.leaf_package),
+            PythonResource::ExtensionModule(em) if !em.is_stdlib => Some(&em.name),
+            PythonResource::GettextCatalog(catalog) => Some(&catalog.leaf_package),
+            _ => None,
+        }
+        .and_then(|name| self.package_hints().and_then(|hints| hints.find(name)));
+
+        if let Some(hint) = hint {
+            if hint.requires_filesystem {
+                location = self.filesystem_location();
+                location_fallback = None;
+                rules.location = format!("package_hint:{}", hint.package);
+            }
+
+            if let PythonResource::PackageResource(resource) = resource {
+                if hint.include_resources && resource.is_stdlib {
+                    include = self.include_classified_resources
+                        && (self.include_test || !resource.is_test);
+                    rules.include = format!("package_hint:{}", hint.package);
+                }
+            }
+        }
+
+        if let PythonResource::GettextCatalog(_) = resource {
+            if let Some(gettext_location) = &self.gettext_catalogs_location {
+                location = gettext_location.clone();
+                location_fallback = None;
+                rules.location = "gettext_catalogs_location".to_string();
+            }
+        }
+
+        let package_override = match resource {
+            PythonResource::ModuleSource(module) => Some(&module.name),
+            PythonResource::ModuleBytecodeRequest(module) => Some(&module.name),
+            PythonResource::PackageResource(resource) => Some(&resource.leaf_package),
+            PythonResource::PackageDistributionResource(resource) => Some(&resource.package),
+            PythonResource::ExtensionModule(em) => Some(&em.name),
+            PythonResource::GettextCatalog(catalog) => Some(&catalog.leaf_package),
+            _ => None,
+        }
+        .and_then(|name| self.find_package_override(name));
+
+        if let Some(o) = package_override {
+            let rule = format!("package_override:{}", o.package);
+
+            if let Some(value) = o.include {
+                include = value;
+                rules.include = rule.clone();
+            }
+
+            if let (Some(value), PythonResource::ModuleSource(_)) = (o.include_sources, resource) {
+                store_source = value;
+                rules.store_source = rule.clone();
+            }
+
+            if let Some(value) = &o.resources_location {
+                location = value.clone();
+                location_fallback = o.resources_location_fallback.clone();
+                rules.location = rule;
+            }
+        }
+
+        if self.is_excluded(resource) {
+            include = false;
+            rules.include = "exclusions".to_string();
+        }
+
+        let stdlib_name = match resource {
+            PythonResource::ModuleSource(module) if module.is_stdlib => Some(&module.name),
+            PythonResource::ModuleBytecodeRequest(module) if module.is_stdlib => Some(&module.name),
+            PythonResource::PackageResource(resource) if resource.is_stdlib => {
+                Some(&resource.leaf_package)
+            }
+            PythonResource::ExtensionModule(em) if em.is_stdlib => Some(&em.name),
+            _ => None,
+        };
+
+        if let Some(name) = stdlib_name {
+            if self.is_stdlib_trimmed(name) {
+                include = false;
+                rules.include = "stdlib_profile".to_string();
+            } else if self.is_stdlib_excluded(name) {
+                include = false;
+                rules.include = "excluded_modules".to_string();
+            }
+        }
+
+        if let Some(matched) = self.matches_patterns(resource) {
+            include = matched;
+            rules.include = if matched {
+                "include_patterns"
+            } else {
+                "exclude_patterns"
+            }
+            .to_string();
+        }
+
+        let optimize_levels = match resource {
+            PythonResource::ModuleSource(module) => Some(&module.name),
+            PythonResource::ModuleBytecodeRequest(module) => Some(&module.name),
+            _ => None,
+        }
+        .and_then(|name| self.find_package_optimize_levels(name));
+
+        let (optimize_level_zero, optimize_level_one, optimize_level_two) = match optimize_levels {
+            Some(levels) => (
+                levels.contains(&0),
+                levels.contains(&1),
+                levels.contains(&2),
+            ),
+            None => (
+                self.bytecode_optimize_level_zero,
+                self.bytecode_optimize_level_one,
+                self.bytecode_optimize_level_two,
+            ),
+        };
+
+        (
+            PythonResourceAddCollectionContext {
+                include,
+                location,
+                location_fallback,
+                store_source,
+                optimize_level_zero,
+                optimize_level_one,
+                optimize_level_two,
+            },
+            rules,
+        )
+    }
+
+    /// The filesystem location resources are installed to when required.
+    ///
+    /// This is the configured filesystem location, if any, or `lib` next to
+    /// the built binary.
+    pub fn filesystem_location(&self) -> ConcreteResourceLocation {
+        match (&self.resources_location, &self.resources_location_fallback) {
+            (ConcreteResourceLocation::RelativePath(_), _) => self.resources_location.clone(),
+            (_, Some(ConcreteResourceLocation::RelativePath(prefix))) => {
+                ConcreteResourceLocation::RelativePath(prefix.clone())
+            }
+            _ => ConcreteResourceLocation::RelativePath("lib".to_string()),
+        }
+    }
+
+    /// Whether package hints require resources on the filesystem.
+    pub fn package_hints_require_filesystem(&self) -> bool {
+        self.package_hints()
+            .map(|hints| hints.iter().any(|hint| hint.requires_filesystem))
+            .unwrap_or(false)
+    }
+
+    /// Whether a resource is at or below a path in `exclusions`.
+    fn is_excluded(&self, resource: &PythonResource) -> bool {
+        if self.exclusions.is_empty() {
+            return false;
+        }
+
+        let path = match resource {
+            PythonResource::ModuleSource(module) => module.name.replace('.', "/"),
+            PythonResource::ModuleBytecodeRequest(module) => module.name.replace('.', "/"),
+            PythonResource::ModuleBytecode(module) => module.name.replace('.', "/"),
+            PythonResource::ExtensionModule(em) => em.name.replace('.', "/"),
+            PythonResource::PackageResource(resource) => format!(
+                "{}/{}",
+                resource.leaf_package.replace('.', "/"),
+                resource.relative_name
+            ),
+            PythonResource::File(file) => file.path.display().to_string().replace('\\', "/"),
+            PythonResource::GettextCatalog(catalog) => format!(
+                "{}/{}",
+                catalog.leaf_package.replace('.', "/"),
+                catalog.relative_name
+            ),
+            _ => return false,
+        };
+
+        self.exclusions.iter().any(|exclusion| {
+            path == *exclusion
+                || (path.starts_with(exclusion.as_str())
+                    && path.as_bytes().get(exclusion.len()) == Some(&b'/'))
+        })
+    }
 
-        PythonResourceAddCollectionContext {
-            include,
-            location,
-            location_fallback,
-            store_source,
-            optimize_level_zero: self.bytecode_optimize_level_zero,
-            optimize_level_one: self.bytecode_optimize_level_one,
-            optimize_level_two: self.bytecode_optimize_level_two,
+    /// Whether a resource is included or excluded by glob patterns.
+    ///
+    /// Returns `None` if no pattern matches.
+    fn matches_patterns(&self, resource: &PythonResource) -> Option<bool> {
+        if self.include_patterns.is_empty() && self.exclude_patterns.is_empty() {
+            return None;
+        }
+
+        let name = match resource {
+            // Bytecode is derived from requests, which the patterns apply to.
+            PythonResource::ModuleBytecode(_) => return None,
+            PythonResource::EggFile(_) | PythonResource::PathExtension(_) => return None,
+            resource => resource.full_name().replace('\\', "/"),
+        };
+
+        if self
+            .include_patterns
+            .iter()
+            .any(|pattern| matches_pattern(pattern, &name))
+        {
+            Some(true)
+        } else if self
+            .exclude_patterns
+            .iter()
+            .any(|pattern| matches_pattern(pattern, &name))
+        {
+            Some(false)
+        } else {
+            None
         }
     }
 
@@ -494,7 +1493,31 @@ impl PythonPackagingPolicy {
             PythonResource::PathExtension(_) => false,
             PythonResource::EggFile(_) => false,
             PythonResource::File(_) => true,
+            PythonResource::GettextCatalog(_) => true,
+        }
+    }
+
+    /// Choose the variant of an extension module to use.
+    ///
+    /// An explicitly preferred variant always wins. Otherwise, a variant
+    /// targeting the stable ABI is chosen if `prefer_abi3_extensions` is set.
+    fn choose_extension_module_variant<'a>(
+        &self,
+        variants: &'a PythonExtensionModuleVariants,
+    ) -> &'a PythonExtensionModule {
+        let default = variants.default_variant();
+
+        if self.prefer_abi3_extensions
+            && !self
+                .preferred_extension_module_variants
+                .contains_key(&default.name)
+        {
+            if let Some(em) = variants.iter().find(|em| em.is_stable_abi()) {
+                return em;
+            }
         }
+
+        variants.choose_variant(&self.preferred_extension_module_variants)
     }
 
     /// Resolve Python extension modules that are compliant with the policy.
@@ -519,6 +1542,14 @@ impl PythonPackagingPolicy {
                 continue;
             }
 
+            // The extension is trimmed from the standard library.
+            if variants.default_variant().is_stdlib
+                && !variants.default_variant().is_minimally_required()
+                && self.is_stdlib_excluded(name)
+            {
+                continue;
+            }
+
             // Always add minimally required extension modules, because things don't
             // work if we don't do this.
             let ext_variants =
@@ -531,11 +1562,7 @@ impl PythonPackagingPolicy {
                 }));
 
             if !ext_variants.is_empty() {
-                res.push(
-                    ext_variants
-                        .choose_variant(&self.preferred_extension_module_variants)
-                        .clone(),
-                );
+                res.push(self.choose_extension_module_variant(&ext_variants).clone());
             }
 
             match self.extension_module_filter {
@@ -543,11 +1570,7 @@ impl PythonPackagingPolicy {
                 ExtensionModuleFilter::Minimal => {}
 
                 ExtensionModuleFilter::All => {
-                    res.push(
-                        variants
-                            .choose_variant(&self.preferred_extension_module_variants)
-                            .clone(),
-                    );
+                    res.push(self.choose_extension_module_variant(variants).clone());
                 }
 
                 ExtensionModuleFilter::NoLibraries => {
@@ -562,11 +1585,7 @@ impl PythonPackagingPolicy {
                     );
 
                     if !ext_variants.is_empty() {
-                        res.push(
-                            ext_variants
-                                .choose_variant(&self.preferred_extension_module_variants)
-                                .clone(),
-                        );
+                        res.push(self.choose_extension_module_variant(&ext_variants).clone());
                     }
                 }
 
@@ -602,11 +1621,7 @@ impl PythonPackagingPolicy {
                     );
 
                     if !ext_variants.is_empty() {
-                        res.push(
-                            ext_variants
-                                .choose_variant(&self.preferred_extension_module_variants)
-                                .clone(),
-                        );
+                        res.push(self.choose_extension_module_variant(&ext_variants).clone());
                     }
                 }
             }
@@ -620,8 +1635,12 @@ impl PythonPackagingPolicy {
 mod tests {
     use {
         super::*,
-        crate::resource::{DataLocation, FileData},
-        std::path::PathBuf,
+        crate::resource::{
+            DataLocation, FileData, GettextCatalog, PythonModuleSource,
+            PythonPackageDistributionResource, PythonPackageDistributionResourceFlavor,
+            PythonPackageResource,
+        },
+        std::{iter::FromIterator, path::PathBuf},
     };
 
     #[test]
@@ -644,4 +1663,626 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_python_security_baseline() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+        assert_eq!(policy.python_security_baseline(), None);
+
+        policy.set_python_security_baseline(Some("2021-01-01"))?;
+        assert_eq!(policy.python_security_baseline(), Some("2021-01-01"));
+
+        assert!(policy
+            .set_python_security_baseline(Some("2021-1-1"))
+            .is_err());
+        assert!(policy
+            .set_python_security_baseline(Some("yesterday"))
+            .is_err());
+        assert_eq!(policy.python_security_baseline(), Some("2021-01-01"));
+
+        policy.set_python_security_baseline(None)?;
+        assert_eq!(policy.python_security_baseline(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prefer_abi3_extensions() -> Result<()> {
+        let em = PythonExtensionModule {
+            name: "foo".to_string(),
+            init_fn: None,
+            extension_file_suffix: ".cpython-38-x86_64-linux-gnu.so".to_string(),
+            shared_library: None,
+            object_file_data: vec![],
+            is_package: false,
+            link_libraries: vec![],
+            is_stdlib: false,
+            builtin_default: false,
+            required: false,
+            variant: Some("default".to_string()),
+            licenses: None,
+            license_public_domain: None,
+        };
+        let mut em_abi3 = em.clone();
+        em_abi3.extension_file_suffix = ".abi3.so".to_string();
+        em_abi3.variant = Some("abi3".to_string());
+
+        let variants = PythonExtensionModuleVariants::from_iter(vec![em.clone(), em_abi3.clone()]);
+
+        let mut policy = PythonPackagingPolicy::default();
+        let res = policy.resolve_python_extension_modules(
+            vec![&variants].into_iter(),
+            "x86_64-unknown-linux-gnu",
+        )?;
+        assert_eq!(res, vec![em.clone()]);
+
+        policy.set_prefer_abi3_extensions(true);
+        let res = policy.resolve_python_extension_modules(
+            vec![&variants].into_iter(),
+            "x86_64-unknown-linux-gnu",
+        )?;
+        assert_eq!(res, vec![em_abi3]);
+
+        // An explicit variant preference overrides the abi3 preference.
+        policy.set_preferred_extension_module_variant("foo", "default");
+        let res = policy.resolve_python_extension_modules(
+            vec![&variants].into_iter(),
+            "x86_64-unknown-linux-gnu",
+        )?;
+        assert_eq!(res, vec![em]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_package_hints() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+        assert!(policy.apply_package_hints());
+        assert!(policy.package_hints_require_filesystem());
+
+        let module = PythonModuleSource {
+            name: "certifi.core".to_string(),
+            source: DataLocation::Memory(vec![]),
+            is_package: false,
+            cache_tag: "cpython-38".to_string(),
+            is_stdlib: false,
+            is_test: false,
+        };
+
+        let add_context = policy.derive_add_collection_context(&module.clone().into());
+        assert_eq!(
+            add_context.location,
+            ConcreteResourceLocation::RelativePath("lib".to_string())
+        );
+        assert_eq!(add_context.location_fallback, None);
+
+        policy.set_resources_location_fallback(Some(ConcreteResourceLocation::RelativePath(
+            "prefix".to_string(),
+        )));
+        let add_context = policy.derive_add_collection_context(&module.clone().into());
+        assert_eq!(
+            add_context.location,
+            ConcreteResourceLocation::RelativePath("prefix".to_string())
+        );
+
+        let resource = PythonPackageResource {
+            leaf_package: "lib2to3".to_string(),
+            relative_name: "Grammar.txt".to_string(),
+            data: DataLocation::Memory(vec![]),
+            is_stdlib: true,
+            is_test: false,
+        };
+        assert!(!policy.include_distribution_resources());
+        assert!(
+            policy
+                .derive_add_collection_context(&resource.clone().into())
+                .include
+        );
+
+        policy.set_apply_package_hints(false);
+        assert!(policy.package_hints().is_none());
+        let add_context = policy.derive_add_collection_context(&module.clone().into());
+        assert_eq!(add_context.location, ConcreteResourceLocation::InMemory);
+        assert!(
+            !policy
+                .derive_add_collection_context(&resource.into())
+                .include
+        );
+
+        policy.set_apply_package_hints(true);
+        policy.register_package_hint(PackageHint {
+            package: "certifi".to_string(),
+            ..Default::default()
+        });
+        let add_context = policy.derive_add_collection_context(&module.into());
+        assert_eq!(add_context.location, ConcreteResourceLocation::InMemory);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stdlib_profile() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+        assert!(!policy.is_stdlib_trimmed("tkinter"));
+        assert!(policy.set_stdlib_profile(Some("everything")).is_err());
+
+        policy.set_stdlib_profile(Some("no-gui"))?;
+        assert_eq!(policy.stdlib_profile(), Some("no-gui"));
+        assert!(policy.is_stdlib_trimmed("tkinter.ttk"));
+
+        let mut module = PythonModuleSource {
+            name: "tkinter.ttk".to_string(),
+            source: DataLocation::Memory(vec![]),
+            is_package: false,
+            cache_tag: "cpython-38".to_string(),
+            is_stdlib: true,
+            is_test: false,
+        };
+        assert!(
+            !policy
+                .derive_add_collection_context(&module.clone().into())
+                .include
+        );
+
+        // Only the standard library is trimmed.
+        module.is_stdlib = false;
+        assert!(policy.derive_add_collection_context(&module.into()).include);
+
+        let em = PythonExtensionModule {
+            name: "_tkinter".to_string(),
+            init_fn: Some("PyInit__tkinter".to_string()),
+            extension_file_suffix: ".so".to_string(),
+            shared_library: None,
+            object_file_data: vec![],
+            is_package: false,
+            link_libraries: vec![],
+            is_stdlib: true,
+            builtin_default: false,
+            required: false,
+            variant: None,
+            licenses: None,
+            license_public_domain: None,
+        };
+        let variants = PythonExtensionModuleVariants::from_iter(vec![em]);
+
+        let res = policy.resolve_python_extension_modules(
+            vec![&variants].into_iter(),
+            "x86_64-unknown-linux-gnu",
+        )?;
+        assert!(res.is_empty());
+
+        policy.set_stdlib_profile(None)?;
+        let res = policy.resolve_python_extension_modules(
+            vec![&variants].into_iter(),
+            "x86_64-unknown-linux-gnu",
+        )?;
+        assert_eq!(res.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exclude_module() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+        assert!(policy.exclude_module("").is_err());
+        assert!(policy.exclude_module("tkinter.").is_err());
+
+        policy.exclude_module("tkinter")?;
+        policy.exclude_module("tkinter")?;
+        assert_eq!(policy.excluded_modules(), &["tkinter".to_string()]);
+        assert!(policy.is_stdlib_excluded("tkinter.ttk"));
+        assert!(!policy.is_stdlib_excluded("tkinterx"));
+        assert!(!policy.is_stdlib_trimmed("tkinter"));
+
+        let mut module = PythonModuleSource {
+            name: "tkinter.ttk".to_string(),
+            source: DataLocation::Memory(vec![]),
+            is_package: false,
+            cache_tag: "cpython-38".to_string(),
+            is_stdlib: true,
+            is_test: false,
+        };
+        let (context, rules) = policy.explain_add_collection_context(&module.clone().into());
+        assert!(!context.include);
+        assert_eq!(rules.include, "excluded_modules");
+
+        // Only the standard library is excluded.
+        module.is_stdlib = false;
+        assert!(policy.derive_add_collection_context(&module.into()).include);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("foo.tests.*", "foo.tests"));
+        assert!(matches_pattern("foo.tests.*", "foo.tests.test_bar"));
+        assert!(matches_pattern(
+            "foo.tests.*",
+            "foo.tests.data.fixture.json"
+        ));
+        assert!(!matches_pattern("foo.tests.*", "foo.testsuite"));
+        assert!(!matches_pattern("foo.tests.*", "foo"));
+        assert!(matches_pattern("*.tests", "foo.bar.tests"));
+        assert!(matches_pattern("foo.*.tests.*", "foo.bar.tests.baz"));
+        assert!(matches_pattern("foo.ba?", "foo.bar"));
+        assert!(!matches_pattern("foo.ba?", "foo.ba"));
+        assert!(matches_pattern("*", "foo"));
+        assert!(matches_pattern("foo", "foo"));
+        assert!(!matches_pattern("foo", "foobar"));
+    }
+
+    #[test]
+    fn test_include_exclude_patterns() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+        assert!(policy.add_exclude_pattern("").is_err());
+        policy.add_exclude_pattern("foo.*")?;
+        policy.add_exclude_pattern("foo.*")?;
+        policy.add_include_pattern("foo.core.*")?;
+        assert_eq!(policy.exclude_patterns(), &["foo.*".to_string()]);
+        assert_eq!(policy.include_patterns(), &["foo.core.*".to_string()]);
+
+        let module = |name: &str, is_test: bool| -> PythonResource {
+            PythonModuleSource {
+                name: name.to_string(),
+                source: DataLocation::Memory(vec![]),
+                is_package: false,
+                cache_tag: "cpython-38".to_string(),
+                is_stdlib: false,
+                is_test,
+            }
+            .into()
+        };
+
+        assert!(
+            !policy
+                .derive_add_collection_context(&module("foo", false))
+                .include
+        );
+        assert!(
+            !policy
+                .derive_add_collection_context(&module("foo.bar", false))
+                .include
+        );
+        assert!(
+            policy
+                .derive_add_collection_context(&module("foo.core.x", false))
+                .include
+        );
+        assert!(
+            policy
+                .derive_add_collection_context(&module("bar", false))
+                .include
+        );
+
+        // Include patterns override other settings.
+        assert!(
+            !policy
+                .derive_add_collection_context(&module("bar.tests", true))
+                .include
+        );
+        policy.add_include_pattern("bar.tests")?;
+        assert!(
+            policy
+                .derive_add_collection_context(&module("bar.tests", true))
+                .include
+        );
+
+        let resource = PythonPackageResource {
+            leaf_package: "foo".to_string(),
+            relative_name: "data/x.txt".to_string(),
+            data: DataLocation::Memory(vec![]),
+            is_stdlib: false,
+            is_test: false,
+        };
+        policy.include_distribution_resources = true;
+        assert!(
+            !policy
+                .derive_add_collection_context(&resource.into())
+                .include
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_licenses() -> Result<()> {
+        let metadata = |package: &str, license: &str| {
+            PythonResource::from(PythonPackageDistributionResource {
+                location: PythonPackageDistributionResourceFlavor::DistInfo,
+                package: package.to_string(),
+                version: "1.0".to_string(),
+                name: "METADATA".to_string(),
+                data: DataLocation::Memory(
+                    format!("Name: {}\nLicense: {}\n\n", package, license).into_bytes(),
+                ),
+            })
+        };
+        let resources = vec![metadata("foo", "MIT"), metadata("bar", "GPL-3.0")];
+
+        let mut policy = PythonPackagingPolicy::default();
+        assert!(policy.check_package_licenses(&resources).is_ok());
+
+        policy.set_denied_licenses(vec!["gpl*".to_string()])?;
+        assert_eq!(
+            policy.license_violation(&["GPL-3.0"]),
+            Some("license GPL-3.0 matches denied license gpl*".to_string())
+        );
+        assert_eq!(policy.license_violation(&["MIT"]), None);
+        assert_eq!(policy.license_violation(&[]), None);
+        assert_eq!(policy.license_violation(&["MIT OR GPL-3.0"]), None);
+        assert_eq!(
+            policy.license_violation(&["MIT AND GPL-3.0"]),
+            Some("license MIT AND GPL-3.0 matches denied license gpl*".to_string())
+        );
+        assert!(policy
+            .license_violation(&["GPL-2.0-only WITH Classpath-exception-2.0"])
+            .is_some());
+        assert!(policy
+            .license_violation(&["GNU General Public License v3 (GPLv3)"])
+            .is_none());
+        let err = policy.check_package_licenses(&resources).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "packages with disallowed licenses:\n  bar 1.0: license GPL-3.0 matches denied license gpl*"
+        );
+
+        policy.set_denied_licenses(vec![])?;
+        policy.set_allowed_licenses(vec!["MIT".to_string(), "BSD*".to_string()])?;
+        assert_eq!(policy.license_violation(&["GPL-3.0", "BSD License"]), None);
+        assert!(policy.license_violation(&["GPL-3.0"]).is_some());
+        assert!(policy.license_violation(&[]).is_some());
+        assert_eq!(policy.license_violation(&["(GPL-3.0 OR MIT)"]), None);
+        assert_eq!(policy.license_violation(&["BSD-3-Clause AND MIT"]), None);
+        assert!(policy.license_violation(&["MIT AND GPL-3.0"]).is_some());
+        assert!(policy.check_package_licenses(&resources[0..1]).is_ok());
+        assert!(policy.check_package_licenses(&resources).is_err());
+
+        assert!(policy.set_allowed_licenses(vec!["".to_string()]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_package_optimize_levels() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+        policy.set_bytecode_optimize_level_zero(false);
+        policy.set_bytecode_optimize_level_two(true);
+
+        assert!(policy.set_package_optimize_levels("", &[0]).is_err());
+        assert!(policy.set_package_optimize_levels("mypkg", &[3]).is_err());
+
+        policy.set_package_optimize_levels("mypkg", &[1, 0, 0])?;
+        policy.set_package_optimize_levels("mypkg.fast", &[2])?;
+        assert_eq!(
+            policy.package_optimize_levels().collect::<Vec<_>>(),
+            vec![("mypkg", &[0, 1][..]), ("mypkg.fast", &[2][..])]
+        );
+
+        let module = |name: &str| -> PythonResource {
+            PythonModuleSource {
+                name: name.to_string(),
+                source: DataLocation::Memory(vec![]),
+                is_package: false,
+                cache_tag: "cpython-38".to_string(),
+                is_stdlib: false,
+                is_test: false,
+            }
+            .into()
+        };
+
+        let context = policy.derive_add_collection_context(&module("mypkg.debug"));
+        assert!(context.optimize_level_zero);
+        assert!(context.optimize_level_one);
+        assert!(!context.optimize_level_two);
+
+        // The most specific package applies.
+        let context = policy.derive_add_collection_context(&module("mypkg.fast.core"));
+        assert!(!context.optimize_level_zero);
+        assert!(!context.optimize_level_one);
+        assert!(context.optimize_level_two);
+
+        let context = policy.derive_add_collection_context(&module("requests"));
+        assert!(!context.optimize_level_zero);
+        assert!(!context.optimize_level_one);
+        assert!(context.optimize_level_two);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_package_overrides() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+        policy.set_resources_location_fallback(Some(ConcreteResourceLocation::RelativePath(
+            "prefix".to_string(),
+        )));
+        assert!(policy
+            .set_package_override(PackageOverride::default())
+            .is_err());
+
+        policy.set_package_override(PackageOverride {
+            package: "numpy".to_string(),
+            include_sources: Some(false),
+            resources_location: Some(ConcreteResourceLocation::RelativePath("lib".to_string())),
+            ..PackageOverride::default()
+        })?;
+        policy.set_package_override(PackageOverride {
+            package: "numpy.tests".to_string(),
+            include: Some(false),
+            ..PackageOverride::default()
+        })?;
+        assert_eq!(policy.package_overrides().count(), 2);
+
+        let module = |name: &str| -> PythonResource {
+            PythonModuleSource {
+                name: name.to_string(),
+                source: DataLocation::Memory(vec![]),
+                is_package: false,
+                cache_tag: "cpython-38".to_string(),
+                is_stdlib: false,
+                is_test: false,
+            }
+            .into()
+        };
+
+        let context = policy.derive_add_collection_context(&module("numpy.core"));
+        assert!(context.include);
+        assert!(!context.store_source);
+        assert_eq!(
+            context.location,
+            ConcreteResourceLocation::RelativePath("lib".to_string())
+        );
+        assert_eq!(context.location_fallback, None);
+
+        // The most specific override applies.
+        let context = policy.derive_add_collection_context(&module("numpy.tests.foo"));
+        assert!(!context.include);
+        assert!(context.store_source);
+        assert_eq!(context.location, ConcreteResourceLocation::InMemory);
+
+        let context = policy.derive_add_collection_context(&module("numpyx"));
+        assert!(context.include);
+        assert!(context.store_source);
+        assert_eq!(context.location, ConcreteResourceLocation::InMemory);
+        assert_eq!(
+            context.location_fallback,
+            Some(ConcreteResourceLocation::RelativePath("prefix".to_string()))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain_add_collection_context() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+        policy.set_package_override(PackageOverride {
+            package: "numpy".to_string(),
+            include_sources: Some(false),
+            resources_location: Some(ConcreteResourceLocation::RelativePath("lib".to_string())),
+            ..PackageOverride::default()
+        })?;
+        policy.add_exclusion("numpy/tests");
+        policy.add_include_pattern("numpy.tests.keep")?;
+
+        let module = |name: &str| -> PythonResource {
+            PythonModuleSource {
+                name: name.to_string(),
+                source: DataLocation::Memory(vec![]),
+                is_package: false,
+                cache_tag: "cpython-38".to_string(),
+                is_stdlib: false,
+                is_test: false,
+            }
+            .into()
+        };
+
+        let (context, rules) = policy.explain_add_collection_context(&module("foo"));
+        assert_eq!(
+            context,
+            policy.derive_add_collection_context(&module("foo"))
+        );
+        assert_eq!(rules, AddCollectionContextRules::default());
+
+        let (context, rules) = policy.explain_add_collection_context(&module("numpy.tests.foo"));
+        assert!(!context.include);
+        assert_eq!(
+            rules,
+            AddCollectionContextRules {
+                include: "exclusions".to_string(),
+                location: "package_override:numpy".to_string(),
+                store_source: "package_override:numpy".to_string(),
+            }
+        );
+
+        let (context, rules) = policy.explain_add_collection_context(&module("numpy.tests.keep"));
+        assert!(context.include);
+        assert_eq!(rules.include, "include_patterns");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_gettext_catalogs_location() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+        assert!(policy
+            .set_gettext_catalogs_location(Some(ConcreteResourceLocation::InMemory))
+            .is_err());
+
+        let catalog = GettextCatalog {
+            leaf_package: "foo".to_string(),
+            relative_name: "locale/de/LC_MESSAGES/foo.mo".to_string(),
+            locale: "de".to_string(),
+            domain: "foo".to_string(),
+            data: DataLocation::Memory(vec![]),
+        };
+
+        let add_context = policy.derive_add_collection_context(&catalog.clone().into());
+        assert!(add_context.include);
+        assert_eq!(add_context.location, ConcreteResourceLocation::InMemory);
+
+        policy.set_gettext_catalogs_location(Some(ConcreteResourceLocation::RelativePath(
+            "share".to_string(),
+        )))?;
+        policy.set_resources_location_fallback(Some(ConcreteResourceLocation::RelativePath(
+            "lib".to_string(),
+        )));
+
+        let add_context = policy.derive_add_collection_context(&catalog.into());
+        assert_eq!(
+            add_context.location,
+            ConcreteResourceLocation::RelativePath("share".to_string())
+        );
+        assert_eq!(add_context.location_fallback, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_trace() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+        policy.set_import_trace(Some(ImportTrace::from_importtime_output(
+            "import time: 10 | 10 | foo",
+        )?));
+
+        let module = PythonModuleSource {
+            name: "foo".to_string(),
+            source: DataLocation::Memory(vec![]),
+            is_package: false,
+            cache_tag: "cpython-38".to_string(),
+            is_stdlib: false,
+            is_test: false,
+        };
+
+        let add_context = policy.derive_add_collection_context(&module.clone().into());
+        assert_eq!(add_context.location, ConcreteResourceLocation::InMemory);
+        assert_eq!(
+            add_context.location_fallback,
+            Some(ConcreteResourceLocation::RelativePath("lib".to_string()))
+        );
+
+        let cold = PythonModuleSource {
+            name: "bar".to_string(),
+            ..module.clone()
+        };
+
+        let add_context = policy.derive_add_collection_context(&cold.into());
+        assert_eq!(
+            add_context.location,
+            ConcreteResourceLocation::RelativePath("lib".to_string())
+        );
+        assert_eq!(add_context.location_fallback, None);
+
+        policy.register_package_hint(PackageHint {
+            package: "foo".to_string(),
+            requires_filesystem: true,
+            ..PackageHint::default()
+        });
+        let add_context = policy.derive_add_collection_context(&module.into());
+        assert_eq!(
+            add_context.location,
+            ConcreteResourceLocation::RelativePath("lib".to_string())
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,295 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Packaging profiles for families of packages with demanding requirements.
+
+Large machine learning packages like PyTorch and TensorFlow ship dozens of
+shared libraries which locate each other relative to the package directory,
+plus C++ headers and build files which are only needed to compile against
+them. A profile bundles the package hints and exclusions such packages need,
+so a packaging policy can accommodate them with a single call.
+*/
+
+use crate::package_hints::{BuiltinHint, PackageHint};
+
+/// A named set of packaging adjustments for a family of packages.
+pub struct PackagingProfile {
+    /// Name the profile is applied by.
+    pub name: &'static str,
+
+    /// Hints for the packages the profile covers.
+    hints: &'static [BuiltinHint],
+
+    /// Resources that are never needed at run-time.
+    ///
+    /// Paths are relative to the package root, e.g. `torch/include`. See
+    /// `PythonPackagingPolicy::add_exclusion()`.
+    pub exclusions: &'static [&'static str],
+}
+
+impl PackagingProfile {
+    /// Obtain the package hints of this profile.
+    pub fn hints(&self) -> impl Iterator<Item = PackageHint> {
+        self.hints.iter().map(|hint| hint.to_hint())
+    }
+}
+
+/// Profiles applied by `PythonPackagingPolicy::apply_profile()`.
+const PROFILES: &[PackagingProfile] = &[
+    PackagingProfile {
+        name: "pytorch",
+        hints: &[
+            BuiltinHint {
+                package: "torch",
+                hidden_imports: &[],
+                // Shared libraries in `torch/lib` are loaded relative to
+                // `__file__` and TorchScript reads module source files.
+                requires_filesystem: true,
+                include_resources: false,
+            },
+            BuiltinHint {
+                package: "functorch",
+                hidden_imports: &[],
+                requires_filesystem: true,
+                include_resources: false,
+            },
+            BuiltinHint {
+                package: "torchaudio",
+                hidden_imports: &[],
+                // Native operators are registered via `torch.ops.load_library()`.
+                requires_filesystem: true,
+                include_resources: false,
+            },
+            BuiltinHint {
+                package: "torchvision",
+                hidden_imports: &[],
+                requires_filesystem: true,
+                include_resources: false,
+            },
+        ],
+        exclusions: &[
+            "torch/include",
+            "torch/share",
+            "torchaudio/include",
+            "torchvision/include",
+        ],
+    },
+    PackagingProfile {
+        name: "tensorflow",
+        hints: &[
+            BuiltinHint {
+                package: "tensorflow",
+                // The public API modules are loaded lazily.
+                hidden_imports: &["tensorflow._api", "tensorflow.python"],
+                // `libtensorflow_framework` is loaded relative to `__file__`.
+                requires_filesystem: true,
+                include_resources: false,
+            },
+            BuiltinHint {
+                package: "tensorflow_core",
+                hidden_imports: &["tensorflow_core._api", "tensorflow_core.python"],
+                requires_filesystem: true,
+                include_resources: false,
+            },
+            BuiltinHint {
+                package: "tensorflow_io_gcs_filesystem",
+                hidden_imports: &[],
+                requires_filesystem: true,
+                include_resources: false,
+            },
+        ],
+        exclusions: &["tensorflow/include", "tensorflow_core/include"],
+    },
+];
+
+/// Find a profile by name.
+pub fn find_profile(name: &str) -> Option<&'static PackagingProfile> {
+    PROFILES.iter().find(|profile| profile.name == name)
+}
+
+/// Names of all profiles.
+pub fn profile_names() -> Vec<&'static str> {
+    PROFILES.iter().map(|profile| profile.name).collect()
+}
+
+#[cfg(all(test, feature = "wheel"))]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            location::ConcreteResourceLocation, module_util::PythonModuleSuffixes,
+            policy::PythonPackagingPolicy, resource::PythonResource, wheel::WheelArchive,
+        },
+        anyhow::Result,
+        std::{collections::BTreeSet, io::Write},
+    };
+
+    /// Build a wheel holding empty files at the given paths.
+    fn wheel_resources(basename: &str, paths: &[&str]) -> Result<Vec<PythonResource<'static>>> {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for path in paths {
+            writer.start_file(*path, zip::write::FileOptions::default())?;
+            writer.write_all(b"")?;
+        }
+        let reader = writer.finish()?;
+
+        let suffixes = PythonModuleSuffixes {
+            source: vec![".py".to_string()],
+            bytecode: vec![".pyc".to_string()],
+            debug_bytecode: vec![],
+            optimized_bytecode: vec![],
+            extension: vec![
+                ".cpython-38-x86_64-linux-gnu.so".to_string(),
+                ".abi3.so".to_string(),
+                ".so".to_string(),
+            ],
+        };
+
+        WheelArchive::from_reader(reader, basename)?.python_resources(
+            "cpython-38",
+            &suffixes,
+            false,
+            true,
+        )
+    }
+
+    /// Derive `(name, include, location)` for each resource.
+    fn derive(
+        policy: &PythonPackagingPolicy,
+        resources: &[PythonResource],
+    ) -> Vec<(String, bool, ConcreteResourceLocation)> {
+        resources
+            .iter()
+            .filter(|r| !matches!(r, PythonResource::PackageDistributionResource(_)))
+            .map(|r| {
+                let context = policy.derive_add_collection_context(r);
+                (r.full_name(), context.include, context.location)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_profile_names() {
+        assert_eq!(profile_names(), vec!["pytorch", "tensorflow"]);
+
+        for name in profile_names() {
+            let profile = find_profile(name).unwrap();
+            assert!(profile.hints().count() > 0);
+
+            // Exclusions must be within a package the profile covers.
+            for exclusion in profile.exclusions {
+                let package = exclusion.split('/').next().unwrap();
+                assert!(profile.hints().any(|hint| hint.package == package));
+            }
+        }
+
+        assert!(find_profile("jax").is_none());
+    }
+
+    #[test]
+    fn test_pytorch_wheel() -> Result<()> {
+        let resources = wheel_resources(
+            "torch-1.7.0-cp38-cp38-linux_x86_64.whl",
+            &[
+                "torch/__init__.py",
+                "torch/nn/__init__.py",
+                "torch/_C.cpython-38-x86_64-linux-gnu.so",
+                "torch/lib/libgomp-a34b3233.so.1",
+                "torch/include/ATen/ATen.h",
+                "torch/share/cmake/Torch/TorchConfig.cmake",
+                "torch-1.7.0.dist-info/METADATA",
+                "torch-1.7.0.dist-info/WHEEL",
+                "six.py",
+            ],
+        )?;
+
+        let mut policy = PythonPackagingPolicy::default();
+        let before = derive(&policy, &resources);
+        assert!(before
+            .iter()
+            .all(|(_, _, location)| location == &ConcreteResourceLocation::InMemory));
+
+        policy.apply_profile("pytorch")?;
+        assert!(!policy.allow_in_memory_shared_library_loading());
+
+        let lib = ConcreteResourceLocation::RelativePath("lib".to_string());
+        for (name, include, location) in derive(&policy, &resources) {
+            match name.as_str() {
+                "torch" | "torch.nn" | "torch.lib/libgomp-a34b3233.so.1" => {
+                    assert!(include, "{} is included", name);
+                    assert_eq!(location, lib, "{} is on the filesystem", name);
+                }
+                // Extension modules are included regardless of `include`.
+                "torch._C" => {
+                    assert_eq!(location, lib);
+                }
+                "torch.include/ATen/ATen.h" | "torch.share/cmake/Torch/TorchConfig.cmake" => {
+                    assert!(!include, "{} is excluded", name);
+                }
+                "six" => {
+                    assert!(include);
+                    assert_eq!(location, ConcreteResourceLocation::InMemory);
+                }
+                _ => panic!("unexpected resource {}", name),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tensorflow_wheel() -> Result<()> {
+        let resources = wheel_resources(
+            "tensorflow-2.3.0-cp38-cp38-manylinux2010_x86_64.whl",
+            &[
+                "tensorflow/__init__.py",
+                "tensorflow/_api/__init__.py",
+                "tensorflow/python/__init__.py",
+                "tensorflow/python/_pywrap_tensorflow_internal.so",
+                "tensorflow/libtensorflow_framework.so.2",
+                "tensorflow/include/tensorflow/core/framework/tensor.h",
+                "tensorflow-2.3.0.dist-info/METADATA",
+            ],
+        )?;
+
+        let mut policy = PythonPackagingPolicy::default();
+        policy.apply_profile("tensorflow")?;
+
+        let lib = ConcreteResourceLocation::RelativePath("lib".to_string());
+        for (name, include, location) in derive(&policy, &resources) {
+            if name.starts_with("tensorflow.include/") {
+                assert!(!include, "{} is excluded", name);
+            } else {
+                assert!(
+                    include || name == "tensorflow.python._pywrap_tensorflow_internal",
+                    "{} is included",
+                    name
+                );
+                assert_eq!(location, lib, "{} is on the filesystem", name);
+            }
+        }
+
+        let names = ["tensorflow".to_string()]
+            .iter()
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        let hints = policy.package_hints().unwrap();
+        assert!(hints.is_hidden_import(&names, "tensorflow.python"));
+        assert!(hints.is_hidden_import(&names, "tensorflow._api.v2"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_profile() {
+        let mut policy = PythonPackagingPolicy::default();
+        let err = policy.apply_profile("jax").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown packaging profile: jax (known profiles: pytorch, tensorflow)"
+        );
+        assert_eq!(policy, PythonPackagingPolicy::default());
+    }
+}
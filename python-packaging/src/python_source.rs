@@ -9,6 +9,9 @@ use {anyhow::Result, lazy_static::lazy_static};
 lazy_static! {
     static ref RE_CODING: regex::bytes::Regex =
         regex::bytes::Regex::new(r"^[ \t\f]*#.*?coding[:=][ \t]*([-_.a-zA-Z0-9]+)").unwrap();
+    static ref RE_IMPORT: regex::Regex =
+        regex::Regex::new(r"(?m)^(?:import[ \t]+([\w. \t,]+)|from[ \t]+(\w[\w.]*)[ \t]+import\b)")
+            .unwrap();
 }
 
 /// Derive the source encoding from Python source code.
@@ -31,11 +34,8 @@ pub fn python_source_encoding(source: &[u8]) -> Vec<u8> {
     b"utf-8".to_vec()
 }
 
-/// Whether __file__ occurs in Python source code.
-pub fn has_dunder_file(source: &[u8]) -> Result<bool> {
-    // We can't just look for b"__file__ because the source file may be in
-    // encodings like UTF-16. So we need to decode to Unicode first then look for
-    // the code points.
+/// Decode Python source code to Unicode.
+fn decode_source(source: &[u8]) -> std::borrow::Cow<'_, str> {
     let encoding = python_source_encoding(source);
 
     let encoder = match encoding_rs::Encoding::for_label(&encoding) {
@@ -45,5 +45,53 @@ pub fn has_dunder_file(source: &[u8]) -> Result<bool> {
 
     let (source, ..) = encoder.decode(source);
 
-    Ok(source.contains("__file__"))
+    source
+}
+
+/// Whether __file__ occurs in Python source code.
+pub fn has_dunder_file(source: &[u8]) -> Result<bool> {
+    // We can't just look for b"__file__ because the source file may be in
+    // encodings like UTF-16. So we need to decode to Unicode first then look for
+    // the code points.
+    Ok(decode_source(source).contains("__file__"))
+}
+
+/// Names of modules imported at module level by Python source code.
+///
+/// Only unindented `import` and `from` statements are considered: imports
+/// within functions or `try` blocks don't fail when the module is imported.
+/// Relative imports are ignored.
+pub fn top_level_imports(source: &[u8]) -> Vec<String> {
+    let source = decode_source(source);
+
+    let mut res = vec![];
+
+    for captures in RE_IMPORT.captures_iter(&source) {
+        if let Some(names) = captures.get(1) {
+            for name in names.as_str().split(',') {
+                if let Some(name) = name.split_whitespace().next() {
+                    res.push(name.to_string());
+                }
+            }
+        } else if let Some(name) = captures.get(2) {
+            res.push(name.as_str().to_string());
+        }
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_top_level_imports() {
+        let source = b"import os\nimport os.path, sys as system\nfrom tkinter import ttk\nfrom . import foo\ntry:\n    import readline\nexcept ImportError:\n    pass\n";
+
+        assert_eq!(
+            top_level_imports(source),
+            vec!["os", "os.path", "sys", "tkinter"]
+        );
+    }
 }
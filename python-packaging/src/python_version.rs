@@ -0,0 +1,168 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Properties of individual CPython versions.
+
+Behavior that varies between CPython versions is recorded in a table
+of [`PythonVersionCapabilities`]. Supporting a new Python version should
+only require adding an entry to [`PYTHON_VERSIONS`].
+*/
+
+use anyhow::{anyhow, Result};
+
+/// Describes features of a specific `X.Y` CPython version.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PythonVersionCapabilities {
+    /// Major version component.
+    pub major: u8,
+
+    /// Minor version component.
+    pub minor: u8,
+
+    /// `PyConfig` fields whose availability varies between supported versions.
+    ///
+    /// Fields present in all supported versions are not listed.
+    pub config_fields: &'static [&'static str],
+}
+
+/// Supported CPython versions, in ascending order.
+///
+/// Versions are limited to those the `python3-sys` bindings `pyembed` is
+/// built on support.
+pub const PYTHON_VERSIONS: &[PythonVersionCapabilities] = &[
+    PythonVersionCapabilities {
+        major: 3,
+        minor: 8,
+        config_fields: &["show_alloc_count"],
+    },
+    PythonVersionCapabilities {
+        major: 3,
+        minor: 9,
+        config_fields: &["platlibdir"],
+    },
+];
+
+impl PythonVersionCapabilities {
+    /// Obtain the `X.Y` version string.
+    pub fn major_minor(&self) -> String {
+        format!("{}.{}", self.major, self.minor)
+    }
+
+    /// Obtain the PEP 425 Python tag. e.g. `cp38`.
+    pub fn python_tag(&self) -> String {
+        format!("cp{}{}", self.major, self.minor)
+    }
+
+    /// Obtain the PEP 425 ABI tag. e.g. `cp38`.
+    ///
+    /// Supported versions have no ABI flags, so this is the Python tag.
+    pub fn abi_tag(&self) -> String {
+        self.python_tag()
+    }
+
+    /// Obtain the bytecode cache tag. e.g. `cpython-38`.
+    pub fn cache_tag(&self) -> String {
+        format!("cpython-{}{}", self.major, self.minor)
+    }
+
+    /// Obtain the name of the directory holding the standard library on POSIX.
+    ///
+    /// e.g. `python3.8`.
+    pub fn stdlib_dir_name(&self) -> String {
+        format!("python{}", self.major_minor())
+    }
+
+    /// Whether a version dependent `PyConfig` field is available.
+    pub fn has_config_field(&self, name: &str) -> bool {
+        self.config_fields.contains(&name)
+    }
+}
+
+/// Parse the major and minor components from a version string.
+///
+/// Accepts values like `3.8` and `3.10.1`.
+pub fn parse_major_minor(version: &str) -> Option<(u8, u8)> {
+    let mut parts = version.split('.');
+
+    let major = parts.next()?.parse::<u8>().ok()?;
+    let minor = parts.next()?;
+
+    // Tolerate pre-release suffixes like `3.12rc1` or `3.12.0b2`.
+    let minor = minor
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or(minor, |i| &minor[0..i])
+        .parse::<u8>()
+        .ok()?;
+
+    Some((major, minor))
+}
+
+/// Find the capabilities of a `major.minor` Python version.
+pub fn capabilities_for(major: u8, minor: u8) -> Option<&'static PythonVersionCapabilities> {
+    PYTHON_VERSIONS
+        .iter()
+        .find(|v| v.major == major && v.minor == minor)
+}
+
+/// Resolve the capabilities of a Python version from a version string.
+pub fn resolve_python_version(version: &str) -> Result<&'static PythonVersionCapabilities> {
+    let (major, minor) = parse_major_minor(version)
+        .ok_or_else(|| anyhow!("unable to parse Python version: {}", version))?;
+
+    capabilities_for(major, minor).ok_or_else(|| {
+        anyhow!(
+            "Python {}.{} is not supported; supported versions: {}",
+            major,
+            minor,
+            PYTHON_VERSIONS
+                .iter()
+                .map(|v| v.major_minor())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_major_minor() {
+        assert_eq!(parse_major_minor("3.8"), Some((3, 8)));
+        assert_eq!(parse_major_minor("3.8.6"), Some((3, 8)));
+        assert_eq!(parse_major_minor("3.10.1"), Some((3, 10)));
+        assert_eq!(parse_major_minor("3.12rc1"), Some((3, 12)));
+        assert_eq!(parse_major_minor("3"), None);
+        assert_eq!(parse_major_minor("foo"), None);
+    }
+
+    #[test]
+    fn test_versions_sorted() {
+        for pair in PYTHON_VERSIONS.windows(2) {
+            assert!((pair[0].major, pair[0].minor) < (pair[1].major, pair[1].minor));
+        }
+    }
+
+    #[test]
+    fn test_resolve_python_version() -> Result<()> {
+        let v = resolve_python_version("3.9.0")?;
+        assert_eq!(v.major_minor(), "3.9");
+        assert_eq!(v.python_tag(), "cp39");
+        assert_eq!(v.abi_tag(), "cp39");
+        assert_eq!(v.cache_tag(), "cpython-39");
+        assert_eq!(v.stdlib_dir_name(), "python3.9");
+        assert!(v.has_config_field("platlibdir"));
+        assert!(!v.has_config_field("show_alloc_count"));
+
+        let v = resolve_python_version("3.8.6")?;
+        assert!(v.has_config_field("show_alloc_count"));
+
+        assert!(resolve_python_version("3.7.9").is_err());
+        assert!(resolve_python_version("3.10.1").is_err());
+        assert!(resolve_python_version("invalid").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,448 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Adjust resources of pywin32 to work without its post-install script.
+
+pywin32 installs most of its modules into the `win32`, `win32/lib` and
+`Pythonwin` directories, which `pywin32.pth` adds to `sys.path`. Its core
+DLLs are installed into `pywin32_system32` and copied into the system
+directory by a post-install script. Neither happens for an embedded
+interpreter, so resources found by scanning an installation have the wrong
+names and the DLLs are missing.
+
+The functions in this module rewrite the resources to what the `.pth` file
+and the post-install script would have produced.
+*/
+
+use {
+    crate::resource::{
+        DataLocation, FileData, LibraryDependency, PythonExtensionModule, PythonResource,
+    },
+    std::{borrow::Cow, path::PathBuf},
+};
+
+/// Directory holding the DLLs copied into the system directory.
+pub const SYSTEM32_DIR: &str = "pywin32_system32";
+
+/// Directories `pywin32.pth` adds to `sys.path`, as package names.
+///
+/// More specific directories come first.
+const PATH_DIRS: &[&str] = &["win32.lib", "win32", "Pythonwin"];
+
+/// Module of pywin32 which is always installed.
+const MARKER_MODULE: &str = "win32.lib.pywintypes";
+
+/// Extension modules of pywin32 which link against its DLLs.
+///
+/// These must be installed on the filesystem next to the DLLs for the
+/// Windows loader to find them.
+pub(crate) const EXTENSION_MODULES: &[&str] = &[
+    "_win32sysloader",
+    "_winxptheme",
+    "dde",
+    "mmapfile",
+    "odbc",
+    "perfmon",
+    "pythoncom",
+    "pywintypes",
+    "servicemanager",
+    "timer",
+    "win32api",
+    "win32clipboard",
+    "win32com",
+    "win32comext",
+    "win32console",
+    "win32cred",
+    "win32crypt",
+    "win32event",
+    "win32evtlog",
+    "win32file",
+    "win32gui",
+    "win32help",
+    "win32inet",
+    "win32job",
+    "win32lz",
+    "win32net",
+    "win32pdh",
+    "win32pipe",
+    "win32print",
+    "win32process",
+    "win32profile",
+    "win32ras",
+    "win32security",
+    "win32service",
+    "win32trace",
+    "win32transaction",
+    "win32ts",
+    "win32ui",
+    "win32uiole",
+    "win32wnet",
+    "winxpgui",
+];
+
+/// Whether resources contain pywin32 in its installed layout.
+pub fn has_pywin32(resources: &[PythonResource]) -> bool {
+    resources.iter().any(|r| match r {
+        PythonResource::ModuleSource(m) => m.name == MARKER_MODULE,
+        _ => false,
+    })
+}
+
+/// Strip a `sys.path` directory from a name.
+///
+/// Returns the directory and the remaining name, which is empty if the name
+/// is the directory itself.
+fn split_path_dir(name: &str) -> Option<(&'static str, &str)> {
+    PATH_DIRS.iter().find_map(|dir| {
+        if name == *dir {
+            Some((*dir, ""))
+        } else if name.starts_with(dir) && name.as_bytes().get(dir.len()) == Some(&b'.') {
+            Some((*dir, &name[dir.len() + 1..]))
+        } else {
+            None
+        }
+    })
+}
+
+/// Derive the module name and extension suffix of a DLL in `SYSTEM32_DIR`.
+///
+/// e.g. `pywintypes38.dll` provides the `pywintypes` module.
+fn system32_module_name(filename: &str) -> Option<(String, String)> {
+    let stem = filename.strip_suffix(".dll")?;
+    let stem = stem.strip_suffix("_d").unwrap_or(stem);
+    let name = stem.trim_end_matches(|c: char| c.is_ascii_digit());
+
+    if name.is_empty() || name == stem {
+        None
+    } else {
+        Some((name.to_string(), filename[name.len()..].to_string()))
+    }
+}
+
+/// Rewrite pywin32 resources to their run-time layout.
+///
+/// `dlls` are the files in `SYSTEM32_DIR`. They become the extension modules
+/// the `pywintypes` and `pythoncom` modules otherwise load from the system
+/// directory, replacing those modules. Other DLLs in the `sys.path`
+/// directories become dependencies of the extension modules next to them.
+/// Files in those directories which aren't needed at run-time, like C
+/// headers, are dropped.
+pub fn fixup_resources<'a>(
+    resources: Vec<PythonResource<'a>>,
+    dlls: &[FileData],
+) -> Vec<PythonResource<'a>> {
+    let system32_modules = dlls
+        .iter()
+        .filter_map(|file| {
+            let filename = file.path.file_name()?.to_str()?;
+            let (name, suffix) = system32_module_name(filename)?;
+
+            Some(PythonExtensionModule {
+                name: name.clone(),
+                init_fn: Some(format!("PyInit_{}", name)),
+                extension_file_suffix: suffix,
+                shared_library: Some(file.data.clone()),
+                object_file_data: vec![],
+                is_package: false,
+                link_libraries: vec![],
+                is_stdlib: false,
+                builtin_default: false,
+                required: false,
+                variant: None,
+                licenses: None,
+                license_public_domain: None,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let replaced = |name: &str| system32_modules.iter().any(|em| em.name == name);
+
+    // DLLs in `sys.path` directories, by directory.
+    let mut dir_libraries: Vec<(&'static str, LibraryDependency)> = vec![];
+    // Extension modules, with the directory they were in.
+    let mut extension_modules: Vec<(Option<&'static str>, PythonExtensionModule)> = vec![];
+    let mut res = vec![];
+
+    for resource in resources {
+        match resource {
+            PythonResource::ModuleSource(m) => {
+                let mut m = m.into_owned();
+                if let Some((_, name)) = split_path_dir(&m.name) {
+                    m.name = name.to_string();
+                    if replaced(&m.name) {
+                        continue;
+                    }
+                }
+                res.push(m.into());
+            }
+            PythonResource::ModuleBytecodeRequest(m) => {
+                let mut m = m.into_owned();
+                if let Some((_, name)) = split_path_dir(&m.name) {
+                    m.name = name.to_string();
+                    if replaced(&m.name) {
+                        continue;
+                    }
+                }
+                res.push(m.into());
+            }
+            PythonResource::ModuleBytecode(m) => {
+                let mut m = m.into_owned();
+                if let Some((_, name)) = split_path_dir(&m.name) {
+                    m.name = name.to_string();
+                    if replaced(&m.name) {
+                        continue;
+                    }
+                }
+                res.push(m.into());
+            }
+            PythonResource::ExtensionModule(em) => {
+                let mut em = em.into_owned();
+                let dir = match split_path_dir(&em.name) {
+                    Some((dir, name)) => {
+                        em.name = name.to_string();
+                        Some(dir)
+                    }
+                    None => None,
+                };
+                extension_modules.push((dir, em));
+            }
+            PythonResource::PackageResource(r) => {
+                let mut r = r.into_owned();
+                match split_path_dir(&r.leaf_package) {
+                    Some((dir, "")) => {
+                        if r.relative_name.ends_with(".dll") && !r.relative_name.contains('/') {
+                            dir_libraries.push((
+                                dir,
+                                LibraryDependency {
+                                    name: r.relative_name.clone(),
+                                    static_library: None,
+                                    static_filename: None,
+                                    dynamic_library: Some(r.data.clone()),
+                                    dynamic_filename: Some(PathBuf::from(&r.relative_name)),
+                                    framework: false,
+                                    system: false,
+                                },
+                            ));
+                        }
+                    }
+                    Some((_, package)) => {
+                        r.leaf_package = package.to_string();
+                        res.push(r.into());
+                    }
+                    None => {
+                        res.push(r.into());
+                    }
+                }
+            }
+            r => res.push(r),
+        }
+    }
+
+    for (dir, mut em) in extension_modules {
+        if replaced(&em.name) {
+            continue;
+        }
+
+        if let Some(dir) = dir {
+            em.link_libraries.extend(
+                dir_libraries
+                    .iter()
+                    .filter(|(d, _)| *d == dir)
+                    .map(|(_, library)| library.clone()),
+            );
+        }
+
+        res.push(PythonResource::ExtensionModule(Cow::Owned(em)));
+    }
+
+    res.extend(system32_modules.into_iter().map(PythonResource::from));
+
+    res
+}
+
+/// Obtain `FileData` for the DLLs in `SYSTEM32_DIR` of a set of files.
+///
+/// Paths of `files` are relative to the installation root.
+pub fn system32_dlls(files: &[FileData]) -> Vec<FileData> {
+    files
+        .iter()
+        .filter(|file| {
+            file.path.parent() == Some(std::path::Path::new(SYSTEM32_DIR))
+                && file
+                    .path
+                    .extension()
+                    .map(|ext| ext.eq_ignore_ascii_case("dll"))
+                    .unwrap_or(false)
+        })
+        .cloned()
+        .collect()
+}
+
+/// Obtain `FileData` for the DLLs in `SYSTEM32_DIR` of an installation root.
+pub fn read_system32_dlls(root: &std::path::Path) -> std::io::Result<Vec<FileData>> {
+    let dir = root.join(SYSTEM32_DIR);
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut files = vec![];
+    for entry in std::fs::read_dir(&dir)? {
+        let path = entry?.path();
+        files.push(FileData {
+            path: PathBuf::from(SYSTEM32_DIR).join(path.file_name().unwrap()),
+            is_executable: false,
+            data: DataLocation::Path(path),
+        });
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(system32_dlls(&files))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{filesystem_scanning::PythonResourceIterator, module_util::PythonModuleSuffixes},
+        anyhow::Result,
+    };
+
+    fn file(path: &str) -> FileData {
+        FileData {
+            path: PathBuf::from(path),
+            is_executable: false,
+            data: DataLocation::Memory(path.as_bytes().to_vec()),
+        }
+    }
+
+    #[test]
+    fn test_system32_module_name() {
+        assert_eq!(
+            system32_module_name("pywintypes38.dll"),
+            Some(("pywintypes".to_string(), "38.dll".to_string()))
+        );
+        assert_eq!(
+            system32_module_name("pythoncom310_d.dll"),
+            Some(("pythoncom".to_string(), "310_d.dll".to_string()))
+        );
+        assert_eq!(system32_module_name("pywintypes.dll"), None);
+        assert_eq!(system32_module_name("pywintypes38.pyd"), None);
+    }
+
+    #[test]
+    fn test_fixup_resources() -> Result<()> {
+        let files = [
+            "pywin32.pth",
+            "win32/win32api.cp38-win_amd64.pyd",
+            "win32/include/PyWinTypes.h",
+            "win32/lib/pywintypes.py",
+            "win32/lib/win32con.py",
+            "Pythonwin/win32ui.cp38-win_amd64.pyd",
+            "Pythonwin/mfc140u.dll",
+            "Pythonwin/license.txt",
+            "Pythonwin/pywin/__init__.py",
+            "Pythonwin/pywin/default.cfg",
+            "win32com/__init__.py",
+            "pywin32_system32/pywintypes38.dll",
+            "pywin32_system32/pythoncom38.dll",
+        ]
+        .iter()
+        .map(|path| file(path))
+        .collect::<Vec<_>>();
+
+        let suffixes = PythonModuleSuffixes {
+            source: vec![".py".to_string()],
+            bytecode: vec![".pyc".to_string()],
+            debug_bytecode: vec![],
+            optimized_bytecode: vec![],
+            extension: vec![".cp38-win_amd64.pyd".to_string(), ".pyd".to_string()],
+        };
+
+        let resources = PythonResourceIterator::from_data_locations(
+            &files,
+            "cpython-38",
+            &suffixes,
+            false,
+            true,
+        )
+        .collect::<Result<Vec<_>>>()?;
+        assert!(has_pywin32(&resources));
+
+        let resources = fixup_resources(resources, &system32_dlls(&files));
+        assert!(!has_pywin32(&resources));
+
+        let mut names = resources
+            .iter()
+            .filter(|r| !matches!(r, PythonResource::PathExtension(_)))
+            .map(|r| r.full_name())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "pythoncom",
+                "pywin",
+                "pywin.default.cfg",
+                "pywintypes",
+                "win32api",
+                "win32com",
+                "win32con",
+                "win32ui"
+            ]
+        );
+
+        let extension = |name: &str| {
+            resources
+                .iter()
+                .find_map(|r| match r {
+                    PythonResource::ExtensionModule(em) if em.name == name => Some(em.clone()),
+                    _ => None,
+                })
+                .unwrap()
+        };
+
+        let em = extension("pywintypes");
+        assert_eq!(em.file_name(), "pywintypes38.dll");
+        assert_eq!(em.init_fn, Some("PyInit_pywintypes".to_string()));
+
+        let em = extension("win32api");
+        assert_eq!(em.init_fn, Some("PyInit_win32api".to_string()));
+        assert!(em.link_libraries.is_empty());
+
+        let em = extension("win32ui");
+        assert_eq!(em.link_libraries.len(), 1);
+        assert_eq!(em.link_libraries[0].name, "mfc140u.dll");
+        assert_eq!(
+            em.link_libraries[0].dynamic_filename,
+            Some(PathBuf::from("mfc140u.dll"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_pywin32() -> Result<()> {
+        let resources = vec![PythonResource::from(PythonExtensionModule {
+            name: "win32api".to_string(),
+            init_fn: Some("PyInit_win32api".to_string()),
+            extension_file_suffix: ".pyd".to_string(),
+            shared_library: None,
+            object_file_data: vec![],
+            is_package: false,
+            link_libraries: vec![],
+            is_stdlib: false,
+            builtin_default: false,
+            required: false,
+            variant: None,
+            licenses: None,
+            license_public_domain: None,
+        })];
+
+        assert!(!has_pywin32(&resources));
+        assert_eq!(fixup_resources(resources.clone(), &[]), resources);
+
+        Ok(())
+    }
+}
@@ -203,15 +203,19 @@ impl PythonModuleBytecodeFromSource {
     }
 
     /// Compile source to bytecode using a compiler.
+    ///
+    /// Docstrings are removed if `strip_docstrings` is true.
     pub fn compile(
         &self,
         compiler: &mut dyn PythonBytecodeCompiler,
+        strip_docstrings: bool,
         mode: CompileMode,
     ) -> Result<Vec<u8>> {
         compiler.compile(
             &self.source.resolve()?,
             &self.name,
             self.optimize_level,
+            strip_docstrings,
             mode,
         )
     }
@@ -382,6 +386,91 @@ impl PythonPackageResource {
     }
 }
 
+/// A compiled gettext message catalog.
+///
+/// Catalogs are `.mo` files in a `locale/<locale>/LC_MESSAGES/<domain>.mo`
+/// layout within a Python package. Python sees them as package resources.
+/// But `gettext` can only load catalogs from the filesystem, from the locale
+/// directory bound to their domain.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GettextCatalog {
+    /// The leaf-most Python package the catalog belongs to.
+    pub leaf_package: String,
+    /// The relative path within `leaf_package` to the catalog.
+    pub relative_name: String,
+    /// Locale of messages in the catalog. e.g. `de` or `pt_BR`.
+    pub locale: String,
+    /// The gettext domain of the catalog.
+    pub domain: String,
+    /// Location of catalog data.
+    pub data: DataLocation,
+}
+
+impl GettextCatalog {
+    /// Classify a package resource as a gettext catalog.
+    ///
+    /// Returns `None` if the resource isn't in the layout of a catalog.
+    pub fn from_package_resource(resource: &PythonPackageResource) -> Option<Self> {
+        let components = resource.relative_name.split('/').collect::<Vec<_>>();
+
+        if components.len() < 4 {
+            return None;
+        }
+
+        let components = &components[components.len() - 4..];
+
+        if components[0] != "locale" || components[1].is_empty() || components[2] != "LC_MESSAGES" {
+            return None;
+        }
+
+        let domain = components[3].strip_suffix(".mo")?;
+        if domain.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            leaf_package: resource.leaf_package.clone(),
+            relative_name: resource.relative_name.clone(),
+            locale: components[1].to_string(),
+            domain: domain.to_string(),
+            data: resource.data.clone(),
+        })
+    }
+
+    pub fn to_memory(&self) -> Result<Self> {
+        Ok(Self {
+            leaf_package: self.leaf_package.clone(),
+            relative_name: self.relative_name.clone(),
+            locale: self.locale.clone(),
+            domain: self.domain.clone(),
+            data: self.data.to_memory()?,
+        })
+    }
+
+    pub fn symbolic_name(&self) -> String {
+        format!("{}:{}", self.leaf_package, self.relative_name)
+    }
+
+    /// Obtain the package resource this catalog was classified from.
+    pub fn to_package_resource(&self) -> PythonPackageResource {
+        PythonPackageResource {
+            leaf_package: self.leaf_package.clone(),
+            relative_name: self.relative_name.clone(),
+            data: self.data.clone(),
+            is_stdlib: false,
+            is_test: false,
+        }
+    }
+
+    /// Resolve the filesystem path of the catalog within a locale directory.
+    pub fn resolve_path(&self, locale_dir: &Path) -> PathBuf {
+        locale_dir
+            .join(&self.locale)
+            .join("LC_MESSAGES")
+            .join(format!("{}.mo", self.domain))
+    }
+}
+
 /// Represents where a Python package distribution resource is materialized.
 #[derive(Clone, Debug, PartialEq)]
 pub enum PythonPackageDistributionResourceFlavor {
@@ -647,6 +736,57 @@ impl PythonExtensionModule {
     pub fn in_libpython(&self) -> bool {
         self.is_stdlib && (self.builtin_default || self.shared_library.is_none())
     }
+
+    /// Whether this extension module targets the stable ABI.
+    ///
+    /// Extension modules built against `Py_LIMITED_API` advertise this via
+    /// an `.abi3` component in their filename suffix.
+    pub fn is_stable_abi(&self) -> bool {
+        self.extension_file_suffix.starts_with(".abi3")
+    }
+
+    /// Obtain the Python version this extension module was built for.
+    ///
+    /// The version is derived from the filename suffix. e.g.
+    /// `.cpython-38-x86_64-linux-gnu.so` and `.cp38-win_amd64.pyd` both
+    /// resolve to `38`. `None` is returned if the suffix doesn't identify
+    /// a Python version.
+    pub fn python_version_tag(&self) -> Option<String> {
+        let suffix = &self.extension_file_suffix;
+
+        let remaining = suffix
+            .strip_prefix(".cpython-")
+            .or_else(|| suffix.strip_prefix(".cp"))?;
+
+        let version = remaining
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect::<String>();
+
+        if version.is_empty() {
+            None
+        } else {
+            Some(version)
+        }
+    }
+
+    /// Whether this extension module can be loaded by an interpreter with the given cache tag.
+    ///
+    /// Extension modules targeting the stable ABI or not advertising a Python
+    /// version are assumed to be compatible.
+    pub fn is_compatible_with_cache_tag(&self, cache_tag: &str) -> bool {
+        if self.is_stable_abi() {
+            return true;
+        }
+
+        match (
+            self.python_version_tag(),
+            cache_tag.strip_prefix("cpython-"),
+        ) {
+            (Some(version), Some(wanted)) => version == wanted,
+            _ => true,
+        }
+    }
 }
 
 /// Represents a collection of variants for a given Python extension module.
@@ -794,6 +934,8 @@ pub enum PythonResource<'a> {
     PathExtension(Cow<'a, PythonPathExtension>),
     /// An arbitrary file and its data.
     File(Cow<'a, FileData>),
+    /// A compiled gettext message catalog.
+    GettextCatalog(Cow<'a, GettextCatalog>),
 }
 
 impl<'a> PythonResource<'a> {
@@ -813,6 +955,9 @@ impl<'a> PythonResource<'a> {
             PythonResource::EggFile(_) => "".to_string(),
             PythonResource::PathExtension(_) => "".to_string(),
             PythonResource::File(f) => format!("{}", f.path.display()),
+            PythonResource::GettextCatalog(catalog) => {
+                format!("{}.{}", catalog.leaf_package, catalog.relative_name)
+            }
         }
     }
 
@@ -827,6 +972,7 @@ impl<'a> PythonResource<'a> {
             PythonResource::EggFile(_) => return false,
             PythonResource::PathExtension(_) => return false,
             PythonResource::File(_) => return false,
+            PythonResource::GettextCatalog(catalog) => &catalog.leaf_package,
         };
 
         for package in packages {
@@ -854,6 +1000,7 @@ impl<'a> PythonResource<'a> {
             PythonResource::EggFile(e) => e.to_memory()?.into(),
             PythonResource::PathExtension(e) => e.to_memory()?.into(),
             PythonResource::File(f) => f.to_memory()?.into(),
+            PythonResource::GettextCatalog(c) => c.to_memory()?.into(),
         })
     }
 }
@@ -966,12 +1113,66 @@ impl<'a> From<&'a FileData> for PythonResource<'a> {
     }
 }
 
+impl<'a> From<GettextCatalog> for PythonResource<'a> {
+    fn from(c: GettextCatalog) -> Self {
+        PythonResource::GettextCatalog(Cow::Owned(c))
+    }
+}
+
+impl<'a> From<&'a GettextCatalog> for PythonResource<'a> {
+    fn from(c: &'a GettextCatalog) -> Self {
+        PythonResource::GettextCatalog(Cow::Borrowed(c))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     const DEFAULT_CACHE_TAG: &str = "cpython-37";
 
+    fn extension_with_suffix(suffix: &str) -> PythonExtensionModule {
+        PythonExtensionModule {
+            name: "foo".to_string(),
+            init_fn: None,
+            extension_file_suffix: suffix.to_string(),
+            shared_library: None,
+            object_file_data: vec![],
+            is_package: false,
+            link_libraries: vec![],
+            is_stdlib: false,
+            builtin_default: false,
+            required: false,
+            variant: None,
+            licenses: None,
+            license_public_domain: None,
+        }
+    }
+
+    #[test]
+    fn test_extension_module_version_compatibility() {
+        let em = extension_with_suffix(".cpython-37m-x86_64-linux-gnu.so");
+        assert!(!em.is_stable_abi());
+        assert_eq!(em.python_version_tag(), Some("37".to_string()));
+        assert!(em.is_compatible_with_cache_tag("cpython-37"));
+        assert!(!em.is_compatible_with_cache_tag("cpython-38"));
+
+        let em = extension_with_suffix(".cp38-win_amd64.pyd");
+        assert_eq!(em.python_version_tag(), Some("38".to_string()));
+        assert!(em.is_compatible_with_cache_tag("cpython-38"));
+        assert!(!em.is_compatible_with_cache_tag("cpython-37"));
+
+        let em = extension_with_suffix(".abi3.so");
+        assert!(em.is_stable_abi());
+        assert_eq!(em.python_version_tag(), None);
+        assert!(em.is_compatible_with_cache_tag("cpython-37"));
+        assert!(em.is_compatible_with_cache_tag("cpython-38"));
+
+        let em = extension_with_suffix(".so");
+        assert_eq!(em.python_version_tag(), None);
+        assert!(em.is_compatible_with_cache_tag("cpython-38"));
+    }
+
     #[test]
     fn test_is_in_packages() {
         let source = PythonResource::ModuleSource(Cow::Owned(PythonModuleSource {
@@ -999,4 +1200,39 @@ mod tests {
         assert!(!bytecode.is_in_packages(&[]));
         assert!(!bytecode.is_in_packages(&["bar".to_string()]));
     }
+
+    #[test]
+    fn test_gettext_catalog() {
+        let resource = |name: &str| PythonPackageResource {
+            leaf_package: "foo".to_string(),
+            relative_name: name.to_string(),
+            data: DataLocation::Memory(vec![]),
+            is_stdlib: false,
+            is_test: false,
+        };
+
+        let catalog = GettextCatalog::from_package_resource(&resource(
+            "data/locale/pt_BR/LC_MESSAGES/foo.mo",
+        ))
+        .unwrap();
+        assert_eq!(catalog.locale, "pt_BR");
+        assert_eq!(catalog.domain, "foo");
+        assert_eq!(
+            catalog.resolve_path(Path::new("locale")),
+            PathBuf::from("locale/pt_BR/LC_MESSAGES/foo.mo")
+        );
+        assert_eq!(
+            catalog.to_package_resource(),
+            resource("data/locale/pt_BR/LC_MESSAGES/foo.mo")
+        );
+
+        for name in &[
+            "locale/de/LC_MESSAGES/foo.po",
+            "locale/de/foo.mo",
+            "l10n/de/LC_MESSAGES/foo.mo",
+            "locale/de/LC_MESSAGES/.mo",
+        ] {
+            assert!(GettextCatalog::from_package_resource(&resource(name)).is_none());
+        }
+    }
 }
@@ -10,24 +10,27 @@ use {
             compute_bytecode_header, BytecodeHeaderMode, CompileMode, PythonBytecodeCompiler,
         },
         libpython::LibPythonBuildContext,
-        location::{AbstractResourceLocation, ConcreteResourceLocation},
+        location::{
+            top_level_package, AbstractResourceLocation, ConcreteResourceLocation,
+            PACKAGE_PLACEHOLDER,
+        },
         module_util::{packages_from_module_name, resolve_path_for_module},
-        python_source::has_dunder_file,
+        python_source::{has_dunder_file, top_level_imports},
         resource::{
-            BytecodeOptimizationLevel, DataLocation, FileData, PythonExtensionModule,
-            PythonModuleBytecode, PythonModuleBytecodeFromSource, PythonModuleSource,
-            PythonPackageDistributionResource, PythonPackageResource, PythonResource,
-            SharedLibrary,
+            BytecodeOptimizationLevel, DataLocation, FileData, GettextCatalog,
+            PythonExtensionModule, PythonModuleBytecode, PythonModuleBytecodeFromSource,
+            PythonModuleSource, PythonPackageDistributionResource, PythonPackageResource,
+            PythonResource, SharedLibrary,
         },
     },
-    anyhow::{anyhow, Result},
+    anyhow::{anyhow, Context, Result},
     python_packed_resources::data::{Resource, ResourceFlavor},
     std::{
         borrow::Cow,
-        collections::{BTreeMap, BTreeSet, HashMap},
+        collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
         convert::TryFrom,
         iter::FromIterator,
-        path::PathBuf,
+        path::{Path, PathBuf},
     },
 };
 
@@ -89,14 +92,93 @@ pub struct PrePackagedResource {
 }
 
 impl PrePackagedResource {
+    /// Obtain a human readable description of this resource.
+    ///
+    /// The description contains the resource's type, name, and where its
+    /// data will be stored.
+    pub fn describe(&self) -> String {
+        let kind = if self.is_builtin_extension_module {
+            "builtin extension module"
+        } else if self.is_frozen_module {
+            "frozen module"
+        } else if self.is_extension_module {
+            "extension module"
+        } else if self.is_module {
+            "module"
+        } else if self.is_shared_library {
+            "shared library"
+        } else if self.is_utf8_filename_data {
+            "file"
+        } else {
+            "resource"
+        };
+
+        let mut locations = vec![];
+
+        if self.in_memory_source.is_some() {
+            locations.push("in-memory source");
+        }
+        if self.in_memory_bytecode.is_some()
+            || self.in_memory_bytecode_opt1.is_some()
+            || self.in_memory_bytecode_opt2.is_some()
+        {
+            locations.push("in-memory bytecode");
+        }
+        if self.in_memory_extension_module_shared_library.is_some()
+            || self.in_memory_shared_library.is_some()
+        {
+            locations.push("in-memory library");
+        }
+        if self.in_memory_resources.is_some() {
+            locations.push("in-memory package resources");
+        }
+        if self.in_memory_distribution_resources.is_some() {
+            locations.push("in-memory distribution resources");
+        }
+        if self.file_data_embedded.is_some() {
+            locations.push("in-memory file data");
+        }
+        if self.relative_path_module_source.is_some() {
+            locations.push("filesystem source");
+        }
+        if self.relative_path_bytecode.is_some()
+            || self.relative_path_bytecode_opt1.is_some()
+            || self.relative_path_bytecode_opt2.is_some()
+        {
+            locations.push("filesystem bytecode");
+        }
+        if self.relative_path_extension_module_shared_library.is_some()
+            || self.relative_path_shared_library.is_some()
+        {
+            locations.push("filesystem library");
+        }
+        if self.relative_path_package_resources.is_some() {
+            locations.push("filesystem package resources");
+        }
+        if self.relative_path_distribution_resources.is_some() {
+            locations.push("filesystem distribution resources");
+        }
+        if self.file_data_utf8_relative_path.is_some() {
+            locations.push("filesystem file data");
+        }
+
+        if locations.is_empty() {
+            format!("{} {}", kind, self.name)
+        } else {
+            format!("{} {} ({})", kind, self.name, locations.join(", "))
+        }
+    }
+
     /// Convert the instance to a `Resource`.
     ///
-    /// This will compile bytecode from source code using the specified compiler.
-    /// It will also emit a list of file installs that must be performed for all
-    /// referenced resources to function as intended.
+    /// This will compile bytecode from source code using the specified compiler,
+    /// removing docstrings if `strip_docstrings` is true. It will also emit a
+    /// list of file installs that must be performed for all referenced resources
+    /// to function as intended.
     pub fn to_resource<'a>(
         &self,
         compiler: &mut dyn PythonBytecodeCompiler,
+        strip_docstrings: bool,
     ) -> Result<(Resource<'a, u8>, Vec<FileInstall>)> {
         let mut installs = Vec::new();
 
@@ -119,6 +201,7 @@ impl PrePackagedResource {
                         &location.resolve()?,
                         &self.name,
                         BytecodeOptimizationLevel::Zero,
+                        strip_docstrings,
                         CompileMode::Bytecode,
                     )?))
                 }
@@ -133,6 +216,7 @@ impl PrePackagedResource {
                         &location.resolve()?,
                         &self.name,
                         BytecodeOptimizationLevel::One,
+                        strip_docstrings,
                         CompileMode::Bytecode,
                     )?))
                 }
@@ -147,6 +231,7 @@ impl PrePackagedResource {
                         &location.resolve()?,
                         &self.name,
                         BytecodeOptimizationLevel::Two,
+                        strip_docstrings,
                         CompileMode::Bytecode,
                     )?))
                 }
@@ -223,6 +308,7 @@ impl PrePackagedResource {
                             &location.resolve()?,
                             &self.name,
                             BytecodeOptimizationLevel::Zero,
+                            strip_docstrings,
                             CompileMode::PycUncheckedHash,
                         )?,
                         PythonModuleBytecodeProvider::Provided(location) => {
@@ -263,6 +349,7 @@ impl PrePackagedResource {
                             &location.resolve()?,
                             &self.name,
                             BytecodeOptimizationLevel::One,
+                            strip_docstrings,
                             CompileMode::PycUncheckedHash,
                         )?,
                         PythonModuleBytecodeProvider::Provided(location) => {
@@ -303,6 +390,7 @@ impl PrePackagedResource {
                             &location.resolve()?,
                             &self.name,
                             BytecodeOptimizationLevel::Two,
+                            strip_docstrings,
                             CompileMode::PycUncheckedHash,
                         )?,
                         PythonModuleBytecodeProvider::Provided(location) => {
@@ -588,20 +676,128 @@ impl PythonResourceAddCollectionContext {
 pub struct CompiledResourcesCollection<'a> {
     pub resources: BTreeMap<String, Resource<'a, u8>>,
     pub extra_files: Vec<FileInstall>,
+    /// Locale directories to bind gettext domains to, relative to the binary.
+    pub gettext_domains: BTreeMap<String, PathBuf>,
 }
 
 impl<'a> CompiledResourcesCollection<'a> {
     /// Write resources to packed resources data, version 1.
+    ///
+    /// Resources are written sorted by name, so the data only depends on the
+    /// collected resources.
     pub fn write_packed_resources<W: std::io::Write>(&self, writer: &mut W) -> Result<()> {
-        python_packed_resources::writer::write_packed_resources_v3(
-            &self
-                .resources
-                .values()
-                .cloned()
-                .collect::<Vec<Resource<'a, u8>>>(),
-            writer,
-            None,
-        )
+        let resources = self
+            .resources
+            .values()
+            .cloned()
+            .collect::<Vec<Resource<'a, u8>>>();
+
+        python_packed_resources::writer::check_resources_order(&resources)?;
+        python_packed_resources::writer::write_packed_resources_v3(&resources, writer, None)
+    }
+
+    /// Obtain the size of resource data attributed to each top-level package.
+    ///
+    /// Data embedded in packed resources and files installed next to the
+    /// binary both count towards a package. Files not belonging to a
+    /// resource are ignored.
+    pub fn package_sizes(&self) -> Result<BTreeMap<String, u64>> {
+        let mut file_sizes = HashMap::new();
+
+        for (path, location, _) in &self.extra_files {
+            let size = match location {
+                DataLocation::Path(p) => std::fs::metadata(p)
+                    .with_context(|| format!("reading metadata of {}", p.display()))?
+                    .len(),
+                DataLocation::Memory(data) => data.len() as u64,
+            };
+
+            file_sizes.insert(path.as_path(), size);
+        }
+
+        let mut res = BTreeMap::new();
+
+        for resource in self.resources.values() {
+            let mut size = vec![
+                &resource.in_memory_source,
+                &resource.in_memory_bytecode,
+                &resource.in_memory_bytecode_opt1,
+                &resource.in_memory_bytecode_opt2,
+                &resource.in_memory_extension_module_shared_library,
+                &resource.in_memory_shared_library,
+                &resource.file_data_embedded,
+            ]
+            .into_iter()
+            .flatten()
+            .map(|data| data.len() as u64)
+            .sum::<u64>();
+
+            for resources in vec![
+                &resource.in_memory_package_resources,
+                &resource.in_memory_distribution_resources,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                size += resources
+                    .values()
+                    .map(|data| data.len() as u64)
+                    .sum::<u64>();
+            }
+
+            let relative_paths = vec![
+                &resource.relative_path_module_source,
+                &resource.relative_path_module_bytecode,
+                &resource.relative_path_module_bytecode_opt1,
+                &resource.relative_path_module_bytecode_opt2,
+                &resource.relative_path_extension_module_shared_library,
+            ]
+            .into_iter()
+            .flatten()
+            .map(|path| path.as_ref())
+            .chain(
+                vec![
+                    &resource.relative_path_package_resources,
+                    &resource.relative_path_distribution_resources,
+                ]
+                .into_iter()
+                .flatten()
+                .flat_map(|paths| paths.values().map(|path| path.as_ref())),
+            )
+            .chain(
+                resource
+                    .file_data_utf8_relative_path
+                    .iter()
+                    .map(|path| Path::new(path.as_ref())),
+            );
+
+            size += relative_paths
+                .filter_map(|path| file_sizes.get(path))
+                .sum::<u64>();
+
+            if size == 0 {
+                continue;
+            }
+
+            let package = if resource.is_utf8_filename_data {
+                Path::new(resource.name.as_ref())
+                    .components()
+                    .next()
+                    .map(|c| c.as_os_str().to_string_lossy().to_string())
+                    .unwrap_or_default()
+            } else {
+                resource
+                    .name
+                    .split('.')
+                    .next()
+                    .unwrap_or_default()
+                    .to_string()
+            };
+
+            *res.entry(package).or_insert(0) += size;
+        }
+
+        Ok(res)
     }
 }
 
@@ -636,8 +832,16 @@ pub struct PythonResourceCollector {
 
     /// Named resources that have been collected.
     resources: BTreeMap<String, PrePackagedResource>,
+    /// Gettext catalogs installed in a locale directory, keyed by path.
+    ///
+    /// Values are the symbolic name of the catalog and its data.
+    gettext_catalogs: BTreeMap<PathBuf, (String, DataLocation)>,
+    /// Locale directories gettext domains are bound to.
+    gettext_domains: BTreeMap<String, PathBuf>,
     /// Bytecode cache tag to use for compiled bytecode modules.
     cache_tag: String,
+    /// Whether to remove docstrings when compiling bytecode.
+    strip_docstrings: bool,
 }
 
 impl PythonResourceCollector {
@@ -661,10 +865,25 @@ impl PythonResourceCollector {
             allow_new_builtin_extension_modules,
             allow_files,
             resources: BTreeMap::new(),
+            gettext_catalogs: BTreeMap::new(),
+            gettext_domains: BTreeMap::new(),
             cache_tag: cache_tag.to_string(),
+            strip_docstrings: false,
         }
     }
 
+    /// Whether docstrings are removed when compiling bytecode.
+    pub fn strip_docstrings(&self) -> bool {
+        self.strip_docstrings
+    }
+
+    /// Set whether docstrings are removed when compiling bytecode.
+    ///
+    /// This applies to all optimization levels, not just level 2.
+    pub fn set_strip_docstrings(&mut self, value: bool) {
+        self.strip_docstrings = value;
+    }
+
     /// Obtain locations that resources can be loaded from.
     pub fn allowed_locations(&self) -> &Vec<AbstractResourceLocation> {
         &self.allowed_locations
@@ -712,6 +931,7 @@ impl PythonResourceCollector {
         location: &ConcreteResourceLocation,
     ) -> Result<()> {
         self.check_policy(location.into())?;
+        let location = &location.resolve_for_package(top_level_package(&module.name));
 
         let entry = self
             .resources
@@ -805,6 +1025,7 @@ impl PythonResourceCollector {
         location: &ConcreteResourceLocation,
     ) -> Result<()> {
         self.check_policy(location.into())?;
+        let location = &location.resolve_for_package(top_level_package(&module.name));
 
         let entry = self
             .resources
@@ -913,6 +1134,7 @@ impl PythonResourceCollector {
         location: &ConcreteResourceLocation,
     ) -> Result<()> {
         self.check_policy(location.into())?;
+        let location = &location.resolve_for_package(top_level_package(&module.name));
 
         let entry = self
             .resources
@@ -1021,6 +1243,7 @@ impl PythonResourceCollector {
         location: &ConcreteResourceLocation,
     ) -> Result<()> {
         self.check_policy(location.into())?;
+        let location = &location.resolve_for_package(top_level_package(&resource.leaf_package));
 
         let entry = self
             .resources
@@ -1091,6 +1314,7 @@ impl PythonResourceCollector {
         location: &ConcreteResourceLocation,
     ) -> Result<()> {
         self.check_policy(location.into())?;
+        let location = &location.resolve_for_package(top_level_package(&resource.package));
 
         let entry = self
             .resources
@@ -1347,6 +1571,7 @@ impl PythonResourceCollector {
         location: &ConcreteResourceLocation,
     ) -> Result<()> {
         self.check_policy(location.into())?;
+        let location = &location.resolve_for_package(top_level_package(&module.name));
 
         let data = match &module.shared_library {
             Some(location) => location.resolve()?,
@@ -1433,6 +1658,7 @@ impl PythonResourceCollector {
         location: &ConcreteResourceLocation,
     ) -> Result<()> {
         self.check_policy(location.into())?;
+        let location = &location.resolve_for_package(&library.name);
 
         let entry = self
             .resources
@@ -1460,6 +1686,72 @@ impl PythonResourceCollector {
         Ok(())
     }
 
+    /// Move package resources of a package tree to the filesystem.
+    ///
+    /// In-memory resources of `package` and its sub-packages are installed
+    /// relative to the binary under `prefix` instead, preserving their layout.
+    /// This is needed for data that must exist as files, such as plugins
+    /// loaded by native code. Returns the number of resources moved.
+    pub fn relocate_package_resources(&mut self, package: &str, prefix: &str) -> Result<usize> {
+        self.check_policy(AbstractResourceLocation::RelativePath)?;
+
+        let package_prefix = format!("{}.", package);
+        let prefix = prefix.replace(PACKAGE_PLACEHOLDER, top_level_package(package));
+        let mut count = 0;
+
+        for (name, entry) in self.resources.iter_mut() {
+            if name != package && !name.starts_with(&package_prefix) {
+                continue;
+            }
+
+            let resources = match entry.in_memory_resources.take() {
+                Some(resources) => resources,
+                None => continue,
+            };
+
+            let mut package_path = PathBuf::from(&prefix);
+            for p in name.split('.') {
+                package_path = package_path.join(p);
+            }
+
+            let relative = entry
+                .relative_path_package_resources
+                .get_or_insert_with(BTreeMap::new);
+
+            for (relative_name, location) in resources {
+                let path = package_path.join(&relative_name);
+                relative.insert(relative_name, (path, location));
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Record that an extension module depends on a shared library.
+    ///
+    /// The extension module must have already been added.
+    pub fn add_extension_module_shared_library_dependency(
+        &mut self,
+        module: &str,
+        library: &str,
+    ) -> Result<()> {
+        let entry = match self.resources.get_mut(module) {
+            Some(entry) if entry.is_extension_module || entry.is_builtin_extension_module => entry,
+            _ => return Err(anyhow!("extension module {} has not been added", module)),
+        };
+
+        let names = entry
+            .shared_library_dependency_names
+            .get_or_insert_with(Vec::new);
+
+        if !names.iter().any(|name| name == library) {
+            names.push(library.to_string());
+        }
+
+        Ok(())
+    }
+
     pub fn add_file_data(
         &mut self,
         file: &FileData,
@@ -1473,13 +1765,33 @@ impl PythonResourceCollector {
 
         self.check_policy(location.into())?;
 
-        let entry =
-            self.resources
-                .entry(file.path_string())
-                .or_insert_with(|| PrePackagedResource {
-                    name: file.path_string(),
-                    ..PrePackagedResource::default()
-                });
+        // Files in a directory are attributed to a package named after it.
+        let package = match file.path.parent() {
+            Some(parent) if parent != Path::new("") => file
+                .path
+                .components()
+                .next()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .unwrap_or_default(),
+            _ => "".to_string(),
+        };
+        let location = &location.resolve_for_package(&package);
+
+        // Packed resources index files by their UTF-8 path.
+        let name = file.path.to_str().ok_or_else(|| {
+            anyhow!(
+                "file path {} is not valid Unicode and cannot be packaged",
+                file.path.display()
+            )
+        })?;
+
+        let entry = self
+            .resources
+            .entry(name.to_string())
+            .or_insert_with(|| PrePackagedResource {
+                name: name.to_string(),
+                ..PrePackagedResource::default()
+            });
 
         entry.is_utf8_filename_data = true;
         entry.file_executable = file.is_executable;
@@ -1513,6 +1825,80 @@ impl PythonResourceCollector {
         )
     }
 
+    /// Add a gettext catalog to a given location.
+    ///
+    /// A catalog in memory is stored as a package resource. `gettext` can't
+    /// load it, but it remains accessible via resource APIs. A catalog on the
+    /// filesystem is installed in the `locale` directory under the location's
+    /// prefix and its domain is bound to that directory.
+    pub fn add_gettext_catalog(
+        &mut self,
+        catalog: &GettextCatalog,
+        location: &ConcreteResourceLocation,
+    ) -> Result<()> {
+        let location = &location.resolve_for_package(top_level_package(&catalog.leaf_package));
+
+        let prefix = match location {
+            ConcreteResourceLocation::InMemory => {
+                return self.add_python_package_resource(&catalog.to_package_resource(), location);
+            }
+            ConcreteResourceLocation::RelativePath(prefix) => prefix,
+        };
+
+        self.check_policy(location.into())?;
+
+        let locale_dir = PathBuf::from(prefix).join("locale");
+
+        if let Some(existing) = self.gettext_domains.get(&catalog.domain) {
+            if existing != &locale_dir {
+                return Err(anyhow!(
+                    "gettext domain {} is bound to {}; catalog {} cannot be installed to {}",
+                    catalog.domain,
+                    existing.display(),
+                    catalog.symbolic_name(),
+                    locale_dir.display()
+                ));
+            }
+        }
+
+        let path = catalog.resolve_path(&locale_dir);
+
+        if let Some((name, _)) = self.gettext_catalogs.get(&path) {
+            if name != &catalog.symbolic_name() {
+                return Err(anyhow!(
+                    "gettext catalog {} conflicts with {}; both install to {}",
+                    catalog.symbolic_name(),
+                    name,
+                    path.display()
+                ));
+            }
+        }
+
+        self.gettext_domains
+            .insert(catalog.domain.clone(), locale_dir);
+        self.gettext_catalogs
+            .insert(path, (catalog.symbolic_name(), catalog.data.clone()));
+
+        Ok(())
+    }
+
+    /// Add a gettext catalog using an add context.
+    pub fn add_gettext_catalog_with_context(
+        &mut self,
+        catalog: &GettextCatalog,
+        add_context: &PythonResourceAddCollectionContext,
+    ) -> Result<()> {
+        if !add_context.include {
+            return Ok(());
+        }
+
+        self.add_python_resource_with_locations(
+            &catalog.into(),
+            &add_context.location,
+            &add_context.location_fallback,
+        )
+    }
+
     fn add_python_resource_with_locations(
         &mut self,
         resource: &PythonResource,
@@ -1590,6 +1976,18 @@ impl PythonResourceCollector {
                     }
                 }
             },
+            PythonResource::GettextCatalog(catalog) => {
+                match self.add_gettext_catalog(catalog, location) {
+                    Ok(()) => Ok(()),
+                    Err(err) => {
+                        if let Some(location) = fallback_location {
+                            self.add_gettext_catalog(catalog, location)
+                        } else {
+                            Err(err)
+                        }
+                    }
+                }
+            }
             _ => Err(anyhow!("PythonResource variant not yet supported")),
         }
     }
@@ -1636,6 +2034,128 @@ impl PythonResourceCollector {
         Ok(res)
     }
 
+    /// Searches Python sources for module level imports of modules matching a predicate.
+    ///
+    /// Returns a mapping of module name to the matching modules it imports.
+    pub fn find_imports_of<F>(&self, predicate: F) -> Result<BTreeMap<String, BTreeSet<String>>>
+    where
+        F: Fn(&str) -> bool,
+    {
+        let mut res = BTreeMap::new();
+
+        for (name, module) in &self.resources {
+            let mut sources = vec![];
+            sources.extend(module.in_memory_source.iter());
+            sources.extend(module.relative_path_module_source.iter().map(|(_, l)| l));
+
+            for provider in [
+                &module.in_memory_bytecode,
+                &module.in_memory_bytecode_opt1,
+                &module.in_memory_bytecode_opt2,
+            ]
+            .iter()
+            {
+                if let Some(PythonModuleBytecodeProvider::FromSource(location)) = provider {
+                    sources.push(location);
+                }
+            }
+
+            for entry in [
+                &module.relative_path_bytecode,
+                &module.relative_path_bytecode_opt1,
+                &module.relative_path_bytecode_opt2,
+            ]
+            .iter()
+            {
+                if let Some((_, _, PythonModuleBytecodeProvider::FromSource(location))) = entry {
+                    sources.push(location);
+                }
+            }
+
+            for location in sources {
+                for import in top_level_imports(&location.resolve()?) {
+                    if predicate(&import) {
+                        res.entry(name.clone())
+                            .or_insert_with(BTreeSet::new)
+                            .insert(import);
+                    }
+                }
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Find chains of module level imports leading to modules matching a predicate.
+    ///
+    /// There is a chain for each import of a matching module. It ends with
+    /// the matching module and the module importing it and begins with a
+    /// module no other collected module imports, e.g. `app`, `app.gui`,
+    /// `tkinter`. When there are several such chains, the shortest is used.
+    pub fn find_import_chains<F>(&self, predicate: F) -> Result<Vec<Vec<String>>>
+    where
+        F: Fn(&str) -> bool,
+    {
+        let offending = self.find_imports_of(&predicate)?;
+        if offending.is_empty() {
+            return Ok(vec![]);
+        }
+
+        // Collected modules importing each collected module.
+        let mut importers: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for (module, imports) in self.find_imports_of(|name| self.resources.contains_key(name))? {
+            for import in imports {
+                if import != module {
+                    importers
+                        .entry(import)
+                        .or_insert_with(BTreeSet::new)
+                        .insert(module.clone());
+                }
+            }
+        }
+
+        let mut chains = vec![];
+
+        for (module, imports) in offending {
+            // Search importers breadth first for a module nothing imports,
+            // recording which module led to each one visited.
+            let mut next = BTreeMap::new();
+            let mut queue = VecDeque::new();
+            queue.push_back(module.clone());
+            let mut root = module.clone();
+
+            while let Some(current) = queue.pop_front() {
+                match importers.get(&current) {
+                    Some(names) => {
+                        for name in names {
+                            if name != &module && !next.contains_key(name) {
+                                next.insert(name.clone(), current.clone());
+                                queue.push_back(name.clone());
+                            }
+                        }
+                    }
+                    None => {
+                        root = current;
+                        break;
+                    }
+                }
+            }
+
+            let mut chain = vec![root];
+            while let Some(name) = next.get(chain.last().unwrap()) {
+                chain.push(name.clone());
+            }
+
+            for import in imports {
+                let mut chain = chain.clone();
+                chain.push(import);
+                chains.push(chain);
+            }
+        }
+
+        Ok(chains)
+    }
+
     /// Compiles resources into a finalized collection.
     ///
     /// This will take all resources collected so far and convert them into
@@ -1653,7 +2173,7 @@ impl PythonResourceCollector {
         let mut extra_files = Vec::new();
 
         for (name, resource) in &input_resources {
-            let (entry, installs) = resource.to_resource(compiler)?;
+            let (entry, installs) = resource.to_resource(compiler, self.strip_docstrings)?;
 
             for install in installs {
                 extra_files.push(install);
@@ -1662,9 +2182,14 @@ impl PythonResourceCollector {
             resources.insert(name.clone(), entry);
         }
 
+        for (path, (_, location)) in &self.gettext_catalogs {
+            extra_files.push((path.clone(), location.clone(), false));
+        }
+
         Ok(CompiledResourcesCollection {
             resources,
             extra_files,
+            gettext_domains: self.gettext_domains.clone(),
         })
     }
 }
@@ -1692,6 +2217,7 @@ mod tests {
             source: &[u8],
             _filename: &str,
             optimize: BytecodeOptimizationLevel,
+            strip_docstrings: bool,
             _output_mode: CompileMode,
         ) -> Result<Vec<u8>> {
             let mut res = Vec::new();
@@ -1701,6 +2227,9 @@ mod tests {
                 BytecodeOptimizationLevel::One => b"bc1",
                 BytecodeOptimizationLevel::Two => b"bc2",
             });
+            if strip_docstrings {
+                res.extend(b"s");
+            }
 
             res.extend(source);
 
@@ -1720,7 +2249,7 @@ mod tests {
             ..PrePackagedResource::default()
         };
 
-        let (resource, installs) = pre.to_resource(&mut compiler)?;
+        let (resource, installs) = pre.to_resource(&mut compiler, false)?;
 
         assert_eq!(
             resource,
@@ -1738,6 +2267,40 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_describe() {
+        let pre = PrePackagedResource {
+            is_module: true,
+            name: "foo".to_string(),
+            ..PrePackagedResource::default()
+        };
+        assert_eq!(pre.describe(), "module foo");
+
+        let pre = PrePackagedResource {
+            is_module: true,
+            name: "foo".to_string(),
+            in_memory_source: Some(DataLocation::Memory(b"source".to_vec())),
+            relative_path_bytecode: Some((
+                "lib".to_string(),
+                DEFAULT_CACHE_TAG.to_string(),
+                PythonModuleBytecodeProvider::Provided(DataLocation::Memory(b"bc".to_vec())),
+            )),
+            ..PrePackagedResource::default()
+        };
+        assert_eq!(
+            pre.describe(),
+            "module foo (in-memory source, filesystem bytecode)"
+        );
+
+        let pre = PrePackagedResource {
+            is_builtin_extension_module: true,
+            is_extension_module: true,
+            name: "_ssl".to_string(),
+            ..PrePackagedResource::default()
+        };
+        assert_eq!(pre.describe(), "builtin extension module _ssl");
+    }
+
     #[test]
     fn test_resource_conversion_in_memory_source() -> Result<()> {
         let mut compiler = FakeBytecodeCompiler { magic_number: 42 };
@@ -1749,7 +2312,7 @@ mod tests {
             ..PrePackagedResource::default()
         };
 
-        let (resource, installs) = pre.to_resource(&mut compiler)?;
+        let (resource, installs) = pre.to_resource(&mut compiler, false)?;
 
         assert_eq!(
             resource,
@@ -1779,7 +2342,7 @@ mod tests {
             ..PrePackagedResource::default()
         };
 
-        let (resource, installs) = pre.to_resource(&mut compiler)?;
+        let (resource, installs) = pre.to_resource(&mut compiler, false)?;
 
         assert_eq!(
             resource,
@@ -1808,7 +2371,7 @@ mod tests {
             ..PrePackagedResource::default()
         };
 
-        let (resource, installs) = pre.to_resource(&mut compiler)?;
+        let (resource, installs) = pre.to_resource(&mut compiler, false)?;
 
         assert_eq!(
             resource,
@@ -1837,7 +2400,7 @@ mod tests {
             ..PrePackagedResource::default()
         };
 
-        let (resource, installs) = pre.to_resource(&mut compiler)?;
+        let (resource, installs) = pre.to_resource(&mut compiler, false)?;
 
         assert_eq!(
             resource,
@@ -1866,7 +2429,7 @@ mod tests {
             ..PrePackagedResource::default()
         };
 
-        let (resource, installs) = pre.to_resource(&mut compiler)?;
+        let (resource, installs) = pre.to_resource(&mut compiler, false)?;
 
         assert_eq!(
             resource,
@@ -1895,7 +2458,7 @@ mod tests {
             ..PrePackagedResource::default()
         };
 
-        let (resource, installs) = pre.to_resource(&mut compiler)?;
+        let (resource, installs) = pre.to_resource(&mut compiler, false)?;
 
         assert_eq!(
             resource,
@@ -1924,7 +2487,7 @@ mod tests {
             ..PrePackagedResource::default()
         };
 
-        let (resource, installs) = pre.to_resource(&mut compiler)?;
+        let (resource, installs) = pre.to_resource(&mut compiler, false)?;
 
         assert_eq!(
             resource,
@@ -1953,7 +2516,7 @@ mod tests {
             ..PrePackagedResource::default()
         };
 
-        let (resource, installs) = pre.to_resource(&mut compiler)?;
+        let (resource, installs) = pre.to_resource(&mut compiler, false)?;
 
         assert_eq!(
             resource,
@@ -1984,7 +2547,7 @@ mod tests {
             ..PrePackagedResource::default()
         };
 
-        let (resource, installs) = pre.to_resource(&mut compiler)?;
+        let (resource, installs) = pre.to_resource(&mut compiler, false)?;
 
         let mut resources = HashMap::new();
         resources.insert(Cow::Owned("foo".to_string()), Cow::Owned(b"value".to_vec()));
@@ -2018,7 +2581,7 @@ mod tests {
             ..PrePackagedResource::default()
         };
 
-        let (resource, installs) = pre.to_resource(&mut compiler)?;
+        let (resource, installs) = pre.to_resource(&mut compiler, false)?;
 
         let mut resources = HashMap::new();
         resources.insert(Cow::Owned("foo".to_string()), Cow::Owned(b"value".to_vec()));
@@ -2050,7 +2613,7 @@ mod tests {
             ..PrePackagedResource::default()
         };
 
-        let (resource, installs) = pre.to_resource(&mut compiler)?;
+        let (resource, installs) = pre.to_resource(&mut compiler, false)?;
 
         assert_eq!(
             resource,
@@ -2085,7 +2648,7 @@ mod tests {
             ..PrePackagedResource::default()
         };
 
-        let (resource, installs) = pre.to_resource(&mut compiler)?;
+        let (resource, installs) = pre.to_resource(&mut compiler, false)?;
 
         assert_eq!(
             resource,
@@ -2124,7 +2687,7 @@ mod tests {
             ..PrePackagedResource::default()
         };
 
-        let (resource, installs) = pre.to_resource(&mut compiler)?;
+        let (resource, installs) = pre.to_resource(&mut compiler, false)?;
 
         assert_eq!(
             resource,
@@ -2168,7 +2731,7 @@ mod tests {
             ..PrePackagedResource::default()
         };
 
-        let (resource, installs) = pre.to_resource(&mut compiler)?;
+        let (resource, installs) = pre.to_resource(&mut compiler, false)?;
 
         assert_eq!(
             resource,
@@ -2209,7 +2772,7 @@ mod tests {
             ..PrePackagedResource::default()
         };
 
-        let (resource, installs) = pre.to_resource(&mut compiler)?;
+        let (resource, installs) = pre.to_resource(&mut compiler, false)?;
 
         assert_eq!(
             resource,
@@ -2253,7 +2816,7 @@ mod tests {
             ..PrePackagedResource::default()
         };
 
-        let (resource, installs) = pre.to_resource(&mut compiler)?;
+        let (resource, installs) = pre.to_resource(&mut compiler, false)?;
 
         assert_eq!(
             resource,
@@ -2294,7 +2857,7 @@ mod tests {
             ..PrePackagedResource::default()
         };
 
-        let (resource, installs) = pre.to_resource(&mut compiler)?;
+        let (resource, installs) = pre.to_resource(&mut compiler, false)?;
 
         assert_eq!(
             resource,
@@ -2338,7 +2901,7 @@ mod tests {
             ..PrePackagedResource::default()
         };
 
-        let (resource, installs) = pre.to_resource(&mut compiler)?;
+        let (resource, installs) = pre.to_resource(&mut compiler, false)?;
 
         assert_eq!(
             resource,
@@ -2378,7 +2941,7 @@ mod tests {
             ..PrePackagedResource::default()
         };
 
-        let (resource, installs) = pre.to_resource(&mut compiler)?;
+        let (resource, installs) = pre.to_resource(&mut compiler, false)?;
 
         assert_eq!(
             resource,
@@ -2432,7 +2995,7 @@ mod tests {
             ..PrePackagedResource::default()
         };
 
-        let (resource, installs) = pre.to_resource(&mut compiler)?;
+        let (resource, installs) = pre.to_resource(&mut compiler, false)?;
 
         let mut resources = HashMap::new();
         resources.insert(
@@ -2494,7 +3057,7 @@ mod tests {
             ..PrePackagedResource::default()
         };
 
-        let (resource, installs) = pre.to_resource(&mut compiler)?;
+        let (resource, installs) = pre.to_resource(&mut compiler, false)?;
 
         let mut resources = HashMap::new();
         resources.insert(
@@ -2539,7 +3102,7 @@ mod tests {
             ..PrePackagedResource::default()
         };
 
-        let (resource, installs) = pre.to_resource(&mut compiler)?;
+        let (resource, installs) = pre.to_resource(&mut compiler, false)?;
 
         assert_eq!(
             resource,
@@ -2890,6 +3453,67 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_package_sizes() -> Result<()> {
+        let mut r = PythonResourceCollector::new(
+            vec![
+                AbstractResourceLocation::InMemory,
+                AbstractResourceLocation::RelativePath,
+            ],
+            vec![],
+            false,
+            true,
+            DEFAULT_CACHE_TAG,
+        );
+
+        for (name, size, location) in &[
+            ("foo", 3, ConcreteResourceLocation::InMemory),
+            (
+                "foo.bar",
+                5,
+                ConcreteResourceLocation::RelativePath("prefix".to_string()),
+            ),
+            ("baz", 2, ConcreteResourceLocation::InMemory),
+        ] {
+            r.add_python_module_source(
+                &PythonModuleSource {
+                    name: name.to_string(),
+                    source: DataLocation::Memory(vec![42; *size]),
+                    is_package: *name == "foo",
+                    cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                    is_stdlib: false,
+                    is_test: false,
+                },
+                location,
+            )?;
+        }
+
+        r.add_file_data(
+            &FileData {
+                path: PathBuf::from("data/file.txt"),
+                is_executable: false,
+                data: DataLocation::Memory(vec![42; 7]),
+            },
+            &ConcreteResourceLocation::RelativePath("".to_string()),
+        )?;
+
+        let mut compiler = FakeBytecodeCompiler { magic_number: 42 };
+        let resources = r.compile_resources(&mut compiler)?;
+
+        assert_eq!(
+            resources.package_sizes()?,
+            vec![
+                ("baz".to_string(), 2),
+                ("data".to_string(), 7),
+                ("foo".to_string(), 8),
+            ]
+            .into_iter()
+            .collect()
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_add_relative_path_source_module() -> Result<()> {
         let mut r = PythonResourceCollector::new(
@@ -2971,6 +3595,67 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_add_relative_path_package_placeholder() -> Result<()> {
+        let mut r = PythonResourceCollector::new(
+            vec![AbstractResourceLocation::RelativePath],
+            vec![],
+            false,
+            true,
+            DEFAULT_CACHE_TAG,
+        );
+        let location = ConcreteResourceLocation::RelativePath("lib/{package}".to_string());
+
+        r.add_python_module_source(
+            &PythonModuleSource {
+                name: "foo.bar".to_string(),
+                source: DataLocation::Memory(vec![42]),
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &location,
+        )?;
+        r.add_python_package_resource(
+            &PythonPackageResource {
+                leaf_package: "baz.sub".to_string(),
+                relative_name: "data.txt".to_string(),
+                data: DataLocation::Memory(vec![42]),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &location,
+        )?;
+        r.add_file_data(
+            &FileData {
+                path: PathBuf::from("docs/README"),
+                is_executable: false,
+                data: DataLocation::Memory(vec![42]),
+            },
+            &location,
+        )?;
+
+        let mut compiler = FakeBytecodeCompiler { magic_number: 42 };
+        let resources = r.compile_resources(&mut compiler)?;
+
+        assert_eq!(
+            resources
+                .extra_files
+                .iter()
+                .map(|(path, _, _)| path.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                PathBuf::from("lib/baz/baz/sub/data.txt"),
+                PathBuf::from("lib/docs/docs/README"),
+                PathBuf::from("lib/foo/foo/__init__.py"),
+                PathBuf::from("lib/foo/foo/bar.py"),
+            ]
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_add_module_source_with_context() -> Result<()> {
         let mut r = PythonResourceCollector::new(
@@ -3197,6 +3882,13 @@ mod tests {
         );
         assert!(resources.extra_files.is_empty());
 
+        r.set_strip_docstrings(true);
+        let resources = r.compile_resources(&mut compiler)?;
+        assert_eq!(
+            resources.resources.get("foo").unwrap().in_memory_bytecode,
+            Some(Cow::Owned(b"bc0s\x2a".to_vec()))
+        );
+
         Ok(())
     }
 
@@ -4312,6 +5004,95 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_relocate_package_resources() -> Result<()> {
+        let mut r = PythonResourceCollector::new(
+            vec![AbstractResourceLocation::InMemory],
+            vec![],
+            false,
+            false,
+            DEFAULT_CACHE_TAG,
+        );
+
+        r.add_python_package_resource(
+            &PythonPackageResource {
+                leaf_package: "foo".to_string(),
+                relative_name: "Qt/plugins/libbar.so".to_string(),
+                data: DataLocation::Memory(vec![42]),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+
+        // Relative paths aren't allowed by this collector.
+        assert!(r.relocate_package_resources("foo", "lib").is_err());
+
+        r.allowed_locations
+            .push(AbstractResourceLocation::RelativePath);
+        assert_eq!(r.relocate_package_resources("foobar", "lib")?, 0);
+        assert_eq!(r.relocate_package_resources("foo", "lib")?, 1);
+
+        let entry = r.resources.get("foo").unwrap();
+        assert!(entry.in_memory_resources.is_none());
+        assert_eq!(
+            entry.relative_path_package_resources,
+            Some(BTreeMap::from_iter(vec![(
+                "Qt/plugins/libbar.so".to_string(),
+                (
+                    PathBuf::from("lib/foo/Qt/plugins/libbar.so"),
+                    DataLocation::Memory(vec![42])
+                )
+            )]))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_extension_module_shared_library_dependency() -> Result<()> {
+        let mut r = PythonResourceCollector::new(
+            vec![AbstractResourceLocation::InMemory],
+            vec![],
+            false,
+            false,
+            DEFAULT_CACHE_TAG,
+        );
+
+        assert!(r
+            .add_extension_module_shared_library_dependency("foo", "mylib")
+            .is_err());
+
+        r.add_builtin_python_extension_module(&PythonExtensionModule {
+            name: "foo".to_string(),
+            init_fn: Some("PyInit_foo".to_string()),
+            extension_file_suffix: "".to_string(),
+            shared_library: None,
+            object_file_data: vec![],
+            is_package: false,
+            link_libraries: vec![],
+            is_stdlib: false,
+            builtin_default: false,
+            required: false,
+            variant: None,
+            licenses: None,
+            license_public_domain: None,
+        })?;
+
+        r.add_extension_module_shared_library_dependency("foo", "mylib")?;
+        r.add_extension_module_shared_library_dependency("foo", "mylib")?;
+
+        assert_eq!(
+            r.resources
+                .get("foo")
+                .unwrap()
+                .shared_library_dependency_names,
+            Some(vec!["mylib".to_string()])
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_add_in_memory_file_data() -> Result<()> {
         let mut r = PythonResourceCollector::new(
@@ -4430,6 +5211,106 @@ mod tests {
         Ok(())
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_add_non_unicode_file_data() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let mut r = PythonResourceCollector::new(
+            vec![AbstractResourceLocation::InMemory],
+            vec![],
+            false,
+            true,
+            DEFAULT_CACHE_TAG,
+        );
+
+        let res = r.add_file_data(
+            &FileData {
+                path: PathBuf::from(OsStr::from_bytes(b"caf\xe9.txt")),
+                is_executable: false,
+                data: DataLocation::Memory(vec![42]),
+            },
+            &ConcreteResourceLocation::InMemory,
+        );
+
+        assert!(res.is_err());
+        assert!(r.resources.is_empty());
+    }
+
+    #[test]
+    fn test_add_gettext_catalog() -> Result<()> {
+        let mut r = PythonResourceCollector::new(
+            vec![
+                AbstractResourceLocation::InMemory,
+                AbstractResourceLocation::RelativePath,
+            ],
+            vec![],
+            false,
+            false,
+            DEFAULT_CACHE_TAG,
+        );
+
+        let catalog = GettextCatalog {
+            leaf_package: "foo".to_string(),
+            relative_name: "locale/de/LC_MESSAGES/foo.mo".to_string(),
+            locale: "de".to_string(),
+            domain: "foo".to_string(),
+            data: DataLocation::Memory(vec![42]),
+        };
+
+        r.add_gettext_catalog(&catalog, &ConcreteResourceLocation::InMemory)?;
+        assert_eq!(
+            r.resources
+                .get("foo")
+                .and_then(|entry| entry.in_memory_resources.as_ref())
+                .and_then(|resources| resources.get("locale/de/LC_MESSAGES/foo.mo")),
+            Some(&DataLocation::Memory(vec![42]))
+        );
+
+        let location = ConcreteResourceLocation::RelativePath("share".to_string());
+        r.add_gettext_catalog(&catalog, &location)?;
+
+        // Another domain's catalog can't be installed to the same path.
+        let mut other = catalog.clone();
+        other.leaf_package = "bar".to_string();
+        assert!(r.add_gettext_catalog(&other, &location).is_err());
+
+        // Catalogs of a domain must share a locale directory.
+        other.locale = "fr".to_string();
+        assert!(r
+            .add_gettext_catalog(
+                &other,
+                &ConcreteResourceLocation::RelativePath("lib".to_string())
+            )
+            .is_err());
+        r.add_gettext_catalog(&other, &location)?;
+
+        let mut compiler = FakeBytecodeCompiler { magic_number: 42 };
+        let resources = r.compile_resources(&mut compiler)?;
+
+        assert_eq!(
+            resources.extra_files,
+            vec![
+                (
+                    PathBuf::from("share/locale/de/LC_MESSAGES/foo.mo"),
+                    DataLocation::Memory(vec![42]),
+                    false
+                ),
+                (
+                    PathBuf::from("share/locale/fr/LC_MESSAGES/foo.mo"),
+                    DataLocation::Memory(vec![42]),
+                    false
+                ),
+            ]
+        );
+        assert_eq!(
+            resources.gettext_domains.get("foo"),
+            Some(&PathBuf::from("share/locale"))
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_add_file_data_with_context() -> Result<()> {
         let mut r = PythonResourceCollector::new(
@@ -4553,4 +5434,97 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_find_imports_of() -> Result<()> {
+        let mut r = PythonResourceCollector::new(
+            vec![AbstractResourceLocation::InMemory],
+            vec![],
+            false,
+            false,
+            DEFAULT_CACHE_TAG,
+        );
+
+        let source = DataLocation::Memory(Vec::from(
+            "import os\nfrom tkinter import ttk\n\ndef f():\n    import turtle\n",
+        ));
+
+        r.add_python_module_source(
+            &PythonModuleSource {
+                name: "foo".to_string(),
+                source: source.clone(),
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+        r.add_python_module_bytecode_from_source(
+            &PythonModuleBytecodeFromSource {
+                name: "foo".to_string(),
+                source,
+                optimize_level: BytecodeOptimizationLevel::Zero,
+                is_package: false,
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+
+        let imports = r.find_imports_of(|name| name == "tkinter" || name == "turtle")?;
+        assert_eq!(imports.len(), 1);
+        assert_eq!(
+            imports.get("foo").unwrap().iter().collect::<Vec<_>>(),
+            vec!["tkinter"]
+        );
+
+        assert!(r.find_imports_of(|name| name == "sys")?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_import_chains() -> Result<()> {
+        let mut r = PythonResourceCollector::new(
+            vec![AbstractResourceLocation::InMemory],
+            vec![],
+            false,
+            false,
+            DEFAULT_CACHE_TAG,
+        );
+
+        for (name, source) in &[
+            ("app", "import app.cli\nimport app.gui\n"),
+            ("app.cli", "import argparse\n"),
+            ("app.gui", "import app.widgets\n"),
+            ("app.widgets", "import app.gui\nimport tkinter\n"),
+            ("plugin", "import app.widgets\n"),
+        ] {
+            r.add_python_module_source(
+                &PythonModuleSource {
+                    name: name.to_string(),
+                    source: DataLocation::Memory(Vec::from(*source)),
+                    is_package: false,
+                    cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                    is_stdlib: false,
+                    is_test: false,
+                },
+                &ConcreteResourceLocation::InMemory,
+            )?;
+        }
+
+        assert_eq!(
+            r.find_import_chains(|name| name == "tkinter")?,
+            vec![vec![
+                "plugin".to_string(),
+                "app.widgets".to_string(),
+                "tkinter".to_string()
+            ]]
+        );
+        assert!(r.find_import_chains(|name| name == "turtle")?.is_empty());
+
+        Ok(())
+    }
 }
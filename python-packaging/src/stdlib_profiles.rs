@@ -0,0 +1,160 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Presets for trimming unused parts of the Python standard library.
+
+Most applications use a fraction of the standard library. A preset removes
+a coherent group of modules and extension modules that a class of
+applications doesn't need, e.g. GUI support for a network service. Trimmed
+modules are removed along with their submodules.
+
+Modules remaining in a trimmed standard library must not import trimmed
+modules at module level. Otherwise they fail to import at run-time. The
+presets have been composed to satisfy this for the standard libraries of
+the supported Python distributions and packaging verifies it for all
+packaged modules. See `PythonResourceCollector::find_import_chains()`.
+*/
+
+/// GUI toolkits and applications built on them.
+const GUI: &[&str] = &["_tkinter", "idlelib", "tkinter", "turtle", "turtledemo"];
+
+/// Tools used to develop and install Python software.
+const DEVELOPMENT: &[&str] = &[
+    "_msi",
+    "distutils",
+    "ensurepip",
+    "lib2to3",
+    "msilib",
+    "pydoc_data",
+    "venv",
+];
+
+/// Interactive terminal support.
+const TERMINAL: &[&str] = &["_curses", "_curses_panel", "curses", "readline"];
+
+/// Audio files and devices.
+///
+/// `sndhdr` is retained: `email.mime.audio` imports it at module level. It
+/// only imports the trimmed modules when sniffing their formats.
+const AUDIO: &[&str] = &[
+    "aifc",
+    "audioop",
+    "chunk",
+    "ossaudiodev",
+    "sunau",
+    "wave",
+    "winsound",
+];
+
+/// Network servers, mail protocols and embedded databases.
+const SERVICES: &[&str] = &[
+    "_dbm",
+    "_gdbm",
+    "_sqlite3",
+    "asynchat",
+    "asyncore",
+    "cgi",
+    "cgitb",
+    "dbm",
+    "ftplib",
+    "http.server",
+    "imaplib",
+    "mailbox",
+    "nntplib",
+    "poplib",
+    "smtpd",
+    "sqlite3",
+    "telnetlib",
+    "wsgiref",
+    "xmlrpc",
+];
+
+/// A named group of standard library modules to exclude.
+pub struct StdlibProfile {
+    /// Name the profile is selected by.
+    pub name: &'static str,
+
+    /// Groups of modules the profile excludes.
+    groups: &'static [&'static [&'static str]],
+}
+
+impl StdlibProfile {
+    /// Iterate over the names of modules the profile excludes.
+    ///
+    /// Submodules of these modules are excluded as well.
+    pub fn modules(&self) -> impl Iterator<Item = &'static str> {
+        self.groups.iter().flat_map(|group| group.iter().copied())
+    }
+
+    /// Whether the profile excludes a module or package name.
+    pub fn excludes(&self, name: &str) -> bool {
+        self.modules().any(|module| {
+            name == module
+                || (name.starts_with(module) && name.as_bytes().get(module.len()) == Some(&b'.'))
+        })
+    }
+}
+
+/// Profiles selectable via `PythonPackagingPolicy::set_stdlib_profile()`.
+const PROFILES: &[StdlibProfile] = &[
+    StdlibProfile {
+        name: "no-gui",
+        groups: &[GUI],
+    },
+    // Services run without a terminal or a user and are deployed without
+    // Python development tools.
+    StdlibProfile {
+        name: "network-service",
+        groups: &[GUI, DEVELOPMENT, TERMINAL, AUDIO],
+    },
+    // Command line tools retain terminal support but not the means to
+    // serve requests or store data.
+    StdlibProfile {
+        name: "cli-minimal",
+        groups: &[GUI, DEVELOPMENT, AUDIO, SERVICES],
+    },
+];
+
+/// Find a standard library profile by name.
+pub fn find_stdlib_profile(name: &str) -> Option<&'static StdlibProfile> {
+    PROFILES.iter().find(|profile| profile.name == name)
+}
+
+/// Names of available standard library profiles.
+pub fn stdlib_profile_names() -> Vec<&'static str> {
+    PROFILES.iter().map(|profile| profile.name).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles() {
+        let profile = find_stdlib_profile("no-gui").unwrap();
+        assert!(profile.excludes("tkinter"));
+        assert!(profile.excludes("tkinter.ttk"));
+        assert!(!profile.excludes("tkinterx"));
+        assert!(!profile.excludes("curses"));
+
+        let profile = find_stdlib_profile("network-service").unwrap();
+        assert!(profile.excludes("_tkinter"));
+        assert!(profile.excludes("wave"));
+        assert!(!profile.excludes("sndhdr"));
+        assert!(profile.excludes("curses.ascii"));
+        assert!(!profile.excludes("http.server"));
+
+        let profile = find_stdlib_profile("cli-minimal").unwrap();
+        assert!(profile.excludes("http.server"));
+        assert!(!profile.excludes("http.client"));
+        assert!(!profile.excludes("readline"));
+
+        assert!(find_stdlib_profile("everything").is_none());
+        assert_eq!(
+            stdlib_profile_names(),
+            vec!["no-gui", "network-service", "cli-minimal"]
+        );
+    }
+}
@@ -26,6 +26,7 @@ for the canonical specification of this format.
 */
 
 pub mod data;
+pub mod overlay;
 #[allow(unused)]
 pub mod parser;
 pub mod writer;
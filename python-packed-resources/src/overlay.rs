@@ -0,0 +1,156 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Packed resources data appended to executables.
+
+Instead of compiling packed resources data into an executable, the data can
+be appended to the linked executable as an *overlay*. Operating system
+loaders ignore data past the end of the sections they map, so the
+executable runs as before and can locate the data by reading its own file.
+
+The overlay consists of the packed resources data followed by a trailer:
+the length of the data as a little-endian `u64` and the 16 byte
+`OVERLAY_MAGIC`.
+
+Authenticode signing appends a certificate table to PE files, after padding
+them to a multiple of 8 bytes. In signed PE files, the overlay ends before
+the certificate table and its padding instead of at the end of the file.
+*/
+
+use std::convert::TryFrom;
+
+/// Magic bytes ending a file with a packed resources overlay.
+pub const OVERLAY_MAGIC: &[u8; 16] = b"pyembed-overlay1";
+
+/// Size of the trailer following overlay data.
+const TRAILER_SIZE: usize = 8 + OVERLAY_MAGIC.len();
+
+/// Append packed resources data as an overlay to executable data.
+pub fn append_overlay(exe: &mut Vec<u8>, resources: &[u8]) {
+    exe.extend_from_slice(resources);
+    exe.extend_from_slice(&(resources.len() as u64).to_le_bytes());
+    exe.extend_from_slice(OVERLAY_MAGIC);
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset.checked_add(2)?)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset.checked_add(4)?)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// Resolve the file offset of the certificate table of PE data.
+///
+/// Returns `None` if data isn't a PE file or isn't signed.
+fn pe_certificate_table_offset(data: &[u8]) -> Option<usize> {
+    if !data.starts_with(b"MZ") {
+        return None;
+    }
+
+    let pe_offset = read_u32(data, 0x3c)? as usize;
+    if data.get(pe_offset..pe_offset.checked_add(4)?)? != b"PE\0\0" {
+        return None;
+    }
+
+    // The optional header follows the 4 byte signature and 20 byte COFF header.
+    let optional_header = pe_offset.checked_add(24)?;
+
+    let data_directories = match read_u16(data, optional_header)? {
+        0x10b => optional_header.checked_add(96)?,
+        0x20b => optional_header.checked_add(112)?,
+        _ => return None,
+    };
+
+    // The certificate table is the 5th data directory. Its address is a file offset.
+    let entry = data_directories.checked_add(4 * 8)?;
+    let offset = read_u32(data, entry)? as usize;
+    let size = read_u32(data, entry.checked_add(4)?)? as usize;
+
+    if offset == 0 || size == 0 || offset > data.len() {
+        None
+    } else {
+        Some(offset)
+    }
+}
+
+/// Find packed resources data in an overlay at the end of file data.
+///
+/// The overlay of signed PE files is found before their certificate table.
+/// Returns `None` if the data doesn't end with a valid overlay.
+pub fn find_overlay(data: &[u8]) -> Option<&[u8]> {
+    let data = &data[..pe_certificate_table_offset(data).unwrap_or(data.len())];
+
+    // Strip padding to a multiple of 8 bytes added by signing tools.
+    let padding = data.iter().rev().take(7).take_while(|b| **b == 0).count();
+    let data = &data[..data.len() - padding];
+
+    if data.len() < TRAILER_SIZE || !data.ends_with(OVERLAY_MAGIC) {
+        return None;
+    }
+
+    let trailer_start = data.len() - TRAILER_SIZE;
+
+    let mut length = [0u8; 8];
+    length.copy_from_slice(&data[trailer_start..trailer_start + 8]);
+    let length = usize::try_from(u64::from_le_bytes(length)).ok()?;
+
+    let start = trailer_start.checked_sub(length)?;
+
+    Some(&data[start..trailer_start])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlay() {
+        let mut exe = b"executable".to_vec();
+        assert_eq!(find_overlay(&exe), None);
+
+        append_overlay(&mut exe, b"resources");
+        assert!(exe.starts_with(b"executable"));
+        assert_eq!(find_overlay(&exe), Some(&b"resources"[..]));
+
+        let mut exe = vec![];
+        append_overlay(&mut exe, b"");
+        assert_eq!(find_overlay(&exe), Some(&b""[..]));
+
+        // The recorded length exceeds the file.
+        let mut exe = b"resources".to_vec();
+        exe.extend_from_slice(&100u64.to_le_bytes());
+        exe.extend_from_slice(OVERLAY_MAGIC);
+        assert_eq!(find_overlay(&exe), None);
+    }
+
+    #[test]
+    fn test_overlay_signed_pe() {
+        // A PE32+ file with data directories ending at 0x40 + 24 + 112 + 5 * 8.
+        let mut exe = vec![0u8; 0x100];
+        exe[0..2].copy_from_slice(b"MZ");
+        exe[0x3c..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+        exe[0x40..0x44].copy_from_slice(b"PE\0\0");
+        exe[0x58..0x5a].copy_from_slice(&0x20bu16.to_le_bytes());
+
+        append_overlay(&mut exe, b"resources");
+        assert_eq!(find_overlay(&exe), Some(&b"resources"[..]));
+
+        // Sign it: pad to 8 bytes and append a certificate table.
+        exe.extend(vec![0; (8 - exe.len() % 8) % 8]);
+        let certificate_offset = exe.len() as u32;
+        exe.extend_from_slice(&[0xff; 16]);
+        let entry = 0x58 + 112 + 4 * 8;
+        exe[entry..entry + 4].copy_from_slice(&certificate_offset.to_le_bytes());
+        exe[entry + 4..entry + 8].copy_from_slice(&16u32.to_le_bytes());
+
+        assert_eq!(find_overlay(&exe), Some(&b"resources"[..]));
+
+        // A certificate table past the end of the file is ignored.
+        exe[entry..entry + 4].copy_from_slice(&0x10000u32.to_le_bytes());
+        assert_eq!(find_overlay(&exe), None);
+    }
+}
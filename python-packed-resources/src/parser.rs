@@ -36,6 +36,7 @@ struct BlobSection {
 #[derive(Clone, Copy, Debug)]
 struct BlobSectionReadState {
     offset: usize,
+    end: usize,
     interior_padding: BlobInteriorPadding,
 }
 
@@ -48,6 +49,10 @@ pub struct ResourceParserIterator<'a> {
     blob_sections: [Option<BlobSectionReadState>; 256],
     claimed_resources_count: usize,
     read_resources_count: usize,
+    /// Offset where the resources index ends and blob data begins.
+    blob_start_offset: usize,
+    /// Offset where blob data ends.
+    blob_end_offset: usize,
 }
 
 impl<'a> ResourceParserIterator<'a> {
@@ -61,39 +66,95 @@ impl<'a> ResourceParserIterator<'a> {
     /// This accepts a reference to the original blobs payload, an array of
     /// current blob section offsets, the resource field being accessed, and the
     /// length of the blob and returns a slice to that blob.
-    fn resolve_blob_data(&mut self, resource_field: ResourceField, length: usize) -> &'a [u8] {
-        let mut state = self.blob_sections[resource_field as usize]
+    ///
+    /// An error is returned if the blob section isn't defined or if the blob
+    /// extends beyond the end of its section.
+    fn resolve_blob_data(
+        &mut self,
+        resource_field: ResourceField,
+        length: usize,
+    ) -> Result<&'a [u8], &'static str> {
+        let state = self.blob_sections[resource_field as usize]
             .as_mut()
-            .expect("blob state not found");
-
-        let blob = &self.data[state.offset..state.offset + length];
+            .ok_or("blob section for resource field not defined")?;
 
         let increment = match &state.interior_padding {
-            BlobInteriorPadding::None => length,
-            BlobInteriorPadding::Null => length + 1,
+            BlobInteriorPadding::None => Some(length),
+            BlobInteriorPadding::Null => length.checked_add(1),
         };
 
-        state.offset += increment;
+        let end = state.offset.checked_add(length);
+        let next_offset = increment.and_then(|x| state.offset.checked_add(x));
+
+        match (end, next_offset) {
+            (Some(end), Some(next_offset)) if next_offset <= state.end => {
+                let blob = &self.data[state.offset..end];
+                state.offset = next_offset;
+
+                Ok(blob)
+            }
+            _ => Err("blob data extends beyond end of blob section"),
+        }
+    }
 
-        blob
+    /// Resolve a blob holding a UTF-8 string.
+    fn resolve_str(
+        &mut self,
+        resource_field: ResourceField,
+        length: usize,
+    ) -> Result<&'a str, &'static str> {
+        std::str::from_utf8(self.resolve_blob_data(resource_field, length)?)
+            .map_err(|_| "blob data is not valid UTF-8")
     }
 
     #[cfg(unix)]
-    fn resolve_path(&mut self, resource_field: ResourceField, length: usize) -> Cow<'a, Path> {
-        let path_str = OsStr::from_bytes(self.resolve_blob_data(resource_field, length));
-        Cow::Borrowed(Path::new(path_str))
+    fn resolve_path(
+        &mut self,
+        resource_field: ResourceField,
+        length: usize,
+    ) -> Result<Cow<'a, Path>, &'static str> {
+        let path_str = OsStr::from_bytes(self.resolve_blob_data(resource_field, length)?);
+        Ok(Cow::Borrowed(Path::new(path_str)))
     }
 
     #[cfg(windows)]
-    fn resolve_path(&mut self, resource_field: ResourceField, length: usize) -> Cow<'a, Path> {
-        let raw = self.resolve_blob_data(resource_field, length);
-        let raw = unsafe { std::slice::from_raw_parts(raw.as_ptr() as *const u16, raw.len() / 2) };
+    fn resolve_path(
+        &mut self,
+        resource_field: ResourceField,
+        length: usize,
+    ) -> Result<Cow<'a, Path>, &'static str> {
+        let raw = self.resolve_blob_data(resource_field, length)?;
+
+        if raw.len() % 2 != 0 {
+            return Err("path blob data is not a sequence of 16-bit units");
+        }
+
+        let raw = raw
+            .chunks_exact(2)
+            .map(|x| u16::from_le_bytes([x[0], x[1]]))
+            .collect::<Vec<_>>();
 
         // There isn't an API that lets us get a OsStr from &[u16]. So we need to use
         // owned types.
-        let path_string = OsString::from_wide(raw);
+        let path_string = OsString::from_wide(&raw);
 
-        Cow::Owned(PathBuf::from(path_string))
+        Ok(Cow::Owned(PathBuf::from(path_string)))
+    }
+
+    /// Obtain the offset of the resources index reader.
+    ///
+    /// The offset is relative to the end of the 8 byte header.
+    fn index_offset(&self) -> usize {
+        self.reader.position() as usize
+    }
+
+    /// Find a blob section whose data was not entirely consumed.
+    fn unconsumed_blob_section(&self) -> Option<u8> {
+        self.blob_sections
+            .iter()
+            .enumerate()
+            .find(|(_, state)| matches!(state, Some(state) if state.offset != state.end))
+            .map(|(field, _)| field as u8)
     }
 
     fn parse_next(&mut self) -> Result<Option<Resource<'a, u8>>, &'static str> {
@@ -152,9 +213,7 @@ impl<'a> ResourceParserIterator<'a> {
                         .map_err(|_| "failed reading resource name length")?
                         as usize;
 
-                    let name = unsafe {
-                        std::str::from_utf8_unchecked(self.resolve_blob_data(field_type, l))
-                    };
+                    let name = self.resolve_str(field_type, l)?;
 
                     current_resource_name = Some(name);
                     current_resource.name = Cow::Borrowed(name);
@@ -173,7 +232,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.in_memory_source =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)?));
                 }
                 ResourceField::InMemoryBytecode => {
                     let l = self
@@ -183,7 +242,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.in_memory_bytecode =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)?));
                 }
                 ResourceField::InMemoryBytecodeOpt1 => {
                     let l = self
@@ -193,7 +252,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.in_memory_bytecode_opt1 =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)?));
                 }
                 ResourceField::InMemoryBytecodeOpt2 => {
                     let l = self
@@ -203,7 +262,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.in_memory_bytecode_opt2 =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)?));
                 }
                 ResourceField::InMemoryExtensionModuleSharedLibrary => {
                     let l = self
@@ -213,7 +272,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.in_memory_extension_module_shared_library =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)?));
                 }
 
                 ResourceField::InMemoryResourcesData => {
@@ -223,7 +282,9 @@ impl<'a> ResourceParserIterator<'a> {
                         .map_err(|_| "failed reading resources length")?
                         as usize;
 
-                    let mut resources = HashMap::with_capacity(resource_count);
+                    // Every entry occupies index bytes. So bound the pre-allocation by
+                    // the data size to avoid huge allocations from corrupted counts.
+                    let mut resources = HashMap::with_capacity(resource_count.min(self.data.len()));
 
                     for _ in 0..resource_count {
                         let resource_name_length = self
@@ -232,11 +293,7 @@ impl<'a> ResourceParserIterator<'a> {
                             .map_err(|_| "failed reading resource name")?
                             as usize;
 
-                        let resource_name = unsafe {
-                            std::str::from_utf8_unchecked(
-                                self.resolve_blob_data(field_type, resource_name_length),
-                            )
-                        };
+                        let resource_name = self.resolve_str(field_type, resource_name_length)?;
 
                         let resource_length = self
                             .reader
@@ -244,7 +301,7 @@ impl<'a> ResourceParserIterator<'a> {
                             .map_err(|_| "failed reading resource length")?
                             as usize;
 
-                        let resource_data = self.resolve_blob_data(field_type, resource_length);
+                        let resource_data = self.resolve_blob_data(field_type, resource_length)?;
 
                         resources
                             .insert(Cow::Borrowed(resource_name), Cow::Borrowed(resource_data));
@@ -260,7 +317,7 @@ impl<'a> ResourceParserIterator<'a> {
                         .map_err(|_| "failed reading package distribution length")?
                         as usize;
 
-                    let mut resources = HashMap::with_capacity(resource_count);
+                    let mut resources = HashMap::with_capacity(resource_count.min(self.data.len()));
 
                     for _ in 0..resource_count {
                         let name_length = self
@@ -269,18 +326,14 @@ impl<'a> ResourceParserIterator<'a> {
                             .map_err(|_| "failed reading distribution metadata name")?
                             as usize;
 
-                        let name = unsafe {
-                            std::str::from_utf8_unchecked(
-                                self.resolve_blob_data(field_type, name_length),
-                            )
-                        };
+                        let name = self.resolve_str(field_type, name_length)?;
 
                         let resource_length =
                             self.reader.read_u64::<LittleEndian>().map_err(|_| {
                                 "failed reading package distribution resource length"
                             })? as usize;
 
-                        let resource_data = self.resolve_blob_data(field_type, resource_length);
+                        let resource_data = self.resolve_blob_data(field_type, resource_length)?;
 
                         resources.insert(Cow::Borrowed(name), Cow::Borrowed(resource_data));
                     }
@@ -296,7 +349,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.in_memory_shared_library =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)?));
                 }
 
                 ResourceField::SharedLibraryDependencyNames => {
@@ -314,11 +367,7 @@ impl<'a> ResourceParserIterator<'a> {
                                 "failed reading shared library dependency name length"
                             })? as usize;
 
-                        let name = unsafe {
-                            std::str::from_utf8_unchecked(
-                                self.resolve_blob_data(field_type, name_length),
-                            )
-                        };
+                        let name = self.resolve_str(field_type, name_length)?;
 
                         names.push(Cow::Borrowed(name));
                     }
@@ -333,7 +382,7 @@ impl<'a> ResourceParserIterator<'a> {
                         .map_err(|_| "failed reading Python module relative path length")?
                         as usize;
 
-                    let path = self.resolve_path(field_type, path_length);
+                    let path = self.resolve_path(field_type, path_length)?;
 
                     current_resource.relative_path_module_source = Some(path);
                 }
@@ -344,7 +393,7 @@ impl<'a> ResourceParserIterator<'a> {
                             "failed reading Python module bytecode relative path length"
                         })? as usize;
 
-                    let path = self.resolve_path(field_type, path_length);
+                    let path = self.resolve_path(field_type, path_length)?;
 
                     current_resource.relative_path_module_bytecode = Some(path);
                 }
@@ -354,7 +403,7 @@ impl<'a> ResourceParserIterator<'a> {
                         "failed reading Python module bytecode opt 1 relative path length"
                     })? as usize;
 
-                    let path = self.resolve_path(field_type, path_length);
+                    let path = self.resolve_path(field_type, path_length)?;
 
                     current_resource.relative_path_module_bytecode_opt1 = Some(path);
                 }
@@ -364,7 +413,7 @@ impl<'a> ResourceParserIterator<'a> {
                         "failed reading Python module bytecode opt 2 relative path length"
                     })? as usize;
 
-                    let path = self.resolve_path(field_type, path_length);
+                    let path = self.resolve_path(field_type, path_length)?;
 
                     current_resource.relative_path_module_bytecode_opt2 = Some(path);
                 }
@@ -374,7 +423,7 @@ impl<'a> ResourceParserIterator<'a> {
                         "failed reading Python extension module shared library relative path length"
                     })? as usize;
 
-                    let path = self.resolve_path(field_type, path_length);
+                    let path = self.resolve_path(field_type, path_length)?;
 
                     current_resource.relative_path_extension_module_shared_library = Some(path);
                 }
@@ -385,7 +434,7 @@ impl<'a> ResourceParserIterator<'a> {
                             "failed reading package resources relative path item count"
                         })? as usize;
 
-                    let mut resources = HashMap::with_capacity(resource_count);
+                    let mut resources = HashMap::with_capacity(resource_count.min(self.data.len()));
 
                     for _ in 0..resource_count {
                         let resource_name_length = self
@@ -394,11 +443,7 @@ impl<'a> ResourceParserIterator<'a> {
                             .map_err(|_| "failed reading resource name")?
                             as usize;
 
-                        let resource_name = unsafe {
-                            std::str::from_utf8_unchecked(
-                                self.resolve_blob_data(field_type, resource_name_length),
-                            )
-                        };
+                        let resource_name = self.resolve_str(field_type, resource_name_length)?;
 
                         let path_length = self
                             .reader
@@ -406,7 +451,7 @@ impl<'a> ResourceParserIterator<'a> {
                             .map_err(|_| "failed reading resource path length")?
                             as usize;
 
-                        let path = self.resolve_path(field_type, path_length);
+                        let path = self.resolve_path(field_type, path_length)?;
 
                         resources.insert(Cow::Borrowed(resource_name), path);
                     }
@@ -419,7 +464,7 @@ impl<'a> ResourceParserIterator<'a> {
                         "failed reading package distribution relative path item count"
                     })? as usize;
 
-                    let mut resources = HashMap::with_capacity(resource_count);
+                    let mut resources = HashMap::with_capacity(resource_count.min(self.data.len()));
 
                     for _ in 0..resource_count {
                         let name_length = self
@@ -428,11 +473,7 @@ impl<'a> ResourceParserIterator<'a> {
                             .map_err(|_| "failed reading package distribution metadata name")?
                             as usize;
 
-                        let name = unsafe {
-                            std::str::from_utf8_unchecked(
-                                self.resolve_blob_data(field_type, name_length),
-                            )
-                        };
+                        let name = self.resolve_str(field_type, name_length)?;
 
                         let path_length = self
                             .reader
@@ -440,7 +481,7 @@ impl<'a> ResourceParserIterator<'a> {
                             .map_err(|_| "failed reading package distribution path length")?
                             as usize;
 
-                        let path = self.resolve_path(field_type, path_length);
+                        let path = self.resolve_path(field_type, path_length)?;
 
                         resources.insert(Cow::Borrowed(name), path);
                     }
@@ -484,7 +525,7 @@ impl<'a> ResourceParserIterator<'a> {
                         as usize;
 
                     current_resource.file_data_embedded =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)?));
                 }
 
                 ResourceField::FileDataUtf8RelativePath => {
@@ -494,9 +535,8 @@ impl<'a> ResourceParserIterator<'a> {
                         .map_err(|_| "failed reading file data relative path length")?
                         as usize;
 
-                    current_resource.file_data_utf8_relative_path = Some(Cow::Borrowed(unsafe {
-                        std::str::from_utf8_unchecked(self.resolve_blob_data(field_type, l))
-                    }));
+                    current_resource.file_data_utf8_relative_path =
+                        Some(Cow::Borrowed(self.resolve_str(field_type, l)?));
                 }
             }
         }
@@ -516,7 +556,11 @@ impl<'a> Iterator for ResourceParserIterator<'a> {
                 Some(entry) => Some(Ok(entry)),
                 None => None,
             },
-            Err(e) => Some(Err(e)),
+            Err(e) => {
+                // The parser state is unknown after an error. So stop iterating.
+                self.done = true;
+                Some(Err(e))
+            }
         }
     }
 }
@@ -535,6 +579,109 @@ pub fn load_resources<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>,
     }
 }
 
+/// Describes a structural problem with a packed resources blob.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VerifyError {
+    /// Offset within the data at which the problem was detected, if known.
+    pub offset: Option<usize>,
+
+    /// Index of the resource being parsed when the problem was detected, if any.
+    pub resource_index: Option<usize>,
+
+    /// Description of the problem.
+    pub message: &'static str,
+}
+
+impl std::fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message)?;
+
+        if let Some(index) = self.resource_index {
+            write!(f, " (resource #{})", index)?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " (offset {})", offset)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Strictly validate a packed resources data blob.
+///
+/// In addition to everything checked when loading resources, this verifies
+/// that every resource can be parsed, that resource names are unique, that
+/// the resources index and blob sections are consumed exactly, and that no
+/// data trails the blob sections.
+///
+/// This function only operates on a byte slice and has no side effects,
+/// making it suitable as a fuzzing target.
+///
+/// Returns the number of resources on success.
+pub fn verify_resources(data: &[u8]) -> Result<usize, VerifyError> {
+    let error = |offset, resource_index, message| VerifyError {
+        offset,
+        resource_index,
+        message,
+    };
+
+    let mut resources = load_resources(data).map_err(|e| error(None, None, e))?;
+
+    let mut names = HashSet::new();
+    let mut count = 0;
+
+    loop {
+        // Offsets are relative to the start of the data, including the header.
+        let offset = HEADER_V3.len() + resources.index_offset();
+
+        match resources.next() {
+            Some(Ok(resource)) => {
+                if !names.insert(resource.name) {
+                    return Err(error(Some(offset), Some(count), "duplicate resource name"));
+                }
+
+                count += 1;
+            }
+            Some(Err(e)) => {
+                return Err(error(
+                    Some(HEADER_V3.len() + resources.index_offset()),
+                    Some(count),
+                    e,
+                ));
+            }
+            None => break,
+        }
+    }
+
+    if count > 0 && resources.index_offset() != resources.blob_start_offset {
+        return Err(error(
+            Some(HEADER_V3.len() + resources.index_offset()),
+            None,
+            "resources index length does not match its content",
+        ));
+    }
+
+    if resources.unconsumed_blob_section().is_some() {
+        return Err(error(
+            None,
+            None,
+            "blob section contains data not referenced by any resource",
+        ));
+    }
+
+    if HEADER_V3.len() + resources.blob_end_offset != data.len() {
+        return Err(error(
+            Some(HEADER_V3.len() + resources.blob_end_offset),
+            None,
+            "data trails blob sections",
+        ));
+    }
+
+    Ok(count)
+}
+
 fn load_resources_v3<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &'static str> {
     let mut reader = Cursor::new(data);
 
@@ -637,9 +784,20 @@ fn load_resources_v3<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &
     let mut current_blob_offset = 0;
 
     for section in &blob_sections {
-        let section_start_offset = blob_start_offset + current_blob_offset;
+        let section_start_offset = blob_start_offset
+            .checked_add(current_blob_offset)
+            .ok_or("blob section offset overflow")?;
+        let section_end_offset = section_start_offset
+            .checked_add(section.raw_payload_length)
+            .ok_or("blob section offset overflow")?;
+
+        if section_end_offset > data.len() {
+            return Err("blob section extends beyond end of data");
+        }
+
         blob_offsets[section.resource_field as usize] = Some(BlobSectionReadState {
             offset: section_start_offset,
+            end: section_end_offset,
             interior_padding: match section.interior_padding {
                 Some(padding) => padding,
                 None => BlobInteriorPadding::None,
@@ -655,6 +813,8 @@ fn load_resources_v3<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &
         blob_sections: blob_offsets,
         claimed_resources_count: resources_count,
         read_resources_count: 0,
+        blob_start_offset,
+        blob_end_offset: blob_start_offset + current_blob_offset,
     })
 }
 
@@ -730,6 +890,115 @@ mod tests {
         assert_eq!(res.next(), None);
     }
 
+    fn fuzz_resource() -> Resource<'static, u8> {
+        let mut in_memory_resources = HashMap::new();
+        in_memory_resources.insert(Cow::from("resource"), Cow::from(b"value".to_vec()));
+
+        Resource {
+            is_module: true,
+            name: Cow::from("foo"),
+            is_package: true,
+            in_memory_source: Some(Cow::from(b"source".to_vec())),
+            in_memory_bytecode: Some(Cow::from(b"bytecode".to_vec())),
+            in_memory_package_resources: Some(in_memory_resources),
+            relative_path_module_source: Some(Cow::from(Path::new("foo/__init__.py"))),
+            shared_library_dependency_names: Some(vec![Cow::from("libfoo")]),
+            ..Resource::default()
+        }
+    }
+
+    #[test]
+    fn test_verify_valid() {
+        let resource2 = Resource {
+            name: Cow::from("bar"),
+            ..Resource::default()
+        };
+
+        for padding in &[BlobInteriorPadding::None, BlobInteriorPadding::Null] {
+            let mut data = Vec::new();
+            write_packed_resources_v3(
+                &[fuzz_resource(), resource2.clone()],
+                &mut data,
+                Some(*padding),
+            )
+            .unwrap();
+
+            assert_eq!(verify_resources(&data), Ok(2));
+        }
+
+        let mut data = Vec::new();
+        write_packed_resources_v3::<Resource<u8>, _>(&[], &mut data, None).unwrap();
+        assert_eq!(verify_resources(&data), Ok(0));
+    }
+
+    #[test]
+    fn test_verify_trailing_data() {
+        let mut data = Vec::new();
+        write_packed_resources_v3(&[fuzz_resource()], &mut data, None).unwrap();
+        let len = data.len();
+        data.push(0);
+
+        assert_eq!(
+            verify_resources(&data),
+            Err(VerifyError {
+                offset: Some(len),
+                resource_index: None,
+                message: "data trails blob sections",
+            })
+        );
+    }
+
+    #[test]
+    fn test_verify_duplicate_names() {
+        let mut data = Vec::new();
+        write_packed_resources_v3(&[fuzz_resource(), fuzz_resource()], &mut data, None).unwrap();
+
+        let err = verify_resources(&data).unwrap_err();
+        assert_eq!(err.message, "duplicate resource name");
+        assert_eq!(err.resource_index, Some(1));
+    }
+
+    #[test]
+    fn test_verify_blob_overrun() {
+        let resource = Resource {
+            name: Cow::from("foo"),
+            ..Resource::default()
+        };
+
+        let mut data = Vec::new();
+        write_packed_resources_v3(&[resource], &mut data, None).unwrap();
+
+        // Shrink the advertised length of the blob section holding the name.
+        let length = b"\x03\x00\x00\x00\x00\x00\x00\x00";
+        let pos = data.windows(8).position(|x| x == length).unwrap();
+        data[pos] = 0x02;
+
+        let err = verify_resources(&data).unwrap_err();
+        assert_eq!(err.message, "blob data extends beyond end of blob section");
+        assert_eq!(err.resource_index, Some(0));
+    }
+
+    #[test]
+    fn test_verify_corrupted_never_panics() {
+        let mut data = Vec::new();
+        write_packed_resources_v3(&[fuzz_resource()], &mut data, None).unwrap();
+
+        // Every truncation must be rejected without panicking.
+        for i in 0..data.len() {
+            assert!(verify_resources(&data[0..i]).is_err());
+        }
+
+        // Mutating any byte must not panic.
+        for i in 0..data.len() {
+            for value in &[0x00, 0x01, 0x7f, 0x80, 0xff] {
+                let mut mutated = data.clone();
+                mutated[i] = *value;
+                let _ = verify_resources(&mutated);
+                let _ = load_resources(&mutated).map(|x| x.collect::<Vec<_>>());
+            }
+        }
+    }
+
     #[test]
     fn test_just_resource_name() {
         let resource = Resource {
@@ -1556,4 +1825,69 @@ mod tests {
 
         assert_eq!(resources, loaded);
     }
+
+    #[test]
+    fn test_non_ascii_round_trip() {
+        let mut package_resources = HashMap::new();
+        package_resources.insert(Cow::from("données.txt"), Cow::from(b"data".to_vec()));
+
+        let mut relative_resources = HashMap::new();
+        relative_resources.insert(
+            Cow::from("画像/ファイル.png"),
+            Cow::from(Path::new("lib/café/画像/ファイル.png")),
+        );
+
+        let resources: Vec<Resource<u8>> = vec![
+            Resource {
+                flavor: ResourceFlavor::Module,
+                name: Cow::from("café.モジュール"),
+                is_package: true,
+                in_memory_source: Some(Cow::from("print('été')".as_bytes().to_vec())),
+                in_memory_package_resources: Some(package_resources),
+                relative_path_module_source: Some(Cow::from(Path::new(
+                    "lib/café/モジュール/__init__.py",
+                ))),
+                relative_path_package_resources: Some(relative_resources),
+                ..Resource::default()
+            },
+            Resource {
+                flavor: ResourceFlavor::None,
+                name: Cow::from("données/ファイル.txt"),
+                is_utf8_filename_data: true,
+                file_data_utf8_relative_path: Some(Cow::from("lib/données/ファイル.txt")),
+                ..Resource::default()
+            },
+        ];
+
+        let mut data = Vec::new();
+        write_packed_resources_v3(&resources, &mut data, None).unwrap();
+        let loaded = load_resources(&data)
+            .unwrap()
+            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .unwrap();
+
+        assert_eq!(resources, loaded);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_path_round_trip() {
+        // Filesystem paths on Unix are bytes and are stored as such.
+        let path = Path::new(OsStr::from_bytes(b"lib/caf\xe9.py"));
+
+        let resource = Resource {
+            name: Cow::from("foo"),
+            relative_path_module_source: Some(Cow::from(path)),
+            ..Resource::default()
+        };
+
+        let mut data = Vec::new();
+        write_packed_resources_v3(&[resource.clone()], &mut data, None).unwrap();
+        let loaded = load_resources(&data)
+            .unwrap()
+            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .unwrap();
+
+        assert_eq!(loaded, vec![resource]);
+    }
 }
@@ -10,7 +10,12 @@ use {
     },
     anyhow::{anyhow, Context, Result},
     byteorder::{LittleEndian, WriteBytesExt},
-    std::{collections::BTreeMap, convert::TryFrom, io::Write, path::Path},
+    std::{
+        collections::{BTreeMap, HashMap},
+        convert::TryFrom,
+        io::Write,
+        path::Path,
+    },
 };
 
 #[cfg(unix)]
@@ -671,7 +676,7 @@ where
             dest.write_u32::<LittleEndian>(l)
                 .context("writing in-memory resources data length")?;
 
-            for (name, value) in resources.iter() {
+            for (name, value) in sorted_entries(resources) {
                 let name_length = u16::try_from(name.as_bytes().len())
                     .context("converting resource name length to u16")?;
                 dest.write_u16::<LittleEndian>(name_length)
@@ -689,7 +694,7 @@ where
             dest.write_u32::<LittleEndian>(l)
                 .context("writing in-memory package distribution length")?;
 
-            for (name, value) in metadata {
+            for (name, value) in sorted_entries(metadata) {
                 let name_length = u16::try_from(name.as_bytes().len())
                     .context("converting distribution name length to u16")?;
                 dest.write_u16::<LittleEndian>(name_length)
@@ -777,7 +782,7 @@ where
             dest.write_u32::<LittleEndian>(l)
                 .context("writing relative path resources resources data length")?;
 
-            for (name, path) in resources.iter() {
+            for (name, path) in sorted_entries(resources) {
                 let name_length = u16::try_from(name.as_bytes().len())
                     .context("converting resource name length to u16")?;
                 let path_length = u32::try_from(path_bytes_length(path))
@@ -797,7 +802,7 @@ where
             dest.write_u32::<LittleEndian>(l)
                 .context("writing relative path distribution data length")?;
 
-            for (name, path) in metadata.iter() {
+            for (name, path) in sorted_entries(metadata) {
                 let name_length = u16::try_from(name.as_bytes().len())
                     .context("converting resource name length to u16")?;
                 let path_length = u32::try_from(path_bytes_length(path))
@@ -869,6 +874,38 @@ where
     }
 }
 
+/// Entries of a map, sorted by key.
+///
+/// Maps are written in key order so packed resources data doesn't depend on
+/// `HashMap` iteration order, which differs between processes.
+fn sorted_entries<K: Ord, V>(map: &HashMap<K, V>) -> Vec<(&K, &V)> {
+    let mut entries = map.iter().collect::<Vec<_>>();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+
+    entries
+}
+
+/// Verify resources are sorted by name, without duplicates.
+///
+/// The index of a resource is its position in packed resources data. In
+/// this order, indices only depend on the set of resources, not on the order
+/// they were collected in, so data built on different machines is identical.
+pub fn check_resources_order<'a, T: AsRef<Resource<'a, u8>>>(resources: &[T]) -> Result<()> {
+    for pair in resources.windows(2) {
+        let (a, b) = (&pair[0].as_ref().name, &pair[1].as_ref().name);
+
+        if a >= b {
+            return Err(anyhow!(
+                "resource {} comes after {}; resources must be sorted by name and unique",
+                b,
+                a
+            ));
+        }
+    }
+
+    Ok(())
+}
+
 /// Write packed resources data, version 3.
 #[allow(clippy::cognitive_complexity)]
 pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
@@ -1067,7 +1104,7 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
 
     for module in modules {
         if let Some(resources) = &module.as_ref().in_memory_package_resources {
-            for (key, value) in resources.iter() {
+            for (key, value) in sorted_entries(resources) {
                 dest.write_all(key.as_bytes())?;
                 add_interior_padding(dest)?;
                 dest.write_all(value)?;
@@ -1078,7 +1115,7 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
 
     for module in modules {
         if let Some(resources) = &module.as_ref().in_memory_distribution_resources {
-            for (key, value) in resources {
+            for (key, value) in sorted_entries(resources) {
                 dest.write_all(key.as_bytes())?;
                 add_interior_padding(dest)?;
                 dest.write_all(value)?;
@@ -1143,7 +1180,7 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
 
     for module in modules {
         if let Some(resources) = &module.as_ref().relative_path_package_resources {
-            for (key, path) in resources.iter() {
+            for (key, path) in sorted_entries(resources) {
                 dest.write_all(key.as_bytes())?;
                 add_interior_padding(dest)?;
                 dest.write_all(&path_to_bytes(path))?;
@@ -1154,7 +1191,7 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
 
     for module in modules {
         if let Some(resources) = &module.as_ref().relative_path_distribution_resources {
-            for (key, path) in resources {
+            for (key, path) in sorted_entries(resources) {
                 dest.write_all(key.as_bytes())?;
                 add_interior_padding(dest)?;
                 dest.write_all(&path_to_bytes(path))?;
@@ -1251,4 +1288,59 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_write_package_resources_sorted() -> Result<()> {
+        let names = (0..32)
+            .map(|i| format!("file{}.txt", i))
+            .collect::<Vec<_>>();
+
+        let mut forward = HashMap::new();
+        for name in &names {
+            forward.insert(Cow::from(name.as_str()), Cow::from(name.as_bytes()));
+        }
+        let mut backward = HashMap::with_capacity(1024);
+        for name in names.iter().rev() {
+            backward.insert(Cow::from(name.as_str()), Cow::from(name.as_bytes()));
+        }
+
+        let write = |resources| -> Result<Vec<u8>> {
+            let mut data = Vec::new();
+            let module = Resource {
+                flavor: ResourceFlavor::Module,
+                name: Cow::Owned("foo".to_string()),
+                in_memory_package_resources: Some(resources),
+                ..Resource::default()
+            };
+            write_packed_resources_v3(&[module], &mut data, None)?;
+
+            Ok(data)
+        };
+
+        let data = write(forward)?;
+        assert_eq!(data, write(backward)?);
+
+        let mut sorted = names.clone();
+        sorted.sort();
+        let blob = sorted
+            .iter()
+            .map(|name| format!("{}{}", name, name))
+            .collect::<String>();
+        assert!(data.ends_with(blob.as_bytes()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_resources_order() {
+        let resource = |name: &'static str| Resource {
+            name: Cow::Borrowed(name),
+            ..Resource::default()
+        };
+
+        assert!(check_resources_order::<Resource<u8>>(&[]).is_ok());
+        assert!(check_resources_order(&[resource("bar"), resource("foo")]).is_ok());
+        assert!(check_resources_order(&[resource("foo"), resource("bar")]).is_err());
+        assert!(check_resources_order(&[resource("foo"), resource("foo")]).is_err());
+    }
 }
@@ -83,8 +83,21 @@ pub struct Target {
     ///
     /// TODO consider making this an Arc<T> so we don't have to clone it.
     pub built_target: Option<ResolvedTarget>,
+
+    /// Entry point to invoke after the target is built to test it.
+    ///
+    /// Interpretation of the value is up to the dialect consumer.
+    pub post_build_test: Option<String>,
+
+    /// Host operating system this target is a default target for.
+    pub default_for_host: Option<String>,
 }
 
+/// Host operating systems targets can be a default target for.
+///
+/// Values match `std::env::consts::OS`.
+pub const HOST_OPERATING_SYSTEMS: &[&str] = &["linux", "macos", "windows"];
+
 #[derive(Debug)]
 pub enum GetStateError {
     /// The requested key is not valid for this context.
@@ -163,6 +176,9 @@ pub struct EnvironmentContext {
     ///
     /// This will change the default target to resolve.
     pub build_script_mode: bool,
+
+    /// Operating system of the machine evaluating the configuration.
+    host_os: String,
 }
 
 impl EnvironmentContext {
@@ -179,6 +195,7 @@ impl EnvironmentContext {
             resolve_targets: None,
             default_build_script_target: None,
             build_script_mode: false,
+            host_os: std::env::consts::OS.to_string(),
         }
     }
 
@@ -218,8 +235,38 @@ impl EnvironmentContext {
     }
 
     /// Obtain the default target to resolve.
+    ///
+    /// Targets that are the default for the host operating system take
+    /// precedence.
     pub fn default_target(&self) -> Option<&str> {
-        self.default_target.as_deref()
+        self.host_default_targets()
+            .into_iter()
+            .next()
+            .or(self.default_target.as_deref())
+    }
+
+    /// Obtain the operating system default targets are selected for.
+    pub fn host_os(&self) -> &str {
+        &self.host_os
+    }
+
+    /// Set the operating system default targets are selected for.
+    pub fn set_host_os(&mut self, os: &str) {
+        self.host_os = os.to_string();
+    }
+
+    /// Obtain targets that are the default for the host operating system.
+    ///
+    /// Targets are returned in registration order.
+    pub fn host_default_targets(&self) -> Vec<&str> {
+        self.targets_order
+            .iter()
+            .filter(|name| {
+                self.targets[name.as_str()].default_for_host.as_deref()
+                    == Some(self.host_os.as_str())
+            })
+            .map(|name| name.as_str())
+            .collect()
     }
 
     /// Obtain a named target.
@@ -243,6 +290,7 @@ impl EnvironmentContext {
     }
 
     /// Register a named target.
+    #[allow(clippy::too_many_arguments)]
     pub fn register_target(
         &mut self,
         target: String,
@@ -250,6 +298,8 @@ impl EnvironmentContext {
         depends: Vec<String>,
         default: bool,
         default_build_script: bool,
+        post_build_test: Option<String>,
+        default_for_host: Option<String>,
     ) {
         if !self.targets.contains_key(&target) {
             self.targets_order.push(target.clone());
@@ -262,6 +312,8 @@ impl EnvironmentContext {
                 depends,
                 resolved_value: None,
                 built_target: None,
+                post_build_test,
+                default_for_host,
             },
         );
 
@@ -283,6 +335,11 @@ impl EnvironmentContext {
             targets.clone()
         } else if self.build_script_mode && self.default_build_script_target.is_some() {
             vec![self.default_build_script_target.clone().unwrap()]
+        } else if !self.host_default_targets().is_empty() {
+            self.host_default_targets()
+                .into_iter()
+                .map(|x| x.to_string())
+                .collect()
         } else if let Some(target) = &self.default_target {
             vec![target.to_string()]
         } else {
@@ -532,7 +589,8 @@ fn starlark_print(type_values: &TypeValues, args: &[Value]) -> ValueResult {
     Ok(Value::new(NoneType::None))
 }
 
-/// register_target(target, callable, depends=None, default=false)
+/// register_target(target, callable, depends=None, default=false, default_build_script=false, post_build_test=None, default_for_host=None)
+#[allow(clippy::too_many_arguments)]
 fn starlark_register_target(
     type_values: &TypeValues,
     target: String,
@@ -540,9 +598,27 @@ fn starlark_register_target(
     depends: Value,
     default: bool,
     default_build_script: bool,
+    post_build_test: Value,
+    default_for_host: Value,
 ) -> ValueResult {
     required_type_arg("callable", "function", &callable)?;
     optional_list_arg("depends", "string", &depends)?;
+    let post_build_test = optional_str_arg("post_build_test", &post_build_test)?;
+    let default_for_host = optional_str_arg("default_for_host", &default_for_host)?;
+
+    if let Some(os) = &default_for_host {
+        if !HOST_OPERATING_SYSTEMS.contains(&os.as_str()) {
+            return Err(ValueError::from(RuntimeError {
+                code: INCORRECT_PARAMETER_TYPE_ERROR_CODE,
+                message: format!(
+                    "default_for_host must be one of {}; got {}",
+                    HOST_OPERATING_SYSTEMS.join(", "),
+                    os
+                ),
+                label: "register_target()".to_string(),
+            }));
+        }
+    }
 
     let depends = match depends.get_type() {
         "list" => depends.iter()?.iter().map(|x| x.to_string()).collect(),
@@ -554,7 +630,15 @@ fn starlark_register_target(
         .downcast_mut::<EnvironmentContext>()?
         .ok_or(ValueError::IncorrectParameterType)?;
 
-    context.register_target(target, callable, depends, default, default_build_script);
+    context.register_target(
+        target,
+        callable,
+        depends,
+        default,
+        default_build_script,
+        post_build_test,
+        default_for_host,
+    );
 
     Ok(Value::new(NoneType::None))
 }
@@ -714,9 +798,20 @@ starlark_module! { build_targets_module =>
         callable,
         depends = NoneType::None,
         default: bool = false,
-        default_build_script: bool = false
+        default_build_script: bool = false,
+        post_build_test = NoneType::None,
+        default_for_host = NoneType::None
     ) {
-        starlark_register_target(env, target, callable, depends, default, default_build_script)
+        starlark_register_target(
+            env,
+            target,
+            callable,
+            depends,
+            default,
+            default_build_script,
+            post_build_test,
+            default_for_host,
+        )
     }
 
     resolve_target(env env, call_stack cs, target: String) {
@@ -740,6 +835,10 @@ pub fn populate_environment(
 ) -> Result<(), EnvironmentError> {
     build_targets_module(env, type_values);
 
+    // Targets can return a `struct` to expose several values to dependent
+    // targets.
+    starlark::stdlib::structs::global(env, type_values);
+
     env.set(ENVIRONMENT_CONTEXT_SYMBOL, Value::new(context))?;
 
     // We alias various globals as BuildTargets.* attributes so they are
@@ -811,4 +910,87 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_register_target_post_build_test() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+        env.eval("def foo(): pass")?;
+        env.eval("register_target('foo', foo)")?;
+        env.eval("register_target('bar', foo, post_build_test='myapp.selftest:main')")?;
+        assert!(env
+            .eval("register_target('baz', foo, post_build_test=True)")
+            .is_err());
+
+        let context_value = get_context_value(&env.type_values).unwrap();
+        let context = context_value
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)
+            .unwrap();
+
+        assert_eq!(context.get_target("foo").unwrap().post_build_test, None);
+        assert_eq!(
+            context.get_target("bar").unwrap().post_build_test,
+            Some("myapp.selftest:main".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_register_target_default_for_host() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+        env.eval("def foo(): pass")?;
+        env.eval("register_target('install', foo, default=True)")?;
+        env.eval("register_target('msi', foo, default_for_host='windows')")?;
+        env.eval("register_target('exe_windows', foo, default_for_host='windows')")?;
+        env.eval("register_target('pkg', foo, default_for_host='macos')")?;
+        assert!(env
+            .eval("register_target('bad', foo, default_for_host='beos')")
+            .is_err());
+
+        let context_value = get_context_value(&env.type_values).unwrap();
+        let mut context = context_value
+            .downcast_mut::<EnvironmentContext>()
+            .unwrap()
+            .unwrap();
+
+        context.set_host_os("windows");
+        assert_eq!(context.default_target(), Some("msi"));
+        assert_eq!(
+            context.targets_to_resolve(),
+            vec!["msi".to_string(), "exe_windows".to_string()]
+        );
+
+        context.set_host_os("macos");
+        assert_eq!(context.targets_to_resolve(), vec!["pkg".to_string()]);
+
+        context.set_host_os("linux");
+        assert_eq!(context.default_target(), Some("install"));
+        assert_eq!(context.targets_to_resolve(), vec!["install".to_string()]);
+
+        context.set_resolve_targets(vec!["pkg".to_string()]);
+        assert_eq!(context.targets_to_resolve(), vec!["pkg".to_string()]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_target_struct() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+        env.eval("def foo(): return struct(name='app', version='1.0')")?;
+        env.eval("def bar(foo): return foo.name + '-' + foo.version")?;
+        env.eval("register_target('foo', foo)")?;
+        env.eval("register_target('bar', bar, depends=['foo'])")?;
+
+        assert_eq!(
+            env.eval("resolve_target('bar')")?.to_string(),
+            "app-1.0".to_string()
+        );
+        assert_eq!(
+            env.eval("resolve_target('foo').version")?.to_string(),
+            "1.0".to_string()
+        );
+
+        Ok(())
+    }
 }
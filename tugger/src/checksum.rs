@@ -0,0 +1,195 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Generate checksum files over file manifests.
+
+Checksum files use the format of the `sha256sum` family of tools, so they
+can be verified with e.g. `sha256sum -c SHA256SUMS`.
+*/
+
+use {
+    crate::file_resource::{FileContent, FileManifest},
+    anyhow::{anyhow, Result},
+    sha2::Digest,
+    std::{convert::TryFrom, path::Path},
+};
+
+/// A hashing algorithm for checksum files.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChecksumAlgorithm {
+    Sha256,
+    Sha512,
+}
+
+impl ToString for ChecksumAlgorithm {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Sha256 => "sha256",
+            Self::Sha512 => "sha512",
+        }
+        .to_string()
+    }
+}
+
+impl TryFrom<&str> for ChecksumAlgorithm {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "sha256" => Ok(Self::Sha256),
+            "sha512" => Ok(Self::Sha512),
+            _ => Err(format!(
+                "{} is not a valid checksum algorithm; use sha256 or sha512",
+                value
+            )),
+        }
+    }
+}
+
+impl ChecksumAlgorithm {
+    /// Compute the hex digest of data.
+    pub fn hex_digest(&self, data: &[u8]) -> String {
+        match self {
+            Self::Sha256 => hex::encode(sha2::Sha256::digest(data)),
+            Self::Sha512 => hex::encode(sha2::Sha512::digest(data)),
+        }
+    }
+}
+
+/// Generate a checksum file for files in a manifest.
+///
+/// The checksum file is to be installed at `path`. It covers files in the
+/// directory of `path` and its subdirectories, with paths relative to that
+/// directory, so it can be verified from there. A file at `path` itself is
+/// not covered.
+pub fn checksums_file(
+    manifest: &FileManifest,
+    algorithm: ChecksumAlgorithm,
+    path: &Path,
+) -> Result<String> {
+    let root = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut res = String::new();
+
+    for (file_path, content) in manifest.entries() {
+        if file_path == path {
+            continue;
+        }
+
+        if let Ok(relative) = file_path.strip_prefix(root) {
+            let name = relative
+                .to_str()
+                .ok_or_else(|| anyhow!("path is not valid UTF-8: {}", relative.display()))?
+                .replace('\\', "/");
+
+            if name.contains('\n') {
+                return Err(anyhow!(
+                    "path cannot be represented in a checksum file: {}",
+                    name
+                ));
+            }
+
+            res.push_str(&format!(
+                "{}  {}\n",
+                algorithm.hex_digest(&content.data),
+                name
+            ));
+        }
+    }
+
+    Ok(res)
+}
+
+/// Add a checksum file covering files in a manifest to that manifest.
+///
+/// See `checksums_file()` for what is covered.
+pub fn add_checksums_file(
+    manifest: &mut FileManifest,
+    algorithm: ChecksumAlgorithm,
+    path: &Path,
+) -> Result<()> {
+    let data = checksums_file(manifest, algorithm, path)?;
+
+    manifest.add_file(
+        path,
+        &FileContent {
+            data: data.into_bytes(),
+            executable: false,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(data: &[u8]) -> FileContent {
+        FileContent {
+            data: data.to_vec(),
+            executable: false,
+        }
+    }
+
+    #[test]
+    fn test_checksums_file() -> Result<()> {
+        let mut manifest = FileManifest::default();
+        manifest.add_file("bin/app", &file(b"app"))?;
+        manifest.add_file("README", &file(b""))?;
+
+        add_checksums_file(
+            &mut manifest,
+            ChecksumAlgorithm::Sha256,
+            Path::new("SHA256SUMS"),
+        )?;
+
+        let (_, content) = manifest
+            .entries()
+            .find(|(path, _)| path == &Path::new("SHA256SUMS"))
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(content.data.clone())?,
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855  README\n\
+             a172cedcae47474b615c54d510a5d84a8dea3032e958587430b413538be3f333  bin/app\n"
+        );
+
+        // Regenerating doesn't cover the previous checksum file.
+        add_checksums_file(
+            &mut manifest,
+            ChecksumAlgorithm::Sha256,
+            Path::new("SHA256SUMS"),
+        )?;
+        assert_eq!(
+            checksums_file(
+                &manifest,
+                ChecksumAlgorithm::Sha256,
+                Path::new("SHA256SUMS")
+            )?
+            .lines()
+            .count(),
+            2
+        );
+
+        // Only files below the checksum file are covered.
+        assert_eq!(
+            checksums_file(
+                &manifest,
+                ChecksumAlgorithm::Sha512,
+                Path::new("bin/SHA512SUMS")
+            )?,
+            format!("{}  app\n", ChecksumAlgorithm::Sha512.hex_digest(b"app"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_algorithm() {
+        assert_eq!(
+            ChecksumAlgorithm::try_from("sha512"),
+            Ok(ChecksumAlgorithm::Sha512)
+        );
+        assert_eq!(ChecksumAlgorithm::Sha256.to_string(), "sha256");
+        assert!(ChecksumAlgorithm::try_from("md5").is_err());
+    }
+}
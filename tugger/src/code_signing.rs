@@ -0,0 +1,267 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Discover code signing certificates available on the current machine.
+
+On Windows, certificates are read from the `My` stores of the current
+user and local machine via PowerShell. On macOS, signing identities are
+read from the keychain search list via `security find-identity`: identities
+valid for the `codesigning` policy, used by `codesign`, and installer
+identities valid for the `basic` policy, used by `productsign`.
+
+Other platforms have no well-known certificate stores and no certificates
+are discovered.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    duct::cmd,
+};
+
+/// PowerShell script emitting code signing certificates as JSON.
+const POWERSHELL_SCRIPT: &str = "Get-ChildItem Cert:\\CurrentUser\\My,Cert:\\LocalMachine\\My -CodeSigningCert | Select-Object Subject,Thumbprint,@{Name='NotAfter';Expression={$_.NotAfter.ToString('o')}},PSParentPath | ConvertTo-Json";
+
+/// A code signing certificate installed on the current machine.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CodeSigningCertificate {
+    /// Store the certificate was found in.
+    ///
+    /// e.g. `CurrentUser\My` on Windows or `keychain` on macOS.
+    pub store: String,
+
+    /// Subject of the certificate.
+    ///
+    /// On macOS, this is the name of the identity as accepted by
+    /// `productsign --sign` and `codesign --sign`.
+    pub subject: String,
+
+    /// Upper case hex SHA-1 thumbprint of the certificate.
+    pub thumbprint: String,
+
+    /// Time the certificate expires, in ISO 8601 format, if known.
+    pub not_after: Option<String>,
+}
+
+/// Whether the name of a macOS signing identity denotes an installer identity.
+///
+/// Installer identities sign packages with `productsign`. They aren't valid
+/// for the `codesigning` policy.
+fn is_installer_identity(subject: &str) -> bool {
+    subject.starts_with("Developer ID Installer: ")
+        || subject.starts_with("3rd Party Mac Developer Installer: ")
+        || subject.starts_with("Mac Installer Distribution: ")
+}
+
+/// Merge macOS identities valid for the `codesigning` and `basic` policies.
+///
+/// Identities valid for the `basic` policy are only kept if they are
+/// installer identities. Identities are identified by their thumbprint.
+pub fn merge_macos_identities(
+    codesigning: Vec<CodeSigningCertificate>,
+    basic: Vec<CodeSigningCertificate>,
+) -> Vec<CodeSigningCertificate> {
+    let mut res = codesigning;
+
+    for cert in basic {
+        if is_installer_identity(&cert.subject)
+            && !res.iter().any(|c| c.thumbprint == cert.thumbprint)
+        {
+            res.push(cert);
+        }
+    }
+
+    res
+}
+
+/// Parse the output of `security find-identity -v -p <policy>`.
+///
+/// Identities are listed as `  1) <thumbprint> "<name>"` followed by a
+/// summary line, which is ignored.
+pub fn parse_security_find_identity(output: &str) -> Vec<CodeSigningCertificate> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let rest = &line[line.find(") ")? + 2..];
+            let (thumbprint, subject) = rest.split_at(rest.find(' ')?);
+            let subject = subject.trim();
+
+            if !subject.starts_with('"') || !subject.ends_with('"') || subject.len() < 2 {
+                return None;
+            }
+
+            Some(CodeSigningCertificate {
+                store: "keychain".to_string(),
+                subject: subject[1..subject.len() - 1].to_string(),
+                thumbprint: thumbprint.to_uppercase(),
+                not_after: None,
+            })
+        })
+        .collect()
+}
+
+/// Parse the JSON emitted by [`POWERSHELL_SCRIPT`].
+///
+/// `ConvertTo-Json` emits a single object when there is one certificate,
+/// an array when there are several and nothing when there are none.
+pub fn parse_powershell_certificates(output: &str) -> Result<Vec<CodeSigningCertificate>> {
+    if output.trim().is_empty() {
+        return Ok(vec![]);
+    }
+
+    let value: serde_json::Value =
+        serde_json::from_str(output).context("parsing PowerShell output")?;
+    let entries = match value {
+        serde_json::Value::Array(entries) => entries,
+        value => vec![value],
+    };
+
+    entries
+        .iter()
+        .map(|entry| {
+            let field = |name: &str| entry.get(name).and_then(|v| v.as_str());
+
+            // PSParentPath is e.g. `Microsoft.PowerShell.Security\Certificate::CurrentUser\My`.
+            let store = field("PSParentPath")
+                .map(|path| match path.find("::") {
+                    Some(index) => &path[index + 2..],
+                    None => path,
+                })
+                .unwrap_or_default();
+
+            Ok(CodeSigningCertificate {
+                store: store.to_string(),
+                subject: field("Subject")
+                    .ok_or_else(|| anyhow!("certificate has no subject"))?
+                    .to_string(),
+                thumbprint: field("Thumbprint")
+                    .ok_or_else(|| anyhow!("certificate has no thumbprint"))?
+                    .to_uppercase(),
+                not_after: field("NotAfter").map(|s| s.to_string()),
+            })
+        })
+        .collect()
+}
+
+/// Find code signing certificates available on the current machine.
+pub fn find_code_signing_certificates() -> Result<Vec<CodeSigningCertificate>> {
+    if cfg!(target_os = "windows") {
+        let output = cmd(
+            "powershell.exe",
+            &[
+                "-NoProfile",
+                "-NonInteractive",
+                "-Command",
+                POWERSHELL_SCRIPT,
+            ],
+        )
+        .read()
+        .context("running powershell.exe")?;
+
+        parse_powershell_certificates(&output)
+    } else if cfg!(target_os = "macos") {
+        let find_identities = |policy: &str| -> Result<Vec<CodeSigningCertificate>> {
+            let output = cmd("security", &["find-identity", "-v", "-p", policy])
+                .read()
+                .context("running security find-identity")?;
+
+            Ok(parse_security_find_identity(&output))
+        };
+
+        Ok(merge_macos_identities(
+            find_identities("codesigning")?,
+            find_identities("basic")?,
+        ))
+    } else {
+        Ok(vec![])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_security_find_identity() {
+        let output = concat!(
+            "  1) 0123456789ABCDEF0123456789ABCDEF01234567 \"Developer ID Application: Example (ABCDE12345)\"\n",
+            "  2) fedcba9876543210fedcba9876543210fedcba98 \"Developer ID Installer: Example (ABCDE12345)\"\n",
+            "     2 valid identities found\n",
+        );
+
+        let certs = parse_security_find_identity(output);
+        assert_eq!(certs.len(), 2);
+        assert_eq!(
+            certs[0],
+            CodeSigningCertificate {
+                store: "keychain".to_string(),
+                subject: "Developer ID Application: Example (ABCDE12345)".to_string(),
+                thumbprint: "0123456789ABCDEF0123456789ABCDEF01234567".to_string(),
+                not_after: None,
+            }
+        );
+        assert_eq!(
+            certs[1].thumbprint,
+            "FEDCBA9876543210FEDCBA9876543210FEDCBA98"
+        );
+
+        assert!(parse_security_find_identity("     0 valid identities found\n").is_empty());
+    }
+
+    #[test]
+    fn test_merge_macos_identities() {
+        let identity = |subject: &str, thumbprint: &str| CodeSigningCertificate {
+            store: "keychain".to_string(),
+            subject: subject.to_string(),
+            thumbprint: thumbprint.to_string(),
+            not_after: None,
+        };
+
+        let application = identity("Developer ID Application: Example (ABCDE12345)", "01");
+        let installer = identity("Developer ID Installer: Example (ABCDE12345)", "02");
+        let other = identity("Example TLS Client", "03");
+
+        assert_eq!(
+            merge_macos_identities(
+                vec![application.clone()],
+                vec![application.clone(), installer.clone(), other]
+            ),
+            vec![application, installer]
+        );
+    }
+
+    #[test]
+    fn test_parse_powershell_certificates() -> Result<()> {
+        assert!(parse_powershell_certificates("")?.is_empty());
+
+        let single = r#"{
+            "Subject": "CN=Example Corp",
+            "Thumbprint": "0123456789abcdef0123456789abcdef01234567",
+            "NotAfter": "2022-01-01T00:00:00.0000000Z",
+            "PSParentPath": "Microsoft.PowerShell.Security\\Certificate::CurrentUser\\My"
+        }"#;
+        assert_eq!(
+            parse_powershell_certificates(single)?,
+            vec![CodeSigningCertificate {
+                store: "CurrentUser\\My".to_string(),
+                subject: "CN=Example Corp".to_string(),
+                thumbprint: "0123456789ABCDEF0123456789ABCDEF01234567".to_string(),
+                not_after: Some("2022-01-01T00:00:00.0000000Z".to_string()),
+            }]
+        );
+
+        let multiple = format!(
+            "[{}, {{\"Subject\": \"CN=Other\", \"Thumbprint\": \"AB\", \"NotAfter\": null, \"PSParentPath\": \"Microsoft.PowerShell.Security\\\\Certificate::LocalMachine\\\\My\"}}]",
+            single
+        );
+        let certs = parse_powershell_certificates(&multiple)?;
+        assert_eq!(certs.len(), 2);
+        assert_eq!(certs[1].store, "LocalMachine\\My");
+        assert_eq!(certs[1].not_after, None);
+
+        assert!(parse_powershell_certificates("{\"Subject\": \"CN=Example\"}").is_err());
+
+        Ok(())
+    }
+}
@@ -3,6 +3,7 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use {
+    crate::paths::long_path,
     anyhow::{anyhow, Context, Result},
     std::{
         collections::{btree_map::Iter, BTreeMap, BTreeSet},
@@ -54,18 +55,218 @@ impl TryFrom<&Path> for FileContent {
     type Error = std::io::Error;
 
     fn try_from(value: &Path) -> Result<Self, Self::Error> {
-        let data = std::fs::read(value)?;
-        let metadata = std::fs::metadata(value)?;
+        let value = long_path(value);
+        let data = std::fs::read(&value)?;
+        let metadata = std::fs::metadata(&value)?;
         let executable = is_executable(&metadata);
 
         Ok(FileContent { data, executable })
     }
 }
 
+/// Whether a string is a portable Unix user or group name.
+fn is_unix_account_name(value: &str) -> bool {
+    let mut chars = value.chars();
+
+    match chars.next() {
+        Some(c) if c.is_ascii_lowercase() || c == '_' => {}
+        _ => return false,
+    }
+
+    value.len() <= 32
+        && value
+            .trim_end_matches('$')
+            .chars()
+            .skip(1)
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+}
+
+/// Names of Linux capabilities, indexed by capability number.
+const LINUX_CAPABILITIES: &[&str] = &[
+    "chown",
+    "dac_override",
+    "dac_read_search",
+    "fowner",
+    "fsetid",
+    "kill",
+    "setgid",
+    "setuid",
+    "setpcap",
+    "linux_immutable",
+    "net_bind_service",
+    "net_broadcast",
+    "net_admin",
+    "net_raw",
+    "ipc_lock",
+    "ipc_owner",
+    "sys_module",
+    "sys_rawio",
+    "sys_chroot",
+    "sys_ptrace",
+    "sys_pacct",
+    "sys_admin",
+    "sys_boot",
+    "sys_nice",
+    "sys_resource",
+    "sys_time",
+    "sys_tty_config",
+    "mknod",
+    "lease",
+    "audit_write",
+    "audit_control",
+    "setfcap",
+    "mac_override",
+    "mac_admin",
+    "syslog",
+    "wake_alarm",
+    "block_suspend",
+    "audit_read",
+    "perfmon",
+    "bpf",
+    "checkpoint_restore",
+];
+
+/// Ownership and capabilities of an installed file on Unix.
+///
+/// This is consumed by targets producing system packages, which can record
+/// it without the package running `chown` or `setcap` at install time.
+/// Targets materializing files directly, like `FileManifest::write_to_path()`,
+/// ignore it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UnixFileAttributes {
+    /// Name of the user owning the file. `None` means `root`.
+    pub owner: Option<String>,
+
+    /// Name of the group owning the file. `None` means `root`.
+    pub group: Option<String>,
+
+    /// File capabilities, in the textual form of `setcap`.
+    ///
+    /// e.g. `cap_net_bind_service=ep`.
+    pub capabilities: Option<String>,
+}
+
+impl UnixFileAttributes {
+    /// Ensure values are usable by package formats.
+    pub fn validate(&self) -> Result<()> {
+        for (field, value) in &[("owner", &self.owner), ("group", &self.group)] {
+            if let Some(value) = value {
+                if !is_unix_account_name(value) {
+                    return Err(anyhow!("invalid {} name: {}", field, value));
+                }
+            }
+        }
+
+        if let Some(caps) = &self.capabilities {
+            capabilities_xattr(caps).map_err(|e| {
+                anyhow!(
+                    "invalid capabilities: {}: {}; expected a value like cap_net_bind_service=ep",
+                    caps,
+                    e
+                )
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Obtain the value of the `security.capability` extended attribute.
+    ///
+    /// This is the `vfs_cap_data` structure the kernel reads file
+    /// capabilities from.
+    pub fn capabilities_xattr(&self) -> Result<Option<Vec<u8>>> {
+        match &self.capabilities {
+            Some(caps) => Ok(Some(capabilities_xattr(caps)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Convert capabilities in the textual form of `setcap` to `vfs_cap_data`.
+///
+/// Clauses like `cap_net_raw,cap_net_admin+ep` are separated by whitespace.
+/// Version 2 of the structure only has a single effective flag, so
+/// capabilities are either all effective or none are.
+fn capabilities_xattr(value: &str) -> Result<Vec<u8>> {
+    const VFS_CAP_REVISION_2: u32 = 0x0200_0000;
+    const VFS_CAP_FLAGS_EFFECTIVE: u32 = 0x0000_0001;
+
+    let mut permitted = 0u64;
+    let mut inheritable = 0u64;
+    let mut effective = 0u64;
+
+    for clause in value.split_whitespace() {
+        let op_index = clause
+            .find(&['=', '+', '-'][..])
+            .ok_or_else(|| anyhow!("missing operator in {}", clause))?;
+        let (names, rest) = clause.split_at(op_index);
+        let op = rest.chars().next().unwrap();
+        let flags = &rest[1..];
+
+        if let Some(c) = flags.chars().find(|c| !"eip".contains(*c)) {
+            return Err(anyhow!("unknown flag {} in {}", c, clause));
+        }
+
+        let mut mask = 0u64;
+        for name in names.split(',') {
+            let index = name
+                .strip_prefix("cap_")
+                .and_then(|name| LINUX_CAPABILITIES.iter().position(|x| *x == name))
+                .ok_or_else(|| anyhow!("unknown capability {}", name))?;
+            mask |= 1 << index;
+        }
+
+        for (flag, set) in &mut [
+            ('p', &mut permitted),
+            ('i', &mut inheritable),
+            ('e', &mut effective),
+        ] {
+            match op {
+                '=' if flags.contains(*flag) => **set |= mask,
+                '=' => **set &= !mask,
+                '+' if flags.contains(*flag) => **set |= mask,
+                '-' if flags.contains(*flag) => **set &= !mask,
+                _ => {}
+            }
+        }
+    }
+
+    if permitted == 0 && inheritable == 0 {
+        return Err(anyhow!("no permitted or inheritable capabilities"));
+    }
+
+    if effective != 0 && effective != permitted | inheritable {
+        return Err(anyhow!(
+            "effective capabilities must include all permitted and inheritable capabilities"
+        ));
+    }
+
+    let magic = VFS_CAP_REVISION_2
+        | if effective != 0 {
+            VFS_CAP_FLAGS_EFFECTIVE
+        } else {
+            0
+        };
+
+    let mut data = Vec::with_capacity(20);
+    for value in &[
+        magic,
+        permitted as u32,
+        inheritable as u32,
+        (permitted >> 32) as u32,
+        (inheritable >> 32) as u32,
+    ] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    Ok(data)
+}
+
 /// Represents a virtual tree of files.
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct FileManifest {
     files: BTreeMap<PathBuf, FileContent>,
+    unix_attributes: BTreeMap<PathBuf, UnixFileAttributes>,
 }
 
 impl FileManifest {
@@ -93,9 +294,36 @@ impl FileManifest {
             self.add_file(key.as_path(), value)?;
         }
 
+        for (key, value) in &other.unix_attributes {
+            self.unix_attributes.insert(key.clone(), value.clone());
+        }
+
         Ok(())
     }
 
+    /// Define the Unix ownership and capabilities of a file in the manifest.
+    pub fn set_unix_attributes<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        attributes: UnixFileAttributes,
+    ) -> Result<()> {
+        let path = path.as_ref();
+
+        if !self.files.contains_key(path) {
+            return Err(anyhow!("{} is not in the manifest", path.display()));
+        }
+
+        attributes.validate()?;
+        self.unix_attributes.insert(path.to_path_buf(), attributes);
+
+        Ok(())
+    }
+
+    /// Obtain the Unix ownership and capabilities of a file, if defined.
+    pub fn unix_attributes(&self, path: &Path) -> Option<&UnixFileAttributes> {
+        self.unix_attributes.get(path)
+    }
+
     /// All relative directories contained within files in this manifest.
     ///
     /// The root directory is not represented in the return value.
@@ -181,18 +409,22 @@ impl FileManifest {
     }
 
     /// Write the contents of the install manifest to a filesystem path.
+    ///
+    /// Paths too long for Windows APIs are handled.
     pub fn write_to_path(&self, path: &Path) -> Result<()> {
         for (p, c) in &self.files {
-            let dest_path = path.join(p);
+            let dest_path = long_path(&path.join(p));
             let parent = dest_path
                 .parent()
                 .ok_or_else(|| anyhow!("unable to resolve parent directory"))?;
 
             std::fs::create_dir_all(parent)
-                .context("creating parent directory for FileManifest")?;
+                .with_context(|| format!("creating {}", parent.display()))?;
 
-            let mut fh = std::fs::File::create(&dest_path)?;
-            fh.write_all(&c.data)?;
+            let mut fh = std::fs::File::create(&dest_path)
+                .with_context(|| format!("creating {}", dest_path.display()))?;
+            fh.write_all(&c.data)
+                .with_context(|| format!("writing {}", dest_path.display()))?;
             if c.executable {
                 set_executable(&mut fh)?;
             }
@@ -204,8 +436,11 @@ impl FileManifest {
     /// Write the contents of the install manifest to a filesystem path,
     /// replacing any existing content at the specified path.
     pub fn replace_path(&self, path: &Path) -> Result<()> {
-        if path.exists() {
-            std::fs::remove_dir_all(path)?;
+        let long = long_path(path);
+
+        if long.exists() {
+            std::fs::remove_dir_all(&long)
+                .with_context(|| format!("removing {}", path.display()))?;
         }
 
         self.write_to_path(path)
@@ -248,6 +483,74 @@ mod tests {
         assert!(res.is_err());
     }
 
+    #[test]
+    fn test_unix_attributes() -> Result<()> {
+        let mut v = FileManifest::default();
+        let f = FileContent {
+            data: vec![],
+            executable: true,
+        };
+        let attributes = UnixFileAttributes {
+            owner: Some("myapp".to_string()),
+            group: Some("_myapp-1$".to_string()),
+            capabilities: Some("cap_net_bind_service=ep".to_string()),
+        };
+
+        v.add_file(Path::new("bin/myapp"), &f)?;
+        assert!(v
+            .set_unix_attributes("bin/other", attributes.clone())
+            .is_err());
+        v.set_unix_attributes("bin/myapp", attributes.clone())?;
+        assert_eq!(v.unix_attributes(Path::new("bin/myapp")), Some(&attributes));
+
+        let mut other = FileManifest::default();
+        other.add_manifest(&v)?;
+        assert_eq!(
+            other.unix_attributes(Path::new("bin/myapp")),
+            Some(&attributes)
+        );
+
+        for bad in &[
+            UnixFileAttributes {
+                owner: Some("Root".to_string()),
+                ..Default::default()
+            },
+            UnixFileAttributes {
+                group: Some("1wheel".to_string()),
+                ..Default::default()
+            },
+            UnixFileAttributes {
+                capabilities: Some("cap_net_raw=ep; rm -rf /".to_string()),
+                ..Default::default()
+            },
+            UnixFileAttributes {
+                capabilities: Some("cap_net_raw=e".to_string()),
+                ..Default::default()
+            },
+        ] {
+            assert!(v.set_unix_attributes("bin/myapp", bad.clone()).is_err());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_capabilities_xattr() -> Result<()> {
+        assert_eq!(
+            capabilities_xattr("cap_net_bind_service=ep")?,
+            vec![1, 0, 0, 2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            capabilities_xattr("cap_net_raw,cap_bpf+p")?,
+            vec![0, 0, 0, 2, 0, 0x20, 0, 0, 0, 0, 0, 0, 0x80, 0, 0, 0, 0, 0, 0, 0]
+        );
+        assert!(capabilities_xattr("cap_net_raw").is_err());
+        assert!(capabilities_xattr("cap_bogus=ep").is_err());
+        assert!(capabilities_xattr("cap_net_raw=epx").is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_relative_directories() {
         let mut v = FileManifest::default();
@@ -19,12 +19,22 @@ Rust crate and utility. It just happens to be developed alongside
 PyOxidizer.
 */
 
+pub mod checksum;
+pub mod code_signing;
 pub mod file_resource;
 pub mod glob;
 pub mod http;
+pub mod macos_pkg;
+pub mod paths;
+pub mod package_managers;
+pub mod release;
+pub mod secrets;
+pub mod shims;
 pub mod starlark;
+pub mod systemd;
 pub mod tarball;
 #[cfg(test)]
 mod testutil;
+pub mod vc_redist;
 pub mod wix;
 pub mod zipfile;
@@ -0,0 +1,511 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Build macOS `.pkg` installers.
+
+Installers are *product archives* built with `productbuild` from one or
+more *component packages* built with `pkgbuild`. Product archives can be
+signed with a `Developer ID Installer` identity, which MDM solutions
+require for deploying packages to managed machines, and notarized with
+`notarytool`.
+
+Both tools ship with Xcode and are only available on macOS.
+*/
+
+use {
+    crate::{
+        file_resource::{FileContent, FileManifest},
+        secrets::Secret,
+    },
+    anyhow::{anyhow, Result},
+    duct::cmd,
+    slog::warn,
+    std::{
+        io::{BufRead, BufReader},
+        path::{Path, PathBuf},
+    },
+    xml::escape::{escape_str_attribute, escape_str_pcdata},
+};
+
+/// Scripts run by the installer, if present in a component's scripts.
+const INSTALL_SCRIPTS: &[&str] = &["preinstall", "postinstall"];
+
+/// Whether a string is a valid package identifier.
+///
+/// Identifiers are reverse DNS style, e.g. `com.example.myapp`.
+fn is_valid_identifier(value: &str) -> bool {
+    value.contains('.')
+        && value.split('.').all(|part| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+}
+
+/// Resolve the installer host architecture of a target triple.
+///
+/// The installer refuses to run on machines of other architectures.
+fn host_architecture(target_triple: &str) -> Result<&'static str> {
+    match target_triple {
+        "x86_64-apple-darwin" => Ok("x86_64"),
+        "aarch64-apple-darwin" => Ok("arm64"),
+        _ => Err(anyhow!(
+            "unable to build macOS installer for target triple {}; expected x86_64-apple-darwin or aarch64-apple-darwin",
+            target_triple
+        )),
+    }
+}
+
+/// A component package of an installer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PkgComponent {
+    /// Identifier of the package, e.g. `com.example.myapp`.
+    pub identifier: String,
+
+    /// Version of the package.
+    pub version: String,
+
+    /// Absolute path files are installed to.
+    pub install_location: String,
+
+    /// Files to install.
+    pub files: FileManifest,
+
+    /// Scripts run during installation.
+    ///
+    /// `preinstall` and `postinstall` are run by the installer. Other files
+    /// are available to these scripts.
+    pub scripts: FileManifest,
+}
+
+impl PkgComponent {
+    /// Create a component installing files to a location.
+    pub fn new(
+        identifier: &str,
+        version: &str,
+        install_location: &str,
+        files: FileManifest,
+    ) -> Result<Self> {
+        if !is_valid_identifier(identifier) {
+            return Err(anyhow!(
+                "invalid package identifier: {}; use a reverse DNS name like com.example.myapp",
+                identifier
+            ));
+        }
+
+        if !install_location.starts_with('/') {
+            return Err(anyhow!(
+                "install location must be an absolute path; got {}",
+                install_location
+            ));
+        }
+
+        Ok(Self {
+            identifier: identifier.to_string(),
+            version: version.to_string(),
+            install_location: install_location.to_string(),
+            files,
+            scripts: FileManifest::default(),
+        })
+    }
+
+    /// Filename of the component package in the product archive.
+    fn filename(&self) -> String {
+        format!("{}.pkg", self.identifier)
+    }
+}
+
+/// Credentials used to notarize an installer.
+///
+/// Credentials are stored in a keychain profile with
+/// `xcrun notarytool store-credentials` so they are never passed to
+/// `notarytool` on its command line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Notarization {
+    /// Name of the `notarytool` keychain profile holding the credentials.
+    pub keychain_profile: String,
+
+    /// Keychain holding the profile. Defaults to the keychain search list.
+    pub keychain: Option<PathBuf>,
+}
+
+/// Entity used to build a macOS `.pkg` installer.
+#[derive(Clone, Debug)]
+pub struct MacOsPkgBuilder {
+    /// Title shown by the installer.
+    pub title: String,
+
+    /// Identifier of the product.
+    pub identifier: String,
+
+    /// Version of the product.
+    pub version: String,
+
+    /// Name of a `Developer ID Installer` identity to sign the installer with.
+    pub signing_identity: Option<String>,
+
+    /// Keychain to search for the signing identity.
+    pub keychain: Option<PathBuf>,
+
+    /// Password unlocking the keychain before signing.
+    pub keychain_password: Option<Secret>,
+
+    /// How to notarize the signed installer.
+    pub notarization: Option<Notarization>,
+
+    components: Vec<PkgComponent>,
+}
+
+impl MacOsPkgBuilder {
+    /// Create a new instance for a product.
+    pub fn new(title: &str, identifier: &str, version: &str) -> Result<Self> {
+        if !is_valid_identifier(identifier) {
+            return Err(anyhow!(
+                "invalid product identifier: {}; use a reverse DNS name like com.example.myapp",
+                identifier
+            ));
+        }
+
+        Ok(Self {
+            title: title.to_string(),
+            identifier: identifier.to_string(),
+            version: version.to_string(),
+            signing_identity: None,
+            keychain: None,
+            keychain_password: None,
+            notarization: None,
+            components: vec![],
+        })
+    }
+
+    /// Component packages of the installer.
+    pub fn components(&self) -> &[PkgComponent] {
+        &self.components
+    }
+
+    /// Add a component package to the installer.
+    pub fn add_component(&mut self, component: PkgComponent) -> Result<()> {
+        if self
+            .components
+            .iter()
+            .any(|c| c.identifier == component.identifier)
+        {
+            return Err(anyhow!(
+                "component {} already added to installer",
+                component.identifier
+            ));
+        }
+
+        self.components.push(component);
+
+        Ok(())
+    }
+
+    /// Obtain the distribution file describing the product archive.
+    ///
+    /// The installer only runs on the architecture of `target_triple`.
+    pub fn distribution_xml(&self, target_triple: &str) -> Result<String> {
+        let host_architecture = host_architecture(target_triple)?;
+
+        let mut res = String::new();
+
+        res.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        res.push_str("<installer-gui-script minSpecVersion=\"2\">\n");
+        res.push_str(&format!(
+            "    <title>{}</title>\n",
+            escape_str_pcdata(&self.title)
+        ));
+        res.push_str(&format!(
+            "    <product id=\"{}\" version=\"{}\"/>\n",
+            escape_str_attribute(&self.identifier),
+            escape_str_attribute(&self.version)
+        ));
+        res.push_str(&format!(
+            "    <options customize=\"never\" require-scripts=\"false\" hostArchitectures=\"{}\"/>\n",
+            host_architecture
+        ));
+        res.push_str(
+            "    <domains enable_anywhere=\"false\" enable_currentUserHome=\"false\" enable_localSystem=\"true\"/>\n",
+        );
+        res.push_str("    <choices-outline>\n");
+        res.push_str("        <line choice=\"default\">\n");
+        for component in &self.components {
+            res.push_str(&format!(
+                "            <line choice=\"{}\"/>\n",
+                escape_str_attribute(&component.identifier)
+            ));
+        }
+        res.push_str("        </line>\n");
+        res.push_str("    </choices-outline>\n");
+        res.push_str("    <choice id=\"default\"/>\n");
+
+        for component in &self.components {
+            let id = escape_str_attribute(&component.identifier);
+
+            res.push_str(&format!(
+                "    <choice id=\"{}\" visible=\"false\">\n        <pkg-ref id=\"{}\"/>\n    </choice>\n",
+                id, id
+            ));
+            res.push_str(&format!(
+                "    <pkg-ref id=\"{}\" version=\"{}\" onConclusion=\"none\">{}</pkg-ref>\n",
+                id,
+                escape_str_attribute(&component.version),
+                escape_str_pcdata(&component.filename())
+            ));
+        }
+
+        res.push_str("</installer-gui-script>\n");
+
+        Ok(res)
+    }
+
+    /// Produce a `.pkg` installer using the configuration in this builder.
+    ///
+    /// `build_path` is used to stage component packages. `target_triple` is
+    /// the target the installed files were built for.
+    pub fn build_pkg(
+        &self,
+        logger: &slog::Logger,
+        target_triple: &str,
+        build_path: &Path,
+        output_path: &Path,
+    ) -> Result<()> {
+        if self.components.is_empty() {
+            return Err(anyhow!("installer has no components"));
+        }
+
+        if self.notarization.is_some() && self.signing_identity.is_none() {
+            return Err(anyhow!("notarized installers must be signed"));
+        }
+
+        host_architecture(target_triple)?;
+
+        let packages_path = build_path.join("packages");
+        std::fs::create_dir_all(&packages_path)?;
+
+        for component in &self.components {
+            let component_path = build_path.join("components").join(&component.identifier);
+            let root_path = component_path.join("root");
+            let scripts_path = component_path.join("scripts");
+
+            component.files.replace_path(&root_path)?;
+
+            let mut args = vec![
+                "--root".to_string(),
+                root_path.display().to_string(),
+                "--identifier".to_string(),
+                component.identifier.clone(),
+                "--version".to_string(),
+                component.version.clone(),
+                "--install-location".to_string(),
+                component.install_location.clone(),
+            ];
+
+            if component.scripts.entries().next().is_some() {
+                // The installer only runs scripts marked executable.
+                let mut scripts = FileManifest::default();
+                for (path, content) in component.scripts.entries() {
+                    let executable =
+                        content.executable || INSTALL_SCRIPTS.iter().any(|s| path == Path::new(s));
+
+                    scripts.add_file(
+                        path,
+                        &FileContent {
+                            data: content.data.clone(),
+                            executable,
+                        },
+                    )?;
+                }
+                scripts.replace_path(&scripts_path)?;
+
+                args.push("--scripts".to_string());
+                args.push(scripts_path.display().to_string());
+            }
+
+            args.push(
+                packages_path
+                    .join(component.filename())
+                    .display()
+                    .to_string(),
+            );
+
+            warn!(
+                logger,
+                "building component package {}", component.identifier
+            );
+            run_tool(logger, "pkgbuild", args)?;
+        }
+
+        let distribution_path = build_path.join("distribution.xml");
+        std::fs::write(&distribution_path, self.distribution_xml(target_triple)?)?;
+
+        let mut args = vec![
+            "--distribution".to_string(),
+            distribution_path.display().to_string(),
+            "--package-path".to_string(),
+            packages_path.display().to_string(),
+        ];
+
+        if let Some(identity) = &self.signing_identity {
+            if let Some(password) = &self.keychain_password {
+                // Without -p, the password is read from stdin, keeping it
+                // out of the process list.
+                let mut unlock_args = vec!["unlock-keychain".to_string()];
+                if let Some(keychain) = &self.keychain {
+                    unlock_args.push(keychain.display().to_string());
+                }
+
+                warn!(logger, "unlocking keychain");
+                run_tool_with_input(
+                    logger,
+                    "security",
+                    unlock_args,
+                    format!("{}\n", password.resolve()?).as_bytes(),
+                )?;
+            }
+
+            args.push("--sign".to_string());
+            args.push(identity.clone());
+            args.push("--timestamp".to_string());
+
+            if let Some(keychain) = &self.keychain {
+                args.push("--keychain".to_string());
+                args.push(keychain.display().to_string());
+            }
+        }
+
+        args.push(output_path.display().to_string());
+
+        warn!(logger, "building installer {}", output_path.display());
+        run_tool(logger, "productbuild", args)?;
+
+        if let Some(notarization) = &self.notarization {
+            warn!(logger, "notarizing installer {}", output_path.display());
+            let mut notarize_args = vec![
+                "notarytool".to_string(),
+                "submit".to_string(),
+                output_path.display().to_string(),
+                "--keychain-profile".to_string(),
+                notarization.keychain_profile.clone(),
+            ];
+            if let Some(keychain) = &notarization.keychain {
+                notarize_args.push("--keychain".to_string());
+                notarize_args.push(keychain.display().to_string());
+            }
+            notarize_args.push("--wait".to_string());
+
+            run_tool(logger, "xcrun", notarize_args)?;
+            run_tool(
+                logger,
+                "xcrun",
+                vec![
+                    "stapler".to_string(),
+                    "staple".to_string(),
+                    output_path.display().to_string(),
+                ],
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Run a packaging tool, logging its output.
+fn run_tool(logger: &slog::Logger, program: &str, args: Vec<String>) -> Result<()> {
+    run_tool_with_input(logger, program, args, b"")
+}
+
+/// Run a packaging tool with data on its stdin, logging its output.
+fn run_tool_with_input(
+    logger: &slog::Logger,
+    program: &str,
+    args: Vec<String>,
+    input: &[u8],
+) -> Result<()> {
+    let command = cmd(program, args)
+        .stdin_bytes(input.to_vec())
+        .stderr_to_stdout()
+        .unchecked()
+        .reader()
+        .map_err(|e| anyhow!("unable to run {}: {}; is Xcode installed?", program, e))?;
+    {
+        let reader = BufReader::new(&command);
+        for line in reader.lines() {
+            warn!(logger, "{}", line?);
+        }
+    }
+
+    let output = command
+        .try_wait()?
+        .ok_or_else(|| anyhow!("unable to wait on command"))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!("{} failed", program))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identifiers() {
+        assert!(MacOsPkgBuilder::new("My App", "myapp", "1.0").is_err());
+        assert!(MacOsPkgBuilder::new("My App", "com.example.", "1.0").is_err());
+        assert!(MacOsPkgBuilder::new("My App", "com.example.my app", "1.0").is_err());
+        assert!(MacOsPkgBuilder::new("My App", "com.example.my-app", "1.0").is_ok());
+
+        assert!(PkgComponent::new(
+            "com.example.myapp",
+            "1.0",
+            "Applications",
+            FileManifest::default()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_distribution_xml() -> Result<()> {
+        let mut builder = MacOsPkgBuilder::new("My <App>", "com.example.myapp", "1.0")?;
+
+        let component = PkgComponent::new(
+            "com.example.myapp.cli",
+            "1.0",
+            "/usr/local/myapp",
+            FileManifest::default(),
+        )?;
+        builder.add_component(component.clone())?;
+        assert!(builder.add_component(component).is_err());
+
+        assert!(builder.distribution_xml("x86_64-pc-windows-msvc").is_err());
+        assert!(builder
+            .distribution_xml("aarch64-apple-darwin")?
+            .contains("hostArchitectures=\"arm64\""));
+
+        assert_eq!(
+            builder.distribution_xml("x86_64-apple-darwin")?,
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <installer-gui-script minSpecVersion=\"2\">\n\
+             \x20   <title>My &lt;App></title>\n\
+             \x20   <product id=\"com.example.myapp\" version=\"1.0\"/>\n\
+             \x20   <options customize=\"never\" require-scripts=\"false\" hostArchitectures=\"x86_64\"/>\n\
+             \x20   <domains enable_anywhere=\"false\" enable_currentUserHome=\"false\" enable_localSystem=\"true\"/>\n\
+             \x20   <choices-outline>\n\
+             \x20       <line choice=\"default\">\n\
+             \x20           <line choice=\"com.example.myapp.cli\"/>\n\
+             \x20       </line>\n\
+             \x20   </choices-outline>\n\
+             \x20   <choice id=\"default\"/>\n\
+             \x20   <choice id=\"com.example.myapp.cli\" visible=\"false\">\n\
+             \x20       <pkg-ref id=\"com.example.myapp.cli\"/>\n\
+             \x20   </choice>\n\
+             \x20   <pkg-ref id=\"com.example.myapp.cli\" version=\"1.0\" onConclusion=\"none\">com.example.myapp.cli.pkg</pkg-ref>\n\
+             </installer-gui-script>\n"
+        );
+
+        Ok(())
+    }
+}
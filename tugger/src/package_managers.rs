@@ -0,0 +1,752 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Generate manifests for publishing artifacts to package managers.
+
+Manifests point at the URLs artifacts are published at and record their
+SHA-256 digests, which are computed from the built artifacts. The
+following package managers are supported:
+
+* Chocolatey: a package skeleton (`.nuspec` and install script) for an
+  MSI, executable installer or zip archive.
+* winget: a singleton manifest for an MSI or executable installer.
+* Homebrew: a cask for a `.pkg` installer or a formula for a `.tar.gz` or
+  `.zip` archive.
+*/
+
+use {
+    crate::{
+        checksum::ChecksumAlgorithm,
+        file_resource::{FileContent, FileManifest},
+    },
+    anyhow::{anyhow, Result},
+    std::{convert::TryFrom, path::Path},
+    xml::escape::escape_str_pcdata,
+};
+
+/// The kind of a published artifact, derived from its filename.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArtifactKind {
+    Msi,
+    Exe,
+    Zip,
+    Pkg,
+    TarGz,
+}
+
+impl ArtifactKind {
+    /// Resolve the kind of an artifact from its filename.
+    pub fn from_filename(filename: &str) -> Result<Self> {
+        let lower = filename.to_lowercase();
+
+        if lower.ends_with(".msi") {
+            Ok(Self::Msi)
+        } else if lower.ends_with(".exe") {
+            Ok(Self::Exe)
+        } else if lower.ends_with(".zip") {
+            Ok(Self::Zip)
+        } else if lower.ends_with(".pkg") {
+            Ok(Self::Pkg)
+        } else if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
+            Ok(Self::TarGz)
+        } else {
+            Err(anyhow!(
+                "unsupported artifact: {}; expected a .msi, .exe, .zip, .pkg or .tar.gz file",
+                filename
+            ))
+        }
+    }
+}
+
+/// Metadata describing a package.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackageMetadata {
+    /// Identifier of the package for Chocolatey and Homebrew, e.g. `myapp`.
+    pub id: String,
+
+    /// Human readable name of the package.
+    pub name: String,
+
+    /// Version of the package.
+    pub version: String,
+
+    /// Publisher of the package.
+    pub publisher: String,
+
+    /// Short description of the package.
+    pub description: String,
+
+    /// URL of the project's homepage.
+    pub homepage: Option<String>,
+
+    /// SPDX license expression of the package.
+    pub license: Option<String>,
+}
+
+impl PackageMetadata {
+    /// Create an instance with required fields.
+    ///
+    /// `id` is restricted to the lowercase names Chocolatey and Homebrew
+    /// accept.
+    pub fn new(
+        id: &str,
+        name: &str,
+        version: &str,
+        publisher: &str,
+        description: &str,
+    ) -> Result<Self> {
+        if id.is_empty()
+            || id.starts_with('-')
+            || !id
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '.')
+        {
+            return Err(anyhow!(
+                "invalid package id: {}; ids can only contain lowercase letters, digits, '-' and '.'",
+                id
+            ));
+        }
+
+        Ok(Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            version: version.to_string(),
+            publisher: publisher.to_string(),
+            description: description.to_string(),
+            homepage: None,
+            license: None,
+        })
+    }
+
+    /// The winget package identifier, `<Publisher>.<Name>` without whitespace.
+    pub fn winget_identifier(&self) -> String {
+        let strip = |s: &str| s.split_whitespace().collect::<String>();
+
+        format!("{}.{}", strip(&self.publisher), strip(&self.name))
+    }
+}
+
+/// An artifact published at a URL.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PublishedArtifact {
+    /// Filename of the artifact.
+    pub filename: String,
+
+    /// URL the artifact is downloadable from.
+    pub url: String,
+
+    /// Hex SHA-256 digest of the artifact.
+    pub sha256: String,
+
+    /// Kind of the artifact.
+    pub kind: ArtifactKind,
+}
+
+impl PublishedArtifact {
+    /// Create an instance from artifact content.
+    pub fn new(filename: &str, url: &str, data: &[u8]) -> Result<Self> {
+        Ok(Self {
+            filename: filename.to_string(),
+            url: url.to_string(),
+            sha256: ChecksumAlgorithm::Sha256.hex_digest(data),
+            kind: ArtifactKind::from_filename(filename)?,
+        })
+    }
+
+    /// Create an instance from an artifact file.
+    pub fn from_path(path: &Path, url: &str) -> Result<Self> {
+        let filename = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| anyhow!("invalid artifact path: {}", path.display()))?;
+        let data = std::fs::read(path)
+            .map_err(|e| anyhow!("unable to read artifact {}: {}", path.display(), e))?;
+
+        Self::new(filename, url, &data)
+    }
+}
+
+/// Windows architecture of an artifact.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WindowsArchitecture {
+    X86,
+    X64,
+}
+
+impl ToString for WindowsArchitecture {
+    fn to_string(&self) -> String {
+        match self {
+            Self::X86 => "x86",
+            Self::X64 => "x64",
+        }
+        .to_string()
+    }
+}
+
+impl TryFrom<&str> for WindowsArchitecture {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "x86" => Ok(Self::X86),
+            "x64" => Ok(Self::X64),
+            _ => Err(format!(
+                "{} is not a valid architecture; use x86 or x64",
+                value
+            )),
+        }
+    }
+}
+
+impl WindowsArchitecture {
+    /// Resolve the architecture for a target triple.
+    pub fn from_target_triple(target_triple: &str) -> Self {
+        if target_triple.starts_with("i686-") {
+            Self::X86
+        } else {
+            Self::X64
+        }
+    }
+}
+
+/// Quote a string for PowerShell.
+fn powershell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Quote a string for YAML.
+fn yaml_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Quote a string for Ruby.
+fn ruby_quote(value: &str) -> String {
+    format!(
+        "\"{}\"",
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('#', "\\#")
+    )
+}
+
+/// Obtain the `.nuspec` file of a Chocolatey package.
+pub fn chocolatey_nuspec(metadata: &PackageMetadata) -> String {
+    let mut res = String::new();
+
+    res.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    res.push_str("<package xmlns=\"http://schemas.microsoft.com/packaging/2015/06/nuspec.xsd\">\n");
+    res.push_str("    <metadata>\n");
+    for (element, value) in &[
+        ("id", Some(&metadata.id)),
+        ("version", Some(&metadata.version)),
+        ("title", Some(&metadata.name)),
+        ("authors", Some(&metadata.publisher)),
+        ("projectUrl", metadata.homepage.as_ref()),
+        ("description", Some(&metadata.description)),
+    ] {
+        if let Some(value) = value {
+            res.push_str(&format!(
+                "        <{}>{}</{}>\n",
+                element,
+                escape_str_pcdata(value),
+                element
+            ));
+        }
+    }
+    res.push_str("    </metadata>\n");
+    res.push_str("    <files>\n");
+    res.push_str("        <file src=\"tools\\**\" target=\"tools\"/>\n");
+    res.push_str("    </files>\n");
+    res.push_str("</package>\n");
+
+    res
+}
+
+/// Obtain the `chocolateyinstall.ps1` script installing an artifact.
+pub fn chocolatey_install_script(
+    artifact: &PublishedArtifact,
+    architecture: WindowsArchitecture,
+) -> Result<String> {
+    let (command, file_type, silent_args) = match artifact.kind {
+        ArtifactKind::Msi => (
+            "Install-ChocolateyPackage",
+            Some("msi"),
+            Some("/qn /norestart"),
+        ),
+        ArtifactKind::Exe => ("Install-ChocolateyPackage", Some("exe"), Some("/quiet")),
+        ArtifactKind::Zip => ("Install-ChocolateyZipPackage", None, None),
+        _ => {
+            return Err(anyhow!(
+                "{} cannot be installed by Chocolatey",
+                artifact.filename
+            ))
+        }
+    };
+
+    let suffix = match architecture {
+        WindowsArchitecture::X86 => "",
+        WindowsArchitecture::X64 => "64",
+    };
+
+    let mut args = vec![("packageName", "$env:ChocolateyPackageName".to_string())];
+    if let Some(file_type) = file_type {
+        args.push(("fileType", powershell_quote(file_type)));
+    }
+    if artifact.kind == ArtifactKind::Zip {
+        args.push(("unzipLocation", "$toolsDir".to_string()));
+    }
+    let url_key = format!("url{}", suffix);
+    let checksum_key = format!("checksum{}", suffix);
+    let checksum_type_key = format!("checksumType{}", suffix);
+    args.push((url_key.as_str(), powershell_quote(&artifact.url)));
+    args.push((checksum_key.as_str(), powershell_quote(&artifact.sha256)));
+    args.push((checksum_type_key.as_str(), powershell_quote("sha256")));
+    if let Some(silent_args) = silent_args {
+        args.push(("silentArgs", powershell_quote(silent_args)));
+        args.push(("validExitCodes", "@(0, 3010, 1641)".to_string()));
+    }
+
+    let width = args.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+
+    let mut res = String::new();
+    res.push_str("$ErrorActionPreference = 'Stop'\n");
+    res.push_str("$toolsDir = Split-Path -Parent $MyInvocation.MyCommand.Definition\n");
+    res.push('\n');
+    res.push_str("$packageArgs = @{\n");
+    for (key, value) in &args {
+        res.push_str(&format!("  {:width$} = {}\n", key, value, width = width));
+    }
+    res.push_str("}\n");
+    res.push('\n');
+    res.push_str(&format!("{} @packageArgs\n", command));
+
+    Ok(res)
+}
+
+/// Obtain a winget singleton manifest for an installer.
+pub fn winget_manifest(
+    metadata: &PackageMetadata,
+    artifact: &PublishedArtifact,
+    architecture: WindowsArchitecture,
+) -> Result<String> {
+    let installer_type = match artifact.kind {
+        ArtifactKind::Msi => "msi",
+        // Executable installers produced by WiX are burn bundles.
+        ArtifactKind::Exe => "burn",
+        _ => {
+            return Err(anyhow!(
+                "{} cannot be installed by winget; use an MSI or executable installer",
+                artifact.filename
+            ))
+        }
+    };
+
+    let mut res = String::new();
+    res.push_str(&format!(
+        "PackageIdentifier: {}\n",
+        yaml_quote(&metadata.winget_identifier())
+    ));
+    res.push_str(&format!(
+        "PackageVersion: {}\n",
+        yaml_quote(&metadata.version)
+    ));
+    res.push_str(&format!("PackageName: {}\n", yaml_quote(&metadata.name)));
+    res.push_str(&format!("Publisher: {}\n", yaml_quote(&metadata.publisher)));
+    res.push_str(&format!(
+        "License: {}\n",
+        yaml_quote(metadata.license.as_deref().unwrap_or("Proprietary"))
+    ));
+    res.push_str(&format!(
+        "ShortDescription: {}\n",
+        yaml_quote(&metadata.description)
+    ));
+    if let Some(homepage) = &metadata.homepage {
+        res.push_str(&format!("PackageUrl: {}\n", yaml_quote(homepage)));
+    }
+    res.push_str("Installers:\n");
+    res.push_str(&format!("  - Architecture: {}\n", architecture.to_string()));
+    res.push_str(&format!("    InstallerType: {}\n", installer_type));
+    res.push_str(&format!(
+        "    InstallerUrl: {}\n",
+        yaml_quote(&artifact.url)
+    ));
+    res.push_str(&format!(
+        "    InstallerSha256: {}\n",
+        artifact.sha256.to_uppercase()
+    ));
+    res.push_str("ManifestType: singleton\n");
+    res.push_str("ManifestVersion: 1.0.0\n");
+
+    Ok(res)
+}
+
+/// Obtain the filename Homebrew stores a download from a URL as.
+///
+/// This is the percent-decoded last segment of the URL path.
+fn url_basename(value: &str) -> Result<String> {
+    let url = url::Url::parse(value).map_err(|e| anyhow!("invalid URL {}: {}", value, e))?;
+
+    let basename = url
+        .path_segments()
+        .and_then(|segments| segments.last())
+        .filter(|segment| !segment.is_empty())
+        .ok_or_else(|| anyhow!("URL {} doesn't end with a filename", value))?;
+
+    Ok(percent_encoding::percent_decode_str(basename)
+        .decode_utf8_lossy()
+        .to_string())
+}
+
+/// Obtain a Homebrew cask installing a `.pkg` installer.
+///
+/// Homebrew names the downloaded installer after its URL, so the `pkg`
+/// stanza refers to the basename of `artifact.url`.
+///
+/// `pkg_ids` are the identifiers of the component packages installed,
+/// which are forgotten on uninstall.
+pub fn homebrew_cask(
+    metadata: &PackageMetadata,
+    artifact: &PublishedArtifact,
+    pkg_ids: &[String],
+) -> Result<String> {
+    if artifact.kind != ArtifactKind::Pkg {
+        return Err(anyhow!(
+            "Homebrew casks require a .pkg installer; got {}",
+            artifact.filename
+        ));
+    }
+
+    let mut res = String::new();
+    res.push_str(&format!("cask {} do\n", ruby_quote(&metadata.id)));
+    res.push_str(&format!("  version {}\n", ruby_quote(&metadata.version)));
+    res.push_str(&format!("  sha256 {}\n", ruby_quote(&artifact.sha256)));
+    res.push('\n');
+    res.push_str(&format!("  url {}\n", ruby_quote(&artifact.url)));
+    res.push_str(&format!("  name {}\n", ruby_quote(&metadata.name)));
+    res.push_str(&format!("  desc {}\n", ruby_quote(&metadata.description)));
+    if let Some(homepage) = &metadata.homepage {
+        res.push_str(&format!("  homepage {}\n", ruby_quote(homepage)));
+    }
+    res.push('\n');
+    res.push_str(&format!(
+        "  pkg {}\n",
+        ruby_quote(&url_basename(&artifact.url)?)
+    ));
+
+    if !pkg_ids.is_empty() {
+        res.push('\n');
+        res.push_str(&format!(
+            "  uninstall pkgutil: [{}]\n",
+            pkg_ids
+                .iter()
+                .map(|id| ruby_quote(id))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    res.push_str("end\n");
+
+    Ok(res)
+}
+
+/// Obtain the Ruby class name of a Homebrew formula.
+fn formula_class_name(id: &str) -> String {
+    id.split(&['-', '.'][..])
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// Obtain a Homebrew formula installing an archive.
+///
+/// The archive content is installed to the formula's `libexec` directory
+/// so files next to executables remain available. `binaries` are the
+/// executables in the archive to link into the `bin` directory.
+pub fn homebrew_formula(
+    metadata: &PackageMetadata,
+    artifact: &PublishedArtifact,
+    binaries: &[String],
+) -> Result<String> {
+    match artifact.kind {
+        ArtifactKind::TarGz | ArtifactKind::Zip => {}
+        _ => {
+            return Err(anyhow!(
+                "Homebrew formulae require a .tar.gz or .zip archive; got {}",
+                artifact.filename
+            ))
+        }
+    }
+
+    if binaries.is_empty() {
+        return Err(anyhow!("at least one binary is required"));
+    }
+
+    let mut res = String::new();
+    res.push_str(&format!(
+        "class {} < Formula\n",
+        formula_class_name(&metadata.id)
+    ));
+    res.push_str(&format!("  desc {}\n", ruby_quote(&metadata.description)));
+    if let Some(homepage) = &metadata.homepage {
+        res.push_str(&format!("  homepage {}\n", ruby_quote(homepage)));
+    }
+    res.push_str(&format!("  url {}\n", ruby_quote(&artifact.url)));
+    res.push_str(&format!("  version {}\n", ruby_quote(&metadata.version)));
+    res.push_str(&format!("  sha256 {}\n", ruby_quote(&artifact.sha256)));
+    if let Some(license) = &metadata.license {
+        res.push_str(&format!("  license {}\n", ruby_quote(license)));
+    }
+    res.push('\n');
+    res.push_str("  def install\n");
+    res.push_str("    libexec.install Dir[\"*\"]\n");
+    for binary in binaries {
+        res.push_str(&format!(
+            "    bin.install_symlink libexec/{}\n",
+            ruby_quote(binary)
+        ));
+    }
+    res.push_str("  end\n");
+    res.push_str("end\n");
+
+    Ok(res)
+}
+
+/// Manifests for publishing a package to package managers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackageManagerManifests {
+    pub metadata: PackageMetadata,
+
+    /// Windows artifact and its architecture.
+    pub windows: Option<(PublishedArtifact, WindowsArchitecture)>,
+
+    /// macOS artifact.
+    pub macos: Option<PublishedArtifact>,
+
+    /// Identifiers of component packages installed by a `.pkg` artifact.
+    pub pkg_ids: Vec<String>,
+
+    /// Executables linked into `bin` by a Homebrew formula.
+    pub binaries: Vec<String>,
+}
+
+impl PackageManagerManifests {
+    /// Create an instance without artifacts.
+    pub fn new(metadata: PackageMetadata) -> Self {
+        let binaries = vec![metadata.id.clone()];
+
+        Self {
+            metadata,
+            windows: None,
+            macos: None,
+            pkg_ids: vec![],
+            binaries,
+        }
+    }
+
+    /// Add all manifests to a `FileManifest`.
+    ///
+    /// Manifests are generated for each package manager supporting an
+    /// artifact:
+    ///
+    /// * `chocolatey/<id>/<id>.nuspec` and
+    ///   `chocolatey/<id>/tools/chocolateyinstall.ps1`
+    /// * `winget/<identifier>.yaml`
+    /// * `homebrew/Casks/<id>.rb` or `homebrew/Formula/<id>.rb`
+    pub fn add_to_manifest(&self, manifest: &mut FileManifest) -> Result<()> {
+        let text = |data: String| FileContent {
+            data: data.into_bytes(),
+            executable: false,
+        };
+
+        let id = &self.metadata.id;
+
+        if let Some((artifact, architecture)) = &self.windows {
+            let chocolatey = Path::new("chocolatey").join(id);
+            manifest.add_file(
+                chocolatey.join(format!("{}.nuspec", id)),
+                &text(chocolatey_nuspec(&self.metadata)),
+            )?;
+            manifest.add_file(
+                chocolatey.join("tools").join("chocolateyinstall.ps1"),
+                &text(chocolatey_install_script(artifact, *architecture)?),
+            )?;
+
+            if artifact.kind != ArtifactKind::Zip {
+                manifest.add_file(
+                    Path::new("winget").join(format!("{}.yaml", self.metadata.winget_identifier())),
+                    &text(winget_manifest(&self.metadata, artifact, *architecture)?),
+                )?;
+            }
+        }
+
+        if let Some(artifact) = &self.macos {
+            let (path, data) = if artifact.kind == ArtifactKind::Pkg {
+                (
+                    Path::new("homebrew").join("Casks"),
+                    homebrew_cask(&self.metadata, artifact, &self.pkg_ids)?,
+                )
+            } else {
+                (
+                    Path::new("homebrew").join("Formula"),
+                    homebrew_formula(&self.metadata, artifact, &self.binaries)?,
+                )
+            };
+
+            manifest.add_file(path.join(format!("{}.rb", id)), &text(data))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::path::PathBuf};
+
+    fn metadata() -> Result<PackageMetadata> {
+        let mut metadata =
+            PackageMetadata::new("myapp", "My App", "1.0", "Example Inc", "Does \"things\"")?;
+        metadata.homepage = Some("https://example.com/".to_string());
+
+        Ok(metadata)
+    }
+
+    #[test]
+    fn test_metadata() -> Result<()> {
+        assert!(PackageMetadata::new("MyApp", "My App", "1.0", "Example", "").is_err());
+        assert!(PackageMetadata::new("my app", "My App", "1.0", "Example", "").is_err());
+
+        assert_eq!(metadata()?.winget_identifier(), "ExampleInc.MyApp");
+        assert_eq!(formula_class_name("my-app.cli"), "MyAppCli");
+
+        assert_eq!(
+            ArtifactKind::from_filename("a.TAR.GZ")?,
+            ArtifactKind::TarGz
+        );
+        assert!(ArtifactKind::from_filename("a.dmg").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_windows() -> Result<()> {
+        let metadata = metadata()?;
+        let msi = PublishedArtifact::new("myapp-1.0.msi", "https://example.com/myapp.msi", b"")?;
+
+        assert_eq!(
+            chocolatey_install_script(&msi, WindowsArchitecture::X64)?,
+            "$ErrorActionPreference = 'Stop'\n\
+             $toolsDir = Split-Path -Parent $MyInvocation.MyCommand.Definition\n\
+             \n\
+             $packageArgs = @{\n\
+             \x20 packageName    = $env:ChocolateyPackageName\n\
+             \x20 fileType       = 'msi'\n\
+             \x20 url64          = 'https://example.com/myapp.msi'\n\
+             \x20 checksum64     = 'e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855'\n\
+             \x20 checksumType64 = 'sha256'\n\
+             \x20 silentArgs     = '/qn /norestart'\n\
+             \x20 validExitCodes = @(0, 3010, 1641)\n\
+             }\n\
+             \n\
+             Install-ChocolateyPackage @packageArgs\n"
+        );
+
+        assert_eq!(
+            winget_manifest(&metadata, &msi, WindowsArchitecture::X86)?,
+            "PackageIdentifier: 'ExampleInc.MyApp'\n\
+             PackageVersion: '1.0'\n\
+             PackageName: 'My App'\n\
+             Publisher: 'Example Inc'\n\
+             License: 'Proprietary'\n\
+             ShortDescription: 'Does \"things\"'\n\
+             PackageUrl: 'https://example.com/'\n\
+             Installers:\n\
+             \x20 - Architecture: x86\n\
+             \x20   InstallerType: msi\n\
+             \x20   InstallerUrl: 'https://example.com/myapp.msi'\n\
+             \x20   InstallerSha256: E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855\n\
+             ManifestType: singleton\n\
+             ManifestVersion: 1.0.0\n"
+        );
+
+        assert!(chocolatey_nuspec(&metadata).contains("<authors>Example Inc</authors>"));
+
+        let zip = PublishedArtifact::new("myapp.zip", "https://example.com/myapp.zip", b"")?;
+        assert!(chocolatey_install_script(&zip, WindowsArchitecture::X64)?
+            .contains("Install-ChocolateyZipPackage @packageArgs"));
+        assert!(winget_manifest(&metadata, &zip, WindowsArchitecture::X64).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_homebrew() -> Result<()> {
+        let metadata = metadata()?;
+        let pkg = PublishedArtifact::new("myapp-1.0.pkg", "https://example.com/myapp.pkg", b"")?;
+
+        assert_eq!(
+            homebrew_cask(&metadata, &pkg, &["com.example.myapp".to_string()])?,
+            "cask \"myapp\" do\n\
+             \x20 version \"1.0\"\n\
+             \x20 sha256 \"e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855\"\n\
+             \n\
+             \x20 url \"https://example.com/myapp.pkg\"\n\
+             \x20 name \"My App\"\n\
+             \x20 desc \"Does \\\"things\\\"\"\n\
+             \x20 homepage \"https://example.com/\"\n\
+             \n\
+             \x20 pkg \"myapp.pkg\"\n\
+             \n\
+             \x20 uninstall pkgutil: [\"com.example.myapp\"]\n\
+             end\n"
+        );
+
+        let pkg = PublishedArtifact::new(
+            "myapp-1.0.pkg",
+            "https://example.com/download/My%20App.pkg?token=abc",
+            b"",
+        )?;
+        assert!(homebrew_cask(&metadata, &pkg, &[])?.contains("  pkg \"My App.pkg\"\n"));
+        let pkg = PublishedArtifact::new("myapp-1.0.pkg", "https://example.com/", b"")?;
+        assert!(homebrew_cask(&metadata, &pkg, &[]).is_err());
+
+        let archive =
+            PublishedArtifact::new("myapp.tar.gz", "https://example.com/myapp.tar.gz", b"")?;
+        let formula = homebrew_formula(&metadata, &archive, &["myapp".to_string()])?;
+        assert!(formula.starts_with("class Myapp < Formula\n"));
+        assert!(formula.contains("    bin.install_symlink libexec/\"myapp\"\n"));
+        assert!(homebrew_formula(&metadata, &pkg, &["myapp".to_string()]).is_err());
+
+        let mut manifests = PackageManagerManifests::new(metadata);
+        manifests.windows = Some((
+            PublishedArtifact::new("myapp.msi", "https://example.com/myapp.msi", b"")?,
+            WindowsArchitecture::X64,
+        ));
+        manifests.macos = Some(archive);
+
+        let mut manifest = FileManifest::default();
+        manifests.add_to_manifest(&mut manifest)?;
+        assert_eq!(
+            manifest
+                .entries()
+                .map(|(path, _)| path.to_path_buf())
+                .collect::<Vec<_>>(),
+            vec![
+                PathBuf::from("chocolatey/myapp/myapp.nuspec"),
+                PathBuf::from("chocolatey/myapp/tools/chocolateyinstall.ps1"),
+                PathBuf::from("homebrew/Formula/myapp.rb"),
+                PathBuf::from("winget/ExampleInc.MyApp.yaml"),
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Filesystem path handling that holds up on Windows.
+//!
+//! Windows APIs reject paths longer than `MAX_PATH` (260 characters) unless
+//! they use the `\\?\` verbatim form, which deep `site-packages` trees
+//! easily exceed. Verbatim paths aren't normalized by Windows, so they must
+//! be absolute and only use `\` separators.
+
+use std::path::{Path, PathBuf};
+
+/// Maximum length of a path accepted by Windows APIs in non-verbatim form.
+const MAX_PATH: usize = 260;
+
+/// Paths this long are converted to verbatim form before being used.
+///
+/// Directories are limited to `MAX_PATH` minus the length of an 8.3 file
+/// name, so this is lower than `MAX_PATH`.
+pub const LONG_PATH_THRESHOLD: usize = MAX_PATH - 12;
+
+/// Convert an absolute Windows path to its `\\?\` verbatim form.
+///
+/// `.` and `..` components are resolved since Windows doesn't do it for
+/// verbatim paths. UNC paths (`\\server\share\...`) become
+/// `\\?\UNC\server\share\...`. Returns `None` for relative paths.
+fn verbatim_string(path: &str) -> Option<String> {
+    let path = path.replace('/', "\\");
+
+    if path.starts_with(r"\\?\") {
+        return Some(path);
+    }
+
+    let (mut res, rest) = if let Some(unc) = path.strip_prefix(r"\\") {
+        let mut parts = unc.splitn(3, '\\');
+        let server = parts.next().filter(|s| !s.is_empty())?;
+        let share = parts.next().filter(|s| !s.is_empty())?;
+
+        (
+            format!(r"\\?\UNC\{}\{}", server, share),
+            parts.next().unwrap_or("").to_string(),
+        )
+    } else if path.len() >= 3
+        && path.as_bytes()[0].is_ascii_alphabetic()
+        && &path.as_bytes()[1..3] == b":\\"
+    {
+        (format!(r"\\?\{}", &path[0..2]), path[3..].to_string())
+    } else {
+        return None;
+    };
+
+    let mut components = vec![];
+    for component in rest.split('\\') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            c => components.push(c),
+        }
+    }
+
+    if components.is_empty() {
+        res.push('\\');
+    }
+    for component in components {
+        res.push('\\');
+        res.push_str(component);
+    }
+
+    Some(res)
+}
+
+/// Obtain a path usable with filesystem APIs regardless of its length.
+///
+/// On Windows, paths too long for the regular form are made absolute and
+/// converted to verbatim form. Other paths are returned as is.
+pub fn long_path(path: &Path) -> PathBuf {
+    if !cfg!(windows) || path.as_os_str().len() < LONG_PATH_THRESHOLD {
+        return path.to_path_buf();
+    }
+
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        match std::env::current_dir() {
+            Ok(cwd) => cwd.join(path),
+            Err(_) => return path.to_path_buf(),
+        }
+    };
+
+    match verbatim_string(&absolute.display().to_string()) {
+        Some(s) => PathBuf::from(s),
+        None => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verbatim_string() {
+        assert_eq!(
+            verbatim_string(r"C:\Users\foo\..\bar\.\baz"),
+            Some(r"\\?\C:\Users\bar\baz".to_string())
+        );
+        assert_eq!(verbatim_string("C:/a/b/"), Some(r"\\?\C:\a\b".to_string()));
+        assert_eq!(verbatim_string(r"C:\"), Some(r"\\?\C:\".to_string()));
+        assert_eq!(
+            verbatim_string(r"\\server\share\a\..\..\b"),
+            Some(r"\\?\UNC\server\share\b".to_string())
+        );
+        assert_eq!(
+            verbatim_string(r"\\?\C:\a/b"),
+            Some(r"\\?\C:\a\b".to_string())
+        );
+        assert_eq!(verbatim_string(r"\\server"), None);
+        assert_eq!(verbatim_string(r"a\b"), None);
+        assert_eq!(verbatim_string("/usr/lib"), None);
+    }
+
+    #[test]
+    fn test_long_path() {
+        let path = PathBuf::from("a".repeat(300));
+
+        if cfg!(windows) {
+            assert!(long_path(&path).display().to_string().starts_with(r"\\?\"));
+        } else {
+            assert_eq!(long_path(&path), path);
+        }
+
+        assert_eq!(long_path(Path::new("short")), PathBuf::from("short"));
+    }
+}
@@ -0,0 +1,480 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Verify and upload release artifacts.
+
+Artifacts can be uploaded to GitHub Releases, S3 buckets or any HTTP
+server accepting `PUT` requests. Before uploading, signatures of artifacts
+can be verified so unsigned builds are never published.
+*/
+
+use {
+    crate::{http::get_http_client, secrets::Secret},
+    anyhow::{anyhow, Result},
+    duct::cmd,
+    slog::warn,
+    std::{
+        io::{BufRead, BufReader},
+        path::{Path, PathBuf},
+    },
+};
+
+/// Run a command, logging its output.
+fn run_command(logger: &slog::Logger, program: &str, args: Vec<String>) -> Result<()> {
+    let command = cmd(program, args)
+        .stderr_to_stdout()
+        .unchecked()
+        .reader()
+        .map_err(|e| anyhow!("unable to run {}: {}", program, e))?;
+    {
+        let reader = BufReader::new(&command);
+        for line in reader.lines() {
+            warn!(logger, "{}", line?);
+        }
+    }
+
+    let output = command
+        .try_wait()?
+        .ok_or_else(|| anyhow!("unable to wait on command"))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!("{} failed", program))
+    }
+}
+
+/// Obtain the filename of an artifact.
+fn artifact_filename(path: &Path) -> Result<&str> {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow!("invalid artifact path: {}", path.display()))
+}
+
+/// How the signature of an artifact is verified.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SignatureCheck {
+    /// A signed macOS installer, checked with `pkgutil`.
+    Pkgutil,
+
+    /// An Authenticode signed Windows file, checked with `signtool`.
+    Signtool,
+
+    /// A detached GPG signature, checked with `gpg`.
+    Gpg(PathBuf),
+}
+
+impl SignatureCheck {
+    /// Resolve how to verify the signature of an artifact.
+    ///
+    /// Installers carry their signatures. Other artifacts need a detached
+    /// signature next to them, named `<artifact>.asc` or `<artifact>.sig`.
+    pub fn for_artifact(path: &Path) -> Result<Self> {
+        let filename = artifact_filename(path)?.to_lowercase();
+
+        if filename.ends_with(".pkg") {
+            return Ok(Self::Pkgutil);
+        }
+
+        if filename.ends_with(".msi") || filename.ends_with(".exe") {
+            return Ok(Self::Signtool);
+        }
+
+        for extension in &["asc", "sig"] {
+            let mut signature = path.as_os_str().to_os_string();
+            signature.push(".");
+            signature.push(extension);
+            let signature = PathBuf::from(signature);
+
+            if signature.exists() {
+                return Ok(Self::Gpg(signature));
+            }
+        }
+
+        Err(anyhow!(
+            "no signature found for {}; expected a .asc or .sig file next to it",
+            path.display()
+        ))
+    }
+
+    /// Verify the signature of an artifact.
+    pub fn verify(&self, logger: &slog::Logger, path: &Path) -> Result<()> {
+        let path_arg = path.display().to_string();
+
+        match self {
+            Self::Pkgutil => run_command(
+                logger,
+                "pkgutil",
+                vec!["--check-signature".to_string(), path_arg],
+            ),
+            Self::Signtool => run_command(
+                logger,
+                "signtool",
+                vec!["verify".to_string(), "/pa".to_string(), path_arg],
+            ),
+            Self::Gpg(signature) => run_command(
+                logger,
+                "gpg",
+                vec![
+                    "--verify".to_string(),
+                    signature.display().to_string(),
+                    path_arg,
+                ],
+            ),
+        }
+        .map_err(|e| anyhow!("signature verification of {} failed: {}", path.display(), e))
+    }
+}
+
+/// Verify the signature of an artifact.
+pub fn verify_signature(logger: &slog::Logger, path: &Path) -> Result<()> {
+    warn!(logger, "verifying signature of {}", path.display());
+    SignatureCheck::for_artifact(path)?.verify(logger, path)
+}
+
+/// A location release artifacts are uploaded to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum UploadDestination {
+    /// Assets of a GitHub release.
+    ///
+    /// The release is created as a draft if it doesn't exist.
+    GitHubRelease {
+        /// Repository, as `<owner>/<name>`.
+        repository: String,
+
+        /// Tag of the release.
+        tag: String,
+
+        /// Access token.
+        token: Secret,
+    },
+
+    /// Objects in an S3 bucket, uploaded with the `aws` CLI.
+    S3 {
+        /// Name of the bucket.
+        bucket: String,
+
+        /// Key prefix of uploaded objects.
+        prefix: String,
+    },
+
+    /// `PUT` requests to URLs below a base URL.
+    HttpPut {
+        /// Base URL. The artifact filename is appended.
+        url: String,
+
+        /// Bearer token.
+        token: Option<Secret>,
+    },
+}
+
+impl UploadDestination {
+    /// Describe where an artifact with a filename is uploaded to.
+    pub fn describe(&self, filename: &str) -> String {
+        match self {
+            Self::GitHubRelease {
+                repository, tag, ..
+            } => format!("GitHub release {} of {}: {}", tag, repository, filename),
+            Self::S3 { .. } | Self::HttpPut { .. } => self.location(filename),
+        }
+    }
+
+    /// The location of an uploaded artifact.
+    fn location(&self, filename: &str) -> String {
+        match self {
+            Self::GitHubRelease {
+                repository, tag, ..
+            } => format!(
+                "https://github.com/{}/releases/download/{}/{}",
+                repository, tag, filename
+            ),
+            Self::S3 { bucket, prefix } => {
+                format!("s3://{}/{}{}", bucket, s3_key_prefix(prefix), filename)
+            }
+            Self::HttpPut { url, .. } => format!("{}/{}", url.trim_end_matches('/'), filename),
+        }
+    }
+
+    /// Upload artifacts.
+    pub fn upload(&self, logger: &slog::Logger, paths: &[PathBuf]) -> Result<()> {
+        // Resolving a GitHub release may create it, so it is done once for
+        // all artifacts.
+        let github_release = match self {
+            Self::GitHubRelease {
+                repository,
+                tag,
+                token,
+            } => Some(GitHubRelease::resolve(repository, tag, token.resolve()?)?),
+            _ => None,
+        };
+
+        for path in paths {
+            let filename = artifact_filename(path)?;
+            warn!(
+                logger,
+                "uploading {} to {}",
+                path.display(),
+                self.describe(filename)
+            );
+
+            match self {
+                Self::GitHubRelease { .. } => github_release
+                    .as_ref()
+                    .expect("GitHub release should be resolved")
+                    .upload_asset(filename, std::fs::read(path)?)?,
+                Self::S3 { .. } => run_command(
+                    logger,
+                    "aws",
+                    vec![
+                        "s3".to_string(),
+                        "cp".to_string(),
+                        path.display().to_string(),
+                        self.location(filename),
+                    ],
+                )?,
+                Self::HttpPut { token, .. } => {
+                    let client = get_http_client()?;
+                    let mut request = client
+                        .put(&self.location(filename))
+                        .body(std::fs::read(path)?);
+                    if let Some(token) = token {
+                        request = request.bearer_auth(token.resolve()?);
+                    }
+
+                    let response = request.send()?;
+                    if !response.status().is_success() {
+                        return Err(anyhow!(
+                            "uploading {} failed: HTTP {}",
+                            filename,
+                            response.status()
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Normalize an S3 key prefix to be empty or end with `/`.
+fn s3_key_prefix(prefix: &str) -> String {
+    let prefix = prefix.trim_matches('/');
+
+    if prefix.is_empty() {
+        String::new()
+    } else {
+        format!("{}/", prefix)
+    }
+}
+
+/// A GitHub release assets are uploaded to.
+struct GitHubRelease {
+    client: reqwest::blocking::Client,
+    token: String,
+    tag: String,
+    upload_url: String,
+}
+
+impl GitHubRelease {
+    /// Send an authenticated GitHub API request.
+    fn send(
+        &self,
+        builder: reqwest::blocking::RequestBuilder,
+    ) -> Result<reqwest::blocking::Response> {
+        Ok(builder
+            .header("Authorization", format!("token {}", self.token))
+            .header("Accept", "application/vnd.github.v3+json")
+            .header("User-Agent", "tugger")
+            .send()?)
+    }
+
+    /// Resolve the release of a tag, creating a draft release if needed.
+    ///
+    /// Draft releases aren't returned when looking up a release by tag, so
+    /// releases are listed to find an existing draft.
+    fn resolve(repository: &str, tag: &str, token: String) -> Result<Self> {
+        let mut res = Self {
+            client: get_http_client()?,
+            token,
+            tag: tag.to_string(),
+            upload_url: String::new(),
+        };
+        let api = format!("https://api.github.com/repos/{}/releases", repository);
+
+        let error = |status: reqwest::StatusCode| {
+            anyhow!(
+                "unable to resolve GitHub release {} of {}: HTTP {}",
+                tag,
+                repository,
+                status
+            )
+        };
+
+        let response = res.send(res.client.get(&format!("{}/tags/{}", api, tag)))?;
+        let release = if response.status().is_success() {
+            Some(serde_json::from_slice::<serde_json::Value>(
+                &response.bytes()?,
+            )?)
+        } else if response.status() == reqwest::StatusCode::NOT_FOUND {
+            res.find_draft(&api)?
+        } else {
+            return Err(error(response.status()));
+        };
+
+        let release = match release {
+            Some(release) => release,
+            None => {
+                let body = serde_json::json!({
+                    "tag_name": tag,
+                    "name": tag,
+                    "draft": true,
+                });
+
+                let response = res.send(
+                    res.client
+                        .post(&api)
+                        .header("Content-Type", "application/json")
+                        .body(serde_json::to_vec(&body)?),
+                )?;
+                if !response.status().is_success() {
+                    return Err(error(response.status()));
+                }
+
+                serde_json::from_slice(&response.bytes()?)?
+            }
+        };
+
+        let upload_url = release["upload_url"]
+            .as_str()
+            .ok_or_else(|| anyhow!("GitHub release has no upload URL"))?;
+        // The URL is a template like `.../assets{?name,label}`.
+        res.upload_url = upload_url
+            .split('{')
+            .next()
+            .unwrap_or(upload_url)
+            .to_string();
+
+        Ok(res)
+    }
+
+    /// Find a draft release of the tag.
+    fn find_draft(&self, api: &str) -> Result<Option<serde_json::Value>> {
+        for page in 1.. {
+            let page = page.to_string();
+            let response = self.send(
+                self.client
+                    .get(api)
+                    .query(&[("per_page", "100"), ("page", page.as_str())]),
+            )?;
+            if !response.status().is_success() {
+                return Err(anyhow!(
+                    "unable to list GitHub releases: HTTP {}",
+                    response.status()
+                ));
+            }
+
+            let releases: Vec<serde_json::Value> = serde_json::from_slice(&response.bytes()?)?;
+            if releases.is_empty() {
+                break;
+            }
+
+            if let Some(release) = releases
+                .into_iter()
+                .find(|r| r["draft"] == true && r["tag_name"] == self.tag.as_str())
+            {
+                return Ok(Some(release));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Upload an asset to the release.
+    fn upload_asset(&self, filename: &str, data: Vec<u8>) -> Result<()> {
+        let response = self.send(
+            self.client
+                .post(&self.upload_url)
+                .query(&[("name", filename)])
+                .header("Content-Type", "application/octet-stream")
+                .body(data),
+        )?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "uploading {} to GitHub release {} failed: HTTP {}",
+                filename,
+                self.tag,
+                response.status()
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_check() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("tugger-test")?;
+        let archive = temp_dir.path().join("myapp.tar.gz");
+        std::fs::write(&archive, b"")?;
+
+        assert_eq!(
+            SignatureCheck::for_artifact(Path::new("myapp.pkg"))?,
+            SignatureCheck::Pkgutil
+        );
+        assert_eq!(
+            SignatureCheck::for_artifact(Path::new("MyApp.MSI"))?,
+            SignatureCheck::Signtool
+        );
+        assert!(SignatureCheck::for_artifact(&archive).is_err());
+
+        let signature = temp_dir.path().join("myapp.tar.gz.asc");
+        std::fs::write(&signature, b"")?;
+        assert_eq!(
+            SignatureCheck::for_artifact(&archive)?,
+            SignatureCheck::Gpg(signature)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_destinations() {
+        let github = UploadDestination::GitHubRelease {
+            repository: "example/myapp".to_string(),
+            tag: "v1.0".to_string(),
+            token: Secret::Env("GITHUB_TOKEN".to_string()),
+        };
+        assert_eq!(
+            github.location("myapp.msi"),
+            "https://github.com/example/myapp/releases/download/v1.0/myapp.msi"
+        );
+        assert_eq!(
+            github.describe("myapp.msi"),
+            "GitHub release v1.0 of example/myapp: myapp.msi"
+        );
+
+        let s3 = UploadDestination::S3 {
+            bucket: "releases".to_string(),
+            prefix: "/myapp/1.0/".to_string(),
+        };
+        assert_eq!(
+            s3.describe("myapp.msi"),
+            "s3://releases/myapp/1.0/myapp.msi"
+        );
+
+        let http = UploadDestination::HttpPut {
+            url: "https://example.com/uploads/".to_string(),
+            token: None,
+        };
+        assert_eq!(
+            http.describe("myapp.msi"),
+            "https://example.com/uploads/myapp.msi"
+        );
+    }
+}
@@ -0,0 +1,150 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Secrets used during builds, such as signing and upload credentials.
+
+A [`Secret`] describes where a value is obtained from. The value is only
+read when it is needed and is never stored, so secrets can be freely
+cloned, logged and written to reports: only their source is revealed.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    duct::cmd,
+    std::path::PathBuf,
+};
+
+/// A source of a secret value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Secret {
+    /// An environment variable.
+    Env(String),
+
+    /// The content of a file, without trailing newlines.
+    File(PathBuf),
+
+    /// A generic password in the OS keychain.
+    ///
+    /// On macOS, this is read from the keychain search list with
+    /// `security`. On Linux, it is read from the Secret Service with
+    /// `secret-tool`.
+    Keychain {
+        /// Service the password is stored for.
+        service: String,
+
+        /// Account the password is stored for.
+        account: String,
+    },
+
+    /// The output of a command, without trailing newlines.
+    ///
+    /// The first element is the program to run and the remaining ones
+    /// are its arguments.
+    Command(Vec<String>),
+}
+
+impl Secret {
+    /// Describe where the secret is obtained from.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Env(name) => format!("environment variable {}", name),
+            Self::File(path) => format!("file {}", path.display()),
+            Self::Keychain { service, account } => {
+                format!("keychain item {} of {}", service, account)
+            }
+            Self::Command(args) => format!("command {}", args.join(" ")),
+        }
+    }
+
+    /// Obtain the value of the secret.
+    ///
+    /// Errors describe the source of the secret but never contain its value
+    /// or output of commands reading it.
+    pub fn resolve(&self) -> Result<String> {
+        let value = match self {
+            Self::Env(name) => {
+                std::env::var(name).map_err(|_| anyhow!("{} is not set", self.describe()))?
+            }
+            Self::File(path) => std::fs::read_to_string(path)
+                .with_context(|| format!("reading {}", self.describe()))?
+                .trim_end_matches(&['\r', '\n'][..])
+                .to_string(),
+            Self::Keychain { service, account } => {
+                let expression = if cfg!(target_os = "macos") {
+                    cmd!(
+                        "security",
+                        "find-generic-password",
+                        "-s",
+                        service,
+                        "-a",
+                        account,
+                        "-w"
+                    )
+                } else if cfg!(target_os = "windows") {
+                    return Err(anyhow!(
+                        "reading {} is not supported on Windows; use a command instead",
+                        self.describe()
+                    ));
+                } else {
+                    cmd!(
+                        "secret-tool",
+                        "lookup",
+                        "service",
+                        service,
+                        "account",
+                        account
+                    )
+                };
+
+                expression
+                    .stderr_null()
+                    .read()
+                    .map_err(|_| anyhow!("unable to read {}", self.describe()))?
+            }
+            Self::Command(args) => {
+                let (program, args) = args
+                    .split_first()
+                    .ok_or_else(|| anyhow!("secret command is empty"))?;
+
+                cmd(program, args)
+                    .read()
+                    .map_err(|_| anyhow!("unable to read secret from {}", self.describe()))?
+            }
+        };
+
+        if value.is_empty() {
+            return Err(anyhow!("{} is empty", self.describe()));
+        }
+
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("tugger-test")?;
+        let path = temp_dir.path().join("token");
+        std::fs::write(&path, "hunter2\n")?;
+
+        assert_eq!(Secret::File(path.clone()).resolve()?, "hunter2");
+
+        std::fs::write(&path, "")?;
+        let err = Secret::File(path).resolve().unwrap_err();
+        assert!(err.to_string().ends_with("is empty"));
+
+        let secret = Secret::Env("TUGGER_TEST_SECRET_UNSET".to_string());
+        assert_eq!(
+            secret.resolve().unwrap_err().to_string(),
+            "environment variable TUGGER_TEST_SECRET_UNSET is not set"
+        );
+
+        assert!(Secret::Command(vec![]).resolve().is_err());
+
+        Ok(())
+    }
+}
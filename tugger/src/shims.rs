@@ -0,0 +1,498 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Generate shims for invoking commands of an installed executable by name.
+
+An executable providing several commands dispatches on the name it is
+invoked as or on its first argument. Shims are small launchers installed
+next to the executable that run it with a command as the first argument.
+Installer metadata makes the commands available on `PATH` after
+installation: a WiX fragment adding the install directory to `PATH` on
+Windows and scripts maintaining wrapper scripts in e.g. `/usr/local/bin`
+for macOS and Linux packages.
+
+Commands are never symlinks to the executable: an embedded interpreter
+locates its resources relative to the path the executable is run from,
+which for a symlink is the directory of the link.
+*/
+
+use {
+    crate::file_resource::{FileContent, FileManifest},
+    anyhow::{anyhow, Result},
+    std::path::Path,
+    uuid::Uuid,
+};
+
+/// The platform to generate shims for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ShimPlatform {
+    /// `.cmd` shims and a WiX fragment.
+    Windows,
+
+    /// Shell script shims and package scripts maintaining symlinks.
+    Unix,
+}
+
+impl ShimPlatform {
+    /// Resolve the platform for a target triple.
+    pub fn from_target_triple(target_triple: &str) -> Self {
+        if target_triple.contains("-windows-") {
+            Self::Windows
+        } else {
+            Self::Unix
+        }
+    }
+}
+
+/// Whether a string can be used in generated scripts without quoting.
+fn is_safe_name(value: &str) -> bool {
+    !value.is_empty()
+        && !value.starts_with('-')
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+/// Quote a value for use in shell scripts.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Obtain the manifest content for generated text.
+fn text(data: String, executable: bool) -> FileContent {
+    FileContent {
+        data: data.into_bytes(),
+        executable,
+    }
+}
+
+/// Shims for commands provided by an executable.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConsoleScriptShims {
+    /// Filename of the executable, in the directory the shims are installed in.
+    pub executable: String,
+
+    /// Commands to generate shims for.
+    pub commands: Vec<String>,
+
+    /// Directory to create command symlinks in on Unix.
+    pub bin_dir: String,
+
+    /// Directory the executable is installed in on Unix.
+    ///
+    /// If not set, package scripts use their second argument, which is the
+    /// install location for scripts run by macOS installer packages.
+    pub install_dir: Option<String>,
+
+    /// Id of the WiX directory the executable is installed in.
+    pub wix_directory_id: String,
+}
+
+impl ConsoleScriptShims {
+    /// Create an instance for commands of an executable with default settings.
+    ///
+    /// Command names are used verbatim in generated scripts, so they are
+    /// restricted to characters not needing quoting.
+    pub fn new(executable: &str, commands: Vec<String>) -> Result<Self> {
+        if !is_safe_name(executable) {
+            return Err(anyhow!("invalid executable filename: {}", executable));
+        }
+
+        if commands.is_empty() {
+            return Err(anyhow!("at least one command is required"));
+        }
+
+        for command in &commands {
+            if !is_safe_name(command) {
+                return Err(anyhow!(
+                    "invalid command name: {}; names can only contain letters, digits, '-', '_' and '.'",
+                    command
+                ));
+            }
+        }
+
+        Ok(Self {
+            executable: executable.to_string(),
+            commands,
+            bin_dir: "/usr/local/bin".to_string(),
+            install_dir: None,
+            wix_directory_id: "APPLICATIONFOLDER".to_string(),
+        })
+    }
+
+    /// Whether the executable itself is invoked as a command.
+    ///
+    /// No shim is needed for such a command.
+    fn is_executable_command(&self, command: &str) -> bool {
+        self.executable
+            .strip_suffix(".exe")
+            .unwrap_or(&self.executable)
+            == command
+    }
+
+    /// Obtain the content of the `.cmd` shim for a command.
+    pub fn windows_shim(&self, command: &str) -> String {
+        format!(
+            "@echo off\r\n\
+             \"%~dp0{}\" {} %*\r\n\
+             exit /b %ERRORLEVEL%\r\n",
+            self.executable, command
+        )
+    }
+
+    /// Obtain the content of the shell script shim for a command.
+    ///
+    /// Symlinks to the shim are resolved so the executable is run from its
+    /// install directory.
+    pub fn unix_shim(&self, command: &str) -> String {
+        format!(
+            "#!/bin/sh\n\
+             self=\"$0\"\n\
+             while [ -L \"$self\" ]; do\n\
+             \x20   link=\"$(readlink \"$self\")\"\n\
+             \x20   case \"$link\" in\n\
+             \x20       /*) self=\"$link\" ;;\n\
+             \x20       *) self=\"$(dirname \"$self\")/$link\" ;;\n\
+             \x20   esac\n\
+             done\n\
+             exec \"$(dirname \"$self\")/{}\" {} \"$@\"\n",
+            self.executable, command
+        )
+    }
+
+    /// Obtain a WiX fragment adding the install directory to `PATH`.
+    ///
+    /// The fragment defines the `ConsoleScriptsPath` component group, to
+    /// be referenced from a feature of the installer.
+    pub fn wix_fragment(&self) -> String {
+        let guid = Uuid::new_v5(
+            &Uuid::NAMESPACE_DNS,
+            format!("tugger.shims.{}.{}", self.executable, self.wix_directory_id).as_bytes(),
+        );
+
+        format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <Wix xmlns=\"http://schemas.microsoft.com/wix/2006/wi\">\n\
+             \x20   <Fragment>\n\
+             \x20       <DirectoryRef Id=\"{dir}\">\n\
+             \x20           <Component Id=\"ConsoleScriptsPath\" Guid=\"{guid}\" KeyPath=\"yes\">\n\
+             \x20               <Environment Id=\"ConsoleScriptsPath\" Name=\"PATH\" Value=\"[{dir}]\" Permanent=\"no\" Part=\"last\" Action=\"set\" System=\"yes\"/>\n\
+             \x20           </Component>\n\
+             \x20       </DirectoryRef>\n\
+             \x20       <ComponentGroup Id=\"ConsoleScriptsPath\">\n\
+             \x20           <ComponentRef Id=\"ConsoleScriptsPath\"/>\n\
+             \x20       </ComponentGroup>\n\
+             \x20   </Fragment>\n\
+             </Wix>\n",
+            dir = self.wix_directory_id,
+            guid = guid.to_string().to_uppercase(),
+        )
+    }
+
+    /// Shell script lines defining `exe` as the single quoted absolute path
+    /// of the executable.
+    fn script_preamble(&self) -> String {
+        let install_dir = match &self.install_dir {
+            Some(dir) => shell_quote(dir),
+            None => "\"$2\"".to_string(),
+        };
+
+        format!(
+            "install_dir={}\n\
+             exe=\"'$(printf '%s\\n' \"$install_dir/{}\" | sed \"s/'/'\\\\\\\\''/g\")'\"\n",
+            install_dir, self.executable
+        )
+    }
+
+    /// The arguments a wrapper script passes to the executable for a command.
+    fn wrapper_args(&self, command: &str) -> String {
+        if self.is_executable_command(command) {
+            "\\\"\\$@\\\"".to_string()
+        } else {
+            format!("{} \\\"\\$@\\\"", command)
+        }
+    }
+
+    /// Obtain a script creating a wrapper script for each command.
+    ///
+    /// Wrappers exec the executable by its absolute path. Existing files
+    /// are removed first so a symlink is never written through.
+    pub fn postinstall_script(&self) -> String {
+        let bin_dir = shell_quote(&self.bin_dir);
+
+        let mut res = format!(
+            "#!/bin/sh\n\
+             set -e\n\
+             {}\
+             mkdir -p {}\n",
+            self.script_preamble(),
+            bin_dir
+        );
+
+        for command in &self.commands {
+            res.push_str(&format!(
+                "rm -f {bin}/{cmd}\n\
+                 printf \"#!/bin/sh\\nexec %s {args}\\n\" \"$exe\" > {bin}/{cmd}\n\
+                 chmod 755 {bin}/{cmd}\n",
+                bin = bin_dir,
+                cmd = command,
+                args = self.wrapper_args(command),
+            ));
+        }
+
+        res
+    }
+
+    /// Obtain a script removing wrappers created by `postinstall_script()`.
+    ///
+    /// Only wrappers still running the executable are removed.
+    pub fn uninstall_script(&self) -> String {
+        let bin_dir = shell_quote(&self.bin_dir);
+
+        let mut res = format!("#!/bin/sh\n{}", self.script_preamble());
+
+        for command in &self.commands {
+            res.push_str(&format!(
+                "if grep -qF \"exec $exe \" {bin}/{cmd} 2>/dev/null; then rm -f {bin}/{cmd}; fi\n",
+                bin = bin_dir,
+                cmd = command,
+            ));
+        }
+
+        res
+    }
+
+    /// Add shims to a manifest.
+    ///
+    /// Shims are installed in `prefix`, which should be the directory
+    /// holding the executable.
+    pub fn add_shims(
+        &self,
+        manifest: &mut FileManifest,
+        platform: ShimPlatform,
+        prefix: &Path,
+    ) -> Result<()> {
+        for command in &self.commands {
+            if self.is_executable_command(command) {
+                continue;
+            }
+
+            match platform {
+                ShimPlatform::Windows => manifest.add_file(
+                    prefix.join(format!("{}.cmd", command)),
+                    &text(self.windows_shim(command), false),
+                )?,
+                ShimPlatform::Unix => {
+                    manifest.add_file(prefix.join(command), &text(self.unix_shim(command), true))?
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Add shims and installer metadata to a manifest.
+    ///
+    /// Shims are added with `add_shims()`. Installer metadata is installed
+    /// in `metadata_prefix`: `console-scripts.wxs` on Windows and
+    /// `scripts/postinstall` and `scripts/uninstall` on Unix.
+    pub fn add_to_manifest(
+        &self,
+        manifest: &mut FileManifest,
+        platform: ShimPlatform,
+        prefix: &Path,
+        metadata_prefix: &Path,
+    ) -> Result<()> {
+        self.add_shims(manifest, platform, prefix)?;
+
+        match platform {
+            ShimPlatform::Windows => {
+                manifest.add_file(
+                    metadata_prefix.join("console-scripts.wxs"),
+                    &text(self.wix_fragment(), false),
+                )?;
+            }
+            ShimPlatform::Unix => {
+                let scripts = metadata_prefix.join("scripts");
+                manifest.add_file(
+                    scripts.join("postinstall"),
+                    &text(self.postinstall_script(), true),
+                )?;
+                manifest.add_file(
+                    scripts.join("uninstall"),
+                    &text(self.uninstall_script(), true),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::path::PathBuf};
+
+    #[test]
+    fn test_shims() -> Result<()> {
+        assert!(ConsoleScriptShims::new("myapp", vec![]).is_err());
+        assert!(ConsoleScriptShims::new("myapp", vec!["a b".to_string()]).is_err());
+        assert!(ConsoleScriptShims::new("myapp", vec!["--help".to_string()]).is_err());
+        assert!(ConsoleScriptShims::new("my app", vec!["tool".to_string()]).is_err());
+
+        let mut shims =
+            ConsoleScriptShims::new("myapp", vec!["tool-a".to_string(), "tool-b".to_string()])?;
+
+        assert!(shims
+            .unix_shim("tool-a")
+            .ends_with("exec \"$(dirname \"$self\")/myapp\" tool-a \"$@\"\n"));
+
+        let script = shims.postinstall_script();
+        assert!(script.starts_with("#!/bin/sh\nset -e\ninstall_dir=\"$2\"\n"));
+        assert!(script.contains(
+            "printf \"#!/bin/sh\\nexec %s tool-b \\\"\\$@\\\"\\n\" \"$exe\" > '/usr/local/bin'/tool-b\n"
+        ));
+        assert!(!script.contains("ln -s"));
+
+        shims.install_dir = Some("/opt/my app".to_string());
+        assert!(shims
+            .uninstall_script()
+            .starts_with("#!/bin/sh\ninstall_dir='/opt/my app'\n"));
+
+        let shims = ConsoleScriptShims::new("myapp.exe", vec!["tool-a".to_string()])?;
+        assert_eq!(
+            shims.windows_shim("tool-a"),
+            "@echo off\r\n\"%~dp0myapp.exe\" tool-a %*\r\nexit /b %ERRORLEVEL%\r\n"
+        );
+        assert!(shims
+            .wix_fragment()
+            .contains("<DirectoryRef Id=\"APPLICATIONFOLDER\">"));
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unix_scripts() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("tugger-test")?;
+        let install_dir = temp_dir.path().join("it's installed");
+        let bin_dir = temp_dir.path().join("bin");
+
+        let mut shims =
+            ConsoleScriptShims::new("myapp", vec!["myapp".to_string(), "tool".to_string()])?;
+        shims.bin_dir = bin_dir.display().to_string();
+
+        let mut manifest = FileManifest::default();
+        manifest.add_file(
+            Path::new("myapp"),
+            &text("#!/bin/sh\necho \"$0\" \"$@\"\n".to_string(), true),
+        )?;
+        shims.add_to_manifest(
+            &mut manifest,
+            ShimPlatform::Unix,
+            Path::new(""),
+            Path::new("installer"),
+        )?;
+        manifest.write_to_path(&install_dir)?;
+
+        // A stale symlink is replaced rather than written through.
+        std::fs::create_dir(&bin_dir)?;
+        std::os::unix::fs::symlink(install_dir.join("tool"), bin_dir.join("tool"))?;
+
+        let run = |program: &Path, args: &[&Path]| -> Result<String> {
+            let output = std::process::Command::new(program).args(args).output()?;
+            assert!(output.status.success());
+            Ok(String::from_utf8(output.stdout)?)
+        };
+
+        let scripts = install_dir.join("installer").join("scripts");
+        run(
+            &scripts.join("postinstall"),
+            &[Path::new("pkg"), &install_dir],
+        )?;
+
+        assert_eq!(
+            run(&bin_dir.join("tool"), &[Path::new("arg")])?,
+            format!("{} tool arg\n", install_dir.join("myapp").display())
+        );
+        assert_eq!(
+            run(&bin_dir.join("myapp"), &[])?,
+            format!("{}\n", install_dir.join("myapp").display())
+        );
+        assert!(std::fs::read_to_string(install_dir.join("tool"))?.starts_with("#!/bin/sh\nself="));
+
+        // Shims run the executable from its install directory when symlinked.
+        let link = temp_dir.path().join("link");
+        std::os::unix::fs::symlink(install_dir.join("tool"), &link)?;
+        assert_eq!(
+            run(&link, &[])?,
+            format!("{} tool\n", install_dir.join("myapp").display())
+        );
+
+        run(
+            &scripts.join("uninstall"),
+            &[Path::new("pkg"), &install_dir],
+        )?;
+        assert!(!bin_dir.join("tool").exists());
+        assert!(!bin_dir.join("myapp").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_to_manifest() -> Result<()> {
+        let shims =
+            ConsoleScriptShims::new("myapp", vec!["myapp".to_string(), "tool".to_string()])?;
+
+        let mut manifest = FileManifest::default();
+        shims.add_to_manifest(
+            &mut manifest,
+            ShimPlatform::Unix,
+            Path::new(""),
+            Path::new("installer"),
+        )?;
+
+        let paths = manifest
+            .entries()
+            .map(|(path, content)| (path.to_path_buf(), content.executable))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                (PathBuf::from("installer/scripts/postinstall"), true),
+                (PathBuf::from("installer/scripts/uninstall"), true),
+                (PathBuf::from("tool"), true),
+            ]
+        );
+
+        let mut manifest = FileManifest::default();
+        shims.add_to_manifest(
+            &mut manifest,
+            ShimPlatform::Windows,
+            Path::new("bin"),
+            Path::new("installer"),
+        )?;
+        assert_eq!(
+            manifest
+                .entries()
+                .map(|(path, _)| path.to_path_buf())
+                .collect::<Vec<_>>(),
+            vec![
+                PathBuf::from("bin/tool.cmd"),
+                PathBuf::from("installer/console-scripts.wxs"),
+            ]
+        );
+
+        assert_eq!(
+            ShimPlatform::from_target_triple("x86_64-pc-windows-msvc"),
+            ShimPlatform::Windows
+        );
+        assert_eq!(
+            ShimPlatform::from_target_triple("x86_64-apple-darwin"),
+            ShimPlatform::Unix
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    crate::code_signing::{find_code_signing_certificates, CodeSigningCertificate},
+    starlark::{
+        values::{
+            error::{RuntimeError, UnsupportedOperation, ValueError},
+            none::NoneType,
+            {Immutable, TypedValue, Value, ValueResult},
+        },
+        {
+            starlark_fun, starlark_module, starlark_signature, starlark_signature_extraction,
+            starlark_signatures,
+        },
+    },
+};
+
+#[derive(Clone, Debug)]
+pub struct CodeSigningCertificateValue {
+    pub inner: CodeSigningCertificate,
+}
+
+impl TypedValue for CodeSigningCertificateValue {
+    type Holder = Immutable<CodeSigningCertificateValue>;
+    const TYPE: &'static str = "CodeSigningCertificate";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+
+    fn to_str(&self) -> String {
+        format!(
+            "{}<subject={}, thumbprint={}>",
+            Self::TYPE,
+            self.inner.subject,
+            self.inner.thumbprint
+        )
+    }
+
+    fn to_repr(&self) -> String {
+        self.to_str()
+    }
+
+    fn get_attr(&self, attribute: &str) -> ValueResult {
+        Ok(match attribute {
+            "not_after" => match &self.inner.not_after {
+                Some(value) => Value::from(value.as_str()),
+                None => Value::from(NoneType::None),
+            },
+            "store" => Value::from(self.inner.store.as_str()),
+            "subject" => Value::from(self.inner.subject.as_str()),
+            "thumbprint" => Value::from(self.inner.thumbprint.as_str()),
+            attr => {
+                return Err(ValueError::OperationNotSupported {
+                    op: UnsupportedOperation::GetAttr(attr.to_string()),
+                    left: Self::TYPE.to_string(),
+                    right: None,
+                })
+            }
+        })
+    }
+
+    fn has_attr(&self, attribute: &str) -> Result<bool, ValueError> {
+        Ok(matches!(
+            attribute,
+            "not_after" | "store" | "subject" | "thumbprint"
+        ))
+    }
+}
+
+/// code_signing_certificates()
+fn starlark_code_signing_certificates() -> ValueResult {
+    let certificates = find_code_signing_certificates().map_err(|e| {
+        ValueError::from(RuntimeError {
+            code: "PYOXIDIZER_BUILD",
+            message: format!("{:#}", e),
+            label: "code_signing_certificates()".to_string(),
+        })
+    })?;
+
+    Ok(Value::from(
+        certificates
+            .into_iter()
+            .map(|inner| Value::new(CodeSigningCertificateValue { inner }))
+            .collect::<Vec<_>>(),
+    ))
+}
+
+starlark_module! { code_signing_module =>
+    code_signing_certificates() {
+        starlark_code_signing_certificates()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::starlark::testutil::*, anyhow::Result};
+
+    #[test]
+    fn test_certificate_attrs() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+        env.env
+            .set(
+                "cert",
+                Value::new(CodeSigningCertificateValue {
+                    inner: CodeSigningCertificate {
+                        store: "keychain".to_string(),
+                        subject: "Developer ID Installer: Example (ABCDE12345)".to_string(),
+                        thumbprint: "0123456789ABCDEF0123456789ABCDEF01234567".to_string(),
+                        not_after: None,
+                    },
+                }),
+            )
+            .unwrap();
+
+        assert_eq!(
+            env.eval("cert.subject")?.to_str(),
+            "Developer ID Installer: Example (ABCDE12345)"
+        );
+        assert_eq!(env.eval("cert.store")?.to_str(), "keychain");
+        assert_eq!(env.eval("cert.not_after")?.get_type(), "NoneType");
+        assert!(env.eval("cert.subject = 'foo'").is_err());
+
+        assert_eq!(
+            env.eval("type(code_signing_certificates())")?.to_str(),
+            "list"
+        );
+
+        Ok(())
+    }
+}
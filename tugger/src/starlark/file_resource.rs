@@ -4,8 +4,11 @@
 
 use {
     crate::{
-        file_resource::{FileContent, FileManifest},
+        checksum::{add_checksums_file, ChecksumAlgorithm},
+        file_resource::{FileContent, FileManifest, UnixFileAttributes},
         glob::evaluate_glob,
+        systemd::SystemdServiceUnit,
+        tarball::write_tarball_from_manifest,
     },
     anyhow::Result,
     slog::warn,
@@ -25,7 +28,12 @@ use {
         get_context_value, optional_list_arg, optional_str_arg, required_list_arg, BuildContext,
         BuildTarget, EnvironmentContext, ResolvedTarget, RunMode,
     },
-    std::{collections::HashSet, convert::TryFrom, path::PathBuf},
+    std::{
+        collections::HashSet,
+        convert::TryFrom,
+        io::Write,
+        path::{Path, PathBuf},
+    },
 };
 
 // TODO merge this into `FileValue`?
@@ -117,7 +125,7 @@ impl FileManifestValue {
         self.manifest.add_manifest(&other.manifest).map_err(|e| {
             ValueError::from(RuntimeError {
                 code: "PYOXIDIZER_BUILD",
-                message: e.to_string(),
+                message: format!("{:#}", e),
                 label: "add_manifest()".to_string(),
             })
         })?;
@@ -125,6 +133,103 @@ impl FileManifestValue {
         Ok(Value::new(NoneType::None))
     }
 
+    /// FileManifest.add_systemd_unit(name, exec_start, description=None, user=None, working_directory=None, restart="on-failure", wanted_by="multi-user.target", prefix="lib/systemd/system")
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_systemd_unit(
+        &mut self,
+        name: String,
+        exec_start: String,
+        description: &Value,
+        user: &Value,
+        working_directory: &Value,
+        restart: String,
+        wanted_by: String,
+        prefix: String,
+    ) -> ValueResult {
+        let description = optional_str_arg("description", description)?;
+        let user = optional_str_arg("user", user)?;
+        let working_directory = optional_str_arg("working_directory", working_directory)?;
+
+        let mut unit =
+            SystemdServiceUnit::new(description.as_deref().unwrap_or(&name), &exec_start);
+        unit.user = user;
+        unit.working_directory = working_directory;
+        unit.restart = restart;
+        unit.wanted_by = wanted_by;
+
+        let path = PathBuf::from(prefix).join(format!("{}.service", name));
+
+        unit.to_unit_file()
+            .and_then(|data| {
+                self.manifest.add_file(
+                    &path,
+                    &FileContent {
+                        data: data.into_bytes(),
+                        executable: false,
+                    },
+                )
+            })
+            .map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: format!("{:#}", e),
+                    label: "add_systemd_unit()".to_string(),
+                })
+            })?;
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// FileManifest.add_checksums(algorithm="sha256", path=None)
+    pub fn add_checksums(&mut self, algorithm: String, path: &Value) -> ValueResult {
+        let algorithm = ChecksumAlgorithm::try_from(algorithm.as_str()).map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_BUILD",
+                message: e,
+                label: "add_checksums()".to_string(),
+            })
+        })?;
+        let path = optional_str_arg("path", path)?
+            .unwrap_or_else(|| format!("{}SUMS", algorithm.to_string().to_uppercase()));
+
+        add_checksums_file(&mut self.manifest, algorithm, &PathBuf::from(path)).map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_BUILD",
+                message: format!("{:#}", e),
+                label: "add_checksums()".to_string(),
+            })
+        })?;
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// FileManifest.set_unix_attributes(path, owner=None, group=None, capabilities=None)
+    pub fn set_unix_attributes(
+        &mut self,
+        path: String,
+        owner: &Value,
+        group: &Value,
+        capabilities: &Value,
+    ) -> ValueResult {
+        let attributes = UnixFileAttributes {
+            owner: optional_str_arg("owner", owner)?,
+            group: optional_str_arg("group", group)?,
+            capabilities: optional_str_arg("capabilities", capabilities)?,
+        };
+
+        self.manifest
+            .set_unix_attributes(&path, attributes)
+            .map_err(|e| {
+                ValueError::from(RuntimeError {
+                    code: "PYOXIDIZER_BUILD",
+                    message: e.to_string(),
+                    label: "set_unix_attributes()".to_string(),
+                })
+            })?;
+
+        Ok(Value::new(NoneType::None))
+    }
+
     /// FileManifest.install(path, replace=true)
     pub fn install(&self, type_values: &TypeValues, path: String, replace: bool) -> ValueResult {
         let raw_context = get_context_value(type_values)?;
@@ -149,6 +254,45 @@ impl FileManifestValue {
 
         Ok(Value::new(NoneType::None))
     }
+
+    /// FileManifest.write_tarball(path, prefix=None)
+    pub fn write_tarball(
+        &self,
+        type_values: &TypeValues,
+        path: String,
+        prefix: &Value,
+    ) -> ValueResult {
+        let prefix = optional_str_arg("prefix", prefix)?;
+
+        let raw_context = get_context_value(type_values)?;
+        let context = raw_context
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let dest_path = context.build_path().join(path);
+
+        let write = || -> anyhow::Result<()> {
+            if let Some(parent) = dest_path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            let mut fh = std::io::BufWriter::new(std::fs::File::create(&dest_path)?);
+            write_tarball_from_manifest(&mut fh, &self.manifest, prefix.as_deref().map(Path::new))?;
+            fh.flush()?;
+
+            Ok(())
+        };
+
+        write().map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_INSTALL",
+                message: format!("error writing tarball: {}", e),
+                label: "FileManifest.write_tarball()".to_string(),
+            })
+        })?;
+
+        Ok(Value::new(NoneType::None))
+    }
 }
 
 /// glob(include, exclude=None, relative_to=None)
@@ -185,7 +329,7 @@ fn starlark_glob(
         for p in evaluate_glob(context.cwd(), &v).map_err(|e| {
             ValueError::from(RuntimeError {
                 code: "PYOXIDIZER_BUILD",
-                message: e.to_string(),
+                message: format!("{:#}", e),
                 label: "glob()".to_string(),
             })
         })? {
@@ -198,7 +342,7 @@ fn starlark_glob(
         for p in evaluate_glob(context.cwd(), &v).map_err(|e| {
             ValueError::from(RuntimeError {
                 code: "PYOXIDIZER_BUILD",
-                message: e.to_string(),
+                message: format!("{:#}", e),
                 label: "glob()".to_string(),
             })
         })? {
@@ -212,7 +356,7 @@ fn starlark_glob(
         let content = FileContent::try_from(path.as_path()).map_err(|e| {
             ValueError::from(RuntimeError {
                 code: "PYOXIDIZER_BUILD",
-                message: e.to_string(),
+                message: format!("{:#}", e),
                 label: "glob()".to_string(),
             })
         })?;
@@ -222,7 +366,7 @@ fn starlark_glob(
                 .map_err(|e| {
                     ValueError::from(RuntimeError {
                         code: "PYOXIDIZER_BUILD",
-                        message: e.to_string(),
+                        message: format!("{:#}", e),
                         label: "glob()".to_string(),
                     })
                 })?
@@ -234,7 +378,7 @@ fn starlark_glob(
         manifest.add_file(&path, &content).map_err(|e| {
             ValueError::from(RuntimeError {
                 code: "PYOXIDIZER_BUILD",
-                message: e.to_string(),
+                message: format!("{:#}", e),
                 label: "glob()".to_string(),
             })
         })?;
@@ -263,12 +407,69 @@ starlark_module! { file_resource_module =>
         }
     }
 
+    FileManifest.add_systemd_unit(
+        this,
+        name: String,
+        exec_start: String,
+        description = NoneType::None,
+        user = NoneType::None,
+        working_directory = NoneType::None,
+        restart: String = "on-failure".to_string(),
+        wanted_by: String = "multi-user.target".to_string(),
+        prefix: String = "lib/systemd/system".to_string()
+    ) {
+        match this.clone().downcast_mut::<FileManifestValue>()? {
+            Some(mut manifest) => manifest.add_systemd_unit(
+                name,
+                exec_start,
+                &description,
+                &user,
+                &working_directory,
+                restart,
+                wanted_by,
+                prefix,
+            ),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    FileManifest.add_checksums(
+        this,
+        algorithm: String = "sha256".to_string(),
+        path = NoneType::None
+    ) {
+        match this.clone().downcast_mut::<FileManifestValue>()? {
+            Some(mut manifest) => manifest.add_checksums(algorithm, &path),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    FileManifest.set_unix_attributes(
+        this,
+        path: String,
+        owner = NoneType::None,
+        group = NoneType::None,
+        capabilities = NoneType::None
+    ) {
+        match this.clone().downcast_mut::<FileManifestValue>()? {
+            Some(mut manifest) => manifest.set_unix_attributes(path, &owner, &group, &capabilities),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
     FileManifest.install(env env, this, path: String, replace: bool = true) {
         match this.clone().downcast_ref::<FileManifestValue>() {
             Some(manifest) => manifest.install(&env, path, replace),
             None => Err(ValueError::IncorrectParameterType),
         }
     }
+
+    FileManifest.write_tarball(env env, this, path: String, prefix = NoneType::None) {
+        match this.clone().downcast_ref::<FileManifestValue>() {
+            Some(manifest) => manifest.write_tarball(&env, path, &prefix),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -294,4 +495,127 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_add_systemd_unit() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+        env.eval("m = FileManifest()")?;
+        env.eval("m.add_systemd_unit('myapp', '/opt/myapp/myapp', user = 'myapp')")?;
+
+        {
+            let m = env.eval("m")?;
+            let m = m.downcast_ref::<FileManifestValue>().unwrap();
+
+            let (path, content) = m.manifest.entries().next().unwrap();
+            assert_eq!(path, &PathBuf::from("lib/systemd/system/myapp.service"));
+            let unit = String::from_utf8(content.data.clone())?;
+            assert!(unit.as_str().contains("Description=myapp\n"));
+            assert!(unit.as_str().contains("ExecStart=/opt/myapp/myapp\n"));
+            assert!(unit.as_str().contains("User=myapp\n"));
+            assert!(!content.executable);
+        }
+
+        assert!(env
+            .eval("m.add_systemd_unit('bad', '/bin/true', description = 'a\\nb')")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_checksums() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+        env.eval("m = FileManifest()")?;
+        env.eval("m.add_systemd_unit('myapp', '/opt/myapp/myapp')")?;
+        env.eval("m.add_checksums()")?;
+        env.eval("m.add_checksums(algorithm = 'sha512', path = 'lib/SUMS')")?;
+
+        {
+            let m = env.eval("m")?;
+            let m = m.downcast_ref::<FileManifestValue>().unwrap();
+
+            let paths = m.manifest.entries().map(|(p, _)| p).collect::<Vec<_>>();
+            assert_eq!(
+                paths,
+                vec![
+                    &PathBuf::from("SHA256SUMS"),
+                    &PathBuf::from("lib/SUMS"),
+                    &PathBuf::from("lib/systemd/system/myapp.service")
+                ]
+            );
+        }
+
+        assert!(env.eval("m.add_checksums(algorithm = 'md5')").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_unix_attributes() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+        env.eval("m = FileManifest()")?;
+        env.eval("m.add_systemd_unit('myapp', '/opt/myapp/myapp')")?;
+        env.eval("m.set_unix_attributes('lib/systemd/system/myapp.service', owner = 'myapp', capabilities = 'cap_net_bind_service=ep')")?;
+
+        {
+            let m = env.eval("m")?;
+            let m = m.downcast_ref::<FileManifestValue>().unwrap();
+
+            assert_eq!(
+                m.manifest
+                    .unix_attributes(Path::new("lib/systemd/system/myapp.service")),
+                Some(&UnixFileAttributes {
+                    owner: Some("myapp".to_string()),
+                    group: None,
+                    capabilities: Some("cap_net_bind_service=ep".to_string()),
+                })
+            );
+        }
+
+        assert!(env
+            .eval("m.set_unix_attributes('missing', owner = 'myapp')")
+            .is_err());
+        assert!(env
+            .eval("m.set_unix_attributes('lib/systemd/system/myapp.service', group = 'Bad Group')")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_tarball() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("tugger-test")?;
+        let tar_path = temp_dir.path().join("dist").join("myapp.tar");
+
+        let mut env = StarlarkEnvironment::new()?;
+        env.eval("m = FileManifest()")?;
+        env.eval("m.add_systemd_unit('myapp', '/opt/myapp/myapp')")?;
+        env.eval("m.set_unix_attributes('lib/systemd/system/myapp.service', owner = 'myapp')")?;
+        env.eval(&format!(
+            "m.write_tarball({:?}, prefix = 'usr')",
+            tar_path.display().to_string()
+        ))?;
+
+        let mut archive = tar::Archive::new(std::fs::File::open(&tar_path)?);
+        let owners = archive
+            .entries()?
+            .map(|entry| {
+                let entry = entry?;
+                Ok((
+                    entry.path()?.display().to_string(),
+                    entry.header().username()?.map(|x| x.to_string()),
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(
+            owners.last(),
+            Some(&(
+                "usr/lib/systemd/system/myapp.service".to_string(),
+                Some("myapp".to_string())
+            ))
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,250 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    crate::{
+        macos_pkg::{MacOsPkgBuilder, Notarization, PkgComponent},
+        starlark::{file_resource::FileManifestValue, secrets::SecretValue},
+    },
+    anyhow::Result,
+    starlark::{
+        values::{
+            error::{RuntimeError, ValueError},
+            none::NoneType,
+            {Mutable, TypedValue, Value, ValueResult},
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+    starlark_dialect_build_targets::{
+        optional_str_arg, optional_type_arg, BuildContext, BuildTarget, ResolvedTarget, RunMode,
+    },
+    std::path::PathBuf,
+};
+
+fn build_error(label: &str, message: String) -> ValueError {
+    ValueError::from(RuntimeError {
+        code: "PYOXIDIZER_BUILD",
+        message,
+        label: label.to_string(),
+    })
+}
+
+#[derive(Clone, Debug)]
+pub struct MacOsPkgBuilderValue {
+    pub builder: MacOsPkgBuilder,
+}
+
+impl MacOsPkgBuilderValue {
+    /// Filename of the installer produced by this builder.
+    pub fn filename(&self) -> String {
+        format!("{}-{}.pkg", self.builder.identifier, self.builder.version)
+    }
+}
+
+impl BuildTarget for MacOsPkgBuilderValue {
+    fn build(&mut self, context: &dyn BuildContext) -> Result<ResolvedTarget> {
+        let output_path = context.get_state_path("output_path")?;
+
+        self.builder.build_pkg(
+            context.logger(),
+            context.get_state_string("target_triple")?,
+            &output_path.join("pkg-staging"),
+            &output_path.join(self.filename()),
+        )?;
+
+        Ok(ResolvedTarget {
+            run_mode: RunMode::None,
+            output_path: output_path.to_path_buf(),
+        })
+    }
+}
+
+impl TypedValue for MacOsPkgBuilderValue {
+    type Holder = Mutable<MacOsPkgBuilderValue>;
+    const TYPE: &'static str = "MacOsPkgBuilder";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+}
+
+// Starlark functions.
+impl MacOsPkgBuilderValue {
+    /// MacOsPkgBuilder(title, identifier, version)
+    fn new_from_args(title: String, identifier: String, version: String) -> ValueResult {
+        let builder = MacOsPkgBuilder::new(&title, &identifier, &version)
+            .map_err(|e| build_error("MacOsPkgBuilder()", format!("{:#}", e)))?;
+
+        Ok(Value::new(MacOsPkgBuilderValue { builder }))
+    }
+
+    /// MacOsPkgBuilder.add_component(identifier, files, install_location, scripts=None, version=None)
+    pub fn add_component(
+        &mut self,
+        identifier: String,
+        files: FileManifestValue,
+        install_location: String,
+        scripts: &Value,
+        version: &Value,
+    ) -> ValueResult {
+        optional_type_arg("scripts", "FileManifest", scripts)?;
+        let version =
+            optional_str_arg("version", version)?.unwrap_or_else(|| self.builder.version.clone());
+
+        let mut component =
+            PkgComponent::new(&identifier, &version, &install_location, files.manifest)
+                .map_err(|e| build_error("add_component()", format!("{:#}", e)))?;
+
+        if let Some(scripts) = scripts.downcast_ref::<FileManifestValue>() {
+            component.scripts = scripts.manifest.clone();
+        }
+
+        self.builder
+            .add_component(component)
+            .map_err(|e| build_error("add_component()", format!("{:#}", e)))?;
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// MacOsPkgBuilder.sign(identity, keychain=None, keychain_password=None)
+    pub fn sign(
+        &mut self,
+        identity: String,
+        keychain: &Value,
+        keychain_password: &Value,
+    ) -> ValueResult {
+        let keychain = optional_str_arg("keychain", keychain)?;
+        optional_type_arg("keychain_password", "Secret", keychain_password)?;
+
+        self.builder.signing_identity = Some(identity);
+        self.builder.keychain = keychain.map(PathBuf::from);
+        self.builder.keychain_password = keychain_password
+            .downcast_ref::<SecretValue>()
+            .map(|secret| secret.inner.clone());
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// MacOsPkgBuilder.notarize(keychain_profile, keychain=None)
+    pub fn notarize(&mut self, keychain_profile: String, keychain: &Value) -> ValueResult {
+        let keychain = optional_str_arg("keychain", keychain)?;
+
+        self.builder.notarization = Some(Notarization {
+            keychain_profile,
+            keychain: keychain.map(PathBuf::from),
+        });
+
+        Ok(Value::new(NoneType::None))
+    }
+}
+
+starlark_module! { macos_pkg_module =>
+    #[allow(non_snake_case)]
+    MacOsPkgBuilder(title: String, identifier: String, version: String) {
+        MacOsPkgBuilderValue::new_from_args(title, identifier, version)
+    }
+
+    MacOsPkgBuilder.add_component(
+        this,
+        identifier: String,
+        files: FileManifestValue,
+        install_location: String,
+        scripts = NoneType::None,
+        version = NoneType::None
+    ) {
+        match this.clone().downcast_mut::<MacOsPkgBuilderValue>()? {
+            Some(mut builder) => builder.add_component(
+                identifier,
+                files,
+                install_location,
+                &scripts,
+                &version,
+            ),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    MacOsPkgBuilder.notarize(this, keychain_profile: String, keychain = NoneType::None) {
+        match this.clone().downcast_mut::<MacOsPkgBuilderValue>()? {
+            Some(mut builder) => builder.notarize(keychain_profile, &keychain),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    MacOsPkgBuilder.sign(
+        this,
+        identity: String,
+        keychain = NoneType::None,
+        keychain_password = NoneType::None
+    ) {
+        match this.clone().downcast_mut::<MacOsPkgBuilderValue>()? {
+            Some(mut builder) => builder.sign(identity, &keychain, &keychain_password),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::secrets::Secret, crate::starlark::testutil::*};
+
+    #[test]
+    fn test_add_component() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+        env.eval("pkg = MacOsPkgBuilder('My App', 'com.example.myapp', '1.0')")?;
+        env.eval("files = FileManifest()")?;
+        env.eval("pkg.add_component('com.example.myapp.cli', files, '/usr/local/myapp', scripts = FileManifest(), version = '1.1')")?;
+        env.eval("pkg.sign('Developer ID Installer: Example (ABCDE12345)')")?;
+
+        {
+            let pkg = env.eval("pkg")?;
+            let pkg = pkg.downcast_ref::<MacOsPkgBuilderValue>().unwrap();
+
+            assert_eq!(pkg.filename(), "com.example.myapp-1.0.pkg");
+            assert_eq!(pkg.builder.components().len(), 1);
+            assert_eq!(pkg.builder.components()[0].version, "1.1");
+            assert_eq!(
+                pkg.builder.signing_identity,
+                Some("Developer ID Installer: Example (ABCDE12345)".to_string())
+            );
+            assert_eq!(pkg.builder.keychain, None);
+            assert_eq!(pkg.builder.keychain_password, None);
+        }
+
+        env.eval("pkg.sign('Developer ID Installer: Example (ABCDE12345)', keychain = 'build.keychain', keychain_password = secret_from_env('KEYCHAIN_PASSWORD'))")?;
+        env.eval("pkg.notarize('myapp-notary', keychain = 'build.keychain')")?;
+
+        {
+            let pkg = env.eval("pkg")?;
+            let pkg = pkg.downcast_ref::<MacOsPkgBuilderValue>().unwrap();
+
+            assert_eq!(
+                pkg.builder.keychain_password,
+                Some(Secret::Env("KEYCHAIN_PASSWORD".to_string()))
+            );
+            assert_eq!(
+                pkg.builder.notarization,
+                Some(Notarization {
+                    keychain_profile: "myapp-notary".to_string(),
+                    keychain: Some(PathBuf::from("build.keychain")),
+                })
+            );
+        }
+
+        assert!(env
+            .eval("pkg.add_component('com.example.myapp.cli', files, '/usr/local/myapp')")
+            .is_err());
+        assert!(env
+            .eval("pkg.add_component('com.example.myapp.lib', files, 'lib')")
+            .is_err());
+        assert!(env
+            .eval("MacOsPkgBuilder('My App', 'myapp', '1.0')")
+            .is_err());
+
+        Ok(())
+    }
+}
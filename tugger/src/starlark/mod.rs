@@ -8,7 +8,11 @@ The `starlark` module and related sub-modules define the
 Tugger.
 */
 
+pub mod code_signing;
 pub mod file_resource;
+pub mod macos_pkg;
+pub mod package_managers;
+pub mod secrets;
 #[cfg(test)]
 mod testutil;
 
@@ -19,7 +23,11 @@ pub fn populate_environment(
     env: &mut Environment,
     type_values: &mut TypeValues,
 ) -> Result<(), EnvironmentError> {
+    code_signing::code_signing_module(env, type_values);
     file_resource::file_resource_module(env, type_values);
+    macos_pkg::macos_pkg_module(env, type_values);
+    package_managers::package_managers_module(env, type_values);
+    secrets::secrets_module(env, type_values);
 
     Ok(())
 }
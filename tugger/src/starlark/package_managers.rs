@@ -0,0 +1,356 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    crate::{
+        file_resource::FileManifest,
+        package_managers::{
+            ArtifactKind, PackageManagerManifests, PackageMetadata, PublishedArtifact,
+            WindowsArchitecture,
+        },
+    },
+    anyhow::Result,
+    slog::warn,
+    starlark::{
+        environment::TypeValues,
+        values::{
+            error::{RuntimeError, ValueError},
+            none::NoneType,
+            {Mutable, TypedValue, Value, ValueResult},
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+    starlark_dialect_build_targets::{
+        get_context_value, optional_list_arg, optional_str_arg, BuildContext, BuildTarget,
+        EnvironmentContext, ResolvedTarget, RunMode,
+    },
+    std::{
+        convert::TryFrom,
+        path::{Path, PathBuf},
+    },
+};
+
+fn build_error(label: &str, message: String) -> ValueError {
+    ValueError::from(RuntimeError {
+        code: "PYOXIDIZER_BUILD",
+        message,
+        label: label.to_string(),
+    })
+}
+
+/// An artifact to be hashed when manifests are built.
+#[derive(Clone, Debug)]
+pub struct PendingArtifact {
+    pub path: PathBuf,
+    pub url: String,
+}
+
+impl PendingArtifact {
+    fn new(
+        type_values: &TypeValues,
+        path: String,
+        url: String,
+        label: &str,
+    ) -> Result<Self, ValueError> {
+        let raw_context = get_context_value(type_values)?;
+        let context = raw_context
+            .downcast_ref::<EnvironmentContext>()
+            .ok_or(ValueError::IncorrectParameterType)?;
+
+        let path = PathBuf::from(path);
+        let path = if path.is_absolute() {
+            path
+        } else {
+            context.cwd().join(path)
+        };
+
+        // Validate the artifact type early. The artifact itself may not be
+        // built yet.
+        let filename = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        ArtifactKind::from_filename(filename)
+            .map_err(|e| build_error(label, format!("{:#}", e)))?;
+
+        Ok(Self { path, url })
+    }
+
+    fn resolve(&self) -> Result<PublishedArtifact> {
+        PublishedArtifact::from_path(&self.path, &self.url)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PackageManagerManifestsValue {
+    pub metadata: PackageMetadata,
+    pub windows: Option<(PendingArtifact, WindowsArchitecture)>,
+    pub macos: Option<PendingArtifact>,
+    pub pkg_ids: Vec<String>,
+    pub binaries: Option<Vec<String>>,
+}
+
+impl PackageManagerManifestsValue {
+    fn resolve_with<F>(&self, resolve_artifact: F) -> Result<PackageManagerManifests>
+    where
+        F: Fn(&PendingArtifact) -> Result<PublishedArtifact>,
+    {
+        let mut manifests = PackageManagerManifests::new(self.metadata.clone());
+
+        if let Some((artifact, architecture)) = &self.windows {
+            manifests.windows = Some((resolve_artifact(artifact)?, *architecture));
+        }
+        if let Some(artifact) = &self.macos {
+            manifests.macos = Some(resolve_artifact(artifact)?);
+        }
+        manifests.pkg_ids = self.pkg_ids.clone();
+        if let Some(binaries) = &self.binaries {
+            manifests.binaries = binaries.clone();
+        }
+
+        Ok(manifests)
+    }
+
+    /// Resolve manifests by hashing artifacts.
+    pub fn resolve(&self) -> Result<PackageManagerManifests> {
+        self.resolve_with(|artifact| artifact.resolve())
+    }
+
+    /// Paths of artifacts manifests are generated for.
+    pub fn artifact_paths(&self) -> Vec<&Path> {
+        self.windows
+            .iter()
+            .map(|(artifact, _)| artifact)
+            .chain(self.macos.iter())
+            .map(|artifact| artifact.path.as_path())
+            .collect()
+    }
+
+    /// Relative paths of manifests that would be written.
+    ///
+    /// Artifacts are not read, so they don't need to be built.
+    pub fn output_paths(&self) -> Result<Vec<PathBuf>> {
+        let mut manifest = FileManifest::default();
+        self.resolve_with(|artifact| {
+            PublishedArtifact::new(&artifact.path.to_string_lossy(), &artifact.url, b"")
+        })?
+        .add_to_manifest(&mut manifest)?;
+
+        Ok(manifest
+            .entries()
+            .map(|(path, _)| path.to_path_buf())
+            .collect())
+    }
+}
+
+impl BuildTarget for PackageManagerManifestsValue {
+    fn build(&mut self, context: &dyn BuildContext) -> Result<ResolvedTarget> {
+        let output_path = context.get_state_path("output_path")?;
+
+        let mut manifest = FileManifest::default();
+        self.resolve()?.add_to_manifest(&mut manifest)?;
+
+        warn!(
+            context.logger(),
+            "writing package manager manifests to {}",
+            output_path.display()
+        );
+        manifest.replace_path(output_path)?;
+
+        Ok(ResolvedTarget {
+            run_mode: RunMode::None,
+            output_path: output_path.to_path_buf(),
+        })
+    }
+}
+
+impl TypedValue for PackageManagerManifestsValue {
+    type Holder = Mutable<PackageManagerManifestsValue>;
+    const TYPE: &'static str = "PackageManagerManifests";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+}
+
+// Starlark functions.
+impl PackageManagerManifestsValue {
+    /// PackageManagerManifests(id, name, version, publisher, description, homepage=None, license=None)
+    fn new_from_args(
+        id: String,
+        name: String,
+        version: String,
+        publisher: String,
+        description: String,
+        homepage: &Value,
+        license: &Value,
+    ) -> ValueResult {
+        let mut metadata = PackageMetadata::new(&id, &name, &version, &publisher, &description)
+            .map_err(|e| build_error("PackageManagerManifests()", format!("{:#}", e)))?;
+        metadata.homepage = optional_str_arg("homepage", homepage)?;
+        metadata.license = optional_str_arg("license", license)?;
+
+        Ok(Value::new(PackageManagerManifestsValue {
+            metadata,
+            windows: None,
+            macos: None,
+            pkg_ids: vec![],
+            binaries: None,
+        }))
+    }
+
+    /// PackageManagerManifests.add_windows_artifact(path, url, architecture="x64")
+    pub fn add_windows_artifact(
+        &mut self,
+        type_values: &TypeValues,
+        path: String,
+        url: String,
+        architecture: String,
+    ) -> ValueResult {
+        let label = "add_windows_artifact()";
+
+        let architecture = WindowsArchitecture::try_from(architecture.as_str())
+            .map_err(|e| build_error(label, e))?;
+        let artifact = PendingArtifact::new(type_values, path, url, label)?;
+
+        self.windows = Some((artifact, architecture));
+
+        Ok(Value::new(NoneType::None))
+    }
+
+    /// PackageManagerManifests.add_macos_artifact(path, url, pkg_ids=None, binaries=None)
+    pub fn add_macos_artifact(
+        &mut self,
+        type_values: &TypeValues,
+        path: String,
+        url: String,
+        pkg_ids: &Value,
+        binaries: &Value,
+    ) -> ValueResult {
+        optional_list_arg("pkg_ids", "string", pkg_ids)?;
+        optional_list_arg("binaries", "string", binaries)?;
+
+        let artifact = PendingArtifact::new(type_values, path, url, "add_macos_artifact()")?;
+
+        self.macos = Some(artifact);
+        self.pkg_ids = match pkg_ids.get_type() {
+            "list" => pkg_ids.iter()?.iter().map(|x| x.to_string()).collect(),
+            _ => vec![],
+        };
+        self.binaries = match binaries.get_type() {
+            "list" => Some(binaries.iter()?.iter().map(|x| x.to_string()).collect()),
+            _ => None,
+        };
+
+        Ok(Value::new(NoneType::None))
+    }
+}
+
+starlark_module! { package_managers_module =>
+    #[allow(non_snake_case)]
+    PackageManagerManifests(
+        id: String,
+        name: String,
+        version: String,
+        publisher: String,
+        description: String,
+        homepage = NoneType::None,
+        license = NoneType::None
+    ) {
+        PackageManagerManifestsValue::new_from_args(
+            id,
+            name,
+            version,
+            publisher,
+            description,
+            &homepage,
+            &license,
+        )
+    }
+
+    PackageManagerManifests.add_windows_artifact(
+        env env,
+        this,
+        path: String,
+        url: String,
+        architecture: String = "x64".to_string()
+    ) {
+        match this.clone().downcast_mut::<PackageManagerManifestsValue>()? {
+            Some(mut manifests) => manifests.add_windows_artifact(&env, path, url, architecture),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+
+    PackageManagerManifests.add_macos_artifact(
+        env env,
+        this,
+        path: String,
+        url: String,
+        pkg_ids = NoneType::None,
+        binaries = NoneType::None
+    ) {
+        match this.clone().downcast_mut::<PackageManagerManifestsValue>()? {
+            Some(mut manifests) => manifests.add_macos_artifact(&env, path, url, &pkg_ids, &binaries),
+            None => Err(ValueError::IncorrectParameterType),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::starlark::testutil::*};
+
+    #[test]
+    fn test_package_manager_manifests() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("tugger-test")?;
+        let msi_path = temp_dir.path().join("myapp.msi");
+        std::fs::write(&msi_path, b"msi")?;
+
+        let mut env = StarlarkEnvironment::new()?;
+        env.eval("m = PackageManagerManifests('myapp', 'My App', '1.0', 'Example', 'An app', license = 'MIT')")?;
+        env.eval(&format!(
+            "m.add_windows_artifact({:?}, 'https://example.com/myapp.msi', architecture = 'x86')",
+            msi_path.display().to_string()
+        ))?;
+        env.eval("m.add_macos_artifact('myapp.pkg', 'https://example.com/myapp.pkg', pkg_ids = ['com.example.myapp'])")?;
+
+        {
+            let m = env.eval("m")?;
+            let m = m.downcast_ref::<PackageManagerManifestsValue>().unwrap();
+
+            assert_eq!(m.metadata.license, Some("MIT".to_string()));
+            assert_eq!(m.pkg_ids, vec!["com.example.myapp".to_string()]);
+            let (windows, architecture) = m.windows.as_ref().unwrap();
+            assert_eq!(windows.path, msi_path);
+            assert_eq!(*architecture, WindowsArchitecture::X86);
+
+            // The macOS artifact doesn't exist.
+            assert!(m.resolve().is_err());
+            assert_eq!(
+                m.output_paths()?,
+                vec![
+                    PathBuf::from("chocolatey/myapp/myapp.nuspec"),
+                    PathBuf::from("chocolatey/myapp/tools/chocolateyinstall.ps1"),
+                    PathBuf::from("homebrew/Casks/myapp.rb"),
+                    PathBuf::from("winget/Example.MyApp.yaml"),
+                ]
+            );
+        }
+
+        assert!(env
+            .eval("m.add_windows_artifact('myapp.dmg', 'https://example.com/myapp.dmg')")
+            .is_err());
+        assert!(env
+            .eval("m.add_windows_artifact('myapp.msi', 'https://example.com/myapp.msi', architecture = 'arm')")
+            .is_err());
+        assert!(env
+            .eval("PackageManagerManifests('My App', 'My App', '1.0', 'Example', 'An app')")
+            .is_err());
+
+        Ok(())
+    }
+}
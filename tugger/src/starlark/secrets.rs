@@ -0,0 +1,163 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    crate::secrets::Secret,
+    starlark::{
+        environment::TypeValues,
+        values::{
+            error::{RuntimeError, ValueError},
+            {Immutable, TypedValue, Value, ValueResult},
+        },
+        {
+            starlark_fun, starlark_module, starlark_parse_param_type, starlark_signature,
+            starlark_signature_extraction, starlark_signatures,
+        },
+    },
+    starlark_dialect_build_targets::{get_context_value, required_list_arg, EnvironmentContext},
+    std::{fmt::Write, path::PathBuf},
+};
+
+/// An opaque handle to a secret.
+///
+/// Only the source of the secret is held. Its value is read by the
+/// consumer when building, so it never appears in Starlark.
+#[derive(Clone, Debug)]
+pub struct SecretValue {
+    pub inner: Secret,
+}
+
+impl TypedValue for SecretValue {
+    type Holder = Immutable<SecretValue>;
+    const TYPE: &'static str = "Secret";
+
+    fn values_for_descendant_check_and_freeze(&self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(std::iter::empty())
+    }
+
+    fn to_repr_impl(&self, buf: &mut String) -> std::fmt::Result {
+        write!(buf, "{}<{}>", Self::TYPE, self.inner.describe())
+    }
+}
+
+fn secret_error(label: &str, message: String) -> ValueError {
+    ValueError::from(RuntimeError {
+        code: "PYOXIDIZER_BUILD",
+        message,
+        label: label.to_string(),
+    })
+}
+
+/// secret_from_env(name)
+fn starlark_secret_from_env(name: String) -> ValueResult {
+    if name.is_empty() {
+        return Err(secret_error(
+            "secret_from_env()",
+            "environment variable name cannot be empty".to_string(),
+        ));
+    }
+
+    Ok(Value::new(SecretValue {
+        inner: Secret::Env(name),
+    }))
+}
+
+/// secret_from_file(path)
+fn starlark_secret_from_file(type_values: &TypeValues, path: String) -> ValueResult {
+    let raw_context = get_context_value(type_values)?;
+    let context = raw_context
+        .downcast_ref::<EnvironmentContext>()
+        .ok_or(ValueError::IncorrectParameterType)?;
+
+    Ok(Value::new(SecretValue {
+        inner: Secret::File(context.cwd().join(PathBuf::from(path))),
+    }))
+}
+
+/// secret_from_keychain(service, account)
+fn starlark_secret_from_keychain(service: String, account: String) -> ValueResult {
+    Ok(Value::new(SecretValue {
+        inner: Secret::Keychain { service, account },
+    }))
+}
+
+/// secret_from_command(args)
+fn starlark_secret_from_command(args: &Value) -> ValueResult {
+    required_list_arg("args", "string", args)?;
+
+    let args = args
+        .iter()?
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>();
+    if args.is_empty() {
+        return Err(secret_error(
+            "secret_from_command()",
+            "command cannot be empty".to_string(),
+        ));
+    }
+
+    Ok(Value::new(SecretValue {
+        inner: Secret::Command(args),
+    }))
+}
+
+starlark_module! { secrets_module =>
+    secret_from_command(args) {
+        starlark_secret_from_command(&args)
+    }
+
+    secret_from_env(name: String) {
+        starlark_secret_from_env(name)
+    }
+
+    secret_from_file(env env, path: String) {
+        starlark_secret_from_file(&env, path)
+    }
+
+    secret_from_keychain(service: String, account: String) {
+        starlark_secret_from_keychain(service, account)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::starlark::testutil::*, anyhow::Result};
+
+    #[test]
+    fn test_secrets() -> Result<()> {
+        let mut env = StarlarkEnvironment::new()?;
+
+        let secret = env.eval("secret_from_env('UPLOAD_TOKEN')")?;
+        assert_eq!(secret.to_str(), "Secret<environment variable UPLOAD_TOKEN>");
+        assert_eq!(
+            secret.downcast_ref::<SecretValue>().unwrap().inner,
+            Secret::Env("UPLOAD_TOKEN".to_string())
+        );
+
+        let secret = env.eval("secret_from_file('token.txt')")?;
+        assert_eq!(
+            secret.downcast_ref::<SecretValue>().unwrap().inner,
+            Secret::File(std::env::current_dir()?.join("token.txt"))
+        );
+
+        let secret = env.eval("secret_from_keychain('myapp-signing', 'ci')")?;
+        assert_eq!(secret.get_type(), "Secret");
+
+        let secret = env.eval("secret_from_command(['pass', 'show', 'myapp/token'])")?;
+        assert_eq!(
+            secret.downcast_ref::<SecretValue>().unwrap().inner,
+            Secret::Command(vec![
+                "pass".to_string(),
+                "show".to_string(),
+                "myapp/token".to_string()
+            ])
+        );
+
+        assert!(env.eval("secret_from_env('')").is_err());
+        assert!(env.eval("secret_from_command([])").is_err());
+
+        Ok(())
+    }
+}
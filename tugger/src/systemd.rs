@@ -0,0 +1,122 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Generate systemd unit files. */
+
+use anyhow::{anyhow, Result};
+
+/// Describes a systemd service unit.
+///
+/// See `man systemd.service` for the meaning of each setting.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SystemdServiceUnit {
+    /// Value of `Description=`.
+    pub description: String,
+
+    /// Value of `ExecStart=`. The command to run.
+    pub exec_start: String,
+
+    /// Value of `User=`. The service runs as root if not set.
+    pub user: Option<String>,
+
+    /// Value of `WorkingDirectory=`.
+    pub working_directory: Option<String>,
+
+    /// Value of `Restart=`.
+    pub restart: String,
+
+    /// Value of `WantedBy=`. The target the service is enabled in.
+    pub wanted_by: String,
+}
+
+impl SystemdServiceUnit {
+    /// Create a new instance running a command with default settings.
+    pub fn new(description: &str, exec_start: &str) -> Self {
+        Self {
+            description: description.to_string(),
+            exec_start: exec_start.to_string(),
+            user: None,
+            working_directory: None,
+            restart: "on-failure".to_string(),
+            wanted_by: "multi-user.target".to_string(),
+        }
+    }
+
+    /// Obtain the content of the `.service` file for this unit.
+    pub fn to_unit_file(&self) -> Result<String> {
+        let entries = vec![
+            ("Unit", "Description", Some(self.description.as_str())),
+            ("Unit", "After", Some("network.target")),
+            ("Service", "Type", Some("simple")),
+            ("Service", "ExecStart", Some(self.exec_start.as_str())),
+            ("Service", "User", self.user.as_deref()),
+            (
+                "Service",
+                "WorkingDirectory",
+                self.working_directory.as_deref(),
+            ),
+            ("Service", "Restart", Some(self.restart.as_str())),
+            ("Install", "WantedBy", Some(self.wanted_by.as_str())),
+        ];
+
+        let mut res = String::new();
+        let mut section = "";
+
+        for (entry_section, key, value) in entries {
+            let value = match value {
+                Some(value) => value,
+                None => continue,
+            };
+
+            // Values are written verbatim, so a newline would inject settings.
+            if value.contains('\n') || value.contains('\r') {
+                return Err(anyhow!("systemd {} value cannot contain newlines", key));
+            }
+
+            if entry_section != section {
+                if !section.is_empty() {
+                    res.push('\n');
+                }
+                res.push_str(&format!("[{}]\n", entry_section));
+                section = entry_section;
+            }
+
+            res.push_str(&format!("{}={}\n", key, value));
+        }
+
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_file() -> Result<()> {
+        let mut unit = SystemdServiceUnit::new("My App", "/opt/myapp/myapp --serve");
+        unit.user = Some("myapp".to_string());
+
+        assert_eq!(
+            unit.to_unit_file()?,
+            "[Unit]\n\
+             Description=My App\n\
+             After=network.target\n\
+             \n\
+             [Service]\n\
+             Type=simple\n\
+             ExecStart=/opt/myapp/myapp --serve\n\
+             User=myapp\n\
+             Restart=on-failure\n\
+             \n\
+             [Install]\n\
+             WantedBy=multi-user.target\n"
+        );
+
+        unit.exec_start = "/bin/true\nExecStartPre=/bin/evil".to_string();
+        assert!(unit.to_unit_file().is_err());
+
+        Ok(())
+    }
+}
@@ -3,12 +3,105 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use {
+    crate::file_resource::FileManifest,
     anyhow::Result,
     slog::warn,
     std::{io::Write, path::Path},
     tar,
 };
 
+/// Format a record of a PAX extended header.
+///
+/// The record length includes the digits of the length itself.
+fn pax_record(key: &str, value: &[u8]) -> Vec<u8> {
+    let rest = key.len() + value.len() + 3;
+    let mut len = rest + rest.to_string().len();
+    if len.to_string().len() != rest.to_string().len() {
+        len += 1;
+    }
+
+    let mut record = format!("{} {}=", len, key).into_bytes();
+    record.extend_from_slice(value);
+    record.push(b'\n');
+
+    record
+}
+
+/// Create a tarball from a `FileManifest`.
+///
+/// Unix attributes of files in the manifest are applied: owner and group
+/// names are recorded in file headers (with uid and gid 0) and capabilities
+/// are stored as the `security.capability` extended attribute, which
+/// `tar --xattrs` restores. Entries are written in a deterministic order
+/// with a zero modification time.
+pub fn write_tarball_from_manifest<W: Write>(
+    fh: &mut W,
+    manifest: &FileManifest,
+    archive_prefix: Option<&Path>,
+) -> Result<()> {
+    let mut builder = tar::Builder::new(fh);
+
+    let archive_path = |path: &Path| match archive_prefix {
+        Some(prefix) => prefix.join(path),
+        None => path.to_path_buf(),
+    };
+
+    for dir in manifest.relative_directories() {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_mode(0o755);
+        header.set_mtime(0);
+        header.set_size(0);
+        header.set_username("root")?;
+        header.set_groupname("root")?;
+        builder.append_data(&mut header, archive_path(&dir), std::io::empty())?;
+    }
+
+    for (path, content) in manifest.entries() {
+        let attributes = manifest.unix_attributes(path);
+        let path = archive_path(path);
+
+        if let Some(xattr) = attributes
+            .map(|a| a.capabilities_xattr())
+            .transpose()?
+            .flatten()
+        {
+            let data = pax_record("SCHILY.xattr.security.capability", &xattr);
+
+            let mut header = tar::Header::new_ustar();
+            header.set_entry_type(tar::EntryType::XHeader);
+            header.set_mode(0o644);
+            header.set_mtime(0);
+            header.set_size(data.len() as u64);
+            builder.append_data(
+                &mut header,
+                Path::new("PaxHeaders.0").join(path.file_name().unwrap()),
+                data.as_slice(),
+            )?;
+        }
+
+        let mut header = tar::Header::new_gnu();
+        header.set_mode(if content.executable { 0o755 } else { 0o644 });
+        header.set_mtime(0);
+        header.set_size(content.data.len() as u64);
+        header.set_username(
+            attributes
+                .and_then(|a| a.owner.as_deref())
+                .unwrap_or("root"),
+        )?;
+        header.set_groupname(
+            attributes
+                .and_then(|a| a.group.as_deref())
+                .unwrap_or("root"),
+        )?;
+        builder.append_data(&mut header, &path, content.data.as_slice())?;
+    }
+
+    builder.finish()?;
+
+    Ok(())
+}
+
 /// Create a tarball from a filesystem path.
 ///
 /// The uncompressed tar contents will be emitted to the passed writer.
@@ -57,3 +150,95 @@ pub fn write_tarball_from_directory<W: Write, P: AsRef<Path>>(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::file_resource::{FileContent, UnixFileAttributes},
+        std::io::Read,
+    };
+
+    #[test]
+    fn test_pax_record() {
+        assert_eq!(pax_record("a", b"b"), b"6 a=b\n".to_vec());
+        assert_eq!(pax_record("key", b"value"), b"13 key=value\n".to_vec());
+        assert_eq!(pax_record("k", &[b'v'; 94]), {
+            let mut v = b"101 k=".to_vec();
+            v.extend_from_slice(&[b'v'; 94]);
+            v.push(b'\n');
+            v
+        });
+    }
+
+    #[test]
+    fn test_write_tarball_from_manifest() -> Result<()> {
+        let mut manifest = FileManifest::default();
+        manifest.add_file(
+            Path::new("bin/myapp"),
+            &FileContent {
+                data: b"app".to_vec(),
+                executable: true,
+            },
+        )?;
+        manifest.add_file(
+            Path::new("etc/myapp.conf"),
+            &FileContent {
+                data: b"conf".to_vec(),
+                executable: false,
+            },
+        )?;
+        manifest.set_unix_attributes(
+            "bin/myapp",
+            UnixFileAttributes {
+                owner: Some("myapp".to_string()),
+                group: Some("daemon".to_string()),
+                capabilities: Some("cap_net_bind_service=ep".to_string()),
+            },
+        )?;
+
+        let mut data = Vec::new();
+        write_tarball_from_manifest(&mut data, &manifest, Some(Path::new("opt")))?;
+
+        let mut archive = tar::Archive::new(data.as_slice());
+        let mut seen = vec![];
+
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.display().to_string();
+            let header = entry.header().clone();
+
+            if path == "opt/bin/myapp" {
+                let pax = entry
+                    .pax_extensions()?
+                    .expect("PAX extensions should be present")
+                    .map(|e| e.map(|e| (e.key().unwrap().to_string(), e.value_bytes().to_vec())))
+                    .collect::<std::io::Result<Vec<_>>>()?;
+                assert_eq!(
+                    pax,
+                    vec![(
+                        "SCHILY.xattr.security.capability".to_string(),
+                        vec![1, 0, 0, 2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+                    )]
+                );
+                assert_eq!(header.username()?, Some("myapp"));
+                assert_eq!(header.groupname()?, Some("daemon"));
+                assert_eq!(header.mode()?, 0o755);
+            } else if path == "opt/etc/myapp.conf" {
+                assert_eq!(header.username()?, Some("root"));
+                let mut content = String::new();
+                entry.read_to_string(&mut content)?;
+                assert_eq!(content, "conf");
+            }
+
+            seen.push(path);
+        }
+
+        assert_eq!(
+            seen,
+            vec!["opt/bin", "opt/etc", "opt/bin/myapp", "opt/etc/myapp.conf"]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,315 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Locate Microsoft's redistributable C runtime files.
+
+Binaries built with MSVC depend on the Visual C++ runtime (`vcruntime140.dll`
+and friends). Microsoft allows applications to redistribute it, either as
+DLLs installed next to the application ("app-local" deployment) or, for MSI
+installers, as a merge module. The redistributable files are taken from a
+local Visual Studio installation.
+
+See <https://docs.microsoft.com/en-us/cpp/windows/redistributing-visual-cpp-files>.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    duct::cmd,
+    std::{
+        env,
+        path::{Path, PathBuf},
+    },
+};
+
+/// A machine architecture redistributable files exist for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VcRedistPlatform {
+    X86,
+    X64,
+    Arm64,
+}
+
+impl VcRedistPlatform {
+    /// Resolve the platform of a Rust target triple.
+    pub fn from_target_triple(target_triple: &str) -> Option<Self> {
+        if !target_triple.contains("-windows-") {
+            None
+        } else if target_triple.starts_with("i686-") {
+            Some(Self::X86)
+        } else if target_triple.starts_with("x86_64-") {
+            Some(Self::X64)
+        } else if target_triple.starts_with("aarch64-") {
+            Some(Self::Arm64)
+        } else {
+            None
+        }
+    }
+
+    /// The name of the platform in Microsoft's directory layouts.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::X86 => "x86",
+            Self::X64 => "x64",
+            Self::Arm64 => "arm64",
+        }
+    }
+}
+
+/// Parse a dotted version string into numeric components.
+fn parse_version(s: &str) -> Option<Vec<u64>> {
+    s.split('.').map(|part| part.parse::<u64>().ok()).collect()
+}
+
+/// Find the subdirectory of `path` named after the highest version.
+fn latest_version_dir(path: &Path) -> Result<PathBuf> {
+    let mut candidates = std::fs::read_dir(path)
+        .with_context(|| format!("reading {}", path.display()))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            parse_version(&entry.file_name().to_string_lossy()).map(|v| (v, entry.path()))
+        })
+        .collect::<Vec<_>>();
+
+    candidates.sort();
+
+    candidates
+        .pop()
+        .map(|(_, path)| path)
+        .ok_or_else(|| anyhow!("no versioned directories in {}", path.display()))
+}
+
+/// Find the entry in `path` whose file name matches a predicate.
+///
+/// If several match, the last by name wins.
+fn find_entry(path: &Path, predicate: impl Fn(&str) -> bool) -> Result<PathBuf> {
+    std::fs::read_dir(path)
+        .with_context(|| format!("reading {}", path.display()))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|p| {
+            p.file_name()
+                .map(|name| predicate(&name.to_string_lossy()))
+                .unwrap_or(false)
+        })
+        .max()
+        .ok_or_else(|| anyhow!("no matching entry in {}", path.display()))
+}
+
+/// Obtain DLL files in a directory, sorted by path.
+fn dll_files(path: &Path) -> Result<Vec<PathBuf>> {
+    let mut res = std::fs::read_dir(path)
+        .with_context(|| format!("reading {}", path.display()))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|p| {
+            p.extension()
+                .map(|ext| ext.eq_ignore_ascii_case("dll"))
+                .unwrap_or(false)
+        })
+        .collect::<Vec<_>>();
+
+    res.sort();
+
+    Ok(res)
+}
+
+/// Find the `Program Files (x86)` directory.
+fn program_files_x86() -> PathBuf {
+    env::var_os("ProgramFiles(x86)")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(r"C:\Program Files (x86)"))
+}
+
+/// Find the Visual C++ redistributable directory of a Visual Studio installation.
+///
+/// This is the `VC\Redist\MSVC\<version>` directory. The `VCToolsRedistDir`
+/// environment variable, as defined by a Visual Studio developer command
+/// prompt, is honored. Otherwise the latest Visual Studio installation is
+/// located with `vswhere`.
+pub fn find_visual_cpp_redist_dir() -> Result<PathBuf> {
+    if let Some(path) = env::var_os("VCToolsRedistDir") {
+        return Ok(PathBuf::from(path));
+    }
+
+    let vswhere = program_files_x86()
+        .join("Microsoft Visual Studio")
+        .join("Installer")
+        .join("vswhere.exe");
+
+    if !vswhere.exists() {
+        return Err(anyhow!(
+            "unable to locate Visual Studio: {} does not exist",
+            vswhere.display()
+        ));
+    }
+
+    let install_path = cmd(
+        &vswhere,
+        &[
+            "-latest",
+            "-products",
+            "*",
+            "-requires",
+            "Microsoft.VisualStudio.Component.VC.Tools.x86.x64",
+            "-property",
+            "installationPath",
+        ],
+    )
+    .read()
+    .context("running vswhere")?;
+
+    let install_path = install_path.trim();
+    if install_path.is_empty() {
+        return Err(anyhow!(
+            "unable to locate a Visual Studio installation with C++ tools"
+        ));
+    }
+
+    latest_version_dir(
+        &PathBuf::from(install_path)
+            .join("VC")
+            .join("Redist")
+            .join("MSVC"),
+    )
+}
+
+/// Find the Visual C++ runtime DLLs within a redistributable directory.
+///
+/// DLLs are in `<platform>\Microsoft.VC<version>.<component>`, where the
+/// components are the C runtime (`CRT`), OpenMP, MFC, etc.
+pub fn visual_cpp_redistributable_dlls(
+    redist_dir: &Path,
+    platform: VcRedistPlatform,
+) -> Result<Vec<PathBuf>> {
+    let platform_dir = redist_dir.join(platform.as_str());
+
+    let mut res = vec![];
+
+    for entry in std::fs::read_dir(&platform_dir)
+        .with_context(|| format!("reading {}", platform_dir.display()))?
+    {
+        let path = entry?.path();
+
+        if path.is_dir()
+            && path
+                .file_name()
+                .map(|name| name.to_string_lossy().starts_with("Microsoft.VC"))
+                .unwrap_or(false)
+        {
+            res.extend(dll_files(&path)?);
+        }
+    }
+
+    if res.is_empty() {
+        return Err(anyhow!(
+            "no Visual C++ runtime DLLs in {}",
+            platform_dir.display()
+        ));
+    }
+
+    res.sort();
+
+    Ok(res)
+}
+
+/// Find the Visual C++ runtime merge module within a redistributable directory.
+///
+/// The merge module is `MergeModules\Microsoft_VC<version>_CRT_<platform>.msm`.
+pub fn visual_cpp_merge_module(redist_dir: &Path, platform: VcRedistPlatform) -> Result<PathBuf> {
+    let suffix = format!("_CRT_{}.msm", platform.as_str());
+
+    find_entry(&redist_dir.join("MergeModules"), |name| {
+        name.starts_with("Microsoft_VC") && name.ends_with(&suffix)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn touch(path: &Path) -> Result<()> {
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(path, b"")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_redist_layout() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("tugger-test")?;
+        let msvc = temp_dir.path().join("VC").join("Redist").join("MSVC");
+
+        for version in &["14.16.27012", "14.28.29325", "v142"] {
+            std::fs::create_dir_all(msvc.join(version))?;
+        }
+        let redist_dir = latest_version_dir(&msvc)?;
+        assert_eq!(redist_dir, msvc.join("14.28.29325"));
+
+        let crt = redist_dir.join("x64").join("Microsoft.VC142.CRT");
+        let openmp = redist_dir.join("x64").join("Microsoft.VC142.OpenMP");
+        touch(&crt.join("vcruntime140.dll"))?;
+        touch(&crt.join("vcruntime140_1.dll"))?;
+        touch(&crt.join("msvcp140.dll"))?;
+        touch(&openmp.join("vcomp140.dll"))?;
+        touch(
+            &redist_dir
+                .join("x86")
+                .join("Microsoft.VC142.CRT")
+                .join("README.txt"),
+        )?;
+        touch(
+            &redist_dir
+                .join("MergeModules")
+                .join("Microsoft_VC142_CRT_x64.msm"),
+        )?;
+        touch(
+            &redist_dir
+                .join("MergeModules")
+                .join("Microsoft_VC142_CRT_x86.msm"),
+        )?;
+
+        assert_eq!(
+            visual_cpp_redistributable_dlls(&redist_dir, VcRedistPlatform::X64)?,
+            vec![
+                crt.join("msvcp140.dll"),
+                crt.join("vcruntime140.dll"),
+                crt.join("vcruntime140_1.dll"),
+                openmp.join("vcomp140.dll"),
+            ]
+        );
+        assert!(visual_cpp_redistributable_dlls(&redist_dir, VcRedistPlatform::X86).is_err());
+
+        assert_eq!(
+            visual_cpp_merge_module(&redist_dir, VcRedistPlatform::X86)?,
+            redist_dir
+                .join("MergeModules")
+                .join("Microsoft_VC142_CRT_x86.msm")
+        );
+        assert!(visual_cpp_merge_module(&redist_dir, VcRedistPlatform::Arm64).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_platform() {
+        assert_eq!(
+            VcRedistPlatform::from_target_triple("x86_64-pc-windows-msvc"),
+            Some(VcRedistPlatform::X64)
+        );
+        assert_eq!(
+            VcRedistPlatform::from_target_triple("i686-pc-windows-msvc"),
+            Some(VcRedistPlatform::X86)
+        );
+        assert_eq!(
+            VcRedistPlatform::from_target_triple("aarch64-pc-windows-msvc"),
+            Some(VcRedistPlatform::Arm64)
+        );
+        assert_eq!(
+            VcRedistPlatform::from_target_triple("x86_64-unknown-linux-gnu"),
+            None
+        );
+    }
+}
@@ -6,6 +6,8 @@ use {
     crate::{
         file_resource::{FileContent, FileManifest},
         http::download_and_verify,
+        shims::{ConsoleScriptShims, ShimPlatform},
+        vc_redist::{find_visual_cpp_redist_dir, visual_cpp_merge_module, VcRedistPlatform},
         zipfile::extract_zip,
     },
     anyhow::{anyhow, Result},
@@ -344,6 +346,37 @@ impl WiXInstallerBuilder {
             .insert(key.to_string(), value.map(|x| x.to_string()));
     }
 
+    /// Install the Visual C++ runtime from its merge module.
+    ///
+    /// The merge module is exposed to wxs files as the `VCRedistMergeModule`
+    /// preprocessor variable. The simple wxs file installs it with the
+    /// application when defined.
+    pub fn add_visual_cpp_merge_module<P: AsRef<Path>>(&mut self, path: P) {
+        self.set_preprocessor_parameter(
+            "VCRedistMergeModule".to_string(),
+            path.as_ref().display().to_string(),
+        );
+    }
+
+    /// Install the Visual C++ runtime for the target from the build machine.
+    ///
+    /// The merge module for the target's platform is located in
+    /// `redist_dir`, or the Visual C++ redistributable directory of the
+    /// installed Visual Studio if not specified.
+    pub fn add_visual_cpp_runtime(&mut self, redist_dir: Option<&Path>) -> Result<()> {
+        let platform = VcRedistPlatform::from_target_triple(&self.target_triple)
+            .ok_or_else(|| anyhow!("no Visual C++ runtime for {}", self.target_triple))?;
+
+        let redist_dir = match redist_dir {
+            Some(path) => path.to_path_buf(),
+            None => find_visual_cpp_redist_dir()?,
+        };
+
+        self.add_visual_cpp_merge_module(visual_cpp_merge_module(&redist_dir, platform)?);
+
+        Ok(())
+    }
+
     /// Add content for a `.wxs` file to be processed.
     ///
     /// The file data will be materialized at `path` in a build directory.
@@ -355,6 +388,31 @@ impl WiXInstallerBuilder {
         self.wxs_files.add_file(path, content)
     }
 
+    /// Install shims for commands of an executable and add them to `PATH`.
+    ///
+    /// Shims are installed next to the executable, which must be at the root
+    /// of the install files. The WiX fragment from the shims is processed and
+    /// the simple wxs file references its component group from the feature
+    /// modifying `PATH`.
+    pub fn add_console_script_shims(&mut self, shims: &ConsoleScriptShims) -> Result<()> {
+        shims.add_shims(
+            &mut self.install_files,
+            ShimPlatform::Windows,
+            Path::new(""),
+        )?;
+
+        self.add_wxs_file_content(
+            Path::new("console-scripts.wxs"),
+            &FileContent {
+                data: shims.wix_fragment().into_bytes(),
+                executable: false,
+            },
+        )?;
+        self.set_preprocessor_parameter("ConsoleScriptsPath", "1");
+
+        Ok(())
+    }
+
     /// Add a `.wxs` file to be processed from a filesystem file.
     ///
     /// The file will be copied into the root directory of a staging area
@@ -546,6 +604,24 @@ impl WiXBundleInstallerBuilder {
         }
     }
 
+    /// Include the Visual C++ Redistributable installer for a platform.
+    ///
+    /// The bundle runs the installer, which installs the Visual C++ runtime
+    /// system-wide, before installing other components.
+    pub fn add_vc_redist_installer(&mut self, platform: VcRedistPlatform) -> Result<()> {
+        match platform {
+            VcRedistPlatform::X86 => self.include_vc_redist_x86 = true,
+            VcRedistPlatform::X64 => self.include_vc_redist_x64 = true,
+            VcRedistPlatform::Arm64 => {
+                return Err(anyhow!(
+                    "bundling the Visual C++ Redistributable is not supported for arm64"
+                ))
+            }
+        }
+
+        Ok(())
+    }
+
     /// Produce an executable containing defined components.
     pub fn build_exe<P: AsRef<Path>>(
         &self,
@@ -886,6 +962,64 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_add_console_script_shims() -> Result<()> {
+        let shims = ConsoleScriptShims::new("myapp.exe", vec!["tool".to_string()])?;
+
+        let mut builder = WiXInstallerBuilder::new("x86_64-pc-windows-msvc".to_string());
+        builder.add_console_script_shims(&shims)?;
+
+        assert_eq!(
+            builder
+                .install_files
+                .entries()
+                .map(|(p, _)| p.to_path_buf())
+                .collect::<Vec<_>>(),
+            vec![PathBuf::from("tool.cmd")]
+        );
+        assert_eq!(
+            builder
+                .wxs_files
+                .entries()
+                .map(|(p, _)| p.to_path_buf())
+                .collect::<Vec<_>>(),
+            vec![PathBuf::from("console-scripts.wxs")]
+        );
+        assert!(builder
+            .preprocess_parameters
+            .contains_key("ConsoleScriptsPath"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_visual_cpp_runtime() -> Result<()> {
+        let temp_dir = tempdir::TempDir::new("tugger-test")?;
+        let merge_modules = temp_dir.path().join("MergeModules");
+        std::fs::create_dir_all(&merge_modules)?;
+        std::fs::write(merge_modules.join("Microsoft_VC142_CRT_x64.msm"), b"")?;
+
+        let mut builder = WiXInstallerBuilder::new("x86_64-pc-windows-msvc".to_string());
+        builder.add_visual_cpp_runtime(Some(temp_dir.path()))?;
+
+        assert_eq!(
+            builder.preprocess_parameters.get("VCRedistMergeModule"),
+            Some(
+                &merge_modules
+                    .join("Microsoft_VC142_CRT_x64.msm")
+                    .display()
+                    .to_string()
+            )
+        );
+
+        let mut builder = WiXInstallerBuilder::new("i686-pc-windows-msvc".to_string());
+        assert!(builder
+            .add_visual_cpp_runtime(Some(temp_dir.path()))
+            .is_err());
+
+        Ok(())
+    }
+
     #[cfg(windows)]
     #[test]
     fn test_installer_builder_simple() -> Result<()> {